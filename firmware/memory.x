@@ -1,6 +1,10 @@
 MEMORY {
     BOOT2 : ORIGIN = 0x10000000, LENGTH = 0x100
-    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100
+    /* The last 32 KiB hold the tester's name (src/identity.rs), the
+       calibration history (src/callog.rs), the test log (src/testlog.rs),
+       the test profiles (src/profile.rs) and the persistent fault log
+       (src/errlog.rs). */
+    FLASH : ORIGIN = 0x10000100, LENGTH = 2048K - 0x100 - 32K
     RAM   : ORIGIN = 0x20000000, LENGTH = 256K
 }
 
@@ -12,4 +16,4 @@ SECTIONS {
     {
         KEEP(*(.boot2));
     } > BOOT2
-} INSERT BEFORE .text;
\ No newline at end of file
+} INSERT BEFORE .text;
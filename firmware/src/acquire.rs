@@ -0,0 +1,419 @@
+//! Load-cell acquisition on core 1.
+//!
+//! Core 1 owns every converter and does nothing but wait for conversions,
+//! so sample timing no longer depends on how long core 0 spends in USB
+//! polling or command handling. Timestamped readings go to core 0 through a
+//! single-producer ring buffer in RAM; the SIO FIFO carries the few
+//! requests core 0 makes back (rate, input and power changes, and parking
+//! core 1 while the flash is written).
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use fugit::ExtU64;
+use rp_pico::hal::{
+    multicore::{Multicore, Stack},
+    pac,
+    sio::{Sio, SioFifo},
+    timer::{Alarm, Alarm1},
+    Timer,
+};
+use tensile_core::quantity::Micros;
+use tensile_core::range::Input;
+
+use crate::log;
+use crate::sensor::{ForceSensor, SensorError, SETTLE_CONVERSIONS};
+
+/// Load cells besides the primary one.
+pub const EXTRA_CHANNELS: usize = cfg!(feature = "hx711-ch1") as usize;
+
+/// Readings buffered for core 0: 32 covers USB stalls of several periods
+/// even at the fastest rate.
+const RING_LEN: usize = 32;
+
+/// Reads that find no new conversion are retried this often, for up to two
+/// sample periods, before the sample counts as missing.
+const DATA_RETRY_US: u64 = 1_000;
+
+// FIFO words: request in the top byte, argument below.
+const OP_SHIFT: u32 = 24;
+const OP_RATE: u32 = 1;
+const OP_PARK: u32 = 2;
+const OP_RESUME: u32 = 3;
+const OP_INPUT: u32 = 4;
+const OP_POWER: u32 = 5;
+const ARG_MASK: u32 = (1 << OP_SHIFT) - 1;
+const REPLY_OK: u32 = 0;
+const REPLY_UNSUPPORTED: u32 = 1;
+const REPLY_BUS: u32 = 2;
+
+static mut CORE1_STACK: Stack<2048> = Stack::new();
+static RING: Ring = Ring::new();
+
+/// One primary conversion and whatever the extra channels had ready.
+#[derive(Clone, Copy)]
+pub struct Reading {
+    pub t: Micros,
+    /// The primary converter's input the value came from.
+    pub input: Input,
+    /// `Err` when the converter failed or produced nothing for two periods.
+    pub value: Result<i32, SensorError>,
+    /// `None` when that channel had no new conversion.
+    pub extra: [Option<Result<i32, SensorError>>; EXTRA_CHANNELS],
+}
+
+impl Reading {
+    const EMPTY: Reading = Reading {
+        t: Micros(0),
+        input: Input::A,
+        value: Err(SensorError::Bus),
+        extra: [None; EXTRA_CHANNELS],
+    };
+}
+
+/// Lock-free ring with core 1 as the only writer and core 0 the only reader.
+struct Ring {
+    slots: [UnsafeCell<Reading>; RING_LEN],
+    head: AtomicUsize,
+    tail: AtomicUsize,
+    dropped: AtomicU32,
+}
+
+// SAFETY: a slot is only written by the producer while it lies outside
+// tail..head and only read by the consumer while inside it; the indices are
+// published with release/acquire ordering.
+unsafe impl Sync for Ring {}
+
+impl Ring {
+    const fn new() -> Self {
+        Self {
+            slots: [const { UnsafeCell::new(Reading::EMPTY) }; RING_LEN],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            dropped: AtomicU32::new(0),
+        }
+    }
+
+    /// Core 1 only.
+    fn push(&self, reading: Reading) {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = (head + 1) % RING_LEN;
+        if next == self.tail.load(Ordering::Acquire) {
+            // No read-modify-write atomics on the M0+, but this core is the
+            // only writer.
+            let dropped = self.dropped.load(Ordering::Relaxed);
+            self.dropped
+                .store(dropped.wrapping_add(1), Ordering::Relaxed);
+            return;
+        }
+        // SAFETY: see `impl Sync`.
+        unsafe { *self.slots[head].get() = reading };
+        self.head.store(next, Ordering::Release);
+    }
+
+    /// Core 0 only.
+    fn pop(&self) -> Option<Reading> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: see `impl Sync`.
+        let reading = unsafe { *self.slots[tail].get() };
+        self.tail.store((tail + 1) % RING_LEN, Ordering::Release);
+        Some(reading)
+    }
+}
+
+/// Core 0's end of the link to the acquisition core.
+pub struct Acquisition {
+    fifo: SioFifo,
+}
+
+impl Acquisition {
+    /// Start core 1 reading `sensor` and `extra` at `sps`, which the
+    /// converters must already be set to.
+    pub fn spawn<S, X>(
+        psm: &mut pac::PSM,
+        ppb: &mut pac::PPB,
+        mut fifo: SioFifo,
+        sensor: S,
+        extra: [X; EXTRA_CHANNELS],
+        mut timer: Timer,
+        sps: u32,
+    ) -> Self
+    where
+        S: ForceSensor + Send + 'static,
+        X: ForceSensor + Send + 'static,
+    {
+        let alarm = timer.alarm_1().unwrap();
+        {
+            let mut mc = Multicore::new(psm, ppb, &mut fifo);
+            let core1 = &mut mc.cores()[1];
+            // SAFETY: the stack is handed to core 1 exactly once, here.
+            let stack = unsafe { &mut (*core::ptr::addr_of_mut!(CORE1_STACK)).mem };
+            core1
+                .spawn(stack, move || run(sensor, extra, timer, alarm, sps))
+                .unwrap();
+        }
+        Self { fifo }
+    }
+
+    /// Next buffered reading, oldest first.
+    pub fn next(&mut self) -> Option<Reading> {
+        RING.pop()
+    }
+
+    /// Most readings that can wait; one slot of the ring stays empty.
+    pub const CAPACITY: usize = RING_LEN - 1;
+
+    /// Readings waiting, out of `CAPACITY`.
+    pub fn buffered(&self) -> usize {
+        let head = RING.head.load(Ordering::Acquire);
+        let tail = RING.tail.load(Ordering::Relaxed);
+        (head + RING_LEN - tail) % RING_LEN
+    }
+
+    /// Readings lost because core 0 fell behind.
+    pub fn dropped(&self) -> u32 {
+        RING.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Switch the primary converter's output rate.
+    pub fn set_rate(&mut self, sps: u32) -> Result<(), SensorError> {
+        if sps == 0 || sps > ARG_MASK {
+            return Err(SensorError::Unsupported);
+        }
+        self.fifo.write_blocking(OP_RATE << OP_SHIFT | sps);
+        match self.fifo.read_blocking() {
+            REPLY_OK => Ok(()),
+            REPLY_UNSUPPORTED => Err(SensorError::Unsupported),
+            _ => Err(SensorError::Bus),
+        }
+    }
+
+    /// Switch the primary converter's input. Readings stop while it
+    /// settles, and those after carry the new input.
+    pub fn set_input(&mut self, input: Input) -> Result<(), SensorError> {
+        self.fifo
+            .write_blocking(OP_INPUT << OP_SHIFT | (input as u32 & ARG_MASK));
+        match self.fifo.read_blocking() {
+            REPLY_OK => Ok(()),
+            REPLY_UNSUPPORTED => Err(SensorError::Unsupported),
+            _ => Err(SensorError::Bus),
+        }
+    }
+
+    /// Power the primary converter down between samples `interval_ms`
+    /// apart, waking it in time to settle before each, or keep it
+    /// converting with `None`.
+    pub fn set_low_power(&mut self, interval_ms: Option<u32>) -> Result<(), SensorError> {
+        let ms = interval_ms.unwrap_or(0).min(ARG_MASK);
+        self.fifo.write_blocking(OP_POWER << OP_SHIFT | ms);
+        match self.fifo.read_blocking() {
+            REPLY_OK => Ok(()),
+            _ => Err(SensorError::Bus),
+        }
+    }
+
+    /// Run `f` with core 1 spinning in RAM, as the flash routines require.
+    /// Acquisition stops for the duration.
+    pub fn parked<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        self.fifo.write_blocking(OP_PARK << OP_SHIFT);
+        let _ = self.fifo.read_blocking();
+        let result = f();
+        self.fifo.write_blocking(OP_RESUME << OP_SHIFT);
+        result
+    }
+}
+
+/// Let pending interrupts wake the calling core from WFE, even with the
+/// interrupt disabled in the NVIC, so sleeping needs no handlers.
+pub fn wake_on_pending() {
+    const SCR_SEVONPEND: u32 = 1 << 4;
+    // SAFETY: each core has its own SCB, and nothing else writes SCR.
+    unsafe {
+        let scb = &*cortex_m::peripheral::SCB::PTR;
+        scb.scr.modify(|scr| scr | SCR_SEVONPEND);
+    }
+}
+
+/// Core 1 main loop.
+fn run<S: ForceSensor, X: ForceSensor>(
+    mut sensor: S,
+    mut extra: [X; EXTRA_CHANNELS],
+    timer: Timer,
+    mut alarm: Alarm1,
+    sps: u32,
+) {
+    // SAFETY: core 1 only uses its own FIFO ends; the rest of SIO is unused.
+    let mut fifo = Sio::new(unsafe { pac::Peripherals::steal() }.SIO).fifo;
+    // Sleep between conversions: the alarm going pending, or core 0's SEV
+    // after a FIFO write, wakes the core without an interrupt handler.
+    wake_on_pending();
+    alarm.enable_interrupt();
+    let mut period = (1_000_000 / sps as u64).micros::<1, 1_000_000>();
+    let mut next_read = timer.get_counter() + period;
+    let mut last_data = timer.get_counter();
+    let mut input = Input::A;
+    // Low power: the converter sleeps between samples this far apart, and
+    // drops the conversions still settling after it wakes.
+    let mut sleep_interval = None;
+    let mut asleep = false;
+    let mut settling = 0;
+
+    loop {
+        if let Some(word) = fifo.read() {
+            match word >> OP_SHIFT {
+                OP_RATE => {
+                    let sps = word & ARG_MASK;
+                    let reply = match sensor.set_rate(sps) {
+                        Ok(()) => {
+                            period = (1_000_000 / sps as u64).micros();
+                            next_read = timer.get_counter() + period;
+                            last_data = timer.get_counter();
+                            REPLY_OK
+                        }
+                        Err(SensorError::Unsupported) => REPLY_UNSUPPORTED,
+                        Err(_) => REPLY_BUS,
+                    };
+                    fifo.write_blocking(reply);
+                }
+                OP_INPUT => {
+                    let to = match word & ARG_MASK {
+                        0 => Input::A,
+                        1 => Input::B,
+                        _ => Input::A64,
+                    };
+                    let reply = match sensor.select_input(to) {
+                        Ok(()) => {
+                            input = to;
+                            // The settling conversions are not missing data.
+                            last_data = timer.get_counter() + period * SETTLE_CONVERSIONS;
+                            REPLY_OK
+                        }
+                        Err(SensorError::Unsupported) => REPLY_UNSUPPORTED,
+                        Err(_) => REPLY_BUS,
+                    };
+                    fifo.write_blocking(reply);
+                }
+                OP_POWER => {
+                    let ms = (word & ARG_MASK) as u64;
+                    sleep_interval = (ms > 0).then(|| ms.millis::<1, 1_000_000>());
+                    asleep = false;
+                    let reply = match sensor.set_power(true) {
+                        Ok(()) => REPLY_OK,
+                        Err(_) => REPLY_BUS,
+                    };
+                    settling = SETTLE_CONVERSIONS;
+                    last_data = timer.get_counter() + period * SETTLE_CONVERSIONS;
+                    next_read = timer.get_counter() + period;
+                    fifo.write_blocking(reply);
+                }
+                // SAFETY: only touches SIO registers, from RAM.
+                OP_PARK => unsafe { park() },
+                // Only ever answered from `park`, and expects no reply.
+                OP_RESUME => {}
+                // Answered all the same, or core 0 would wait for ever.
+                _ => fifo.write_blocking(REPLY_UNSUPPORTED),
+            }
+        }
+
+        let now = timer.get_counter();
+        if now < next_read {
+            let _ = alarm.schedule_at(next_read);
+            cortex_m::asm::wfe();
+            alarm.clear_interrupt();
+            cortex_m::peripheral::NVIC::unpend(pac::Interrupt::TIMER_IRQ_1);
+            continue;
+        }
+        if asleep {
+            asleep = false;
+            // A failed wake shows up as missing data.
+            if let Err(e) = sensor.set_power(true) {
+                log::debug!("sensor wake failed: {}", e.as_str());
+            }
+            settling = SETTLE_CONVERSIONS;
+            let now = timer.get_counter();
+            last_data = now + period * SETTLE_CONVERSIONS;
+            next_read = now + period * 9 / 10;
+            continue;
+        }
+        // The converter's own clock sets the pace. Waking a little early
+        // and retrying keeps reads locked to it instead of drifting past a
+        // conversion, and only a real gap counts as missing data.
+        let value = match sensor.read() {
+            Ok(_) if settling > 0 => {
+                settling -= 1;
+                last_data = now;
+                next_read = now + period * 9 / 10;
+                continue;
+            }
+            Ok(value) => {
+                last_data = now;
+                next_read = now + period * 9 / 10;
+                Ok(value)
+            }
+            Err(nb::Error::WouldBlock) if now < last_data + period * 2 => {
+                next_read = now + DATA_RETRY_US.micros();
+                continue;
+            }
+            Err(e) => {
+                last_data = now;
+                next_read = now + period;
+                let e = match e {
+                    nb::Error::Other(e) => e,
+                    nb::Error::WouldBlock => SensorError::Bus,
+                };
+                log::debug!("conversion failed: {}", e.as_str());
+                Err(e)
+            }
+        };
+        let mut reading = Reading {
+            t: Micros(now.ticks()),
+            input,
+            value,
+            extra: [None; EXTRA_CHANNELS],
+        };
+        for (cell, out) in extra.iter_mut().zip(reading.extra.iter_mut()) {
+            *out = match cell.read() {
+                Ok(value) => Some(Ok(value)),
+                Err(nb::Error::WouldBlock) => None,
+                Err(nb::Error::Other(e)) => Some(Err(e)),
+            };
+        }
+        RING.push(reading);
+        // Wake core 0 to process it.
+        cortex_m::asm::sev();
+        if let Some(interval) = sleep_interval {
+            let _ = sensor.set_power(false);
+            asleep = true;
+            // Wake early enough to settle by the next sample.
+            let warmup = period * (SETTLE_CONVERSIONS + 1);
+            next_read = now
+                + if interval > warmup {
+                    interval - warmup
+                } else {
+                    0.micros()
+                };
+        }
+    }
+}
+
+const SIO_FIFO_ST: *mut u32 = 0xd000_0050 as *mut u32;
+const SIO_FIFO_WR: *mut u32 = 0xd000_0054 as *mut u32;
+const SIO_FIFO_RD: *mut u32 = 0xd000_0058 as *mut u32;
+const FIFO_ST_VLD: u32 = 1 << 0;
+const FIFO_ST_RDY: u32 = 1 << 1;
+
+/// Acknowledge a park request and spin until resumed, without fetching
+/// anything from flash.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn park() {
+    while core::ptr::read_volatile(SIO_FIFO_ST) & FIFO_ST_RDY == 0 {}
+    core::ptr::write_volatile(SIO_FIFO_WR, REPLY_OK);
+    // Wake core 0 out of the WFE in `read_blocking`.
+    core::arch::asm!("sev");
+    while core::ptr::read_volatile(SIO_FIFO_ST) & FIFO_ST_VLD == 0 {}
+    let _ = core::ptr::read_volatile(SIO_FIFO_RD);
+}
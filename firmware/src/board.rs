@@ -0,0 +1,298 @@
+//! The Raspberry Pi Pico: clock setup and which GPIO does what.
+//!
+//! `main` only deals in the roles below, so a new board (or a Pico 2, once
+//! rp235x-hal is a dependency) means another module with the same items.
+//!
+//! Wiring that differs between carrier PCBs is picked by feature:
+//!
+//! | Layout          | HX711 DT/SCK | Button | Bluetooth STATE |
+//! |-----------------|--------------|--------|-----------------|
+//! | default         | GPIO16/17    | GPIO3  | GPIO2           |
+//! | `board-carrier` | GPIO2/3      | GPIO16 | GPIO17          |
+//!
+//! The carrier also routes a stepper driver to GPIO10–12. Nothing here
+//! drives it; those pins keep the trigger outputs, which stay low unless
+//! `TRIG` arms them.
+
+use bsp::hal::{
+    clocks::{init_clocks_and_plls, ClocksManager},
+    gpio::{bank0::*, FunctionNull, Pin, PullDown},
+    pac,
+    watchdog::Watchdog,
+};
+use rp_pico as bsp;
+
+/// The crystal on the Pico.
+pub const XTAL_FREQ_HZ: u32 = 12_000_000;
+
+/// Reported by `INFO?`.
+#[cfg(not(feature = "board-carrier"))]
+pub const NAME: &str = "pico";
+#[cfg(feature = "board-carrier")]
+pub const NAME: &str = "carrier";
+
+/// The front-panel button, as `INFO?` lists it.
+#[cfg(all(
+    any(feature = "encoder", feature = "tare-button"),
+    not(feature = "board-carrier")
+))]
+pub const BUTTON_PIN: &str = "3";
+#[cfg(all(
+    any(feature = "encoder", feature = "tare-button"),
+    feature = "board-carrier"
+))]
+pub const BUTTON_PIN: &str = "16";
+
+/// The Bluetooth module's STATE output, as `INFO?` lists it.
+#[cfg(all(feature = "bluetooth", not(feature = "board-carrier")))]
+pub const BT_STATE_PIN: &str = "2";
+#[cfg(all(feature = "bluetooth", feature = "board-carrier"))]
+pub const BT_STATE_PIN: &str = "17";
+
+/// HX711 DT and SCK, as `INFO?` lists them.
+#[cfg(not(feature = "board-carrier"))]
+pub const HX711_PINS: &str = "16,17";
+#[cfg(feature = "board-carrier")]
+pub const HX711_PINS: &str = "2,3";
+
+/// Multiplies every load-cell reading, so that tension reads positive.
+/// -1 for a bridge wired the other way round.
+pub const LOAD_CELL_POLARITY: i32 = 1;
+
+/// System clock at 125 MHz and USB at 48 MHz, both from the crystal.
+pub fn init_clocks(
+    xosc: pac::XOSC,
+    clocks: pac::CLOCKS,
+    pll_sys: pac::PLL_SYS,
+    pll_usb: pac::PLL_USB,
+    resets: &mut pac::RESETS,
+    watchdog: &mut Watchdog,
+) -> ClocksManager {
+    init_clocks_and_plls(
+        XTAL_FREQ_HZ,
+        xosc,
+        clocks,
+        pll_sys,
+        pll_usb,
+        resets,
+        watchdog,
+    )
+    .ok()
+    .unwrap()
+}
+
+/// A pin as it comes out of reset.
+type Unconfigured<Id> = Pin<Id, FunctionNull, PullDown>;
+
+#[cfg(any(feature = "modbus", feature = "uart-stream"))]
+pub type UartTx = Gpio0;
+#[cfg(any(feature = "modbus", feature = "uart-stream"))]
+pub type UartRx = Gpio1;
+#[cfg(all(feature = "bluetooth", not(feature = "board-carrier")))]
+pub type BtState = Gpio2;
+#[cfg(all(feature = "bluetooth", feature = "board-carrier"))]
+pub type BtState = Gpio17;
+/// RS-485 transceiver DE/RE.
+#[cfg(feature = "modbus")]
+pub type Rs485Enable = Gpio2;
+#[cfg(not(feature = "board-carrier"))]
+type Hx711Dout = Gpio16;
+#[cfg(not(feature = "board-carrier"))]
+type Hx711Sck = Gpio17;
+#[cfg(feature = "board-carrier")]
+type Hx711Dout = Gpio2;
+#[cfg(feature = "board-carrier")]
+type Hx711Sck = Gpio3;
+pub type Led = Gpio25;
+#[cfg(feature = "buzzer")]
+pub type Buzzer = Gpio15;
+#[cfg(feature = "servo")]
+pub type Servo = Gpio8;
+#[cfg(feature = "chamber")]
+pub type Heater = Gpio9;
+#[cfg(feature = "extensometer")]
+pub type ExtensometerA = Gpio14;
+#[cfg(feature = "extensometer")]
+pub type ExtensometerB = Gpio15;
+#[cfg(feature = "encoder")]
+pub type EncoderA = Gpio8;
+#[cfg(feature = "encoder")]
+pub type EncoderB = Gpio9;
+#[cfg(all(
+    any(feature = "encoder", feature = "tare-button"),
+    not(feature = "board-carrier")
+))]
+pub type Button = Gpio3;
+#[cfg(all(
+    any(feature = "encoder", feature = "tare-button"),
+    feature = "board-carrier"
+))]
+pub type Button = Gpio16;
+
+/// Every pin the firmware uses, by role, not yet configured.
+pub struct Pins {
+    #[cfg(any(feature = "modbus", feature = "uart-stream"))]
+    pub uart_tx: Unconfigured<UartTx>,
+    #[cfg(any(feature = "modbus", feature = "uart-stream"))]
+    pub uart_rx: Unconfigured<UartRx>,
+    #[cfg(feature = "modbus")]
+    pub rs485_enable: Unconfigured<Rs485Enable>,
+    /// High while the Bluetooth module has a connection.
+    #[cfg(feature = "bluetooth")]
+    pub bt_state: Unconfigured<BtState>,
+    /// NAU7802 on I2C0.
+    #[cfg(feature = "nau7802")]
+    pub nau_sda: Unconfigured<Gpio4>,
+    #[cfg(feature = "nau7802")]
+    pub nau_scl: Unconfigured<Gpio5>,
+    /// I2C1, for the SSD1306 and DS3231 or the I2C target.
+    #[cfg(any(feature = "oled", feature = "rtc", feature = "i2c-target"))]
+    pub i2c1_sda: Unconfigured<Gpio6>,
+    #[cfg(any(feature = "oled", feature = "rtc", feature = "i2c-target"))]
+    pub i2c1_scl: Unconfigured<Gpio7>,
+    /// Rotary encoder.
+    #[cfg(feature = "encoder")]
+    pub encoder_a: Unconfigured<EncoderA>,
+    #[cfg(feature = "encoder")]
+    pub encoder_b: Unconfigured<EncoderB>,
+    #[cfg(any(feature = "encoder", feature = "tare-button"))]
+    pub button: Unconfigured<Button>,
+    /// Clamp servo, PWM4 A.
+    #[cfg(feature = "servo")]
+    pub servo: Unconfigured<Servo>,
+    /// Chamber heater SSR.
+    #[cfg(feature = "chamber")]
+    pub heater: Unconfigured<Heater>,
+    pub triggers: (
+        Unconfigured<Gpio10>,
+        Unconfigured<Gpio11>,
+        Unconfigured<Gpio12>,
+        Unconfigured<Gpio13>,
+    ),
+    /// Second HX711, channel 1.
+    #[cfg(feature = "hx711-ch1")]
+    pub ch1_dout: Unconfigured<Gpio14>,
+    #[cfg(feature = "hx711-ch1")]
+    pub ch1_sck: Unconfigured<Gpio15>,
+    /// Extensometer A and B, read by PIO0.
+    #[cfg(feature = "extensometer")]
+    pub ext_a: Unconfigured<ExtensometerA>,
+    #[cfg(feature = "extensometer")]
+    pub ext_b: Unconfigured<ExtensometerB>,
+    /// Piezo buzzer, PWM7 B.
+    #[cfg(feature = "buzzer")]
+    pub buzzer: Unconfigured<Buzzer>,
+    pub hx711_dout: Unconfigured<Hx711Dout>,
+    pub hx711_sck: Unconfigured<Hx711Sck>,
+    /// ADS1256 on SPI0.
+    #[cfg(feature = "ads1256")]
+    pub ads_sclk: Unconfigured<Gpio18>,
+    #[cfg(feature = "ads1256")]
+    pub ads_mosi: Unconfigured<Gpio19>,
+    #[cfg(feature = "ads1256")]
+    pub ads_miso: Unconfigured<Gpio20>,
+    #[cfg(feature = "ads1256")]
+    pub ads_cs: Unconfigured<Gpio21>,
+    #[cfg(feature = "ads1256")]
+    pub ads_drdy: Unconfigured<Gpio22>,
+    /// Logic inputs 0-3.
+    #[cfg(feature = "digital-inputs")]
+    pub inputs: (
+        Unconfigured<Gpio18>,
+        Unconfigured<Gpio19>,
+        Unconfigured<Gpio20>,
+        Unconfigured<Gpio21>,
+    ),
+    pub led: Unconfigured<Led>,
+    /// ADC0-2.
+    pub aux: (
+        Unconfigured<Gpio26>,
+        Unconfigured<Gpio27>,
+        Unconfigured<Gpio28>,
+    ),
+    /// VSYS/3.
+    pub vsys: Unconfigured<Gpio29>,
+}
+
+impl Pins {
+    pub fn new(
+        io: pac::IO_BANK0,
+        pads: pac::PADS_BANK0,
+        sio: bsp::hal::sio::SioGpioBank0,
+        resets: &mut pac::RESETS,
+    ) -> Self {
+        let pins = bsp::Pins::new(io, pads, sio, resets);
+        Self {
+            #[cfg(any(feature = "modbus", feature = "uart-stream"))]
+            uart_tx: pins.gpio0,
+            #[cfg(any(feature = "modbus", feature = "uart-stream"))]
+            uart_rx: pins.gpio1,
+            #[cfg(feature = "modbus")]
+            rs485_enable: pins.gpio2,
+            #[cfg(all(feature = "bluetooth", not(feature = "board-carrier")))]
+            bt_state: pins.gpio2,
+            #[cfg(all(feature = "bluetooth", feature = "board-carrier"))]
+            bt_state: pins.gpio17,
+            #[cfg(feature = "nau7802")]
+            nau_sda: pins.gpio4,
+            #[cfg(feature = "nau7802")]
+            nau_scl: pins.gpio5,
+            #[cfg(any(feature = "oled", feature = "rtc", feature = "i2c-target"))]
+            i2c1_sda: pins.gpio6,
+            #[cfg(any(feature = "oled", feature = "rtc", feature = "i2c-target"))]
+            i2c1_scl: pins.gpio7,
+            #[cfg(feature = "encoder")]
+            encoder_a: pins.gpio8,
+            #[cfg(feature = "encoder")]
+            encoder_b: pins.gpio9,
+            #[cfg(all(
+                any(feature = "encoder", feature = "tare-button"),
+                not(feature = "board-carrier")
+            ))]
+            button: pins.gpio3,
+            #[cfg(all(
+                any(feature = "encoder", feature = "tare-button"),
+                feature = "board-carrier"
+            ))]
+            button: pins.gpio16,
+            #[cfg(feature = "servo")]
+            servo: pins.gpio8,
+            #[cfg(feature = "chamber")]
+            heater: pins.gpio9,
+            triggers: (pins.gpio10, pins.gpio11, pins.gpio12, pins.gpio13),
+            #[cfg(feature = "hx711-ch1")]
+            ch1_dout: pins.gpio14,
+            #[cfg(feature = "hx711-ch1")]
+            ch1_sck: pins.gpio15,
+            #[cfg(feature = "extensometer")]
+            ext_a: pins.gpio14,
+            #[cfg(feature = "extensometer")]
+            ext_b: pins.gpio15,
+            #[cfg(feature = "buzzer")]
+            buzzer: pins.gpio15,
+            #[cfg(not(feature = "board-carrier"))]
+            hx711_dout: pins.gpio16,
+            #[cfg(not(feature = "board-carrier"))]
+            hx711_sck: pins.gpio17,
+            #[cfg(feature = "board-carrier")]
+            hx711_dout: pins.gpio2,
+            #[cfg(feature = "board-carrier")]
+            hx711_sck: pins.gpio3,
+            #[cfg(feature = "ads1256")]
+            ads_sclk: pins.gpio18,
+            #[cfg(feature = "ads1256")]
+            ads_mosi: pins.gpio19,
+            #[cfg(feature = "ads1256")]
+            ads_miso: pins.gpio20,
+            #[cfg(feature = "ads1256")]
+            ads_cs: pins.gpio21,
+            #[cfg(feature = "ads1256")]
+            ads_drdy: pins.gpio22,
+            #[cfg(feature = "digital-inputs")]
+            inputs: (pins.gpio18, pins.gpio19, pins.gpio20, pins.gpio21),
+            led: pins.led,
+            aux: (pins.gpio26, pins.gpio27, pins.gpio28),
+            vsys: pins.voltage_monitor,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Piezo buzzer on GPIO15 (PWM slice 7, channel B), built with the `buzzer`
+//! feature. Patterns come from `tensile_core::alarm`.
+
+use bsp::hal::{
+    gpio::{FunctionNull, Pin, PullDown},
+    pwm::{FreeRunning, Pwm7, Slice},
+    timer::Instant,
+};
+use embedded_hal::pwm::SetDutyCycle;
+use rp_pico as bsp;
+use tensile_core::alarm::{Alarm, Sounder};
+
+use crate::board::Buzzer as BuzzerPin;
+
+/// Divides the 125 MHz system clock to a 1 MHz PWM count, which reaches
+/// down to 16 Hz.
+const DIVIDER: u8 = 125;
+const COUNT_HZ: u32 = 1_000_000;
+
+pub struct Buzzer {
+    slice: Slice<Pwm7, FreeRunning>,
+    sounder: Sounder,
+    tone: Option<u32>,
+}
+
+impl Buzzer {
+    pub fn new(
+        mut slice: Slice<Pwm7, FreeRunning>,
+        pin: Pin<BuzzerPin, FunctionNull, PullDown>,
+    ) -> Self {
+        slice.set_div_int(DIVIDER);
+        slice.channel_b.output_to(pin);
+        let _ = slice.channel_b.set_duty_cycle(0);
+        slice.enable();
+        Self {
+            slice,
+            sounder: Sounder::new(),
+            tone: None,
+        }
+    }
+
+    pub fn sound(&mut self, alarm: Alarm, now: Instant) {
+        self.sounder.start(alarm, now.ticks() / 1000);
+    }
+
+    /// Drive the output for `now`. Returns when it next changes.
+    pub fn update(&mut self, now: Instant) -> Option<Instant> {
+        let now_ms = now.ticks() / 1000;
+        let tone = self.sounder.tone(now_ms);
+        if tone != self.tone {
+            self.tone = tone;
+            let duty = match tone {
+                Some(hz) => {
+                    let top = (COUNT_HZ / hz.max(16) - 1) as u16;
+                    self.slice.set_top(top);
+                    top / 2
+                }
+                None => 0,
+            };
+            let _ = self.slice.channel_b.set_duty_cycle(duty);
+        }
+        self.sounder
+            .next_change(now_ms)
+            .map(|ms| Instant::from_ticks(ms * 1000))
+    }
+}
@@ -0,0 +1,60 @@
+//! Calibration history persisted in the two flash sectors below the test
+//! log, read back with `CALLOG?`.
+
+use tensile_core::callog::{Record, RECORD_LEN};
+use tensile_core::errlog::RingIndex;
+
+use crate::flash::{self, PAGE_SIZE, SECTOR_SIZE};
+use crate::testlog;
+
+/// Must match the space carved out of FLASH in memory.x.
+pub const LOG_OFFSET: u32 = testlog::LOG_OFFSET - SECTORS as u32 * SECTOR_SIZE;
+const SECTORS: usize = 2;
+const SLOTS_PER_SECTOR: usize = SECTOR_SIZE as usize / RECORD_LEN;
+
+pub struct CalLog {
+    ring: RingIndex,
+}
+
+impl CalLog {
+    /// Find where the history left off before this boot.
+    pub fn load() -> Self {
+        let seqs = (0..SECTORS * SLOTS_PER_SECTOR).map(|slot| read_slot(slot).map(|r| r.seq));
+        Self {
+            ring: RingIndex::scan(SECTORS, SLOTS_PER_SECTOR, seqs),
+        }
+    }
+
+    /// Record a calibration; its `seq` is filled in. Core 1 must be parked.
+    pub fn append(&mut self, record: Record) {
+        let append = self.ring.append();
+        if let Some(sector) = append.erase {
+            flash::erase_sector(LOG_OFFSET + sector as u32 * SECTOR_SIZE);
+        }
+        let record = Record {
+            seq: append.seq,
+            ..record
+        };
+        let offset = slot_offset(append.slot);
+        let page = offset & !(PAGE_SIZE as u32 - 1);
+        let at = (offset - page) as usize;
+        let mut data = [0xFF; PAGE_SIZE];
+        data[at..at + RECORD_LEN].copy_from_slice(&record.encode());
+        flash::program_page(page, &data);
+    }
+
+    /// Every stored record, oldest first.
+    pub fn records(&self) -> impl Iterator<Item = Record> + '_ {
+        self.ring.oldest_first().filter_map(read_slot)
+    }
+}
+
+fn slot_offset(slot: usize) -> u32 {
+    LOG_OFFSET + (slot * RECORD_LEN) as u32
+}
+
+fn read_slot(slot: usize) -> Option<Record> {
+    let mut raw = [0; RECORD_LEN];
+    flash::read(slot_offset(slot), &mut raw);
+    Record::decode(&raw)
+}
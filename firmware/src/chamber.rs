@@ -0,0 +1,103 @@
+//! Temperature chamber, built with the `chamber` feature: an NTC thermistor
+//! on ADC2 (GPIO28) and a heater SSR on GPIO9.
+//!
+//! The SSR switches at mains zero crossings, so the heater is
+//! time-proportioned over a slow window rather than PWM'd. The PID loop in
+//! `tensile_core::chamber` picks each window's duty.
+
+use bsp::hal::{
+    gpio::{FunctionSioOutput, Pin, PullDown},
+    timer::Instant,
+};
+use embedded_hal::digital::OutputPin;
+use fugit::ExtU64;
+use rp_pico as bsp;
+use tensile_core::chamber::{thermistor_temp, Gains, Pid, FULL_DUTY, OVERSAMPLE};
+use tensile_core::quantity::MilliCelsius;
+
+use crate::board::Heater;
+
+/// One heater cycle, and how often the temperature is read.
+const WINDOW_MS: u64 = 2_000;
+
+/// The highest setpoint accepted.
+pub const MAX_SETPOINT: MilliCelsius = MilliCelsius(200_000);
+
+pub struct Chamber {
+    heater: Pin<Heater, FunctionSioOutput, PullDown>,
+    pid: Pid,
+    setpoint: Option<MilliCelsius>,
+    temp: Option<MilliCelsius>,
+    duty: u16,
+    window_start: Option<Instant>,
+}
+
+impl Chamber {
+    pub fn new(heater: Pin<Heater, FunctionSioOutput, PullDown>) -> Self {
+        Self {
+            heater,
+            pid: Pid::new(Gains::DEFAULT),
+            setpoint: None,
+            temp: None,
+            duty: 0,
+            window_start: None,
+        }
+    }
+
+    /// Hold `setpoint`, or turn the heater off.
+    pub fn set(&mut self, setpoint: Option<MilliCelsius>) {
+        self.setpoint = setpoint;
+        self.pid.reset();
+        // Start a fresh window on the next update.
+        self.window_start = None;
+    }
+
+    pub fn set_gains(&mut self, gains: Gains) {
+        self.pid = Pid::new(gains);
+    }
+
+    pub fn gains(&self) -> Gains {
+        self.pid.gains()
+    }
+
+    pub fn setpoint(&self) -> Option<MilliCelsius> {
+        self.setpoint
+    }
+
+    /// The last reading, `None` while the thermistor is open or shorted.
+    pub fn temp(&self) -> Option<MilliCelsius> {
+        self.temp
+    }
+
+    /// Heater duty in permille.
+    pub fn duty(&self) -> u16 {
+        self.duty
+    }
+
+    /// Drive the heater for `now`, reading the thermistor with `read` at
+    /// each new window. Returns when it next needs to run.
+    pub fn update(&mut self, now: Instant, mut read: impl FnMut() -> u16) -> Instant {
+        let start = match self.window_start {
+            Some(start) if now < start + WINDOW_MS.millis() => start,
+            _ => {
+                self.window_start = Some(now);
+                let sum = (0..OVERSAMPLE).map(|_| read() as u32).sum();
+                self.temp = thermistor_temp(sum);
+                // No reading, no heat.
+                self.duty = match (self.setpoint, self.temp) {
+                    (Some(set), Some(temp)) => self.pid.update(set, temp, WINDOW_MS as u32),
+                    _ => 0,
+                };
+                now
+            }
+        };
+        let off_at = start + (WINDOW_MS * self.duty as u64 / FULL_DUTY as u64).millis();
+        let on = now < off_at;
+        let _ = self.heater.set_state(on.into());
+        if on {
+            off_at
+        } else {
+            start + WINDOW_MS.millis()
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//! Additional load cells read alongside the primary one.
+//!
+//! Each extra channel keeps its own zero and calibration. Core 1 polls its
+//! converter once per primary sample; the value streamed is the newest
+//! conversion it has, so a channel converting at the same rate lags by at
+//! most one period.
+
+use crate::sensor::SensorError;
+use tensile_core::quantity::Counts;
+use tensile_core::tare::{Tare, TareResult};
+use tensile_core::units::Scale;
+
+pub struct Channel {
+    offset: i32,
+    tare: Option<Tare>,
+    scale: Option<Scale>,
+    latest: Option<Counts>,
+}
+
+impl Channel {
+    /// Starts with a power-on tare.
+    pub fn new() -> Self {
+        Self {
+            offset: 0,
+            tare: Some(Tare::new(Tare::DEFAULT_SAMPLES)),
+            scale: None,
+            latest: None,
+        }
+    }
+
+    /// Take a conversion from core 1. Returns the result of a tare that
+    /// completed on it.
+    pub fn push(&mut self, sample: Result<i32, SensorError>) -> Option<TareResult> {
+        let Ok(raw) = sample else {
+            self.latest = None;
+            return None;
+        };
+        let done = self.tare.as_mut().and_then(|t| t.push(raw));
+        if let Some(result) = done {
+            self.tare = None;
+            self.offset = result.offset;
+        }
+        self.latest = Some(Counts(raw - self.offset));
+        done
+    }
+
+    /// Zero on the average of the next `samples` conversions.
+    pub fn start_tare(&mut self, samples: usize) {
+        self.tare = Some(Tare::new(samples));
+    }
+
+    /// Latest tared reading, or `None` before the first conversion or after
+    /// a bus error.
+    pub fn latest(&self) -> Option<Counts> {
+        self.latest
+    }
+
+    pub fn scale(&self) -> Option<Scale> {
+        self.scale
+    }
+
+    /// Apply a `CAL` factor. Returns false if it was rejected.
+    pub fn calibrate(&mut self, counts_per_kg: i32, gravity_um_s2: u32) -> bool {
+        match Scale::new(counts_per_kg, gravity_um_s2) {
+            Some(scale) => {
+                self.scale = Some(scale);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear_calibration(&mut self) {
+        self.scale = None;
+    }
+
+    pub fn set_gravity(&mut self, gravity_um_s2: u32) {
+        self.scale = self
+            .scale
+            .and_then(|s| Scale::new(s.counts_per_kg(), gravity_um_s2));
+    }
+}
@@ -0,0 +1,1227 @@
+//! Line-oriented commands received over the USB serial port.
+//!
+//! Commands are ASCII words separated by spaces and terminated by CR and/or
+//! LF. Keywords are case-insensitive.
+//!
+//! Anything the short commands don't claim is tried as SCPI, so tooling
+//! that speaks `*IDN?` and `MEAS:FORC?` works alongside them.
+//!
+//! Every short command ends its reply with `OK` or `ERR <code> <reason>`,
+//! after any reply lines of its own. SCPI queries answer with their value
+//! alone and other SCPI commands with `OK`; SCPI failures are answered
+//! `ERR <SCPI code> <message>` and also queued for `SYST:ERR?`.
+
+use tensile_core::calcheck::CalCheckError;
+use tensile_core::callog::Operator;
+use tensile_core::capture::Length;
+use tensile_core::chamber::Gains;
+use tensile_core::columns::Columns;
+use tensile_core::creep::Creep;
+use tensile_core::digital::Setting;
+use tensile_core::dual::Combine;
+use tensile_core::filter::{MovingAverage, SpikeFilter};
+use tensile_core::identity::DeviceName;
+use tensile_core::loading::{Direction, Polarity};
+use tensile_core::log::Level;
+use tensile_core::mark::Label;
+use tensile_core::meta::Entry;
+use tensile_core::midtest::Policy;
+use tensile_core::profile::{Name, Profile};
+use tensile_core::quantity::Milligrams;
+use tensile_core::scpi::{self, header_matches};
+use tensile_core::servo;
+use tensile_core::stream::Protocol;
+use tensile_core::tare::Tare;
+use tensile_core::trigger::Edge;
+use tensile_core::units::Unit;
+
+/// Room for a `PROFILE` with every option, as `CONFIG DUMP` writes it.
+const LINE_LEN: usize = 128;
+
+/// Disagreement between the two cells, in percent, that `DUAL` warns at
+/// unless told otherwise.
+const DEFAULT_DUAL_LIMIT_PCT: u32 = 10;
+
+/// Accumulates received bytes into complete lines.
+pub struct LineBuffer {
+    buf: [u8; LINE_LEN],
+    len: usize,
+    overflow: bool,
+    complete: bool,
+}
+
+impl LineBuffer {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; LINE_LEN],
+            len: 0,
+            overflow: false,
+            complete: false,
+        }
+    }
+
+    /// Feed one byte. Returns the line once a terminator arrives, or
+    /// `Malformed` for one that overflowed the buffer or is not UTF-8.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&str, ParseError>> {
+        if self.complete {
+            self.len = 0;
+            self.complete = false;
+        }
+        match byte {
+            b'\r' | b'\n' => {
+                let overflow = core::mem::replace(&mut self.overflow, false);
+                if overflow {
+                    self.len = 0;
+                    return Some(Err(ParseError::Malformed));
+                }
+                if self.len == 0 {
+                    return None;
+                }
+                self.complete = true;
+                Some(core::str::from_utf8(&self.buf[..self.len]).map_err(|_| ParseError::Malformed))
+            }
+            _ if self.len < LINE_LEN => {
+                self.buf[self.len] = byte;
+                self.len += 1;
+                None
+            }
+            _ => {
+                self.overflow = true;
+                None
+            }
+        }
+    }
+}
+
+/// What a `CAL` records in the calibration history besides the factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalNote {
+    /// `MASS <g>`: the reference mass the factor was taken with.
+    pub mass: Option<Milligrams>,
+    /// `BY <operator>`.
+    pub operator: Option<Operator>,
+    /// `CREEP <ppm> <tau s>`: the cell's creep, compensated from then on.
+    pub creep: Option<Creep>,
+}
+
+/// When a test should begin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartTime {
+    Now,
+    /// Delay in seconds from receipt of the command.
+    In(u32),
+    /// Wall-clock time in seconds since the Unix epoch.
+    At(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// `START`, `START IN <s>`, `START AT <unix s>`
+    Start(StartTime),
+    /// `STOP` — ends a running test or cancels a scheduled one.
+    Stop,
+    /// `UNLOCK <serial number>` — allow the next `START`, `RUN` or `INIT`
+    /// with the `interlock` feature.
+    Unlock(u64),
+    /// `LOCK` — withdraw an unused unlock.
+    Lock,
+    /// `LOCK?`
+    LockQuery,
+    /// `TEST PAUSE` — hold the running test and stop logging.
+    TestPause,
+    /// `TEST RESUME` — continue a paused test if the force is still close to
+    /// where it was paused.
+    TestResume,
+    /// `TEST?` — the latest test's number and how the last finished one
+    /// went.
+    TestQuery,
+    /// `PAUSE [HOLD]` — stop sending `Force:` lines, leaving acquisition,
+    /// tare and any test untouched. `HOLD` keeps them for `RESUME` to send.
+    Pause(bool),
+    /// `RESUME` — send `Force:` lines again, held ones first.
+    Resume,
+    /// `FILTER AVG <n>` — boxcar window of up to 32 samples, 0 disables.
+    FilterAverage(usize),
+    /// `FILTER IIR <Hz>` — low-pass cutoff in millihertz, 0 disables.
+    FilterLowPass(u32),
+    /// `FILTER MEDIAN <n>` — median over 3–5 samples, 0 disables.
+    FilterMedian(usize),
+    /// `FILTER MAD <k>` — replace samples more than k MADs out (k up to
+    /// 20), 0 disables.
+    FilterMad(u32),
+    /// `FILTER OFF` — disable every stage.
+    FilterOff,
+    /// `RATE <n>` — report dF/dt over the last n samples, 0 disables.
+    Rate(usize),
+    /// `DECIMATE <n>` — send one `Force:` line in every n; all of them are
+    /// still kept for `REPLAY`.
+    Decimate(u32),
+    /// `FORMAT <key,key,...>` or `FORMAT ALL` — which fields `Force:` lines
+    /// carry.
+    Format(Columns),
+    /// `FORMAT?`
+    FormatQuery,
+    /// `PROTOCOL ASCII|BINARY` — how samples go out on the data port;
+    /// `PROTOCOL AUTO` lets each host's first byte choose.
+    Protocol(Option<Protocol>),
+    /// `PROTOCOL?`
+    ProtocolQuery,
+    /// `SAMPLERATE <sps>` — converter output rate; must be one the fitted
+    /// backend supports.
+    SampleRate(u32),
+    /// `SAMPLERATE?` — current and supported output rates.
+    SampleRateQuery,
+    /// `UNITS RAW|N|KGF|LBF|G` — unit of the `Force:` value.
+    Units(Unit),
+    /// `UNITS?`
+    UnitsQuery,
+    /// `DECIMALS <n>` — decimal places of calibrated values, 0–6.
+    Decimals(u32),
+    /// `POLARITY NORMAL|INVERTED` — whether tension or compression reads
+    /// positive.
+    Polarity(Polarity),
+    /// `MODE TENSION|COMPRESSION` — which way tests load the specimen, for
+    /// peaks and break detection.
+    Mode(Direction),
+    /// `MODE?`
+    ModeQuery,
+    /// `PRELOAD <counts>` or `PRELOAD OFF` — load, the way the test loads
+    /// the specimen, that must be reached before a test counts as running.
+    Preload(Option<u32>),
+    /// `CAL <counts per kg> [MASS <g>] [BY <operator>] [CREEP <ppm> <tau s>]`
+    /// — scale from tared counts to mass, recorded in the calibration
+    /// history.
+    Calibrate(i32, CalNote),
+    /// `GRAVITY <m/s²>` — local gravity in µm/s².
+    Gravity(u32),
+    /// `ZERO TRACK <band counts> <hold s>` or `ZERO TRACK OFF` — slowly
+    /// re-tare while idle and near zero.
+    ZeroTrack(Option<(u32, u32)>),
+    /// `TARE [n]` — zero on the average of the next n readings.
+    Tare(usize),
+    /// `CH <n> TARE [samples]` — zero extra load-cell channel n (1-based).
+    ChannelTare(usize, usize),
+    /// `CH <n> CAL <counts per kg> [MASS <g>] [BY <operator>]` — calibrate
+    /// extra channel n.
+    ChannelCal(usize, i32, CalNote),
+    /// `DUAL SUM|AVG [limit %]` or `DUAL OFF` — report the primary and
+    /// channel 1 cells combined, warning when they disagree by more than
+    /// the limit.
+    Dual(Option<(Combine, u32)>),
+    /// `DUAL?`
+    DualQuery,
+    /// `RANGE <B counts per kg> <switch counts> <hysteresis counts>` or
+    /// `RANGE OFF` — auto-range between the HX711's inputs: B, calibrated
+    /// with the factor given, up to the switch point, A above it.
+    Range(Option<(i32, u32, u32)>),
+    /// `RANGE?`
+    RangeQuery,
+    /// `GAINCHECK <minutes> <tolerance counts>` or `GAINCHECK OFF` — compare
+    /// the HX711's input A at gain 64 and 128 this often while idle.
+    GainCheck(Option<(u32, u32)>),
+    /// `GAINCHECK?`
+    GainCheckQuery,
+    /// `TRIG <out> ABOVE|BELOW <counts> [hysteresis]` or `TRIG <out> OFF` —
+    /// drive output `out` while the filtered, tared force is past the level.
+    Trigger(usize, Option<(Edge, i32, u32)>),
+    /// `TRIG <out> TEST` — drive output `out` for as long as a test runs,
+    /// so its rising edge can start other testers, cameras or DAQs.
+    TriggerTest(usize),
+    /// `TRIG ARM <in> [RISE|FALL]` or `TRIG ARM OFF` — start a test on that
+    /// edge of logic input `in`.
+    TriggerArm(Option<(usize, bool)>),
+    /// `OUT <out> ON|OFF` — drive output `out` by hand, disarming its
+    /// trigger.
+    Output(usize, bool),
+    /// `TRIG?`
+    TriggerQuery,
+    /// `DIN <in> ON [INVERT] [GATE]` or `DIN <in> OFF` — record logic input
+    /// `in` with each sample; with `GATE` tests only run while it is active.
+    Din(usize, Option<Setting>),
+    /// `DIN?`
+    DinQuery,
+    /// `CAPTURE ABOVE|BELOW <counts> COUNT <n>|TIME <s> [PRE <n>]` or
+    /// `CAPTURE OFF` — hold back `Force:` lines until the force crosses the
+    /// level, then send the `PRE` samples before it and that many samples or
+    /// seconds after.
+    Capture(Option<(Edge, i32, Length, u32)>),
+    /// `CAPTURE?`
+    CaptureQuery,
+    /// `BURST <s>` or `BURST STOP` — record every conversion for that long
+    /// at the fastest rate into RAM, streaming nothing, then send them all
+    /// as `Burst:` lines.
+    Burst(Option<u32>),
+    /// `BREAK <drop %> <min peak counts>` or `BREAK OFF` — report a specimen
+    /// break when the force falls that far below its peak during a test.
+    Break(Option<(u32, u32)>),
+    /// `SLIP <drop %> <min peak counts> <window samples> [STOP]` or
+    /// `SLIP OFF` — report the grips slipping when the force falls that far
+    /// below its peak and climbs back within the window; `STOP` also ends
+    /// the test.
+    Slip(Option<(u32, u32, u32, bool)>),
+    /// `MODBUS <unit>` or `MODBUS OFF` — Modbus RTU server address on
+    /// UART0, 1–247.
+    Modbus(Option<u8>),
+    /// `I2C <address>` or `I2C OFF` — I2C target address on I2C1, in hex,
+    /// 08–77.
+    I2cTarget(Option<u8>),
+    /// `UART <baud> MIRROR|ONLY` or `UART OFF` — copy the stream to UART0,
+    /// or move the `Force:` lines there.
+    Uart(Option<(u32, UartMode)>),
+    /// `BT?` — whether a tablet is connected over Bluetooth.
+    BluetoothQuery,
+    /// `TIME SET <unix s>` — set the wall clock, and the RTC if fitted.
+    TimeSet(u64),
+    /// `TIME?`
+    TimeQuery,
+    /// `SYNC <token>` — reply at once with the device clock, echoing the
+    /// host's token (typically its own clock) for offset estimation.
+    Sync(u64),
+    /// `META <key>=<value>` — describe the test for the stream; an empty
+    /// value removes the key.
+    Meta(Entry),
+    /// `META CLEAR`
+    MetaClear,
+    /// `MARK <label>` — annotate the next sample, e.g. `MARK necking visible`.
+    Mark(Label),
+    /// `META?`
+    MetaQuery,
+    /// `PROFILE <name> [BREAK <drop %> <min peak>] [LIMIT <counts>]
+    /// [TIME <s>] [CLAMP CLOSE|<deg>] [PRELOAD <counts>] [END <out> ON|OFF]`
+    /// — store a test profile, replacing any of that name.
+    Profile(Profile),
+    /// `PROFILE <name> DELETE`
+    ProfileDelete(Name),
+    /// `PROFILE?` — list the stored profiles.
+    ProfileQuery,
+    /// `RUN <name>` — apply a stored profile and start a test.
+    Run(Name),
+    /// `CLAMP OPEN|CLOSE|<deg>` — move the clamp servo.
+    Clamp(u8),
+    /// `CLAMP?`
+    ClampQuery,
+    /// `REPLAY <seq>` — resend the Force lines after `seq` that are still
+    /// held, for a host that lost its connection.
+    Replay(u32),
+    /// `ERRLOG?` — dump the persistent fault log.
+    ErrLog,
+    /// `CALLOG? [n]` — the last n calibrations, or all that are kept.
+    CalLog(Option<usize>),
+    /// `TEMPCO <counts/°C>` — temperature coefficient in thousandths of a
+    /// count, 0 disables.
+    TempCo(i32),
+    /// `AUX <ch> <gain> [offset]` or `AUX <ch> OFF` — stream ADC channel
+    /// `ch` (0–2, GPIO26–28) as `gain * volts + offset`, in thousandths.
+    Aux(usize, Option<(i32, i32)>),
+    /// `EXT ZERO` — take the extensometer's present position as zero.
+    ExtZero,
+    /// `EXT SCALE <µm>` — extensometer travel per count, negative to
+    /// reverse it, in nanometres.
+    ExtScale(i32),
+    /// `EXT?`
+    ExtQuery,
+    /// `WORK AUX <ch> [STREAM]` or `WORK OFF` — integrate calibrated force
+    /// over AUX channel `ch`, scaled to mm, during each test. `STREAM` adds
+    /// the running total to Force lines.
+    Work(Option<(usize, bool)>),
+    /// `WORK?`
+    WorkQuery,
+    /// `CHAMBER <°C>` or `CHAMBER OFF` — hold the chamber at a temperature,
+    /// in thousandths of a degree.
+    Chamber(Option<u32>),
+    /// `CHAMBER PID <kp> <ki> <kd>` — heater duty in permille per °C of
+    /// error, per °C·s and per °C/s.
+    ChamberPid(Gains),
+    /// `CHAMBER?`
+    ChamberQuery,
+    /// `CONFIG?` — every setting that differs from power-on, as the
+    /// commands that would restore it.
+    Config,
+    /// `CONFIG DUMP` — `CONFIG?` plus the profiles and name kept in flash,
+    /// ending `Config: n=<lines> crc=<hex>`: everything needed to clone the
+    /// tester.
+    ConfigDump,
+    /// `CONFIG LOAD` — reset as `*RST` does, forget the calibration,
+    /// profiles and name, then run the `Config: ` lines of a dump that
+    /// follow until its `Config: n=` line.
+    ConfigLoad,
+    /// `INFO?` — firmware version, build and board identity.
+    Info,
+    /// `NAME <name>` or `NAME CLEAR` — what the lab calls this tester, kept
+    /// in flash and reported by `INFO?`.
+    Name(Option<DeviceName>),
+    /// `NAME?`
+    NameQuery,
+    /// `SELFTEST?` — repeat the boot self-test report.
+    SelfTest,
+    /// `CAPS?` — features this firmware was built with.
+    Caps,
+    /// `STATUS?`
+    Status,
+    /// `LOG LEVEL <error|warn|info|debug|trace>` or `LOG LEVEL OFF` — which
+    /// log lines are sent to the host.
+    Log(Option<Level>),
+    /// `LOG?`
+    LogQuery,
+    /// `DIAG?` — main loop, conversion and stream timing since boot or
+    /// `DIAG RESET`.
+    Diag,
+    DiagReset,
+    /// `HEARTBEAT <ms>` — send a `Heartbeat:` record this often whether or
+    /// not samples are flowing, 0 disables.
+    Heartbeat(u32),
+    /// `POWER LOW <s>` — power the converter down between samples this
+    /// many seconds apart and quiet the status outputs; `POWER NORMAL`.
+    Power(Option<u32>),
+    /// `POWER?`
+    PowerQuery,
+    /// `PEAK?` — max/min force since tare or `PEAK RESET`.
+    Peak,
+    /// `PEAK RESET`
+    PeakReset,
+    /// `CALCHECK START` — begin a reference-weight check.
+    CalCheckStart,
+    /// `CALCHECK POINT <g>` — average the placed reference mass, in
+    /// milligrams.
+    CalCheckPoint(Milligrams),
+    /// `CALCHECK END` — finish the check and report its certificate.
+    CalCheckEnd,
+    /// `CALCHECK?` — repeat the last certificate.
+    CalCheckQuery,
+    /// `STATS?` — min/max/mean/RMS/σ over the current test or since
+    /// `STATS RESET`.
+    Stats,
+    /// `STATS RESET`
+    StatsReset,
+    /// `NOISE?` — measure the idle noise for a few seconds, then report it.
+    NoiseQuery,
+    /// `MIDTEST <REJECT|DEFER>` — what happens to commands that would
+    /// change a running test's scale.
+    MidTest(Policy),
+    /// `MIDTEST?`
+    MidTestQuery,
+    /// An SCPI command.
+    Scpi(Scpi),
+}
+
+impl Command {
+    /// Would change what a running test's readings mean, so falls under
+    /// `MIDTEST` while one runs.
+    pub fn rescales(&self) -> bool {
+        matches!(
+            self,
+            Command::Tare(_)
+                | Command::ChannelTare(..)
+                | Command::Calibrate(..)
+                | Command::ChannelCal(..)
+                | Command::Units(_)
+                | Command::Gravity(_)
+                | Command::SampleRate(_)
+                | Command::Polarity(_)
+                | Command::ZeroTrack(_)
+                | Command::TempCo(_)
+                | Command::Dual(_)
+                | Command::Range(_)
+                | Command::ExtZero
+                | Command::ExtScale(_)
+                | Command::Scpi(Scpi::Unit(_))
+                | Command::Scpi(Scpi::Zero)
+        )
+    }
+}
+
+/// The SCPI subset understood, by header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scpi {
+    /// `*IDN?`
+    Identify,
+    /// `*RST` — settings, sample rate and gravity included, back to
+    /// power-on defaults; calibration kept.
+    Reset,
+    /// `*CLS` — empty the error queue.
+    ClearStatus,
+    /// `*TST?` — 0 if the boot self-test passed.
+    SelfTest,
+    /// `*OPC?` — commands run in order, so always 1.
+    OperationComplete,
+    /// `SYSTem:ERRor[:NEXT]?`
+    Error,
+    /// `SYSTem:ERRor:COUNt?`
+    ErrorCount,
+    /// `SYSTem:VERSion?`
+    Version,
+    /// `MEASure[:SCALar]:FORCe?` — latest filtered force in the current unit.
+    MeasureForce,
+    /// `MEASure[:SCALar]:PEAK?` — largest force since tare or `PEAK RESET`.
+    MeasurePeak,
+    /// `INITiate[:IMMediate]` — start a test.
+    Initiate,
+    /// `ABORt` — stop the test, if any.
+    Abort,
+    /// `UNIT:FORCe RAW|N|KGF|LBF|G`
+    Unit(Unit),
+    /// `UNIT:FORCe?`
+    UnitQuery,
+    /// `CALibration:ZERO` — tare on the default number of readings.
+    Zero,
+}
+
+impl Scpi {
+    /// Answered with a bare value rather than `OK`.
+    pub fn is_query(self) -> bool {
+        matches!(
+            self,
+            Scpi::Identify
+                | Scpi::SelfTest
+                | Scpi::OperationComplete
+                | Scpi::Error
+                | Scpi::ErrorCount
+                | Scpi::Version
+                | Scpi::MeasureForce
+                | Scpi::MeasurePeak
+                | Scpi::UnitQuery
+        )
+    }
+}
+
+/// Headers and what they parse to, for the commands without parameters.
+const SCPI_HEADERS: &[(&str, Scpi)] = &[
+    ("*IDN?", Scpi::Identify),
+    ("*RST", Scpi::Reset),
+    ("*CLS", Scpi::ClearStatus),
+    ("*TST?", Scpi::SelfTest),
+    ("*OPC?", Scpi::OperationComplete),
+    ("SYSTem:ERRor[:NEXT]?", Scpi::Error),
+    ("SYSTem:ERRor:COUNt?", Scpi::ErrorCount),
+    ("SYSTem:VERSion?", Scpi::Version),
+    ("MEASure[:SCALar]:FORCe?", Scpi::MeasureForce),
+    ("MEASure[:SCALar]:PEAK?", Scpi::MeasurePeak),
+    ("INITiate[:IMMediate]", Scpi::Initiate),
+    ("ABORt", Scpi::Abort),
+    ("UNIT:FORCe?", Scpi::UnitQuery),
+    ("CALibration:ZERO", Scpi::Zero),
+];
+
+/// True for lines in SCPI form, whose errors also go in the SCPI error
+/// queue.
+pub fn is_scpi(line: &str) -> bool {
+    let keyword = line.split_ascii_whitespace().next().unwrap_or_default();
+    keyword.starts_with('*')
+        || keyword.contains(':')
+        || SCPI_HEADERS
+            .iter()
+            .any(|(pattern, _)| header_matches(pattern, keyword))
+}
+
+/// What the UART stream carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UartMode {
+    /// Everything the USB port gets.
+    Mirror,
+    /// The `Force:` lines, which then no longer go over USB.
+    Only,
+}
+
+impl UartMode {
+    #[cfg(feature = "uart-stream")]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UartMode::Mirror => "mirror",
+            UartMode::Only => "only",
+        }
+    }
+
+    /// Parse `MIRROR` or `ONLY`, case-insensitively.
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("MIRROR") {
+            Some(UartMode::Mirror)
+        } else if name.eq_ignore_ascii_case("ONLY") {
+            Some(UartMode::Only)
+        } else {
+            None
+        }
+    }
+}
+
+/// The `<code>` in `ERR <code> <reason>`, by what the host can do about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// Not a command; check the spelling.
+    Unknown = 1,
+    /// An argument is missing, malformed or out of range.
+    Argument = 2,
+    /// Not now: a test is running, nothing is calibrated yet, and so on.
+    State = 3,
+    /// This build or board lacks what the command needs.
+    Unsupported = 4,
+    /// No room left to store it.
+    Full = 5,
+    /// The hardware failed to carry it out.
+    Hardware = 6,
+}
+
+impl From<CalCheckError> for ErrorCode {
+    fn from(e: CalCheckError) -> Self {
+        match e {
+            CalCheckError::Full => ErrorCode::Full,
+            _ => ErrorCode::State,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    Unknown,
+    BadArgument,
+    /// Too long for the line buffer, or not text.
+    Malformed,
+}
+
+impl ParseError {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ParseError::Unknown => "unknown command",
+            ParseError::BadArgument => "bad argument",
+            ParseError::Malformed => "line too long or not text",
+        }
+    }
+
+    pub fn code(self) -> ErrorCode {
+        match self {
+            ParseError::Unknown | ParseError::Malformed => ErrorCode::Unknown,
+            ParseError::BadArgument => ErrorCode::Argument,
+        }
+    }
+
+    pub fn scpi(self) -> scpi::Error {
+        match self {
+            ParseError::Unknown | ParseError::Malformed => scpi::Error::UndefinedHeader,
+            ParseError::BadArgument => scpi::Error::IllegalParameterValue,
+        }
+    }
+}
+
+pub fn parse(line: &str) -> Result<Command, ParseError> {
+    let mut words = line.split_ascii_whitespace();
+    let keyword = words.next().ok_or(ParseError::Unknown)?;
+
+    let command = if keyword.eq_ignore_ascii_case("START") {
+        let when = match words.next() {
+            None => StartTime::Now,
+            Some(w) if w.eq_ignore_ascii_case("IN") => StartTime::In(number(words.next())?),
+            Some(w) if w.eq_ignore_ascii_case("AT") => StartTime::At(number(words.next())?),
+            Some(_) => return Err(ParseError::BadArgument),
+        };
+        Command::Start(when)
+    } else if keyword.eq_ignore_ascii_case("STOP") {
+        Command::Stop
+    } else if keyword.eq_ignore_ascii_case("UNLOCK") {
+        let token = words.next().and_then(|w| u64::from_str_radix(w, 16).ok());
+        Command::Unlock(token.ok_or(ParseError::BadArgument)?)
+    } else if keyword.eq_ignore_ascii_case("LOCK") {
+        Command::Lock
+    } else if keyword.eq_ignore_ascii_case("LOCK?") {
+        Command::LockQuery
+    } else if keyword.eq_ignore_ascii_case("TEST") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("PAUSE") => Command::TestPause,
+            Some(w) if w.eq_ignore_ascii_case("RESUME") => Command::TestResume,
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("TEST?") {
+        Command::TestQuery
+    } else if keyword.eq_ignore_ascii_case("PAUSE") {
+        match words.next() {
+            None => Command::Pause(false),
+            Some(w) if w.eq_ignore_ascii_case("HOLD") => Command::Pause(true),
+            Some(_) => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("RESUME") {
+        Command::Resume
+    } else if keyword.eq_ignore_ascii_case("FILTER") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("AVG") => match number(words.next())? {
+                n if n > MovingAverage::MAX_WINDOW => return Err(ParseError::BadArgument),
+                n => Command::FilterAverage(n),
+            },
+            Some(w) if w.eq_ignore_ascii_case("IIR") => {
+                Command::FilterLowPass(milli(words.next())?)
+            }
+            Some(w) if w.eq_ignore_ascii_case("MEDIAN") => match number(words.next())? {
+                0 => Command::FilterMedian(0),
+                n if (SpikeFilter::MIN_WINDOW..=SpikeFilter::MAX_WINDOW).contains(&n) => {
+                    Command::FilterMedian(n)
+                }
+                _ => return Err(ParseError::BadArgument),
+            },
+            Some(w) if w.eq_ignore_ascii_case("MAD") => match number(words.next())? {
+                k if k > SpikeFilter::MAX_K => return Err(ParseError::BadArgument),
+                k => Command::FilterMad(k),
+            },
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::FilterOff,
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("SAMPLERATE?") {
+        Command::SampleRateQuery
+    } else if keyword.eq_ignore_ascii_case("SAMPLERATE") {
+        Command::SampleRate(number(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("DECIMATE") {
+        Command::Decimate(number(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("FORMAT?") {
+        Command::FormatQuery
+    } else if keyword.eq_ignore_ascii_case("FORMAT") {
+        let columns = words.next().and_then(Columns::parse);
+        Command::Format(columns.ok_or(ParseError::BadArgument)?)
+    } else if keyword.eq_ignore_ascii_case("PROTOCOL?") {
+        Command::ProtocolQuery
+    } else if keyword.eq_ignore_ascii_case("PROTOCOL") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("ASCII") => Command::Protocol(Some(Protocol::Ascii)),
+            Some(w) if w.eq_ignore_ascii_case("BINARY") => {
+                Command::Protocol(Some(Protocol::Binary))
+            }
+            Some(w) if w.eq_ignore_ascii_case("AUTO") => Command::Protocol(None),
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("RATE") {
+        Command::Rate(number(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("DECIMALS") {
+        Command::Decimals(number(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("UNITS?") {
+        Command::UnitsQuery
+    } else if keyword.eq_ignore_ascii_case("UNITS") {
+        let unit = words.next().and_then(Unit::parse);
+        Command::Units(unit.ok_or(ParseError::BadArgument)?)
+    } else if keyword.eq_ignore_ascii_case("POLARITY") {
+        let polarity = words.next().and_then(Polarity::parse);
+        Command::Polarity(polarity.ok_or(ParseError::BadArgument)?)
+    } else if keyword.eq_ignore_ascii_case("MODE") {
+        let direction = words.next().and_then(Direction::parse);
+        Command::Mode(direction.ok_or(ParseError::BadArgument)?)
+    } else if keyword.eq_ignore_ascii_case("MODE?") {
+        Command::ModeQuery
+    } else if keyword.eq_ignore_ascii_case("PRELOAD") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Preload(None),
+            counts => Command::Preload(Some(number(counts)?)),
+        }
+    } else if keyword.eq_ignore_ascii_case("CAL") {
+        Command::Calibrate(number(words.next())?, cal_note(&mut words)?)
+    } else if keyword.eq_ignore_ascii_case("GRAVITY") {
+        Command::Gravity(fixed(words.next(), 6)?)
+    } else if keyword.eq_ignore_ascii_case("ZERO") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("TRACK") => match words.next() {
+                Some(w) if w.eq_ignore_ascii_case("OFF") => Command::ZeroTrack(None),
+                band => Command::ZeroTrack(Some((number(band)?, number(words.next())?))),
+            },
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("TARE") {
+        match words.next() {
+            None => Command::Tare(Tare::DEFAULT_SAMPLES),
+            n => Command::Tare(number(n)?),
+        }
+    } else if keyword.eq_ignore_ascii_case("CH") {
+        let channel = number(words.next())?;
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("TARE") => match words.next() {
+                None => Command::ChannelTare(channel, Tare::DEFAULT_SAMPLES),
+                n => Command::ChannelTare(channel, number(n)?),
+            },
+            Some(w) if w.eq_ignore_ascii_case("CAL") => {
+                Command::ChannelCal(channel, number(words.next())?, cal_note(&mut words)?)
+            }
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("DUAL?") {
+        Command::DualQuery
+    } else if keyword.eq_ignore_ascii_case("DUAL") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Dual(None),
+            mode => {
+                let mode = mode.and_then(Combine::parse);
+                let limit = match words.next() {
+                    None => DEFAULT_DUAL_LIMIT_PCT,
+                    limit => number(limit)?,
+                };
+                Command::Dual(Some((mode.ok_or(ParseError::BadArgument)?, limit)))
+            }
+        }
+    } else if keyword.eq_ignore_ascii_case("RANGE?") {
+        Command::RangeQuery
+    } else if keyword.eq_ignore_ascii_case("RANGE") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Range(None),
+            b_per_kg => Command::Range(Some((
+                number(b_per_kg)?,
+                number(words.next())?,
+                number(words.next())?,
+            ))),
+        }
+    } else if keyword.eq_ignore_ascii_case("GAINCHECK?") {
+        Command::GainCheckQuery
+    } else if keyword.eq_ignore_ascii_case("GAINCHECK") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::GainCheck(None),
+            minutes => Command::GainCheck(Some((number(minutes)?, number(words.next())?))),
+        }
+    } else if keyword.eq_ignore_ascii_case("TRIG?") {
+        Command::TriggerQuery
+    } else if keyword.eq_ignore_ascii_case("TRIG") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("ARM") => match words.next() {
+                Some(w) if w.eq_ignore_ascii_case("OFF") => Command::TriggerArm(None),
+                input => {
+                    let input = number(input)?;
+                    let rising = match words.next() {
+                        None => true,
+                        Some(w) if w.eq_ignore_ascii_case("RISE") => true,
+                        Some(w) if w.eq_ignore_ascii_case("FALL") => false,
+                        Some(_) => return Err(ParseError::BadArgument),
+                    };
+                    Command::TriggerArm(Some((input, rising)))
+                }
+            },
+            output => {
+                let output = number(output)?;
+                match words.next() {
+                    Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Trigger(output, None),
+                    Some(w) if w.eq_ignore_ascii_case("TEST") => Command::TriggerTest(output),
+                    edge => {
+                        let edge = edge.and_then(Edge::parse).ok_or(ParseError::BadArgument)?;
+                        let level = number(words.next())?;
+                        let hysteresis = match words.next() {
+                            None => 0,
+                            hysteresis => number(hysteresis)?,
+                        };
+                        Command::Trigger(output, Some((edge, level, hysteresis)))
+                    }
+                }
+            }
+        }
+    } else if keyword.eq_ignore_ascii_case("DIN?") {
+        Command::DinQuery
+    } else if keyword.eq_ignore_ascii_case("DIN") {
+        let input = number(words.next())?;
+        if !on_off(words.next())? {
+            Command::Din(input, None)
+        } else {
+            let mut setting = Setting {
+                invert: false,
+                gate: false,
+            };
+            for word in words.by_ref() {
+                if word.eq_ignore_ascii_case("INVERT") {
+                    setting.invert = true;
+                } else if word.eq_ignore_ascii_case("GATE") {
+                    setting.gate = true;
+                } else {
+                    return Err(ParseError::BadArgument);
+                }
+            }
+            Command::Din(input, Some(setting))
+        }
+    } else if keyword.eq_ignore_ascii_case("OUT") {
+        let output = number(words.next())?;
+        Command::Output(output, on_off(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("CAPTURE?") {
+        Command::CaptureQuery
+    } else if keyword.eq_ignore_ascii_case("CAPTURE") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Capture(None),
+            edge => {
+                let edge = edge.and_then(Edge::parse).ok_or(ParseError::BadArgument)?;
+                let level = number(words.next())?;
+                let length = match words.next() {
+                    Some(w) if w.eq_ignore_ascii_case("COUNT") => {
+                        Length::Samples(number(words.next())?)
+                    }
+                    Some(w) if w.eq_ignore_ascii_case("TIME") => {
+                        Length::Millis(milli(words.next())?)
+                    }
+                    _ => return Err(ParseError::BadArgument),
+                };
+                let pre_trigger = match words.next() {
+                    None => 0,
+                    Some(w) if w.eq_ignore_ascii_case("PRE") => number(words.next())?,
+                    Some(_) => return Err(ParseError::BadArgument),
+                };
+                Command::Capture(Some((edge, level, length, pre_trigger)))
+            }
+        }
+    } else if keyword.eq_ignore_ascii_case("BURST") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("STOP") => Command::Burst(None),
+            secs => Command::Burst(Some(milli(secs)?)),
+        }
+    } else if keyword.eq_ignore_ascii_case("BREAK") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Break(None),
+            drop_pct => Command::Break(Some((number(drop_pct)?, number(words.next())?))),
+        }
+    } else if keyword.eq_ignore_ascii_case("SLIP") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Slip(None),
+            drop_pct => {
+                let drop_pct = number(drop_pct)?;
+                let min_peak = number(words.next())?;
+                let window = number(words.next())?;
+                let stop = match words.next() {
+                    None => false,
+                    Some(w) if w.eq_ignore_ascii_case("STOP") => true,
+                    Some(_) => return Err(ParseError::BadArgument),
+                };
+                Command::Slip(Some((drop_pct, min_peak, window, stop)))
+            }
+        }
+    } else if keyword.eq_ignore_ascii_case("MODBUS") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Modbus(None),
+            unit => Command::Modbus(Some(number(unit)?)),
+        }
+    } else if keyword.eq_ignore_ascii_case("I2C") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::I2cTarget(None),
+            Some(w) => {
+                let hex = w.strip_prefix("0x").unwrap_or(w);
+                let address = u8::from_str_radix(hex, 16).map_err(|_| ParseError::BadArgument)?;
+                Command::I2cTarget(Some(address))
+            }
+            None => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("UART") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Uart(None),
+            baud => {
+                let baud = number(baud)?;
+                let mode = words.next().and_then(UartMode::parse);
+                Command::Uart(Some((baud, mode.ok_or(ParseError::BadArgument)?)))
+            }
+        }
+    } else if keyword.eq_ignore_ascii_case("BT?") {
+        Command::BluetoothQuery
+    } else if keyword.eq_ignore_ascii_case("TIME") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("SET") => Command::TimeSet(number(words.next())?),
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("TIME?") {
+        Command::TimeQuery
+    } else if keyword.eq_ignore_ascii_case("SYNC") {
+        Command::Sync(number(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("META?") {
+        Command::MetaQuery
+    } else if keyword.eq_ignore_ascii_case("META") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("CLEAR") => Command::MetaClear,
+            entry => Command::Meta(
+                entry
+                    .and_then(Entry::parse)
+                    .ok_or(ParseError::BadArgument)?,
+            ),
+        }
+    } else if keyword.eq_ignore_ascii_case("MARK") {
+        // The rest of the line, spaces and all.
+        let label = line.trim_start()[keyword.len()..].trim();
+        return Label::parse(label)
+            .map(Command::Mark)
+            .ok_or(ParseError::BadArgument);
+    } else if keyword.eq_ignore_ascii_case("PROFILE?") {
+        Command::ProfileQuery
+    } else if keyword.eq_ignore_ascii_case("PROFILE") {
+        let name = words.next().and_then(Name::new);
+        let name = name.ok_or(ParseError::BadArgument)?;
+        let mut option = words.next();
+        if option.is_some_and(|w| w.eq_ignore_ascii_case("DELETE")) {
+            Command::ProfileDelete(name)
+        } else {
+            let mut profile = Profile::new(name);
+            while let Some(w) = option {
+                if w.eq_ignore_ascii_case("BREAK") {
+                    profile.breaks = Some((number(words.next())?, number(words.next())?));
+                } else if w.eq_ignore_ascii_case("LIMIT") {
+                    profile.limits.max_force = Some(number(words.next())?);
+                } else if w.eq_ignore_ascii_case("TIME") {
+                    profile.limits.max_duration_s = Some(number(words.next())?);
+                } else if w.eq_ignore_ascii_case("CLAMP") {
+                    profile.clamp = Some(clamp_angle(words.next())?);
+                } else if w.eq_ignore_ascii_case("PRELOAD") {
+                    profile.preload = Some(number(words.next())?);
+                } else if w.eq_ignore_ascii_case("END") {
+                    let output = number(words.next())?;
+                    profile.end_output = Some((output, on_off(words.next())?));
+                } else {
+                    return Err(ParseError::BadArgument);
+                }
+                option = words.next();
+            }
+            Command::Profile(profile)
+        }
+    } else if keyword.eq_ignore_ascii_case("RUN") {
+        let name = words.next().and_then(Name::new);
+        Command::Run(name.ok_or(ParseError::BadArgument)?)
+    } else if keyword.eq_ignore_ascii_case("CLAMP") {
+        Command::Clamp(clamp_angle(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("CLAMP?") {
+        Command::ClampQuery
+    } else if keyword.eq_ignore_ascii_case("REPLAY") {
+        Command::Replay(number(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("ERRLOG?") {
+        Command::ErrLog
+    } else if keyword.eq_ignore_ascii_case("CALLOG?") {
+        match words.next() {
+            None => Command::CalLog(None),
+            n => Command::CalLog(Some(number(n)?)),
+        }
+    } else if keyword.eq_ignore_ascii_case("TEMPCO") {
+        Command::TempCo(signed_milli(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("AUX") {
+        let channel = number(words.next())?;
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Aux(channel, None),
+            gain => {
+                let gain = signed_milli(gain)?;
+                let offset = match words.next() {
+                    None => 0,
+                    offset => signed_milli(offset)?,
+                };
+                Command::Aux(channel, Some((gain, offset)))
+            }
+        }
+    } else if keyword.eq_ignore_ascii_case("EXT") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("ZERO") => Command::ExtZero,
+            Some(w) if w.eq_ignore_ascii_case("SCALE") => {
+                Command::ExtScale(signed_milli(words.next())?)
+            }
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("EXT?") {
+        Command::ExtQuery
+    } else if keyword.eq_ignore_ascii_case("WORK") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Work(None),
+            Some(w) if w.eq_ignore_ascii_case("AUX") => {
+                let channel = number(words.next())?;
+                let stream = match words.next() {
+                    None => false,
+                    Some(w) if w.eq_ignore_ascii_case("STREAM") => true,
+                    Some(_) => return Err(ParseError::BadArgument),
+                };
+                Command::Work(Some((channel, stream)))
+            }
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("WORK?") {
+        Command::WorkQuery
+    } else if keyword.eq_ignore_ascii_case("CHAMBER") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("OFF") => Command::Chamber(None),
+            Some(w) if w.eq_ignore_ascii_case("PID") => Command::ChamberPid(Gains {
+                kp_milli: milli(words.next())?,
+                ki_milli: milli(words.next())?,
+                kd_milli: milli(words.next())?,
+            }),
+            temp => Command::Chamber(Some(milli(temp)?)),
+        }
+    } else if keyword.eq_ignore_ascii_case("CHAMBER?") {
+        Command::ChamberQuery
+    } else if keyword.eq_ignore_ascii_case("CONFIG?") {
+        Command::Config
+    } else if keyword.eq_ignore_ascii_case("CONFIG") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("DUMP") => Command::ConfigDump,
+            Some(w) if w.eq_ignore_ascii_case("LOAD") => Command::ConfigLoad,
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("INFO?") {
+        Command::Info
+    } else if keyword.eq_ignore_ascii_case("NAME?") {
+        Command::NameQuery
+    } else if keyword.eq_ignore_ascii_case("NAME") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("CLEAR") => Command::Name(None),
+            name => Command::Name(Some(
+                name.and_then(DeviceName::new)
+                    .ok_or(ParseError::BadArgument)?,
+            )),
+        }
+    } else if keyword.eq_ignore_ascii_case("SELFTEST?") {
+        Command::SelfTest
+    } else if keyword.eq_ignore_ascii_case("CAPS?") {
+        Command::Caps
+    } else if keyword.eq_ignore_ascii_case("STATUS?") {
+        Command::Status
+    } else if keyword.eq_ignore_ascii_case("LOG?") {
+        Command::LogQuery
+    } else if keyword.eq_ignore_ascii_case("LOG") {
+        match (words.next(), words.next()) {
+            (Some(w), Some(level)) if w.eq_ignore_ascii_case("LEVEL") => {
+                if level.eq_ignore_ascii_case("OFF") {
+                    Command::Log(None)
+                } else {
+                    Command::Log(Some(Level::parse(level).ok_or(ParseError::BadArgument)?))
+                }
+            }
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("MIDTEST?") {
+        Command::MidTestQuery
+    } else if keyword.eq_ignore_ascii_case("MIDTEST") {
+        let policy = words.next().and_then(Policy::parse);
+        Command::MidTest(policy.ok_or(ParseError::BadArgument)?)
+    } else if keyword.eq_ignore_ascii_case("DIAG?") {
+        Command::Diag
+    } else if keyword.eq_ignore_ascii_case("DIAG") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("RESET") => Command::DiagReset,
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("HEARTBEAT") {
+        Command::Heartbeat(number(words.next())?)
+    } else if keyword.eq_ignore_ascii_case("POWER?") {
+        Command::PowerQuery
+    } else if keyword.eq_ignore_ascii_case("POWER") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("NORMAL") => Command::Power(None),
+            Some(w) if w.eq_ignore_ascii_case("LOW") => Command::Power(Some(number(words.next())?)),
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("PEAK?") {
+        Command::Peak
+    } else if keyword.eq_ignore_ascii_case("PEAK") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("RESET") => Command::PeakReset,
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("CALCHECK?") {
+        Command::CalCheckQuery
+    } else if keyword.eq_ignore_ascii_case("CALCHECK") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("START") => Command::CalCheckStart,
+            Some(w) if w.eq_ignore_ascii_case("POINT") => {
+                Command::CalCheckPoint(Milligrams(milli(words.next())?))
+            }
+            Some(w) if w.eq_ignore_ascii_case("END") => Command::CalCheckEnd,
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if keyword.eq_ignore_ascii_case("NOISE?") {
+        Command::NoiseQuery
+    } else if keyword.eq_ignore_ascii_case("STATS?") {
+        Command::Stats
+    } else if keyword.eq_ignore_ascii_case("STATS") {
+        match words.next() {
+            Some(w) if w.eq_ignore_ascii_case("RESET") => Command::StatsReset,
+            _ => return Err(ParseError::BadArgument),
+        }
+    } else if header_matches("UNIT:FORCe", keyword) {
+        let unit = words.next().and_then(Unit::parse);
+        Command::Scpi(Scpi::Unit(unit.ok_or(ParseError::BadArgument)?))
+    } else if let Some((_, scpi)) = SCPI_HEADERS
+        .iter()
+        .find(|(pattern, _)| header_matches(pattern, keyword))
+    {
+        Command::Scpi(*scpi)
+    } else {
+        return Err(ParseError::Unknown);
+    };
+
+    if words.next().is_some() {
+        return Err(ParseError::BadArgument);
+    }
+    Ok(command)
+}
+
+fn on_off(word: Option<&str>) -> Result<bool, ParseError> {
+    match word {
+        Some(w) if w.eq_ignore_ascii_case("ON") => Ok(true),
+        Some(w) if w.eq_ignore_ascii_case("OFF") => Ok(false),
+        _ => Err(ParseError::BadArgument),
+    }
+}
+
+/// The `MASS <g>`, `BY <operator>` and `CREEP <ppm> <tau s>` after a `CAL`
+/// factor, in any order.
+fn cal_note<'a>(mut words: impl Iterator<Item = &'a str>) -> Result<CalNote, ParseError> {
+    let mut note = CalNote {
+        mass: None,
+        operator: None,
+        creep: None,
+    };
+    while let Some(word) = words.next() {
+        if word.eq_ignore_ascii_case("MASS") && note.mass.is_none() {
+            note.mass = Some(Milligrams(milli(words.next())?));
+        } else if word.eq_ignore_ascii_case("BY") && note.operator.is_none() {
+            let operator = words.next().and_then(Operator::parse);
+            note.operator = Some(operator.ok_or(ParseError::BadArgument)?);
+        } else if word.eq_ignore_ascii_case("CREEP") && note.creep.is_none() {
+            let creep = Creep::new(number(words.next())?, number(words.next())?);
+            note.creep = Some(creep.ok_or(ParseError::BadArgument)?);
+        } else {
+            return Err(ParseError::BadArgument);
+        }
+    }
+    Ok(note)
+}
+
+/// `OPEN`, `CLOSE` or an angle in degrees.
+fn clamp_angle(word: Option<&str>) -> Result<u8, ParseError> {
+    match word {
+        Some(w) if w.eq_ignore_ascii_case("OPEN") => Ok(servo::OPEN_DEG),
+        Some(w) if w.eq_ignore_ascii_case("CLOSE") => Ok(servo::CLOSED_DEG),
+        w => match number(w)? {
+            deg if deg <= servo::MAX_DEG => Ok(deg),
+            _ => Err(ParseError::BadArgument),
+        },
+    }
+}
+
+fn number<T: core::str::FromStr>(word: Option<&str>) -> Result<T, ParseError> {
+    word.and_then(|w| w.parse().ok())
+        .ok_or(ParseError::BadArgument)
+}
+
+/// Parse a non-negative decimal such as `12`, `0.5` or `2.125` into
+/// thousandths.
+fn milli(word: Option<&str>) -> Result<u32, ParseError> {
+    fixed(word, 3)
+}
+
+/// Like `milli` but accepts a leading `-`.
+fn signed_milli(word: Option<&str>) -> Result<i32, ParseError> {
+    let word = word.ok_or(ParseError::BadArgument)?;
+    let (negative, digits) = match word.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, word),
+    };
+    let value = i32::try_from(milli(Some(digits))?).map_err(|_| ParseError::BadArgument)?;
+    Ok(if negative { -value } else { value })
+}
+
+/// Parse a non-negative decimal with at most `places` fractional digits,
+/// scaled by `10^places`.
+fn fixed(word: Option<&str>, places: u32) -> Result<u32, ParseError> {
+    let word = word.ok_or(ParseError::BadArgument)?;
+    let (int, frac) = word.split_once('.').unwrap_or((word, ""));
+    if frac.len() > places as usize || (int.is_empty() && frac.is_empty()) {
+        return Err(ParseError::BadArgument);
+    }
+    let int: u32 = if int.is_empty() {
+        0
+    } else {
+        int.parse().map_err(|_| ParseError::BadArgument)?
+    };
+    let mut fraction = 0;
+    let mut weight = 10u32.pow(places);
+    for digit in frac.bytes() {
+        if !digit.is_ascii_digit() {
+            return Err(ParseError::BadArgument);
+        }
+        weight /= 10;
+        fraction += (digit - b'0') as u32 * weight;
+    }
+    int.checked_mul(10u32.pow(places))
+        .and_then(|v| v.checked_add(fraction))
+        .ok_or(ParseError::BadArgument)
+}
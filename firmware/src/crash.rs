@@ -0,0 +1,38 @@
+//! Panic handler that keeps the panic in RAM across the reboot, so a board
+//! in the field can say why it went down. Reported with `Event: PANIC` to
+//! the first host that connects.
+
+use core::mem::MaybeUninit;
+use core::panic::PanicInfo;
+use core::ptr::{addr_of, addr_of_mut};
+
+use tensile_core::crash::{Crash, RECORD_LEN};
+
+/// Left alone by the runtime at reset, unlike `.bss`.
+#[link_section = ".uninit.CRASH"]
+static mut CRASH: MaybeUninit<[u8; RECORD_LEN]> = MaybeUninit::uninit();
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    cortex_m::interrupt::disable();
+    let (file, line) = info.location().map_or(("", 0), |l| (l.file(), l.line()));
+    let crash = Crash::new(file, line, info.message());
+    // SAFETY: interrupts are off and nothing else touches the record.
+    unsafe { addr_of_mut!(CRASH).write_volatile(MaybeUninit::new(*crash.raw())) };
+    defmt::error!("{}", defmt::Display2Format(info));
+    // `HardFault` drops the outputs and reboots.
+    cortex_m::asm::udf()
+}
+
+/// The panic that ended the last boot, if it was one. Clears the record,
+/// so it is only reported once.
+pub fn take() -> Option<Crash> {
+    // SAFETY: called once at boot, before the other core is started. Any
+    // bytes will do; they are checked before use.
+    let raw = unsafe {
+        let raw = addr_of!(CRASH).read_volatile().assume_init();
+        addr_of_mut!(CRASH).write_volatile(MaybeUninit::new([0; RECORD_LEN]));
+        raw
+    };
+    Crash::from_raw(raw)
+}
@@ -0,0 +1,69 @@
+//! Fault log persisted in the last two flash sectors, read back with
+//! `ERRLOG?`.
+
+use tensile_core::errlog::{Entry, RingIndex, ENTRY_LEN};
+
+use crate::flash::{self, PAGE_SIZE, SECTOR_SIZE};
+
+/// Must match the space carved out of FLASH in memory.x.
+pub const LOG_OFFSET: u32 = 2048 * 1024 - SECTORS as u32 * SECTOR_SIZE;
+const SECTORS: usize = 2;
+const SLOTS_PER_SECTOR: usize = SECTOR_SIZE as usize / ENTRY_LEN;
+
+pub struct ErrorLog {
+    ring: RingIndex,
+}
+
+impl ErrorLog {
+    /// Find where the log left off before this boot.
+    pub fn load() -> Self {
+        let seqs = (0..SECTORS * SLOTS_PER_SECTOR).map(|slot| read_slot(slot).map(|e| e.seq));
+        Self {
+            ring: RingIndex::scan(SECTORS, SLOTS_PER_SECTOR, seqs),
+        }
+    }
+
+    /// Write one entry. Takes a few ms, or ~50 ms when a sector is erased.
+    /// Core 1 must be parked for the duration.
+    pub fn append(&mut self, session: u32, uptime_s: u32, code: u16, arg: u16) {
+        let append = self.ring.append();
+        if let Some(sector) = append.erase {
+            flash::erase_sector(LOG_OFFSET + sector as u32 * SECTOR_SIZE);
+        }
+        let entry = Entry {
+            seq: append.seq,
+            session,
+            uptime_s,
+            code,
+            arg,
+        };
+        let offset = slot_offset(append.slot);
+        let page = offset & !(PAGE_SIZE as u32 - 1);
+        let at = (offset - page) as usize;
+        let mut data = [0xFF; PAGE_SIZE];
+        data[at..at + ENTRY_LEN].copy_from_slice(&entry.encode());
+        flash::program_page(page, &data);
+    }
+
+    /// Number of stored entries, and how many of them look corrupted.
+    pub fn check(&self) -> (u32, u32) {
+        self.entries().fold((0, 0), |(n, bad), e| {
+            (n + 1, bad + !e.is_plausible() as u32)
+        })
+    }
+
+    /// Every stored entry, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = Entry> + '_ {
+        self.ring.oldest_first().filter_map(read_slot)
+    }
+}
+
+fn slot_offset(slot: usize) -> u32 {
+    LOG_OFFSET + (slot * ENTRY_LEN) as u32
+}
+
+fn read_slot(slot: usize) -> Option<Entry> {
+    let mut raw = [0; ENTRY_LEN];
+    flash::read(slot_offset(slot), &mut raw);
+    Entry::decode(&raw)
+}
@@ -0,0 +1,157 @@
+//! Erase and program the on-board QSPI flash through the boot ROM, and read
+//! its unique ID.
+//!
+//! The firmware executes in place from this same flash, so while the ROM
+//! routines run nothing may fetch from it: interrupts are masked, the
+//! sequence itself runs from RAM, and callers must have core 1 parked in RAM
+//! (`Acquisition::parked`). Afterwards XIP is restored by calling a RAM
+//! copy of the second-stage bootloader, which sets the fast read mode back up.
+
+use rp_pico::hal::rom_data;
+
+pub const SECTOR_SIZE: u32 = 4096;
+pub const PAGE_SIZE: usize = 256;
+
+const XIP_BASE: u32 = 0x1000_0000;
+/// Sector erase command; the ROM falls back to it for small ranges anyway.
+const SECTOR_ERASE_CMD: u8 = 0x20;
+
+/// Read Unique ID: the command, four dummy bytes, then the 64-bit ID.
+const UNIQUE_ID_CMD: u8 = 0x4B;
+const UNIQUE_ID_DUMMY: usize = 4;
+pub const UNIQUE_ID_LEN: usize = 8;
+
+// Registers for driving the SSI by hand once XIP is down.
+const SSI_SR: *const u32 = 0x1800_0028 as *const u32;
+const SSI_DR0: *mut u32 = 0x1800_0060 as *mut u32;
+const SSI_SR_TFNF: u32 = 1 << 1;
+const SSI_SR_RFNE: u32 = 1 << 3;
+/// FIFO depth less a margin, so the receive FIFO can never overflow.
+const SSI_MAX_IN_FLIGHT: usize = 14;
+/// `GPIO_QSPI_SS_CTRL`: chip-select output override.
+const QSPI_SS_CTRL: *mut u32 = 0x4001_800c as *mut u32;
+const QSPI_SS_OUTOVER_MASK: u32 = 3 << 8;
+const QSPI_SS_OUTOVER_LOW: u32 = 2 << 8;
+const QSPI_SS_OUTOVER_HIGH: u32 = 3 << 8;
+
+/// Copy `buf.len()` bytes starting `offset` bytes into flash.
+pub fn read(offset: u32, buf: &mut [u8]) {
+    for (i, byte) in buf.iter_mut().enumerate() {
+        // SAFETY: the whole 2 MiB flash is mapped read-only at XIP_BASE.
+        *byte = unsafe { core::ptr::read_volatile((XIP_BASE + offset + i as u32) as *const u8) };
+    }
+}
+
+/// Erase the 4 KiB sector at `offset`, which must be sector aligned.
+pub fn erase_sector(offset: u32) {
+    run(offset, None);
+}
+
+/// Program one 256-byte page at `offset`, which must be page aligned. Bits
+/// can only be cleared, so 0xFF bytes leave existing data untouched.
+pub fn program_page(offset: u32, data: &[u8; PAGE_SIZE]) {
+    run(offset, Some(data));
+}
+
+/// The flash chip's factory-programmed 64-bit unique ID, which the RP2040
+/// itself lacks. Like the write routines, core 1 must not be executing
+/// from flash.
+pub fn unique_id() -> [u8; UNIQUE_ID_LEN] {
+    let (rom, boot2) = prepare();
+    let mut buf = [0u8; 1 + UNIQUE_ID_DUMMY + UNIQUE_ID_LEN];
+    buf[0] = UNIQUE_ID_CMD;
+    cortex_m::interrupt::free(|_| {
+        // SAFETY: as in `run`.
+        unsafe { transfer_from_ram(&rom, boot2.as_ptr(), buf.as_mut_ptr(), buf.len()) }
+    });
+    let mut id = [0; UNIQUE_ID_LEN];
+    id.copy_from_slice(&buf[1 + UNIQUE_ID_DUMMY..]);
+    id
+}
+
+struct RomFns {
+    connect_internal_flash: unsafe extern "C" fn(),
+    flash_exit_xip: unsafe extern "C" fn(),
+    flash_range_erase: unsafe extern "C" fn(u32, usize, u32, u8),
+    flash_range_program: unsafe extern "C" fn(u32, *const u8, usize),
+    flash_flush_cache: unsafe extern "C" fn(),
+}
+
+/// Look everything up while flash is still readable.
+fn prepare() -> (RomFns, [u32; 64]) {
+    let rom = RomFns {
+        connect_internal_flash: rom_data::connect_internal_flash::ptr(),
+        flash_exit_xip: rom_data::flash_exit_xip::ptr(),
+        flash_range_erase: rom_data::flash_range_erase::ptr(),
+        flash_range_program: rom_data::flash_range_program::ptr(),
+        flash_flush_cache: rom_data::flash_flush_cache::ptr(),
+    };
+    let mut boot2 = [0u32; 64];
+    for (i, word) in boot2.iter_mut().enumerate() {
+        // SAFETY: boot2 occupies the first 256 bytes of flash.
+        *word = unsafe { core::ptr::read_volatile((XIP_BASE as *const u32).add(i)) };
+    }
+    (rom, boot2)
+}
+
+fn run(offset: u32, data: Option<&[u8; PAGE_SIZE]>) {
+    let (rom, boot2) = prepare();
+    let data = data.map_or(core::ptr::null(), |d| d.as_ptr());
+
+    cortex_m::interrupt::free(|_| {
+        // SAFETY: interrupts are off and the caller has parked core 1, so
+        // nothing else touches flash while XIP is down.
+        unsafe { run_from_ram(&rom, boot2.as_ptr(), offset, data) }
+    });
+}
+
+/// Everything between leaving and re-entering XIP. Only calls through the
+/// pointers it is given, so it never fetches from flash.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn run_from_ram(rom: &RomFns, boot2: *const u32, offset: u32, data: *const u8) {
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    if data.is_null() {
+        (rom.flash_range_erase)(offset, SECTOR_SIZE as usize, SECTOR_SIZE, SECTOR_ERASE_CMD);
+    } else {
+        (rom.flash_range_program)(offset, data, PAGE_SIZE);
+    }
+    (rom.flash_flush_cache)();
+    // Thumb bit set; boot2 returns to the caller when entered with lr != 0.
+    let boot2: unsafe extern "C" fn() = core::mem::transmute(boot2 as usize | 1);
+    boot2();
+}
+
+/// Clock `len` bytes of `buf` out to the flash as one command and replace
+/// them with what comes back, with chip select held low throughout.
+#[inline(never)]
+#[link_section = ".data.ram_func"]
+unsafe fn transfer_from_ram(rom: &RomFns, boot2: *const u32, buf: *mut u8, len: usize) {
+    (rom.connect_internal_flash)();
+    (rom.flash_exit_xip)();
+    let ss = core::ptr::read_volatile(QSPI_SS_CTRL) & !QSPI_SS_OUTOVER_MASK;
+    core::ptr::write_volatile(QSPI_SS_CTRL, ss | QSPI_SS_OUTOVER_LOW);
+    // Wrapping arithmetic throughout: an overflow check would call into
+    // the panic machinery in flash.
+    let (mut sent, mut received) = (0usize, 0usize);
+    while received < len {
+        let status = core::ptr::read_volatile(SSI_SR);
+        if status & SSI_SR_TFNF != 0
+            && sent < len
+            && sent.wrapping_sub(received) < SSI_MAX_IN_FLIGHT
+        {
+            core::ptr::write_volatile(SSI_DR0, *buf.add(sent) as u32);
+            sent = sent.wrapping_add(1);
+        }
+        if status & SSI_SR_RFNE != 0 {
+            *buf.add(received) = core::ptr::read_volatile(SSI_DR0) as u8;
+            received = received.wrapping_add(1);
+        }
+    }
+    core::ptr::write_volatile(QSPI_SS_CTRL, ss | QSPI_SS_OUTOVER_HIGH);
+    // Also hands chip select back to the SSI.
+    (rom.flash_flush_cache)();
+    let boot2: unsafe extern "C" fn() = core::mem::transmute(boot2 as usize | 1);
+    boot2();
+}
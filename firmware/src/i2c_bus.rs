@@ -0,0 +1,21 @@
+//! One I2C bus shared by several drivers on core 0.
+
+use core::cell::RefCell;
+
+use embedded_hal::i2c::{ErrorType, I2c, Operation};
+
+pub struct SharedI2c<'a, I>(pub &'a RefCell<I>);
+
+impl<I: ErrorType> ErrorType for SharedI2c<'_, I> {
+    type Error = I::Error;
+}
+
+impl<I: I2c> I2c for SharedI2c<'_, I> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.0.borrow_mut().transaction(address, operations)
+    }
+}
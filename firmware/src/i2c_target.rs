@@ -0,0 +1,182 @@
+//! I2C target on I2C1 for a PLC or another microcontroller to poll,
+//! alongside the USB host.
+//!
+//! GPIO6 is SDA and GPIO7 SCL, at up to 400 kHz; the bus needs its own
+//! pull-ups. Write the register address, then read on from it. Registers,
+//! 32-bit values high byte first:
+//!
+//! | Address   | Value                                                    |
+//! |-----------|----------------------------------------------------------|
+//! | 0x00      | 0x54 (`T`), to tell the tester is there                  |
+//! | 0x01      | status bits: 0 running, 1 paused, 2 start scheduled,     |
+//! |           | 3 overload, 4 sensor fault, 5 calibrated                 |
+//! | 0x02–0x05 | filtered, tared force in counts                          |
+//! | 0x06–0x09 | force in thousandths of the current unit (counts if raw) |
+//! | 0x0A–0x0D | peak force in counts since tare or peak reset            |
+//! | 0x0E–0x11 | extensometer displacement in µm                          |
+//! | 0x12–0x15 | sequence number of the next `Force:` line                |
+//! | 0x16      | command: write 1 start, 2 stop, 3 tare, 4 peak reset     |
+//!
+//! Forces and displacement read `i32::MIN` until there is a value, and
+//! displacement always without the `extensometer` feature. A read takes
+//! every value from the same moment.
+
+use bsp::hal::{
+    fugit::ExtU64,
+    gpio::{
+        bank0::{Gpio6, Gpio7},
+        FunctionI2C, Pin, PullUp,
+    },
+    i2c::{peripheral::Event, Peripheral},
+    pac, Timer, I2C,
+};
+use rp_pico as bsp;
+use tensile_core::i2c_target::Bank;
+pub use tensile_core::i2c_target::ADDRESSES;
+
+/// Address used until `I2C` picks another.
+pub const DEFAULT_ADDRESS: u8 = 0x2A;
+
+const ID: u8 = b'T';
+const COMMAND: u8 = 0x16;
+const MAP_LEN: usize = COMMAND as usize + 1;
+
+/// How long the main loop waits on a transfer that has started. 24 bytes
+/// take 2.4 ms at 100 kHz; a slower controller has the clock stretched
+/// until the next pass.
+const TRANSFER_MS: u64 = 5;
+
+pub type Pins = (
+    Pin<Gpio6, FunctionI2C, PullUp>,
+    Pin<Gpio7, FunctionI2C, PullUp>,
+);
+type Bus = I2C<pac::I2C1, Pins, Peripheral>;
+
+/// Actions requested through the command register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Start,
+    Stop,
+    Tare,
+    PeakReset,
+}
+
+/// Snapshot of the tester served to one transfer.
+pub struct Image {
+    pub force: Option<i32>,
+    pub force_unit: Option<i32>,
+    pub peak: Option<i32>,
+    pub displacement: Option<i32>,
+    pub status: u8,
+    pub seq: u32,
+}
+
+impl Image {
+    pub const RUNNING: u8 = 1 << 0;
+    pub const PAUSED: u8 = 1 << 1;
+    pub const SCHEDULED: u8 = 1 << 2;
+    pub const OVERLOAD: u8 = 1 << 3;
+    pub const FAULT: u8 = 1 << 4;
+    pub const CALIBRATED: u8 = 1 << 5;
+
+    fn encode(&self) -> [u8; MAP_LEN] {
+        let mut regs = [0; MAP_LEN];
+        regs[0] = ID;
+        regs[1] = self.status;
+        let values = [
+            self.force,
+            self.force_unit,
+            self.peak,
+            self.displacement,
+            Some(self.seq as i32),
+        ];
+        for (value, at) in values.iter().zip(regs[2..COMMAND as usize].chunks_mut(4)) {
+            at.copy_from_slice(&value.unwrap_or(i32::MIN).to_be_bytes());
+        }
+        regs
+    }
+}
+
+enum Port {
+    Listening(Bus),
+    Off(pac::I2C1, Pins),
+}
+
+pub struct Target {
+    /// Only ever `None` while the address changes.
+    port: Option<Port>,
+    address: Option<u8>,
+    bank: Bank<MAP_LEN>,
+}
+
+impl Target {
+    pub fn new(i2c: pac::I2C1, pins: Pins, resets: &mut pac::RESETS) -> Self {
+        let mut target = Self {
+            port: Some(Port::Off(i2c, pins)),
+            address: None,
+            bank: Bank::new(),
+        };
+        // Starts, requests and received bytes wake the main loop out of WFE.
+        target.set_address(Some(DEFAULT_ADDRESS), resets);
+        target
+    }
+
+    /// Bus address, or `None` while switched off.
+    pub fn address(&self) -> Option<u8> {
+        self.address
+    }
+
+    /// Drops a transfer in progress.
+    pub fn set_address(&mut self, address: Option<u8>, resets: &mut pac::RESETS) {
+        let (i2c, (sda, scl)) = match self.port.take() {
+            Some(Port::Listening(bus)) => bus.free(resets),
+            Some(Port::Off(i2c, pins)) => (i2c, pins),
+            None => unreachable!(),
+        };
+        self.port = Some(match address {
+            Some(address) => Port::Listening(I2C::new_peripheral_event_iterator(
+                i2c, sda, scl, resets, address,
+            )),
+            None => Port::Off(i2c, (sda, scl)),
+        });
+        self.address = address;
+    }
+
+    /// Answer whatever the controller is doing. `image` is built at each
+    /// start condition. Returns the last command written, if any.
+    pub fn serve(&mut self, timer: &Timer, mut image: impl FnMut() -> Image) -> Option<Control> {
+        let Some(Port::Listening(bus)) = &mut self.port else {
+            return None;
+        };
+        let mut control = None;
+        let mut deadline = None;
+        loop {
+            match bus.next_event() {
+                Some(Event::Start) => {
+                    self.bank.start(image().encode());
+                    deadline = Some(timer.get_counter() + TRANSFER_MS.millis());
+                }
+                Some(Event::Restart) => self.bank.restart(),
+                Some(Event::TransferRead) => {
+                    bus.write(&[self.bank.send()]);
+                }
+                Some(Event::TransferWrite) => {
+                    let mut buf = [0; 16];
+                    let n = bus.read(&mut buf);
+                    for &byte in &buf[..n] {
+                        control = match self.bank.receive(byte) {
+                            Some((COMMAND, 1)) => Some(Control::Start),
+                            Some((COMMAND, 2)) => Some(Control::Stop),
+                            Some((COMMAND, 3)) => Some(Control::Tare),
+                            Some((COMMAND, 4)) => Some(Control::PeakReset),
+                            _ => control,
+                        };
+                    }
+                }
+                Some(Event::Stop) => deadline = None,
+                None if deadline.is_some_and(|at| timer.get_counter() < at) => {}
+                None => return control,
+            }
+        }
+    }
+}
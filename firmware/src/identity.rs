@@ -0,0 +1,26 @@
+//! The tester's name, persisted in the flash sector below the calibration
+//! history and set with `NAME`.
+
+use tensile_core::identity::{DeviceName, RECORD_LEN};
+
+use crate::callog;
+use crate::flash::{self, PAGE_SIZE, SECTOR_SIZE};
+
+/// Must match the space carved out of FLASH in memory.x.
+const NAME_OFFSET: u32 = callog::LOG_OFFSET - SECTOR_SIZE;
+
+pub fn load() -> Option<DeviceName> {
+    let mut raw = [0; RECORD_LEN];
+    flash::read(NAME_OFFSET, &mut raw);
+    DeviceName::decode(&raw)
+}
+
+/// Store `name`, or erase it. Core 1 must be parked.
+pub fn save(name: Option<&DeviceName>) {
+    flash::erase_sector(NAME_OFFSET);
+    if let Some(name) = name {
+        let mut page = [0xFF; PAGE_SIZE];
+        page[..RECORD_LEN].copy_from_slice(&name.encode());
+        flash::program_page(NAME_OFFSET, &page);
+    }
+}
@@ -0,0 +1,100 @@
+//! Log lines for the host, so driver diagnostics can be had in the field
+//! without a debug probe. Every line goes to defmt as before; those at or
+//! above `LOG LEVEL` are also queued to go out as `Log: <level> <text>`.
+//!
+//! Safe from either core.
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use critical_section::Mutex;
+pub use tensile_core::log::Level;
+use tensile_core::log::{Entry, Queue};
+use ufmt::uWrite;
+
+const QUEUE_LEN: usize = 16;
+
+/// `Level as u8`, 0 while off.
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+static QUEUE: Mutex<RefCell<Queue<QUEUE_LEN>>> = Mutex::new(RefCell::new(Queue::new()));
+
+/// A line being formatted.
+pub struct Line(pub Entry);
+
+impl uWrite for Line {
+    type Error = ();
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        self.0.push_str(s);
+        Ok(())
+    }
+}
+
+pub fn level() -> Option<Level> {
+    Level::from_u8(LEVEL.load(Ordering::Relaxed))
+}
+
+pub fn set_level(level: Option<Level>) {
+    LEVEL.store(level.map_or(0, |l| l as u8), Ordering::Relaxed);
+}
+
+/// Lines the queue had to drop since boot.
+pub fn lost() -> u32 {
+    critical_section::with(|cs| QUEUE.borrow_ref(cs).lost())
+}
+
+/// The oldest line not yet sent.
+pub fn pop() -> Option<Entry> {
+    critical_section::with(|cs| QUEUE.borrow_ref_mut(cs).pop())
+}
+
+/// Log the line `write` builds. Use the macros.
+pub fn log(level: Level, write: impl FnOnce(&mut Line)) {
+    let mut line = Line(Entry::new(level));
+    write(&mut line);
+    let text = line.0.as_str();
+    match level {
+        Level::Error => defmt::error!("{=str}", text),
+        Level::Warn => defmt::warn!("{=str}", text),
+        Level::Info => defmt::info!("{=str}", text),
+        Level::Debug => defmt::debug!("{=str}", text),
+        Level::Trace => defmt::trace!("{=str}", text),
+    }
+    if self::level().is_some_and(|max| level <= max) {
+        critical_section::with(|cs| QUEUE.borrow_ref_mut(cs).push(line.0));
+    }
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Error, |w| {
+            let _ = ufmt::uwrite!(w, $($arg)*);
+        })
+    };
+}
+
+// Renamed on export; `warn` alone clashes with the built-in attribute.
+macro_rules! warn_ {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Warn, |w| {
+            let _ = ufmt::uwrite!(w, $($arg)*);
+        })
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Info, |w| {
+            let _ = ufmt::uwrite!(w, $($arg)*);
+        })
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::Level::Debug, |w| {
+            let _ = ufmt::uwrite!(w, $($arg)*);
+        })
+    };
+}
+
+pub(crate) use {debug, error, info, warn_ as warn};
@@ -1,64 +1,937 @@
 #![no_std]
 #![no_main]
 
+mod acquire;
+mod board;
+#[cfg(feature = "buzzer")]
+mod buzzer;
+mod callog;
+#[cfg(feature = "chamber")]
+mod chamber;
+mod channel;
+mod command;
+mod crash;
+mod errlog;
+mod flash;
+#[cfg(any(feature = "oled", feature = "rtc"))]
+mod i2c_bus;
+#[cfg(feature = "i2c-target")]
+mod i2c_target;
+mod identity;
+mod log;
+#[cfg(feature = "modbus")]
+mod modbus;
+#[cfg(feature = "oled")]
+mod oled;
+#[cfg(any(feature = "encoder", feature = "tare-button"))]
+mod panel;
+mod profile;
+#[cfg(feature = "extensometer")]
+mod quadrature;
+#[cfg(feature = "rtc")]
+mod rtc;
+mod sensor;
+#[cfg(feature = "servo")]
+mod servo;
+mod status;
+mod testlog;
+#[cfg(feature = "uart-stream")]
+mod uart;
+
+#[cfg(all(feature = "modbus", feature = "uart-stream"))]
+compile_error!("`modbus` and `uart-stream` both use UART0");
+#[cfg(all(feature = "modbus", feature = "board-carrier"))]
+compile_error!("the carrier board's HX711 is on GPIO2, Modbus's driver enable");
+#[cfg(all(feature = "i2c-target", any(feature = "oled", feature = "rtc")))]
+compile_error!("`i2c-target` takes I2C1, the OLED's and RTC's bus");
+#[cfg(all(feature = "buzzer", feature = "hx711-ch1"))]
+compile_error!("`buzzer` and `hx711-ch1` both use GPIO15");
+#[cfg(all(
+    feature = "extensometer",
+    any(feature = "hx711-ch1", feature = "buzzer")
+))]
+compile_error!("`extensometer` uses GPIO14/15, as do `hx711-ch1` and `buzzer`");
+#[cfg(all(feature = "chamber", feature = "encoder"))]
+compile_error!("`chamber` and `encoder` both use GPIO9");
+#[cfg(all(feature = "servo", feature = "encoder"))]
+compile_error!("`servo` and `encoder` both use GPIO8");
+#[cfg(all(feature = "encoder", feature = "tare-button"))]
+compile_error!("`tare-button` and `encoder` share the button; the encoder's tares when held");
+#[cfg(all(feature = "digital-inputs", feature = "ads1256"))]
+compile_error!("`digital-inputs` use GPIO18-21, the ADS1256's SPI bus");
+
 use bsp::entry;
+use cortex_m_rt::{exception, ExceptionFrame};
 use defmt_rtt as _;
-use panic_probe as _;
 use rp_pico as bsp;
 
+#[cfg(any(feature = "ads1256", feature = "modbus", feature = "uart-stream"))]
+use bsp::hal::clocks::Clock;
+#[cfg(feature = "digital-inputs")]
+use bsp::hal::gpio::Interrupt;
 use bsp::hal::{
-    clocks::{init_clocks_and_plls, Clock},
+    adc::{Adc, AdcPin},
     pac,
+    rosc::RingOscillator,
     sio::Sio,
+    timer::Alarm,
     usb::UsbBus,
     watchdog::Watchdog,
     Timer, // Import Timer
 };
+#[cfg(feature = "ads1256")]
+use bsp::hal::{gpio::FunctionSpi, spi::Spi};
+#[cfg(any(feature = "nau7802", feature = "oled", feature = "rtc"))]
+use bsp::hal::{
+    gpio::{FunctionI2C, Pin, PullUp},
+    I2C,
+};
+
+use acquire::{Acquisition, EXTRA_CHANNELS};
+use channel::Channel;
+#[cfg(feature = "uart-stream")]
+use command::UartMode;
+use command::{CalNote, Command, ErrorCode, LineBuffer, Scpi, StartTime};
+use embedded_hal::delay::DelayNs;
+#[cfg(any(feature = "digital-inputs", feature = "bluetooth"))]
+use embedded_hal::digital::InputPin;
+use embedded_hal::digital::{OutputPin, StatefulOutputPin};
+use embedded_hal_0_2::adc::OneShot;
+use fugit::ExtU64; // Import the time extension traits
+#[cfg(any(
+    feature = "nau7802",
+    feature = "ads1256",
+    feature = "oled",
+    feature = "rtc"
+))]
+use fugit::RateExtU32;
+#[cfg(feature = "buzzer")]
+use tensile_core::alarm::Alarm as AudibleAlarm;
+use tensile_core::analog::{adc_to_millivolts, AuxScale};
+#[cfg(feature = "bluetooth")]
+use tensile_core::bluetooth::Link;
+use tensile_core::burst::{self, Burst};
+use tensile_core::calcheck::CalCheck;
+use tensile_core::callog::{Operator, Record as CalRecord};
+use tensile_core::capture::{Capture, Length, Step};
+#[cfg(feature = "chamber")]
+use tensile_core::chamber::Gains;
+use tensile_core::clock::{DateTime, WallClock};
+use tensile_core::columns::{Column, Columns};
+use tensile_core::creep::CreepComp;
+use tensile_core::decimal;
+use tensile_core::diag::{Intervals, Timing};
+use tensile_core::digital::{DigitalInputs, INPUTS};
+#[cfg(feature = "oled")]
+use tensile_core::display::Frame;
+use tensile_core::dual::{Combine, DualCell};
+use tensile_core::errlog::{code_str, health_code};
+#[cfg(feature = "extensometer")]
+use tensile_core::extensometer::Extensometer;
+use tensile_core::filter::{
+    Decimator, FilterChain, LowPass, MovingAverage, SpikeFilter, SpikeMode,
+};
+use tensile_core::gaincheck::{Action, GainCheck};
+use tensile_core::health::{Health, SensorMonitor};
+use tensile_core::history::History;
+use tensile_core::indicator::Indication;
+#[cfg(feature = "encoder")]
+use tensile_core::input::Press;
+#[cfg(feature = "interlock")]
+use tensile_core::interlock::Interlock;
+use tensile_core::loading::{Direction, Loading, Polarity};
+use tensile_core::mark::Marks;
+use tensile_core::math::{crc32, Crc32};
+use tensile_core::meta::Metadata;
+use tensile_core::midtest::{Deferred, Policy};
+use tensile_core::pager::Pager;
+use tensile_core::peak::PeakHold;
+use tensile_core::profile::{Limits, Profiles};
+use tensile_core::qa::{DiffNoise, FrameDrift, NoiseCheck};
+use tensile_core::quantity::{Counts, Micros, MilliCelsius};
+use tensile_core::range::{AutoRange, Input};
+use tensile_core::rate::Derivative;
+use tensile_core::scpi::{self, ErrorQueue};
+use tensile_core::sequencer::{Phase, ResumeError, Sequencer};
+use tensile_core::specimen::{BreakDetector, SlipDetector};
+use tensile_core::stats::RunningStats;
+use tensile_core::stream::{self, AsciiEncoder, BinaryEncoder, Encoder, Protocol, Value};
+use tensile_core::tare::Tare;
+use tensile_core::temp::{adc_to_millicelsius, TempComp};
+use tensile_core::testlog::Summary;
+use tensile_core::trigger::Trigger;
+use tensile_core::units::{Scale, Unit, STANDARD_GRAVITY_UM_S2};
+use tensile_core::work::Work;
+use tensile_core::zero::ZeroTracker;
 
-use fugit::ExtU64;
-use hx711::Hx711; // Import the time extension trait
+#[cfg(feature = "ads1256")]
+use sensor::Ads1256Sensor;
+#[cfg(feature = "nau7802")]
+use sensor::Nau7802Sensor;
+use sensor::{AnySensor, Backend, ForceSensor, Hx711Sensor, SensorError};
 
 // --- USB IMPORTS ---
-use ufmt::{uWrite, uwriteln};
+use ufmt::{uWrite, uwrite, uwriteln};
 use usb_device::{class_prelude::*, prelude::*};
 use usbd_serial::SerialPort;
 
 // --- GLUE CODE ---
-struct SerialWrapper<'a, B: usb_device::bus::UsbBus>(SerialPort<'a, B>);
+/// Room for command responses and events waiting on the CDC endpoint.
+const CONTROL_QUEUE_LEN: usize = 256;
+/// Room for a whole `CONFIG?` or `CONFIG DUMP` reply, every line of which
+/// is shorter than the control queue.
+const PAGE_LEN: usize = 8 * 1024;
+
+/// Routes output over the two CDC interfaces. `port` carries the Force
+/// stream. While a host has `events` open, responses and events go there
+/// and `port` stays clean; otherwise everything shares `port`, as single-port
+/// tools expect.
+struct SerialWrapper<'a, B: usb_device::bus::UsbBus> {
+    port: SerialPort<'a, B>,
+    events: SerialPort<'a, B>,
+    /// Bytes the CDC endpoint had no room for.
+    dropped: u32,
+    /// The command being handled was answered `ERR <code>`, so gets no
+    /// `OK`.
+    rejected: Option<i16>,
+    /// Set while a deferred command runs: its `ERR` is only kept in
+    /// `rejected`, for the `APPLIED` event that answers it.
+    replaying: bool,
+    /// Control traffic (responses, events) not yet accepted by the endpoint.
+    /// On a shared port it always goes out before any more sample data.
+    control: [u8; CONTROL_QUEUE_LEN],
+    control_len: usize,
+    /// Set while writing sample data, which is dropped rather than queued
+    /// when the link is busy.
+    bulk: bool,
+    /// A replayed line, or the rest of a live one, still being written out.
+    /// Nothing else is sent until it has gone, so lines never interleave.
+    resend: [u8; STREAM_LINE_LEN],
+    resend_pos: usize,
+    resend_len: usize,
+    /// Set while `CONFIG DUMP` sums what it writes to `pager`.
+    summing: Option<Crc32>,
+    /// Set while a reply too long for `control` is written; it goes to
+    /// `pager`, and so does anything else until that has all gone.
+    paging: bool,
+    pager: &'a mut Pager<PAGE_LEN>,
+    /// Set while the data port's host reads binary frames; `Event:` lines
+    /// then go out as event frames.
+    frame_events: bool,
+    /// The control line being written, while `frame_events` is set.
+    line: LineBuf,
+    /// The line outgrew `line`, so the rest of it goes out as text.
+    spilled: bool,
+    #[cfg(feature = "uart-stream")]
+    uart: uart::UartStream,
+}
+
+impl<B: usb_device::bus::UsbBus> SerialWrapper<'_, B> {
+    /// True while control traffic has its own interface.
+    fn split(&self) -> bool {
+        self.events.dtr()
+    }
+
+    /// Push as much queued control traffic as the endpoint will take.
+    fn flush_control(&mut self) {
+        let split = self.split();
+        if self.resend_pos < self.resend_len {
+            let written = self
+                .port
+                .write(&self.resend[self.resend_pos..self.resend_len])
+                .unwrap_or(0);
+            self.resend_pos += written;
+            if self.resend_pos < self.resend_len && !split {
+                return;
+            }
+        }
+        while let Some(line) = self.pager.line() {
+            let end = self.control_len + line.len();
+            if end > CONTROL_QUEUE_LEN {
+                break;
+            }
+            self.control[self.control_len..end].copy_from_slice(line);
+            self.control_len = end;
+            self.pager.advance();
+        }
+        if self.control_len == 0 {
+            return;
+        }
+        let target = if split {
+            &mut self.events
+        } else {
+            &mut self.port
+        };
+        let written = target.write(&self.control[..self.control_len]).unwrap_or(0);
+        self.control.copy_within(written..self.control_len, 0);
+        self.control_len -= written;
+    }
+
+    /// Bytes of control traffic that can be queued without any being lost.
+    fn room(&self) -> usize {
+        if self.pager.is_empty() {
+            CONTROL_QUEUE_LEN - self.control_len
+        } else {
+            0
+        }
+    }
+
+    /// True once everything queued for the data interface has gone to the
+    /// endpoint.
+    fn idle(&self) -> bool {
+        ((self.control_len == 0 && self.pager.is_empty()) || self.split())
+            && self.resend_pos == self.resend_len
+    }
+
+    /// Refuse the command being handled: `ERR <code> <reason>`.
+    fn reject(&mut self, code: ErrorCode, reason: &str) {
+        self.rejected = Some(code as i16);
+        if !self.replaying {
+            let _ = uwriteln!(self, "ERR {} {}\r", code as u8, reason);
+        }
+    }
+
+    /// Refuse an SCPI command, queueing the error for `SYST:ERR?` too.
+    fn reject_scpi<const N: usize>(&mut self, queue: &mut ErrorQueue<N>, error: scpi::Error) {
+        queue.push(error);
+        self.rejected = Some(error.code());
+        if !self.replaying {
+            let _ = uwriteln!(self, "ERR {} {}\r", error.code(), error.message());
+        }
+    }
+
+    /// True when commands have arrived on the UART.
+    fn uart_readable(&self) -> bool {
+        #[cfg(feature = "uart-stream")]
+        return self.uart.readable();
+        #[cfg(not(feature = "uart-stream"))]
+        false
+    }
+
+    fn read_uart(&mut self, _buf: &mut [u8]) -> usize {
+        #[cfg(feature = "uart-stream")]
+        return self.uart.read(_buf);
+        #[cfg(not(feature = "uart-stream"))]
+        0
+    }
+
+    /// Send `bytes`, as sample data if `bulk` is set, else as control
+    /// traffic.
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        #[cfg(feature = "uart-stream")]
+        {
+            let lost = self.uart.write(bytes);
+            if self.bulk && self.uart.mode() == Some(UartMode::Only) {
+                self.dropped = self.dropped.saturating_add(lost as u32);
+                return;
+            }
+        }
+        if self.bulk {
+            self.flush_control();
+            if !self.idle() {
+                self.dropped = self.dropped.saturating_add(bytes.len() as u32);
+                return;
+            }
+            // A line the endpoint only took part of finishes from `resend`,
+            // so it is never torn; later lines wait behind it.
+            let written = self.port.write(bytes).unwrap_or(0);
+            if written == 0 {
+                self.dropped = self.dropped.saturating_add(bytes.len() as u32);
+                return;
+            }
+            let tail = &bytes[written..];
+            self.resend[..tail.len()].copy_from_slice(tail);
+            self.resend_pos = 0;
+            self.resend_len = tail.len();
+        } else if self.frame_events {
+            self.frame_lines(bytes);
+        } else {
+            self.queue_control(bytes);
+        }
+    }
+
+    /// Queue control traffic a whole line at a time, each `Event:` line as
+    /// an event frame.
+    fn frame_lines(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let end = bytes
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(bytes.len(), |i| i + 1);
+            let (part, rest) = bytes.split_at(end);
+            bytes = rest;
+            let complete = part.ends_with(b"\n");
+            if self.spilled || self.line.len + part.len() > STREAM_LINE_LEN {
+                let held = core::mem::replace(&mut self.line, LineBuf::new());
+                self.queue_control(held.as_bytes());
+                self.queue_control(part);
+                self.spilled = !complete;
+                continue;
+            }
+            self.line.buf[self.line.len..self.line.len + part.len()].copy_from_slice(part);
+            self.line.len += part.len();
+            if !complete {
+                continue;
+            }
+            let line = core::mem::replace(&mut self.line, LineBuf::new());
+            match line.as_bytes().strip_prefix(b"Event: ") {
+                Some(text) => {
+                    let text = core::str::from_utf8(text.trim_ascii_end()).unwrap_or_default();
+                    let mut frame = [0; STREAM_LINE_LEN];
+                    self.queue_control(stream::encode_event(text, &mut frame));
+                }
+                None => self.queue_control(line.as_bytes()),
+            }
+        }
+    }
+
+    /// Queue control traffic for the endpoint, through `pager` while that
+    /// is in use.
+    fn queue_control(&mut self, bytes: &[u8]) {
+        if self.paging || !self.pager.is_empty() {
+            let lost = self.pager.push(bytes);
+            // Only what is on its way counts, so the sum matches what the
+            // host receives.
+            if let Some(crc) = &mut self.summing {
+                crc.update(&bytes[..bytes.len() - lost]);
+            }
+            self.dropped = self.dropped.saturating_add(lost as u32);
+            self.flush_control();
+        } else {
+            let queued = bytes.len().min(CONTROL_QUEUE_LEN - self.control_len);
+            self.control[self.control_len..self.control_len + queued]
+                .copy_from_slice(&bytes[..queued]);
+            self.control_len += queued;
+            self.dropped = self.dropped.saturating_add((bytes.len() - queued) as u32);
+            self.flush_control();
+        }
+    }
+}
 
 impl<B: usb_device::bus::UsbBus> uWrite for SerialWrapper<'_, B> {
     type Error = ();
     fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
-        let _ = self.0.write(s.as_bytes());
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// A `CONFIG LOAD` under way: what has been replayed of the dump so far.
+struct ConfigLoad {
+    /// Over each `Config: ` line with its `\r\n`, as `CONFIG DUMP` sums it.
+    crc: Crc32,
+    lines: u32,
+    failed: u32,
+}
+
+/// Longest stream line; also the unit kept for replay.
+const STREAM_LINE_LEN: usize = stream::LINE_LEN;
+
+/// Builds one line of text in RAM, e.g. for the display.
+struct LineBuf {
+    buf: [u8; STREAM_LINE_LEN],
+    len: usize,
+}
+
+impl LineBuf {
+    fn new() -> Self {
+        Self {
+            buf: [0; STREAM_LINE_LEN],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    fn as_str(&self) -> &str {
+        // Only ever filled from `&str`s, cut at a char boundary.
+        core::str::from_utf8(self.as_bytes()).unwrap_or_default()
+    }
+}
+
+impl uWrite for LineBuf {
+    type Error = ();
+    fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+        let room = STREAM_LINE_LEN - self.len;
+        let mut take = s.len().min(room);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
         Ok(())
     }
 }
 // ----------------
 
+/// Output rate every backend supports; `SAMPLERATE` changes it at runtime.
+const DEFAULT_SAMPLE_SPS: u32 = 10;
+
+/// Decimal places of calibrated values; `DECIMALS` changes it.
+const DEFAULT_DECIMALS: u32 = 3;
+
+/// Longest interval `GAINCHECK` takes: a day.
+const MAX_GAIN_CHECK_MIN: u32 = 1_440;
+
+/// Longest `Log:` line.
+const LOG_LINE_LEN: usize = tensile_core::log::LINE_LEN + 16;
+
+/// Bytes of recent Force lines kept for REPLAY: about 45 s at 10 SPS.
+const HISTORY_LEN: usize = 32 * 1024;
+
+/// Conversions one `BURST` can hold: 48 KiB, 6 s at the ADS1256's 1 kSPS
+/// and the full 10 s at the NAU7802's 320.
+const BURST_LEN: usize = 6 * 1024;
+
+/// The start time of a test armed by `TRIG ARM`, until the edge comes.
+#[cfg(feature = "digital-inputs")]
+const AWAIT_EDGE: Micros = Micros(u64::MAX);
+
+/// Longest `Burst:` line, so one is only started when the control queue
+/// has room for all of it.
+const BURST_LINE_LEN: usize = 64;
+
+/// Longest `ErrLog:` or `CalLog:` line, so one is only started when the
+/// control queue has room for all of it.
+const LIST_LINE_LEN: usize = 224;
+
+/// A log listing going out over several passes, as the link takes it.
+#[derive(Debug, Clone, Copy)]
+enum Listing {
+    /// `after` is the last entry sent, `n` how many have gone.
+    ErrLog {
+        after: Option<u32>,
+        n: u32,
+    },
+    CalLog {
+        after: Option<u32>,
+        n: u32,
+    },
+}
+
+/// Window for the MAD spike filter; the median filter takes its own.
+const MAD_WINDOW: usize = 5;
+
+/// How often a running test logs a `QA:` health record.
+const QA_PERIOD_S: u64 = 60;
+
+/// Idle readings `NOISE?` takes, in seconds at the current rate.
+const NOISE_CHECK_S: u32 = 3;
+/// Longest `POWER LOW` sample interval, in seconds.
+const MAX_SLEEP_S: u32 = 3_600;
+
+/// Power-on `HEARTBEAT` interval.
+const DEFAULT_HEARTBEAT_MS: u32 = 1_000;
+
+/// `faults=` bits in the `Heartbeat:` record.
+const FAULT_SENSOR: u32 = 1 << 0;
+const FAULT_OVERLOAD: u32 = 1 << 1;
+/// Stream bytes the USB endpoint had no room for since the test started.
+const FAULT_TX_DROPPED: u32 = 1 << 2;
+/// Readings lost since boot because core 0 fell behind.
+const FAULT_ACQ_DROPPED: u32 = 1 << 3;
+
+/// Whether `Force:` lines go out, set by `PAUSE` and `RESUME`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Live,
+    Paused,
+    /// Lines from `from` on are kept in the history for `RESUME`.
+    Holding {
+        from: u32,
+    },
+}
+
+impl Stream {
+    fn as_str(self) -> &'static str {
+        match self {
+            Stream::Live => "live",
+            Stream::Paused => "paused",
+            Stream::Holding { .. } => "holding",
+        }
+    }
+}
+
+/// Accepted `GRAVITY` range in µm/s²; anything outside is a typo.
+const GRAVITY_RANGE_UM_S2: core::ops::RangeInclusive<u32> = 9_700_000..=9_900_000;
+
+/// A fault that repeats within this long is not logged to flash again, so a
+/// flapping sensor can't wear out the log sectors.
+const ERRLOG_HOLDOFF_S: u64 = 10;
+
+/// Die temperature readings averaged; one is taken per sample period.
+const TEMP_WINDOW: usize = 16;
+
+/// ADC conversions averaged per auxiliary reading (2 µs each).
+const AUX_OVERSAMPLE: u32 = 4;
+
+/// Samples averaged for each reference-weight placement.
+const CAL_SAMPLES: u32 = 30;
+
+/// Errors held for `SYSTem:ERRor?`.
+const SCPI_ERROR_QUEUE_LEN: usize = 10;
+
+/// Load-cell converter to use. `None` probes NAU7802 (I2C0), then ADS1256
+/// (SPI0), and falls back to the HX711. Only backends enabled as features
+/// are probed.
+#[cfg(any(feature = "nau7802", feature = "ads1256"))]
+const SENSOR_BACKEND: Option<sensor::Backend> = None;
+
+#[cfg(any(feature = "nau7802", feature = "ads1256"))]
+fn wants(backend: sensor::Backend) -> bool {
+    SENSOR_BACKEND.is_none_or(|b| b == backend)
+}
+
+/// Threshold-driven outputs, on GPIO10–13.
+const TRIGGER_OUTPUTS: usize = 4;
+const TRIGGER_PIN_MASK: u32 = 0xF << 10;
+
+/// The watchdog reboots the board if no reading is processed for this long,
+/// which catches a hang on either core. Readings arrive at least every two
+/// periods at the slowest rate (5 SPS); the rest is margin for flash erases
+/// and other blocking work.
+const WATCHDOG_TIMEOUT_US: u32 = 2_000_000;
+/// How often the main loop wakes to feed the watchdog under `POWER LOW`.
+const WATCHDOG_FEED_MS: u64 = 1_000;
+
+/// Times the converter is power-cycled at boot while waiting for its first
+/// conversion, before carrying on without one.
+const SENSOR_INIT_ATTEMPTS: u32 = 3;
+
+/// Wait for a first conversion per attempt. The HX711 takes about 400 ms to
+/// settle after power-up at 10 SPS.
+const SENSOR_INIT_TIMEOUT_MS: u32 = 500;
+
+/// Refresh interval of the OLED.
+#[cfg(feature = "oled")]
+const DISPLAY_PERIOD_MS: u64 = 250;
+
+/// A finished tare lights the LED this long, so a tare started from the
+/// button is confirmed without a host.
+const TARE_FLASH_MS: u64 = 300;
+
+/// Time for the clamp servo to close before a profile's test starts.
+#[cfg(feature = "servo")]
+const CLAMP_SETTLE_MS: u64 = 500;
+
+/// Current USB start-of-frame number (11 bits, 1 ms per frame).
+fn usb_frame_number() -> u16 {
+    // SAFETY: read-only status register; the USB driver never writes it.
+    unsafe { (*pac::USBCTRL_REGS::ptr()).sof_rd().read().count().bits() }
+}
+
+/// Print a value held in thousandths as a decimal, e.g. `-0.050`.
+fn write_milli<W: uWrite>(w: &mut W, milli: i64) {
+    write_fixed(w, milli, 3);
+}
+
+/// Lay out the OLED: state and unit, the force large, then the peak and
+/// any fault. `show` prints a force the way the stream does.
+#[cfg(feature = "oled")]
+fn draw_display(
+    frame: &mut Frame,
+    phase: Phase,
+    health: Health,
+    force: Option<Counts>,
+    peak: Option<Counts>,
+    unit: Unit,
+    show: impl Fn(&mut LineBuf, Counts),
+) {
+    frame.clear();
+    let state = match health {
+        Health::Ok => phase.as_str(),
+        Health::Overload => "overload",
+        Health::Fault(_) => "fault",
+    };
+    frame.text(0, 0, state, 1);
+    frame.text_right(0, unit.as_str(), 1);
+    let mut line = LineBuf::new();
+    match force {
+        Some(force) => show(&mut line, force),
+        None => {
+            let _ = uwrite!(line, "---");
+        }
+    }
+    frame.text_right(2, line.as_str(), 2);
+    if let Some(peak) = peak {
+        let mut line = LineBuf::new();
+        show(&mut line, peak);
+        frame.text(5, 0, "peak", 1);
+        frame.text_right(5, line.as_str(), 1);
+    }
+    if let Health::Fault(kind) = health {
+        frame.text(7, 0, kind.as_str(), 1);
+    }
+}
+
+/// Print `t` as ISO 8601 UTC, e.g. `2026-10-15T12:00:00Z`.
+fn write_utc<W: uWrite>(w: &mut W, t: DateTime) {
+    let _ = uwrite!(w, "{}", t.year);
+    for (sep, value) in [
+        ('-', t.month),
+        ('-', t.day),
+        ('T', t.hour),
+        (':', t.minute),
+        (':', t.second),
+    ] {
+        let _ = uwrite!(w, "{}{}{}", sep, value / 10, value % 10);
+    }
+    let _ = uwrite!(w, "Z");
+}
+
+/// `Meta:` and the test metadata as `KEY=VALUE` fields.
+fn write_meta<W: uWrite>(w: &mut W, metadata: &Metadata) {
+    let _ = uwrite!(w, "Meta:");
+    for entry in metadata.iter() {
+        let _ = uwrite!(w, " {}={}", entry.key(), entry.value());
+    }
+    let _ = uwriteln!(w, "\r");
+}
+
+/// Bit n set while output n is high.
+fn output_bits<P: StatefulOutputPin>(pins: &mut [P]) -> u8 {
+    pins.iter_mut().enumerate().fold(0, |bits, (n, pin)| {
+        bits | (pin.is_set_high().unwrap_or(false) as u8) << n
+    })
+}
+
+/// End an event line with ` unix=` once the wall clock is set.
+fn write_unix<W: uWrite>(w: &mut W, wall: &WallClock, t: u64) {
+    if let Some(unix) = wall.unix(Micros(t)) {
+        let _ = uwrite!(w, " unix={}", unix);
+    }
+    let _ = uwriteln!(w, "\r");
+}
+
+/// Print `value` with `places` implied decimal places.
+fn write_fixed<W: uWrite>(w: &mut W, value: i64, places: u32) {
+    let mut buf = [0; decimal::MAX_LEN];
+    let _ = w.write_str(decimal::fixed(value, places, &mut buf));
+}
+
+/// Print the chosen columns' keys, comma-separated.
+fn write_columns<W: uWrite>(w: &mut W, columns: Columns) {
+    for (i, column) in columns.iter().enumerate() {
+        let _ = uwrite!(w, "{}{}", if i == 0 { "" } else { "," }, column.as_str());
+    }
+}
+
+/// Print a force in `unit`: counts as-is, anything else to `decimals`
+/// places.
+fn write_force<W: uWrite>(
+    w: &mut W,
+    counts: Counts,
+    unit: Unit,
+    scale: Option<Scale>,
+    decimals: u32,
+) {
+    match scale {
+        Some(scale) if unit != Unit::Raw => {
+            write_fixed(w, scale.convert_places(counts, unit, decimals), decimals)
+        }
+        _ => {
+            let _ = uwrite!(w, "{}", counts.0);
+        }
+    }
+}
+
+/// A force as a stream field, like [`write_force`] prints it.
+fn force_value(counts: Counts, unit: Unit, scale: Option<Scale>, decimals: u32) -> Value {
+    match scale {
+        Some(scale) if unit != Unit::Raw => Value::Fixed {
+            value: scale.convert_places(counts, unit, decimals),
+            places: decimals,
+        },
+        _ => Value::whole(counts.0 as i64),
+    }
+}
+
+/// A calibration as the history keeps it. Without `BY` the operator comes
+/// from `META OPERATOR=`, when set.
+fn cal_record(
+    channel: u8,
+    counts_per_kg: i32,
+    note: CalNote,
+    unix: Option<u64>,
+    metadata: &Metadata,
+) -> CalRecord {
+    let operator = note.operator.or_else(|| {
+        metadata
+            .iter()
+            .find(|e| e.key().eq_ignore_ascii_case("OPERATOR"))
+            .and_then(|e| Operator::parse(e.value()))
+    });
+    CalRecord {
+        seq: 0,
+        unix: unix.and_then(|u| u32::try_from(u).ok()),
+        channel,
+        counts_per_kg,
+        mass: note.mass,
+        operator,
+        creep: note.creep,
+    }
+}
+
+/// Combine the primary cell with channel 1, if both are calibrated.
+fn dual_cell(
+    mode: Combine,
+    limit_pct: u32,
+    primary: Option<Scale>,
+    secondary: Option<Scale>,
+) -> Option<DualCell> {
+    let (a, b) = primary.zip(secondary)?;
+    // Mismatch is only judged above 100 g per cell.
+    let floor = a.counts_per_kg().unsigned_abs() / 10;
+    DualCell::new(mode, a.counts_per_kg(), b.counts_per_kg(), limit_pct, floor)
+}
+
+/// Checksum of everything that turns counts into force: the `CAL` factors
+/// of every cell (0 when uncalibrated) and gravity. A dataset and an
+/// `INFO?` reply with the same value were taken with the same calibration.
+fn calibration_crc(scale: Option<Scale>, channels: &[Channel], gravity: u32) -> u32 {
+    let mut data = [0u8; 4 * (2 + EXTRA_CHANNELS)];
+    let factors = core::iter::once(scale)
+        .chain(channels.iter().map(Channel::scale))
+        .map(|s| s.map_or(0, |s| s.counts_per_kg()) as u32)
+        .chain(core::iter::once(gravity));
+    for (chunk, value) in data.chunks_exact_mut(4).zip(factors) {
+        chunk.copy_from_slice(&value.to_le_bytes());
+    }
+    crc32(&data)
+}
+
+/// What the boot self-test found.
+struct SelfTest {
+    /// What caused the last reset: `fault`, `watchdog` or `other`.
+    reset: &'static str,
+    backend: Backend,
+    /// Power-ups it took for the converter to produce a conversion, or
+    /// `None` if it never did.
+    sensor_attempts: Option<u32>,
+    log_entries: u32,
+    /// Fault-log entries that look corrupted.
+    log_bad: u32,
+}
+
+impl SelfTest {
+    fn passed(&self) -> bool {
+        self.sensor_attempts.is_some() && self.log_bad == 0
+    }
+}
+
+/// Print the self-test report. There are no limit switches on this rig,
+/// which the report says rather than leaving the host to guess.
+fn write_selftest<W: uWrite>(w: &mut W, test: &SelfTest) {
+    let _ = uwrite!(
+        w,
+        "SelfTest: result={} reset={} backend={}",
+        if test.passed() { "pass" } else { "fail" },
+        test.reset,
+        test.backend.as_str()
+    );
+    match test.sensor_attempts {
+        Some(n) => {
+            let _ = uwrite!(w, " sensor=ok attempts={}", n);
+        }
+        None => {
+            let _ = uwrite!(w, " sensor=timeout attempts={}", SENSOR_INIT_ATTEMPTS);
+        }
+    }
+    let _ = uwriteln!(
+        w,
+        " errlog={} entries={} bad={} limits=none\r",
+        if test.log_bad == 0 { "ok" } else { "corrupt" },
+        test.log_entries,
+        test.log_bad
+    );
+}
+
+/// Print the last calibration-check certificate followed by its points.
+fn write_certificate<W: uWrite>(w: &mut W, check: &CalCheck, session_id: u32) {
+    let Some(cert) = check.certificate() else {
+        return;
+    };
+    let _ = uwrite!(
+        w,
+        "CalCheck: points={} session={:x}",
+        cert.points,
+        session_id
+    );
+    if let Some(k) = cert.counts_per_kg {
+        let _ = uwrite!(w, " counts_per_kg={}", k);
+    }
+    let _ = uwriteln!(w, " max_error={}\r", cert.max_error);
+    for (i, p) in check.points().iter().enumerate() {
+        let _ = uwriteln!(
+            w,
+            "CalPoint: {} mass_mg={} mean={} sigma={}\r",
+            i + 1,
+            p.mass.0,
+            p.mean.0,
+            p.sigma
+        );
+    }
+}
+
+/// Reached on any fault, and on `panic!` through crash.rs. The trigger
+/// outputs are dropped straight away, then the board reboots rather than
+/// halting with them in whatever state the fault left.
+#[exception]
+unsafe fn HardFault(_frame: &ExceptionFrame) -> ! {
+    (*pac::SIO::ptr())
+        .gpio_out_clr()
+        .write(|w| w.bits(TRIGGER_PIN_MASK));
+    (*pac::WATCHDOG::ptr())
+        .ctrl()
+        .write(|w| w.trigger().set_bit());
+    loop {
+        cortex_m::asm::nop();
+    }
+}
+
 #[entry]
 fn main() -> ! {
     let mut pac = pac::Peripherals::take().unwrap();
-    let core = pac::CorePeripherals::take().unwrap();
+    // A forced watchdog reset comes from `HardFault`, a timed-out one from
+    // a hang.
+    let reason = pac.WATCHDOG.reason().read();
+    let reset = if reason.force().bit_is_set() {
+        "fault"
+    } else if reason.timer().bit_is_set() {
+        "watchdog"
+    } else {
+        "other"
+    };
+    let mut panicked = crash::take();
     let mut watchdog = Watchdog::new(pac.WATCHDOG);
     let sio = Sio::new(pac.SIO);
 
-    let external_xtal_freq_hz = 12_000_000u32;
-
     // 1. INITIALIZE CLOCKS FIRST
-    let clocks = init_clocks_and_plls(
-        external_xtal_freq_hz,
+    let clocks = board::init_clocks(
         pac.XOSC,
         pac.CLOCKS,
         pac.PLL_SYS,
         pac.PLL_USB,
         &mut pac.RESETS,
         &mut watchdog,
-    )
-    .ok()
-    .unwrap();
+    );
 
     // 2. NOW INITIALIZE TIMER (Because it needs &clocks)
-    let timer = Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+    let mut timer = Timer::new(pac.TIMER, &mut pac.RESETS, &clocks);
+
+    // Sample timestamps are the 64-bit µs timer, which starts at zero on every
+    // boot. A random session id lets the host tell boots apart.
+    let rosc = RingOscillator::new(pac.ROSC).initialize();
+    let session_id = (0..32).fold(0u32, |id, _| (id << 1) | rosc.get_random_bit() as u32);
+
+    let pins = board::Pins::new(
+        pac.IO_BANK0,
+        pac.PADS_BANK0,
+        sio.gpio_bank0,
+        &mut pac.RESETS,
+    );
 
     // --- USB SETUP ---
     let usb_bus = UsbBusAllocator::new(UsbBus::new(
@@ -69,57 +942,3688 @@ fn main() -> ! {
         &mut pac.RESETS,
     ));
 
-    let serial = SerialPort::new(&usb_bus);
-    let mut serial_wrapper = SerialWrapper(serial);
+    // Interface names let host tools pick the right port.
+    let serial = SerialPort::new_with_interface_names(&usb_bus, Some("Tensile Data"), None);
+    let events = SerialPort::new_with_interface_names(&usb_bus, Some("Tensile Control"), None);
+    let mut serial_wrapper = SerialWrapper {
+        port: serial,
+        events,
+        dropped: 0,
+        rejected: None,
+        replaying: false,
+        control: [0; CONTROL_QUEUE_LEN],
+        control_len: 0,
+        bulk: false,
+        resend: [0; STREAM_LINE_LEN],
+        resend_pos: 0,
+        resend_len: 0,
+        summing: None,
+        paging: false,
+        pager: cortex_m::singleton!(: Pager<PAGE_LEN> = Pager::new()).unwrap(),
+        frame_events: false,
+        line: LineBuf::new(),
+        spilled: false,
+        #[cfg(feature = "uart-stream")]
+        uart: uart::UartStream::new(
+            pac.UART0,
+            (pins.uart_tx.into_function(), pins.uart_rx.into_function()),
+            &mut pac.RESETS,
+            clocks.peripheral_clock.freq(),
+        ),
+    };
+
+    // Several testers on one PC are told apart by serial number, taken from
+    // the flash chip's unique ID. Core 1 is not running yet, so reading it
+    // needs no parking.
+    let serial_number =
+        cortex_m::singleton!(: [u8; 2 * flash::UNIQUE_ID_LEN] = [0; 2 * flash::UNIQUE_ID_LEN])
+            .unwrap();
+    let unique_id = flash::unique_id();
+    for (pair, byte) in serial_number.chunks_exact_mut(2).zip(unique_id) {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+        pair[0] = HEX[(byte >> 4) as usize];
+        pair[1] = HEX[(byte & 0xF) as usize];
+    }
+    let serial_number = core::str::from_utf8(serial_number).unwrap_or_default();
 
     let mut usb_dev = UsbDeviceBuilder::new(&usb_bus, UsbVidPid(0x16c0, 0x27dd))
-        .device_class(2)
+        .strings(&[StringDescriptors::default()
+            .manufacturer("leafy-sys")
+            .product("Pico Tensile Tester")
+            .serial_number(serial_number)])
+        .unwrap()
+        .composite_with_iads()
         .build();
 
     // --- LOAD CELL SETUP ---
-    let pins = bsp::Pins::new(
-        pac.IO_BANK0,
-        pac.PADS_BANK0,
-        sio.gpio_bank0,
-        &mut pac.RESETS,
-    );
+    // VSYS/3 on GPIO29 for the supply check
+    let mut adc = Adc::new(pac.ADC, &mut pac.RESETS);
+    let mut vsys_pin = AdcPin::new(pins.vsys.into_floating_input()).unwrap();
+    // Auxiliary 0-3.3 V inputs (e.g. an extensometer) on ADC0-2
+    let mut aux0_pin = AdcPin::new(pins.aux.0.into_floating_input()).unwrap();
+    let mut aux1_pin = AdcPin::new(pins.aux.1.into_floating_input()).unwrap();
+    let mut aux2_pin = AdcPin::new(pins.aux.2.into_floating_input()).unwrap();
+    // On-die sensor; the load-cell bridge drifts with the board temperature.
+    let mut temp_sensor = adc.take_temp_sensor().unwrap();
 
-    let dt_pin = pins.gpio16.into_floating_input();
-    let sck_pin = pins.gpio17.into_push_pull_output();
+    // HX711: bit-banged DT/SCK
+    let dt_pin = pins.hx711_dout.into_floating_input();
+    let sck_pin = pins.hx711_sck.into_push_pull_output();
 
-    // Create a delay for the HX711 initialization
-    let delay = cortex_m::delay::Delay::new(core.SYST, clocks.system_clock.freq().to_Hz());
+    // NAU7802: I2C0 on GPIO4/5
+    #[cfg(feature = "nau7802")]
+    let nau = wants(sensor::Backend::Nau7802)
+        .then(|| {
+            let sda: Pin<_, FunctionI2C, PullUp> = pins.nau_sda.reconfigure();
+            let scl: Pin<_, FunctionI2C, PullUp> = pins.nau_scl.reconfigure();
+            let i2c = I2C::i2c0(
+                pac.I2C0,
+                sda,
+                scl,
+                400.kHz(),
+                &mut pac.RESETS,
+                &clocks.system_clock,
+            );
+            Nau7802Sensor::detect(i2c, timer)
+        })
+        .flatten();
+    #[cfg(not(feature = "nau7802"))]
+    let nau: Option<core::convert::Infallible> = None;
 
-    let mut load_cell = Hx711::new(delay, dt_pin, sck_pin).ok().unwrap();
+    // ADS1256: SPI0 on GPIO18-20, CS on GPIO21, DRDY on GPIO22
+    #[cfg(feature = "ads1256")]
+    let ads = (nau.is_none() && wants(sensor::Backend::Ads1256))
+        .then(|| {
+            let spi_sclk = pins.ads_sclk.into_function::<FunctionSpi>();
+            let spi_mosi = pins.ads_mosi.into_function::<FunctionSpi>();
+            let spi_miso = pins.ads_miso.into_function::<FunctionSpi>();
+            let spi = Spi::<_, _, _, 8>::new(pac.SPI0, (spi_mosi, spi_miso, spi_sclk)).init(
+                &mut pac.RESETS,
+                clocks.peripheral_clock.freq(),
+                1.MHz(),
+                embedded_hal::spi::MODE_1,
+            );
+            let mut ads_cs = pins.ads_cs.into_push_pull_output();
+            let _ = ads_cs.set_high();
+            let ads_drdy = pins.ads_drdy.into_pull_up_input();
+            Ads1256Sensor::detect(spi, ads_cs, ads_drdy, timer)
+        })
+        .flatten();
+    #[cfg(not(feature = "ads1256"))]
+    let ads: Option<core::convert::Infallible> = None;
+
+    let mut load_cell = if let Some(nau) = nau {
+        AnySensor::Nau7802(nau)
+    } else if let Some(ads) = ads {
+        AnySensor::Ads1256(ads)
+    } else {
+        // Delays come from the timer: the driver runs on core 1, which has
+        // its own, unconfigured SysTick.
+        match Hx711Sensor::new(timer, dt_pin, sck_pin) {
+            Ok(hx) => AnySensor::Hx711(hx),
+            Err(_) => AnySensor::Absent(Backend::Hx711),
+        }
+    };
+    log::info!("load cell backend: {}", load_cell.backend().as_str());
 
-    let mut offset = 0;
-    for _ in 0..10 {
-        if let Ok(reading) = load_cell.retrieve() {
-            offset = reading;
+    // Make sure the converter is awake and converting before taking the zero,
+    // power-cycling it if nothing arrives. A missing sensor must not stop
+    // the board: USB still comes up, and the health monitor reports the
+    // fault once the readings keep failing.
+    //
+    // A single reading gives a usable zero straight away; the averaged tare
+    // started below refines it once enough samples are in.
+    let mut offset = None;
+    let mut sensor_attempts = None;
+    for attempt in 0..SENSOR_INIT_ATTEMPTS {
+        if attempt > 0 {
+            let _ = load_cell.set_power(false);
+            timer.delay_ms(10);
+        }
+        let _ = load_cell.set_power(true);
+        let _ = load_cell.start_conversion();
+        for _ in 0..SENSOR_INIT_TIMEOUT_MS / 10 {
+            if let Ok(reading) = load_cell.read() {
+                offset = Some(reading);
+                break;
+            }
+            timer.delay_ms(10);
+        }
+        if offset.is_some() {
+            sensor_attempts = Some(attempt + 1);
             break;
         }
-        cortex_m::asm::delay(1_000_000);
+        log::warn!("no conversion from load cell (attempt {})", attempt + 1);
     }
+    let mut offset = offset.unwrap_or(0);
 
-    // Set the first target time (Now + 100ms)
-    // FIX: Use 100u64 so .millis() works!
-    let mut next_read = timer.get_counter() + 100u64.millis();
+    // Second HX711 on GPIO14 (DT) / GPIO15 (SCK), streamed as channel 1.
+    #[cfg(feature = "hx711-ch1")]
+    let extra = {
+        let dt = pins.ch1_dout.into_floating_input();
+        let sck = pins.ch1_sck.into_push_pull_output();
+        let mut cell: AnySensor<_, core::convert::Infallible, core::convert::Infallible> =
+            match Hx711Sensor::new(timer, dt, sck) {
+                Ok(hx) => AnySensor::Hx711(hx),
+                Err(_) => AnySensor::Absent(Backend::Hx711),
+            };
+        let _ = cell.set_power(true);
+        [cell]
+    };
+    #[cfg(not(feature = "hx711-ch1"))]
+    let extra: [core::convert::Infallible; 0] = [];
+    let mut channels: [Channel; EXTRA_CHANNELS] = core::array::from_fn(|_| Channel::new());
+
+    let mut sample_sps = DEFAULT_SAMPLE_SPS;
+    let _ = load_cell.set_rate(sample_sps);
+    let backend = load_cell.backend();
+    let supported_rates = load_cell.supported_rates();
+    // From here on the converters belong to core 1.
+    let mut acquisition = Acquisition::spawn(
+        &mut pac.PSM,
+        &mut pac.PPB,
+        sio.fifo,
+        load_cell,
+        extra,
+        timer,
+        sample_sps,
+    );
+    // A reset also returns the trigger outputs to inputs, so a hang cannot
+    // leave external equipment switched on.
+    watchdog.pause_on_debug(true);
+    watchdog.start(fugit::MicrosDurationU32::micros(WATCHDOG_TIMEOUT_US));
+
+    // The loop sleeps between events. Pending interrupts wake it without
+    // handlers: USB activity, the alarm below for timed work, and core 1's
+    // SEV after each reading.
+    acquire::wake_on_pending();
+    let mut alarm = timer.alarm_0().unwrap();
+    alarm.enable_interrupt();
+
+    let mut led = status::StatusLed::new(pins.led.into_push_pull_output());
+    #[cfg(any(feature = "buzzer", feature = "servo"))]
+    let pwm = bsp::hal::pwm::Slices::new(pac.PWM, &mut pac.RESETS);
+    #[cfg(feature = "buzzer")]
+    let mut buzzer = buzzer::Buzzer::new(pwm.pwm7, pins.buzzer);
+    #[cfg(feature = "servo")]
+    let mut servo = servo::Servo::new(pwm.pwm4, pins.servo);
+    #[cfg(feature = "chamber")]
+    let mut chamber = chamber::Chamber::new(pins.heater.into_push_pull_output());
+    #[cfg(feature = "extensometer")]
+    let mut quadrature =
+        quadrature::Quadrature::new(pac.PIO0, pins.ext_a, pins.ext_b, &mut pac.RESETS);
+    #[cfg(feature = "extensometer")]
+    let mut extensometer = Extensometer::new(Extensometer::DEFAULT_NM_PER_COUNT);
+    // Trigger outputs, low until armed.
+    let mut trigger_pins = [
+        pins.triggers.0.into_push_pull_output().into_dyn_pin(),
+        pins.triggers.1.into_push_pull_output().into_dyn_pin(),
+        pins.triggers.2.into_push_pull_output().into_dyn_pin(),
+        pins.triggers.3.into_push_pull_output().into_dyn_pin(),
+    ];
+    let mut triggers: [Option<Trigger>; TRIGGER_OUTPUTS] = [None; TRIGGER_OUTPUTS];
+    // Outputs that `TRIG <out> TEST` raises for each test, and whether they
+    // are up.
+    let mut test_outputs = [false; TRIGGER_OUTPUTS];
+    let mut test_outputs_up = false;
+    // The input and edge `TRIG ARM` waits on.
+    #[cfg(feature = "digital-inputs")]
+    let mut sync_arm: Option<(usize, bool)> = None;
+    #[cfg(feature = "digital-inputs")]
+    let mut input_pins = [
+        pins.inputs.0.into_pull_up_input().into_dyn_pin(),
+        pins.inputs.1.into_pull_up_input().into_dyn_pin(),
+        pins.inputs.2.into_pull_up_input().into_dyn_pin(),
+        pins.inputs.3.into_pull_up_input().into_dyn_pin(),
+    ];
+    let mut digital = DigitalInputs::new();
+    // Pulled down, so a module that is missing reads as no connection.
+    #[cfg(feature = "bluetooth")]
+    let mut bt_state = pins.bt_state.into_pull_down_input();
+    #[cfg(feature = "bluetooth")]
+    let mut bt_link = Link::new();
+
+    // I2C1 carries the SSD1306 and DS3231, for use without a host. Missing
+    // devices are skipped.
+    #[cfg(any(feature = "oled", feature = "rtc"))]
+    let i2c1 = {
+        let sda: Pin<_, FunctionI2C, PullUp> = pins.i2c1_sda.reconfigure();
+        let scl: Pin<_, FunctionI2C, PullUp> = pins.i2c1_scl.reconfigure();
+        core::cell::RefCell::new(I2C::i2c1(
+            pac.I2C1,
+            sda,
+            scl,
+            400.kHz(),
+            &mut pac.RESETS,
+            &clocks.system_clock,
+        ))
+    };
+    #[cfg(feature = "oled")]
+    let mut display = oled::Oled::detect(i2c_bus::SharedI2c(&i2c1));
+    #[cfg(feature = "oled")]
+    let mut frame = Frame::new();
+    #[cfg(feature = "oled")]
+    let mut next_display = timer.get_counter();
+
+    // Wall-clock time comes from the RTC at boot, or a later `TIME SET`.
+    #[cfg(feature = "rtc")]
+    let mut rtc = rtc::Ds3231::detect(i2c_bus::SharedI2c(&i2c1));
+    let mut wall = WallClock::new();
+    let mut time_source = "none";
+    #[cfg(feature = "rtc")]
+    if let Some(unix) = rtc
+        .as_mut()
+        .and_then(|rtc| rtc.read().ok().flatten())
+        .and_then(DateTime::to_unix)
+    {
+        wall.set(unix, Micros(timer.get_counter().ticks()));
+        time_source = "rtc";
+    }
+
+    #[cfg(feature = "encoder")]
+    let mut panel = panel::Panel::new(
+        pins.encoder_a.into_pull_up_input(),
+        pins.encoder_b.into_pull_up_input(),
+        pins.button.into_pull_up_input(),
+    );
+    #[cfg(feature = "tare-button")]
+    let mut tare_button = panel::PushButton::new(pins.button.into_pull_up_input());
+
+    #[cfg(feature = "modbus")]
+    let mut modbus = modbus::Server::new(
+        pac.UART0,
+        (pins.uart_tx.into_function(), pins.uart_rx.into_function()),
+        pins.rs485_enable.into_push_pull_output(),
+        &mut pac.RESETS,
+        clocks.peripheral_clock.freq(),
+    );
+    #[cfg(feature = "i2c-target")]
+    let mut i2c_target = i2c_target::Target::new(
+        pac.I2C1,
+        (pins.i2c1_sda.reconfigure(), pins.i2c1_scl.reconfigure()),
+        &mut pac.RESETS,
+    );
+
+    // One per interface, so commands arriving on both never mix.
+    let mut line_buffers = [
+        LineBuffer::new(),
+        LineBuffer::new(),
+        LineBuffer::new(),
+        LineBuffer::new(),
+    ];
+    let mut sequencer = Sequencer::new();
+    let mut last_force = None;
+    let mut filter = FilterChain::default();
+    let mut frame_drift = FrameDrift::default();
+    let mut noise = DiffNoise::default();
+    let mut monitor = SensorMonitor::new();
+    let mut peak = PeakHold::new();
+    let mut stats = RunningStats::new();
+    let mut cal_check = CalCheck::new(CAL_SAMPLES);
+    let mut noise_check: Option<NoiseCheck> = None;
+    let mut rate: Option<Derivative> = None;
+    let mut decimate = Decimator::new(1);
+    let mut columns = Columns::ALL;
+    // How samples go out on the data port. Unless PROTOCOL fixed it, the
+    // first byte a newly attached host sends picks it.
+    let mut ascii = AsciiEncoder::new();
+    let mut binary = BinaryEncoder::new();
+    let mut protocol = Protocol::Ascii;
+    let mut protocol_fixed = false;
+    let mut protocol_pending = false;
+    let mut unit = Unit::Raw;
+    let mut decimals = DEFAULT_DECIMALS;
+    let mut loading = Loading::TENSION;
+    let mut gravity = STANDARD_GRAVITY_UM_S2;
+    let mut scale: Option<Scale> = None;
+    // The primary cell's creep model, taken on with its calibration.
+    let mut creep: Option<CreepComp> = None;
+    let mut zero_track: Option<ZeroTracker> = None;
+    let mut dual: Option<DualCell> = None;
+    // B gets its own zero, from its first reading until a tare refines it.
+    let mut range: Option<AutoRange> = None;
+    let mut b_offset: Option<i32> = None;
+    // The input the last reading came from.
+    let mut input = Input::A;
+    // Kept so the rate-dependent stages can be rebuilt by `SAMPLERATE`.
+    let mut low_pass_cutoff = 0;
+    let mut zero_track_setting = None;
+    let mut breaks: Option<BreakDetector> = None;
+    // With whether a slip ends the test.
+    let mut slips: Option<(SlipDetector, bool)> = None;
+    let mut capture: Option<Capture> = None;
+    let mut stream = Stream::Live;
+    let mut metadata = Metadata::new();
+    // Waiting for the next sample's sequence number.
+    let mut marks = Marks::new();
+    let mut profiles = profile::load();
+    let mut device_name = identity::load();
+    let mut config_load: Option<ConfigLoad> = None;
+    // Set by `RUN` for the test it starts; a plain `START` runs without.
+    let mut run_limits: Option<Limits> = None;
+    // What the running test's profile sets an output to when it ends.
+    let mut end_output = None;
+    let mut run_end_output = None;
+    // Work is integrated over this AUX channel during a test, and
+    // optionally streamed.
+    let mut work_source: Option<(usize, bool)> = None;
+    let mut work = Work::new();
+    let mut work_running = false;
+    let mut limits = Limits::NONE;
+    let mut test_started = 0;
+    // Peak of the running test, the way it loads the specimen.
+    let mut test_peak: Option<i32> = None;
+    let mut test_log = testlog::TestLog::load();
+    let mut tare = Some(Tare::new(Tare::DEFAULT_SAMPLES));
+    let mut error_log = errlog::ErrorLog::load();
+    let mut cal_log = callog::CalLog::load();
+    let (log_entries, log_bad) = error_log.check();
+    let self_test = SelfTest {
+        reset,
+        backend,
+        sensor_attempts,
+        log_entries,
+        log_bad,
+    };
+    if self_test.passed() {
+        log::info!("self-test passed");
+    } else {
+        log::error!("self-test failed");
+    }
+    let mut self_test_sent = false;
+    let mut temp_average = MovingAverage::new(TEMP_WINDOW);
+    let mut aux: [Option<AuxScale>; 3] = [None; 3];
+    let mut temp: Option<MilliCelsius> = None;
+    let mut temp_coeff = 0;
+    let mut scpi_errors = ErrorQueue::<SCPI_ERROR_QUEUE_LEN>::new();
+    // Die temperature when the zero was last taken.
+    let mut temp_ref = None;
+    let mut last_logged: Option<(u16, fugit::Instant<u64, 1, 1_000_000>)> = None;
+    let mut next_qa = timer.get_counter();
+    let mut heartbeat_ms = DEFAULT_HEARTBEAT_MS;
+    let mut next_heartbeat = timer.get_counter();
+    // Seconds between samples while the converter sleeps between them.
+    let mut low_power: Option<u32> = None;
+    let mut last_reading = timer.get_counter();
+    // Starts from a serial port need `UNLOCK <serial number>` first.
+    #[cfg(feature = "interlock")]
+    let mut interlock = Interlock::new(u64::from_be_bytes(unique_id), Interlock::DEFAULT_RELOCK_MS);
+    let mut host_attached = [false; 2];
+    // A test the host was lost during, and whether it was stopped or only
+    // armed, until the next host is told.
+    let mut host_lost: Option<(u64, &str)> = None;
+    // Recent Force lines for REPLAY, and the next one to resend.
+    let history = cortex_m::singleton!(: History<HISTORY_LEN> = History::new()).unwrap();
+    let mut replay: Option<u32> = None;
+    let burst = cortex_m::singleton!(: Burst<BURST_LEN> = Burst::new()).unwrap();
+    let mut gain_check: Option<GainCheck> = None;
+    let mut listing: Option<Listing> = None;
+    // Commands that arrived during a listing, read once it is done.
+    let mut input_held = false;
+    let mut mid_test = Policy::Reject;
+    let mut deferred = Deferred::new();
+    // For DIAG?: how long each pass of the loop is busy, the time between
+    // conversions, and how old a sample is when its line goes out.
+    let mut loop_time = Timing::new();
+    let mut conversions = Intervals::new();
+    let mut latency = Timing::new();
 
     loop {
+        let woke = timer.get_counter().ticks();
+        // --- Synchronised start ---
+        // First thing after waking, so the edge is timed closely. An arm a
+        // STOP cancelled or a START replaced is dropped.
+        #[cfg(feature = "digital-inputs")]
+        if let Some((n, rising)) = sync_arm {
+            let edge = if rising {
+                Interrupt::EdgeHigh
+            } else {
+                Interrupt::EdgeLow
+            };
+            let pin = &mut input_pins[n];
+            let seen = pin.interrupt_status(edge);
+            let t = timer.get_counter().ticks();
+            if seen || sequencer.start_at() != Some(AWAIT_EDGE) {
+                pin.set_interrupt_enabled(edge, false);
+                pin.clear_interrupt(edge);
+                sync_arm = None;
+            }
+            if seen && sequencer.start_at() == Some(AWAIT_EDGE) {
+                sequencer.arm(Micros(t));
+                let _ = uwriteln!(serial_wrapper, "Event: SYNC in={} t={}\r", n, t);
+            }
+        }
+
         // --- 1. Poll USB ---
-        usb_dev.poll(&mut [&mut serial_wrapper.0]);
+        serial_wrapper.flush_control();
+        // A replay goes out one whole line at a time, behind any responses.
+        while let Some(seq) = replay {
+            if !serial_wrapper.idle() {
+                break;
+            }
+            if seq == history.next_seq() {
+                replay = None;
+                break;
+            }
+            // Lines evicted while the replay was running are skipped.
+            if let Some(len) = history.copy(seq, &mut serial_wrapper.resend) {
+                serial_wrapper.resend_pos = 0;
+                serial_wrapper.resend_len = len;
+                serial_wrapper.flush_control();
+            }
+            replay = Some(seq + 1);
+        }
+        // Log lines go out behind everything else, as room allows.
+        while serial_wrapper.room() >= LOG_LINE_LEN {
+            let Some(entry) = log::pop() else { break };
+            let _ = uwriteln!(
+                serial_wrapper,
+                "Log: {} {}\r",
+                entry.level().as_str(),
+                entry.as_str()
+            );
+        }
+        let usb_ready = usb_dev.poll(&mut [&mut serial_wrapper.port, &mut serial_wrapper.events]);
+        // Held commands go through once the test is over, one per pass.
+        // Nothing new is read while a listing is going out, so replies
+        // can't come between its lines.
+        let resumed = if sequencer.testing() || listing.is_some() {
+            None
+        } else {
+            deferred.pop()
+        };
+        let input_ready = usb_ready || serial_wrapper.uart_readable() || input_held;
+        if listing.is_some() {
+            input_held |= input_ready;
+        } else if input_ready || resumed.is_some() {
+            input_held = false;
+            // Commands are accepted on either interface, and the UART.
+            let mut rx = [0u8; 32];
+            let mut rx_events = [0u8; 32];
+            let mut rx_uart = [0u8; 32];
+            let count = serial_wrapper.port.read(&mut rx).unwrap_or(0);
+            let count_events = serial_wrapper.events.read(&mut rx_events).unwrap_or(0);
+            let count_uart = serial_wrapper.read_uart(&mut rx_uart);
+            let bytes = rx[..count].iter().map(|&b| (0, b));
+            let bytes = bytes.chain(rx_events[..count_events].iter().map(|&b| (1, b)));
+            let bytes = bytes.chain(rx_uart[..count_uart].iter().map(|&b| (2, b)));
+            let held = resumed.as_ref().map_or("", |line| line.as_str());
+            let bytes = bytes.chain(held.bytes().chain(*b"\r").map(|b| (3, b)));
+            for (source, byte) in bytes {
+                if source == 0 && protocol_pending {
+                    protocol_pending = false;
+                    protocol = Protocol::detect(byte);
+                    serial_wrapper.frame_events = protocol == Protocol::Binary;
+                    if protocol == Protocol::Binary {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: PROTOCOL mode=binary t={}\r",
+                            timer.get_counter().ticks()
+                        );
+                    }
+                }
+                // A binary host's hello is not part of a command.
+                if byte == stream::HELLO {
+                    continue;
+                }
+                let Some(line) = line_buffers[source].push(byte) else {
+                    continue;
+                };
+                // Inside `CONFIG LOAD` a dump's `Config: ` lines are its
+                // commands, up to the `Config: n=` line that closes it.
+                let mut line = line;
+                let mut from_dump = false;
+                if let (Some(load), Ok(text)) = (&mut config_load, line) {
+                    if let Some(rest) = text.strip_prefix("Config: ") {
+                        if let Some(end) = rest.strip_prefix("n=") {
+                            let mut fields = end.split_ascii_whitespace();
+                            let n = fields.next().and_then(|n| n.parse().ok());
+                            let crc = fields
+                                .next()
+                                .and_then(|c| c.strip_prefix("crc="))
+                                .and_then(|c| u32::from_str_radix(c, 16).ok());
+                            let intact = n == Some(load.lines) && crc == Some(load.crc.finish());
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: CONFIG_LOADED n={} failed={} crc={} t={}\r",
+                                load.lines,
+                                load.failed,
+                                if intact { "ok" } else { "bad" },
+                                timer.get_counter().ticks()
+                            );
+                            config_load = None;
+                            if intact {
+                                let _ = uwriteln!(serial_wrapper, "OK\r");
+                            } else {
+                                serial_wrapper.reject(ErrorCode::Argument, "dump damaged");
+                            }
+                            continue;
+                        }
+                        load.crc.update(text.as_bytes());
+                        load.crc.update(b"\r\n");
+                        load.lines += 1;
+                        line = Ok(rest);
+                        from_dump = true;
+                    }
+                }
+                let parsed = line.and_then(command::parse);
+                let query = matches!(parsed, Ok(Command::Scpi(scpi)) if scpi.is_query());
+                serial_wrapper.rejected = None;
+                serial_wrapper.replaying = source == 3;
+                match parsed {
+                    Ok(command) if command.rescales() && sequencer.testing() => {
+                        let refused = match mid_test {
+                            Policy::Reject => Some((ErrorCode::State, "test running")),
+                            Policy::Defer => match line.map(|text| deferred.push(text)) {
+                                Ok(Ok(())) => {
+                                    let _ = uwriteln!(
+                                        serial_wrapper,
+                                        "Event: DEFERRED n={} t={}\r",
+                                        deferred.len(),
+                                        timer.get_counter().ticks()
+                                    );
+                                    None
+                                }
+                                _ => Some((ErrorCode::Full, "deferred queue full")),
+                            },
+                        };
+                        match refused {
+                            Some(_) if matches!(command, Command::Scpi(_)) => serial_wrapper
+                                .reject_scpi(&mut scpi_errors, scpi::Error::SettingsConflict),
+                            Some((code, reason)) => serial_wrapper.reject(code, reason),
+                            None => {}
+                        }
+                    }
+                    Ok(Command::Start(_)) if sequencer.testing() => {
+                        serial_wrapper.reject(ErrorCode::State, "test already running");
+                    }
+                    Ok(Command::Start(_) | Command::Run(_) | Command::SampleRate(_))
+                        if burst.recording() =>
+                    {
+                        serial_wrapper.reject(ErrorCode::State, "burst recording");
+                    }
+                    Ok(Command::TriggerArm(Some(_))) if sequencer.testing() => {
+                        serial_wrapper.reject(ErrorCode::State, "test already running");
+                    }
+                    Ok(Command::TriggerArm(Some(_))) if burst.recording() => {
+                        serial_wrapper.reject(ErrorCode::State, "burst recording");
+                    }
+                    Ok(Command::Start(_) | Command::Run(_) | Command::TriggerArm(Some(_)))
+                        if sequencer.phase() == Phase::Fault =>
+                    {
+                        serial_wrapper.reject(ErrorCode::State, "sensor fault");
+                    }
+                    #[cfg(feature = "interlock")]
+                    Ok(Command::Start(_) | Command::Run(_) | Command::TriggerArm(Some(_)))
+                        if !sequencer.testing()
+                            && !interlock.take(timer.get_counter().ticks() / 1_000) =>
+                    {
+                        serial_wrapper.reject(ErrorCode::State, "locked, UNLOCK first");
+                    }
+                    Ok(Command::Start(_) | Command::Run(_) | Command::TriggerArm(Some(_)))
+                        if !sequencer.testing() && digital.blocking().is_some() =>
+                    {
+                        serial_wrapper.reject(ErrorCode::State, "gating input inactive");
+                    }
+                    Ok(Command::Start(StartTime::Now)) => {
+                        sequencer.arm(Micros(timer.get_counter().ticks()));
+                    }
+                    Ok(Command::Start(StartTime::In(secs))) => {
+                        sequencer.arm(Micros(
+                            timer.get_counter().ticks() + secs as u64 * 1_000_000,
+                        ));
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: TEST_SCHEDULED in={} t={}\r",
+                            secs,
+                            timer.get_counter().ticks()
+                        );
+                    }
+                    Ok(Command::Start(StartTime::At(unix))) => match wall.timer_at(unix) {
+                        Some(at) => {
+                            let now = timer.get_counter();
+                            sequencer.arm(Micros(at.0.max(now.ticks())));
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_SCHEDULED at={} t={}\r",
+                                unix,
+                                now.ticks()
+                            );
+                        }
+                        None => {
+                            serial_wrapper.reject(ErrorCode::State, "clock not set");
+                        }
+                    },
+                    Ok(Command::Stop) => {
+                        let t = timer.get_counter().ticks();
+                        if sequencer.stop("command", Micros(t)) {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_STOP reason=command t={}\r",
+                                t
+                            );
+                        } else if sequencer.cancel() {
+                            let _ = uwriteln!(serial_wrapper, "Event: TEST_CANCELLED t={}\r", t);
+                        } else {
+                            serial_wrapper.reject(ErrorCode::State, "no test running");
+                        }
+                    }
+                    #[cfg(feature = "interlock")]
+                    Ok(Command::Unlock(token)) => {
+                        if !interlock.unlock(token, timer.get_counter().ticks() / 1_000) {
+                            serial_wrapper.reject(ErrorCode::Argument, "wrong serial number");
+                        }
+                    }
+                    #[cfg(feature = "interlock")]
+                    Ok(Command::Lock) => interlock.lock(),
+                    #[cfg(feature = "interlock")]
+                    Ok(Command::LockQuery) => {
+                        match interlock.remaining_ms(timer.get_counter().ticks() / 1_000) {
+                            Some(ms) => uwriteln!(
+                                serial_wrapper,
+                                "Lock: state=unlocked remaining_ms={}\r",
+                                ms
+                            ),
+                            None => uwriteln!(serial_wrapper, "Lock: state=locked\r"),
+                        }
+                        .ok();
+                    }
+                    #[cfg(not(feature = "interlock"))]
+                    Ok(Command::Unlock(_) | Command::Lock | Command::LockQuery) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "interlock not built");
+                    }
+                    Ok(Command::Pause(hold)) => {
+                        if stream == Stream::Live {
+                            let now = timer.get_counter().ticks();
+                            stream = if hold {
+                                Stream::Holding {
+                                    from: history.next_seq(),
+                                }
+                            } else {
+                                Stream::Paused
+                            };
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: STREAM_PAUSE hold={} t={}\r",
+                                hold as u8,
+                                now
+                            );
+                        }
+                    }
+                    Ok(Command::Resume) => {
+                        let next = history.next_seq();
+                        let held = match stream {
+                            Stream::Holding { from } => {
+                                // Lines evicted while paused are lost.
+                                let start = history.oldest().unwrap_or(next).max(from);
+                                replay = (start < next).then_some(start);
+                                next - start
+                            }
+                            _ => 0,
+                        };
+                        if stream != Stream::Live {
+                            stream = Stream::Live;
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: STREAM_RESUME held={} t={}\r",
+                                held,
+                                timer.get_counter().ticks()
+                            );
+                        }
+                    }
+                    Ok(Command::TestPause) => match (sequencer.phase(), last_force) {
+                        (Phase::Running, Some(force)) => {
+                            sequencer.hold(force);
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_PAUSE force={} t={}\r",
+                                force,
+                                timer.get_counter().ticks()
+                            );
+                        }
+                        (Phase::Running, None) => {
+                            serial_wrapper.reject(ErrorCode::State, "no force reading");
+                        }
+                        _ => {
+                            serial_wrapper.reject(ErrorCode::State, "no test running");
+                        }
+                    },
+                    Ok(Command::TestResume) => {
+                        let now = last_force.or(sequencer.held()).unwrap_or_default();
+                        match sequencer.resume(now) {
+                            Ok(()) => {
+                                let _ = uwriteln!(
+                                    serial_wrapper,
+                                    "Event: TEST_RESUME force={} t={}\r",
+                                    now,
+                                    timer.get_counter().ticks()
+                                );
+                            }
+                            Err(ResumeError::NotHolding) => {
+                                serial_wrapper.reject(ErrorCode::State, "test not paused");
+                            }
+                            Err(ResumeError::Drifted { drift, limit }) => {
+                                let mut reason = LineBuf::new();
+                                let _ =
+                                    uwrite!(reason, "force drifted by {} (limit {})", drift, limit);
+                                serial_wrapper.reject(ErrorCode::State, reason.as_str());
+                            }
+                        }
+                    }
+                    Ok(Command::FilterAverage(window)) => {
+                        filter.average = (window > 1).then(|| MovingAverage::new(window));
+                    }
+                    Ok(Command::FilterLowPass(cutoff_mhz)) => {
+                        low_pass_cutoff = cutoff_mhz;
+                        filter.low_pass =
+                            (cutoff_mhz > 0).then(|| LowPass::new(cutoff_mhz, sample_sps * 1000));
+                    }
+                    Ok(Command::FilterMedian(window)) => {
+                        filter.spike =
+                            (window > 0).then(|| SpikeFilter::new(SpikeMode::Median, window, 5));
+                    }
+                    Ok(Command::FilterMad(k)) => {
+                        filter.spike =
+                            (k > 0).then(|| SpikeFilter::new(SpikeMode::Mad, MAD_WINDOW, k));
+                    }
+                    Ok(Command::FilterOff) => {
+                        filter = FilterChain::default();
+                        low_pass_cutoff = 0;
+                    }
+                    Ok(Command::SampleRate(sps)) if !supported_rates.contains(&sps) => {
+                        let mut reason = LineBuf::new();
+                        let _ = uwrite!(reason, "rate not supported by {}", backend.as_str());
+                        serial_wrapper.reject(ErrorCode::Unsupported, reason.as_str());
+                    }
+                    Ok(Command::SampleRate(sps)) => match acquisition.set_rate(sps) {
+                        Ok(()) => {
+                            sample_sps = sps;
+                            conversions.restart();
+                            if low_pass_cutoff > 0 {
+                                filter.low_pass = Some(LowPass::new(low_pass_cutoff, sps * 1000));
+                            }
+                            zero_track = zero_track_setting.map(|(band, hold_s)| {
+                                ZeroTracker::new(band, u32::saturating_mul(hold_s, sps))
+                            });
+                        }
+                        Err(SensorError::Unsupported) => {
+                            let mut reason = LineBuf::new();
+                            let _ = uwrite!(reason, "rate not supported by {}", backend.as_str());
+                            serial_wrapper.reject(ErrorCode::Unsupported, reason.as_str());
+                        }
+                        Err(_) => {
+                            serial_wrapper.reject(ErrorCode::Hardware, "sensor");
+                        }
+                    },
+                    Ok(Command::SampleRateQuery) => {
+                        let _ =
+                            uwrite!(serial_wrapper, "SampleRate: sps={} supported=", sample_sps);
+                        for (i, sps) in supported_rates.iter().enumerate() {
+                            let sep = if i == 0 { "" } else { "," };
+                            let _ = uwrite!(serial_wrapper, "{}{}", sep, *sps);
+                        }
+                        let _ = uwriteln!(serial_wrapper, "\r");
+                    }
+                    Ok(Command::Rate(window)) => {
+                        rate = (window > 0).then(|| Derivative::new(window));
+                    }
+                    Ok(Command::Decimate(n)) if (1..=Decimator::MAX).contains(&n) => {
+                        decimate = Decimator::new(n);
+                    }
+                    Ok(Command::Decimate(_)) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    Ok(Command::Format(new)) => {
+                        columns = new;
+                        let _ = uwrite!(serial_wrapper, "Event: FORMAT columns=");
+                        write_columns(&mut serial_wrapper, columns);
+                        let _ = uwriteln!(serial_wrapper, " t={}\r", timer.get_counter().ticks());
+                    }
+                    Ok(Command::FormatQuery) => {
+                        let _ = uwrite!(serial_wrapper, "Format: columns=");
+                        write_columns(&mut serial_wrapper, columns);
+                        let _ = uwriteln!(serial_wrapper, "\r");
+                    }
+                    Ok(Command::Protocol(Some(new))) => {
+                        protocol = new;
+                        serial_wrapper.frame_events = protocol == Protocol::Binary;
+                        protocol_fixed = true;
+                        protocol_pending = false;
+                    }
+                    Ok(Command::Protocol(None)) => protocol_fixed = false,
+                    Ok(Command::ProtocolQuery) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Protocol: mode={} select={}\r",
+                            protocol.as_str(),
+                            if protocol_fixed { "fixed" } else { "auto" }
+                        );
+                    }
+                    Ok(Command::Units(new_unit)) => {
+                        if new_unit != Unit::Raw && scale.is_none() {
+                            serial_wrapper.reject(ErrorCode::State, "not calibrated");
+                        } else {
+                            unit = new_unit;
+                        }
+                    }
+                    Ok(Command::Mode(_) | Command::Preload(_)) if sequencer.testing() => {
+                        serial_wrapper.reject(ErrorCode::State, "test running");
+                    }
+                    Ok(Command::Polarity(polarity)) => {
+                        if polarity != loading.polarity {
+                            loading.polarity = polarity;
+                            // Readings and offset flip together, so the
+                            // tare still holds.
+                            offset = offset.saturating_neg();
+                            b_offset = b_offset.map(i32::saturating_neg);
+                            filter.reset();
+                            peak.reset();
+                            if let Some(zero) = &mut zero_track {
+                                zero.reset();
+                            }
+                        }
+                    }
+                    Ok(Command::Mode(direction)) => loading.direction = direction,
+                    Ok(Command::Preload(Some(0))) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "preload must be above zero");
+                    }
+                    Ok(Command::Preload(counts)) => {
+                        sequencer.set_preload(counts.map(|c| c.min(i32::MAX as u32) as i32));
+                    }
+                    Ok(Command::ModeQuery) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Mode: mode={} polarity={}\r",
+                            loading.direction.as_str(),
+                            loading.polarity.as_str()
+                        );
+                    }
+                    Ok(Command::Decimals(n)) if n <= Scale::MAX_PLACES => decimals = n,
+                    Ok(Command::Decimals(_)) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    Ok(Command::UnitsQuery) => {
+                        let _ = uwrite!(serial_wrapper, "Units: unit={}", unit.as_str());
+                        if let Some(scale) = scale {
+                            let _ =
+                                uwrite!(serial_wrapper, " counts_per_kg={}", scale.counts_per_kg());
+                        }
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            " gravity_um_s2={} decimals={}\r",
+                            gravity,
+                            decimals
+                        );
+                    }
+                    Ok(Command::Calibrate(counts_per_kg, note)) => {
+                        match Scale::new(counts_per_kg, gravity) {
+                            Some(new_scale) => {
+                                scale = Some(new_scale);
+                                creep = note.creep.map(CreepComp::new);
+                                let now = timer.get_counter().ticks();
+                                let unix = wall.unix(Micros(now));
+                                let record = cal_record(0, counts_per_kg, note, unix, &metadata);
+                                acquisition.parked(|| cal_log.append(record));
+                                let _ = uwriteln!(
+                                    serial_wrapper,
+                                    "Event: CAL counts_per_kg={} t={}\r",
+                                    counts_per_kg,
+                                    now
+                                );
+                                let secondary = channels.first().and_then(|c| c.scale());
+                                dual = dual.and_then(|d| {
+                                    dual_cell(d.mode(), d.limit_pct(), scale, secondary)
+                                });
+                                if let Some(range) = &mut range {
+                                    range.set_a_per_kg(counts_per_kg);
+                                }
+                            }
+                            None => {
+                                serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                            }
+                        }
+                    }
+                    Ok(Command::Gravity(g)) if GRAVITY_RANGE_UM_S2.contains(&g) => {
+                        gravity = g;
+                        scale = scale.and_then(|s| Scale::new(s.counts_per_kg(), g));
+                        for channel in &mut channels {
+                            channel.set_gravity(g);
+                        }
+                    }
+                    Ok(Command::Gravity(_)) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "gravity out of range");
+                    }
+                    Ok(Command::ZeroTrack(setting)) => {
+                        zero_track_setting = setting;
+                        zero_track = setting.map(|(band, hold_s)| {
+                            ZeroTracker::new(band, u32::saturating_mul(hold_s, sample_sps))
+                        });
+                    }
+                    Ok(Command::Tare(samples)) if (1..=Tare::MAX_SAMPLES).contains(&samples) => {
+                        tare = Some(Tare::new(samples));
+                    }
+                    Ok(Command::Tare(_)) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    Ok(Command::ChannelTare(ch, _) | Command::ChannelCal(ch, ..))
+                        if !(1..=channels.len()).contains(&ch) =>
+                    {
+                        serial_wrapper.reject(ErrorCode::Argument, "no such channel");
+                    }
+                    Ok(Command::ChannelTare(ch, samples))
+                        if (1..=Tare::MAX_SAMPLES).contains(&samples) =>
+                    {
+                        channels[ch - 1].start_tare(samples);
+                    }
+                    Ok(Command::ChannelTare(..)) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    Ok(Command::Dual(Some(_))) if channels.is_empty() => {
+                        serial_wrapper.reject(ErrorCode::Argument, "no such channel");
+                    }
+                    Ok(Command::Dual(Some((mode, limit_pct)))) => {
+                        dual = dual_cell(
+                            mode,
+                            limit_pct,
+                            scale,
+                            channels.first().and_then(|c| c.scale()),
+                        );
+                        if dual.is_none() {
+                            serial_wrapper.reject(ErrorCode::State, "not calibrated");
+                        }
+                    }
+                    Ok(Command::Dual(None)) => dual = None,
+                    Ok(Command::DualQuery) => match &dual {
+                        Some(dual) => {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Dual: mode={} limit_pct={} mismatch={}\r",
+                                dual.mode().as_str(),
+                                dual.limit_pct(),
+                                dual.is_mismatched() as u8
+                            );
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "Dual: mode=off\r");
+                        }
+                    },
+                    Ok(Command::Range(Some(_))) if scale.is_none() => {
+                        serial_wrapper.reject(ErrorCode::State, "not calibrated");
+                    }
+                    Ok(Command::Range(Some((b_per_kg, switch, hysteresis)))) => {
+                        let a_per_kg = scale.map_or(0, |s| s.counts_per_kg());
+                        match AutoRange::new(a_per_kg, b_per_kg, switch, hysteresis) {
+                            // Starts on B, zeroed there, so the cell must be
+                            // unloaded.
+                            Some(new) => match acquisition.set_input(Input::B) {
+                                Ok(()) => {
+                                    range = Some(new);
+                                    b_offset = None;
+                                    tare = Some(Tare::new(Tare::DEFAULT_SAMPLES));
+                                }
+                                Err(_) => {
+                                    serial_wrapper
+                                        .reject(ErrorCode::Unsupported, "no second input");
+                                }
+                            },
+                            None => {
+                                serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                            }
+                        }
+                    }
+                    Ok(Command::Range(None)) => {
+                        if range.take().is_some() {
+                            let _ = acquisition.set_input(Input::A);
+                        }
+                    }
+                    Ok(Command::GainCheck(_)) if backend != sensor::Backend::Hx711 => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "hx711 only");
+                    }
+                    Ok(Command::GainCheck(Some((minutes, _))))
+                        if !(1..=MAX_GAIN_CHECK_MIN).contains(&minutes) =>
+                    {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    Ok(Command::GainCheck(setting)) => {
+                        if gain_check.as_mut().is_some_and(GainCheck::abort) {
+                            let _ = acquisition.set_input(Input::A);
+                        }
+                        let now_ms = timer.get_counter().ticks() / 1_000;
+                        gain_check = setting.map(|(minutes, tolerance)| {
+                            GainCheck::new(minutes as u64 * 60_000, tolerance, now_ms)
+                        });
+                    }
+                    Ok(Command::MidTest(policy)) => mid_test = policy,
+                    Ok(Command::MidTestQuery) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "MidTest: policy={} deferred={}\r",
+                            mid_test.as_str(),
+                            deferred.len()
+                        );
+                    }
+                    Ok(Command::GainCheckQuery) => match &gain_check {
+                        Some(check) => {
+                            let _ = uwrite!(
+                                serial_wrapper,
+                                "GainCheck: every_min={} tol={}",
+                                check.period_ms() / 60_000,
+                                check.tolerance()
+                            );
+                            if let Some(last) = check.last() {
+                                let _ = uwrite!(
+                                    serial_wrapper,
+                                    " result={} high={} low={} diff={}",
+                                    last.as_str(),
+                                    last.high,
+                                    last.low,
+                                    last.diff()
+                                );
+                            }
+                            let _ = uwriteln!(serial_wrapper, "\r");
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "GainCheck: off\r");
+                        }
+                    },
+                    Ok(Command::RangeQuery) => match &range {
+                        Some(range) => {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Range: b_counts_per_kg={} switch={} hysteresis={} input={}\r",
+                                range.b_per_kg(),
+                                range.switch(),
+                                range.hysteresis(),
+                                range.active().as_str()
+                            );
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "Range: state=off\r");
+                        }
+                    },
+                    Ok(Command::ChannelCal(_, _, note)) if note.creep.is_some() => {
+                        serial_wrapper.reject(ErrorCode::Argument, "CREEP is for the primary cell");
+                    }
+                    Ok(Command::ChannelCal(ch, counts_per_kg, note)) => {
+                        if !channels[ch - 1].calibrate(counts_per_kg, gravity) {
+                            serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                        } else {
+                            let now = timer.get_counter().ticks();
+                            let unix = wall.unix(Micros(now));
+                            let record = cal_record(ch as u8, counts_per_kg, note, unix, &metadata);
+                            acquisition.parked(|| cal_log.append(record));
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: CAL ch={} counts_per_kg={} t={}\r",
+                                ch,
+                                counts_per_kg,
+                                now
+                            );
+                            if ch == 1 {
+                                let secondary = channels.first().and_then(|c| c.scale());
+                                dual = dual.and_then(|d| {
+                                    dual_cell(d.mode(), d.limit_pct(), scale, secondary)
+                                });
+                            }
+                        }
+                    }
+                    Ok(Command::Trigger(out, _)) if out >= TRIGGER_OUTPUTS => {
+                        serial_wrapper.reject(ErrorCode::Argument, "no such output");
+                    }
+                    Ok(Command::Trigger(out, setting)) => {
+                        triggers[out] = setting
+                            .map(|(edge, level, hysteresis)| Trigger::new(edge, level, hysteresis));
+                        test_outputs[out] = false;
+                        let _ = trigger_pins[out].set_low();
+                    }
+                    Ok(Command::TriggerTest(out)) if out >= TRIGGER_OUTPUTS => {
+                        serial_wrapper.reject(ErrorCode::Argument, "no such output");
+                    }
+                    Ok(Command::TriggerTest(out)) => {
+                        triggers[out] = None;
+                        test_outputs[out] = true;
+                        let _ = trigger_pins[out].set_state(test_outputs_up.into());
+                    }
+                    #[cfg(feature = "digital-inputs")]
+                    Ok(Command::TriggerArm(Some((n, _)))) if n >= INPUTS => {
+                        serial_wrapper.reject(ErrorCode::Argument, "no such input");
+                    }
+                    #[cfg(feature = "digital-inputs")]
+                    Ok(Command::TriggerArm(Some((n, rising)))) => {
+                        if let Some((old, _)) = sync_arm {
+                            for edge in [Interrupt::EdgeHigh, Interrupt::EdgeLow] {
+                                input_pins[old].set_interrupt_enabled(edge, false);
+                            }
+                        }
+                        let edge = if rising {
+                            Interrupt::EdgeHigh
+                        } else {
+                            Interrupt::EdgeLow
+                        };
+                        // Only edges from now on; the interrupt wakes the
+                        // loop but has no handler.
+                        input_pins[n].clear_interrupt(edge);
+                        input_pins[n].set_interrupt_enabled(edge, true);
+                        sync_arm = Some((n, rising));
+                        sequencer.arm(AWAIT_EDGE);
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: TRIG_ARMED in={} edge={} t={}\r",
+                            n,
+                            if rising { "rise" } else { "fall" },
+                            timer.get_counter().ticks()
+                        );
+                    }
+                    #[cfg(feature = "digital-inputs")]
+                    Ok(Command::TriggerArm(None)) => {
+                        if sync_arm.is_some() && sequencer.start_at() == Some(AWAIT_EDGE) {
+                            // The loop drops the arm once it sees it cancelled.
+                            sequencer.cancel();
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_CANCELLED t={}\r",
+                                timer.get_counter().ticks()
+                            );
+                        } else {
+                            serial_wrapper.reject(ErrorCode::State, "not armed");
+                        }
+                    }
+                    #[cfg(not(feature = "digital-inputs"))]
+                    Ok(Command::TriggerArm(_)) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "digital-inputs not built");
+                    }
+                    Ok(Command::Output(out, _)) if out >= TRIGGER_OUTPUTS => {
+                        serial_wrapper.reject(ErrorCode::Argument, "no such output");
+                    }
+                    Ok(Command::Output(out, on)) => {
+                        triggers[out] = None;
+                        test_outputs[out] = false;
+                        let _ = trigger_pins[out].set_state(on.into());
+                    }
+                    #[cfg(feature = "digital-inputs")]
+                    Ok(Command::Din(input, setting)) => {
+                        if !digital.set(input, setting) {
+                            serial_wrapper.reject(ErrorCode::Argument, "no such input");
+                        }
+                    }
+                    #[cfg(feature = "digital-inputs")]
+                    Ok(Command::DinQuery) => {
+                        for input in 0..INPUTS {
+                            let _ = match digital.setting(input) {
+                                Some(s) => uwriteln!(
+                                    serial_wrapper,
+                                    "Din: in={} active={} invert={} gate={}\r",
+                                    input,
+                                    (digital.state() >> input & 1),
+                                    s.invert as u8,
+                                    s.gate as u8
+                                ),
+                                None => uwriteln!(serial_wrapper, "Din: in={} active=off\r", input),
+                            };
+                        }
+                    }
+                    #[cfg(not(feature = "digital-inputs"))]
+                    Ok(Command::Din(..) | Command::DinQuery) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "digital-inputs not built");
+                    }
+                    Ok(Command::TriggerQuery) => {
+                        for (out, trigger) in triggers.iter().enumerate() {
+                            match trigger {
+                                None if test_outputs[out] => {
+                                    let _ = uwriteln!(
+                                        serial_wrapper,
+                                        "Trig: out={} edge=test state={}\r",
+                                        out,
+                                        test_outputs_up as u8
+                                    );
+                                }
+                                Some(t) => {
+                                    let _ = uwriteln!(
+                                        serial_wrapper,
+                                        "Trig: out={} edge={} level={} hyst={} state={}\r",
+                                        out,
+                                        t.edge().as_str(),
+                                        t.level(),
+                                        t.hysteresis(),
+                                        t.is_active() as u8
+                                    );
+                                }
+                                None => {
+                                    let _ =
+                                        uwriteln!(serial_wrapper, "Trig: out={} edge=off\r", out);
+                                }
+                            }
+                        }
+                    }
+                    Ok(Command::Capture(setting)) => {
+                        capture = setting.map(|(edge, level, length, pre_trigger)| {
+                            Capture::new(edge, Counts(level), length, pre_trigger)
+                        });
+                    }
+                    Ok(Command::CaptureQuery) => match &capture {
+                        Some(c) => {
+                            let w = &mut serial_wrapper;
+                            let _ = uwrite!(
+                                w,
+                                "Capture: state={} edge={} level={}",
+                                c.state_str(),
+                                c.edge().as_str(),
+                                c.level().0
+                            );
+                            match c.length() {
+                                Length::Samples(n) => {
+                                    let _ = uwrite!(w, " count={}", n);
+                                }
+                                Length::Millis(ms) => {
+                                    let _ = uwrite!(w, " time=");
+                                    write_milli(w, ms as i64);
+                                }
+                            }
+                            let _ = uwriteln!(w, " pre={}\r", c.pre_trigger());
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "Capture: state=off\r");
+                        }
+                    },
+                    Ok(Command::Burst(Some(_))) if sequencer.testing() => {
+                        serial_wrapper.reject(ErrorCode::State, "test running");
+                    }
+                    Ok(Command::Burst(Some(_))) if burst.recording() => {
+                        serial_wrapper.reject(ErrorCode::State, "burst recording");
+                    }
+                    // Readings from B would need the range switch followed.
+                    Ok(Command::Burst(Some(_))) if range.is_some() => {
+                        serial_wrapper.reject(ErrorCode::State, "RANGE on");
+                    }
+                    Ok(Command::Burst(Some(ms))) if ms == 0 || ms > burst::MAX_MS => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    Ok(Command::Burst(Some(ms))) => {
+                        let fastest = supported_rates.iter().copied().max().unwrap_or(sample_sps);
+                        match acquisition.set_rate(fastest) {
+                            Ok(()) => {
+                                burst.start(ms);
+                                let _ = uwriteln!(
+                                    serial_wrapper,
+                                    "Event: BURST_START sps={} t={}\r",
+                                    fastest,
+                                    timer.get_counter().ticks()
+                                );
+                            }
+                            Err(_) => {
+                                serial_wrapper.reject(ErrorCode::Hardware, "sensor");
+                            }
+                        }
+                    }
+                    Ok(Command::Burst(None)) => burst.stop(),
+                    Ok(Command::Break(setting)) => {
+                        breaks = setting
+                            .map(|(drop_pct, min_peak)| BreakDetector::new(drop_pct, min_peak));
+                    }
+                    Ok(Command::Slip(setting)) => match setting {
+                        Some((0, ..)) | Some((_, _, 0, _)) => {
+                            serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                        }
+                        Some((drop_pct, min_peak, window, stop)) => {
+                            slips = Some((SlipDetector::new(drop_pct, min_peak, window), stop));
+                        }
+                        None => slips = None,
+                    },
+                    #[cfg(feature = "modbus")]
+                    Ok(Command::Modbus(unit)) => match unit {
+                        Some(1..=247) | None => modbus.set_unit(unit),
+                        Some(_) => {
+                            serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                        }
+                    },
+                    #[cfg(not(feature = "modbus"))]
+                    Ok(Command::Modbus(_)) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "modbus not built");
+                    }
+                    #[cfg(feature = "i2c-target")]
+                    Ok(Command::I2cTarget(address)) => match address {
+                        Some(address) if !i2c_target::ADDRESSES.contains(&address) => {
+                            serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                        }
+                        address => i2c_target.set_address(address, &mut pac.RESETS),
+                    },
+                    #[cfg(not(feature = "i2c-target"))]
+                    Ok(Command::I2cTarget(_)) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "i2c-target not built");
+                    }
+                    #[cfg(feature = "uart-stream")]
+                    Ok(Command::Uart(setting)) => match setting {
+                        Some((baud, _)) if !uart::BAUD_RANGE.contains(&baud) => {
+                            serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                        }
+                        setting => serial_wrapper.uart.set(setting),
+                    },
+                    #[cfg(not(feature = "uart-stream"))]
+                    Ok(Command::Uart(_)) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "uart-stream not built");
+                    }
+                    #[cfg(feature = "bluetooth")]
+                    Ok(Command::BluetoothQuery) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Bluetooth: link={} baud={}\r",
+                            if bt_link.connected() { "up" } else { "down" },
+                            serial_wrapper.uart.baud()
+                        );
+                    }
+                    #[cfg(not(feature = "bluetooth"))]
+                    Ok(Command::BluetoothQuery) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "bluetooth not built");
+                    }
+                    Ok(Command::TimeSet(unix)) => {
+                        let now = timer.get_counter().ticks();
+                        wall.set(unix, Micros(now));
+                        time_source = "host";
+                        let _ =
+                            uwriteln!(serial_wrapper, "Event: TIME_SET unix={} t={}\r", unix, now);
+                        // The clock is set either way; only the copy that
+                        // survives a power cycle is missing.
+                        #[cfg(feature = "rtc")]
+                        if let Some(rtc) = &mut rtc {
+                            if rtc.write(DateTime::from_unix(unix)).is_err() {
+                                serial_wrapper.reject(ErrorCode::Hardware, "rtc write failed");
+                            }
+                        }
+                    }
+                    Ok(Command::TimeQuery) => {
+                        let now = timer.get_counter().ticks();
+                        let _ = uwrite!(serial_wrapper, "Time:");
+                        if let Some(unix) = wall.unix(Micros(now)) {
+                            let _ = uwrite!(serial_wrapper, " unix={} utc=", unix);
+                            write_utc(&mut serial_wrapper, DateTime::from_unix(unix));
+                        }
+                        let _ = uwriteln!(serial_wrapper, " source={} t={}\r", time_source, now);
+                    }
+                    Ok(Command::Sync(token)) => {
+                        let now = timer.get_counter().ticks();
+                        let _ = uwriteln!(serial_wrapper, "Sync: host={} t={}\r", token, now);
+                    }
+                    Ok(Command::Meta(entry)) => {
+                        if metadata.set(entry).is_err() {
+                            serial_wrapper.reject(ErrorCode::Full, "metadata full");
+                        }
+                    }
+                    Ok(Command::MetaClear) => metadata.clear(),
+                    Ok(Command::Mark(label)) => {
+                        if marks.push(label).is_err() {
+                            serial_wrapper.reject(ErrorCode::Full, "too many marks waiting");
+                        }
+                    }
+                    Ok(Command::MetaQuery) => write_meta(&mut serial_wrapper, &metadata),
+                    Ok(Command::Profile(_) | Command::ProfileDelete(_)) if sequencer.testing() => {
+                        serial_wrapper.reject(ErrorCode::State, "test running");
+                    }
+                    Ok(Command::Profile(new))
+                        if new
+                            .end_output
+                            .is_some_and(|(out, _)| out as usize >= TRIGGER_OUTPUTS) =>
+                    {
+                        serial_wrapper.reject(ErrorCode::Argument, "no such output");
+                    }
+                    Ok(Command::Profile(new)) if new.preload == Some(0) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "preload must be above zero");
+                    }
+                    Ok(Command::Profile(new))
+                        if new.clamp.is_some() && !cfg!(feature = "servo") =>
+                    {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "servo not built");
+                    }
+                    Ok(Command::Profile(new)) => match profiles.set(new) {
+                        Ok(()) => acquisition.parked(|| profile::save(&profiles)),
+                        Err(_) => {
+                            serial_wrapper.reject(ErrorCode::Full, "profiles full");
+                        }
+                    },
+                    Ok(Command::ProfileDelete(name)) => {
+                        if profiles.remove(&name) {
+                            acquisition.parked(|| profile::save(&profiles));
+                        } else {
+                            serial_wrapper.reject(ErrorCode::Argument, "no such profile");
+                        }
+                    }
+                    Ok(Command::ProfileQuery) => {
+                        let mut count = 0;
+                        for p in profiles.iter() {
+                            count += 1;
+                            let w = &mut serial_wrapper;
+                            let _ = uwrite!(w, "Profile: name={} break=", p.name.as_str());
+                            let _ = match p.breaks {
+                                Some((drop_pct, min_peak)) => {
+                                    uwrite!(w, "{},{}", drop_pct, min_peak)
+                                }
+                                None => uwrite!(w, "off"),
+                            };
+                            let _ = match p.limits.max_force {
+                                Some(max) => uwrite!(w, " limit={}", max),
+                                None => uwrite!(w, " limit=off"),
+                            };
+                            let _ = match p.limits.max_duration_s {
+                                Some(max) => uwrite!(w, " time={}", max),
+                                None => uwrite!(w, " time=off"),
+                            };
+                            let _ = match p.clamp {
+                                Some(deg) => uwrite!(w, " clamp={}", deg),
+                                None => uwrite!(w, " clamp=off"),
+                            };
+                            if let Some(counts) = p.preload {
+                                let _ = uwrite!(w, " preload={}", counts);
+                            }
+                            let _ = match p.end_output {
+                                Some((out, on)) => {
+                                    uwriteln!(w, " end={},{}\r", out, if on { "on" } else { "off" })
+                                }
+                                None => uwriteln!(w, " end=off\r"),
+                            };
+                            serial_wrapper.flush_control();
+                        }
+                        let _ = uwriteln!(serial_wrapper, "Profile: end n={}\r", count);
+                    }
+                    Ok(Command::Run(_)) if sequencer.testing() => {
+                        serial_wrapper.reject(ErrorCode::State, "test already running");
+                    }
+                    Ok(Command::Run(name)) => match profiles.get(&name) {
+                        Some(p) => {
+                            breaks = p.breaks.map(|(drop_pct, min_peak)| {
+                                BreakDetector::new(drop_pct as u32, min_peak)
+                            });
+                            run_limits = Some(p.limits);
+                            run_end_output = p.end_output;
+                            if let Some(counts) = p.preload {
+                                sequencer.preload_next(counts.min(i32::MAX as u32) as i32);
+                            }
+                            let now = timer.get_counter();
+                            sequencer.arm(Micros(now.ticks()));
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: RUN profile={} t={}\r",
+                                p.name.as_str(),
+                                now.ticks()
+                            );
+                            #[cfg(feature = "servo")]
+                            if let Some(deg) = p.clamp {
+                                servo.set(deg);
+                                sequencer.arm(Micros((now + CLAMP_SETTLE_MS.millis()).ticks()));
+                                let _ = uwriteln!(
+                                    serial_wrapper,
+                                    "Event: CLAMP angle={} t={}\r",
+                                    deg,
+                                    now.ticks()
+                                );
+                            }
+                        }
+                        None => {
+                            serial_wrapper.reject(ErrorCode::Argument, "no such profile");
+                        }
+                    },
+                    #[cfg(feature = "servo")]
+                    Ok(Command::Clamp(deg)) => {
+                        servo.set(deg);
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: CLAMP angle={} t={}\r",
+                            deg,
+                            timer.get_counter().ticks()
+                        );
+                    }
+                    #[cfg(feature = "servo")]
+                    Ok(Command::ClampQuery) => {
+                        let _ = match servo.angle() {
+                            Some(deg) => uwriteln!(serial_wrapper, "Clamp: angle={}\r", deg),
+                            None => uwriteln!(serial_wrapper, "Clamp: angle=off\r"),
+                        };
+                    }
+                    #[cfg(not(feature = "servo"))]
+                    Ok(Command::Clamp(_) | Command::ClampQuery) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "servo not built");
+                    }
+                    Ok(Command::Replay(last)) if last >= history.next_seq() => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    Ok(Command::Replay(last)) => {
+                        let next = history.next_seq();
+                        let from = last + 1;
+                        let start = history.oldest().unwrap_or(next).max(from);
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: REPLAY count={} lost={} t={}\r",
+                            next - start,
+                            start - from,
+                            timer.get_counter().ticks()
+                        );
+                        replay = (start < next).then_some(start);
+                    }
+                    Ok(Command::ErrLog) => {
+                        listing = Some(Listing::ErrLog { after: None, n: 0 });
+                    }
+                    Ok(Command::CalLog(last)) => {
+                        let kept = cal_log.records().count();
+                        let skip = last.map_or(0, |n| kept.saturating_sub(n));
+                        let after = skip.checked_sub(1).and_then(|i| cal_log.records().nth(i));
+                        listing = Some(Listing::CalLog {
+                            after: after.map(|r| r.seq),
+                            n: 0,
+                        });
+                    }
+                    Ok(Command::TempCo(coeff_milli)) => {
+                        temp_coeff = coeff_milli;
+                        temp_ref = temp_ref.or(temp);
+                    }
+                    #[cfg(feature = "chamber")]
+                    Ok(Command::Aux(2, _)) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "ADC2 is the thermistor");
+                    }
+                    Ok(Command::Aux(channel, setting)) if channel < aux.len() => {
+                        aux[channel] = setting.map(|(gain_milli, offset_milli)| AuxScale {
+                            gain_milli,
+                            offset_milli,
+                        });
+                    }
+                    Ok(Command::Aux(..)) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    #[cfg(feature = "extensometer")]
+                    Ok(Command::ExtZero) => extensometer.zero(quadrature.count()),
+                    #[cfg(feature = "extensometer")]
+                    Ok(Command::ExtScale(0)) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    #[cfg(feature = "extensometer")]
+                    Ok(Command::ExtScale(nm)) => extensometer.set_nm_per_count(nm),
+                    #[cfg(feature = "extensometer")]
+                    Ok(Command::ExtQuery) => {
+                        let count = quadrature.count();
+                        let w = &mut serial_wrapper;
+                        let _ = uwrite!(w, "Ext: ext=");
+                        write_milli(w, extensometer.extension_um(count));
+                        let _ = uwrite!(w, " count={} scale=", count);
+                        write_milli(w, extensometer.nm_per_count() as i64);
+                        let _ = uwriteln!(w, "\r");
+                    }
+                    #[cfg(not(feature = "extensometer"))]
+                    Ok(Command::ExtZero | Command::ExtScale(_) | Command::ExtQuery) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "extensometer not built");
+                    }
+                    Ok(Command::Work(Some((ch, _)))) if ch >= aux.len() => {
+                        serial_wrapper.reject(ErrorCode::Argument, "no such channel");
+                    }
+                    Ok(Command::Work(setting)) => work_source = setting,
+                    Ok(Command::WorkQuery) => {
+                        let w = &mut serial_wrapper;
+                        let _ = match work_source {
+                            Some((ch, stream)) => {
+                                uwrite!(w, "Work: source=aux{} stream={}", ch, stream as u8)
+                            }
+                            None => uwrite!(w, "Work: source=off"),
+                        };
+                        let _ = uwrite!(w, " work=");
+                        write_milli(w, work.millijoules());
+                        let _ = uwriteln!(w, "\r");
+                    }
+                    #[cfg(feature = "chamber")]
+                    Ok(Command::Chamber(Some(set))) if set > chamber::MAX_SETPOINT.0 as u32 => {
+                        serial_wrapper.reject(ErrorCode::Argument, "above 200 C");
+                    }
+                    #[cfg(feature = "chamber")]
+                    Ok(Command::Chamber(set)) => {
+                        chamber.set(set.map(|set| MilliCelsius(set as i32)));
+                    }
+                    #[cfg(feature = "chamber")]
+                    Ok(Command::ChamberPid(gains)) => chamber.set_gains(gains),
+                    #[cfg(feature = "chamber")]
+                    Ok(Command::ChamberQuery) => {
+                        let w = &mut serial_wrapper;
+                        let _ = uwrite!(w, "Chamber: temp=");
+                        match chamber.temp() {
+                            Some(temp) => write_milli(w, temp.0 as i64),
+                            None => {
+                                let _ = uwrite!(w, "fault");
+                            }
+                        }
+                        let _ = uwrite!(w, " set=");
+                        match chamber.setpoint() {
+                            Some(set) => write_milli(w, set.0 as i64),
+                            None => {
+                                let _ = uwrite!(w, "off");
+                            }
+                        }
+                        let _ = uwriteln!(w, " duty={}\r", chamber.duty());
+                    }
+                    #[cfg(not(feature = "chamber"))]
+                    Ok(Command::Chamber(_) | Command::ChamberPid(_) | Command::ChamberQuery) => {
+                        serial_wrapper.reject(ErrorCode::Unsupported, "chamber not built");
+                    }
+                    Ok(command @ (Command::Config | Command::ConfigDump)) => {
+                        let dump = command == Command::ConfigDump;
+                        // Ordered so each command finds what it depends on
+                        // already set: rate before filters, CAL before UNITS.
+                        let w = &mut serial_wrapper;
+                        w.paging = true;
+                        if dump {
+                            w.summing = Some(Crc32::new());
+                        }
+                        let mut n = 0;
+                        if sample_sps != DEFAULT_SAMPLE_SPS {
+                            let _ = uwriteln!(w, "Config: SAMPLERATE {}\r", sample_sps);
+                            n += 1;
+                        }
+                        if gravity != STANDARD_GRAVITY_UM_S2 {
+                            let _ = uwrite!(w, "Config: GRAVITY ");
+                            write_fixed(w, gravity as i64, 6);
+                            let _ = uwriteln!(w, "\r");
+                            n += 1;
+                        }
+                        // The sign of CAL depends on it.
+                        if loading.polarity != Polarity::Normal {
+                            let _ =
+                                uwriteln!(w, "Config: POLARITY {}\r", loading.polarity.as_str());
+                            n += 1;
+                        }
+                        if loading.direction != Direction::Tension {
+                            let _ = uwriteln!(w, "Config: MODE {}\r", loading.direction.as_str());
+                            n += 1;
+                        }
+                        if let Some(counts) = sequencer.preload() {
+                            let _ = uwriteln!(w, "Config: PRELOAD {}\r", counts);
+                            n += 1;
+                        }
+                        if let Some(scale) = scale {
+                            let _ = uwrite!(w, "Config: CAL {}", scale.counts_per_kg());
+                            if let Some(c) = creep.map(|c| c.model()) {
+                                let _ = uwrite!(w, " CREEP {} {}", c.ppm, c.tau_s);
+                            }
+                            let _ = uwriteln!(w, "\r");
+                            n += 1;
+                        }
+                        for (i, channel) in channels.iter().enumerate() {
+                            if let Some(scale) = channel.scale() {
+                                let _ = uwriteln!(
+                                    w,
+                                    "Config: CH {} CAL {}\r",
+                                    i + 1,
+                                    scale.counts_per_kg()
+                                );
+                                n += 1;
+                            }
+                        }
+                        if unit != Unit::Raw {
+                            let _ = uwriteln!(w, "Config: UNITS {}\r", unit.as_str());
+                            n += 1;
+                        }
+                        if decimals != DEFAULT_DECIMALS {
+                            let _ = uwriteln!(w, "Config: DECIMALS {}\r", decimals);
+                            n += 1;
+                        }
+                        if let Some(spike) = &filter.spike {
+                            let _ = match spike.mode() {
+                                SpikeMode::Median => {
+                                    uwriteln!(w, "Config: FILTER MEDIAN {}\r", spike.window())
+                                }
+                                SpikeMode::Mad => {
+                                    uwriteln!(w, "Config: FILTER MAD {}\r", spike.k())
+                                }
+                            };
+                            n += 1;
+                        }
+                        if let Some(average) = &filter.average {
+                            let _ = uwriteln!(w, "Config: FILTER AVG {}\r", average.window());
+                            n += 1;
+                        }
+                        if low_pass_cutoff > 0 {
+                            let _ = uwrite!(w, "Config: FILTER IIR ");
+                            write_milli(w, low_pass_cutoff as i64);
+                            let _ = uwriteln!(w, "\r");
+                            n += 1;
+                        }
+                        if let Some(rate) = &rate {
+                            let _ = uwriteln!(w, "Config: RATE {}\r", rate.window());
+                            n += 1;
+                        }
+                        if decimate.n() > 1 {
+                            let _ = uwriteln!(w, "Config: DECIMATE {}\r", decimate.n());
+                            n += 1;
+                        }
+                        if columns != Columns::ALL {
+                            let _ = uwrite!(w, "Config: FORMAT ");
+                            write_columns(w, columns);
+                            let _ = uwriteln!(w, "\r");
+                            n += 1;
+                        }
+                        if protocol_fixed {
+                            let _ = uwriteln!(w, "Config: PROTOCOL {}\r", protocol.as_str());
+                            n += 1;
+                        }
+                        if let Some((band, hold_s)) = zero_track_setting {
+                            let _ = uwriteln!(w, "Config: ZERO TRACK {} {}\r", band, hold_s);
+                            n += 1;
+                        }
+                        if temp_coeff != 0 {
+                            let _ = uwrite!(w, "Config: TEMPCO ");
+                            write_milli(w, temp_coeff as i64);
+                            let _ = uwriteln!(w, "\r");
+                            n += 1;
+                        }
+                        for (ch, scale) in aux.iter().enumerate() {
+                            if let Some(scale) = scale {
+                                let _ = uwrite!(w, "Config: AUX {} ", ch);
+                                write_milli(w, scale.gain_milli as i64);
+                                let _ = uwrite!(w, " ");
+                                write_milli(w, scale.offset_milli as i64);
+                                let _ = uwriteln!(w, "\r");
+                                n += 1;
+                            }
+                        }
+                        #[cfg(feature = "extensometer")]
+                        if extensometer.nm_per_count() != Extensometer::DEFAULT_NM_PER_COUNT {
+                            let _ = uwrite!(w, "Config: EXT SCALE ");
+                            write_milli(w, extensometer.nm_per_count() as i64);
+                            let _ = uwriteln!(w, "\r");
+                            n += 1;
+                        }
+                        if let Some((ch, stream)) = work_source {
+                            let stream = if stream { " STREAM" } else { "" };
+                            let _ = uwriteln!(w, "Config: WORK AUX {}{}\r", ch, stream);
+                            n += 1;
+                        }
+                        if let Some(dual) = &dual {
+                            let _ = uwriteln!(
+                                w,
+                                "Config: DUAL {} {}\r",
+                                dual.mode().as_str(),
+                                dual.limit_pct()
+                            );
+                            n += 1;
+                        }
+                        if let Some(range) = &range {
+                            let _ = uwriteln!(
+                                w,
+                                "Config: RANGE {} {} {}\r",
+                                range.b_per_kg(),
+                                range.switch(),
+                                range.hysteresis()
+                            );
+                            n += 1;
+                        }
+                        if let Some(check) = &gain_check {
+                            let _ = uwriteln!(
+                                w,
+                                "Config: GAINCHECK {} {}\r",
+                                check.period_ms() / 60_000,
+                                check.tolerance()
+                            );
+                            n += 1;
+                        }
+                        for (out, trigger) in triggers.iter().enumerate() {
+                            if let Some(t) = trigger {
+                                let _ = uwriteln!(
+                                    w,
+                                    "Config: TRIG {} {} {} {}\r",
+                                    out,
+                                    t.edge().as_str(),
+                                    t.level(),
+                                    t.hysteresis()
+                                );
+                                n += 1;
+                            }
+                            if test_outputs[out] {
+                                let _ = uwriteln!(w, "Config: TRIG {} TEST\r", out);
+                                n += 1;
+                            }
+                        }
+                        for input in 0..INPUTS {
+                            if let Some(s) = digital.setting(input) {
+                                let _ = uwriteln!(
+                                    w,
+                                    "Config: DIN {} ON{}{}\r",
+                                    input,
+                                    if s.invert { " INVERT" } else { "" },
+                                    if s.gate { " GATE" } else { "" }
+                                );
+                                n += 1;
+                            }
+                        }
+                        if let Some(detector) = &breaks {
+                            let _ = uwriteln!(
+                                w,
+                                "Config: BREAK {} {}\r",
+                                detector.drop_pct(),
+                                detector.min_peak()
+                            );
+                            n += 1;
+                        }
+                        if let Some((detector, stop)) = &slips {
+                            let _ = uwriteln!(
+                                w,
+                                "Config: SLIP {} {} {}{}\r",
+                                detector.drop_pct(),
+                                detector.min_peak(),
+                                detector.window(),
+                                if *stop { " STOP" } else { "" }
+                            );
+                            n += 1;
+                        }
+                        for entry in metadata.iter() {
+                            let _ =
+                                uwriteln!(w, "Config: META {}={}\r", entry.key(), entry.value());
+                            n += 1;
+                        }
+                        if let Some(s) = low_power {
+                            let _ = uwriteln!(w, "Config: POWER LOW {}\r", s);
+                            n += 1;
+                        }
+                        if heartbeat_ms != DEFAULT_HEARTBEAT_MS {
+                            let _ = uwriteln!(w, "Config: HEARTBEAT {}\r", heartbeat_ms);
+                            n += 1;
+                        }
+                        #[cfg(feature = "chamber")]
+                        if chamber.gains() != Gains::DEFAULT {
+                            let gains = chamber.gains();
+                            let _ = uwrite!(w, "Config: CHAMBER PID ");
+                            write_milli(w, gains.kp_milli as i64);
+                            let _ = uwrite!(w, " ");
+                            write_milli(w, gains.ki_milli as i64);
+                            let _ = uwrite!(w, " ");
+                            write_milli(w, gains.kd_milli as i64);
+                            let _ = uwriteln!(w, "\r");
+                            n += 1;
+                        }
+                        #[cfg(feature = "chamber")]
+                        if let Some(set) = chamber.setpoint() {
+                            let _ = uwrite!(w, "Config: CHAMBER ");
+                            write_milli(w, set.0 as i64);
+                            let _ = uwriteln!(w, "\r");
+                            n += 1;
+                        }
+                        if dump {
+                            for p in profiles.iter() {
+                                let _ = uwrite!(w, "Config: PROFILE {}", p.name.as_str());
+                                if let Some((drop_pct, min_peak)) = p.breaks {
+                                    let _ = uwrite!(w, " BREAK {} {}", drop_pct, min_peak);
+                                }
+                                if let Some(max) = p.limits.max_force {
+                                    let _ = uwrite!(w, " LIMIT {}", max);
+                                }
+                                if let Some(max) = p.limits.max_duration_s {
+                                    let _ = uwrite!(w, " TIME {}", max);
+                                }
+                                if let Some(deg) = p.clamp {
+                                    let _ = uwrite!(w, " CLAMP {}", deg);
+                                }
+                                if let Some(counts) = p.preload {
+                                    let _ = uwrite!(w, " PRELOAD {}", counts);
+                                }
+                                if let Some((out, on)) = p.end_output {
+                                    let _ = uwrite!(
+                                        w,
+                                        " END {} {}",
+                                        out,
+                                        if on { "ON" } else { "OFF" }
+                                    );
+                                }
+                                let _ = uwriteln!(w, "\r");
+                                w.flush_control();
+                                n += 1;
+                            }
+                            if let Some(name) = &device_name {
+                                let _ = uwriteln!(w, "Config: NAME {}\r", name.as_str());
+                                n += 1;
+                            }
+                        }
+                        #[cfg(feature = "modbus")]
+                        match modbus.unit() {
+                            Some(modbus::DEFAULT_UNIT) => {}
+                            Some(unit) => {
+                                let _ = uwriteln!(w, "Config: MODBUS {}\r", unit);
+                                n += 1;
+                            }
+                            None => {
+                                let _ = uwriteln!(w, "Config: MODBUS OFF\r");
+                                n += 1;
+                            }
+                        }
+                        if let Some(level) = log::level() {
+                            let _ = uwriteln!(w, "Config: LOG LEVEL {}\r", level.as_str());
+                            n += 1;
+                        }
+                        if mid_test != Policy::Reject {
+                            let _ = uwriteln!(w, "Config: MIDTEST {}\r", mid_test.as_str());
+                            n += 1;
+                        }
+                        #[cfg(feature = "i2c-target")]
+                        match i2c_target.address() {
+                            Some(i2c_target::DEFAULT_ADDRESS) => {}
+                            Some(address) => {
+                                let _ = uwriteln!(w, "Config: I2C {:x}\r", address);
+                                n += 1;
+                            }
+                            None => {
+                                let _ = uwriteln!(w, "Config: I2C OFF\r");
+                                n += 1;
+                            }
+                        }
+                        #[cfg(feature = "uart-stream")]
+                        match w.uart.mode() {
+                            Some(UartMode::Mirror) if w.uart.baud() == uart::DEFAULT_BAUD => {}
+                            Some(mode) => {
+                                let baud = w.uart.baud();
+                                let _ = uwriteln!(w, "Config: UART {} {}\r", baud, mode.as_str());
+                                n += 1;
+                            }
+                            None => {
+                                let _ = uwriteln!(w, "Config: UART OFF\r");
+                                n += 1;
+                            }
+                        }
+                        match w.summing.take() {
+                            Some(crc) => {
+                                let _ = uwriteln!(w, "Config: n={} crc={:x}\r", n, crc.finish());
+                            }
+                            None => {
+                                let _ = uwriteln!(w, "Config: n={}\r", n);
+                            }
+                        }
+                    }
+                    Ok(Command::Name(name)) => {
+                        device_name = name;
+                        acquisition.parked(|| identity::save(device_name.as_ref()));
+                    }
+                    Ok(Command::NameQuery) => {
+                        let name = device_name.as_ref().map_or("none", |n| n.as_str());
+                        let _ = uwriteln!(serial_wrapper, "Name: {}\r", name);
+                    }
+                    Ok(Command::Info) => {
+                        let _ = uwrite!(
+                            serial_wrapper,
+                            "Info: fw={} git={} built={} board=pico serial={} backend={}",
+                            env!("CARGO_PKG_VERSION"),
+                            env!("GIT_HASH"),
+                            env!("BUILD_DATE"),
+                            serial_number,
+                            backend.as_str()
+                        );
+                        if let Some(name) = &device_name {
+                            let _ = uwrite!(serial_wrapper, " name={}", name.as_str());
+                        }
+                        // Pin assignments, for the front ends built in.
+                        let _ = uwrite!(
+                            serial_wrapper,
+                            " board={} pin_hx711={}",
+                            board::NAME,
+                            board::HX711_PINS
+                        );
+                        if cfg!(feature = "nau7802") {
+                            let _ = uwrite!(serial_wrapper, " pin_nau7802=4,5");
+                        }
+                        if cfg!(feature = "ads1256") {
+                            let _ = uwrite!(serial_wrapper, " pin_ads1256=18,19,20,21,22");
+                        }
+                        if cfg!(feature = "hx711-ch1") {
+                            let _ = uwrite!(serial_wrapper, " pin_ch1=14,15");
+                        }
+                        if cfg!(feature = "modbus") {
+                            let _ = uwrite!(serial_wrapper, " pin_modbus=0,1,2");
+                        }
+                        if cfg!(feature = "i2c-target") {
+                            let _ = uwrite!(serial_wrapper, " pin_i2c_target=6,7");
+                        }
+                        if cfg!(feature = "uart-stream") {
+                            let _ = uwrite!(serial_wrapper, " pin_uart=0,1");
+                        }
+                        #[cfg(feature = "bluetooth")]
+                        let _ = uwrite!(serial_wrapper, " pin_bt_state={}", board::BT_STATE_PIN);
+                        if cfg!(feature = "oled") {
+                            let _ = uwrite!(serial_wrapper, " pin_oled=6,7");
+                        }
+                        if cfg!(feature = "rtc") {
+                            let _ = uwrite!(serial_wrapper, " pin_rtc=6,7");
+                        }
+                        if cfg!(feature = "buzzer") {
+                            let _ = uwrite!(serial_wrapper, " pin_buzzer=15");
+                        }
+                        if cfg!(feature = "encoder") {
+                            let _ = uwrite!(serial_wrapper, " pin_encoder=8,9");
+                        }
+                        #[cfg(any(feature = "encoder", feature = "tare-button"))]
+                        let _ = uwrite!(serial_wrapper, " pin_button={}", board::BUTTON_PIN);
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            " pin_aux=26,27,28 pin_trig=10,11,12,13 pin_led=25 cal_crc={:x}\r",
+                            calibration_crc(scale, &channels, gravity)
+                        );
+                    }
+                    Ok(Command::SelfTest) => write_selftest(&mut serial_wrapper, &self_test),
+                    Ok(Command::Caps) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Caps: hx711=1 nau7802={} ads1256={} motion=0 sd=0 display={} rtc={} wifi=0 modbus={} i2c_target={} uart={} bluetooth={} interlock={} servo={} chamber={} extensometer={} backend={} channels={} outputs={} inputs={}\r",
+                            cfg!(feature = "nau7802") as u8,
+                            cfg!(feature = "ads1256") as u8,
+                            cfg!(feature = "oled") as u8,
+                            cfg!(feature = "rtc") as u8,
+                            cfg!(feature = "modbus") as u8,
+                            cfg!(feature = "i2c-target") as u8,
+                            cfg!(feature = "uart-stream") as u8,
+                            cfg!(feature = "bluetooth") as u8,
+                            cfg!(feature = "interlock") as u8,
+                            cfg!(feature = "servo") as u8,
+                            cfg!(feature = "chamber") as u8,
+                            cfg!(feature = "extensometer") as u8,
+                            backend.as_str(),
+                            1 + channels.len(),
+                            TRIGGER_OUTPUTS,
+                            if cfg!(feature = "digital-inputs") { INPUTS } else { 0 }
+                        );
+                    }
+                    Ok(Command::Status) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Status: test={} stream={} sensor={} rejected={} outputs={:x}\r",
+                            sequencer.phase().as_str(),
+                            stream.as_str(),
+                            monitor.health().as_str(),
+                            filter.rejected(),
+                            output_bits(&mut trigger_pins)
+                        );
+                    }
+                    Ok(Command::Log(level)) => log::set_level(level),
+                    Ok(Command::LogQuery) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Log: level={} lost={}\r",
+                            log::level().map_or("off", log::Level::as_str),
+                            log::lost()
+                        );
+                    }
+                    Ok(Command::Diag) => {
+                        let w = &mut serial_wrapper;
+                        let _ = uwrite!(w, "Diag:");
+                        if let Some(s) = loop_time.spread() {
+                            let _ = uwrite!(
+                                w,
+                                " loops={} loop_mean_us={} loop_max_us={}",
+                                s.n,
+                                s.mean,
+                                s.max
+                            );
+                        }
+                        if let Some(s) = conversions.spread() {
+                            let _ = uwrite!(
+                                w,
+                                " intervals={} interval_min_us={} interval_mean_us={} interval_max_us={} jitter_us={}",
+                                s.n,
+                                s.min,
+                                s.mean,
+                                s.max,
+                                s.max - s.min
+                            );
+                        }
+                        if let Some(s) = latency.spread() {
+                            let _ = uwrite!(
+                                w,
+                                " lines={} latency_mean_us={} latency_max_us={}",
+                                s.n,
+                                s.mean,
+                                s.max
+                            );
+                        }
+                        let tx_dropped = w.dropped;
+                        let _ = uwriteln!(
+                            w,
+                            " tx_dropped={} acq_dropped={} t={}\r",
+                            tx_dropped,
+                            acquisition.dropped(),
+                            timer.get_counter().ticks()
+                        );
+                    }
+                    Ok(Command::DiagReset) => {
+                        loop_time = Timing::new();
+                        conversions.restart();
+                        latency = Timing::new();
+                    }
+                    Ok(Command::Heartbeat(ms)) => {
+                        heartbeat_ms = ms;
+                        next_heartbeat = timer.get_counter() + (ms as u64).millis();
+                    }
+                    Ok(Command::Power(Some(s))) if !(1..=MAX_SLEEP_S).contains(&s) => {
+                        serial_wrapper.reject(ErrorCode::Argument, "bad argument");
+                    }
+                    Ok(Command::Power(setting)) => {
+                        match acquisition.set_low_power(setting.map(|s| s * 1_000)) {
+                            Ok(()) => {
+                                low_power = setting;
+                                conversions.restart();
+                            }
+                            Err(_) => {
+                                serial_wrapper.reject(ErrorCode::Hardware, "sensor power failed");
+                            }
+                        }
+                    }
+                    Ok(Command::PowerQuery) => match low_power {
+                        Some(s) => {
+                            let _ = uwriteln!(serial_wrapper, "Power: mode=low interval_s={}\r", s);
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "Power: mode=normal\r");
+                        }
+                    },
+                    Ok(Command::Peak) => {
+                        let _ = uwrite!(serial_wrapper, "Peak:");
+                        if let Some(max) = peak.max() {
+                            let _ =
+                                uwrite!(serial_wrapper, " max={} t_max={}", max.value.0, max.t.0);
+                        }
+                        if let Some(min) = peak.min() {
+                            let _ =
+                                uwrite!(serial_wrapper, " min={} t_min={}", min.value.0, min.t.0);
+                        }
+                        let _ = uwriteln!(serial_wrapper, "\r");
+                    }
+                    Ok(Command::PeakReset) => peak.reset(),
+                    Ok(Command::TestQuery) => {
+                        let w = &mut serial_wrapper;
+                        let _ = uwrite!(w, "Test: number={}", test_log.number());
+                        if let Some((last, summary)) = test_log.last() {
+                            let _ = uwrite!(w, " last={}", last);
+                            if let Some(peak) = summary.peak {
+                                let _ = uwrite!(w, " peak={}", peak);
+                            }
+                            let _ = uwrite!(w, " duration=");
+                            write_milli(w, summary.duration_ms as i64);
+                            let _ = uwrite!(w, " reason={}", summary.reason());
+                        }
+                        let _ = uwriteln!(w, "\r");
+                    }
+                    Ok(Command::CalCheckStart) => {
+                        cal_check.start();
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: CALCHECK_START t={}\r",
+                            timer.get_counter().ticks()
+                        );
+                        let _ =
+                            uwriteln!(serial_wrapper, "Prompt: place weight, CALCHECK POINT <g>\r");
+                    }
+                    Ok(Command::CalCheckPoint(mass_mg)) => {
+                        if let Err(e) = cal_check.begin_point(mass_mg) {
+                            serial_wrapper.reject(e.into(), e.as_str());
+                        }
+                    }
+                    Ok(Command::CalCheckEnd) => match cal_check.finish() {
+                        Ok(_) => write_certificate(&mut serial_wrapper, &cal_check, session_id),
+                        Err(e) => serial_wrapper.reject(e.into(), e.as_str()),
+                    },
+                    Ok(Command::CalCheckQuery) => {
+                        if cal_check.certificate().is_none() {
+                            serial_wrapper.reject(ErrorCode::State, "no calcheck certificate");
+                        }
+                        write_certificate(&mut serial_wrapper, &cal_check, session_id)
+                    }
+                    Ok(Command::Stats) => match stats.summary() {
+                        Some(s) => {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Stats: n={} min={} max={} mean={} rms={} sigma={}\r",
+                                s.count,
+                                s.min,
+                                s.max,
+                                s.mean,
+                                s.rms,
+                                s.sigma
+                            );
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "Stats: n=0\r");
+                        }
+                    },
+                    Ok(Command::StatsReset) => stats.reset(),
+                    Ok(Command::NoiseQuery) if sequencer.testing() => {
+                        serial_wrapper.reject(ErrorCode::State, "test running");
+                    }
+                    Ok(Command::NoiseQuery) => {
+                        noise_check = Some(NoiseCheck::new(NOISE_CHECK_S * sample_sps));
+                    }
+                    // SCPI replies are bare values.
+                    Ok(Command::Scpi(Scpi::Identify)) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "leafy-sys,Pico Tensile Tester,{},{}\r",
+                            serial_number,
+                            env!("CARGO_PKG_VERSION")
+                        );
+                    }
+                    Ok(command @ (Command::Scpi(Scpi::Reset) | Command::ConfigLoad)) => {
+                        let t = timer.get_counter().ticks();
+                        if sequencer.stop("reset", Micros(t)) {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_STOP reason=reset t={}\r",
+                                t
+                            );
+                        }
+                        sequencer.cancel();
+                        sequencer.set_preload(None);
+                        filter = FilterChain::default();
+                        low_pass_cutoff = 0;
+                        rate = None;
+                        decimate = Decimator::new(1);
+                        columns = Columns::ALL;
+                        protocol_fixed = false;
+                        unit = Unit::Raw;
+                        decimals = DEFAULT_DECIMALS;
+                        gravity = STANDARD_GRAVITY_UM_S2;
+                        scale = scale.and_then(|s| Scale::new(s.counts_per_kg(), gravity));
+                        for channel in &mut channels {
+                            channel.set_gravity(gravity);
+                        }
+                        if loading.polarity != Polarity::Normal {
+                            offset = offset.saturating_neg();
+                            b_offset = b_offset.map(i32::saturating_neg);
+                        }
+                        loading = Loading::TENSION;
+                        zero_track_setting = None;
+                        zero_track = None;
+                        temp_coeff = 0;
+                        log::set_level(None);
+                        mid_test = Policy::Reject;
+                        deferred.clear();
+                        if gain_check.take().as_mut().is_some_and(GainCheck::abort) {
+                            let _ = acquisition.set_input(Input::A);
+                        }
+                        aux = [None; 3];
+                        work_source = None;
+                        #[cfg(feature = "extensometer")]
+                        extensometer.set_nm_per_count(Extensometer::DEFAULT_NM_PER_COUNT);
+                        dual = None;
+                        if range.take().is_some() {
+                            let _ = acquisition.set_input(Input::A);
+                        }
+                        breaks = None;
+                        slips = None;
+                        capture = None;
+                        burst.stop();
+                        // A burst still recording puts this rate back as it ends.
+                        sample_sps = DEFAULT_SAMPLE_SPS;
+                        if !burst.recording() {
+                            let _ = acquisition.set_rate(sample_sps);
+                            conversions.restart();
+                        }
+                        metadata.clear();
+                        marks.clear();
+                        heartbeat_ms = DEFAULT_HEARTBEAT_MS;
+                        if low_power.take().is_some() {
+                            let _ = acquisition.set_low_power(None);
+                        }
+                        #[cfg(feature = "chamber")]
+                        {
+                            chamber.set(None);
+                            chamber.set_gains(Gains::DEFAULT);
+                        }
+                        triggers = [None; TRIGGER_OUTPUTS];
+                        test_outputs = [false; TRIGGER_OUTPUTS];
+                        test_outputs_up = false;
+                        digital = DigitalInputs::new();
+                        for pin in &mut trigger_pins {
+                            let _ = pin.set_low();
+                        }
+                        if command == Command::ConfigLoad {
+                            // A dump leaves out CAL lines when uncalibrated.
+                            scale = None;
+                            creep = None;
+                            for channel in &mut channels {
+                                channel.clear_calibration();
+                            }
+                            profiles = Profiles::new();
+                            device_name = None;
+                            acquisition.parked(|| {
+                                profile::save(&profiles);
+                                identity::save(None);
+                            });
+                            config_load = Some(ConfigLoad {
+                                crc: Crc32::new(),
+                                lines: 0,
+                                failed: 0,
+                            });
+                        }
+                    }
+                    Ok(Command::Scpi(Scpi::ClearStatus)) => scpi_errors.clear(),
+                    Ok(Command::Scpi(Scpi::SelfTest)) => {
+                        let _ = uwriteln!(serial_wrapper, "{}\r", !self_test.passed() as u8);
+                    }
+                    Ok(Command::Scpi(Scpi::OperationComplete)) => {
+                        let _ = uwriteln!(serial_wrapper, "1\r");
+                    }
+                    Ok(Command::Scpi(Scpi::Error)) => match scpi_errors.pop() {
+                        Some(e) => {
+                            let _ = uwriteln!(serial_wrapper, "{},\"{}\"\r", e.code(), e.message());
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "0,\"No error\"\r");
+                        }
+                    },
+                    Ok(Command::Scpi(Scpi::ErrorCount)) => {
+                        let _ = uwriteln!(serial_wrapper, "{}\r", scpi_errors.len());
+                    }
+                    Ok(Command::Scpi(Scpi::Version)) => {
+                        let _ = uwriteln!(serial_wrapper, "1999.0\r");
+                    }
+                    Ok(Command::Scpi(Scpi::MeasureForce)) => match (monitor.health(), last_force) {
+                        (Health::Ok, Some(force)) => {
+                            write_force(&mut serial_wrapper, Counts(force), unit, scale, decimals);
+                            let _ = uwriteln!(serial_wrapper, "\r");
+                        }
+                        (Health::Ok, None) => {
+                            serial_wrapper.reject_scpi(&mut scpi_errors, scpi::Error::DataStale)
+                        }
+                        _ => {
+                            serial_wrapper.reject_scpi(&mut scpi_errors, scpi::Error::HardwareError)
+                        }
+                    },
+                    Ok(Command::Scpi(Scpi::MeasurePeak)) => match loading.peak(&peak) {
+                        Some(max) => {
+                            write_force(&mut serial_wrapper, max.value, unit, scale, decimals);
+                            let _ = uwriteln!(serial_wrapper, "\r");
+                        }
+                        None => {
+                            serial_wrapper.reject_scpi(&mut scpi_errors, scpi::Error::DataStale)
+                        }
+                    },
+                    #[cfg(feature = "interlock")]
+                    Ok(Command::Scpi(Scpi::Initiate))
+                        if sequencer.phase() == Phase::Idle
+                            && !interlock.take(timer.get_counter().ticks() / 1_000) =>
+                    {
+                        serial_wrapper.reject_scpi(&mut scpi_errors, scpi::Error::SettingsConflict);
+                    }
+                    Ok(Command::Scpi(Scpi::Initiate)) => {
+                        if sequencer.phase() != Phase::Idle {
+                            serial_wrapper.reject_scpi(&mut scpi_errors, scpi::Error::InitIgnored);
+                        } else {
+                            sequencer.arm(Micros(timer.get_counter().ticks()));
+                        }
+                    }
+                    Ok(Command::Scpi(Scpi::Abort)) => {
+                        let t = timer.get_counter().ticks();
+                        if sequencer.stop("command", Micros(t)) {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_STOP reason=command t={}\r",
+                                t
+                            );
+                        } else if sequencer.cancel() {
+                            let _ = uwriteln!(serial_wrapper, "Event: TEST_CANCELLED t={}\r", t);
+                        }
+                    }
+                    Ok(Command::Scpi(Scpi::Unit(new_unit))) => {
+                        if new_unit != Unit::Raw && scale.is_none() {
+                            serial_wrapper
+                                .reject_scpi(&mut scpi_errors, scpi::Error::SettingsConflict);
+                        } else {
+                            unit = new_unit;
+                        }
+                    }
+                    Ok(Command::Scpi(Scpi::UnitQuery)) => {
+                        let _ = uwriteln!(serial_wrapper, "{}\r", unit.as_str());
+                    }
+                    Ok(Command::Scpi(Scpi::Zero)) => {
+                        tare = Some(Tare::new(Tare::DEFAULT_SAMPLES));
+                    }
+                    Err(e) if line.is_ok_and(command::is_scpi) => {
+                        serial_wrapper.reject_scpi(&mut scpi_errors, e.scpi())
+                    }
+                    Err(e) => serial_wrapper.reject(e.code(), e.as_str()),
+                }
+                // Every command is answered, last; SCPI queries by their
+                // value alone. A listing answers once it has all gone. One
+                // that was held is answered by an event: its host has had
+                // its OK.
+                if serial_wrapper.replaying {
+                    serial_wrapper.replaying = false;
+                    let _ = uwrite!(serial_wrapper, "Event: APPLIED cmd={} ", held);
+                    let _ = match serial_wrapper.rejected {
+                        Some(code) => uwrite!(serial_wrapper, "result=err code={}", code),
+                        None => uwrite!(serial_wrapper, "result=ok"),
+                    };
+                    let _ = uwriteln!(serial_wrapper, " t={}\r", timer.get_counter().ticks());
+                } else if serial_wrapper.rejected.is_none() && !query && listing.is_none() {
+                    let _ = uwriteln!(serial_wrapper, "OK\r");
+                }
+                serial_wrapper.paging = false;
+                if let Some(load) = config_load.as_mut().filter(|_| from_dump) {
+                    load.failed += serial_wrapper.rejected.is_some() as u32;
+                }
+            }
+        }
+
+        // Announce the timestamp epoch whenever a host opens either port, so
+        // a client attaching mid-run knows which session `t=` belongs to. A
+        // fault raised before anyone was listening is repeated too.
+        //
+        // A host lost mid-test, by closing its ports or by the bus suspending
+        // as it does when the cable is pulled or VBUS browns out, can no
+        // longer watch the test or stop it, so the test stops there.
+        let dtr = match usb_dev.state() {
+            UsbDeviceState::Suspend => [false; 2],
+            _ => [serial_wrapper.port.dtr(), serial_wrapper.events.dtr()],
+        };
+        if host_attached.contains(&true) && !dtr.contains(&true) {
+            let t = timer.get_counter().ticks();
+            if sequencer.stop("host_lost", Micros(t)) {
+                let _ = uwriteln!(
+                    serial_wrapper,
+                    "Event: TEST_STOP reason=host_lost t={}\r",
+                    t
+                );
+                host_lost = Some((t, "stopped"));
+            } else if sequencer.cancel() {
+                host_lost = Some((t, "cancelled"));
+            }
+        }
+        if dtr
+            .iter()
+            .zip(host_attached)
+            .any(|(&now, before)| now && !before)
+        {
+            let now = timer.get_counter().ticks();
+            let _ = uwrite!(
+                serial_wrapper,
+                "Event: EPOCH session={:x} t={}",
+                session_id,
+                now
+            );
+            write_unix(&mut serial_wrapper, &wall, now);
+            // The self-test ran long before USB was up; the first host to
+            // connect gets its report, and the panic that rebooted the board
+            // if there was one.
+            if !self_test_sent {
+                write_selftest(&mut serial_wrapper, &self_test);
+                self_test_sent = true;
+            }
+            if let Some(crash) = panicked.take() {
+                let _ = uwriteln!(
+                    serial_wrapper,
+                    "Event: PANIC at={}:{} t={}\r",
+                    crash.file(),
+                    crash.line(),
+                    now
+                );
+                let _ = uwriteln!(serial_wrapper, "Panic: {}\r", crash.message());
+            }
+            if let Some((t, test)) = host_lost.take() {
+                let _ = uwriteln!(serial_wrapper, "Event: HOST_LOST test={} t={}\r", test, t);
+            }
+            // A trimmed format is repeated, so the host knows which fields
+            // to expect.
+            if columns != Columns::ALL {
+                let _ = uwrite!(serial_wrapper, "Event: FORMAT columns=");
+                write_columns(&mut serial_wrapper, columns);
+                let _ = uwriteln!(serial_wrapper, " t={}\r", now);
+            }
+            if let Health::Fault(kind) = monitor.health() {
+                let _ = uwriteln!(
+                    serial_wrapper,
+                    "Event: SENSOR_FAULT reason={} t={}\r",
+                    kind.as_str(),
+                    timer.get_counter().ticks()
+                );
+            }
+        }
+        // A host new on the data port says what it speaks with its first
+        // byte; until then it gets text.
+        if dtr[0] && !host_attached[0] && !protocol_fixed {
+            protocol = Protocol::Ascii;
+            serial_wrapper.frame_events = false;
+            protocol_pending = true;
+        }
+        host_attached = dtr;
+
+        // --- Modbus RTU requests from a PLC ---
+        #[cfg(feature = "modbus")]
+        if let Some(image) = modbus.serve(timer.get_counter(), || {
+            let mut status = match sequencer.phase() {
+                Phase::Armed => modbus::Image::SCHEDULED,
+                Phase::Preload | Phase::Running => modbus::Image::RUNNING,
+                Phase::Holding => modbus::Image::PAUSED,
+                _ => 0,
+            };
+            status |= match monitor.health() {
+                Health::Ok => 0,
+                Health::Overload => modbus::Image::OVERLOAD,
+                Health::Fault(_) => modbus::Image::FAULT,
+            };
+            if scale.is_some() {
+                status |= modbus::Image::CALIBRATED;
+            }
+            modbus::Image {
+                force: last_force,
+                force_unit: last_force.map(|f| match scale {
+                    Some(scale) if unit != Unit::Raw => scale
+                        .convert(Counts(f), unit)
+                        .clamp(i32::MIN as i64, i32::MAX as i64)
+                        as i32,
+                    _ => f,
+                }),
+                peak: loading.peak(&peak).map(|max| max.value.0),
+                status,
+                seq: history.next_seq(),
+                unit,
+                calibrated: scale.is_some(),
+                testing: sequencer.testing(),
+                control: None,
+            }
+        }) {
+            unit = image.unit;
+            match image.control {
+                Some(modbus::Control::Start) if !sequencer.testing() => {
+                    sequencer.arm(Micros(timer.get_counter().ticks()));
+                }
+                Some(modbus::Control::Stop) => {
+                    let t = timer.get_counter().ticks();
+                    if sequencer.stop("modbus", Micros(t)) {
+                        let _ =
+                            uwriteln!(serial_wrapper, "Event: TEST_STOP reason=modbus t={}\r", t);
+                    }
+                }
+                Some(modbus::Control::Tare) if !sequencer.testing() => {
+                    tare = Some(Tare::new(Tare::DEFAULT_SAMPLES));
+                }
+                Some(modbus::Control::PeakReset) => peak.reset(),
+                _ => {}
+            }
+        }
+
+        // --- I2C target transfers from a PLC or microcontroller ---
+        #[cfg(feature = "i2c-target")]
+        match i2c_target.serve(&timer, || {
+            let mut status = match sequencer.phase() {
+                Phase::Armed => i2c_target::Image::SCHEDULED,
+                Phase::Preload | Phase::Running => i2c_target::Image::RUNNING,
+                Phase::Holding => i2c_target::Image::PAUSED,
+                _ => 0,
+            };
+            status |= match monitor.health() {
+                Health::Ok => 0,
+                Health::Overload => i2c_target::Image::OVERLOAD,
+                Health::Fault(_) => i2c_target::Image::FAULT,
+            };
+            if scale.is_some() {
+                status |= i2c_target::Image::CALIBRATED;
+            }
+            #[cfg(feature = "extensometer")]
+            let displacement = Some(extensometer.extension_um(quadrature.count()) as i32);
+            #[cfg(not(feature = "extensometer"))]
+            let displacement = None;
+            i2c_target::Image {
+                force: last_force,
+                force_unit: last_force.map(|f| match scale {
+                    Some(scale) if unit != Unit::Raw => scale
+                        .convert(Counts(f), unit)
+                        .clamp(i32::MIN as i64, i32::MAX as i64)
+                        as i32,
+                    _ => f,
+                }),
+                peak: loading.peak(&peak).map(|max| max.value.0),
+                displacement,
+                status,
+                seq: history.next_seq(),
+            }
+        }) {
+            Some(i2c_target::Control::Start) if !sequencer.testing() => {
+                sequencer.arm(Micros(timer.get_counter().ticks()));
+            }
+            Some(i2c_target::Control::Stop) => {
+                let t = timer.get_counter().ticks();
+                if sequencer.stop("i2c", Micros(t)) {
+                    let _ = uwriteln!(serial_wrapper, "Event: TEST_STOP reason=i2c t={}\r", t);
+                }
+            }
+            Some(i2c_target::Control::Tare) if !sequencer.testing() => {
+                tare = Some(Tare::new(Tare::DEFAULT_SAMPLES));
+            }
+            Some(i2c_target::Control::PeakReset) => peak.reset(),
+            _ => {}
+        }
+
+        // --- Front panel: press to start or stop, hold to tare, turn to
+        // change unit between tests ---
+        #[cfg(feature = "encoder")]
+        {
+            let (turned, press) = panel.poll(timer.get_counter());
+            if turned != 0 && scale.is_some() && !sequencer.testing() {
+                unit = unit.cycle(turned);
+            }
+            match press {
+                Some(Press::Short) if sequencer.phase() == Phase::Idle => {
+                    sequencer.arm(Micros(timer.get_counter().ticks()));
+                }
+                // A press while armed keeps the start already set.
+                Some(Press::Short) if !sequencer.testing() => {}
+                Some(Press::Short) => {
+                    let t = timer.get_counter().ticks();
+                    if sequencer.stop("panel", Micros(t)) {
+                        let _ =
+                            uwriteln!(serial_wrapper, "Event: TEST_STOP reason=panel t={}\r", t);
+                    }
+                }
+                Some(Press::Long) if !sequencer.testing() => {
+                    tare = Some(Tare::new(Tare::DEFAULT_SAMPLES));
+                }
+                _ => {}
+            }
+        }
+
+        // --- Tare button ---
+        #[cfg(feature = "tare-button")]
+        if tare_button.poll(timer.get_counter()).is_some() && !sequencer.testing() {
+            tare = Some(Tare::new(Tare::DEFAULT_SAMPLES));
+        }
+
+        // --- Test log: how the test that just stopped went ---
+        // Before any new start, which would take over its record.
+        if let Some(ending) = sequencer.finish() {
+            let duration_ms = (ending.t.0.saturating_sub(test_started) / 1_000) as u32;
+            let summary = Summary::new(test_peak, duration_ms, ending.reason);
+            acquisition.parked(|| test_log.end(summary));
+        }
+
+        // --- 2. Test start (immediate or scheduled) ---
+        // A gating input holds off any start, whoever asked for it.
+        if let (Some(at), Some(input)) = (sequencer.start_at(), digital.blocking()) {
+            let now = timer.get_counter().ticks();
+            if now >= at.0 && sequencer.cancel() {
+                let _ = uwriteln!(
+                    serial_wrapper,
+                    "Event: TEST_CANCELLED reason=input in={} t={}\r",
+                    input,
+                    now
+                );
+            }
+        }
+        if sequencer.poll(Micros(timer.get_counter().ticks())) {
+            // Other equipment follows these edges, so they go before
+            // anything slow such as the test log.
+            if test_outputs.contains(&true) {
+                let t = timer.get_counter().ticks();
+                for (out, pin) in trigger_pins.iter_mut().enumerate() {
+                    if test_outputs[out] {
+                        let _ = pin.set_high();
+                    }
+                }
+                test_outputs_up = true;
+                for out in (0..TRIGGER_OUTPUTS).filter(|&out| test_outputs[out]) {
+                    let _ = uwriteln!(serial_wrapper, "Event: SYNC out={} t={}\r", out, t);
+                }
+            }
+            frame_drift.restart();
+            noise.reset();
+            stats.reset();
+            // The load would swamp the noise.
+            if noise_check.take().is_some() {
+                let _ = uwriteln!(serial_wrapper, "Noise: state=aborted\r");
+            }
+            if let Some(zero) = &mut zero_track {
+                zero.reset();
+            }
+            serial_wrapper.dropped = 0;
+            next_qa = timer.get_counter() + QA_PERIOD_S.secs();
+            if let Some(detector) = &mut breaks {
+                detector.reset();
+            }
+            if let Some((detector, _)) = &mut slips {
+                detector.reset();
+            }
+            limits = run_limits.take().unwrap_or(Limits::NONE);
+            end_output = run_end_output.take();
+            work.reset();
+            work_running = work_source.is_some();
+            test_peak = None;
+            let number = acquisition.parked(|| test_log.start());
+            let now = timer.get_counter().ticks();
+            test_started = now;
+            let _ = uwrite!(
+                serial_wrapper,
+                "Event: TEST_START test={} t={}",
+                number,
+                now
+            );
+            write_unix(&mut serial_wrapper, &wall, now);
+            if !metadata.is_empty() {
+                write_meta(&mut serial_wrapper, &metadata);
+            }
+        }
+
+        // --- 3. Periodic self-verification during long tests ---
+        if matches!(sequencer.phase(), Phase::Preload | Phase::Running)
+            && timer.get_counter() >= next_qa
+        {
+            next_qa += QA_PERIOD_S.secs();
+            let vsys_raw: u16 = adc.read(&mut vsys_pin).unwrap_or(0);
+            let vsys_mv = vsys_raw as u32 * 3 * 3300 / 4096;
+            let tx_dropped = serial_wrapper.dropped;
+            let acq_dropped = acquisition.dropped();
+            let uptime_s = timer.get_counter().duration_since_epoch().to_secs();
+            let _ = uwrite!(serial_wrapper, "QA: uptime={}", uptime_s);
+            // Unknown measurements are left out rather than faked.
+            if let Some(ppm) = frame_drift.drift_ppm() {
+                let _ = uwrite!(serial_wrapper, " drift_ppm={}", ppm);
+            }
+            if let Some(sigma) = noise.sigma() {
+                let _ = uwrite!(serial_wrapper, " noise={}", sigma);
+            }
+            let _ = uwriteln!(
+                serial_wrapper,
+                " vsys_mv={} tx_dropped={} acq_dropped={} rejected={}\r",
+                vsys_mv,
+                tx_dropped,
+                acq_dropped,
+                filter.rejected()
+            );
+            noise.reset();
+        }
+
+        // --- End of a test: work done and the profile's output ---
+        if !sequencer.testing() && work_running {
+            work_running = false;
+            let _ = uwrite!(serial_wrapper, "Event: WORK work=");
+            write_milli(&mut serial_wrapper, work.millijoules());
+            let _ = uwriteln!(serial_wrapper, " t={}\r", timer.get_counter().ticks());
+        }
+        if !sequencer.testing() && test_outputs_up {
+            test_outputs_up = false;
+            for (out, pin) in trigger_pins.iter_mut().enumerate() {
+                if test_outputs[out] {
+                    let _ = pin.set_low();
+                }
+            }
+        }
+        if !sequencer.testing() {
+            if let Some((out, on)) = end_output.take() {
+                triggers[out as usize] = None;
+                let _ = trigger_pins[out as usize].set_state(on.into());
+                let _ = uwriteln!(
+                    serial_wrapper,
+                    "Event: OUTPUT out={} state={} t={}\r",
+                    out,
+                    on as u8,
+                    timer.get_counter().ticks()
+                );
+            }
+        }
+
+        // --- Bluetooth link ---
+        // A tablet that connects is told the epoch like a USB host; one
+        // lost mid-test with no USB host left stops the test, as a USB
+        // host lost does.
+        #[cfg(feature = "bluetooth")]
+        {
+            let now = timer.get_counter().ticks();
+            let high = bt_state.is_high().unwrap_or(false);
+            if let Some(up) = bt_link.update(high, now / 1_000) {
+                serial_wrapper.uart.set_linked(up);
+                let state = if up { "CONNECTED" } else { "DISCONNECTED" };
+                let _ = uwriteln!(serial_wrapper, "Event: BT_{} t={}\r", state, now);
+                if up {
+                    let _ = uwrite!(
+                        serial_wrapper,
+                        "Event: EPOCH session={:x} t={}",
+                        session_id,
+                        now
+                    );
+                    write_unix(&mut serial_wrapper, &wall, now);
+                } else if !host_attached.contains(&true) && sequencer.stop("host_lost", Micros(now))
+                {
+                    let _ = uwriteln!(
+                        serial_wrapper,
+                        "Event: TEST_STOP reason=host_lost t={}\r",
+                        now
+                    );
+                }
+            }
+        }
+
+        // --- Gain check, only while idle on input A ---
+        if let Some(check) = &mut gain_check {
+            if sequencer.phase() != Phase::Idle
+                || burst.recording()
+                || range.is_some()
+                || low_power.is_some()
+            {
+                // RANGE has already picked its own input.
+                if check.abort() && range.is_none() {
+                    let _ = acquisition.set_input(Input::A);
+                }
+            } else if check.begin(timer.get_counter().ticks() / 1_000) {
+                log::debug!("gain check started");
+            }
+        }
+
+        // --- Interlock ---
+        #[cfg(feature = "interlock")]
+        if interlock.expired(timer.get_counter().ticks() / 1_000) {
+            let _ = uwriteln!(
+                serial_wrapper,
+                "Event: LOCKED reason=timeout t={}\r",
+                timer.get_counter().ticks()
+            );
+        }
+
+        // --- Heartbeat ---
+        // Sent as control traffic, so it gets through while the stream is
+        // paused or capturing.
+        if heartbeat_ms != 0
+            && low_power.is_none()
+            && !burst.recording()
+            && timer.get_counter() >= next_heartbeat
+        {
+            next_heartbeat = timer.get_counter() + (heartbeat_ms as u64).millis();
+            let mut faults = 0;
+            match monitor.health() {
+                Health::Fault(_) => faults |= FAULT_SENSOR,
+                Health::Overload => faults |= FAULT_OVERLOAD,
+                Health::Ok => {}
+            }
+            if serial_wrapper.dropped != 0 {
+                faults |= FAULT_TX_DROPPED;
+            }
+            if acquisition.dropped() != 0 {
+                faults |= FAULT_ACQ_DROPPED;
+            }
+            let now = timer.get_counter();
+            let _ = uwrite!(
+                serial_wrapper,
+                "Heartbeat: test={} stream={} sensor={} buffer={}/{} faults={:x} outputs={:x} uptime={}",
+                sequencer.phase().as_str(),
+                stream.as_str(),
+                monitor.health().as_str(),
+                acquisition.buffered(),
+                Acquisition::CAPACITY,
+                faults,
+                output_bits(&mut trigger_pins),
+                now.duration_since_epoch().to_secs()
+            );
+            if let Some(temp) = temp {
+                let _ = uwrite!(serial_wrapper, " temp=");
+                write_milli(&mut serial_wrapper, temp.0 as i64);
+            }
+            let _ = uwriteln!(serial_wrapper, " t={}\r", now.ticks());
+        }
+
+        // --- ERRLOG? and CALLOG?, as fast as the link takes them ---
+        while let Some(list) = listing {
+            if serial_wrapper.room() < LIST_LINE_LEN {
+                break;
+            }
+            // Picked up by sequence number, so entries logged meanwhile
+            // don't shift the listing.
+            listing = match list {
+                Listing::ErrLog { after, n } => {
+                    match error_log
+                        .entries()
+                        .find(|e| after.is_none_or(|a| e.seq > a))
+                    {
+                        Some(e) => {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "ErrLog: seq={} session={:x} uptime={} code={} reason={} arg={}\r",
+                                e.seq,
+                                e.session,
+                                e.uptime_s,
+                                e.code,
+                                code_str(e.code),
+                                e.arg
+                            );
+                            Some(Listing::ErrLog {
+                                after: Some(e.seq),
+                                n: n + 1,
+                            })
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "ErrLog: end n={}\r", n);
+                            let _ = uwriteln!(serial_wrapper, "OK\r");
+                            None
+                        }
+                    }
+                }
+                Listing::CalLog { after, n } => {
+                    match cal_log.records().find(|r| after.is_none_or(|a| r.seq > a)) {
+                        Some(r) => {
+                            let _ = uwrite!(
+                                serial_wrapper,
+                                "CalLog: seq={} ch={} counts_per_kg={}",
+                                r.seq,
+                                r.channel,
+                                r.counts_per_kg
+                            );
+                            if let Some(unix) = r.unix {
+                                let _ = uwrite!(serial_wrapper, " unix={} utc=", unix);
+                                write_utc(&mut serial_wrapper, DateTime::from_unix(unix as u64));
+                            }
+                            if let Some(mass) = r.mass {
+                                let _ = uwrite!(serial_wrapper, " mass_g=");
+                                write_milli(&mut serial_wrapper, mass.0 as i64);
+                            }
+                            if let Some(operator) = &r.operator {
+                                let _ = uwrite!(serial_wrapper, " operator={}", operator.as_str());
+                            }
+                            if let Some(c) = r.creep {
+                                let _ = uwrite!(
+                                    serial_wrapper,
+                                    " creep_ppm={} creep_tau_s={}",
+                                    c.ppm,
+                                    c.tau_s
+                                );
+                            }
+                            let _ = uwriteln!(serial_wrapper, "\r");
+                            Some(Listing::CalLog {
+                                after: Some(r.seq),
+                                n: n + 1,
+                            })
+                        }
+                        None => {
+                            let _ = uwriteln!(serial_wrapper, "CalLog: end n={}\r", n);
+                            let _ = uwriteln!(serial_wrapper, "OK\r");
+                            None
+                        }
+                    }
+                }
+            };
+        }
+
+        // --- A finished burst, as fast as the link takes it ---
+        while burst.sending() && serial_wrapper.room() >= BURST_LINE_LEN {
+            let Some((t, raw)) = burst.pop() else {
+                break;
+            };
+            let counts = Counts(loading.polarity.apply(raw) - offset);
+            let _ = uwrite!(serial_wrapper, "Burst: ");
+            write_force(&mut serial_wrapper, counts, unit, scale, decimals);
+            let _ = uwriteln!(serial_wrapper, " t={} raw={}\r", t.0, counts.0);
+            if !burst.sending() {
+                let _ = uwriteln!(
+                    serial_wrapper,
+                    "Event: BURST_SENT t={}\r",
+                    timer.get_counter().ticks()
+                );
+            }
+        }
+
+        // --- 4. Drain readings from the acquisition core ---
+        while let Some(reading) = acquisition.next() {
+            watchdog.feed();
+            last_reading = timer.get_counter();
+            // A burst only stores what comes in; nothing is processed or
+            // sent until it is over.
+            if burst.recording() {
+                let value = reading.value.ok().filter(|_| reading.input == Input::A);
+                if let Some(summary) = burst.push(reading.t, value) {
+                    let _ = acquisition.set_rate(sample_sps);
+                    conversions.restart();
+                    filter.reset();
+                    let _ = uwriteln!(
+                        serial_wrapper,
+                        "Event: BURST_END samples={} missing={} full={} t={}\r",
+                        summary.samples,
+                        summary.missing,
+                        summary.full as u8,
+                        reading.t.0
+                    );
+                }
+                continue;
+            }
+            if let Some(check) = &mut gain_check {
+                match reading
+                    .value
+                    .ok()
+                    .and_then(|raw| check.push(reading.input, raw))
+                {
+                    Some(Action::Select(to)) => {
+                        let _ = acquisition.set_input(to);
+                    }
+                    Some(Action::Done(outcome)) => {
+                        log::info!(
+                            "gain check {}: high={} low={}",
+                            outcome.as_str(),
+                            outcome.high,
+                            outcome.low
+                        );
+                        if outcome.drifted {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: DRIFT_WARN high={} low={} diff={} tol={} t={}\r",
+                                outcome.high,
+                                outcome.low,
+                                outcome.diff(),
+                                check.tolerance(),
+                                reading.t.0
+                            );
+                        }
+                    }
+                    None => {}
+                }
+            }
+            // Only the gain check wants these.
+            if reading.input == Input::A64 {
+                continue;
+            }
+            // Still queued from B when `RANGE OFF` switched back.
+            if reading.input == Input::B && range.is_none() {
+                continue;
+            }
+            if reading.input != input {
+                input = reading.input;
+                // A tare must not average the two inputs together.
+                if tare.is_some() {
+                    tare = Some(Tare::new(Tare::DEFAULT_SAMPLES));
+                }
+            }
+            conversions.tick(reading.t);
+            // --- 5. Process Sample ---
+            let (value, change) = match reading.value {
+                Ok(value) => (Some(value), monitor.on_sample(value)),
+                Err(_) => (None, monitor.on_missing()),
+            };
+
+            frame_drift.update(timer.get_counter().ticks(), usb_frame_number());
+            let sample_time = reading.t;
+            for (i, (channel, sample)) in channels.iter_mut().zip(reading.extra).enumerate() {
+                if let Some(result) = sample.and_then(|s| channel.push(s)) {
+                    let _ = uwriteln!(
+                        serial_wrapper,
+                        "Event: TARE ch={} offset={} sigma={} n={} rejected={} t={}\r",
+                        i + 1,
+                        result.offset,
+                        result.sigma,
+                        result.used,
+                        result.rejected,
+                        sample_time.0
+                    );
+                }
+            }
+            // Auxiliary channels are sampled as each reading is processed.
+            let mut aux_values = [None; 3];
+            for (ch, (scale, out)) in aux.iter().zip(aux_values.iter_mut()).enumerate() {
+                let Some(scale) = scale else { continue };
+                let mut sum = 0;
+                for _ in 0..AUX_OVERSAMPLE {
+                    let raw: u16 = match ch {
+                        0 => adc.read(&mut aux0_pin),
+                        1 => adc.read(&mut aux1_pin),
+                        _ => adc.read(&mut aux2_pin),
+                    }
+                    .unwrap_or(0);
+                    sum += raw as u32;
+                }
+                let raw = (sum / AUX_OVERSAMPLE) as u16;
+                *out = Some(scale.apply(adc_to_millivolts(raw)));
+            }
+            if let Ok(raw) = adc.read(&mut temp_sensor) {
+                temp = Some(MilliCelsius(temp_average.push(adc_to_millicelsius(raw).0)));
+            }
+            // So are the logic inputs; a gating one going inactive stops
+            // the test.
+            #[cfg(feature = "digital-inputs")]
+            let levels = input_pins.iter_mut().enumerate().fold(0, |bits, (n, pin)| {
+                bits | (pin.is_high().unwrap_or(true) as u8) << n
+            });
+            #[cfg(not(feature = "digital-inputs"))]
+            let levels = 0;
+            if let Some(state) = digital.update(levels) {
+                let _ = uwriteln!(
+                    serial_wrapper,
+                    "Event: DIN state={} t={}\r",
+                    state,
+                    sample_time.0
+                );
+            }
+            if digital.blocking().is_some() && sequencer.stop("input", sample_time) {
+                let _ = uwriteln!(
+                    serial_wrapper,
+                    "Event: TEST_STOP reason=input t={}\r",
+                    sample_time.0
+                );
+            }
+            if let Some(health) = change {
+                if let Some(code) = health_code(health) {
+                    let now = timer.get_counter();
+                    let repeat = last_logged.is_some_and(|(last, at)| {
+                        last == code && now < at + ERRLOG_HOLDOFF_S.secs()
+                    });
+                    if !repeat {
+                        last_logged = Some((code, now));
+                        let uptime_s = (now.ticks() / 1_000_000) as u32;
+                        acquisition.parked(|| error_log.append(session_id, uptime_s, code, 0));
+                    }
+                }
+                match health {
+                    Health::Ok => {
+                        let _ = uwriteln!(serial_wrapper, "Event: SENSOR_OK t={}\r", sample_time.0);
+                    }
+                    Health::Overload => {
+                        #[cfg(feature = "buzzer")]
+                        buzzer.sound(AudibleAlarm::Overload, timer.get_counter());
+                        let _ = uwriteln!(serial_wrapper, "Event: OVERLOAD t={}\r", sample_time.0);
+                    }
+                    Health::Fault(kind) => {
+                        #[cfg(feature = "buzzer")]
+                        buzzer.sound(AudibleAlarm::Fault, timer.get_counter());
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: SENSOR_FAULT reason={} t={}\r",
+                            kind.as_str(),
+                            sample_time.0
+                        );
+                    }
+                }
+                // A pinned or dead sensor invalidates whatever test is
+                // running, and no test starts until it recovers.
+                if health == Health::Ok {
+                    sequencer.recover();
+                } else {
+                    match sequencer.fault(health.as_str(), sample_time) {
+                        Phase::Preload | Phase::Running | Phase::Holding => {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_STOP reason={} t={}\r",
+                                health.as_str(),
+                                sample_time.0
+                            );
+                        }
+                        Phase::Armed => {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_CANCELLED t={}\r",
+                                sample_time.0
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            // Don't stream garbage while the front end is unhealthy.
+            if let Some(value) = value
+                .filter(|_| monitor.health() == Health::Ok)
+                .map(|v| loading.polarity.apply(v))
+            {
+                if let Some(result) = tare.as_mut().and_then(|t| t.push(value)) {
+                    tare = None;
+                    match input {
+                        Input::A => offset = result.offset,
+                        Input::B => b_offset = Some(result.offset),
+                        Input::A64 => {}
+                    }
+                    temp_ref = temp;
+                    filter.reset();
+                    // The old peak was measured from the old zero.
+                    peak.reset();
+                    if let Some(creep) = &mut creep {
+                        creep.reset();
+                    }
+                    if let Some(zero) = &mut zero_track {
+                        zero.reset();
+                    }
+                    led.flash(timer.get_counter(), TARE_FLASH_MS);
+                    let _ = uwriteln!(
+                        serial_wrapper,
+                        "Event: TARE offset={} sigma={} n={} rejected={} t={}\r",
+                        result.offset,
+                        result.sigma,
+                        result.used,
+                        result.rejected,
+                        sample_time.0
+                    );
+                }
+                let mut clean = match (input, &range) {
+                    (Input::B, Some(range)) => {
+                        range.to_a(Counts(value - *b_offset.get_or_insert(value)))
+                    }
+                    _ => Counts(value - offset),
+                };
+                if let (Some(temp), Some(reference)) = (temp, temp_ref) {
+                    let comp = TempComp {
+                        coeff_milli: temp_coeff,
+                        reference,
+                    };
+                    clean = comp.correct(clean, temp);
+                }
+                if let Some(creep) = &mut creep {
+                    clean = creep.correct(clean, sample_time.0);
+                }
+                if let Some(next) = range.as_mut().and_then(|r| r.push(clean)) {
+                    if acquisition.set_input(next).is_ok() {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: RANGE input={} t={}\r",
+                            next.as_str(),
+                            sample_time.0
+                        );
+                    }
+                }
+                // Off-axis rejection: report both cells as one reading.
+                let secondary = channels.first().and_then(|c| c.latest());
+                if let (Some(dual), Some(secondary)) = (&mut dual, secondary) {
+                    let (combined, change) = dual.push(clean, secondary);
+                    match change {
+                        Some(true) => {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: CELL_MISMATCH a={} b={} t={}\r",
+                                clean.0,
+                                secondary.0,
+                                sample_time.0
+                            );
+                        }
+                        Some(false) => {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: CELL_MATCH t={}\r",
+                                sample_time.0
+                            );
+                        }
+                        None => {}
+                    }
+                    clean = combined;
+                }
+                // Zero tracking would eat a real load, so it only runs idle.
+                if !sequencer.testing() {
+                    if let Some(step) = zero_track.as_mut().and_then(|z| z.push(clean.0)) {
+                        let zero = match (input, &range) {
+                            (Input::B, Some(range)) => b_offset
+                                .insert(b_offset.unwrap_or(value) + range.to_b(Counts(step)).0),
+                            _ => {
+                                offset += step;
+                                &mut offset
+                            }
+                        };
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: ZERO_TRACK offset={} t={}\r",
+                            zero,
+                            sample_time.0
+                        );
+                    }
+                }
+                noise.push(clean.0);
+                let filtered = Counts(filter.push(clean.0));
+                peak.push(filtered, sample_time);
+                last_force = Some(filtered.0);
+                // Outputs switch here, on the sample itself, so equipment
+                // gated by them never waits on the host.
+                for (out, (trigger, pin)) in triggers.iter_mut().zip(&mut trigger_pins).enumerate()
+                {
+                    let Some(trigger) = trigger else { continue };
+                    if let Some(active) = trigger.push(filtered.0) {
+                        let _ = pin.set_state(active.into());
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: TRIGGER out={} state={} t={}\r",
+                            out,
+                            active as u8,
+                            sample_time.0
+                        );
+                    }
+                }
+                let load = loading.load(filtered.0);
+                // Slack is taken up: extension counts from here.
+                if sequencer.push(load) {
+                    #[cfg(feature = "extensometer")]
+                    extensometer.zero(quadrature.count());
+                    let _ = uwriteln!(
+                        serial_wrapper,
+                        "Event: PRELOADED force={} t={}\r",
+                        filtered.0,
+                        sample_time.0
+                    );
+                }
+                if sequencer.phase() == Phase::Running {
+                    if test_peak.is_none_or(|p| loading.load(filtered.0) > loading.load(p)) {
+                        test_peak = Some(filtered.0);
+                    }
+                    if let (Some((ch, _)), Some(scale)) = (work_source, scale) {
+                        if let Some(extension_um) = aux_values[ch] {
+                            work.push(scale.force(filtered), extension_um);
+                        }
+                    }
+                    if let Some(max) = breaks.as_mut().and_then(|b| b.push(load)) {
+                        #[cfg(feature = "buzzer")]
+                        buzzer.sound(AudibleAlarm::Break, timer.get_counter());
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: BREAK peak={} force={} t={}\r",
+                            max,
+                            filtered.0,
+                            sample_time.0
+                        );
+                    }
+                    if let Some((slip, stop)) = slips
+                        .as_mut()
+                        .and_then(|(d, stop)| d.push(load).map(|s| (s, *stop)))
+                    {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: SLIP peak={} low={} t={}\r",
+                            slip.from,
+                            slip.low,
+                            sample_time.0
+                        );
+                        if stop && sequencer.stop("slip", sample_time) {
+                            let _ = uwriteln!(
+                                serial_wrapper,
+                                "Event: TEST_STOP reason=slip t={}\r",
+                                sample_time.0
+                            );
+                        }
+                    }
+                }
+                // Limits guard the preload too.
+                if matches!(sequencer.phase(), Phase::Preload | Phase::Running) {
+                    let elapsed_s = sample_time.0.saturating_sub(test_started) / 1_000_000;
+                    if let Some(reason) = limits.exceeded(filtered.0, elapsed_s) {
+                        sequencer.stop(reason, sample_time);
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: TEST_STOP reason={} t={}\r",
+                            reason,
+                            sample_time.0
+                        );
+                    }
+                }
+                if let Some(report) = noise_check.as_mut().and_then(|c| c.push(clean.0)) {
+                    noise_check = None;
+                    let _ = uwrite!(
+                        serial_wrapper,
+                        "Noise: samples={} sigma={} pp={}",
+                        report.samples,
+                        report.sigma,
+                        report.peak_to_peak
+                    );
+                    if let Some(scale) = scale.filter(|_| unit != Unit::Raw) {
+                        let _ = uwrite!(serial_wrapper, " sigma_force=");
+                        write_force(
+                            &mut serial_wrapper,
+                            Counts(report.sigma as i32),
+                            unit,
+                            Some(scale),
+                            decimals,
+                        );
+                        let _ = uwrite!(serial_wrapper, " pp_force=");
+                        write_force(
+                            &mut serial_wrapper,
+                            Counts(report.peak_to_peak as i32),
+                            unit,
+                            Some(scale),
+                            decimals,
+                        );
+                        let _ = uwrite!(serial_wrapper, " unit={}", unit.as_str());
+                    }
+                    let _ = uwrite!(serial_wrapper, " effective_bits=");
+                    write_fixed(&mut serial_wrapper, report.effective_bits() as i64, 1);
+                    let _ = uwrite!(serial_wrapper, " noise_free_bits=");
+                    write_fixed(&mut serial_wrapper, report.noise_free_bits() as i64, 1);
+                    let _ = uwriteln!(serial_wrapper, "\r");
+                }
+                if let Some(point) = cal_check.push(clean) {
+                    let _ = uwriteln!(
+                        serial_wrapper,
+                        "CalPoint: {} mass_mg={} mean={} sigma={}\r",
+                        cal_check.points().len(),
+                        point.mass.0,
+                        point.mean.0,
+                        point.sigma
+                    );
+                    let _ = uwriteln!(
+                        serial_wrapper,
+                        "Prompt: place next weight, CALCHECK POINT <g> or CALCHECK END\r"
+                    );
+                }
+                let d_dt = rate.as_mut().and_then(|r| r.push(filtered, sample_time));
+                // A held test keeps acquiring but logs nothing.
+                if sequencer.phase() == Phase::Holding {
+                    continue;
+                }
+                stats.push(filtered.0);
+                // An armed capture holds back everything outside its window,
+                // keeping only what it may need as pre-trigger history.
+                let step = capture.as_mut().map(|c| c.push(filtered, sample_time));
+                let pre_trigger = capture.map_or(0, |c| c.pre_trigger());
+                match step {
+                    Some(Step::Waiting) if pre_trigger == 0 => continue,
+                    _ if stream == Stream::Paused => continue,
+                    Some(Step::Quiet) => continue,
+                    Some(Step::Ended { samples }) => {
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: CAPTURE_END samples={} t={}\r",
+                            samples,
+                            sample_time.0
+                        );
+                        continue;
+                    }
+                    _ => {}
+                }
+                // Sample data yields to queued responses so commands such as
+                // STOP are still acknowledged when the link is saturated.
+                // Marks made since the last sample go on this one.
+                while let Some(label) = marks.pop() {
+                    let _ = uwriteln!(
+                        serial_wrapper,
+                        "Event: MARK seq={} t={}\r",
+                        history.next_seq(),
+                        sample_time.0
+                    );
+                    let _ = uwriteln!(serial_wrapper, "Mark: {}\r", label.as_str());
+                }
+                let encoder: &mut dyn Encoder = match protocol {
+                    Protocol::Ascii => &mut ascii,
+                    Protocol::Binary => &mut binary,
+                };
+                encoder.begin();
+                if columns.has(Column::Force) {
+                    let value = force_value(filtered, unit, scale, decimals);
+                    encoder.field(Column::Force, 0, value);
+                }
+                if columns.has(Column::Raw) {
+                    encoder.field(Column::Raw, 0, Value::whole(clean.0 as i64));
+                }
+                if range.is_some() && columns.has(Column::Range) {
+                    encoder.field(Column::Range, 0, Value::Input(input));
+                }
+                if columns.has(Column::T) {
+                    encoder.field(Column::T, 0, Value::whole(sample_time.0 as i64));
+                }
+                if columns.has(Column::Seq) {
+                    encoder.field(Column::Seq, 0, Value::whole(history.next_seq() as i64));
+                }
+                if let Some(d_dt) = d_dt.filter(|_| columns.has(Column::Rate)) {
+                    // The scale is linear, so counts/s convert like counts.
+                    let value = force_value(Counts(d_dt.0), unit, scale, decimals);
+                    encoder.field(Column::Rate, 0, value);
+                }
+                // Extra channels print raw counts until they are calibrated.
+                for (i, channel) in channels.iter().enumerate() {
+                    if let Some(value) = channel.latest().filter(|_| columns.has(Column::Ch)) {
+                        let value = force_value(value, unit, channel.scale(), decimals);
+                        encoder.field(Column::Ch, i as u8 + 1, value);
+                    }
+                }
+                if unit != Unit::Raw && columns.has(Column::Unit) {
+                    encoder.field(Column::Unit, 0, Value::Unit(unit));
+                }
+                for (ch, value) in aux_values.iter().enumerate() {
+                    if let Some(value) = value.filter(|_| columns.has(Column::Aux)) {
+                        encoder.field(Column::Aux, ch as u8, Value::milli(value));
+                    }
+                }
+                #[cfg(feature = "extensometer")]
+                if columns.has(Column::Ext) {
+                    let um = extensometer.extension_um(quadrature.count());
+                    encoder.field(Column::Ext, 0, Value::milli(um));
+                }
+                if work_running
+                    && work_source.is_some_and(|(_, stream)| stream)
+                    && columns.has(Column::Work)
+                {
+                    encoder.field(Column::Work, 0, Value::milli(work.millijoules()));
+                }
+                if let Some(temp) = temp.filter(|_| columns.has(Column::Temp)) {
+                    encoder.field(Column::Temp, 0, Value::milli(temp.0 as i64));
+                }
+                #[cfg(feature = "chamber")]
+                if let Some(temp) = chamber.temp().filter(|_| columns.has(Column::Chamber)) {
+                    encoder.field(Column::Chamber, 0, Value::milli(temp.0 as i64));
+                }
+                if digital.any() && columns.has(Column::Din) {
+                    encoder.field(Column::Din, 0, Value::whole(digital.state() as i64));
+                }
+                let line = encoder.finish();
+                // Kept for REPLAY; while one is running it sends this too, in
+                // order, once it catches up.
+                history.push(line);
+                match step {
+                    Some(Step::Waiting) => {}
+                    _ if stream != Stream::Live => {}
+                    Some(Step::Started) => {
+                        // Send the held pre-trigger lines and this one as a
+                        // replay, so they go out in order.
+                        let seq = history.next_seq().wrapping_sub(1);
+                        let held = seq.wrapping_sub(history.oldest().unwrap_or(seq));
+                        let pre = pre_trigger.min(held);
+                        let _ = uwriteln!(
+                            serial_wrapper,
+                            "Event: CAPTURE_START force={} pre={} t={}\r",
+                            filtered.0,
+                            pre,
+                            sample_time.0
+                        );
+                        replay = Some(seq.wrapping_sub(pre));
+                    }
+                    // Decimation thins only what goes out live.
+                    _ if replay.is_none() && !decimate.push() => {}
+                    _ if replay.is_none() => {
+                        serial_wrapper.bulk = true;
+                        serial_wrapper.write_bytes(line);
+                        serial_wrapper.bulk = false;
+                        latency.record(timer.get_counter().ticks().saturating_sub(sample_time.0));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // --- 6. Status LED ---
+        let indication = match monitor.health() {
+            Health::Fault(_) => Indication::Fault,
+            Health::Overload => Indication::Overload,
+            Health::Ok if low_power.is_some() => Indication::Asleep,
+            Health::Ok if sequencer.testing() => Indication::Running,
+            Health::Ok if host_attached[0] => Indication::Streaming,
+            Health::Ok => Indication::Idle,
+        };
+        let blink_due = led.update(timer.get_counter(), indication);
+        #[cfg(feature = "buzzer")]
+        let buzzer_due = buzzer.update(timer.get_counter());
+        #[cfg(not(feature = "buzzer"))]
+        let buzzer_due = None;
+
+        // --- 7. Display ---
+        #[cfg(feature = "oled")]
+        let display_due = (display.is_some() && low_power.is_none()).then_some(next_display);
+        #[cfg(feature = "oled")]
+        if let Some(oled) = display
+            .as_mut()
+            .filter(|_| low_power.is_none() && timer.get_counter() >= next_display)
+        {
+            draw_display(
+                &mut frame,
+                sequencer.phase(),
+                monitor.health(),
+                last_force.map(Counts),
+                loading.peak(&peak).map(|max| max.value),
+                unit,
+                |line, counts| write_force(line, counts, unit, scale, decimals),
+            );
+            let _ = oled.show(&frame);
+            next_display = timer.get_counter() + DISPLAY_PERIOD_MS.millis();
+        }
+        #[cfg(not(feature = "oled"))]
+        let display_due = None;
 
-        // --- 2. Check Timer (Non-blocking!) ---
-        if timer.get_counter() >= next_read {
-            // Schedule next read
-            next_read = timer.get_counter() + 100u64.millis();
+        // --- Chamber heater ---
+        #[cfg(feature = "chamber")]
+        let chamber_due =
+            Some(chamber.update(timer.get_counter(), || adc.read(&mut aux2_pin).unwrap_or(0)));
+        #[cfg(not(feature = "chamber"))]
+        let chamber_due = None;
 
-            // --- 3. Read Sensor ---
-            if let Ok(value) = load_cell.retrieve() {
-                let clean_value = value - offset;
-                let _ = uwriteln!(serial_wrapper, "Force: {}\r", clean_value);
+        // --- 8. Sleep until the next event ---
+        #[cfg(feature = "uart-stream")]
+        serial_wrapper.uart.pump();
+        let qa_due =
+            matches!(sequencer.phase(), Phase::Preload | Phase::Running).then_some(next_qa);
+        let heartbeat_due = (heartbeat_ms != 0 && low_power.is_none()).then_some(next_heartbeat);
+        // POWER LOW's readings are further apart than the watchdog allows,
+        // so it is fed here too until the next one is overdue.
+        let watchdog_due = low_power.map(|s| {
+            let now = timer.get_counter();
+            let overdue =
+                last_reading + (s as u64 * 1_000_000 + WATCHDOG_TIMEOUT_US as u64).micros();
+            if now < overdue {
+                watchdog.feed();
             }
+            now + WATCHDOG_FEED_MS.millis()
+        });
+        #[cfg(feature = "interlock")]
+        let relock_due = interlock
+            .relock_at()
+            .map(|ms| bsp::hal::timer::Instant::from_ticks(ms * 1_000));
+        #[cfg(not(feature = "interlock"))]
+        let relock_due = None;
+        #[cfg(feature = "modbus")]
+        let frame_due = modbus.due();
+        #[cfg(not(feature = "modbus"))]
+        let frame_due = None;
+        #[cfg(feature = "encoder")]
+        let panel_due = panel.due();
+        #[cfg(feature = "tare-button")]
+        let panel_due = tare_button.due();
+        #[cfg(not(any(feature = "encoder", feature = "tare-button")))]
+        let panel_due = None;
+        let start_due = sequencer
+            .start_at()
+            .map(|at| bsp::hal::timer::Instant::from_ticks(at.0));
+        let wake_at = [
+            watchdog_due,
+            start_due,
+            qa_due,
+            heartbeat_due,
+            relock_due,
+            chamber_due,
+            blink_due,
+            frame_due,
+            display_due,
+            panel_due,
+            buzzer_due,
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        if let Some(at) = wake_at {
+            let _ = alarm.schedule_at(at);
         }
+        loop_time.record(timer.get_counter().ticks() - woke);
+        cortex_m::asm::wfe();
+        alarm.clear_interrupt();
+        cortex_m::peripheral::NVIC::unpend(pac::Interrupt::TIMER_IRQ_0);
+        cortex_m::peripheral::NVIC::unpend(pac::Interrupt::USBCTRL_IRQ);
+        cortex_m::peripheral::NVIC::unpend(pac::Interrupt::UART0_IRQ);
+        cortex_m::peripheral::NVIC::unpend(pac::Interrupt::IO_IRQ_BANK0);
+        // Only as a target; I2C1 as a controller leaves its FIFO-empty
+        // interrupt raised.
+        #[cfg(feature = "i2c-target")]
+        cortex_m::peripheral::NVIC::unpend(pac::Interrupt::I2C1_IRQ);
     }
 }
 
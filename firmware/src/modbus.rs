@@ -0,0 +1,245 @@
+//! Modbus RTU server on UART0 for PLCs on the production line.
+//!
+//! GPIO0 is TX, GPIO1 RX and GPIO2 drives the RS-485 transceiver's DE/RE
+//! pair, high while replying. The line runs 19200 baud, 8E1.
+//!
+//! Input registers, 32-bit values high word first:
+//!
+//! | Address | Value                                                      |
+//! |---------|------------------------------------------------------------|
+//! | 0–1     | filtered, tared force in counts                            |
+//! | 2–3     | force in thousandths of the current unit (counts if raw)   |
+//! | 4–5     | peak force in counts since tare or peak reset              |
+//! | 6       | status bits: 0 running, 1 paused, 2 start scheduled,       |
+//! |         | 3 overload, 4 sensor fault, 5 calibrated                   |
+//! | 7–8     | sequence number of the next `Force:` line                  |
+//!
+//! Forces read `i32::MIN` until there is a reading. Holding registers:
+//!
+//! | Address | Value                                                  |
+//! |---------|--------------------------------------------------------|
+//! | 0       | command: write 1 start, 2 stop, 3 tare, 4 peak reset   |
+//! | 1       | unit: 0 raw, 1 N, 2 kgf, 3 lbf, 4 g                    |
+//!
+//! A unit write during a test is refused with exception 6, server busy.
+//!
+//! The rig has no displacement sensor, so no register maps one.
+
+use crate::board::{Rs485Enable, UartRx, UartTx};
+use bsp::hal::{
+    fugit::{HertzU32, RateExtU32},
+    gpio::{FunctionSioOutput, FunctionUart, Pin, PullDown},
+    pac,
+    timer::Instant,
+    uart::{DataBits, Enabled, Parity, StopBits, UartConfig, UartPeripheral},
+};
+use embedded_hal::digital::OutputPin;
+use fugit::ExtU64;
+use rp_pico as bsp;
+use tensile_core::modbus::{self, Exception, Registers, MAX_FRAME};
+use tensile_core::units::Unit;
+
+/// Server address used until `MODBUS` picks another.
+pub const DEFAULT_UNIT: u8 = 1;
+
+const BAUD: u32 = 19_200;
+
+/// A frame ends after 3.5 character times of silence (11 bits each).
+const FRAME_GAP_US: u64 = 35 * 11 * 1_000_000 / 10 / BAUD as u64;
+
+/// Holding register 1, by value.
+const UNITS: [Unit; 5] = [
+    Unit::Raw,
+    Unit::Newton,
+    Unit::KilogramForce,
+    Unit::PoundForce,
+    Unit::Gram,
+];
+
+pub type UartPins = (
+    Pin<UartTx, FunctionUart, PullDown>,
+    Pin<UartRx, FunctionUart, PullDown>,
+);
+pub type DriverEnable = Pin<Rs485Enable, FunctionSioOutput, PullDown>;
+
+/// Actions requested through the command register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Start,
+    Stop,
+    Tare,
+    PeakReset,
+}
+
+/// Snapshot of the tester served to one request. Writes land here for the
+/// main loop to apply.
+pub struct Image {
+    pub force: Option<i32>,
+    pub force_unit: Option<i32>,
+    pub peak: Option<i32>,
+    pub status: u16,
+    pub seq: u32,
+    pub unit: Unit,
+    pub calibrated: bool,
+    pub testing: bool,
+    pub control: Option<Control>,
+}
+
+impl Image {
+    pub const RUNNING: u16 = 1 << 0;
+    pub const PAUSED: u16 = 1 << 1;
+    pub const SCHEDULED: u16 = 1 << 2;
+    pub const OVERLOAD: u16 = 1 << 3;
+    pub const FAULT: u16 = 1 << 4;
+    pub const CALIBRATED: u16 = 1 << 5;
+}
+
+/// High word for even offsets, low word for odd.
+fn half(value: u32, addr: u16) -> u16 {
+    if addr.is_multiple_of(2) {
+        (value >> 16) as u16
+    } else {
+        value as u16
+    }
+}
+
+impl Registers for Image {
+    fn input(&self, addr: u16) -> Option<u16> {
+        let force = |v: Option<i32>| v.unwrap_or(i32::MIN) as u32;
+        Some(match addr {
+            0..=1 => half(force(self.force), addr),
+            2..=3 => half(force(self.force_unit), addr),
+            4..=5 => half(force(self.peak), addr),
+            6 => self.status,
+            7..=8 => half(self.seq, addr + 1),
+            _ => return None,
+        })
+    }
+
+    fn holding(&self, addr: u16) -> Option<u16> {
+        match addr {
+            0 => Some(0),
+            1 => UNITS.iter().position(|&u| u == self.unit).map(|i| i as u16),
+            _ => None,
+        }
+    }
+
+    fn write_holding(&mut self, addr: u16, value: u16) -> Result<(), Exception> {
+        match (addr, value) {
+            (0, 1) => self.control = Some(Control::Start),
+            (0, 2) => self.control = Some(Control::Stop),
+            (0, 3) => self.control = Some(Control::Tare),
+            (0, 4) => self.control = Some(Control::PeakReset),
+            (1, _) if self.testing => return Err(Exception::ServerDeviceBusy),
+            (1, _) => {
+                let unit = *UNITS
+                    .get(value as usize)
+                    .ok_or(Exception::IllegalDataValue)?;
+                if unit != Unit::Raw && !self.calibrated {
+                    return Err(Exception::IllegalDataValue);
+                }
+                self.unit = unit;
+            }
+            _ => return Err(Exception::IllegalDataValue),
+        }
+        Ok(())
+    }
+}
+
+pub struct Server {
+    uart: UartPeripheral<Enabled, pac::UART0, UartPins>,
+    de: DriverEnable,
+    unit: Option<u8>,
+    rx: [u8; MAX_FRAME],
+    rx_len: usize,
+    /// The frame overran the buffer or had a framing/parity error.
+    corrupt: bool,
+    last_rx: Option<Instant>,
+}
+
+impl Server {
+    pub fn new(
+        uart: pac::UART0,
+        pins: UartPins,
+        de: DriverEnable,
+        resets: &mut pac::RESETS,
+        peripheral_clock: HertzU32,
+    ) -> Self {
+        let config = UartConfig::new(
+            BAUD.Hz(),
+            DataBits::Eight,
+            Some(Parity::Even),
+            StopBits::One,
+        );
+        let mut uart = UartPeripheral::new(uart, pins, resets)
+            .enable(config, peripheral_clock)
+            .unwrap();
+        // Received bytes wake the main loop out of WFE.
+        uart.enable_rx_interrupt();
+        Self {
+            uart,
+            de,
+            unit: Some(DEFAULT_UNIT),
+            rx: [0; MAX_FRAME],
+            rx_len: 0,
+            corrupt: false,
+            last_rx: None,
+        }
+    }
+
+    /// Server address, or `None` while switched off.
+    pub fn unit(&self) -> Option<u8> {
+        self.unit
+    }
+
+    pub fn set_unit(&mut self, unit: Option<u8>) {
+        self.unit = unit;
+    }
+
+    /// When a partly received frame will be complete.
+    pub fn due(&self) -> Option<Instant> {
+        self.last_rx.map(|at| at + FRAME_GAP_US.micros())
+    }
+
+    /// Collect received bytes and answer a frame once the line goes quiet.
+    /// `image` is only built when there is a request to serve; it is
+    /// returned with any writes applied.
+    ///
+    /// The reply is sent blocking. The map is small enough that the longest
+    /// one takes about 12 ms at 19200 baud.
+    pub fn serve(&mut self, now: Instant, image: impl FnOnce() -> Image) -> Option<Image> {
+        let mut buf = [0u8; 32];
+        loop {
+            let count = match self.uart.read_raw(&mut buf) {
+                Ok(count) => count,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(_)) => {
+                    self.corrupt = true;
+                    0
+                }
+            };
+            let room = MAX_FRAME - self.rx_len;
+            self.corrupt |= count > room;
+            let count = count.min(room);
+            self.rx[self.rx_len..self.rx_len + count].copy_from_slice(&buf[..count]);
+            self.rx_len += count;
+            self.last_rx = Some(now);
+        }
+        if self.due().is_none_or(|due| now < due) {
+            return None;
+        }
+        self.last_rx = None;
+        let len = core::mem::replace(&mut self.rx_len, 0);
+        let corrupt = core::mem::replace(&mut self.corrupt, false);
+        let unit = self.unit.filter(|_| !corrupt)?;
+        let mut image = image();
+        let mut reply = [0; MAX_FRAME];
+        if let Some(n) = modbus::respond(&self.rx[..len], unit, &mut image, &mut reply) {
+            let _ = self.de.set_high();
+            self.uart.write_full_blocking(&reply[..n]);
+            while self.uart.uart_is_busy() {}
+            let _ = self.de.set_low();
+        }
+        Some(image)
+    }
+}
@@ -0,0 +1,81 @@
+//! SSD1306 128×64 OLED on I2C1 (GPIO6 SDA, GPIO7 SCL), built with the
+//! `oled` feature.
+
+use embedded_hal::i2c::I2c;
+use tensile_core::display::{Frame, PAGES};
+
+const SSD1306_ADDR: u8 = 0x3C;
+/// Control bytes: the rest of the write is commands, or display data.
+const CONTROL_COMMAND: u8 = 0x00;
+const CONTROL_DATA: u8 = 0x40;
+
+/// Display off, 128×64 with the charge pump on, page addressing scanned so
+/// that page 0 is at the top with the header pins up, then display on.
+const INIT: &[u8] = &[
+    0xAE, // display off
+    0xD5, 0x80, // clock divide
+    0xA8, 0x3F, // multiplex 64
+    0xD3, 0x00, // no display offset
+    0x40, // start line 0
+    0x8D, 0x14, // charge pump on
+    0x20, 0x02, // page addressing
+    0xA1, // column 127 is SEG0
+    0xC8, // scan COM63 to COM0
+    0xDA, 0x12, // alternative COM pins
+    0x81, 0xCF, // contrast
+    0xD9, 0xF1, // precharge
+    0xDB, 0x40, // VCOMH deselect level
+    0xA4, // show RAM
+    0xA6, // not inverted
+    0xAF, // display on
+];
+
+/// Bytes sent per data write, after the control byte.
+const CHUNK: usize = 32;
+
+pub struct Oled<I2C> {
+    i2c: I2C,
+    /// What the panel is showing, so only changed pages are resent.
+    shown: Frame,
+    /// The panel RAM is unknown until the first full update.
+    stale: bool,
+}
+
+impl<I2C: I2c> Oled<I2C> {
+    /// Set the panel up, or `None` if nothing answers at its address.
+    pub fn detect(i2c: I2C) -> Option<Self> {
+        let mut oled = Self {
+            i2c,
+            shown: Frame::new(),
+            stale: true,
+        };
+        oled.command(INIT).ok()?;
+        Some(oled)
+    }
+
+    /// Send the pages of `frame` that differ from the panel. Blocks for
+    /// about 3 ms per page at 400 kHz.
+    pub fn show(&mut self, frame: &Frame) -> Result<(), I2C::Error> {
+        for page in 0..PAGES {
+            if !self.stale && frame.page(page) == self.shown.page(page) {
+                continue;
+            }
+            // Page start address, then column 0.
+            self.command(&[0xB0 | page as u8, 0x00, 0x10])?;
+            for chunk in frame.page(page).chunks(CHUNK) {
+                let mut buf = [CONTROL_DATA; CHUNK + 1];
+                buf[1..=chunk.len()].copy_from_slice(chunk);
+                self.i2c.write(SSD1306_ADDR, &buf[..=chunk.len()])?;
+            }
+        }
+        self.shown = frame.clone();
+        self.stale = false;
+        Ok(())
+    }
+
+    fn command(&mut self, bytes: &[u8]) -> Result<(), I2C::Error> {
+        let mut buf = [CONTROL_COMMAND; CHUNK + 1];
+        buf[1..=bytes.len()].copy_from_slice(bytes);
+        self.i2c.write(SSD1306_ADDR, &buf[..=bytes.len()])
+    }
+}
@@ -0,0 +1,99 @@
+//! Front-panel inputs: the push button, alone with the `tare-button`
+//! feature or under a rotary encoder with `encoder`.
+//!
+//! Inputs are pulled up and switch to ground. Their edge interrupts are
+//! enabled, but not in the NVIC, only so that turning or pressing wakes the
+//! main loop.
+
+use bsp::hal::{
+    gpio::{FunctionSioInput, Interrupt, Pin, PinId, PullUp},
+    timer::Instant,
+};
+use embedded_hal::digital::InputPin;
+use rp_pico as bsp;
+#[cfg(feature = "encoder")]
+use tensile_core::input::Quadrature;
+use tensile_core::input::{Button, Press};
+use tensile_core::quantity::Micros;
+
+use crate::board::Button as ButtonPin;
+#[cfg(feature = "encoder")]
+use crate::board::{EncoderA, EncoderB};
+
+type Input<Id> = Pin<Id, FunctionSioInput, PullUp>;
+
+fn watch<Id: PinId>(pin: &mut Input<Id>) {
+    for edge in [Interrupt::EdgeLow, Interrupt::EdgeHigh] {
+        pin.clear_interrupt(edge);
+        pin.set_interrupt_enabled(edge, true);
+    }
+}
+
+fn acknowledge<Id: PinId>(pin: &mut Input<Id>) {
+    pin.clear_interrupt(Interrupt::EdgeLow);
+    pin.clear_interrupt(Interrupt::EdgeHigh);
+}
+
+pub struct PushButton {
+    pin: Input<ButtonPin>,
+    button: Button,
+}
+
+impl PushButton {
+    pub fn new(mut pin: Input<ButtonPin>) -> Self {
+        watch(&mut pin);
+        Self {
+            pin,
+            button: Button::new(),
+        }
+    }
+
+    /// Any press completed since the last call.
+    pub fn poll(&mut self, now: Instant) -> Option<Press> {
+        acknowledge(&mut self.pin);
+        let pressed = self.pin.is_low().unwrap_or(false);
+        self.button.update(pressed, Micros(now.ticks()))
+    }
+
+    /// When `poll` must run again without an edge, to finish debouncing or
+    /// to see a long press.
+    pub fn due(&self) -> Option<Instant> {
+        self.button.due().map(|at| Instant::from_ticks(at.0))
+    }
+}
+
+#[cfg(feature = "encoder")]
+pub struct Panel {
+    a: Input<EncoderA>,
+    b: Input<EncoderB>,
+    encoder: Quadrature,
+    button: PushButton,
+}
+
+#[cfg(feature = "encoder")]
+impl Panel {
+    pub fn new(mut a: Input<EncoderA>, mut b: Input<EncoderB>, button: Input<ButtonPin>) -> Self {
+        watch(&mut a);
+        watch(&mut b);
+        let encoder = Quadrature::new(a.is_high().unwrap_or(true), b.is_high().unwrap_or(true));
+        Self {
+            a,
+            b,
+            encoder,
+            button: PushButton::new(button),
+        }
+    }
+
+    /// Detents turned since the last call, and any completed press.
+    pub fn poll(&mut self, now: Instant) -> (i32, Option<Press>) {
+        acknowledge(&mut self.a);
+        acknowledge(&mut self.b);
+        let a = self.a.is_high().unwrap_or(true);
+        let b = self.b.is_high().unwrap_or(true);
+        (self.encoder.update(a, b), self.button.poll(now))
+    }
+
+    pub fn due(&self) -> Option<Instant> {
+        self.button.due()
+    }
+}
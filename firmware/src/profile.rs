@@ -0,0 +1,28 @@
+//! Test profiles persisted in the flash sector below the fault log, listed
+//! with `PROFILE?` and started with `RUN <name>`.
+
+use tensile_core::profile::{Profiles, TABLE_LEN};
+
+use crate::errlog::LOG_OFFSET;
+use crate::flash::{self, PAGE_SIZE, SECTOR_SIZE};
+
+/// Must match the space carved out of FLASH in memory.x.
+pub const PROFILE_OFFSET: u32 = LOG_OFFSET - SECTOR_SIZE;
+
+pub fn load() -> Profiles {
+    let mut raw = [0; TABLE_LEN];
+    flash::read(PROFILE_OFFSET, &mut raw);
+    Profiles::decode(&raw)
+}
+
+/// Rewrite the whole table. Takes ~50 ms; core 1 must be parked for the
+/// duration.
+pub fn save(profiles: &Profiles) {
+    flash::erase_sector(PROFILE_OFFSET);
+    let table = profiles.encode();
+    for (i, page) in table.chunks_exact(PAGE_SIZE).enumerate() {
+        if let Ok(page) = page.try_into() {
+            flash::program_page(PROFILE_OFFSET + (i * PAGE_SIZE) as u32, page);
+        }
+    }
+}
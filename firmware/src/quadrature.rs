@@ -0,0 +1,97 @@
+//! Clip-on extensometer on GPIO14/15, built with the `extensometer`
+//! feature.
+//!
+//! A PIO state machine decodes the quadrature at full (4×) resolution and
+//! keeps the count itself, so no edge is lost however busy the cores are.
+//! The program is the Raspberry Pi `quadrature_encoder` example: a jump
+//! table on the previous and current pin states, which has to sit at
+//! address 0.
+
+use bsp::hal::{
+    gpio::{FunctionNull, FunctionPio0, Pin, PullDown, PullUp},
+    pac,
+    pio::{PIOBuilder, PIOExt, Running, Rx, ShiftDirection, StateMachine, PIO0SM0},
+};
+use pio::{InSource, JmpCondition, MovDestination, MovOperation, MovSource, OutDestination};
+use rp_pico as bsp;
+
+use crate::board::{ExtensometerA, ExtensometerB};
+
+pub struct Quadrature {
+    rx: Rx<PIO0SM0>,
+    _sm: StateMachine<PIO0SM0, Running>,
+}
+
+impl Quadrature {
+    pub fn new(
+        pio0: pac::PIO0,
+        a: Pin<ExtensometerA, FunctionNull, PullDown>,
+        b: Pin<ExtensometerB, FunctionNull, PullDown>,
+        resets: &mut pac::RESETS,
+    ) -> Self {
+        // Encoders are usually open collector.
+        let a = a.reconfigure::<FunctionPio0, PullUp>();
+        let _b = b.reconfigure::<FunctionPio0, PullUp>();
+
+        let mut asm = pio::Assembler::<{ pio::RP2040_MAX_PROGRAM_SIZE }>::new();
+        let mut update = asm.label();
+        let mut decrement = asm.label();
+        let mut increment = asm.label();
+        let mut increment_cont = asm.label();
+        let mut wrap_source = asm.label();
+        // Indexed by the old state of B,A and the new one.
+        for step in [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1] {
+            let target = match step {
+                1 => &mut increment,
+                -1 => &mut decrement,
+                _ => &mut update,
+            };
+            asm.jmp(JmpCondition::Always, target);
+        }
+        // Entries 14 and 15 fall straight into their actions. This jump
+        // always goes to the next address; it is only here to decrement Y.
+        asm.bind(&mut decrement);
+        asm.jmp(JmpCondition::YDecNonZero, &mut update);
+        asm.bind(&mut update);
+        asm.mov(MovDestination::ISR, MovOperation::None, MovSource::Y);
+        asm.push(false, false);
+        asm.out(OutDestination::ISR, 2);
+        asm.r#in(InSource::PINS, 2);
+        asm.mov(MovDestination::OSR, MovOperation::None, MovSource::ISR);
+        asm.mov(MovDestination::PC, MovOperation::None, MovSource::ISR);
+        // No increment instruction: negate, decrement, negate.
+        asm.bind(&mut increment);
+        asm.mov(MovDestination::Y, MovOperation::Invert, MovSource::Y);
+        asm.jmp(JmpCondition::YDecNonZero, &mut increment_cont);
+        asm.bind(&mut increment_cont);
+        asm.mov(MovDestination::Y, MovOperation::Invert, MovSource::Y);
+        asm.bind(&mut wrap_source);
+        let program = asm
+            .assemble_with_wrap(wrap_source, update)
+            .set_origin(Some(0));
+
+        let (mut pio, sm0, _, _, _) = pio0.split(resets);
+        let installed = pio.install(&program).unwrap();
+        let (sm, rx, _tx) = PIOBuilder::from_installed_program(installed)
+            .in_pin_base(a.id().num)
+            .in_shift_direction(ShiftDirection::Left)
+            .out_shift_direction(ShiftDirection::Right)
+            .build(sm0);
+        Self {
+            rx,
+            _sm: sm.start(),
+        }
+    }
+
+    pub fn count(&mut self) -> u32 {
+        // The state machine pushes the count on every pass and drops it
+        // while the FIFO is full, so anything queued is stale. A fresh one
+        // is only a few cycles away.
+        while self.rx.read().is_some() {}
+        loop {
+            if let Some(count) = self.rx.read() {
+                return count;
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Maxim DS3231 real-time clock on I2C1, beside the OLED, built with the
+//! `rtc` feature. It keeps UTC in 24-hour mode.
+
+use embedded_hal::i2c::I2c;
+use tensile_core::clock::DateTime;
+
+const DS3231_ADDR: u8 = 0x68;
+const REG_SECONDS: u8 = 0x00;
+const REG_STATUS: u8 = 0x0F;
+/// The oscillator stopped, e.g. on a flat backup cell; the time is invalid
+/// until set again.
+const STATUS_OSF: u8 = 1 << 7;
+const HOUR_12H: u8 = 1 << 6;
+const HOUR_PM: u8 = 1 << 5;
+const MONTH_CENTURY: u8 = 1 << 7;
+
+fn from_bcd(bcd: u8) -> u8 {
+    (bcd >> 4) * 10 + (bcd & 0x0F)
+}
+
+fn to_bcd(value: u8) -> u8 {
+    ((value / 10) << 4) | (value % 10)
+}
+
+pub struct Ds3231<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C: I2c> Ds3231<I2C> {
+    /// `None` if no DS3231 answers on the bus.
+    pub fn detect(i2c: I2C) -> Option<Self> {
+        let mut rtc = Self { i2c };
+        rtc.read_reg(REG_STATUS).ok()?;
+        Some(rtc)
+    }
+
+    /// The current time, or `None` if the clock has not kept it.
+    pub fn read(&mut self) -> Result<Option<DateTime>, I2C::Error> {
+        if self.read_reg(REG_STATUS)? & STATUS_OSF != 0 {
+            return Ok(None);
+        }
+        let mut regs = [0u8; 7];
+        self.i2c
+            .write_read(DS3231_ADDR, &[REG_SECONDS], &mut regs)?;
+        let hour = if regs[2] & HOUR_12H != 0 {
+            from_bcd(regs[2] & 0x1F) % 12 + if regs[2] & HOUR_PM != 0 { 12 } else { 0 }
+        } else {
+            from_bcd(regs[2] & 0x3F)
+        };
+        let century = if regs[5] & MONTH_CENTURY != 0 {
+            2100
+        } else {
+            2000
+        };
+        Ok(Some(DateTime {
+            year: century + from_bcd(regs[6]) as u16,
+            month: from_bcd(regs[5] & 0x1F),
+            day: from_bcd(regs[4] & 0x3F),
+            hour,
+            minute: from_bcd(regs[1] & 0x7F),
+            second: from_bcd(regs[0] & 0x7F),
+        }))
+    }
+
+    /// Set the time and mark it valid. Years before 2000 are not stored.
+    pub fn write(&mut self, t: DateTime) -> Result<(), I2C::Error> {
+        let century = if t.year >= 2100 { MONTH_CENTURY } else { 0 };
+        self.i2c.write(
+            DS3231_ADDR,
+            &[
+                REG_SECONDS,
+                to_bcd(t.second),
+                to_bcd(t.minute),
+                to_bcd(t.hour),
+                t.weekday(),
+                to_bcd(t.day),
+                to_bcd(t.month) | century,
+                to_bcd((t.year % 100) as u8),
+            ],
+        )?;
+        let status = self.read_reg(REG_STATUS)?;
+        self.i2c
+            .write(DS3231_ADDR, &[REG_STATUS, status & !STATUS_OSF])
+    }
+
+    fn read_reg(&mut self, reg: u8) -> Result<u8, I2C::Error> {
+        let mut buf = [0u8];
+        self.i2c.write_read(DS3231_ADDR, &[reg], &mut buf)?;
+        Ok(buf[0])
+    }
+}
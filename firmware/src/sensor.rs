@@ -0,0 +1,321 @@
+//! Load-cell ADC front ends behind a common `ForceSensor` trait.
+//!
+//! One firmware image carries every backend enabled by Cargo features;
+//! `detect` probes the buses at boot and hands back whichever converter
+//! answered, so a fleet with mixed amplifier boards can run the same build.
+//! The HX711 is always built in as the fallback.
+
+#[cfg(feature = "ads1256")]
+mod ads1256;
+#[cfg(feature = "nau7802")]
+mod nau7802;
+
+#[cfg(feature = "ads1256")]
+pub use ads1256::Ads1256Sensor;
+#[cfg(feature = "nau7802")]
+pub use nau7802::Nau7802Sensor;
+
+use tensile_core::range::Input;
+
+/// Conversions dropped after the HX711 changes input: the one already under
+/// way, and the rest of its 400 ms settling time at 10 SPS.
+pub const SETTLE_CONVERSIONS: u32 = 4;
+
+/// Errors reported by a sensor backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum SensorError {
+    /// The bus transaction (I2C/SPI/GPIO) failed.
+    Bus,
+    /// The converter did not signal data-ready in time.
+    #[cfg(any(feature = "nau7802", feature = "ads1256"))]
+    Timeout,
+    /// The requested setting is outside what the converter supports.
+    Unsupported,
+}
+
+impl SensorError {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SensorError::Bus => "bus",
+            #[cfg(any(feature = "nau7802", feature = "ads1256"))]
+            SensorError::Timeout => "timeout",
+            SensorError::Unsupported => "unsupported",
+        }
+    }
+}
+
+/// Common interface for the load-cell converters.
+pub trait ForceSensor {
+    /// Begin a new conversion. Free-running converters treat this as a no-op.
+    fn start_conversion(&mut self) -> Result<(), SensorError>;
+
+    /// True when a conversion result is waiting to be read.
+    fn data_ready(&mut self) -> Result<bool, SensorError>;
+
+    /// Fetch the latest conversion as a sign-extended 24-bit count.
+    fn read(&mut self) -> nb::Result<i32, SensorError>;
+
+    /// Power the converter up or down.
+    fn set_power(&mut self, on: bool) -> Result<(), SensorError>;
+
+    /// Output data rates the converter can run at, in samples per second.
+    fn supported_rates(&self) -> &'static [u32];
+
+    /// Switch to one of `supported_rates`.
+    fn set_rate(&mut self, sps: u32) -> Result<(), SensorError>;
+
+    /// Read from another input. Conversions still settling are dropped.
+    fn select_input(&mut self, input: Input) -> Result<(), SensorError>;
+}
+
+/// Which converter is fitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Backend {
+    Hx711,
+    Nau7802,
+    Ads1256,
+}
+
+impl Backend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Backend::Hx711 => "hx711",
+            Backend::Nau7802 => "nau7802",
+            Backend::Ads1256 => "ads1256",
+        }
+    }
+}
+
+/// Sign-extend a 24-bit two's complement value.
+#[cfg(any(feature = "nau7802", feature = "ads1256"))]
+fn i24_to_i32(raw: u32) -> i32 {
+    ((raw << 8) as i32) >> 8
+}
+
+// --- HX711 ---
+
+/// Wraps the `hx711` crate driver.
+///
+/// The crate only exposes a combined ready-and-read, so `data_ready` performs
+/// the read and parks the value until `read` collects it.
+pub struct Hx711Sensor<D, IN, OUT> {
+    inner: hx711::Hx711<D, IN, OUT>,
+    pending: Option<i32>,
+    /// Conversions left to drop after an input change.
+    settling: u32,
+}
+
+impl<D, IN, OUT> Hx711Sensor<D, IN, OUT>
+where
+    D: embedded_hal_0_2::blocking::delay::DelayUs<u32>,
+    IN: embedded_hal_0_2::digital::v2::InputPin,
+    OUT: embedded_hal_0_2::digital::v2::OutputPin,
+{
+    pub fn new(delay: D, dout: IN, pd_sck: OUT) -> Result<Self, SensorError> {
+        let inner = hx711::Hx711::new(delay, dout, pd_sck).map_err(|_| SensorError::Bus)?;
+        Ok(Self {
+            inner,
+            pending: None,
+            settling: 0,
+        })
+    }
+}
+
+impl<D, IN, OUT> ForceSensor for Hx711Sensor<D, IN, OUT>
+where
+    D: embedded_hal_0_2::blocking::delay::DelayUs<u32>,
+    IN: embedded_hal_0_2::digital::v2::InputPin,
+    OUT: embedded_hal_0_2::digital::v2::OutputPin,
+{
+    fn start_conversion(&mut self) -> Result<(), SensorError> {
+        Ok(())
+    }
+
+    fn data_ready(&mut self) -> Result<bool, SensorError> {
+        if self.pending.is_none() {
+            match self.inner.retrieve() {
+                Ok(_) if self.settling > 0 => self.settling -= 1,
+                Ok(value) => self.pending = Some(value),
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(_)) => return Err(SensorError::Bus),
+            }
+        }
+        Ok(self.pending.is_some())
+    }
+
+    fn read(&mut self) -> nb::Result<i32, SensorError> {
+        if self.data_ready()? {
+            Ok(self.pending.take().unwrap_or_default())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    fn set_power(&mut self, on: bool) -> Result<(), SensorError> {
+        self.pending = None;
+        if on {
+            self.inner.enable()
+        } else {
+            self.inner.disable()
+        }
+        .map_err(|_| SensorError::Bus)
+    }
+
+    /// The RATE pin is strapped on the board, low (10 SPS) on the common
+    /// breakouts; the driver has no way to change it.
+    fn supported_rates(&self) -> &'static [u32] {
+        &[10]
+    }
+
+    fn set_rate(&mut self, sps: u32) -> Result<(), SensorError> {
+        if self.supported_rates().contains(&sps) {
+            Ok(())
+        } else {
+            Err(SensorError::Unsupported)
+        }
+    }
+
+    /// A at gain 128 or 64, B at gain 32. The choice is clocked out after
+    /// the next conversion, so that one still comes from the old input.
+    fn select_input(&mut self, input: Input) -> Result<(), SensorError> {
+        let mode = match input {
+            Input::A => hx711::Mode::ChAGain128,
+            Input::B => hx711::Mode::ChBGain32,
+            // 27 pulses: A at gain 64, whatever the crate calls it.
+            Input::A64 => hx711::Mode::ChBGain64,
+        };
+        self.pending = None;
+        self.settling = SETTLE_CONVERSIONS;
+        match self.inner.set_mode(mode) {
+            Ok(()) | Err(nb::Error::WouldBlock) => Ok(()),
+            Err(nb::Error::Other(_)) => Err(SensorError::Bus),
+        }
+    }
+}
+
+// --- Runtime selection ---
+
+/// Whichever backend was selected at boot.
+pub enum AnySensor<H, N, A> {
+    Hx711(H),
+    Nau7802(N),
+    Ads1256(A),
+    /// The driver for this backend could not be set up. Every call fails
+    /// with `Bus`, so the fault surfaces through the normal health checks
+    /// instead of stopping the board.
+    Absent(Backend),
+}
+
+impl<H, N, A> AnySensor<H, N, A> {
+    pub fn backend(&self) -> Backend {
+        match self {
+            AnySensor::Hx711(_) => Backend::Hx711,
+            AnySensor::Nau7802(_) => Backend::Nau7802,
+            AnySensor::Ads1256(_) => Backend::Ads1256,
+            AnySensor::Absent(backend) => *backend,
+        }
+    }
+}
+
+impl<H, N, A> ForceSensor for AnySensor<H, N, A>
+where
+    H: ForceSensor,
+    N: ForceSensor,
+    A: ForceSensor,
+{
+    fn start_conversion(&mut self) -> Result<(), SensorError> {
+        match self {
+            AnySensor::Hx711(s) => s.start_conversion(),
+            AnySensor::Nau7802(s) => s.start_conversion(),
+            AnySensor::Ads1256(s) => s.start_conversion(),
+            AnySensor::Absent(_) => Err(SensorError::Bus),
+        }
+    }
+
+    fn data_ready(&mut self) -> Result<bool, SensorError> {
+        match self {
+            AnySensor::Hx711(s) => s.data_ready(),
+            AnySensor::Nau7802(s) => s.data_ready(),
+            AnySensor::Ads1256(s) => s.data_ready(),
+            AnySensor::Absent(_) => Err(SensorError::Bus),
+        }
+    }
+
+    /// With `board::LOAD_CELL_POLARITY` applied.
+    fn read(&mut self) -> nb::Result<i32, SensorError> {
+        match self {
+            AnySensor::Hx711(s) => s.read(),
+            AnySensor::Nau7802(s) => s.read(),
+            AnySensor::Ads1256(s) => s.read(),
+            AnySensor::Absent(_) => Err(nb::Error::Other(SensorError::Bus)),
+        }
+        .map(|value| value.wrapping_mul(crate::board::LOAD_CELL_POLARITY))
+    }
+
+    fn set_power(&mut self, on: bool) -> Result<(), SensorError> {
+        match self {
+            AnySensor::Hx711(s) => s.set_power(on),
+            AnySensor::Nau7802(s) => s.set_power(on),
+            AnySensor::Ads1256(s) => s.set_power(on),
+            AnySensor::Absent(_) => Err(SensorError::Bus),
+        }
+    }
+
+    fn supported_rates(&self) -> &'static [u32] {
+        match self {
+            AnySensor::Hx711(s) => s.supported_rates(),
+            AnySensor::Nau7802(s) => s.supported_rates(),
+            AnySensor::Ads1256(s) => s.supported_rates(),
+            AnySensor::Absent(_) => &[],
+        }
+    }
+
+    fn set_rate(&mut self, sps: u32) -> Result<(), SensorError> {
+        match self {
+            AnySensor::Hx711(s) => s.set_rate(sps),
+            AnySensor::Nau7802(s) => s.set_rate(sps),
+            AnySensor::Ads1256(s) => s.set_rate(sps),
+            AnySensor::Absent(_) => Err(SensorError::Bus),
+        }
+    }
+
+    fn select_input(&mut self, input: Input) -> Result<(), SensorError> {
+        match self {
+            AnySensor::Hx711(s) => s.select_input(input),
+            AnySensor::Nau7802(s) => s.select_input(input),
+            AnySensor::Ads1256(s) => s.select_input(input),
+            AnySensor::Absent(_) => Err(SensorError::Bus),
+        }
+    }
+}
+
+/// Stands in for a backend that was compiled out; it can never be constructed.
+impl ForceSensor for core::convert::Infallible {
+    fn start_conversion(&mut self) -> Result<(), SensorError> {
+        match *self {}
+    }
+
+    fn data_ready(&mut self) -> Result<bool, SensorError> {
+        match *self {}
+    }
+
+    fn read(&mut self) -> nb::Result<i32, SensorError> {
+        match *self {}
+    }
+
+    fn set_power(&mut self, _on: bool) -> Result<(), SensorError> {
+        match *self {}
+    }
+
+    fn supported_rates(&self) -> &'static [u32] {
+        match *self {}
+    }
+
+    fn set_rate(&mut self, _sps: u32) -> Result<(), SensorError> {
+        match *self {}
+    }
+
+    fn select_input(&mut self, _input: Input) -> Result<(), SensorError> {
+        match *self {}
+    }
+}
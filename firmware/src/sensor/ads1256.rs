@@ -0,0 +1,174 @@
+//! TI ADS1256 backend, built with the `ads1256` feature.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::spi::SpiBus;
+
+use super::{i24_to_i32, ForceSensor, Input, SensorError};
+use crate::log;
+
+const ADS_CMD_WAKEUP: u8 = 0x00;
+const ADS_CMD_RDATA: u8 = 0x01;
+const ADS_CMD_SDATAC: u8 = 0x0F;
+const ADS_CMD_RREG: u8 = 0x10;
+const ADS_CMD_WREG: u8 = 0x50;
+const ADS_CMD_SELFCAL: u8 = 0xF0;
+const ADS_CMD_SYNC: u8 = 0xFC;
+const ADS_CMD_STANDBY: u8 = 0xFD;
+const ADS_REG_STATUS: u8 = 0x00;
+const ADS_REG_MUX: u8 = 0x01;
+const ADS_REG_ADCON: u8 = 0x02;
+const ADS_REG_DRATE: u8 = 0x03;
+const ADS_CHIP_ID: u8 = 0x3;
+/// DRATE register codes. Faster rates exist but the sample loop and the
+/// USB stream can't keep up with them.
+const ADS_RATES: [u32; 10] = [5, 10, 15, 25, 30, 50, 60, 100, 500, 1000];
+const ADS_DRATE: [u8; 10] = [0x13, 0x23, 0x33, 0x43, 0x53, 0x63, 0x72, 0x82, 0x92, 0xA1];
+
+/// TI ADS1256 24-bit delta-sigma ADC on SPI (mode 1) with a DRDY line.
+pub struct Ads1256Sensor<SPI, CS, DRDY, D> {
+    spi: SPI,
+    cs: CS,
+    drdy: DRDY,
+    delay: D,
+}
+
+impl<SPI, CS, DRDY, D> Ads1256Sensor<SPI, CS, DRDY, D>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+    DRDY: InputPin,
+    D: DelayNs,
+{
+    /// Check the chip ID nibble in STATUS and configure the converter, or
+    /// `None` if no ADS1256 answers.
+    pub fn detect(spi: SPI, cs: CS, drdy: DRDY, delay: D) -> Option<Self> {
+        let mut adc = Self {
+            spi,
+            cs,
+            drdy,
+            delay,
+        };
+        let Ok(status) = adc.read_reg(ADS_REG_STATUS) else {
+            log::debug!("ads1256: bus error");
+            return None;
+        };
+        if status >> 4 != ADS_CHIP_ID {
+            log::debug!("ads1256: chip id {} is not an ADS1256", status >> 4);
+            return None;
+        }
+        if let Err(e) = adc.init() {
+            log::warn!("ads1256: init failed: {}", e.as_str());
+            return None;
+        }
+        Some(adc)
+    }
+
+    /// Differential AIN0/AIN1 at PGA 64, 10 SPS, then self-calibrate.
+    fn init(&mut self) -> Result<(), SensorError> {
+        self.command(ADS_CMD_SDATAC)?;
+        self.write_reg(ADS_REG_MUX, 0x01)?;
+        self.write_reg(ADS_REG_ADCON, 0b110)?;
+        self.write_reg(ADS_REG_DRATE, 0x23)?;
+        self.command(ADS_CMD_SELFCAL)?;
+        self.wait_ready(1000)
+    }
+
+    fn wait_ready(&mut self, timeout_ms: u32) -> Result<(), SensorError> {
+        for _ in 0..timeout_ms {
+            if self.drdy.is_low().map_err(|_| SensorError::Bus)? {
+                return Ok(());
+            }
+            self.delay.delay_ms(1);
+        }
+        Err(SensorError::Timeout)
+    }
+
+    fn command(&mut self, cmd: u8) -> Result<(), SensorError> {
+        self.transaction(|spi, _| spi.write(&[cmd]))
+    }
+
+    fn read_reg(&mut self, reg: u8) -> Result<u8, SensorError> {
+        let mut buf = [0u8];
+        self.transaction(|spi, delay| {
+            spi.write(&[ADS_CMD_RREG | reg, 0])?;
+            // t6: 50 master clock periods before data is clocked out.
+            spi.flush()?;
+            delay.delay_us(10);
+            spi.read(&mut buf)
+        })?;
+        Ok(buf[0])
+    }
+
+    fn write_reg(&mut self, reg: u8, value: u8) -> Result<(), SensorError> {
+        self.transaction(|spi, _| spi.write(&[ADS_CMD_WREG | reg, 0, value]))
+    }
+
+    fn transaction<F>(&mut self, f: F) -> Result<(), SensorError>
+    where
+        F: FnOnce(&mut SPI, &mut D) -> Result<(), SPI::Error>,
+    {
+        self.cs.set_low().map_err(|_| SensorError::Bus)?;
+        let result = f(&mut self.spi, &mut self.delay).and_then(|_| self.spi.flush());
+        self.cs.set_high().map_err(|_| SensorError::Bus)?;
+        result.map_err(|_| SensorError::Bus)
+    }
+}
+
+impl<SPI, CS, DRDY, D> ForceSensor for Ads1256Sensor<SPI, CS, DRDY, D>
+where
+    SPI: SpiBus,
+    CS: OutputPin,
+    DRDY: InputPin,
+    D: DelayNs,
+{
+    fn start_conversion(&mut self) -> Result<(), SensorError> {
+        self.command(ADS_CMD_SYNC)?;
+        self.command(ADS_CMD_WAKEUP)
+    }
+
+    fn data_ready(&mut self) -> Result<bool, SensorError> {
+        self.drdy.is_low().map_err(|_| SensorError::Bus)
+    }
+
+    fn read(&mut self) -> nb::Result<i32, SensorError> {
+        if !self.data_ready()? {
+            return Err(nb::Error::WouldBlock);
+        }
+        let mut buf = [0u8; 3];
+        self.transaction(|spi, delay| {
+            spi.write(&[ADS_CMD_RDATA])?;
+            spi.flush()?;
+            delay.delay_us(10);
+            spi.read(&mut buf)
+        })?;
+        Ok(i24_to_i32(u32::from_be_bytes([0, buf[0], buf[1], buf[2]])))
+    }
+
+    fn set_power(&mut self, on: bool) -> Result<(), SensorError> {
+        self.command(if on { ADS_CMD_WAKEUP } else { ADS_CMD_STANDBY })
+    }
+
+    fn supported_rates(&self) -> &'static [u32] {
+        &ADS_RATES
+    }
+
+    fn set_rate(&mut self, sps: u32) -> Result<(), SensorError> {
+        let i = ADS_RATES
+            .iter()
+            .position(|&rate| rate == sps)
+            .ok_or(SensorError::Unsupported)?;
+        self.write_reg(ADS_REG_DRATE, ADS_DRATE[i])?;
+        // New rate takes effect on the next conversion cycle.
+        self.command(ADS_CMD_SYNC)?;
+        self.command(ADS_CMD_WAKEUP)
+    }
+
+    /// Only the one input is wired.
+    fn select_input(&mut self, input: Input) -> Result<(), SensorError> {
+        match input {
+            Input::A => Ok(()),
+            Input::B | Input::A64 => Err(SensorError::Unsupported),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Nuvoton NAU7802 backend, built with the `nau7802` feature.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use super::{i24_to_i32, ForceSensor, Input, SensorError};
+use crate::log;
+
+const NAU7802_ADDR: u8 = 0x2A;
+const NAU_PU_CTRL: u8 = 0x00;
+const NAU_CTRL1: u8 = 0x01;
+const NAU_CTRL2: u8 = 0x02;
+const NAU_ADCO_B2: u8 = 0x12;
+const NAU_ADC: u8 = 0x15;
+const NAU_REVISION: u8 = 0x1F;
+
+/// Conversion rates and their CTRL2 rate select (CRS) codes.
+const NAU_RATES: [u32; 5] = [10, 20, 40, 80, 320];
+const NAU_CRS: [u8; 5] = [0b000, 0b001, 0b010, 0b011, 0b111];
+const NAU_CRS_MASK: u8 = 0b111 << 4;
+
+const PU_RR: u8 = 1 << 0;
+const PU_PUD: u8 = 1 << 1;
+const PU_PUA: u8 = 1 << 2;
+const PU_PUR: u8 = 1 << 3;
+const PU_CS: u8 = 1 << 4;
+const PU_CR: u8 = 1 << 5;
+const PU_AVDDS: u8 = 1 << 7;
+
+/// Nuvoton NAU7802 24-bit bridge ADC on I2C.
+pub struct Nau7802Sensor<I2C, D> {
+    i2c: I2C,
+    delay: D,
+}
+
+impl<I2C: I2c, D: DelayNs> Nau7802Sensor<I2C, D> {
+    /// Check the revision register and bring the chip up, or `None` if no
+    /// NAU7802 answers on the bus.
+    pub fn detect(i2c: I2C, delay: D) -> Option<Self> {
+        let mut adc = Self { i2c, delay };
+        let Ok(rev) = adc.read_reg(NAU_REVISION) else {
+            log::debug!("nau7802: no reply");
+            return None;
+        };
+        if rev & 0x0F != 0x0F {
+            log::debug!("nau7802: revision {} is not a NAU7802", rev);
+            return None;
+        }
+        if let Err(e) = adc.init() {
+            log::warn!("nau7802: init failed: {}", e.as_str());
+            return None;
+        }
+        Some(adc)
+    }
+
+    /// Reset, power up on the internal 3.3 V LDO at gain 128 / 10 SPS and
+    /// start continuous conversions.
+    fn init(&mut self) -> Result<(), SensorError> {
+        self.write(NAU_PU_CTRL, PU_RR)?;
+        self.delay.delay_ms(1);
+        self.write(NAU_PU_CTRL, PU_PUD)?;
+        self.delay.delay_ms(1);
+        if self.read_reg(NAU_PU_CTRL)? & PU_PUR == 0 {
+            return Err(SensorError::Timeout);
+        }
+        // VLDO = 3.3 V, gain = 128.
+        self.write(NAU_CTRL1, (0b100 << 3) | 0b111)?;
+        // CRS = 10 SPS.
+        self.write(NAU_CTRL2, 0)?;
+        // Turn off the chopper clock as recommended by the datasheet.
+        let adc = self.read_reg(NAU_ADC)?;
+        self.write(NAU_ADC, adc | (0b11 << 4))?;
+        self.write(NAU_PU_CTRL, PU_PUD | PU_PUA | PU_AVDDS | PU_CS)
+    }
+
+    fn write(&mut self, reg: u8, value: u8) -> Result<(), SensorError> {
+        self.i2c
+            .write(NAU7802_ADDR, &[reg, value])
+            .map_err(|_| SensorError::Bus)
+    }
+
+    fn read_reg(&mut self, reg: u8) -> Result<u8, SensorError> {
+        let mut buf = [0u8];
+        self.i2c
+            .write_read(NAU7802_ADDR, &[reg], &mut buf)
+            .map_err(|_| SensorError::Bus)?;
+        Ok(buf[0])
+    }
+}
+
+impl<I2C: I2c, D: DelayNs> ForceSensor for Nau7802Sensor<I2C, D> {
+    fn start_conversion(&mut self) -> Result<(), SensorError> {
+        let ctrl = self.read_reg(NAU_PU_CTRL)?;
+        self.write(NAU_PU_CTRL, ctrl | PU_CS)
+    }
+
+    fn data_ready(&mut self) -> Result<bool, SensorError> {
+        Ok(self.read_reg(NAU_PU_CTRL)? & PU_CR != 0)
+    }
+
+    fn read(&mut self) -> nb::Result<i32, SensorError> {
+        if !self.data_ready()? {
+            return Err(nb::Error::WouldBlock);
+        }
+        let mut buf = [0u8; 3];
+        self.i2c
+            .write_read(NAU7802_ADDR, &[NAU_ADCO_B2], &mut buf)
+            .map_err(|_| SensorError::Bus)?;
+        Ok(i24_to_i32(u32::from_be_bytes([0, buf[0], buf[1], buf[2]])))
+    }
+
+    fn set_power(&mut self, on: bool) -> Result<(), SensorError> {
+        let ctrl = self.read_reg(NAU_PU_CTRL)?;
+        if on {
+            self.write(NAU_PU_CTRL, ctrl | PU_PUD | PU_PUA)?;
+            self.delay.delay_ms(1);
+            self.start_conversion()
+        } else {
+            self.write(NAU_PU_CTRL, ctrl & !(PU_PUD | PU_PUA))
+        }
+    }
+
+    fn supported_rates(&self) -> &'static [u32] {
+        &NAU_RATES
+    }
+
+    fn set_rate(&mut self, sps: u32) -> Result<(), SensorError> {
+        let i = NAU_RATES
+            .iter()
+            .position(|&rate| rate == sps)
+            .ok_or(SensorError::Unsupported)?;
+        let crs = NAU_CRS[i];
+        let ctrl2 = self.read_reg(NAU_CTRL2)?;
+        self.write(NAU_CTRL2, (ctrl2 & !NAU_CRS_MASK) | (crs << 4))
+    }
+
+    /// Only the one input is wired.
+    fn select_input(&mut self, input: Input) -> Result<(), SensorError> {
+        match input {
+            Input::A => Ok(()),
+            Input::B | Input::A64 => Err(SensorError::Unsupported),
+        }
+    }
+}
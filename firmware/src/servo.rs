@@ -0,0 +1,47 @@
+//! Hobby servo on GPIO8 (PWM slice 4, channel A) closing the specimen
+//! clamp, built with the `servo` feature.
+
+use bsp::hal::{
+    gpio::{FunctionNull, Pin, PullDown},
+    pwm::{FreeRunning, Pwm4, Slice},
+};
+use embedded_hal::pwm::SetDutyCycle;
+use rp_pico as bsp;
+use tensile_core::servo::{pulse_us, PERIOD_US};
+
+use crate::board::Servo as ServoPin;
+
+/// Divides the 125 MHz system clock to a 1 MHz PWM count, so the duty is
+/// the pulse width in µs.
+const DIVIDER: u8 = 125;
+
+pub struct Servo {
+    slice: Slice<Pwm4, FreeRunning>,
+    angle: Option<u8>,
+}
+
+impl Servo {
+    /// Sends no pulses until the first `set`, so the clamp stays where it
+    /// was left at power-up.
+    pub fn new(
+        mut slice: Slice<Pwm4, FreeRunning>,
+        pin: Pin<ServoPin, FunctionNull, PullDown>,
+    ) -> Self {
+        slice.set_div_int(DIVIDER);
+        slice.set_top(PERIOD_US - 1);
+        slice.channel_a.output_to(pin);
+        let _ = slice.channel_a.set_duty_cycle(0);
+        slice.enable();
+        Self { slice, angle: None }
+    }
+
+    pub fn set(&mut self, deg: u8) {
+        let _ = self.slice.channel_a.set_duty_cycle(pulse_us(deg));
+        self.angle = Some(deg);
+    }
+
+    /// The last angle set, if any.
+    pub fn angle(&self) -> Option<u8> {
+        self.angle
+    }
+}
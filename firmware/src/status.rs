@@ -0,0 +1,47 @@
+//! The onboard LED as a status indicator.
+//!
+//! The main loop says what to show on every pass; patterns come from
+//! `tensile_core::indicator`, and the loop sleeps until the next edge.
+
+use bsp::hal::{
+    gpio::{FunctionSioOutput, Pin, PullDown},
+    timer::Instant,
+};
+use embedded_hal::digital::OutputPin;
+use fugit::ExtU64;
+use rp_pico as bsp;
+use tensile_core::indicator::Indication;
+
+use crate::board::Led;
+
+pub struct StatusLed {
+    pin: Pin<Led, FunctionSioOutput, PullDown>,
+    flash_until: Option<Instant>,
+}
+
+impl StatusLed {
+    pub fn new(pin: Pin<Led, FunctionSioOutput, PullDown>) -> Self {
+        Self {
+            pin,
+            flash_until: None,
+        }
+    }
+
+    /// Light steadily for `ms` over whatever the pattern shows, to confirm
+    /// an action.
+    pub fn flash(&mut self, now: Instant, ms: u64) {
+        self.flash_until = Some(now + ms.millis());
+    }
+
+    /// Drive the LED for `indication`. Returns when it next changes.
+    pub fn update(&mut self, now: Instant, indication: Indication) -> Option<Instant> {
+        self.flash_until = self.flash_until.filter(|&end| now < end);
+        let t_ms = now.ticks() / 1000;
+        let on = self.flash_until.is_some() || indication.is_on(t_ms);
+        let _ = self.pin.set_state(on.into());
+        let edge = indication
+            .next_change(t_ms)
+            .map(|ms| Instant::from_ticks(ms * 1000));
+        [self.flash_until, edge].into_iter().flatten().min()
+    }
+}
@@ -0,0 +1,97 @@
+//! Test log persisted in the two flash sectors below the profiles: numbers
+//! every test and keeps how it went, read back with `TEST?`.
+
+use tensile_core::errlog::RingIndex;
+use tensile_core::testlog::{Record, Summary, RECORD_LEN};
+
+use crate::flash::{self, PAGE_SIZE, SECTOR_SIZE};
+use crate::profile::PROFILE_OFFSET;
+
+/// Must match the space carved out of FLASH in memory.x.
+pub const LOG_OFFSET: u32 = PROFILE_OFFSET - SECTORS as u32 * SECTOR_SIZE;
+const SECTORS: usize = 2;
+const SLOTS_PER_SECTOR: usize = SECTOR_SIZE as usize / RECORD_LEN;
+const SLOTS: usize = SECTORS * SLOTS_PER_SECTOR;
+
+pub struct TestLog {
+    ring: RingIndex,
+    /// The newest record's number, 0 before the first test.
+    number: u32,
+    /// Slot of the test in progress.
+    running: Option<usize>,
+    /// The newest test that ended.
+    last: Option<Record>,
+}
+
+impl TestLog {
+    /// Find the newest test, and the newest finished one, before this boot.
+    pub fn load() -> Self {
+        let records = || (0..SLOTS).filter_map(read_slot);
+        let number = records().max_by_key(|r| r.seq).map_or(0, |r| r.number());
+        let last = records()
+            .filter(|r| r.summary.is_some())
+            .max_by_key(|r| r.seq);
+        Self {
+            ring: RingIndex::scan(
+                SECTORS,
+                SLOTS_PER_SECTOR,
+                (0..SLOTS).map(|slot| read_slot(slot).map(|r| r.seq)),
+            ),
+            number,
+            running: None,
+            last,
+        }
+    }
+
+    /// Number the test that is starting. Takes a few ms, or ~50 ms when a
+    /// sector is erased. Core 1 must be parked for the duration.
+    pub fn start(&mut self) -> u32 {
+        let append = self.ring.append();
+        if let Some(sector) = append.erase {
+            flash::erase_sector(LOG_OFFSET + sector as u32 * SECTOR_SIZE);
+        }
+        program(append.slot, &Record::encode_start(append.seq));
+        self.running = Some(append.slot);
+        self.number = append.seq.wrapping_add(1);
+        self.number
+    }
+
+    /// Record how the running test went. Core 1 must be parked.
+    pub fn end(&mut self, summary: Summary) {
+        let Some(slot) = self.running.take() else {
+            return;
+        };
+        program(slot, &Record::encode_end(&summary));
+        self.last = read_slot(slot);
+    }
+
+    /// The running or most recent test's number, 0 before the first.
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+
+    /// The newest finished test's number and summary.
+    pub fn last(&self) -> Option<(u32, Summary)> {
+        let last = self.last?;
+        Some((last.number(), last.summary?))
+    }
+}
+
+fn slot_offset(slot: usize) -> u32 {
+    LOG_OFFSET + (slot * RECORD_LEN) as u32
+}
+
+fn program(slot: usize, record: &[u8; RECORD_LEN]) {
+    let offset = slot_offset(slot);
+    let page = offset & !(PAGE_SIZE as u32 - 1);
+    let at = (offset - page) as usize;
+    let mut data = [0xFF; PAGE_SIZE];
+    data[at..at + RECORD_LEN].copy_from_slice(record);
+    flash::program_page(page, &data);
+}
+
+fn read_slot(slot: usize) -> Option<Record> {
+    let mut raw = [0; RECORD_LEN];
+    flash::read(slot_offset(slot), &mut raw);
+    Record::decode(&raw)
+}
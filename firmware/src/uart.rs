@@ -0,0 +1,161 @@
+//! The stream on UART0 (GPIO0 TX, GPIO1 RX), for headless loggers with no
+//! USB host.
+//!
+//! Lines are the same ones the USB port carries, formatted once. `MIRROR`
+//! sends everything on both; `ONLY` moves the `Force:` lines to the UART and
+//! leaves USB with replies and events, so it stays usable for setup.
+//! Commands are accepted on the UART's RX as well.
+//!
+//! With `bluetooth` the UART goes to an HC-05 or HM-10 instead, and nothing
+//! is sent while its STATE pin says no tablet is connected.
+
+use bsp::hal::{
+    fugit::{HertzU32, RateExtU32},
+    gpio::{FunctionUart, Pin, PullDown},
+    pac,
+    uart::{DataBits, Enabled, StopBits, UartConfig, UartPeripheral},
+};
+use rp_pico as bsp;
+
+use crate::board::{UartRx, UartTx};
+use crate::command::UartMode as Mode;
+
+#[cfg(not(feature = "bluetooth"))]
+pub const DEFAULT_BAUD: u32 = 115_200;
+/// What HC-05 and HM-10 modules ship set to.
+#[cfg(feature = "bluetooth")]
+pub const DEFAULT_BAUD: u32 = 9_600;
+
+/// Rates `UART` accepts.
+pub const BAUD_RANGE: core::ops::RangeInclusive<u32> = 1_200..=921_600;
+
+/// Bytes waiting for the TX FIFO. At 115200 baud this drains in 90 ms.
+const TX_LEN: usize = 1024;
+
+pub type UartPins = (
+    Pin<UartTx, FunctionUart, PullDown>,
+    Pin<UartRx, FunctionUart, PullDown>,
+);
+type Uart = UartPeripheral<Enabled, pac::UART0, UartPins>;
+
+pub struct UartStream {
+    /// Only `None` while being reconfigured.
+    uart: Option<Uart>,
+    peripheral_clock: HertzU32,
+    baud: u32,
+    mode: Option<Mode>,
+    /// Something is listening; always so for a wired logger.
+    linked: bool,
+    tx: [u8; TX_LEN],
+    tx_len: usize,
+}
+
+fn config(baud: u32) -> UartConfig {
+    UartConfig::new(baud.Hz(), DataBits::Eight, None, StopBits::One)
+}
+
+impl UartStream {
+    /// Starts mirroring at `DEFAULT_BAUD`, so a logger works from power-on.
+    pub fn new(
+        uart: pac::UART0,
+        pins: UartPins,
+        resets: &mut pac::RESETS,
+        peripheral_clock: HertzU32,
+    ) -> Self {
+        let mut uart = UartPeripheral::new(uart, pins, resets)
+            .enable(config(DEFAULT_BAUD), peripheral_clock)
+            .unwrap();
+        // Received commands wake the main loop out of WFE.
+        uart.enable_rx_interrupt();
+        Self {
+            uart: Some(uart),
+            peripheral_clock,
+            baud: DEFAULT_BAUD,
+            mode: Some(Mode::Mirror),
+            linked: !cfg!(feature = "bluetooth"),
+            tx: [0; TX_LEN],
+            tx_len: 0,
+        }
+    }
+
+    pub fn baud(&self) -> u32 {
+        self.baud
+    }
+
+    /// `None` while switched off.
+    pub fn mode(&self) -> Option<Mode> {
+        self.mode
+    }
+
+    /// Switch mode, and rate if it changed. Anything still queued is lost.
+    pub fn set(&mut self, setting: Option<(u32, Mode)>) {
+        self.tx_len = 0;
+        self.mode = setting.map(|(_, mode)| mode);
+        let Some((baud, _)) = setting.filter(|&(baud, _)| baud != self.baud) else {
+            return;
+        };
+        if let Some(uart) = self.uart.take() {
+            let mut uart = uart
+                .disable()
+                .enable(config(baud), self.peripheral_clock)
+                .unwrap();
+            uart.enable_rx_interrupt();
+            self.uart = Some(uart);
+            self.baud = baud;
+        }
+    }
+
+    /// Hold output while nothing is listening, dropping what was queued.
+    #[cfg(feature = "bluetooth")]
+    pub fn set_linked(&mut self, linked: bool) {
+        self.linked = linked;
+        if !linked {
+            self.tx_len = 0;
+        }
+    }
+
+    /// Queue bytes for sending. Returns how many did not fit.
+    pub fn write(&mut self, bytes: &[u8]) -> usize {
+        if self.mode.is_none() || !self.linked {
+            return 0;
+        }
+        let queued = bytes.len().min(TX_LEN - self.tx_len);
+        self.tx[self.tx_len..self.tx_len + queued].copy_from_slice(&bytes[..queued]);
+        self.tx_len += queued;
+        self.pump();
+        bytes.len() - queued
+    }
+
+    /// Move queued bytes into the TX FIFO. While any remain, the FIFO
+    /// draining wakes the main loop to call this again.
+    pub fn pump(&mut self) {
+        let Some(uart) = &mut self.uart else { return };
+        if self.tx_len > 0 {
+            let written = match uart.write_raw(&self.tx[..self.tx_len]) {
+                Ok(rest) => self.tx_len - rest.len(),
+                Err(_) => 0,
+            };
+            self.tx.copy_within(written..self.tx_len, 0);
+            self.tx_len -= written;
+        }
+        if self.tx_len > 0 {
+            uart.enable_tx_interrupt();
+        } else {
+            uart.disable_tx_interrupt();
+        }
+    }
+
+    pub fn readable(&self) -> bool {
+        self.uart.as_ref().is_some_and(|u| u.uart_is_readable())
+    }
+
+    /// Received bytes; framing errors and bytes received while off are
+    /// dropped.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let Some(uart) = &self.uart else { return 0 };
+        match uart.read_raw(buf) {
+            Ok(count) if self.mode.is_some() => count,
+            _ => 0,
+        }
+    }
+}
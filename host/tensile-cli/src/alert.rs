@@ -0,0 +1,362 @@
+//! `--alert`: conditions watched on the live stream, for long tests nobody
+//! sits through.
+//!
+//! A condition is `force>X`, `force<X`, `rate>Y` (the magnitude of dF/dt,
+//! per second, in the stream's unit) or `gap>N` (more than N samples missing
+//! from the sequence). It alerts when it becomes true, then again only once
+//! it has cleared, and never more than once a minute of device time. Each
+//! alert is printed to stderr and, if asked, raised as a desktop notification
+//! (`notify-send` on Linux, `osascript` on macOS) and POSTed to a webhook as
+//!
+//! ```text
+//! {"type":"alert","condition":"force>500","message":"force 512.3 N above 500","t_us":51334567}
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use tensile_protocol::Sample;
+
+use crate::json::Json;
+
+/// Device time between two alerts of one condition.
+const COOLDOWN_US: u64 = 60_000_000;
+/// Without the device's `rate=`, dF/dt is taken over this much of the
+/// stream, which keeps the noise between neighbouring samples out of it.
+const RATE_SPAN_US: u64 = 1_000_000;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    ForceAbove(f64),
+    ForceBelow(f64),
+    RateAbove(f64),
+    SeqGap(u32),
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let bad = || format!("bad alert {text:?}; expected force>X, force<X, rate>Y or gap>N");
+        let at = text.find(['>', '<']).ok_or_else(bad)?;
+        let (name, limit) = text.split_at(at);
+        let (op, limit) = limit.split_at(1);
+        let number = || limit.parse::<f64>().ok().filter(|x| x.is_finite());
+        let condition = match (name, op) {
+            ("force", ">") => number().map(Condition::ForceAbove),
+            ("force", _) => number().map(Condition::ForceBelow),
+            ("rate", ">") => number().map(Condition::RateAbove),
+            ("gap", ">") => limit.parse().ok().map(Condition::SeqGap),
+            _ => None,
+        };
+        condition.ok_or_else(bad)
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::ForceAbove(x) => write!(f, "force>{x}"),
+            Condition::ForceBelow(x) => write!(f, "force<{x}"),
+            Condition::RateAbove(y) => write!(f, "rate>{y}"),
+            Condition::SeqGap(n) => write!(f, "gap>{n}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub condition: Condition,
+    pub message: String,
+    pub t_us: u64,
+}
+
+impl Alert {
+    pub fn json(&self) -> Json {
+        Json::Object(vec![
+            ("type".into(), "alert".into()),
+            (
+                "condition".into(),
+                self.condition.to_string().as_str().into(),
+            ),
+            ("message".into(), self.message.as_str().into()),
+            ("t_us".into(), Json::Number(self.t_us as f64)),
+        ])
+    }
+}
+
+/// Where alerts go besides stderr.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Alerting {
+    pub conditions: Vec<Condition>,
+    pub desktop: bool,
+    /// An `http://` URL.
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Armed {
+    condition: Condition,
+    /// True while the condition holds.
+    active: bool,
+    last_alert_us: Option<u64>,
+}
+
+/// Checks each sample against the conditions.
+#[derive(Debug)]
+pub struct Watcher {
+    conditions: Vec<Armed>,
+    last_seq: Option<u32>,
+    /// Recent `(t_us, force)`, oldest first, for the rate.
+    recent: VecDeque<(u64, f64)>,
+}
+
+impl Watcher {
+    pub fn new(conditions: &[Condition]) -> Self {
+        Self {
+            conditions: conditions
+                .iter()
+                .map(|&condition| Armed {
+                    condition,
+                    active: false,
+                    last_alert_us: None,
+                })
+                .collect(),
+            last_seq: None,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// The alerts `s` raises.
+    pub fn push(&mut self, s: &Sample) -> Vec<Alert> {
+        let missing = match self.last_seq.replace(s.seq) {
+            // A lower number is the device starting over, not a gap.
+            Some(last) if s.seq > last => s.seq - last - 1,
+            _ => 0,
+        };
+        if self.recent.back().is_some_and(|&(t, _)| t > s.t_us) {
+            self.recent.clear();
+        }
+        self.recent.push_back((s.t_us, s.force));
+        while self.recent.len() > 2 && s.t_us - self.recent[1].0 >= RATE_SPAN_US {
+            self.recent.pop_front();
+        }
+        let rate = s.rate.or_else(|| {
+            let &(t0, f0) = self.recent.front()?;
+            (s.t_us - t0 >= RATE_SPAN_US).then(|| (s.force - f0) / ((s.t_us - t0) as f64 / 1e6))
+        });
+        let unit = s.unit.as_str();
+        let mut alerts = Vec::new();
+        for armed in &mut self.conditions {
+            let message = match armed.condition {
+                Condition::ForceAbove(x) => {
+                    (s.force > x).then(|| format!("force {} {unit} above {x}", s.force))
+                }
+                Condition::ForceBelow(x) => {
+                    (s.force < x).then(|| format!("force {} {unit} below {x}", s.force))
+                }
+                Condition::RateAbove(y) => rate
+                    .filter(|r| r.abs() > y)
+                    .map(|r| format!("rate {r:.3} {unit}/s above {y}")),
+                Condition::SeqGap(n) => {
+                    (missing > n).then(|| format!("{missing} samples missing before seq {}", s.seq))
+                }
+            };
+            let was_active = std::mem::replace(&mut armed.active, message.is_some());
+            // A gap is over as soon as it is seen, so every one counts.
+            let new = !was_active || matches!(armed.condition, Condition::SeqGap(_));
+            let rested = armed
+                .last_alert_us
+                .is_none_or(|t| s.t_us.saturating_sub(t) >= COOLDOWN_US);
+            if let Some(message) = message.filter(|_| new && rested) {
+                armed.last_alert_us = Some(s.t_us);
+                alerts.push(Alert {
+                    condition: armed.condition,
+                    message,
+                    t_us: s.t_us,
+                });
+            }
+        }
+        alerts
+    }
+}
+
+impl Alerting {
+    /// Print `alert` and pass it on. Notifications and webhook calls run in
+    /// the background so the stream keeps being read.
+    pub fn raise(&self, alert: &Alert) {
+        eprintln!(
+            "alert {}: {} t={}",
+            alert.condition, alert.message, alert.t_us
+        );
+        if self.desktop {
+            notify(&alert.message);
+        }
+        if let Some(url) = self.webhook.clone() {
+            let body = alert.json().to_string();
+            std::thread::spawn(move || {
+                if let Err(e) = post(&url, &body) {
+                    eprintln!("webhook {url}: {e}");
+                }
+            });
+        }
+    }
+}
+
+fn notify(message: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let quoted = message.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut c = Command::new("osascript");
+        c.arg("-e").arg(format!(
+            "display notification \"{quoted}\" with title \"Tensile tester\""
+        ));
+        c
+    } else {
+        let mut c = Command::new("notify-send");
+        c.arg("Tensile tester").arg(message);
+        c
+    };
+    let spawned = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        // Reaped in the background so no zombie is left behind.
+        Ok(mut child) => {
+            std::thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("desktop notification: {e}"),
+    }
+}
+
+/// `(host:port, path)` of an `http://` URL.
+pub fn split_url(url: &str) -> Result<(String, String), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("webhook {url:?} must be an http:// URL"))?;
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    if host.is_empty() {
+        return Err(format!("webhook {url:?} has no host"));
+    }
+    let host = if host.contains(':') {
+        host.to_owned()
+    } else {
+        format!("{host}:80")
+    };
+    let path = if path.is_empty() { "/" } else { path };
+    Ok((host, path.to_owned()))
+}
+
+fn post(url: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (host, path) = split_url(url)?;
+    let address = std::net::ToSocketAddrs::to_socket_addrs(&host)?
+        .next()
+        .ok_or("no address")?;
+    let mut stream = TcpStream::connect_timeout(&address, WEBHOOK_TIMEOUT)?;
+    stream.set_read_timeout(Some(WEBHOOK_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    let mut status = [0; 12];
+    stream.read_exact(&mut status)?;
+    match &status[9..10] {
+        b"2" => Ok(()),
+        _ => Err(format!("answered {}", String::from_utf8_lossy(&status[9..])).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tensile_protocol::parse_line;
+
+    fn sample(force: f64, t_us: u64, seq: u32) -> Sample {
+        let line = format!("Force: {force} t={t_us} seq={seq} unit=N");
+        match parse_line(&line) {
+            Ok(tensile_protocol::Line::Sample(s)) => s,
+            other => panic!("not a sample: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_conditions() {
+        assert_eq!(
+            Condition::parse("force>500"),
+            Ok(Condition::ForceAbove(500.0))
+        );
+        assert_eq!(
+            Condition::parse("force<-2.5"),
+            Ok(Condition::ForceBelow(-2.5))
+        );
+        assert_eq!(Condition::parse("rate>20"), Ok(Condition::RateAbove(20.0)));
+        assert_eq!(Condition::parse("gap>0"), Ok(Condition::SeqGap(0)));
+        assert_eq!(Condition::SeqGap(3).to_string(), "gap>3");
+        for bad in ["force=5", "gap>-1", "rate<3", "temp>40", "force>"] {
+            assert!(Condition::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn alerts_on_crossing_then_rests() {
+        let mut watcher = Watcher::new(&[Condition::ForceAbove(100.0)]);
+        assert!(watcher.push(&sample(90.0, 0, 0)).is_empty());
+        let alerts = watcher.push(&sample(101.5, 10_000, 1));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].message, "force 101.5 N above 100");
+        assert!(watcher.push(&sample(120.0, 20_000, 2)).is_empty());
+        // Cleared and crossed again, but within the minute.
+        watcher.push(&sample(80.0, 30_000, 3));
+        assert!(watcher.push(&sample(120.0, 40_000, 4)).is_empty());
+        watcher.push(&sample(80.0, 61_000_000, 5));
+        assert_eq!(watcher.push(&sample(120.0, 62_000_000, 6)).len(), 1);
+    }
+
+    #[test]
+    fn alerts_on_gaps_and_rate() {
+        let mut watcher = Watcher::new(&[Condition::SeqGap(1), Condition::RateAbove(50.0)]);
+        // 10 N/s: under the limit, then a restart, which is no gap.
+        for i in 0..30 {
+            assert!(watcher
+                .push(&sample(i as f64, i * 100_000, i as u32))
+                .is_empty());
+        }
+        assert!(watcher.push(&sample(30.0, 3_000_000, 0)).is_empty());
+        let alerts = watcher.push(&sample(30.0, 3_100_000, 3));
+        assert_eq!(alerts[0].message, "2 samples missing before seq 3");
+        // 100 N/s, seen once a second of it is in.
+        let mut raised = Vec::new();
+        for i in 1..=12 {
+            raised.extend(watcher.push(&sample(
+                30.0 + 10.0 * i as f64,
+                3_100_000 + i * 100_000,
+                3 + i as u32,
+            )));
+        }
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].condition, Condition::RateAbove(50.0));
+        assert_eq!(
+            raised[0].json().to_string(),
+            format!(
+                r#"{{"type":"alert","condition":"rate>50","message":"{}","t_us":{}}}"#,
+                raised[0].message, raised[0].t_us
+            )
+        );
+    }
+
+    #[test]
+    fn splits_webhook_urls() {
+        assert_eq!(
+            split_url("http://lab.local:8080/hooks/rig2"),
+            Ok(("lab.local:8080".into(), "/hooks/rig2".into()))
+        );
+        assert_eq!(split_url("http://lab"), Ok(("lab:80".into(), "/".into())));
+        assert!(split_url("https://lab/x").is_err());
+        assert!(split_url("http:///x").is_err());
+    }
+}
@@ -0,0 +1,425 @@
+//! Command-line parsing.
+
+use std::path::PathBuf;
+
+use tensile_protocol::analysis::Geometry;
+
+use crate::alert::{self, Alerting, Condition};
+
+pub const USAGE: &str = "\
+usage: tensile-cli list
+       tensile-cli [TARGET] tare | start | stop | cal <counts/kg>
+       tensile-cli [TARGET] send <command...>
+       tensile-cli [TARGET] log [--output FILE] [--seconds N]
+           [--area MM2] [--gauge MM] [--extension AUX] [ALERTS]
+       tensile-cli [TARGET] export --output BASE [--seconds N]
+           [--area MM2] [--gauge MM] [--extension AUX]
+       tensile-cli [TARGET] run PLAN.toml
+       tensile-cli [TARGET] doctor [--seconds N] [--output FILE]
+       tensile-cli [TARGET] backup [--output FILE]
+       tensile-cli [TARGET] restore FILE
+       tensile-cli [TARGET] watch
+       tensile-cli [TARGET] serve [--listen ADDR:PORT] [ALERTS]
+       tensile-cli [TARGET] bridge mqtt --broker HOST[:PORT] [--topic PREFIX]
+           [--client-id ID] [--user NAME --password PASS]
+TARGET is --port DEV, --serial ID, --name NAME or --simulate; by default the
+first tester found.
+ALERTS are any of --alert force>X|force<X|rate>Y|gap>N (repeatable), --notify
+and --webhook http://HOST[:PORT]/PATH.";
+
+/// Which tester to talk to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// The first one found.
+    First,
+    Port(PathBuf),
+    /// By USB serial number, as shown by `INFO?`.
+    Serial(String),
+    /// By the name given it with `NAME`.
+    Name(String),
+    /// A simulated tester pulling a plastic dogbone.
+    Simulate,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    List,
+    /// Send one command line and print the replies.
+    Send(String),
+    /// Write Force lines to CSV, to `output` or stdout.
+    Log {
+        output: Option<PathBuf>,
+        seconds: Option<f64>,
+        /// Overrides the device's `AREA`/`GAUGE` metadata.
+        geometry: Geometry,
+        /// The `AUX` channel with the extensometer.
+        extension: usize,
+        alerts: Alerting,
+    },
+    /// Record until the test stops, then write `base`.csv and `base`.json.
+    Export {
+        base: PathBuf,
+        seconds: Option<f64>,
+        geometry: Geometry,
+        extension: usize,
+    },
+    /// Pull the specimens a test plan lists, exporting each.
+    Campaign(PathBuf),
+    /// Check the tester over and write a health report.
+    Doctor {
+        /// Idle stream to look at.
+        seconds: f64,
+        output: Option<PathBuf>,
+    },
+    /// Save its settings and calibration.
+    Backup(Option<PathBuf>),
+    /// Load the settings in a backup back onto it.
+    Restore(PathBuf),
+    /// Plot the force live in the terminal.
+    Watch,
+    /// Republish the stream to an MQTT broker.
+    Bridge(Mqtt),
+    /// Stream and take commands over a WebSocket at `listen`.
+    Serve {
+        listen: String,
+        alerts: Alerting,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mqtt {
+    /// `host` or `host:port`.
+    pub broker: String,
+    /// Prefix for the `sample`, `event` and `error` topics.
+    pub topic: String,
+    pub client_id: String,
+    /// User name and password.
+    pub credentials: Option<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Args {
+    pub target: Target,
+    pub command: Command,
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.into_iter();
+    let mut target = Target::First;
+    let command = loop {
+        let arg = args.next().ok_or("no command given")?;
+        match arg.as_str() {
+            "--port" => target = Target::Port(value(&mut args, "--port")?.into()),
+            "--serial" => target = Target::Serial(value(&mut args, "--serial")?),
+            "--name" => target = Target::Name(value(&mut args, "--name")?),
+            "--simulate" => target = Target::Simulate,
+            _ => break arg,
+        }
+    };
+    let command = match command.as_str() {
+        "list" => Command::List,
+        "tare" => Command::Send("TARE".into()),
+        "start" => Command::Send("START".into()),
+        "stop" => Command::Send("STOP".into()),
+        "cal" => {
+            let counts: i32 = value(&mut args, "cal")?
+                .parse()
+                .map_err(|_| "cal takes counts per kg as an integer")?;
+            Command::Send(format!("CAL {counts}"))
+        }
+        "send" => {
+            let line = args.by_ref().collect::<Vec<_>>().join(" ");
+            if line.is_empty() {
+                return Err("send needs a command".into());
+            }
+            Command::Send(line)
+        }
+        "log" | "export" => {
+            let (mut output, mut seconds) = (None, None);
+            let mut geometry = Geometry::default();
+            let mut extension = 0;
+            let mut alerts = Alerting::default();
+            while let Some(arg) = args.next() {
+                if command == "log" && alert_option(&arg, &mut args, &mut alerts)? {
+                    continue;
+                }
+                match arg.as_str() {
+                    "--output" => output = Some(value(&mut args, "--output")?.into()),
+                    "--seconds" => seconds = Some(number(&mut args, "--seconds")?),
+                    "--area" => geometry.area_mm2 = Some(number(&mut args, "--area")?),
+                    "--gauge" => geometry.gauge_mm = Some(number(&mut args, "--gauge")?),
+                    "--extension" => match number(&mut args, "--extension")? {
+                        n @ 0..=2 => extension = n,
+                        n => return Err(format!("no AUX channel {n}")),
+                    },
+                    _ => return Err(format!("unknown {command} option {arg:?}")),
+                }
+            }
+            if command == "log" {
+                Command::Log {
+                    output,
+                    seconds,
+                    geometry,
+                    extension,
+                    alerts: checked(alerts)?,
+                }
+            } else {
+                Command::Export {
+                    base: output.ok_or("export needs --output")?,
+                    seconds,
+                    geometry,
+                    extension,
+                }
+            }
+        }
+        "run" => Command::Campaign(value(&mut args, "run")?.into()),
+        "doctor" | "backup" => {
+            let (mut output, mut seconds) = (None, 10.0);
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--output" => output = Some(value(&mut args, "--output")?.into()),
+                    "--seconds" if command == "doctor" => seconds = number(&mut args, "--seconds")?,
+                    _ => return Err(format!("unknown {command} option {arg:?}")),
+                }
+            }
+            if command == "doctor" {
+                Command::Doctor { seconds, output }
+            } else {
+                Command::Backup(output)
+            }
+        }
+        "restore" => Command::Restore(value(&mut args, "restore")?.into()),
+        "watch" => Command::Watch,
+        "serve" => {
+            let mut listen = "127.0.0.1:8765".to_owned();
+            let mut alerts = Alerting::default();
+            while let Some(arg) = args.next() {
+                if alert_option(&arg, &mut args, &mut alerts)? {
+                    continue;
+                }
+                match arg.as_str() {
+                    "--listen" => listen = value(&mut args, "--listen")?,
+                    _ => return Err(format!("unknown serve option {arg:?}")),
+                }
+            }
+            Command::Serve {
+                listen,
+                alerts: checked(alerts)?,
+            }
+        }
+        "bridge" => {
+            let kind = value(&mut args, "bridge")?;
+            if kind != "mqtt" {
+                return Err(format!("unknown bridge {kind:?}"));
+            }
+            let (mut broker, mut user, mut password) = (None, None, None);
+            let mut topic = "tensile".to_owned();
+            let mut client_id = "tensile-cli".to_owned();
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--broker" => broker = Some(value(&mut args, "--broker")?),
+                    "--topic" => topic = value(&mut args, "--topic")?,
+                    "--client-id" => client_id = value(&mut args, "--client-id")?,
+                    "--user" => user = Some(value(&mut args, "--user")?),
+                    "--password" => password = Some(value(&mut args, "--password")?),
+                    _ => return Err(format!("unknown bridge option {arg:?}")),
+                }
+            }
+            let credentials = match (user, password) {
+                (Some(user), Some(password)) => Some((user, password)),
+                (None, None) => None,
+                _ => return Err("--user and --password go together".into()),
+            };
+            Command::Bridge(Mqtt {
+                broker: broker.ok_or("bridge mqtt needs --broker")?,
+                topic: topic.trim_end_matches('/').to_owned(),
+                client_id,
+                credentials,
+            })
+        }
+        _ => return Err(format!("unknown command {command:?}")),
+    };
+    if let Some(extra) = args.next() {
+        return Err(format!("unexpected {extra:?}"));
+    }
+    Ok(Args { target, command })
+}
+
+/// Take `arg` into `alerts` if it is one of the alert options.
+fn alert_option(
+    arg: &str,
+    args: &mut impl Iterator<Item = String>,
+    alerts: &mut Alerting,
+) -> Result<bool, String> {
+    match arg {
+        "--alert" => alerts
+            .conditions
+            .push(Condition::parse(&value(args, "--alert")?)?),
+        "--notify" => alerts.desktop = true,
+        "--webhook" => {
+            let url = value(args, "--webhook")?;
+            alert::split_url(&url)?;
+            alerts.webhook = Some(url);
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn checked(alerts: Alerting) -> Result<Alerting, String> {
+    if alerts.conditions.is_empty() && (alerts.desktop || alerts.webhook.is_some()) {
+        return Err("--notify and --webhook need an --alert".into());
+    }
+    Ok(alerts)
+}
+
+fn value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{option} needs a value"))
+}
+
+fn number<T: std::str::FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    option: &str,
+) -> Result<T, String> {
+    let s = value(args, option)?;
+    s.parse().map_err(|_| format!("bad {option} {s:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(line: &str) -> Result<Args, String> {
+        parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parses_commands_and_targets() {
+        assert_eq!(
+            parse_str("--serial E6614C31 cal 41000").unwrap(),
+            Args {
+                target: Target::Serial("E6614C31".into()),
+                command: Command::Send("CAL 41000".into()),
+            }
+        );
+        assert_eq!(
+            parse_str("--name 5kN-frame watch").unwrap(),
+            Args {
+                target: Target::Name("5kN-frame".into()),
+                command: Command::Watch,
+            }
+        );
+        assert_eq!(
+            parse_str("send FILTER AVG 8").unwrap().command,
+            Command::Send("FILTER AVG 8".into())
+        );
+        assert_eq!(
+            parse_str("--port /dev/ttyACM0 log --seconds 2.5").unwrap(),
+            Args {
+                target: Target::Port("/dev/ttyACM0".into()),
+                command: Command::Log {
+                    output: None,
+                    seconds: Some(2.5),
+                    geometry: Geometry::default(),
+                    extension: 0,
+                    alerts: Alerting::default(),
+                },
+            }
+        );
+        assert_eq!(
+            parse_str("log --area 12.5 --extension 2").unwrap().command,
+            Command::Log {
+                output: None,
+                seconds: None,
+                geometry: Geometry {
+                    area_mm2: Some(12.5),
+                    gauge_mm: None,
+                },
+                extension: 2,
+                alerts: Alerting::default(),
+            }
+        );
+        assert_eq!(
+            parse_str("serve --alert force>500 --alert gap>0 --webhook http://lab/hook --notify")
+                .unwrap()
+                .command,
+            Command::Serve {
+                listen: "127.0.0.1:8765".into(),
+                alerts: Alerting {
+                    conditions: vec![Condition::ForceAbove(500.0), Condition::SeqGap(0)],
+                    desktop: true,
+                    webhook: Some("http://lab/hook".into()),
+                },
+            }
+        );
+        assert_eq!(
+            parse_str("--simulate run plans/pla.toml").unwrap().command,
+            Command::Campaign("plans/pla.toml".into())
+        );
+        assert_eq!(
+            parse_str("--serial E6614C31 doctor --seconds 30").unwrap(),
+            Args {
+                target: Target::Serial("E6614C31".into()),
+                command: Command::Doctor {
+                    seconds: 30.0,
+                    output: None,
+                },
+            }
+        );
+        assert_eq!(
+            parse_str("backup --output rig2.json").unwrap().command,
+            Command::Backup(Some("rig2.json".into()))
+        );
+        assert_eq!(
+            parse_str("restore rig2.json").unwrap().command,
+            Command::Restore("rig2.json".into())
+        );
+        assert_eq!(
+            parse_str("export --output runs/a7").unwrap().command,
+            Command::Export {
+                base: "runs/a7".into(),
+                seconds: None,
+                geometry: Geometry::default(),
+                extension: 0,
+            }
+        );
+        assert_eq!(
+            parse_str("--simulate bridge mqtt --broker lab:1884 --topic lab/rig2/").unwrap(),
+            Args {
+                target: Target::Simulate,
+                command: Command::Bridge(Mqtt {
+                    broker: "lab:1884".into(),
+                    topic: "lab/rig2".into(),
+                    client_id: "tensile-cli".into(),
+                    credentials: None,
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(parse_str("").is_err());
+        assert!(parse_str("cal lots").is_err());
+        assert!(parse_str("send").is_err());
+        assert!(parse_str("tare now").is_err());
+        assert!(parse_str("log --seconds").is_err());
+        assert!(parse_str("log --gauge wide").is_err());
+        assert!(parse_str("log --extension 3").is_err());
+        assert!(parse_str("export --seconds 60").is_err());
+        assert!(parse_str("bridge mqtt").is_err());
+        assert!(parse_str("serve --listen").is_err());
+        assert!(parse_str("bridge amqp --broker lab").is_err());
+        assert!(parse_str("bridge mqtt --broker lab --user rig").is_err());
+        assert!(parse_str("--port").is_err());
+        assert!(parse_str("watch fast").is_err());
+        assert!(parse_str("run").is_err());
+        assert!(parse_str("doctor --seconds soon").is_err());
+        assert!(parse_str("backup --seconds 5").is_err());
+        assert!(parse_str("restore").is_err());
+        assert!(parse_str("log --alert force").is_err());
+        assert!(parse_str("log --notify").is_err());
+        assert!(parse_str("serve --alert gap>0 --webhook https://lab").is_err());
+        assert!(parse_str("export --output a --alert gap>0").is_err());
+    }
+}
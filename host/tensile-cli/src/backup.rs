@@ -0,0 +1,233 @@
+//! `backup` and `restore`: a tester's calibration and settings, as
+//! `CONFIG?` reports them, saved with its identity and fault log in one
+//! JSON file, then pushed back to the same board or a replacement.
+//!
+//! ```text
+//! {
+//!   "format": 1,
+//!   "created": "2026-10-15 09:37:39",               UTC
+//!   "info": "Info: fw=0.1.0 serial=E6614C31 ...",   the INFO? reply
+//!   "caps": "Caps: hx711=1 ... outputs=2",          the CAPS? reply
+//!   "config": ["CAL 41000","UNITS N"],              the CONFIG? commands
+//!   "errlog": ["ErrLog: seq=7 ...","ErrLog: end n=1"]
+//! }
+//! ```
+//!
+//! A restore first returns every setting `CONFIG?` can report to its
+//! power-on value, so nothing is left behind from before, then checks the
+//! board reports what the file holds. Settings live in RAM, so it lasts
+//! until the next power cycle. Backups from the old `tensile_cli.py`,
+//! which has `port` where this has `info`, restore the same way.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use tensile_protocol::{parse_line, Line, Reply, Transport};
+
+use crate::json::Json;
+use crate::{ack, datetime, query, text, unix_us, REPLY_WAIT};
+
+const FORMAT: u32 = 1;
+
+/// `CONFIG?` and `ERRLOG?` come as several lines.
+const LIST_WAIT: Duration = Duration::from_secs(3);
+
+/// Back to power-on values. CAL has no "off"; a backup without one leaves
+/// the board's own calibration alone.
+const RESET_COMMANDS: [&str; 9] = [
+    "FILTER OFF",
+    "RATE 0",
+    "UNITS RAW",
+    "ZERO TRACK OFF",
+    "TEMPCO 0",
+    "DUAL OFF",
+    "BREAK OFF",
+    "MODBUS 1",
+    "UART 115200 MIRROR",
+];
+const AUX_CHANNELS: usize = 3;
+
+/// What `restore` needs from a backup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archive {
+    pub caps: Option<String>,
+    pub config: Vec<String>,
+}
+
+impl Archive {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let json = Json::parse(text).ok_or("not a JSON object")?;
+        match json.get("format") {
+            Some(Json::Number(n)) if *n == FORMAT as f64 => {}
+            other => return Err(format!("unsupported backup format {other:?}")),
+        }
+        let Some(Json::Array(config)) = json.get("config") else {
+            return Err("no config list".into());
+        };
+        let config = config
+            .iter()
+            .map(|c| c.as_str().map(str::to_owned))
+            .collect::<Option<_>>()
+            .ok_or("config holds something other than commands")?;
+        Ok(Self {
+            caps: json.get("caps").and_then(Json::as_str).map(str::to_owned),
+            config,
+        })
+    }
+}
+
+/// The backup file for what the device sent.
+fn archive(
+    created: &str,
+    info: Option<&Line>,
+    caps: Option<&Line>,
+    config: &[String],
+    errlog: &[Line],
+) -> Json {
+    let reply = |line: Option<&Line>| match line {
+        Some(line @ Line::Reply(_)) => Json::String(text(line)),
+        _ => Json::Null,
+    };
+    let strings =
+        |items: &mut dyn Iterator<Item = String>| Json::Array(items.map(Json::String).collect());
+    Json::Object(vec![
+        ("format".to_owned(), Json::Number(FORMAT.into())),
+        ("created".to_owned(), created.into()),
+        ("info".to_owned(), reply(info)),
+        ("caps".to_owned(), reply(caps)),
+        ("config".to_owned(), strings(&mut config.iter().cloned())),
+        ("errlog".to_owned(), strings(&mut errlog.iter().map(text))),
+    ])
+}
+
+/// Save the device's settings to `output`.
+pub fn backup(port: &mut dyn Transport, output: &Path) -> Result<(), Box<dyn Error>> {
+    let info = query(port, "INFO?", &["Info"], REPLY_WAIT, |_| true)?.pop();
+    let caps = query(port, "CAPS?", &["Caps"], REPLY_WAIT, |_| true)?.pop();
+    let config = read_config(port)?.ok_or("the device did not send all of its CONFIG? reply")?;
+    let end = |r: &Reply| r.value.as_deref() == Some("end");
+    let errlog = query(port, "ERRLOG?", &["ErrLog"], LIST_WAIT, end)?;
+    let created = datetime(unix_us()? / 1_000_000);
+    let archive = archive(&created, info.as_ref(), caps.as_ref(), &config, &errlog);
+    fs::write(output, format!("{archive:#}\n"))?;
+    println!("saved {} settings to {}", config.len(), output.display());
+    Ok(())
+}
+
+/// Load the settings in `path` onto the device.
+pub fn restore(port: &mut dyn Transport, path: &Path) -> Result<(), Box<dyn Error>> {
+    let archive = Archive::parse(&fs::read_to_string(path)?)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    let caps = match query(port, "CAPS?", &["Caps"], REPLY_WAIT, |_| true)?.pop() {
+        Some(Line::Reply(r)) => Some(r),
+        _ => None,
+    };
+    let old = match archive.caps.as_deref().map(parse_line) {
+        Some(Ok(Line::Reply(r))) => Some(r),
+        _ => None,
+    };
+    let get = |caps: &Option<Reply>, key| caps.as_ref().and_then(|r| r.get(key)).map(str::to_owned);
+    for key in ["backend", "channels", "outputs"] {
+        let (was, is) = (get(&old, key), get(&caps, key));
+        if was != is {
+            eprintln!(
+                "warning: {key} was {}, this board has {}",
+                was.as_deref().unwrap_or("-"),
+                is.as_deref().unwrap_or("-")
+            );
+        }
+    }
+    let outputs = get(&caps, "outputs")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0);
+    let resets = RESET_COMMANDS
+        .iter()
+        .map(|c| c.to_string())
+        .chain((0..AUX_CHANNELS).map(|ch| format!("AUX {ch} OFF")))
+        .chain((0..outputs).map(|n: usize| format!("TRIG {n} OFF")));
+    // Not every build has every setting; those it lacks stay refused.
+    for command in resets {
+        ack(port, &command)?;
+    }
+    let mut rejected = 0;
+    for command in &archive.config {
+        let status = ack(port, command)?;
+        println!("{command:<32} {}", status.as_deref().unwrap_or("ok"));
+        rejected += status.is_some() as usize;
+    }
+    let restored = read_config(port)?;
+    if rejected > 0 {
+        return Err(format!("{rejected} setting(s) were rejected").into());
+    }
+    if restored.as_ref() != Some(&archive.config) {
+        return Err("the device's settings differ from the backup after restoring".into());
+    }
+    println!("restored {} settings", archive.config.len());
+    Ok(())
+}
+
+/// The `CONFIG?` commands; `None` if the reply was cut short.
+fn read_config(port: &mut dyn Transport) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+    let end = |r: &Reply| r.text.starts_with("n=");
+    let replies = query(port, "CONFIG?", &["Config"], LIST_WAIT, end)?;
+    let Some((Line::Reply(last), lines)) = replies.split_last() else {
+        return Ok(None);
+    };
+    let commands: Vec<String> = lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Reply(r) => Some(r.text.clone()),
+            _ => None,
+        })
+        .collect();
+    let n = last.get("n").and_then(|n| n.parse().ok());
+    Ok((end(last) && n == Some(commands.len())).then_some(commands))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backups_restore_what_they_saved() {
+        let line = |s: &str| parse_line(s).unwrap();
+        let config = ["CAL 41000".to_owned(), "UNITS N".to_owned()];
+        let json = archive(
+            "2026-10-15 09:37:39",
+            Some(&line("Info: fw=0.1.0 serial=E6614C31")),
+            Some(&line("Caps: hx711=1 backend=hx711 outputs=2")),
+            &config,
+            &[line("ErrLog: end n=0")],
+        );
+        let text = format!("{json:#}");
+        assert!(text.contains("\"errlog\": [\"ErrLog: end n=0\"]"));
+        assert_eq!(
+            Archive::parse(&text),
+            Ok(Archive {
+                caps: Some("Caps: hx711=1 backend=hx711 outputs=2".into()),
+                config: config.to_vec(),
+            })
+        );
+    }
+
+    #[test]
+    fn reads_older_backups() {
+        let text = r#"{
+  "format": 1,
+  "created": "2026-09-30 14:02:11",
+  "port": "COM4",
+  "caps": null,
+  "config": ["CAL 41000"],
+  "errlog": []
+}"#;
+        let archive = Archive::parse(text).unwrap();
+        assert_eq!(
+            (archive.caps, archive.config),
+            (None, vec!["CAL 41000".into()])
+        );
+        assert!(Archive::parse(&text.replace("\"format\": 1", "\"format\": 2")).is_err());
+        assert!(Archive::parse(&text.replace("[\"CAL 41000\"]", "[7]")).is_err());
+    }
+}
@@ -0,0 +1,510 @@
+//! `run`: a campaign of pulls from a test plan, one export per specimen.
+//!
+//! The plan is a small TOML file:
+//!
+//! ```text
+//! output = "runs/pla-batch-7"     directory for the exports, created if need be
+//! name = "{profile}-{rep}"        file name of each specimen; default "{n}"
+//! pause = true                    wait for the operator before each pull (default)
+//! seconds = 600                   give up on a pull after this long
+//!
+//! [meta]                          META entries for every specimen
+//! SPECIMEN = "B7-{n}"
+//! OPERATOR = "jdoe"
+//!
+//! [[step]]                        one per profile, in order
+//! profile = "pla"                 as stored with PROFILE on the device
+//! repeat = 20
+//! meta = { AREA = "12.5" }        added to, or replacing, the ones above
+//! ```
+//!
+//! In `name` and META values `{n}` is the specimen's number in the
+//! campaign, `{rep}` its number within the step and `{profile}` the
+//! profile, all counted from 1. Each pull is set up with `META`, started
+//! with `RUN <profile>` and recorded as `export` does. `campaign.csv` in
+//! the output directory lists every specimen with its outcome as it goes:
+//!
+//! ```text
+//! n,profile,rep,file,test,status,uts_mpa,elongation_pct
+//! 1,pla,1,pla-1,42,ok,48.20,12.40
+//! 2,pla,2,pla-2,,skipped,,
+//! ```
+//!
+//! The status is `ok`, `timeout` for a pull still going after `seconds`
+//! (which is then stopped), `failed` or `skipped`.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tensile_protocol::analysis::Geometry;
+use tensile_protocol::Transport;
+
+use crate::export;
+
+const INDEX: &str = "campaign.csv";
+const INDEX_HEADER: &str = "n,profile,rep,file,test,status,uts_mpa,elongation_pct";
+/// The device's limits on `META` entries.
+const META_KEY_LEN: usize = 16;
+const META_VALUE_LEN: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub output: PathBuf,
+    /// Template for each specimen's file name.
+    pub name: String,
+    pub pause: bool,
+    pub seconds: Option<f64>,
+    /// Templates for every specimen's `META` entries.
+    pub meta: Vec<(String, String)>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub profile: String,
+    pub repeat: u32,
+    pub meta: Vec<(String, String)>,
+}
+
+/// One pull of the campaign.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Specimen<'a> {
+    /// From 1, across the campaign.
+    pub n: u32,
+    /// From 1, within the step.
+    pub rep: u32,
+    pub step: &'a Step,
+}
+
+impl Specimen<'_> {
+    pub fn fill(&self, template: &str) -> String {
+        template
+            .replace("{n}", &self.n.to_string())
+            .replace("{rep}", &self.rep.to_string())
+            .replace("{profile}", &self.step.profile)
+    }
+
+    /// The plan's entries with the step's over them, filled in.
+    pub fn meta(&self, plan: &Plan) -> Vec<(String, String)> {
+        let mut meta: Vec<(String, String)> = Vec::new();
+        for (key, value) in plan.meta.iter().chain(&self.step.meta) {
+            meta.retain(|(k, _)| k != key);
+            meta.push((key.clone(), self.fill(value)));
+        }
+        meta
+    }
+}
+
+impl Plan {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut plan = Plan {
+            output: PathBuf::new(),
+            name: "{n}".into(),
+            pause: true,
+            seconds: None,
+            meta: Vec::new(),
+            steps: Vec::new(),
+        };
+        let mut output = None;
+        let mut section = "";
+        for (i, line) in text.lines().enumerate() {
+            let at = |e: String| format!("line {}: {e}", i + 1);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "[[step]]" {
+                plan.steps.push(Step {
+                    profile: String::new(),
+                    repeat: 1,
+                    meta: Vec::new(),
+                });
+                section = "step";
+                continue;
+            }
+            if line == "[meta]" {
+                section = "meta";
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(at(format!("unknown table {line}")));
+            }
+            let (key, value) = entry(line).map_err(at)?;
+            match (section, key) {
+                ("", "output") => output = Some(value.string().map_err(at)?.into()),
+                ("", "name") => plan.name = template(value.string().map_err(at)?).map_err(at)?,
+                ("", "pause") => plan.pause = value.bool().map_err(at)?,
+                ("", "seconds") => plan.seconds = Some(value.positive().map_err(at)?),
+                ("meta", key) => plan.meta.push(meta_entry(key, value).map_err(at)?),
+                ("step", key) => {
+                    let step = plan.steps.last_mut().expect("in a step");
+                    match key {
+                        "profile" => step.profile = value.string().map_err(at)?,
+                        "repeat" => step.repeat = value.count().map_err(at)?,
+                        "meta" => {
+                            let Value::Table(entries) = value else {
+                                return Err(at("meta must be an inline table".into()));
+                            };
+                            for (key, value) in entries {
+                                step.meta.push(meta_entry(&key, value).map_err(at)?);
+                            }
+                        }
+                        _ => return Err(at(format!("unknown step key {key}"))),
+                    }
+                }
+                _ => return Err(at(format!("unknown key {key}"))),
+            }
+        }
+        plan.output = output.ok_or("the plan needs an output directory")?;
+        if plan.steps.is_empty() {
+            return Err("the plan has no [[step]]".into());
+        }
+        if let Some(n) = plan.steps.iter().position(|s| s.profile.is_empty()) {
+            return Err(format!("step {} has no profile", n + 1));
+        }
+        Ok(plan)
+    }
+
+    /// Every pull, in order.
+    pub fn specimens(&self) -> impl Iterator<Item = Specimen<'_>> {
+        self.steps
+            .iter()
+            .flat_map(|step| (1..=step.repeat).map(move |rep| (step, rep)))
+            .zip(1..)
+            .map(|((step, rep), n)| Specimen { n, rep, step })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Number(f64),
+    Bool(bool),
+    Table(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn string(self) -> Result<String, String> {
+        match self {
+            Value::String(s) => Ok(s),
+            _ => Err("expected a string".into()),
+        }
+    }
+
+    fn bool(self) -> Result<bool, String> {
+        match self {
+            Value::Bool(b) => Ok(b),
+            _ => Err("expected true or false".into()),
+        }
+    }
+
+    fn positive(self) -> Result<f64, String> {
+        match self {
+            Value::Number(n) if n > 0.0 => Ok(n),
+            _ => Err("expected a positive number".into()),
+        }
+    }
+
+    fn count(self) -> Result<u32, String> {
+        match self {
+            Value::Number(n) if n >= 1.0 && n.fract() == 0.0 && n <= u32::MAX as f64 => {
+                Ok(n as u32)
+            }
+            _ => Err("expected a whole number from 1".into()),
+        }
+    }
+}
+
+/// `line` without a trailing `#` comment, leaving `#` inside strings.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// `key = value`, nothing after it.
+fn entry(line: &str) -> Result<(&str, Value), String> {
+    let (value, rest) = key_value(line)?;
+    if !rest.trim().is_empty() {
+        return Err(format!("unexpected {:?}", rest.trim()));
+    }
+    Ok(value)
+}
+
+fn key_value(text: &str) -> Result<((&str, Value), &str), String> {
+    let (key, rest) = text.split_once('=').ok_or("expected key = value")?;
+    let key = key.trim();
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(format!("bad key {key:?}"));
+    }
+    let (value, rest) = value(rest.trim_start())?;
+    Ok(((key, value), rest))
+}
+
+/// One value from the start of `text`, and what follows it.
+fn value(text: &str) -> Result<(Value, &str), String> {
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut s = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::String(s), &rest[i + 1..])),
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    _ => return Err("bad escape in string".into()),
+                },
+                c => s.push(c),
+            }
+        }
+        return Err("unterminated string".into());
+    }
+    if let Some(mut rest) = text.strip_prefix('{') {
+        let mut entries = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix('}') {
+                return Ok((Value::Table(entries), after));
+            }
+            if !entries.is_empty() {
+                rest = rest.strip_prefix(',').ok_or("expected , or }")?;
+            }
+            let ((key, value), after) = key_value(rest)?;
+            entries.push((key.to_owned(), value));
+            rest = after;
+        }
+    }
+    let end = text
+        .find(|c: char| c == ',' || c == '}' || c.is_whitespace())
+        .unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    let value = match word {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => Value::Number(
+            word.replace('_', "")
+                .parse()
+                .map_err(|_| format!("bad value {word:?}"))?,
+        ),
+    };
+    Ok((value, rest))
+}
+
+/// A `META` entry the device will take once filled in.
+fn meta_entry(key: &str, value: Value) -> Result<(String, String), String> {
+    if key.len() > META_KEY_LEN || key.contains('-') {
+        return Err(format!("bad META key {key:?}"));
+    }
+    let value = template(value.string()?)?;
+    if value.is_empty() || value.contains(|c: char| c == '=' || c.is_whitespace()) {
+        return Err(format!("bad META value {value:?}"));
+    }
+    Ok((key.to_ascii_uppercase(), value))
+}
+
+/// `text` if every `{...}` in it is one the campaign fills in.
+fn template(text: String) -> Result<String, String> {
+    let mut rest = text.as_str();
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..].find('}').ok_or("unclosed { in template")? + open;
+        match &rest[open..=close] {
+            "{n}" | "{rep}" | "{profile}" => rest = &rest[close + 1..],
+            other => return Err(format!("unknown placeholder {other}")),
+        }
+    }
+    Ok(text)
+}
+
+/// Carry out `plan`, asking on stderr and reading answers from `answers`
+/// before each pull when it pauses.
+pub fn campaign(
+    port: &mut dyn Transport,
+    plan: &Plan,
+    answers: &mut dyn BufRead,
+) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(&plan.output)?;
+    let index_path = plan.output.join(INDEX);
+    if index_path.exists() {
+        return Err(format!("{} already holds a campaign", plan.output.display()).into());
+    }
+    let mut index = File::create(&index_path)?;
+    writeln!(index, "{INDEX_HEADER}")?;
+    let total = plan.specimens().count();
+    let mut sent: Vec<String> = Vec::new();
+    for specimen in plan.specimens() {
+        let step = specimen.step;
+        let file = specimen.fill(&plan.name);
+        let mut row = format!("{},{},{},{file},", specimen.n, step.profile, specimen.rep);
+        if plan.pause {
+            eprint!(
+                "specimen {}/{total}: {} {}/{} as {file}; load it and press Enter \
+                 (s skips, q stops) ",
+                specimen.n, step.profile, specimen.rep, step.repeat
+            );
+            let mut answer = String::new();
+            if answers.read_line(&mut answer)? == 0 || answer.trim() == "q" {
+                eprintln!("campaign stopped");
+                break;
+            }
+            if answer.trim() == "s" {
+                writeln!(index, "{row},skipped,,")?;
+                continue;
+            }
+        }
+        let meta = specimen.meta(plan);
+        if let Some((key, _)) = meta.iter().find(|(_, v)| v.len() > META_VALUE_LEN) {
+            eprintln!(
+                "specimen {} failed: META {key} is over {META_VALUE_LEN} characters",
+                specimen.n
+            );
+            writeln!(index, "{row},failed,,")?;
+            continue;
+        }
+        // Entries an earlier step set and this one does not.
+        for key in sent
+            .iter()
+            .filter(|k| meta.iter().all(|(key, _)| key != *k))
+        {
+            port.send(&format!("META {key}="))?;
+        }
+        for (key, value) in &meta {
+            port.send(&format!("META {key}={value}"))?;
+        }
+        sent = meta.into_iter().map(|(k, _)| k).collect();
+        let result = export::export(
+            port,
+            &plan.output.join(&file),
+            plan.seconds.map(Duration::from_secs_f64),
+            Geometry::default(),
+            0,
+            Some(&format!("RUN {}", step.profile)),
+        );
+        match result {
+            Ok(run) => {
+                let status = if run.stopped() {
+                    "ok"
+                } else {
+                    eprintln!("specimen {} ran out of time; stopping it", specimen.n);
+                    port.send("STOP")?;
+                    "timeout"
+                };
+                let summary = run.summary();
+                eprintln!("{}", crate::summary(&summary));
+                let cell = |v: Option<f64>| v.map_or(String::new(), |v| format!("{v:.2}"));
+                row += &format!(
+                    "{},{status},{},{}",
+                    run.test().map_or(String::new(), |t| t.to_string()),
+                    cell(summary.uts_mpa),
+                    cell(summary.elongation_pct)
+                );
+            }
+            // The port is gone; no point asking for the next one.
+            Err(e) if e.is::<io::Error>() => return Err(e),
+            Err(e) => {
+                eprintln!("specimen {} failed: {e}", specimen.n);
+                row += ",failed,,";
+            }
+        }
+        writeln!(index, "{row}")?;
+    }
+    eprintln!("index in {}", index_path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = r#"
+# Two materials, ten of each.
+output = "runs/batch-7"
+name = "{profile}-{rep}"
+seconds = 600
+
+[meta]
+SPECIMEN = "B7-{n}"   # numbered across the batch
+note = "a#b"
+
+[[step]]
+profile = "pla"
+repeat = 2
+meta = { AREA = "12.5", note = "x" }
+
+[[step]]
+profile = "petg"
+repeat = 1_0
+"#;
+
+    #[test]
+    fn parses_a_plan() {
+        let plan = Plan::parse(PLAN).unwrap();
+        assert_eq!(plan.output, PathBuf::from("runs/batch-7"));
+        assert_eq!(plan.name, "{profile}-{rep}");
+        assert!(plan.pause);
+        assert_eq!(plan.seconds, Some(600.0));
+        assert_eq!(
+            plan.meta,
+            [
+                ("SPECIMEN".into(), "B7-{n}".into()),
+                ("NOTE".into(), "a#b".into())
+            ]
+        );
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[1].repeat, 10);
+
+        let specimens: Vec<_> = plan.specimens().collect();
+        assert_eq!(specimens.len(), 12);
+        let s = &specimens[2];
+        assert_eq!((s.n, s.rep, s.step.profile.as_str()), (3, 1, "petg"));
+        assert_eq!(s.fill(&plan.name), "petg-1");
+        assert_eq!(
+            specimens[1].meta(&plan),
+            [
+                ("SPECIMEN".into(), "B7-2".into()),
+                ("AREA".into(), "12.5".into()),
+                ("NOTE".into(), "x".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_plans() {
+        let with = |extra: &str| Plan::parse(&format!("{PLAN}{extra}"));
+        assert!(with("").is_ok());
+        assert!(Plan::parse("output = \"x\"").is_err());
+        assert!(Plan::parse("[[step]]\nprofile = \"pla\"").is_err());
+        assert!(with("[[step]]\nrepeat = 2").is_err());
+        assert!(with("[[step]]\nprofile = \"a\"\nrepeat = 0").is_err());
+        assert!(with("[[step]]\nprofile = \"a\"\nspeed = 5").is_err());
+        assert!(with("[meta]\nAREA = 12.5").is_err());
+        assert!(with("[meta]\nID = \"two words\"").is_err());
+        assert!(with("[meta]\nID = \"{serial}\"").is_err());
+        assert!(with("[results]").is_err());
+        assert_eq!(
+            Plan::parse("output = \"x\nname = 1"),
+            Err("line 1: unterminated string".into())
+        );
+        assert_eq!(
+            with("pause = maybe"),
+            Err("line 19: bad value \"maybe\"".into())
+        );
+    }
+}
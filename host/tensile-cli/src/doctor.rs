@@ -0,0 +1,361 @@
+//! `doctor`: the first thing to run when something looks wrong. It checks
+//! that the tester answers, measures idle noise and timestamp drift,
+//! optionally walks through a reference-weight check, and writes it all to
+//! one report to attach to a support request:
+//!
+//! ```text
+//! Tensile tester health report
+//! generated 2026-10-15 09:37:39 UTC
+//! overall   FAIL: noise
+//!
+//! == Device ==
+//! INFO?        Info: fw=0.1.0 git=ab12cd3 ...
+//! [PASS] device responds
+//! ...
+//! ```
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use tensile_protocol::{Decoder, Line, Reply, Transport};
+
+use crate::{ack, datetime, query, text, unix_us, REPLY_WAIT};
+
+/// Limits for the verdicts; generous enough that a healthy bench passes.
+const MAX_NOISE_COUNTS: f64 = 50.0;
+const MAX_DRIFT_PPM: f64 = 500.0;
+const MIN_SAMPLE_RATIO: f64 = 0.95;
+
+/// The fault log and the certificate come as several lines.
+const LIST_WAIT: Duration = Duration::from_secs(3);
+/// `CALCHECK POINT` averages for a while before it answers.
+const POINT_WAIT: Duration = Duration::from_secs(30);
+
+/// Queries for the device section, with the reply each expects.
+const IDENTITY: [(&str, &str); 6] = [
+    ("INFO?", "Info"),
+    ("CAPS?", "Caps"),
+    ("STATUS?", "Status"),
+    ("SAMPLERATE?", "SampleRate"),
+    ("UNITS?", "Units"),
+    ("SELFTEST?", "SelfTest"),
+];
+
+/// What has been found so far, printed as it goes.
+#[derive(Debug, Default)]
+pub struct Report {
+    lines: Vec<String>,
+    failures: Vec<String>,
+}
+
+impl Report {
+    fn section(&mut self, title: &str) {
+        self.lines.push(String::new());
+        self.line(format!("== {title} =="));
+    }
+
+    fn line(&mut self, text: String) {
+        println!("{text}");
+        self.lines.push(text);
+    }
+
+    fn verdict(&mut self, name: &str, ok: bool) {
+        self.line(format!("[{}] {name}", if ok { "PASS" } else { "FAIL" }));
+        if !ok {
+            self.failures.push(name.to_owned());
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// `PASS`, or `FAIL: ` and the checks that failed.
+    pub fn overall(&self) -> String {
+        match self.passed() {
+            true => "PASS".to_owned(),
+            false => format!("FAIL: {}", self.failures.join(", ")),
+        }
+    }
+
+    /// The report file, `generated` at the given time.
+    pub fn file(&self, generated: &str) -> String {
+        let mut out = format!(
+            "Tensile tester health report\ngenerated {generated}\noverall   {}\n",
+            self.overall()
+        );
+        for line in &self.lines {
+            out += line;
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// One idle sample: when it arrived at the host and its device time, in
+/// µs, and its counts.
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+    host_us: u64,
+    t_us: u64,
+    raw: i32,
+}
+
+/// Run the checks and write the report to `output`. The calibration check
+/// asks on stderr and reads answers from `answers`, and is skipped
+/// without any.
+pub fn doctor(
+    port: &mut dyn Transport,
+    seconds: f64,
+    output: &Path,
+    answers: Option<&mut dyn BufRead>,
+) -> Result<Report, Box<dyn Error>> {
+    let mut report = Report::default();
+    check_identity(port, &mut report)?;
+    report.section(&format!(
+        "Idle stream ({seconds} s, leave the cell unloaded)"
+    ));
+    let sps = query(port, "SAMPLERATE?", &["SampleRate"], REPLY_WAIT, |_| true)?
+        .iter()
+        .find_map(|line| match line {
+            Line::Reply(r) => r.get("sps")?.parse().ok(),
+            _ => None,
+        });
+    let readings = idle(port, Duration::from_secs_f64(seconds))?;
+    check_stream(&mut report, &readings, sps);
+    check_calibration(port, &mut report, answers)?;
+    let generated = datetime(unix_us()? / 1_000_000);
+    fs::write(output, report.file(&format!("{generated} UTC")))?;
+    Ok(report)
+}
+
+fn check_identity(port: &mut dyn Transport, report: &mut Report) -> Result<(), Box<dyn Error>> {
+    report.section("Device");
+    let mut answered = true;
+    let mut replies = Vec::new();
+    for (command, kind) in IDENTITY {
+        let line = query(port, command, &[kind], REPLY_WAIT, |_| true)?.pop();
+        let shown = line.as_ref().map_or("(no reply)".to_owned(), text);
+        report.line(format!("{command:<12} {shown}"));
+        match line {
+            Some(Line::Reply(r)) => replies.push(r),
+            _ => answered = false,
+        }
+    }
+    report.verdict("device responds", answered);
+    report.verdict(
+        "sensor healthy",
+        field(&replies, "Status", "sensor") == Some("ok"),
+    );
+    report.verdict(
+        "boot self-test",
+        field(&replies, "SelfTest", "result") == Some("pass"),
+    );
+
+    report.section("Fault log");
+    let end = |r: &Reply| r.value.as_deref() == Some("end");
+    for line in query(port, "ERRLOG?", &["ErrLog"], LIST_WAIT, end)? {
+        report.line(text(&line));
+    }
+    Ok(())
+}
+
+fn field<'a>(replies: &'a [Reply], kind: &str, key: &str) -> Option<&'a str> {
+    replies.iter().find(|r| r.kind == kind)?.get(key)
+}
+
+/// The samples that carry raw counts, for `duration`.
+fn idle(port: &mut dyn Transport, duration: Duration) -> Result<Vec<Reading>, Box<dyn Error>> {
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    let mut readings = Vec::new();
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        let n = port.read(&mut buf)?;
+        let host_us = unix_us()?;
+        for line in decoder.push(&buf[..n]).into_iter().flatten() {
+            if let Line::Sample(s) = line {
+                if let Some(raw) = s.raw {
+                    readings.push(Reading {
+                        host_us,
+                        t_us: s.t_us,
+                        raw,
+                    });
+                }
+            }
+        }
+    }
+    Ok(readings)
+}
+
+/// Noise, drift against the host clock and, at `sps`, missing samples.
+fn check_stream(report: &mut Report, readings: &[Reading], sps: Option<u32>) {
+    let [first, .., last] = readings else {
+        report.line("no Force samples received".into());
+        report.verdict("stream", false);
+        return;
+    };
+    let n = readings.len() as f64;
+    let mean = readings.iter().map(|r| r.raw as f64).sum::<f64>() / n;
+    let sigma = (readings
+        .iter()
+        .map(|r| (r.raw as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt();
+    let (min, max) = readings.iter().fold((i32::MAX, i32::MIN), |(lo, hi), r| {
+        (lo.min(r.raw), hi.max(r.raw))
+    });
+    report.line(format!("samples      {}", readings.len()));
+    report.line(format!("mean         {mean:.1} counts"));
+    report.line(format!("sigma        {sigma:.2} counts"));
+    report.line(format!("peak-to-peak {} counts", max - min));
+    report.verdict("noise", sigma <= MAX_NOISE_COUNTS);
+
+    // USB latency blurs single samples but not a span of several seconds.
+    let host_s = last.host_us.saturating_sub(first.host_us) as f64 / 1e6;
+    let device_s = last.t_us.saturating_sub(first.t_us) as f64 / 1e6;
+    let drift_ppm = match host_s > 0.0 {
+        true => (device_s - host_s) / host_s * 1e6,
+        false => 0.0,
+    };
+    report.line(format!("drift        {drift_ppm:+.0} ppm vs host clock"));
+    report.verdict("timestamp drift", drift_ppm.abs() <= MAX_DRIFT_PPM);
+
+    if let Some(sps) = sps.filter(|&sps| sps > 0) {
+        let expected = device_s * sps as f64 + 1.0;
+        let gaps = readings
+            .windows(2)
+            .filter(|w| w[1].t_us.saturating_sub(w[0].t_us) as f64 > 1.5e6 / sps as f64)
+            .count();
+        report.line(format!(
+            "received     {} of ~{expected:.0} expected at {sps} SPS, {gaps} gaps",
+            readings.len()
+        ));
+        report.verdict("sample continuity", n / expected >= MIN_SAMPLE_RATIO);
+    }
+}
+
+fn check_calibration(
+    port: &mut dyn Transport,
+    report: &mut Report,
+    answers: Option<&mut dyn BufRead>,
+) -> Result<(), Box<dyn Error>> {
+    report.section("Calibration check");
+    let Some(answers) = answers else {
+        report.line("skipped (not interactive)".into());
+        return Ok(());
+    };
+    if !ask(answers, "Run a reference-weight check now? [y/N] ")?.eq_ignore_ascii_case("y") {
+        report.line("skipped by user".into());
+        return Ok(());
+    }
+    if let Some(e) = ack(port, "CALCHECK START")? {
+        report.line(format!("CALCHECK START: {e}"));
+        report.verdict("calibration check", false);
+        return Ok(());
+    }
+    loop {
+        let grams = ask(
+            answers,
+            "Place a reference weight and enter its mass in g (blank to finish): ",
+        )?;
+        if grams.is_empty() {
+            break;
+        }
+        eprintln!("measuring...");
+        let command = format!("CALCHECK POINT {grams}");
+        for line in query(port, &command, &["CalPoint"], POINT_WAIT, |_| true)? {
+            report.line(text(&line));
+        }
+    }
+    // The certificate is one CalCheck line followed by its CalPoint lines.
+    let certificate = query(
+        port,
+        "CALCHECK END",
+        &["CalCheck", "CalPoint"],
+        LIST_WAIT,
+        |_| false,
+    )?;
+    for line in &certificate {
+        report.line(text(line));
+    }
+    let issued = certificate
+        .iter()
+        .any(|line| matches!(line, Line::Reply(r) if r.kind == "CalCheck"));
+    report.verdict("calibration check", issued);
+    Ok(())
+}
+
+fn ask(answers: &mut dyn BufRead, prompt: &str) -> io::Result<String> {
+    eprint!("{prompt}");
+    let mut answer = String::new();
+    answers.read_line(&mut answer)?;
+    Ok(answer.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n` readings at 10 SPS, the device clock running `ppm` fast.
+    fn readings(n: u64, ppm: f64) -> Vec<Reading> {
+        (0..n)
+            .map(|i| Reading {
+                host_us: 1_000 + i * 100_000,
+                t_us: (i as f64 * 100_000.0 * (1.0 + ppm / 1e6)) as u64,
+                raw: if i % 2 == 0 { 1_000 } else { 1_004 },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_quiet_steady_stream_passes() {
+        let mut report = Report::default();
+        check_stream(&mut report, &readings(101, 20.0), Some(10));
+        assert!(report.passed(), "{:?}", report.lines);
+        assert!(report
+            .lines
+            .contains(&"sigma        2.00 counts".to_owned()));
+        assert!(report
+            .lines
+            .contains(&"drift        +20 ppm vs host clock".to_owned()));
+    }
+
+    #[test]
+    fn gaps_and_drift_fail() {
+        let mut stream = readings(101, 2_000.0);
+        stream.drain(20..40);
+        let mut report = Report::default();
+        check_stream(&mut report, &stream, Some(10));
+        assert_eq!(report.overall(), "FAIL: timestamp drift, sample continuity");
+        assert!(report
+            .lines
+            .iter()
+            .any(|l| l.ends_with("at 10 SPS, 1 gaps")));
+
+        let mut report = Report::default();
+        check_stream(&mut report, &stream[..1], None);
+        assert_eq!(report.overall(), "FAIL: stream");
+    }
+
+    #[test]
+    fn report_file_opens_with_the_verdict() {
+        let mut report = Report::default();
+        report.section("Device");
+        report.verdict("device responds", true);
+        let file = report.file("2026-10-15 09:37:39 UTC");
+        assert_eq!(
+            file,
+            "Tensile tester health report\n\
+             generated 2026-10-15 09:37:39 UTC\n\
+             overall   PASS\n\
+             \n\
+             == Device ==\n\
+             [PASS] device responds\n"
+        );
+    }
+}
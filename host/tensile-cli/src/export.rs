@@ -0,0 +1,452 @@
+//! `export`: one test recorded in the exchange format.
+//!
+//! `<base>.csv` opens with a block of `# key: value` lines, then has the
+//! same columns as `log`:
+//!
+//! ```text
+//! # format: tensile-export 1
+//! # exported: unix=1792065600
+//! # info: fw=0.1.0 git=ab12cd3 built=2026-10-01 board=pico serial=E6614C31 ...
+//! # config: CAL 41000              one per line of the CONFIG? reply
+//! # meta: SPECIMEN=A7 AREA=12.5    as the META? reply
+//! # test: 42                       the device's number for the test
+//! # sync: error_us=180 drift_ppm=-12.4
+//! # geometry: area_mm2=12.5 gauge_mm=50 extension=aux0
+//! # units: host_time_s=s wall_time_s=s t_us=us force=N raw=counts stress_mpa=MPa strain=1
+//! host_time_s,wall_time_s,t_us,seq,force,unit,raw,stress_mpa,strain
+//! ```
+//!
+//! Readers should skip `#` lines they don't know. The stress and strain
+//! columns are there only with a `geometry` line, and either may be empty.
+//! `wall_time_s` is the device time mapped through `SYNC`, empty without a
+//! `sync` line.
+//! `<base>.json` summarises the run:
+//!
+//! ```text
+//! {
+//!   "format": "tensile-export",
+//!   "version": 1,
+//!   "csv": "a7.csv",
+//!   "exported_unix": 1792065600,
+//!   "device": { "fw": "0.1.0", "serial": "E6614C31", ... },    INFO? fields
+//!   "metadata": { "SPECIMEN": "A7", "AREA": "12.5" },
+//!   "test": 42,
+//!   "unit": "N",
+//!   "samples": 4810,
+//!   "duration_s": 60.125,
+//!   "peak": { "force": 812.4, "t_us": 51334567 },
+//!   "uts_mpa": 65.0, "yield_mpa": 48.2, "modulus_mpa": 2710.5, "r_squared": 0.9993,
+//!   "break": { "force": 401.2, "t_us": 52110004, "elongation_pct": 12.4 }
+//! }
+//! ```
+//!
+//! Results that could not be worked out are `null`. The files are written
+//! when recording ends: at `TEST_STOP`, or after `--seconds`.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tensile_protocol::analysis::{Geometry, Summary, Tensile};
+use tensile_protocol::{ClockSync, Decoder, Exchange, Line, Reply, Sample, Transport};
+
+use crate::json::Json;
+use crate::{clock_sync, csv_row, unix_us, CSV_HEADER, CSV_STRESS_HEADER, REPLY_WAIT, SYNC_EVERY};
+
+const FORMAT: &str = "tensile-export";
+const VERSION: u32 = 1;
+
+/// What the device says about itself before the run.
+#[derive(Debug, Default)]
+pub struct Device {
+    pub info: Vec<(String, String)>,
+    /// `CONFIG?` lines, without the `n=` count.
+    pub config: Vec<String>,
+    pub meta: Vec<(String, String)>,
+}
+
+impl Device {
+    pub fn query(port: &mut dyn Transport) -> Result<Self, Box<dyn Error>> {
+        for command in ["INFO?", "CONFIG?", "META?"] {
+            port.send(command)?;
+        }
+        let mut device = Device::default();
+        let mut decoder = Decoder::new();
+        let mut buf = [0; 4096];
+        let deadline = Instant::now() + REPLY_WAIT;
+        while Instant::now() < deadline {
+            let n = port.read(&mut buf)?;
+            for line in decoder.push(&buf[..n]).into_iter().flatten() {
+                let Line::Reply(Reply {
+                    kind, fields, text, ..
+                }) = line
+                else {
+                    continue;
+                };
+                match kind.as_str() {
+                    "Info" => device.info = fields,
+                    "Config" if !text.starts_with("n=") => device.config.push(text),
+                    "Meta" => device.meta = fields,
+                    _ => {}
+                }
+            }
+        }
+        Ok(device)
+    }
+}
+
+/// Everything that goes in the two files.
+pub struct Run {
+    device: Device,
+    geometry: Geometry,
+    extension: usize,
+    tensile: Option<Tensile>,
+    sync: ClockSync,
+    rows: Vec<String>,
+    /// From `TEST_START`, when recording began before it.
+    test: Option<u32>,
+    first: Option<Sample>,
+    last: Option<Sample>,
+    /// Largest magnitude.
+    peak: Option<Sample>,
+    /// The last sample before `BREAK`, and the event's time.
+    broke: Option<(Sample, u64)>,
+    /// Whether `TEST_STOP` came, rather than time running out.
+    stopped: bool,
+}
+
+impl Run {
+    pub fn new(device: Device, geometry: Geometry, extension: usize, sync: ClockSync) -> Self {
+        let geometry = geometry.or(Geometry::from_meta(&device.meta));
+        Self {
+            device,
+            geometry,
+            extension,
+            tensile: (geometry != Geometry::default()).then(|| Tensile::new(geometry, extension)),
+            sync,
+            rows: Vec::new(),
+            test: None,
+            first: None,
+            last: None,
+            peak: None,
+            broke: None,
+            stopped: false,
+        }
+    }
+
+    /// Take in one line, read at `host_us`; false once the test has stopped.
+    pub fn push(&mut self, host_us: u64, line: Line) -> bool {
+        match line {
+            Line::Sample(s) => {
+                let point = self.tensile.as_mut().map(|t| t.push(&s));
+                let wall_s = self.sync.host_us(s.t_us).map(|us| us / 1e6);
+                self.rows
+                    .push(csv_row(host_us as f64 / 1e6, wall_s, &s, point));
+                if self
+                    .peak
+                    .as_ref()
+                    .is_none_or(|p| s.force.abs() > p.force.abs())
+                {
+                    self.peak = Some(s.clone());
+                }
+                self.first.get_or_insert_with(|| s.clone());
+                self.last = Some(s);
+            }
+            Line::Event(e) if e.name == "BREAK" => {
+                if let Some(tensile) = &mut self.tensile {
+                    tensile.mark_break();
+                }
+                if let (None, Some(last)) = (&self.broke, &self.last) {
+                    self.broke = Some((last.clone(), e.t_us));
+                }
+            }
+            Line::Event(e) if e.name == "TEST_START" => {
+                self.test = e.get("test").and_then(|n| n.parse().ok());
+            }
+            Line::Event(e) if e.name == "TEST_STOP" => {
+                self.stopped = true;
+                return false;
+            }
+            Line::Reply(r) => {
+                if let Some(exchange) = Exchange::from_reply(&r, host_us) {
+                    self.sync.push(exchange);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    pub fn csv(&self, exported_unix: u64) -> String {
+        let mut out = format!("# format: {FORMAT} {VERSION}\n# exported: unix={exported_unix}\n");
+        out += &format!("# info:{}\n", pairs(&self.device.info));
+        for line in &self.device.config {
+            out += &format!("# config: {line}\n");
+        }
+        out += &format!("# meta:{}\n", pairs(&self.device.meta));
+        if let Some(test) = self.test {
+            out += &format!("# test: {test}\n");
+        }
+        let mut header = CSV_HEADER.to_owned();
+        if let (Some(error), Some(drift)) = (self.sync.uncertainty_us(), self.sync.drift_ppm()) {
+            out += &format!("# sync: error_us={error} drift_ppm={drift:.1}\n");
+        }
+        let mut units = "host_time_s=s wall_time_s=s t_us=us".to_owned();
+        units += &format!(" force={} raw=counts", self.unit());
+        if self.tensile.is_some() {
+            let mm = |v: Option<f64>| v.map_or("-".to_owned(), |v| v.to_string());
+            out += &format!(
+                "# geometry: area_mm2={} gauge_mm={} extension=aux{}\n",
+                mm(self.geometry.area_mm2),
+                mm(self.geometry.gauge_mm),
+                self.extension
+            );
+            header += CSV_STRESS_HEADER;
+            units += " stress_mpa=MPa strain=1";
+        }
+        out += &format!("# units: {units}\n{header}\n");
+        for row in &self.rows {
+            out += row;
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn json(&self, csv_name: &str, exported_unix: u64) -> Json {
+        let object = |fields: &[(String, String)]| {
+            Json::Object(
+                fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.as_str().into()))
+                    .collect(),
+            )
+        };
+        let at = |s: &Sample| {
+            vec![
+                ("force".to_owned(), Json::Number(s.force)),
+                ("t_us".to_owned(), Json::Number(s.t_us as f64)),
+            ]
+        };
+        let summary = self.summary();
+        let elastic = summary.elastic;
+        let duration = self
+            .first
+            .as_ref()
+            .zip(self.last.as_ref())
+            .map(|(a, b)| b.t_us.saturating_sub(a.t_us) as f64 / 1e6);
+        let field = |k: &str, v: Json| (k.to_owned(), v);
+        Json::Object(vec![
+            field("format", FORMAT.into()),
+            field("version", Json::Number(VERSION.into())),
+            field("csv", csv_name.into()),
+            field("exported_unix", Json::Number(exported_unix as f64)),
+            field("device", object(&self.device.info)),
+            field("metadata", object(&self.device.meta)),
+            field("test", self.test.map(f64::from).into()),
+            field("unit", self.unit().into()),
+            field("samples", Json::Number(self.rows.len() as f64)),
+            field("duration_s", duration.into()),
+            field(
+                "peak",
+                self.peak
+                    .as_ref()
+                    .map_or(Json::Null, |p| Json::Object(at(p))),
+            ),
+            field("uts_mpa", summary.uts_mpa.into()),
+            field("yield_mpa", summary.yield_mpa.into()),
+            field("modulus_mpa", elastic.map(|e| e.modulus_mpa).into()),
+            field("r_squared", elastic.map(|e| e.r_squared).into()),
+            field(
+                "break",
+                self.broke.as_ref().map_or(Json::Null, |(s, t_us)| {
+                    let mut fields = at(s);
+                    fields[1].1 = Json::Number(*t_us as f64);
+                    fields.push(field("elongation_pct", summary.elongation_pct.into()));
+                    Json::Object(fields)
+                }),
+            ),
+        ])
+    }
+
+    /// The device's number for the test, from `TEST_START`.
+    pub fn test(&self) -> Option<u32> {
+        self.test
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// What could be worked out; nothing without the specimen geometry.
+    pub fn summary(&self) -> Summary {
+        self.tensile
+            .as_ref()
+            .map(|t| t.summary())
+            .unwrap_or_default()
+    }
+
+    fn unit(&self) -> &'static str {
+        self.first.as_ref().map_or("-", |s| s.unit.as_str())
+    }
+}
+
+fn pairs(fields: &[(String, String)]) -> String {
+    fields.iter().map(|(k, v)| format!(" {k}={v}")).collect()
+}
+
+/// Record until the test stops or `duration` is up, then write the files.
+/// `start`, when given, is sent once recording has begun; an error in
+/// reply ends the run without writing anything.
+pub fn export(
+    port: &mut dyn Transport,
+    base: &Path,
+    duration: Option<Duration>,
+    geometry: Geometry,
+    extension: usize,
+    start: Option<&str>,
+) -> Result<Run, Box<dyn Error>> {
+    let device = Device::query(port)?;
+    let mut sync = ClockSync::new();
+    clock_sync(port, &mut sync)?;
+    let synced = sync.uncertainty_us().is_some();
+    let mut run = Run::new(device, geometry, extension, sync);
+    eprintln!("recording until TEST_STOP");
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    if let Some(command) = start {
+        port.send(command)?;
+    }
+    let began = Instant::now();
+    let mut last_sync = Instant::now();
+    'record: while duration.is_none_or(|d| began.elapsed() < d) {
+        if synced && last_sync.elapsed() >= SYNC_EVERY {
+            port.send(&format!("SYNC {}", unix_us()?))?;
+            last_sync = Instant::now();
+        }
+        let n = port.read(&mut buf)?;
+        let host_us = unix_us()?;
+        for line in decoder.push(&buf[..n]) {
+            match line {
+                Ok(Line::Error { message, .. }) => {
+                    if let (Some(command), None) = (start, &run.first) {
+                        return Err(format!("device refused {command}: {message}").into());
+                    }
+                }
+                Ok(line) => {
+                    if !run.push(host_us, line) {
+                        break 'record;
+                    }
+                }
+                Err(e) => eprintln!("skipped line: {e}"),
+            }
+        }
+    }
+    let unix = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let csv = base.with_extension("csv");
+    let json = base.with_extension("json");
+    let csv_name = csv
+        .file_name()
+        .map_or(String::new(), |n| n.to_string_lossy().into_owned());
+    fs::write(&csv, run.csv(unix))?;
+    fs::write(&json, format!("{:#}\n", run.json(&csv_name, unix)))?;
+    eprintln!("wrote {} and {}", csv.display(), json.display());
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tensile_protocol::parse_line;
+
+    fn run() -> Run {
+        let device = Device {
+            info: vec![("serial".into(), "E6614C31".into())],
+            config: vec!["CAL 41000".into(), "UNITS N".into()],
+            meta: vec![("AREA".into(), "10".into()), ("GAUGE".into(), "50".into())],
+        };
+        let mut sync = ClockSync::new();
+        sync.push(Exchange {
+            sent_us: 100,
+            device_us: 0,
+            received_us: 300,
+        });
+        let mut run = Run::new(device, Geometry::default(), 0, sync);
+        run.push(0, parse_line("Event: TEST_START test=42 t=900000").unwrap());
+        for (i, line) in [
+            "Force: 0 raw=0 t=1000000 seq=0 unit=N aux0=1.000",
+            "Force: 500 raw=20500 t=1500000 seq=1 unit=N aux0=1.500",
+            "Force: 250 raw=10250 t=2000000 seq=2 unit=N aux0=3.000",
+            "Event: BREAK peak=20500 force=10250 t=2000100",
+            "Force: 0 raw=0 t=2500000 seq=3 unit=N aux0=3.500",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert!(run.push(i as u64 * 1_000_000, parse_line(line).unwrap()));
+        }
+        assert!(!run.push(
+            9_000_000,
+            parse_line("Event: TEST_STOP reason=break t=2600000").unwrap()
+        ));
+        run
+    }
+
+    #[test]
+    fn csv_header_block() {
+        let csv = run().csv(1_792_065_600);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(
+            lines[..10],
+            [
+                "# format: tensile-export 1",
+                "# exported: unix=1792065600",
+                "# info: serial=E6614C31",
+                "# config: CAL 41000",
+                "# config: UNITS N",
+                "# meta: AREA=10 GAUGE=50",
+                "# test: 42",
+                "# sync: error_us=100 drift_ppm=0.0",
+                "# geometry: area_mm2=10 gauge_mm=50 extension=aux0",
+                "# units: host_time_s=s wall_time_s=s t_us=us force=N raw=counts \
+                 stress_mpa=MPa strain=1",
+            ]
+        );
+        assert_eq!(lines[10], format!("{CSV_HEADER}{CSV_STRESS_HEADER}"));
+        assert_eq!(
+            lines[12],
+            "1.000000,1.500200,1500000,1,500,N,20500,50.000000,0.010000"
+        );
+        assert_eq!(lines.len(), 15);
+    }
+
+    #[test]
+    fn json_summary() {
+        let Json::Object(fields) = run().json("a7.csv", 1_792_065_600) else {
+            panic!("not an object");
+        };
+        let get = |key: &str| fields.iter().find(|(k, _)| k == key).unwrap().1.clone();
+        assert_eq!(get("csv"), "a7.csv".into());
+        assert_eq!(get("test"), Json::Number(42.0));
+        assert_eq!(get("unit"), "N".into());
+        assert_eq!(get("samples"), Json::Number(4.0));
+        assert_eq!(get("duration_s"), Json::Number(1.5));
+        assert_eq!(get("uts_mpa"), Json::Number(50.0));
+        assert_eq!(get("modulus_mpa"), Json::Null);
+        assert_eq!(
+            get("peak"),
+            Json::Object(vec![
+                ("force".into(), Json::Number(500.0)),
+                ("t_us".into(), Json::Number(1_500_000.0)),
+            ])
+        );
+        assert_eq!(
+            get("break"),
+            Json::Object(vec![
+                ("force".into(), Json::Number(250.0)),
+                ("t_us".into(), Json::Number(2_000_100.0)),
+                ("elongation_pct".into(), Json::Number(4.0)),
+            ])
+        );
+    }
+}
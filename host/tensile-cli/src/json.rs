@@ -0,0 +1,353 @@
+//! Just enough JSON for the export summary, bridge payloads and `serve`
+//! messages.
+//!
+//! `{}` writes it on one line, `{:#}` indented.
+
+use std::fmt::{self, Write};
+
+use tensile_protocol::{Event, Sample};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl From<Option<f64>> for Json {
+    fn from(v: Option<f64>) -> Self {
+        v.map_or(Json::Null, Json::Number)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_owned())
+    }
+}
+
+/// `t_us`, `seq`, `force` and `unit`, then whichever of `raw`, `rate`,
+/// `ch1`..., `aux0`-`aux2` and `temp_c` the line had.
+impl From<&Sample> for Json {
+    fn from(s: &Sample) -> Self {
+        let mut fields = vec![
+            ("t_us".to_owned(), Json::Number(s.t_us as f64)),
+            ("seq".to_owned(), Json::Number(s.seq.into())),
+            ("force".to_owned(), Json::Number(s.force)),
+            ("unit".to_owned(), s.unit.as_str().into()),
+        ];
+        let mut optional = |key: String, v: Option<f64>| {
+            if let Some(v) = v {
+                fields.push((key, Json::Number(v)));
+            }
+        };
+        optional("raw".into(), s.raw.map(f64::from));
+        optional("rate".into(), s.rate);
+        for (i, &ch) in s.channels.iter().enumerate() {
+            optional(format!("ch{}", i + 1), Some(ch));
+        }
+        for (i, &aux) in s.aux.iter().enumerate() {
+            optional(format!("aux{i}"), aux);
+        }
+        optional("temp_c".into(), s.temp_c);
+        Json::Object(fields)
+    }
+}
+
+/// `name` and `t_us`, then the event's own fields as strings.
+impl From<&Event> for Json {
+    fn from(e: &Event) -> Self {
+        let mut fields = vec![
+            ("name".to_owned(), e.name.as_str().into()),
+            ("t_us".to_owned(), Json::Number(e.t_us as f64)),
+        ];
+        fields.extend(e.fields.iter().map(|(k, v)| (k.clone(), v.as_str().into())));
+        Json::Object(fields)
+    }
+}
+
+fn string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl Json {
+    fn write(&self, f: &mut fmt::Formatter<'_>, indent: Option<usize>) -> fmt::Result {
+        match self {
+            // JSON has no NaN or infinity.
+            Json::Number(n) if n.is_finite() => write!(f, "{n}"),
+            Json::Null | Json::Number(_) => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::String(s) => string(f, s),
+            // Always on one line.
+            Json::Array(items) => {
+                f.write_char('[')?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    item.write(f, None)?;
+                }
+                f.write_char(']')
+            }
+            Json::Object(fields) if fields.is_empty() => f.write_str("{}"),
+            Json::Object(fields) => {
+                f.write_char('{')?;
+                let inner = indent.map(|n| n + 2);
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    if let Some(n) = inner {
+                        write!(f, "\n{:1$}", "", n)?;
+                    }
+                    string(f, key)?;
+                    f.write_str(if indent.is_some() { ": " } else { ":" })?;
+                    value.write(f, inner)?;
+                }
+                if let Some(n) = indent {
+                    write!(f, "\n{:1$}", "", n)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+
+    /// Parse one value, such as a message from a client.
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut parser = Parser {
+            s: text.as_bytes(),
+            i: 0,
+        };
+        let value = parser.value()?;
+        parser.space();
+        (parser.i == parser.s.len()).then_some(value)
+    }
+
+    /// An object's field.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn space(&mut self) {
+        while matches!(self.s.get(self.i), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.i += 1;
+        }
+    }
+
+    /// Skip `token`, after any whitespace, if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.space();
+        let found = self.s[self.i..].starts_with(token.as_bytes());
+        if found {
+            self.i += token.len();
+        }
+        found
+    }
+
+    fn value(&mut self) -> Option<Json> {
+        self.space();
+        match *self.s.get(self.i)? {
+            b'n' => self.eat("null").then_some(Json::Null),
+            b't' => self.eat("true").then_some(Json::Bool(true)),
+            b'f' => self.eat("false").then_some(Json::Bool(false)),
+            b'"' => self.string().map(Json::String),
+            b'[' => {
+                self.i += 1;
+                let mut items = Vec::new();
+                if self.eat("]") {
+                    return Some(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    if self.eat("]") {
+                        return Some(Json::Array(items));
+                    }
+                    if !self.eat(",") {
+                        return None;
+                    }
+                }
+            }
+            b'{' => {
+                self.i += 1;
+                let mut fields = Vec::new();
+                if self.eat("}") {
+                    return Some(Json::Object(fields));
+                }
+                loop {
+                    self.space();
+                    let key = self.string()?;
+                    if !self.eat(":") {
+                        return None;
+                    }
+                    fields.push((key, self.value()?));
+                    if self.eat("}") {
+                        return Some(Json::Object(fields));
+                    }
+                    if !self.eat(",") {
+                        return None;
+                    }
+                }
+            }
+            _ => {
+                let start = self.i;
+                while matches!(
+                    self.s.get(self.i),
+                    Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')
+                ) {
+                    self.i += 1;
+                }
+                let number = std::str::from_utf8(&self.s[start..self.i]).ok()?;
+                number.parse().ok().map(Json::Number)
+            }
+        }
+    }
+
+    /// A string, starting at its opening quote.
+    fn string(&mut self) -> Option<String> {
+        if self.s.get(self.i) != Some(&b'"') {
+            return None;
+        }
+        self.i += 1;
+        let mut out = Vec::new();
+        loop {
+            let byte = *self.s.get(self.i)?;
+            self.i += 1;
+            let escaped = match byte {
+                b'"' => return String::from_utf8(out).ok(),
+                b'\\' => {
+                    self.i += 1;
+                    match *self.s.get(self.i - 1)? {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => {
+                            let hex = self.s.get(self.i..self.i + 4)?;
+                            self.i += 4;
+                            let code =
+                                u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+                            // A lone half of a surrogate pair.
+                            char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        c @ (b'"' | b'\\' | b'/') => c as char,
+                        _ => return None,
+                    }
+                }
+                _ => {
+                    out.push(byte);
+                    continue;
+                }
+            };
+            out.extend(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, f.alternate().then_some(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tensile_protocol::{parse_line, Line};
+
+    #[test]
+    fn writes_nested_objects() {
+        let json = Json::Object(vec![
+            ("name".into(), "A7 \"dogbone\"".into()),
+            ("peak".into(), Json::Number(812.5)),
+            ("yield".into(), Json::Number(f64::NAN)),
+            (
+                "device".into(),
+                Json::Object(vec![("serial".into(), "E661\n".into())]),
+            ),
+            ("meta".into(), Json::Object(Vec::new())),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            "{\"name\":\"A7 \\\"dogbone\\\"\",\"peak\":812.5,\"yield\":null,\
+             \"device\":{\"serial\":\"E661\\u000a\"},\"meta\":{}}"
+        );
+        assert_eq!(
+            format!("{json:#}"),
+            "{\n  \"name\": \"A7 \\\"dogbone\\\"\",\n  \"peak\": 812.5,\n  \"yield\": null,\n  \
+             \"device\": {\n    \"serial\": \"E661\\u000a\"\n  },\n  \"meta\": {}\n}"
+        );
+    }
+
+    #[test]
+    fn stream_lines_as_payloads() {
+        let Ok(Line::Sample(s)) = parse_line("Force: 12.5 raw=1240 t=513 seq=8 unit=N aux1=0.750")
+        else {
+            panic!("not a sample");
+        };
+        assert_eq!(
+            Json::from(&s).to_string(),
+            r#"{"t_us":513,"seq":8,"force":12.5,"unit":"N","raw":1240,"aux1":0.75}"#
+        );
+        let Ok(Line::Event(e)) = parse_line("Event: BREAK peak=20500 force=10250 t=2000") else {
+            panic!("not an event");
+        };
+        assert_eq!(
+            Json::from(&e).to_string(),
+            r#"{"name":"BREAK","t_us":2000,"peak":"20500","force":"10250"}"#
+        );
+    }
+
+    #[test]
+    fn parses_messages() {
+        let json =
+            Json::parse(r#" {"command": "FILTER AVG 8", "id": 3, "tags": [true, null, "µs\n"]} "#)
+                .unwrap();
+        assert_eq!(
+            json.get("command").and_then(Json::as_str),
+            Some("FILTER AVG 8")
+        );
+        assert_eq!(json.get("id"), Some(&Json::Number(3.0)));
+        assert_eq!(
+            json.get("tags"),
+            Some(&Json::Array(vec![
+                Json::Bool(true),
+                Json::Null,
+                "µs\n".into()
+            ]))
+        );
+        assert_eq!(Json::parse(&json.to_string()), Some(json));
+        for bad in ["", "{", r#"{"a" 1}"#, "[1,]", r#""open"#, "1 2", "nan"] {
+            assert_eq!(Json::parse(bad), None, "{bad:?}");
+        }
+    }
+}
@@ -0,0 +1,547 @@
+//! Reference client for the tester's serial protocol: find a tester, send
+//! it commands, log its stream to CSV with host timestamps, export a test
+//! with its results, run a campaign of them from a test plan, watch it live,
+//! bridge it to MQTT, or serve it over a WebSocket, alerting on conditions
+//! in the stream while logging or serving. `doctor` writes a health report
+//! to attach to a support request, and `backup` and `restore` carry a
+//! tester's settings to a file and back.
+//!
+//! Runs on Linux and macOS; run with no arguments for usage.
+
+mod alert;
+mod args;
+mod backup;
+mod campaign;
+mod doctor;
+mod export;
+mod json;
+mod mqtt;
+mod port;
+mod serve;
+mod watch;
+mod ws;
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use alert::{Alerting, Watcher};
+use args::{Command, Target};
+use port::Port;
+use tensile_core::clock::DateTime;
+use tensile_protocol::analysis::{Geometry, Point, Summary, Tensile};
+use tensile_protocol::{
+    ClockSync, Curve, Decoder, Exchange, Line, Reply, Sample, Simulator, Transport,
+};
+
+/// The simulator's noise, the same every run.
+const SIM_SEED: u64 = 0x5eed;
+
+/// How long `send` waits for replies.
+const REPLY_WAIT: Duration = Duration::from_millis(500);
+
+/// `SYNC` round trips before logging starts.
+const SYNC_ROUNDS: usize = 8;
+/// One more round trip this often while logging, to follow drift.
+const SYNC_EVERY: Duration = Duration::from_secs(10);
+/// Three missed heartbeats: `log` warns that the device has gone quiet.
+const SILENT_AFTER: Duration = Duration::from_secs(3);
+
+const CSV_HEADER: &str = "host_time_s,wall_time_s,t_us,seq,force,unit,raw";
+/// Appended when the specimen geometry is known.
+const CSV_STRESS_HEADER: &str = ",stress_mpa,strain";
+
+fn main() -> ExitCode {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("tensile-cli: {e}\n{}", args::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("tensile-cli: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: args::Args) -> Result<(), Box<dyn Error>> {
+    match args.command {
+        Command::List => {
+            let (found, errors) = port::discover();
+            for f in found {
+                let d = f.descriptor;
+                println!(
+                    "{}\tserial={}\tname={}\tfw={}\tboard={}",
+                    f.device.display(),
+                    d.serial,
+                    d.name.as_deref().unwrap_or("-"),
+                    d.firmware,
+                    d.board.as_deref().unwrap_or("?")
+                );
+            }
+            for (device, e) in errors {
+                eprintln!("{}: {e}", device.display());
+            }
+            Ok(())
+        }
+        Command::Send(line) => send(open(&args.target)?.as_mut(), &line),
+        Command::Log {
+            output,
+            seconds,
+            geometry,
+            extension,
+            alerts,
+        } => {
+            let mut port = open(&args.target)?;
+            let out: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout().lock()),
+            };
+            let geometry = match geometry {
+                Geometry {
+                    area_mm2: Some(_),
+                    gauge_mm: Some(_),
+                } => geometry,
+                _ => geometry.or(device_geometry(port.as_mut())?),
+            };
+            log(
+                port.as_mut(),
+                out,
+                seconds.map(Duration::from_secs_f64),
+                geometry,
+                extension,
+                &alerts,
+            )
+        }
+        Command::Export {
+            base,
+            seconds,
+            geometry,
+            extension,
+        } => export::export(
+            open(&args.target)?.as_mut(),
+            &base,
+            seconds.map(Duration::from_secs_f64),
+            geometry,
+            extension,
+            None,
+        )
+        .map(|_| ()),
+        Command::Campaign(path) => {
+            let plan = campaign::Plan::parse(&std::fs::read_to_string(&path)?)
+                .map_err(|e| format!("{}: {e}", path.display()))?;
+            // The simulator waits for RUN rather than starting pulls itself.
+            let mut port: Box<dyn Transport> = match args.target {
+                Target::Simulate => Box::new(Simulator::new(Curve::PLASTIC, SIM_SEED)),
+                _ => open(&args.target)?,
+            };
+            campaign::campaign(port.as_mut(), &plan, &mut io::stdin().lock())
+        }
+        Command::Doctor { seconds, output } => {
+            let output = output.unwrap_or_else(|| stamped("doctor", "txt"));
+            let stdin = io::stdin();
+            let interactive = stdin.is_terminal();
+            let mut answers = stdin.lock();
+            let report = doctor::doctor(
+                open(&args.target)?.as_mut(),
+                seconds,
+                &output,
+                interactive.then_some(&mut answers as &mut dyn BufRead),
+            )?;
+            eprintln!(
+                "\n{}\nreport saved to {}",
+                report.overall(),
+                output.display()
+            );
+            match report.passed() {
+                true => Ok(()),
+                false => Err("the tester failed the health check".into()),
+            }
+        }
+        Command::Backup(output) => backup::backup(
+            open(&args.target)?.as_mut(),
+            &output.unwrap_or_else(|| stamped("backup", "json")),
+        ),
+        Command::Restore(path) => backup::restore(open(&args.target)?.as_mut(), &path),
+        Command::Bridge(settings) => mqtt::bridge(open(&args.target)?.as_mut(), &settings),
+        Command::Serve { listen, alerts } => {
+            serve::serve(open(&args.target)?.as_mut(), &listen, &alerts)
+        }
+        Command::Watch => {
+            let mut port = open(&args.target)?;
+            Ok(watch::watch(port.as_mut(), &mut io::stdout().lock())?)
+        }
+    }
+}
+
+/// The data port of the tester `target` names, or the simulator.
+fn open(target: &Target) -> Result<Box<dyn Transport>, Box<dyn Error>> {
+    match target {
+        Target::Simulate => {
+            return Ok(Box::new(
+                Simulator::new(Curve::PLASTIC, SIM_SEED).repeating(),
+            ))
+        }
+        Target::Port(path) => return Ok(Box::new(Port::open(path)?)),
+        Target::First | Target::Serial(_) | Target::Name(_) => {}
+    }
+    let (found, errors) = port::discover();
+    let tester = found.into_iter().find(|f| match target {
+        Target::Serial(serial) => f.descriptor.serial.eq_ignore_ascii_case(serial),
+        Target::Name(name) => f
+            .descriptor
+            .name
+            .as_deref()
+            .is_some_and(|n| n.eq_ignore_ascii_case(name)),
+        _ => true,
+    });
+    match tester {
+        Some(found) => Ok(Box::new(found.port)),
+        None => {
+            for (device, e) in errors {
+                eprintln!("{}: {e}", device.display());
+            }
+            Err("no tester found; try list or --port".into())
+        }
+    }
+}
+
+/// Send one command and print everything but Force and Heartbeat lines
+/// that comes back.
+fn send(port: &mut dyn Transport, line: &str) -> Result<(), Box<dyn Error>> {
+    port.send(line)?;
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 1024];
+    let mut failed = None;
+    let deadline = Instant::now() + REPLY_WAIT;
+    while Instant::now() < deadline {
+        let n = port.read(&mut buf)?;
+        for line in decoder.push(&buf[..n]).into_iter().flatten() {
+            match line {
+                Line::Sample(_) => {}
+                Line::Reply(r) if r.kind == "Heartbeat" => {}
+                Line::Ok => println!("OK"),
+                Line::Error { code, message } => {
+                    match code {
+                        Some(code) => println!("ERR {code} {message}"),
+                        None => println!("ERR {message}"),
+                    }
+                    failed = Some(message);
+                }
+                Line::Event(e) => {
+                    print!("Event: {}", e.name);
+                    for (k, v) in &e.fields {
+                        print!(" {k}={v}");
+                    }
+                    println!(" t={}", e.t_us);
+                }
+                Line::Reply(r) => {
+                    print!("{}:", r.kind);
+                    if let Some(value) = &r.value {
+                        print!(" {value}");
+                    }
+                    for (k, v) in &r.fields {
+                        print!(" {k}={v}");
+                    }
+                    println!();
+                }
+                Line::Other(text) => println!("{text}"),
+            }
+        }
+    }
+    match failed {
+        Some(message) => Err(format!("device replied: {message}").into()),
+        None => Ok(()),
+    }
+}
+
+/// Send `command` and collect its replies of the `kinds` given, and any
+/// `ERR`, until `done` is true of one or `wait` is up.
+fn query(
+    port: &mut dyn Transport,
+    command: &str,
+    kinds: &[&str],
+    wait: Duration,
+    done: impl Fn(&Reply) -> bool,
+) -> Result<Vec<Line>, Box<dyn Error>> {
+    port.send(command)?;
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    let mut replies = Vec::new();
+    let deadline = Instant::now() + wait;
+    while Instant::now() < deadline {
+        let n = port.read(&mut buf)?;
+        for line in decoder.push(&buf[..n]).into_iter().flatten() {
+            let last = match &line {
+                Line::Reply(r) if kinds.contains(&r.kind.as_str()) => done(r),
+                Line::Error { .. } => true,
+                _ => continue,
+            };
+            replies.push(line);
+            if last {
+                return Ok(replies);
+            }
+        }
+    }
+    Ok(replies)
+}
+
+/// Send `command` and wait for its `OK`. `Some` with what went wrong
+/// otherwise.
+fn ack(port: &mut dyn Transport, command: &str) -> Result<Option<String>, Box<dyn Error>> {
+    port.send(command)?;
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 1024];
+    let deadline = Instant::now() + REPLY_WAIT;
+    while Instant::now() < deadline {
+        let n = port.read(&mut buf)?;
+        for line in decoder.push(&buf[..n]).into_iter().flatten() {
+            match line {
+                Line::Ok => return Ok(None),
+                Line::Error { .. } => return Ok(Some(text(&line))),
+                _ => {}
+            }
+        }
+    }
+    Ok(Some("no reply".into()))
+}
+
+/// A reply or error as the device sent it.
+fn text(line: &Line) -> String {
+    match line {
+        Line::Reply(r) => format!("{}: {}", r.kind, r.text),
+        Line::Error {
+            code: Some(code),
+            message,
+        } => format!("ERR {code} {message}"),
+        Line::Error {
+            code: None,
+            message,
+        } => format!("ERR {message}"),
+        other => format!("{other:?}"),
+    }
+}
+
+/// `unix` as `2026-10-15 09:37:39`, in UTC.
+fn datetime(unix: u64) -> String {
+    let t = DateTime::from_unix(unix);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+/// `<name>_20261015_093739.<extension>`, for now in UTC.
+fn stamped(name: &str, extension: &str) -> PathBuf {
+    let unix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let stamp = datetime(unix).replace(['-', ':'], "").replace(' ', "_");
+    format!("{name}_{stamp}.{extension}").into()
+}
+
+/// The specimen dimensions in the device's metadata, if it has any.
+fn device_geometry(port: &mut dyn Transport) -> Result<Geometry, Box<dyn Error>> {
+    port.send("META?")?;
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 1024];
+    let deadline = Instant::now() + REPLY_WAIT;
+    while Instant::now() < deadline {
+        let n = port.read(&mut buf)?;
+        for line in decoder.push(&buf[..n]).into_iter().flatten() {
+            if let Line::Reply(r) = line {
+                if r.kind == "Meta" {
+                    return Ok(Geometry::from_meta(&r.fields));
+                }
+            }
+        }
+    }
+    Ok(Geometry::default())
+}
+
+fn unix_us() -> Result<u64, Box<dyn Error>> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_micros() as u64)
+}
+
+/// A few `SYNC` round trips, one at a time so that none queues behind
+/// another. Firmware without `SYNC` leaves `sync` empty.
+fn clock_sync(port: &mut dyn Transport, sync: &mut ClockSync) -> Result<(), Box<dyn Error>> {
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    'rounds: for _ in 0..SYNC_ROUNDS {
+        port.send(&format!("SYNC {}", unix_us()?))?;
+        let deadline = Instant::now() + REPLY_WAIT;
+        while Instant::now() < deadline {
+            let n = port.read(&mut buf)?;
+            let received_us = unix_us()?;
+            for line in decoder.push(&buf[..n]).into_iter().flatten() {
+                match line {
+                    Line::Reply(r) => {
+                        if let Some(exchange) = Exchange::from_reply(&r, received_us) {
+                            sync.push(exchange);
+                            continue 'rounds;
+                        }
+                    }
+                    Line::Error { .. } => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write samples as CSV until `duration` is up, or forever. Events and
+/// errors go to stderr, as do the [`Summary`] when a test stops and at the
+/// end, a warning when the device goes quiet, and `alerts`.
+fn log(
+    port: &mut dyn Transport,
+    mut out: Box<dyn Write>,
+    duration: Option<Duration>,
+    geometry: Geometry,
+    extension: usize,
+    alerts: &Alerting,
+) -> Result<(), Box<dyn Error>> {
+    let mut watcher = Watcher::new(&alerts.conditions);
+    let mut tensile = (geometry != Geometry::default()).then(|| Tensile::new(geometry, extension));
+    let mut sync = ClockSync::new();
+    clock_sync(port, &mut sync)?;
+    match (sync.uncertainty_us(), sync.drift_ppm()) {
+        (Some(error), Some(drift)) => {
+            eprintln!("clock synced to ±{error} µs, device drift {drift:+.1} ppm")
+        }
+        _ => eprintln!("no SYNC reply; wall_time_s left empty"),
+    }
+    match tensile {
+        Some(_) => writeln!(out, "{CSV_HEADER}{CSV_STRESS_HEADER}")?,
+        None => writeln!(out, "{CSV_HEADER}")?,
+    }
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    let start = Instant::now();
+    let mut last_sync = Instant::now();
+    let mut last_heard = Instant::now();
+    let mut silent = false;
+    while duration.is_none_or(|d| start.elapsed() < d) {
+        // The reply comes back in the stream like any other line.
+        if sync.uncertainty_us().is_some() && last_sync.elapsed() >= SYNC_EVERY {
+            port.send(&format!("SYNC {}", unix_us()?))?;
+            last_sync = Instant::now();
+        }
+        let n = port.read(&mut buf)?;
+        if n > 0 {
+            if silent {
+                eprintln!("device is talking again");
+            }
+            (last_heard, silent) = (Instant::now(), false);
+        } else if !silent && last_heard.elapsed() >= SILENT_AFTER {
+            eprintln!(
+                "nothing from the device for {} s; it may be hung",
+                SILENT_AFTER.as_secs()
+            );
+            silent = true;
+        }
+        let received_us = unix_us()?;
+        let host_s = received_us as f64 / 1e6;
+        for line in decoder.push(&buf[..n]) {
+            match line {
+                Ok(Line::Sample(s)) => {
+                    for alert in watcher.push(&s) {
+                        alerts.raise(&alert);
+                    }
+                    let point = tensile.as_mut().map(|t| t.push(&s));
+                    let wall_s = sync.host_us(s.t_us).map(|us| us / 1e6);
+                    writeln!(out, "{}", csv_row(host_s, wall_s, &s, point))?;
+                }
+                Ok(Line::Reply(r)) => {
+                    if let Some(exchange) = Exchange::from_reply(&r, received_us) {
+                        sync.push(exchange);
+                    }
+                }
+                Ok(Line::Event(e)) => {
+                    eprintln!("event {} t={}", e.name, e.t_us);
+                    if let Some(tensile) = &mut tensile {
+                        match e.name.as_str() {
+                            "BREAK" => tensile.mark_break(),
+                            "TEST_STOP" => eprintln!("{}", summary(&tensile.summary())),
+                            _ => {}
+                        }
+                    }
+                }
+                Ok(Line::Error { message, .. }) => eprintln!("device error: {message}"),
+                Ok(_) => {}
+                Err(e) => eprintln!("skipped line: {e}"),
+            }
+        }
+        // A killed logger keeps everything up to its last read.
+        out.flush()?;
+    }
+    if let Some(tensile) = tensile {
+        eprintln!("{}", summary(&tensile.summary()));
+    }
+    Ok(())
+}
+
+fn csv_row(host_s: f64, wall_s: Option<f64>, s: &Sample, point: Option<Point>) -> String {
+    let mut row = format!(
+        "{host_s:.6},{},{},{},{},{},{}",
+        wall_s.map_or(String::new(), |v| format!("{v:.6}")),
+        s.t_us,
+        s.seq,
+        s.force,
+        s.unit.as_str(),
+        s.raw.map_or(String::new(), |v| v.to_string())
+    );
+    if let Some(p) = point {
+        let cell = |v: Option<f64>| v.map_or(String::new(), |v| format!("{v:.6}"));
+        row += &format!(",{},{}", cell(p.stress_mpa), cell(p.strain));
+    }
+    row
+}
+
+fn summary(s: &Summary) -> String {
+    let show = |v: Option<f64>, unit| v.map_or("-".to_owned(), |v| format!("{v:.2}{unit}"));
+    format!(
+        "result uts={} yield={} elongation={} modulus={} r2={}",
+        show(s.uts_mpa, " MPa"),
+        show(s.yield_mpa, " MPa"),
+        show(s.elongation_pct, "%"),
+        show(s.elastic.map(|e| e.modulus_mpa / 1000.0), " GPa"),
+        s.elastic
+            .map_or("-".to_owned(), |e| format!("{:.4}", e.r_squared))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_rows_match_the_header() {
+        let Ok(Line::Sample(s)) =
+            tensile_protocol::parse_line("Force: 12.094 raw=1240 t=51334567 seq=813 unit=N")
+        else {
+            panic!("not a sample");
+        };
+        let row = csv_row(1_792_065_600.25, None, &s, None);
+        assert_eq!(row, "1792065600.250000,,51334567,813,12.094,N,1240");
+        assert_eq!(row.split(',').count(), CSV_HEADER.split(',').count());
+
+        let point = Point {
+            stress_mpa: Some(1.2094),
+            strain: None,
+        };
+        let row = csv_row(1_792_065_600.25, Some(1_792_065_600.200_3), &s, Some(point));
+        assert!(row.starts_with("1792065600.250000,1792065600.200300,"));
+        assert!(row.ends_with(",1240,1.209400,"));
+        let header = format!("{CSV_HEADER}{CSV_STRESS_HEADER}");
+        assert_eq!(row.split(',').count(), header.split(',').count());
+    }
+}
@@ -0,0 +1,182 @@
+//! `bridge mqtt`: republish the stream to an MQTT broker for lab
+//! dashboards.
+//!
+//! Samples go to `<topic>/sample`, events to `<topic>/event` and device
+//! errors to `<topic>/error` (`code` and `message`), each as one JSON
+//! object. The client is the
+//! minimum of MQTT 3.1.1 a one-way bridge needs: connect, QoS 0 publish and
+//! keep-alive pings.
+
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use tensile_protocol::{Decoder, Line, Transport};
+
+use crate::args::Mqtt;
+use crate::json::Json;
+
+const DEFAULT_PORT: u16 = 1883;
+const KEEP_ALIVE_S: u16 = 60;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PINGREQ: u8 = 0xc0;
+
+fn string(out: &mut Vec<u8>, s: &str) {
+    out.extend((s.len() as u16).to_be_bytes());
+    out.extend(s.as_bytes());
+}
+
+/// A fixed header, with its variable-length remaining length, and `body`.
+fn packet(kind: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![kind];
+    let mut n = body.len();
+    loop {
+        let byte = (n % 128) as u8;
+        n /= 128;
+        out.push(if n > 0 { byte | 0x80 } else { byte });
+        if n == 0 {
+            break;
+        }
+    }
+    out.extend(body);
+    out
+}
+
+fn connect_packet(client_id: &str, credentials: Option<(&str, &str)>) -> Vec<u8> {
+    let mut body = Vec::new();
+    string(&mut body, "MQTT");
+    // Protocol level 4, clean session, and the user name and password
+    // flags if given.
+    let flags = if credentials.is_some() { 0xc2 } else { 0x02 };
+    body.extend([4, flags]);
+    body.extend(KEEP_ALIVE_S.to_be_bytes());
+    string(&mut body, client_id);
+    if let Some((user, password)) = credentials {
+        string(&mut body, user);
+        string(&mut body, password);
+    }
+    packet(CONNECT, &body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    string(&mut body, topic);
+    body.extend(payload);
+    packet(PUBLISH, &body)
+}
+
+/// A connection to a broker. Nothing it sends after CONNACK matters to a
+/// publisher, so it is never read.
+pub struct Client {
+    stream: TcpStream,
+    last_sent: Instant,
+}
+
+impl Client {
+    pub fn connect(settings: &Mqtt) -> Result<Self, Box<dyn Error>> {
+        let address = if settings.broker.contains(':') {
+            settings.broker.clone()
+        } else {
+            format!("{}:{DEFAULT_PORT}", settings.broker)
+        };
+        let mut stream = TcpStream::connect(&address)?;
+        stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+        let credentials = settings
+            .credentials
+            .as_ref()
+            .map(|(user, password)| (user.as_str(), password.as_str()));
+        stream.write_all(&connect_packet(&settings.client_id, credentials))?;
+        let mut connack = [0; 4];
+        stream.read_exact(&mut connack)?;
+        match connack {
+            [CONNACK, 2, _, 0] => Ok(Self {
+                stream,
+                last_sent: Instant::now(),
+            }),
+            [CONNACK, 2, _, code] => {
+                Err(format!("{address} refused the connection ({code})").into())
+            }
+            _ => Err(format!("{address} is not an MQTT broker").into()),
+        }
+    }
+
+    pub fn publish(&mut self, topic: &str, payload: &Json) -> io::Result<()> {
+        self.last_sent = Instant::now();
+        self.stream
+            .write_all(&publish_packet(topic, payload.to_string().as_bytes()))
+    }
+
+    /// Ping if nothing has been sent for half the keep-alive interval.
+    pub fn keep_alive(&mut self) -> io::Result<()> {
+        if self.last_sent.elapsed() < Duration::from_secs(KEEP_ALIVE_S as u64 / 2) {
+            return Ok(());
+        }
+        self.last_sent = Instant::now();
+        self.stream.write_all(&[PINGREQ, 0])
+    }
+}
+
+/// Republish until the device or the broker goes away.
+pub fn bridge(port: &mut dyn Transport, settings: &Mqtt) -> Result<(), Box<dyn Error>> {
+    let mut client = Client::connect(settings)?;
+    eprintln!("publishing to {}/#", settings.topic);
+    let (sample, event, error) = (
+        format!("{}/sample", settings.topic),
+        format!("{}/event", settings.topic),
+        format!("{}/error", settings.topic),
+    );
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    loop {
+        let n = port.read(&mut buf)?;
+        for line in decoder.push(&buf[..n]).into_iter().flatten() {
+            match line {
+                Line::Sample(s) => client.publish(&sample, &Json::from(&s))?,
+                Line::Event(e) => client.publish(&event, &Json::from(&e))?,
+                Line::Error { code, message } => client.publish(
+                    &error,
+                    &Json::Object(vec![
+                        ("code".into(), code.map(f64::from).into()),
+                        ("message".into(), message.as_str().into()),
+                    ]),
+                )?,
+                _ => {}
+            }
+        }
+        client.keep_alive()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_packets() {
+        assert_eq!(
+            connect_packet("rig-2", None),
+            [
+                0x10, 17, 0, 4, b'M', b'Q', b'T', b'T', 4, 0x02, 0, 60, 0, 5, b'r', b'i', b'g',
+                b'-', b'2'
+            ]
+        );
+        let with_login = connect_packet("c", Some(("lab", "pw")));
+        assert_eq!(with_login[9], 0xc2);
+        assert!(with_login.ends_with(&[0, 1, b'c', 0, 3, b'l', b'a', b'b', 0, 2, b'p', b'w']));
+    }
+
+    #[test]
+    fn publish_uses_multi_byte_lengths() {
+        let short = publish_packet("t/s", b"{}");
+        assert_eq!(short, [0x30, 7, 0, 3, b't', b'/', b's', b'{', b'}']);
+        let long = publish_packet("t", &[b'x'; 200]);
+        // 2 + 1 + 200 = 203 = 0b1_1001011
+        assert_eq!(long[..3], [0x30, 0xcb, 0x01]);
+        assert_eq!(long.len(), 3 + 203);
+    }
+}
@@ -0,0 +1,172 @@
+//! Finding testers and talking to their serial ports.
+//!
+//! USB CDC ignores the baud rate, so a port only needs switching to raw
+//! mode. On Linux sysfs gives each port's USB IDs, serial number and
+//! interface, so only the testers' data ports are candidates; elsewhere
+//! every `/dev/cu.usbmodem*` port is, and `INFO?` picks the testers out.
+//! Either way [`discover`] asks each candidate who it is.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+use std::time::Duration;
+
+use tensile_protocol::{identify, Descriptor, Transport, HELLO, USB_PID, USB_VID};
+
+/// How long a candidate port gets to answer `INFO?`.
+const IDENTIFY_WAIT: Duration = Duration::from_millis(500);
+/// The port that carries replies and events only while it is open.
+const CONTROL_INTERFACE: &str = "Tensile Control";
+
+#[derive(Debug, Clone)]
+struct PortInfo {
+    device: PathBuf,
+    /// The CDC interface name, e.g. "Tensile Data".
+    interface: Option<String>,
+}
+
+impl PortInfo {
+    fn is_control(&self) -> bool {
+        self.interface.as_deref() == Some(CONTROL_INTERFACE)
+    }
+}
+
+fn attribute(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name))
+        .ok()
+        .map(|s| s.trim().to_owned())
+}
+
+/// Every tester port attached, sorted by device name.
+fn list() -> Vec<PortInfo> {
+    let Ok(entries) = fs::read_dir("/sys/class/tty") else {
+        return Vec::new();
+    };
+    let mut ports: Vec<PortInfo> = entries
+        .flatten()
+        .filter_map(|entry| {
+            // `device` is the USB interface; its parent the USB device.
+            let interface = entry.path().join("device");
+            let usb = interface.join("..");
+            let ours = attribute(&usb, "idVendor") == Some(format!("{USB_VID:04x}"))
+                && attribute(&usb, "idProduct") == Some(format!("{USB_PID:04x}"));
+            ours.then(|| PortInfo {
+                device: Path::new("/dev").join(entry.file_name()),
+                interface: attribute(&interface, "interface"),
+            })
+        })
+        .collect();
+    ports.sort_by(|a, b| a.device.cmp(&b.device));
+    ports
+}
+
+/// Ports worth asking `INFO?`, sorted by device name.
+fn candidates() -> Vec<PathBuf> {
+    if Path::new("/sys/class/tty").exists() {
+        return list()
+            .into_iter()
+            .filter(|p| !p.is_control())
+            .map(|p| p.device)
+            .collect();
+    }
+    let Ok(entries) = fs::read_dir("/dev") else {
+        return Vec::new();
+    };
+    let mut ports: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("cu.usbmodem"))
+        .map(|e| e.path())
+        .collect();
+    ports.sort();
+    ports
+}
+
+/// A tester that answered, with its port still open.
+pub struct Found {
+    pub device: PathBuf,
+    pub descriptor: Descriptor,
+    pub port: Port,
+}
+
+/// Every tester that answers `INFO?`, one port each. Ports that cannot be
+/// opened, most likely because another program has them, are listed in
+/// the errors.
+pub fn discover() -> (Vec<Found>, Vec<(PathBuf, io::Error)>) {
+    let (mut found, mut errors) = (Vec::<Found>::new(), Vec::new());
+    for device in candidates() {
+        let mut port = match Port::open(&device) {
+            Ok(port) => port,
+            Err(e) => {
+                errors.push((device, e));
+                continue;
+            }
+        };
+        match identify(&mut port, IDENTIFY_WAIT) {
+            // Without sysfs both of a tester's ports answer; the data port
+            // comes first.
+            Ok(Some(descriptor))
+                if found
+                    .iter()
+                    .all(|f| f.descriptor.serial != descriptor.serial) =>
+            {
+                found.push(Found {
+                    device,
+                    descriptor,
+                    port,
+                })
+            }
+            Ok(_) => {}
+            Err(e) => errors.push((device, e)),
+        }
+    }
+    (found, errors)
+}
+
+pub struct Port {
+    file: File,
+}
+
+impl Port {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(path)?;
+        let fd = file.as_raw_fd();
+        // SAFETY: `fd` is open for the life of `file`, and `tio` is filled
+        // in by tcgetattr before it is used.
+        unsafe {
+            let mut tio: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(fd, &mut tio) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            libc::cfmakeraw(&mut tio);
+            // Reads return after 100 ms with whatever has arrived.
+            tio.c_cc[libc::VMIN] = 0;
+            tio.c_cc[libc::VTIME] = 1;
+            if libc::tcsetattr(fd, libc::TCSANOW, &tio) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        // Samples come as binary frames, which are smaller and need no
+        // number parsing.
+        let mut file = file;
+        file.write_all(&[HELLO])?;
+        Ok(Self { file })
+    }
+}
+
+impl Transport for Port {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        self.file.write_all(command.as_bytes())?;
+        self.file.write_all(b"\r\n")
+    }
+}
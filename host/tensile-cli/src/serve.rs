@@ -0,0 +1,164 @@
+//! `serve`: the stream and the command channel over a local WebSocket, for
+//! browser front ends.
+//!
+//! Every client gets every line as a JSON text message with a `type`:
+//!
+//! ```text
+//! {"type":"sample","t_us":51334567,"seq":813,"force":12.094,"unit":"N","raw":1240}
+//! {"type":"event","name":"TARE","t_us":51200000,"offset":"-8123",...}
+//! {"type":"reply","kind":"Status","text":"test=idle stream=live sensor=ok"}
+//! {"type":"ok"}
+//! {"type":"error","code":2,"message":"bad argument"}
+//! {"type":"text","text":"leafy-sys,Pico Tensile Tester,..."}
+//! ```
+//!
+//! Alerts go to every client too, as `{"type":"alert",...}` (see
+//! [`crate::alert`]).
+//!
+//! Clients send commands as `{"command":"TARE"}`. Anything else is answered,
+//! to that client only, with `{"type":"rejected","message":...}`.
+
+use std::error::Error;
+use std::net::TcpListener;
+
+use tensile_protocol::{Decoder, Line, Transport};
+
+use crate::alert::{Alerting, Watcher};
+use crate::json::Json;
+use crate::ws::Socket;
+
+/// `line` as a message.
+fn message(line: &Line) -> Json {
+    let tagged = |kind: &str, json: Json| {
+        let Json::Object(mut fields) = json else {
+            unreachable!("samples and events are objects");
+        };
+        fields.insert(0, ("type".to_owned(), kind.into()));
+        Json::Object(fields)
+    };
+    let object = |fields: &[(&str, &str)]| {
+        Json::Object(
+            fields
+                .iter()
+                .map(|&(k, v)| (k.to_owned(), v.into()))
+                .collect(),
+        )
+    };
+    match line {
+        Line::Sample(s) => tagged("sample", Json::from(s)),
+        Line::Event(e) => tagged("event", Json::from(e)),
+        Line::Reply(r) => object(&[("type", "reply"), ("kind", &r.kind), ("text", &r.text)]),
+        Line::Ok => object(&[("type", "ok")]),
+        Line::Error { code, message } => Json::Object(vec![
+            ("type".into(), "error".into()),
+            ("code".into(), code.map(f64::from).into()),
+            ("message".into(), message.as_str().into()),
+        ]),
+        Line::Other(text) => object(&[("type", "text"), ("text", text)]),
+    }
+}
+
+/// The command line in a client's message.
+fn command(text: &str) -> Result<String, &'static str> {
+    let json = Json::parse(text).ok_or("not JSON")?;
+    let command = json
+        .get("command")
+        .and_then(Json::as_str)
+        .ok_or("expected {\"command\": \"...\"}")?;
+    if command.is_empty() || command.contains(['\r', '\n']) {
+        return Err("command must be one non-empty line");
+    }
+    Ok(command.to_owned())
+}
+
+/// Serve until the device goes away.
+pub fn serve(
+    port: &mut dyn Transport,
+    listen: &str,
+    alerts: &Alerting,
+) -> Result<(), Box<dyn Error>> {
+    let mut watcher = Watcher::new(&alerts.conditions);
+    let listener = TcpListener::bind(listen)?;
+    listener.set_nonblocking(true)?;
+    eprintln!("serving ws://{}", listener.local_addr()?);
+    let mut clients: Vec<Socket> = Vec::new();
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    loop {
+        while let Ok((stream, address)) = listener.accept() {
+            match Socket::accept(stream) {
+                Ok(socket) => clients.push(socket),
+                Err(e) => eprintln!("{address}: {e}"),
+            }
+        }
+        // Waits up to 100 ms, which paces the loop.
+        let n = port.read(&mut buf)?;
+        for line in decoder.push(&buf[..n]).into_iter().flatten() {
+            let text = message(&line).to_string();
+            clients.retain_mut(|c| c.send_text(&text).is_ok());
+            if let Line::Sample(s) = &line {
+                for alert in watcher.push(s) {
+                    alerts.raise(&alert);
+                    let text = alert.json().to_string();
+                    clients.retain_mut(|c| c.send_text(&text).is_ok());
+                }
+            }
+        }
+        let mut commands = Vec::new();
+        clients.retain_mut(|c| {
+            let Ok(messages) = c.poll() else {
+                return false;
+            };
+            for text in messages {
+                match command(&text) {
+                    Ok(line) => commands.push(line),
+                    Err(reason) => {
+                        let rejected = Json::Object(vec![
+                            ("type".into(), "rejected".into()),
+                            ("message".into(), reason.into()),
+                        ]);
+                        if c.send_text(&rejected.to_string()).is_err() {
+                            return false;
+                        }
+                    }
+                }
+            }
+            true
+        });
+        for line in commands {
+            port.send(&line)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tensile_protocol::parse_line;
+
+    #[test]
+    fn lines_as_messages() {
+        let text = |line: &str| message(&parse_line(line).unwrap()).to_string();
+        assert_eq!(
+            text("Force: 12.5 raw=1240 t=513 seq=8 unit=N"),
+            r#"{"type":"sample","t_us":513,"seq":8,"force":12.5,"unit":"N","raw":1240}"#
+        );
+        assert_eq!(
+            text("Status: test=idle sensor=ok"),
+            r#"{"type":"reply","kind":"Status","text":"test=idle sensor=ok"}"#
+        );
+        assert_eq!(text("OK"), r#"{"type":"ok"}"#);
+        assert_eq!(
+            text("ERR 2 bad argument"),
+            r#"{"type":"error","code":2,"message":"bad argument"}"#
+        );
+    }
+
+    #[test]
+    fn commands_from_clients() {
+        assert_eq!(command(r#"{"command": "TARE"}"#), Ok("TARE".into()));
+        assert!(command("TARE").is_err());
+        assert!(command(r#"{"cmd": "TARE"}"#).is_err());
+        assert!(command(r#"{"command": "TARE\r\nSTART"}"#).is_err());
+    }
+}
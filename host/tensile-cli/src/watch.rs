@@ -0,0 +1,161 @@
+//! `watch`: a live force-vs-time sparkline with the current value, peak and
+//! rate, redrawn in place with plain ANSI escapes.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use tensile_protocol::{Decoder, Line, Sample, Transport};
+
+/// Samples across the plot.
+const WIDTH: usize = 60;
+const REDRAW: Duration = Duration::from_millis(100);
+/// Span `rate` is measured over when the device doesn't report one.
+const RATE_SPAN_US: u64 = 1_000_000;
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Lines drawn per frame, moved back over for the next.
+const FRAME_LINES: usize = 3;
+
+/// The recent samples and the extremes since the watch began.
+#[derive(Debug, Default)]
+pub struct Plot {
+    /// `(t_us, force)`, oldest first.
+    recent: VecDeque<(u64, f64)>,
+    max: Option<f64>,
+    min: Option<f64>,
+    device_rate: Option<f64>,
+    unit: &'static str,
+}
+
+impl Plot {
+    pub fn push(&mut self, s: &Sample) {
+        if self.recent.len() == WIDTH {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((s.t_us, s.force));
+        self.max = Some(self.max.map_or(s.force, |m| m.max(s.force)));
+        self.min = Some(self.min.map_or(s.force, |m| m.min(s.force)));
+        self.device_rate = s.rate;
+        self.unit = s.unit.as_str();
+    }
+
+    /// One bar per sample, scaled between the lowest and highest shown.
+    pub fn sparkline(&self) -> String {
+        let values = self.recent.iter().map(|&(_, f)| f);
+        let lo = values.clone().fold(f64::INFINITY, f64::min);
+        let hi = values.clone().fold(f64::NEG_INFINITY, f64::max);
+        let span = hi - lo;
+        values
+            .map(|f| {
+                let level = if span > 0.0 { (f - lo) / span } else { 0.0 };
+                BARS[(level * (BARS.len() - 1) as f64).round() as usize]
+            })
+            .collect()
+    }
+
+    /// Units per second: the device's `rate=` if it sends one, else the
+    /// slope over about the last second.
+    pub fn rate(&self) -> Option<f64> {
+        if self.device_rate.is_some() {
+            return self.device_rate;
+        }
+        let &(t1, f1) = self.recent.back()?;
+        let &(t0, f0) = self.recent.iter().find(|&&(t, _)| t1 - t <= RATE_SPAN_US)?;
+        (t1 > t0).then(|| (f1 - f0) / ((t1 - t0) as f64 / 1e6))
+    }
+
+    fn frame(&self) -> [String; FRAME_LINES] {
+        let show = |v: Option<f64>| v.map_or("-".to_owned(), |v| format!("{v:.3}"));
+        let current = self.recent.back().map(|&(_, f)| f);
+        [
+            self.sparkline(),
+            format!(
+                "force {} {}   max {}   min {}",
+                show(current),
+                self.unit,
+                show(self.max),
+                show(self.min)
+            ),
+            format!("rate {} {}/s", show(self.rate()), self.unit),
+        ]
+    }
+}
+
+/// Redraw until interrupted.
+pub fn watch(port: &mut dyn Transport, out: &mut impl Write) -> std::io::Result<()> {
+    let mut plot = Plot::default();
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    let mut drawn = false;
+    let mut next_draw = Instant::now();
+    loop {
+        let n = port.read(&mut buf)?;
+        for line in decoder.push(&buf[..n]) {
+            if let Ok(Line::Sample(s)) = line {
+                plot.push(&s);
+            }
+        }
+        if Instant::now() < next_draw {
+            continue;
+        }
+        next_draw += REDRAW;
+        if drawn {
+            // Back to the start of the previous frame.
+            write!(out, "\x1b[{FRAME_LINES}F")?;
+        }
+        for line in plot.frame() {
+            writeln!(out, "{line}\x1b[K")?;
+        }
+        out.flush()?;
+        drawn = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tensile_protocol::Unit;
+
+    fn sample(t_us: u64, force: f64) -> Sample {
+        Sample {
+            force,
+            unit: Unit::Newton,
+            raw: Some(0),
+            t_us,
+            seq: 0,
+            rate: None,
+            channels: Vec::new(),
+            aux: [None; 3],
+            temp_c: None,
+        }
+    }
+
+    #[test]
+    fn sparkline_spans_the_visible_range() {
+        let mut plot = Plot::default();
+        for (i, f) in [0.0, 6.0, 14.0, 2.0].into_iter().enumerate() {
+            plot.push(&sample(i as u64, f));
+        }
+        assert_eq!(plot.sparkline(), "▁▄█▂");
+        for i in 0..WIDTH as u64 {
+            plot.push(&sample(10 + i, 1.0));
+        }
+        assert_eq!(plot.sparkline().chars().count(), WIDTH);
+        assert!(plot.sparkline().chars().all(|c| c == '▁'));
+        assert_eq!((plot.max, plot.min), (Some(14.0), Some(0.0)));
+    }
+
+    #[test]
+    fn rate_is_the_slope_over_the_last_second() {
+        let mut plot = Plot::default();
+        assert_eq!(plot.rate(), None);
+        for i in 0..=20 {
+            plot.push(&sample(i * 100_000, i as f64 * 0.5));
+        }
+        assert_eq!(plot.rate(), Some(5.0));
+        let mut with_rate = sample(2_100_000, 10.0);
+        with_rate.rate = Some(-1.5);
+        plot.push(&with_rate);
+        assert_eq!(plot.rate(), Some(-1.5));
+    }
+}
@@ -0,0 +1,295 @@
+//! The server side of WebSocket (RFC 6455): the opening handshake and
+//! unfragmented frames, which is all `serve` needs.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Larger client frames are refused; commands are short.
+const MAX_FRAME: usize = 64 * 1024;
+
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+    let mut out = [0; 20];
+    for (chunk, word) in out.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() {
+                ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char
+            } else {
+                '='
+            });
+        }
+    }
+    out
+}
+
+/// The `Sec-WebSocket-Accept` value for a client's key.
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{ACCEPT_GUID}").as_bytes()))
+}
+
+/// An unmasked server frame.
+fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x80 | opcode];
+    match payload.len() {
+        n @ 0..=125 => out.push(n as u8),
+        n @ 126..=0xffff => {
+            out.push(126);
+            out.extend((n as u16).to_be_bytes());
+        }
+        n => {
+            out.push(127);
+            out.extend((n as u64).to_be_bytes());
+        }
+    }
+    out.extend(payload);
+    out
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Parsed {
+    /// Not all here yet.
+    Partial,
+    Frame {
+        opcode: u8,
+        payload: Vec<u8>,
+        length: usize,
+    },
+    /// Fragmented, unmasked or too large.
+    Bad,
+}
+
+/// The first frame in `buf`, from a client, unmasked.
+fn parse(buf: &[u8]) -> Parsed {
+    let [first, second, ..] = *buf else {
+        return Parsed::Partial;
+    };
+    if first & 0x80 == 0 || second & 0x80 == 0 {
+        return Parsed::Bad;
+    }
+    let (len, mut at) = match second & 0x7f {
+        126 => match buf.get(2..4) {
+            Some(b) => (u16::from_be_bytes([b[0], b[1]]) as usize, 4),
+            None => return Parsed::Partial,
+        },
+        127 => match buf.get(2..10) {
+            Some(b) => (u64::from_be_bytes(b.try_into().unwrap()) as usize, 10),
+            None => return Parsed::Partial,
+        },
+        n => (n as usize, 2),
+    };
+    if len > MAX_FRAME {
+        return Parsed::Bad;
+    }
+    let Some(mask) = buf.get(at..at + 4) else {
+        return Parsed::Partial;
+    };
+    at += 4;
+    let Some(payload) = buf.get(at..at + len) else {
+        return Parsed::Partial;
+    };
+    Parsed::Frame {
+        opcode: first & 0x0f,
+        payload: payload
+            .iter()
+            .zip(mask.iter().cycle())
+            .map(|(b, m)| b ^ m)
+            .collect(),
+        length: at + len,
+    }
+}
+
+/// One connected client.
+pub struct Socket {
+    stream: TcpStream,
+    received: Vec<u8>,
+}
+
+impl Socket {
+    /// Complete the opening handshake, then switch to non-blocking reads.
+    pub fn accept(mut stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            let n = stream.read(&mut buf)?;
+            if n == 0 || request.len() > 8 * 1024 {
+                return Err(io::ErrorKind::InvalidData.into());
+            }
+            request.extend(&buf[..n]);
+        }
+        let request = String::from_utf8_lossy(&request);
+        let key = request.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("Sec-WebSocket-Key")
+                .then(|| value.trim().to_owned())
+        });
+        let Some(key) = key else {
+            stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a WebSocket request",
+            ));
+        };
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key)
+        )?;
+        // A client that stops reading is dropped rather than stalling the
+        // others.
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            received: Vec::new(),
+        })
+    }
+
+    pub fn send_text(&mut self, text: &str) -> io::Result<()> {
+        self.write(&frame(TEXT, text.as_bytes()))
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        // Blocking for the write, so a full buffer waits rather than
+        // splitting a frame.
+        self.stream.set_nonblocking(false)?;
+        let result = self.stream.write_all(bytes);
+        self.stream.set_nonblocking(true)?;
+        result
+    }
+
+    /// The text messages that have arrived, answering pings on the way.
+    /// An error means the client has gone.
+    pub fn poll(&mut self) -> io::Result<Vec<String>> {
+        let mut buf = [0; 4096];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.received.extend(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        let mut messages = Vec::new();
+        loop {
+            match parse(&self.received) {
+                Parsed::Partial => return Ok(messages),
+                Parsed::Bad => return Err(io::ErrorKind::InvalidData.into()),
+                Parsed::Frame {
+                    opcode,
+                    payload,
+                    length,
+                } => {
+                    self.received.drain(..length);
+                    match opcode {
+                        TEXT => messages.push(String::from_utf8_lossy(&payload).into_owned()),
+                        PING => self.write(&frame(PONG, &payload))?,
+                        CLOSE => {
+                            let _ = self.write(&frame(CLOSE, &[]));
+                            return Err(io::ErrorKind::ConnectionAborted.into());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handshake_key() {
+        // The example in RFC 6455, section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"a"), "YQ==");
+    }
+
+    #[test]
+    fn frames() {
+        assert_eq!(frame(TEXT, b"hi"), [0x81, 2, b'h', b'i']);
+        assert_eq!(frame(TEXT, &[0; 300])[..4], [0x81, 126, 1, 44]);
+
+        // A masked "Hello" from RFC 6455, section 5.7.
+        let masked = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        assert_eq!(parse(&masked[..6]), Parsed::Partial);
+        assert_eq!(
+            parse(&masked),
+            Parsed::Frame {
+                opcode: TEXT,
+                payload: b"Hello".to_vec(),
+                length: 11
+            }
+        );
+        // Unmasked, and fragmented.
+        assert_eq!(parse(&[0x81, 0x05, b'H']), Parsed::Bad);
+        assert_eq!(parse(&[0x01, 0x85, 0, 0, 0, 0]), Parsed::Bad);
+    }
+}
@@ -0,0 +1,334 @@
+//! Engineering stress and strain from specimen geometry, and the material
+//! properties read off the curve.
+
+use tensile_core::units::Unit;
+
+use crate::Sample;
+
+/// Specimen dimensions, from the `AREA` and `GAUGE` metadata or given by
+/// hand.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Geometry {
+    /// Original cross-section in mm².
+    pub area_mm2: Option<f64>,
+    /// Original gauge length in mm.
+    pub gauge_mm: Option<f64>,
+}
+
+impl Geometry {
+    /// From `Meta:` fields; values that are not positive numbers are ignored.
+    pub fn from_meta(fields: &[(String, String)]) -> Self {
+        let get = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| k == key)
+                .and_then(|(_, v)| v.parse::<f64>().ok())
+                .filter(|v| *v > 0.0)
+        };
+        Self {
+            area_mm2: get("AREA"),
+            gauge_mm: get("GAUGE"),
+        }
+    }
+
+    /// Each dimension from `self` if set, else from `other`.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            area_mm2: self.area_mm2.or(other.area_mm2),
+            gauge_mm: self.gauge_mm.or(other.gauge_mm),
+        }
+    }
+}
+
+/// `force` in `unit` as newtons; `None` for uncalibrated counts.
+pub fn newtons(force: f64, unit: Unit) -> Option<f64> {
+    match unit {
+        Unit::Raw => None,
+        Unit::Newton => Some(force),
+        Unit::KilogramForce => Some(force * 9.806_65),
+        Unit::PoundForce => Some(force * 4.448_221_6),
+        Unit::Gram => Some(force * 0.009_806_65),
+    }
+}
+
+/// Stress and strain for one sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub stress_mpa: Option<f64>,
+    /// Dimensionless, e.g. 0.01 for 1%.
+    pub strain: Option<f64>,
+}
+
+/// The headline results of a pull.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    /// Ultimate tensile strength: the highest stress seen.
+    pub uts_mpa: Option<f64>,
+    /// Strain when the device reported `BREAK`, in percent.
+    pub elongation_pct: Option<f64>,
+    pub elastic: Option<Elastic>,
+    /// 0.2% offset yield strength.
+    pub yield_mpa: Option<f64>,
+}
+
+/// Stress range, as fractions of the peak, fitted as the elastic region.
+pub const ELASTIC_WINDOW: (f64, f64) = (0.1, 0.4);
+/// Strain offset for the yield strength.
+pub const YIELD_OFFSET: f64 = 0.002;
+
+/// A least-squares line through the elastic region.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elastic {
+    /// Young's modulus.
+    pub modulus_mpa: f64,
+    /// Stress at zero strain; nonzero from seating and zeroing errors.
+    pub intercept_mpa: f64,
+    pub r_squared: f64,
+}
+
+/// Fit the `(strain, stress)` points up to the peak whose stress lies in
+/// [`ELASTIC_WINDOW`]. Needs three points and a rising line.
+pub fn fit_elastic(curve: &[(f64, f64)]) -> Option<Elastic> {
+    let peak = curve
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1 .1.total_cmp(&b.1 .1))?
+        .0;
+    let max = curve[peak].1;
+    let window = max * ELASTIC_WINDOW.0..=max * ELASTIC_WINDOW.1;
+    let points: Vec<_> = curve[..=peak]
+        .iter()
+        .filter(|(_, stress)| window.contains(stress))
+        .collect();
+    if points.len() < 3 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in points {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        sxx += dx * dx;
+        sxy += dx * dy;
+        syy += dy * dy;
+    }
+    let modulus_mpa = sxy / sxx;
+    (sxx > 0.0 && modulus_mpa > 0.0).then(|| Elastic {
+        modulus_mpa,
+        intercept_mpa: mean_y - modulus_mpa * mean_x,
+        r_squared: sxy * sxy / (sxx * syy),
+    })
+}
+
+/// Where the curve first drops below the elastic line moved along by
+/// [`YIELD_OFFSET`], interpolated between points.
+pub fn offset_yield(curve: &[(f64, f64)], elastic: &Elastic) -> Option<f64> {
+    let above = |&(strain, stress): &(f64, f64)| {
+        stress - (elastic.modulus_mpa * (strain - YIELD_OFFSET) + elastic.intercept_mpa)
+    };
+    curve.windows(2).find_map(|w| {
+        let (d0, d1) = (above(&w[0]), above(&w[1]));
+        (d0 > 0.0 && d1 <= 0.0).then(|| w[0].1 + d0 / (d0 - d1) * (w[1].1 - w[0].1))
+    })
+}
+
+/// Turns samples into stress and strain and tracks the [`Summary`].
+///
+/// Extension comes from an extensometer on an `AUX` channel scaled to mm,
+/// measured from its first reading.
+#[derive(Debug, Clone)]
+pub struct Tensile {
+    geometry: Geometry,
+    aux: usize,
+    zero_mm: Option<f64>,
+    strain: Option<f64>,
+    /// `(strain, stress)` up to the break.
+    curve: Vec<(f64, f64)>,
+    summary: Summary,
+}
+
+impl Tensile {
+    pub fn new(geometry: Geometry, aux: usize) -> Self {
+        Self {
+            geometry,
+            aux,
+            zero_mm: None,
+            strain: None,
+            curve: Vec::new(),
+            summary: Summary::default(),
+        }
+    }
+
+    pub fn push(&mut self, s: &Sample) -> Point {
+        let stress_mpa = self
+            .geometry
+            .area_mm2
+            .zip(newtons(s.force, s.unit))
+            .map(|(area, n)| n / area);
+        if let Some(stress) = stress_mpa {
+            let uts = self.summary.uts_mpa.get_or_insert(stress);
+            *uts = uts.max(stress);
+        }
+        let extension = s.aux.get(self.aux).copied().flatten();
+        let strain = self.geometry.gauge_mm.zip(extension).map(|(gauge, mm)| {
+            let zero = *self.zero_mm.get_or_insert(mm);
+            (mm - zero) / gauge
+        });
+        self.strain = strain.or(self.strain);
+        if let (Some(stress), Some(strain)) = (stress_mpa, strain) {
+            if self.summary.elongation_pct.is_none() {
+                self.curve.push((strain, stress));
+            }
+        }
+        Point { stress_mpa, strain }
+    }
+
+    /// Record the elongation at the latest strain; later breaks are ignored.
+    pub fn mark_break(&mut self) {
+        if self.summary.elongation_pct.is_none() {
+            self.summary.elongation_pct = self.strain.map(|e| e * 100.0);
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        let elastic = fit_elastic(&self.curve);
+        Summary {
+            elastic,
+            yield_mpa: elastic.and_then(|e| offset_yield(&self.curve, &e)),
+            ..self.summary
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_line, Line};
+
+    fn sample(line: &str) -> Sample {
+        match parse_line(line).unwrap() {
+            Line::Sample(s) => s,
+            other => panic!("not a sample: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn geometry_from_metadata() {
+        let Line::Reply(r) = parse_line("Meta: SPECIMEN=A7 AREA=12.5 GAUGE=-1").unwrap() else {
+            panic!("not a reply");
+        };
+        let meta = Geometry::from_meta(&r.fields);
+        assert_eq!(
+            meta,
+            Geometry {
+                area_mm2: Some(12.5),
+                gauge_mm: None
+            }
+        );
+        let flags = Geometry {
+            area_mm2: None,
+            gauge_mm: Some(50.0),
+        };
+        assert_eq!(
+            flags.or(meta),
+            Geometry {
+                area_mm2: Some(12.5),
+                gauge_mm: Some(50.0)
+            }
+        );
+    }
+
+    #[test]
+    fn stress_strain_and_summary() {
+        let geometry = Geometry {
+            area_mm2: Some(10.0),
+            gauge_mm: Some(50.0),
+        };
+        let mut tensile = Tensile::new(geometry, 1);
+        let p = tensile.push(&sample("Force: 0 raw=0 t=0 seq=0 unit=N aux1=2.000"));
+        assert_eq!((p.stress_mpa, p.strain), (Some(0.0), Some(0.0)));
+        let p = tensile.push(&sample("Force: 500 raw=1 t=1 seq=1 unit=N aux1=2.500"));
+        assert_eq!((p.stress_mpa, p.strain), (Some(50.0), Some(0.01)));
+        tensile.push(&sample("Force: 300 raw=1 t=2 seq=2 unit=N aux1=4.500"));
+        tensile.mark_break();
+        tensile.push(&sample("Force: 0 raw=1 t=3 seq=3 unit=N aux1=9.000"));
+        tensile.mark_break();
+        assert_eq!(
+            tensile.summary(),
+            Summary {
+                uts_mpa: Some(50.0),
+                elongation_pct: Some(5.0),
+                elastic: None,
+                yield_mpa: None,
+            }
+        );
+    }
+
+    #[test]
+    fn needs_a_unit_and_an_extensometer() {
+        let mut tensile = Tensile::new(
+            Geometry {
+                area_mm2: Some(2.0),
+                gauge_mm: Some(25.0),
+            },
+            0,
+        );
+        let p = tensile.push(&sample("Force: 812 raw=812 t=0 seq=0"));
+        assert_eq!((p.stress_mpa, p.strain), (None, None));
+        let p = tensile.push(&sample("Force: 1 raw=812 t=0 seq=0 unit=kgf"));
+        assert_eq!(p.stress_mpa, Some(9.806_65 / 2.0));
+        tensile.mark_break();
+        assert_eq!(tensile.summary().elongation_pct, None);
+    }
+
+    /// Elastic to 400 MPa at E = 200 GPa, then hardening at 2 GPa.
+    fn bilinear(noise: f64) -> Vec<(f64, f64)> {
+        (0..=1000)
+            .map(|i| {
+                let strain = i as f64 * 0.0001;
+                let stress = if strain <= 0.002 {
+                    200_000.0 * strain
+                } else {
+                    400.0 + 2_000.0 * (strain - 0.002)
+                };
+                let wobble = if i % 2 == 0 { noise } else { -noise };
+                (strain, stress + wobble)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn modulus_and_offset_yield() {
+        let curve = bilinear(0.0);
+        let elastic = fit_elastic(&curve).unwrap();
+        assert!((elastic.modulus_mpa - 200_000.0).abs() < 1e-6);
+        assert!(elastic.intercept_mpa.abs() < 1e-9);
+        assert!((elastic.r_squared - 1.0).abs() < 1e-12);
+        // 200000 (e - 0.002) = 400 + 2000 (e - 0.002)
+        let expected = 400.0 + 2_000.0 * 400.0 / 198_000.0;
+        assert!((offset_yield(&curve, &elastic).unwrap() - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn noise_lowers_r_squared() {
+        let curve = bilinear(2.0);
+        let elastic = fit_elastic(&curve).unwrap();
+        assert!((elastic.modulus_mpa / 200_000.0 - 1.0).abs() < 0.01);
+        assert!(elastic.r_squared < 1.0 && elastic.r_squared > 0.99);
+        let yield_mpa = offset_yield(&curve, &elastic).unwrap();
+        assert!((yield_mpa - 404.04).abs() < 3.0);
+    }
+
+    #[test]
+    fn too_few_points_to_fit() {
+        assert_eq!(fit_elastic(&[]), None);
+        assert_eq!(
+            fit_elastic(&[(0.0, 0.0), (0.001, 100.0), (0.002, 50.0)]),
+            None
+        );
+        // Never leaves the elastic line, so never yields.
+        let elastic = fit_elastic(&bilinear(0.0)[..=20]).unwrap();
+        assert_eq!(offset_yield(&bilinear(0.0)[..=20], &elastic), None);
+    }
+}
@@ -0,0 +1,193 @@
+//! Splitting the byte stream read from the port into lines.
+
+use tensile_core::columns::Column;
+use tensile_core::stream::{self, decode_event, decode_frame, Value, HELLO};
+use tensile_core::units::Unit;
+
+use crate::line::{parse_line, Line, ParseError, Sample};
+
+/// Collects bytes as they arrive and yields each complete line, parsed.
+///
+/// Lines end in CR, LF or both; empty lines are skipped. A line that is not
+/// UTF-8 (a byte lost on the wire) is parsed with the bad bytes replaced.
+/// Binary frames, between zero bytes, come out as samples and events too;
+/// one that is damaged is skipped.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    partial: Vec<u8>,
+    /// Bytes of a frame, once its opening zero has arrived.
+    frame: Option<Vec<u8>>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed `bytes`; returns the lines they completed, in order.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Result<Line, ParseError>> {
+        let mut lines = Vec::new();
+        for &byte in bytes {
+            match (&mut self.frame, byte) {
+                // Back to back frames share no delimiter: an empty frame
+                // is the next one opening. So is the zero that ends a
+                // damaged one, most likely a frame cut short.
+                (Some(frame), HELLO) if !frame.is_empty() => match frame_line(frame) {
+                    Some(line) => {
+                        lines.push(line);
+                        self.frame = None;
+                    }
+                    None => frame.clear(),
+                },
+                (Some(_), HELLO) => {}
+                (Some(frame), _) => frame.push(byte),
+                (None, HELLO) => self.frame = Some(Vec::new()),
+                (None, b'\r' | b'\n') => {
+                    if !self.partial.is_empty() {
+                        lines.push(parse_line(&String::from_utf8_lossy(&self.partial)));
+                        self.partial.clear();
+                    }
+                }
+                (None, _) => self.partial.push(byte),
+            }
+        }
+        lines
+    }
+}
+
+fn frame_line(frame: &[u8]) -> Option<Result<Line, ParseError>> {
+    if let Some(sample) = frame_sample(frame) {
+        return Some(Ok(Line::Sample(sample)));
+    }
+    let mut scratch = [0; stream::LINE_LEN];
+    let text = decode_event(frame, &mut scratch)?;
+    Some(parse_line(&format!("Event: {text}")))
+}
+
+fn frame_sample(frame: &[u8]) -> Option<Sample> {
+    let mut scratch = [0; stream::LINE_LEN];
+    let mut sample = Sample {
+        force: 0.0,
+        unit: Unit::Raw,
+        raw: None,
+        t_us: 0,
+        seq: 0,
+        rate: None,
+        channels: Vec::new(),
+        aux: [None; 3],
+        temp_c: None,
+    };
+    for (column, index, value) in decode_frame(frame, &mut scratch)? {
+        let number = match value {
+            Value::Fixed { value, places } => value as f64 / 10f64.powi(places as i32),
+            Value::Unit(unit) => {
+                sample.unit = unit;
+                continue;
+            }
+            Value::Input(_) => continue,
+        };
+        match column {
+            Column::Force => sample.force = number,
+            Column::Raw => sample.raw = Some(number as i32),
+            Column::T => sample.t_us = number as u64,
+            Column::Seq => sample.seq = number as u32,
+            Column::Rate => sample.rate = Some(number),
+            Column::Ch if index as usize == sample.channels.len() + 1 => {
+                sample.channels.push(number)
+            }
+            Column::Aux => {
+                if let Some(aux) = sample.aux.get_mut(index as usize) {
+                    *aux = Some(number);
+                }
+            }
+            Column::Temp => sample.temp_c = Some(number),
+            _ => {}
+        }
+    }
+    Some(sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tensile_core::stream::{BinaryEncoder, Encoder};
+
+    #[test]
+    fn joins_lines_split_across_reads() {
+        let mut decoder = Decoder::new();
+        assert!(decoder.push(b"Event: TARE offset=3 t=").is_empty());
+        let lines = decoder.push(b"10\r\nERR bad argument\r\n\r\nForce: 1 raw");
+        assert_eq!(lines.len(), 2);
+        assert!(matches!(&lines[0], Ok(Line::Event(e)) if e.t_us == 10));
+        assert_eq!(
+            lines[1],
+            Ok(Line::Error {
+                code: None,
+                message: "bad argument".into()
+            })
+        );
+        let lines = decoder.push(b"=1 t=20 seq=0\n");
+        assert!(matches!(&lines[0], Ok(Line::Sample(s)) if s.t_us == 20));
+    }
+
+    #[test]
+    fn decodes_frames_between_lines() {
+        let mut encoder = BinaryEncoder::new();
+        encoder.begin();
+        encoder.field(
+            Column::Force,
+            0,
+            Value::Fixed {
+                value: 12_094,
+                places: 3,
+            },
+        );
+        encoder.field(Column::T, 0, Value::whole(51_334_567));
+        encoder.field(Column::Seq, 0, Value::whole(813));
+        encoder.field(
+            Column::Ch,
+            1,
+            Value::Fixed {
+                value: 325,
+                places: 2,
+            },
+        );
+        encoder.field(Column::Unit, 0, Value::Unit(Unit::Newton));
+        encoder.field(Column::Aux, 1, Value::milli(750));
+        let frame = encoder.finish().to_vec();
+
+        let mut decoder = Decoder::new();
+        let mut bytes = b"OK\r\n".to_vec();
+        bytes.extend(&frame);
+        bytes.extend(&frame);
+        // Cut short on the wire: dropped at the next frame's zero.
+        bytes.extend(&frame[..frame.len() / 2]);
+        bytes.extend(&frame);
+        let mut event = [0; stream::LINE_LEN];
+        bytes.extend(stream::encode_event("TARE offset=3 t=10", &mut event));
+        bytes.extend(b"Event: TARE offset=3 t=10\r\n");
+        let (head, tail) = bytes.split_at(7);
+        let mut lines = decoder.push(head);
+        lines.extend(decoder.push(tail));
+
+        assert_eq!(lines[0], Ok(Line::Ok));
+        let samples: Vec<_> = lines[1..lines.len() - 2]
+            .iter()
+            .map(|l| match l {
+                Ok(Line::Sample(s)) => s,
+                other => panic!("not a sample: {other:?}"),
+            })
+            .collect();
+        assert_eq!(samples.len(), 3);
+        let s = samples[0];
+        assert_eq!(
+            (s.force, s.unit, s.t_us, s.seq),
+            (12.094, Unit::Newton, 51_334_567, 813)
+        );
+        assert_eq!((s.raw, s.rate), (None, None));
+        assert_eq!(s.channels, [3.25]);
+        assert_eq!(s.aux, [None, Some(0.75), None]);
+        assert_eq!(lines[lines.len() - 2], lines[lines.len() - 1]);
+        assert!(matches!(lines.last(), Some(Ok(Line::Event(e))) if e.t_us == 10));
+    }
+}
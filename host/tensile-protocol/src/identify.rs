@@ -0,0 +1,122 @@
+//! Telling testers apart by what `INFO?` says about them.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::{Decoder, Line, Reply, Transport};
+
+/// The USB IDs every tester enumerates with.
+pub const USB_VID: u16 = 0x16c0;
+pub const USB_PID: u16 = 0x27dd;
+
+/// One tester, from its `INFO?` reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descriptor {
+    /// The flash chip's unique ID in hex, also the USB serial number.
+    pub serial: String,
+    /// Set on the device with `NAME`.
+    pub name: Option<String>,
+    pub firmware: String,
+    pub git: Option<String>,
+    /// e.g. `pico` or `carrier`.
+    pub board: Option<String>,
+    /// The load-cell ADC.
+    pub backend: Option<String>,
+}
+
+impl Descriptor {
+    /// `None` unless `reply` is an `INFO?` reply with a serial and version.
+    pub fn from_reply(reply: &Reply) -> Option<Self> {
+        if reply.kind != "Info" {
+            return None;
+        }
+        let get = |key: &str| reply.get(key).map(str::to_owned);
+        Some(Self {
+            serial: get("serial")?,
+            name: get("name"),
+            firmware: get("fw")?,
+            git: get("git"),
+            // Given twice; the second is the board the build was for.
+            board: reply
+                .fields
+                .iter()
+                .rev()
+                .find(|(k, _)| k == "board")
+                .map(|(_, v)| v.clone()),
+            backend: get("backend"),
+        })
+    }
+
+    /// Whether `wanted` is this tester's name or serial, ignoring case.
+    pub fn matches(&self, wanted: &str) -> bool {
+        self.serial.eq_ignore_ascii_case(wanted)
+            || self
+                .name
+                .as_deref()
+                .is_some_and(|n| n.eq_ignore_ascii_case(wanted))
+    }
+}
+
+/// Ask whatever is on `transport` for `INFO?`; `None` if nothing answers
+/// like a tester within `wait`.
+pub fn identify(transport: &mut dyn Transport, wait: Duration) -> io::Result<Option<Descriptor>> {
+    transport.send("INFO?")?;
+    let mut decoder = Decoder::new();
+    let mut buf = [0; 4096];
+    let deadline = Instant::now() + wait;
+    while Instant::now() < deadline {
+        let n = transport.read(&mut buf)?;
+        for line in decoder.push(&buf[..n]).into_iter().flatten() {
+            if let Some(descriptor) = match &line {
+                Line::Reply(r) => Descriptor::from_reply(r),
+                _ => None,
+            } {
+                return Ok(Some(descriptor));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_line, Curve, Simulator};
+
+    #[test]
+    fn describes_info_replies() {
+        let Ok(Line::Reply(r)) = parse_line(
+            "Info: fw=0.1.0 git=ab12cd3 built=2026-10-01 board=pico serial=E6614C31 \
+             backend=hx711 name=5kN-frame board=carrier pin_hx711=16,17",
+        ) else {
+            panic!("not a reply");
+        };
+        let d = Descriptor::from_reply(&r).unwrap();
+        assert_eq!(
+            d,
+            Descriptor {
+                serial: "E6614C31".into(),
+                name: Some("5kN-frame".into()),
+                firmware: "0.1.0".into(),
+                git: Some("ab12cd3".into()),
+                board: Some("carrier".into()),
+                backend: Some("hx711".into()),
+            }
+        );
+        assert!(d.matches("5kn-FRAME") && d.matches("e6614c31") && !d.matches("rig2"));
+
+        let Ok(Line::Reply(r)) = parse_line("Status: test=idle") else {
+            panic!("not a reply");
+        };
+        assert_eq!(Descriptor::from_reply(&r), None);
+    }
+
+    #[test]
+    fn identifies_the_simulator() {
+        let mut sim = Simulator::new(Curve::PLASTIC, 1);
+        let d = identify(&mut sim, Duration::from_millis(500))
+            .unwrap()
+            .unwrap();
+        assert_eq!((d.serial.as_str(), d.name), ("SIMULATED", None));
+    }
+}
@@ -0,0 +1,41 @@
+//! Parser for the tester's serial stream, for host tools.
+//!
+//! Lines look like `<Kind>: <value> key=value key=value`, e.g.
+//!
+//! ```text
+//! Force: 12.094 raw=1240 t=51334567 seq=813 unit=N
+//! Event: TARE offset=-8123 t=51200000
+//! Status: test=idle stream=live sensor=ok rejected=0
+//! OK
+//! ERR 2 bad argument
+//! ```
+//!
+//! [`parse_line`] turns one line into a [`Line`]; [`Decoder`] splits a byte
+//! stream read from the port into lines first. A host that sends [`HELLO`]
+//! on opening the port gets samples and events as binary frames instead of
+//! `Force:` and `Event:` lines, which [`Decoder`] turns into the same
+//! [`Sample`]s and [`Event`]s. Timestamps are µs since the device booted;
+//! [`ClockSync`] maps them to wall-clock time. [`analysis`] works results
+//! out from the samples.
+//!
+//! Host tools talk through a [`Transport`], so a [`Simulator`] can stand in
+//! for the device. [`identify`] asks whatever is on one for `INFO?` and
+//! returns a [`Descriptor`] of the tester, to pick one out by serial or
+//! `NAME`.
+
+pub mod analysis;
+mod decoder;
+mod identify;
+mod line;
+mod sim;
+mod sync;
+mod transport;
+
+pub use decoder::Decoder;
+pub use identify::{identify, Descriptor, USB_PID, USB_VID};
+pub use line::{parse_line, Event, Line, ParseError, Reply, Sample};
+pub use sim::{Curve, Simulator};
+pub use sync::{ClockSync, Exchange};
+pub use tensile_core::stream::HELLO;
+pub use tensile_core::units::Unit;
+pub use transport::Transport;
@@ -0,0 +1,288 @@
+//! One stream line into typed values.
+
+use std::fmt;
+
+use tensile_core::units::Unit;
+
+/// A `Force:` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Filtered, tared force in `unit`.
+    pub force: f64,
+    pub unit: Unit,
+    /// Unfiltered counts, unless `FORMAT` left them out.
+    pub raw: Option<i32>,
+    pub t_us: u64,
+    pub seq: u32,
+    /// dF/dt in `unit` per second, when `RATE` is on.
+    pub rate: Option<f64>,
+    /// Extra load-cell channels, from `ch1=` on.
+    pub channels: Vec<f64>,
+    /// `AUX` channels 0–2.
+    pub aux: [Option<f64>; 3],
+    /// Die temperature in °C.
+    pub temp_c: Option<f64>,
+}
+
+/// An `Event:` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// e.g. `TARE`, `TEST_START`.
+    pub name: String,
+    /// The device clock when it happened; every event carries one.
+    pub t_us: u64,
+    /// The other fields, in order.
+    pub fields: Vec<(String, String)>,
+}
+
+impl Event {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        field(&self.fields, key)
+    }
+}
+
+/// Any other `<Kind>: ...` line, such as a `Status:` or `Config:` reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub kind: String,
+    /// The first token, if it is not a `key=value` pair.
+    pub value: Option<String>,
+    pub fields: Vec<(String, String)>,
+    /// Everything after `<Kind>: `, for replies such as `Config: CAL 41000`
+    /// that have more than one bare token.
+    pub text: String,
+}
+
+impl Reply {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        field(&self.fields, key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    Sample(Sample),
+    Event(Event),
+    Reply(Reply),
+    /// `OK`: the last command was carried out.
+    Ok,
+    /// `ERR <code> <message>`: the last command was refused. Firmware
+    /// before acknowledgements sent no code.
+    Error {
+        code: Option<i32>,
+        message: String,
+    },
+    /// Anything else, such as a bare SCPI answer.
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A required field was absent.
+    Missing(&'static str),
+    /// A field did not hold the number or unit it should.
+    Invalid { field: String, value: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Missing(field) => write!(f, "missing {field}"),
+            ParseError::Invalid { field, value } => write!(f, "bad {field}: {value:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn field<'a>(fields: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+fn number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ParseError> {
+    value.parse().map_err(|_| ParseError::Invalid {
+        field: key.to_owned(),
+        value: value.to_owned(),
+    })
+}
+
+fn required<'a>(fields: &'a [(String, String)], key: &'static str) -> Result<&'a str, ParseError> {
+    field(fields, key).ok_or(ParseError::Missing(key))
+}
+
+/// Parse one line, without its terminator. Trailing whitespace is ignored.
+pub fn parse_line(line: &str) -> Result<Line, ParseError> {
+    let line = line.trim_end();
+    if line == "OK" {
+        return Ok(Line::Ok);
+    }
+    if let Some(rest) = line.strip_prefix("ERR ") {
+        let (code, message) = match rest.split_once(' ') {
+            Some((code, message)) if code.parse::<i32>().is_ok() => (code.parse().ok(), message),
+            _ => (None, rest),
+        };
+        return Ok(Line::Error {
+            code,
+            message: message.to_owned(),
+        });
+    }
+    let Some((kind, rest)) = line.split_once(':') else {
+        return Ok(Line::Other(line.to_owned()));
+    };
+    if kind.is_empty() || kind.contains(' ') {
+        return Ok(Line::Other(line.to_owned()));
+    }
+    let mut value = None;
+    let mut fields = Vec::new();
+    for (i, token) in rest.split_whitespace().enumerate() {
+        match token.split_once('=') {
+            Some((k, v)) => fields.push((k.to_owned(), v.to_owned())),
+            None if i == 0 => value = Some(token.to_owned()),
+            None => {}
+        }
+    }
+    match kind {
+        "Force" => sample(value, &fields).map(Line::Sample),
+        "Event" => {
+            let name = value.ok_or(ParseError::Missing("event name"))?;
+            let t_us = number("t", required(&fields, "t")?)?;
+            fields.retain(|(k, _)| k != "t");
+            Ok(Line::Event(Event { name, t_us, fields }))
+        }
+        _ => Ok(Line::Reply(Reply {
+            kind: kind.to_owned(),
+            value,
+            fields,
+            text: rest.trim().to_owned(),
+        })),
+    }
+}
+
+fn sample(value: Option<String>, fields: &[(String, String)]) -> Result<Sample, ParseError> {
+    let force = number("force", &value.ok_or(ParseError::Missing("force"))?)?;
+    let unit = match field(fields, "unit") {
+        None => Unit::Raw,
+        Some(name) => Unit::parse(name).ok_or_else(|| ParseError::Invalid {
+            field: "unit".to_owned(),
+            value: name.to_owned(),
+        })?,
+    };
+    let optional = |key: &str| field(fields, key).map(|v| number(key, v)).transpose();
+    let mut channels = Vec::new();
+    while let Some(value) = field(fields, &format!("ch{}", channels.len() + 1)) {
+        channels.push(number("ch", value)?);
+    }
+    Ok(Sample {
+        force,
+        unit,
+        raw: field(fields, "raw").map(|v| number("raw", v)).transpose()?,
+        t_us: number("t", required(fields, "t")?)?,
+        seq: number("seq", required(fields, "seq")?)?,
+        rate: optional("rate")?,
+        channels,
+        aux: [optional("aux0")?, optional("aux1")?, optional("aux2")?],
+        temp_c: optional("temp")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_force_lines() {
+        let line = "Force: 12.094 raw=1240 t=51334567 seq=813 rate=-0.5 ch1=3.25 unit=N aux1=0.750 temp=27.125\r";
+        let Line::Sample(s) = parse_line(line).unwrap() else {
+            panic!("not a sample");
+        };
+        assert_eq!(s.force, 12.094);
+        assert_eq!(s.unit, Unit::Newton);
+        assert_eq!((s.raw, s.t_us, s.seq), (Some(1240), 51_334_567, 813));
+        assert_eq!(s.rate, Some(-0.5));
+        assert_eq!(s.channels, [3.25]);
+        assert_eq!(s.aux, [None, Some(0.75), None]);
+        assert_eq!(s.temp_c, Some(27.125));
+
+        // FORMAT can leave raw= out.
+        let Line::Sample(s) = parse_line("Force: -31 t=1 seq=0").unwrap() else {
+            panic!("not a sample");
+        };
+        assert_eq!(
+            (s.force, s.unit, s.rate, s.raw),
+            (-31.0, Unit::Raw, None, None)
+        );
+    }
+
+    #[test]
+    fn parses_events_and_replies() {
+        let Line::Event(e) = parse_line("Event: EPOCH session=9f3c01aa t=51200000").unwrap() else {
+            panic!("not an event");
+        };
+        assert_eq!((e.name.as_str(), e.t_us), ("EPOCH", 51_200_000));
+        assert_eq!(e.get("session"), Some("9f3c01aa"));
+        assert_eq!(e.get("t"), None);
+
+        let Line::Reply(r) = parse_line("Status: test=idle sensor=ok rejected=0").unwrap() else {
+            panic!("not a reply");
+        };
+        assert_eq!((r.kind.as_str(), r.value.as_deref()), ("Status", None));
+        assert_eq!(r.get("sensor"), Some("ok"));
+
+        let Line::Reply(r) = parse_line("Config: ZERO TRACK 20 3").unwrap() else {
+            panic!("not a reply");
+        };
+        assert_eq!(
+            (r.value.as_deref(), r.text.as_str()),
+            (Some("ZERO"), "ZERO TRACK 20 3")
+        );
+
+        assert_eq!(parse_line("OK").unwrap(), Line::Ok);
+        assert_eq!(
+            parse_line("ERR 2 bad argument").unwrap(),
+            Line::Error {
+                code: Some(2),
+                message: "bad argument".into()
+            }
+        );
+        assert_eq!(
+            parse_line("ERR -113 Undefined header").unwrap(),
+            Line::Error {
+                code: Some(-113),
+                message: "Undefined header".into()
+            }
+        );
+        assert_eq!(
+            parse_line("ERR bad argument").unwrap(),
+            Line::Error {
+                code: None,
+                message: "bad argument".into()
+            }
+        );
+        assert_eq!(
+            parse_line("leafy-sys,Pico Tensile Tester,ab12,0.1.0").unwrap(),
+            Line::Other("leafy-sys,Pico Tensile Tester,ab12,0.1.0".into())
+        );
+    }
+
+    #[test]
+    fn reports_what_is_wrong() {
+        assert_eq!(
+            parse_line("Force: 1 raw=1 seq=2"),
+            Err(ParseError::Missing("t"))
+        );
+        assert_eq!(
+            parse_line("Force: 1 raw=1 t=5 seq=2 unit=furlong"),
+            Err(ParseError::Invalid {
+                field: "unit".into(),
+                value: "furlong".into()
+            })
+        );
+        assert_eq!(
+            parse_line("Event: TARE offset=3"),
+            Err(ParseError::Missing("t"))
+        );
+    }
+}
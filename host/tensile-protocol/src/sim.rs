@@ -0,0 +1,433 @@
+//! A simulated tester, for developing and testing host code without one.
+
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::Transport;
+
+/// A ductile specimen's engineering stress-strain curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve {
+    pub modulus_mpa: f64,
+    pub yield_mpa: f64,
+    pub uts_mpa: f64,
+    /// Strain at the peak; necking takes the stress down from here.
+    pub uts_strain: f64,
+    pub break_strain: f64,
+}
+
+impl Curve {
+    /// A polymer dogbone.
+    pub const PLASTIC: Curve = Curve {
+        modulus_mpa: 2_000.0,
+        yield_mpa: 40.0,
+        uts_mpa: 50.0,
+        uts_strain: 0.15,
+        break_strain: 0.25,
+    };
+
+    /// Linear to the yield point, hardening smoothly to the UTS, then
+    /// necking to 85% of it by the break; zero after.
+    pub fn stress(&self, strain: f64) -> f64 {
+        let yield_strain = self.yield_mpa / self.modulus_mpa;
+        if strain <= yield_strain {
+            self.modulus_mpa * strain.max(0.0)
+        } else if strain <= self.uts_strain {
+            let x = (strain - yield_strain) / (self.uts_strain - yield_strain);
+            self.yield_mpa + (self.uts_mpa - self.yield_mpa) * (1.0 - (1.0 - x).powi(2))
+        } else if strain < self.break_strain {
+            let x = (strain - self.uts_strain) / (self.break_strain - self.uts_strain);
+            self.uts_mpa * (1.0 - 0.15 * x * x)
+        } else {
+            0.0
+        }
+    }
+}
+
+const RATE_HZ: u64 = 80;
+const PERIOD_US: u64 = 1_000_000 / RATE_HZ;
+/// Crosshead speed, as strain per second.
+const STRAIN_RATE: f64 = 0.01;
+const AREA_MM2: f64 = 10.0;
+const GAUGE_MM: f64 = 50.0;
+/// Counts per newton, for `raw=`.
+const COUNTS_PER_N: f64 = 4_180.0;
+/// How long after the break the simulated operator stops the test.
+const STOP_AFTER_BREAK_US: u64 = 1_000_000;
+/// How long a [`Simulator::repeating`] one waits between tests.
+const REPEAT_AFTER_US: u64 = 2_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Test {
+    Idle { since_us: u64 },
+    Running { started_us: u64 },
+    Broken { at_us: u64 },
+}
+
+/// Streams `Force:` lines for [`Curve`] pulls in real time, and answers a
+/// few commands the way the device does: `START`, `STOP`, `TARE`, `INFO?`,
+/// `CONFIG?`, `META`, `META?` and `SYNC`, each ending with `OK` or `ERR
+/// <code> <reason>`. `RUN <name>` starts a test as if every profile were
+/// stored. Anything else gets `ERR 1 unknown command`.
+/// A `Heartbeat:` goes out every second.
+///
+/// The specimen is 10 mm² with a 50 mm gauge length (reported as `AREA`
+/// and `GAUGE` metadata), pulled at 1% strain per second with the
+/// extension on `aux0`. The test stops a second after the break.
+#[derive(Debug)]
+pub struct Simulator {
+    curve: Curve,
+    repeat: bool,
+    rng: u64,
+    t_us: u64,
+    seq: u32,
+    test: Test,
+    /// Tests started, numbering `TEST_START`.
+    tests: u32,
+    tare_n: f64,
+    /// `KEY=VALUE` pairs for `META?`.
+    meta: Vec<String>,
+    out: VecDeque<u8>,
+    /// Wall time at device time zero.
+    epoch: Instant,
+}
+
+impl Simulator {
+    /// Same `seed`, same noise.
+    pub fn new(curve: Curve, seed: u64) -> Self {
+        Self {
+            curve,
+            repeat: false,
+            rng: seed | 1,
+            t_us: 0,
+            seq: 0,
+            test: Test::Idle { since_us: 0 },
+            tests: 0,
+            tare_n: 0.0,
+            meta: vec![format!("AREA={AREA_MM2}"), format!("GAUGE={GAUGE_MM}")],
+            out: VecDeque::new(),
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Start a test by itself whenever it has been idle for two seconds, as
+    /// if an operator were loading specimens.
+    pub fn repeating(self) -> Self {
+        Self {
+            repeat: true,
+            ..self
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        self.out.extend(line.as_bytes());
+        self.out.extend(b"\r\n");
+    }
+
+    /// Roughly normal, mean 0 and standard deviation 1.
+    fn noise(&mut self) -> f64 {
+        let mut sum = 0.0;
+        for _ in 0..12 {
+            // xorshift64
+            self.rng ^= self.rng << 13;
+            self.rng ^= self.rng >> 7;
+            self.rng ^= self.rng << 17;
+            sum += (self.rng >> 11) as f64 / (1u64 << 53) as f64;
+        }
+        sum - 6.0
+    }
+
+    fn strain(&self) -> f64 {
+        match self.test {
+            Test::Idle { .. } => 0.0,
+            Test::Running { started_us } => (self.t_us - started_us) as f64 / 1e6 * STRAIN_RATE,
+            Test::Broken { .. } => self.curve.break_strain,
+        }
+    }
+
+    /// One sample period on; queue its lines.
+    fn tick(&mut self) {
+        self.t_us += PERIOD_US;
+        self.seq = self.seq.wrapping_add(1);
+        if let Test::Idle { since_us } = self.test {
+            if self.repeat && self.t_us - since_us >= REPEAT_AFTER_US {
+                let _ = self.run("START");
+            }
+        }
+        let strain = self.strain();
+        if let Test::Running { .. } = self.test {
+            if strain >= self.curve.break_strain {
+                let peak = self.curve.uts_mpa * AREA_MM2;
+                let t = self.t_us;
+                self.test = Test::Broken { at_us: t };
+                self.line(&format!("Event: BREAK peak={peak:.0} force=0 t={t}"));
+            }
+        }
+        if let Test::Broken { at_us } = self.test {
+            if self.t_us - at_us >= STOP_AFTER_BREAK_US {
+                let t = self.t_us;
+                self.test = Test::Idle { since_us: t };
+                self.line(&format!("Event: TEST_STOP reason=command t={t}"));
+            }
+        }
+        let load_n = self.curve.stress(strain) * AREA_MM2;
+        let raw = ((load_n + 0.05 * self.noise()) * COUNTS_PER_N).round();
+        let force = raw / COUNTS_PER_N - self.tare_n;
+        let extension_mm = strain * GAUGE_MM + 0.002 * self.noise();
+        let (t, seq) = (self.t_us, self.seq);
+        self.line(&format!(
+            "Force: {force:.3} raw={raw:.0} t={t} seq={seq} unit=N aux0={extension_mm:.3}"
+        ));
+        if t % 1_000_000 == 0 {
+            let test = match self.test {
+                Test::Idle { .. } => "idle",
+                Test::Running { .. } | Test::Broken { .. } => "running",
+            };
+            self.line(&format!(
+                "Heartbeat: test={test} stream=live sensor=ok buffer=0/31 faults=0 outputs=0 uptime={} t={t}",
+                t / 1_000_000
+            ));
+        }
+    }
+
+    fn command(&mut self, command: &str) {
+        match self.run(command) {
+            Ok(()) => self.line("OK"),
+            Err((code, reason)) => self.line(&format!("ERR {code} {reason}")),
+        }
+    }
+
+    /// Carry out `command`, with the firmware's error code if refused.
+    fn run(&mut self, command: &str) -> Result<(), (u8, &'static str)> {
+        let t = self.t_us;
+        if let Some(entry) = command.trim().strip_prefix("META ") {
+            let (key, value) = entry.split_once('=').ok_or((2, "bad argument"))?;
+            let key = key.to_ascii_uppercase();
+            self.meta.retain(|e| !e.starts_with(&format!("{key}=")));
+            if !value.is_empty() {
+                self.meta.push(format!("{key}={value}"));
+            }
+            return Ok(());
+        }
+        let command = command.trim().to_ascii_uppercase();
+        let command = match command.strip_prefix("RUN ") {
+            Some(_) => "START".to_owned(),
+            None => command,
+        };
+        if let Some(token) = command.strip_prefix("SYNC ") {
+            let token: u64 = token.trim().parse().map_err(|_| (2, "bad argument"))?;
+            let now = self.now_us();
+            self.line(&format!("Sync: host={token} t={now}"));
+            return Ok(());
+        }
+        match command.as_str() {
+            "START" if matches!(self.test, Test::Idle { .. }) => {
+                self.test = Test::Running { started_us: t };
+                self.tests += 1;
+                let n = self.tests;
+                self.line(&format!("Event: TEST_START test={n} t={t}"));
+            }
+            "START" => return Err((3, "test already running")),
+            "STOP" if matches!(self.test, Test::Idle { .. }) => return Err((3, "no test running")),
+            "STOP" => {
+                self.test = Test::Idle { since_us: t };
+                self.line(&format!("Event: TEST_STOP reason=command t={t}"));
+            }
+            "TARE" => {
+                self.tare_n = self.curve.stress(self.strain()) * AREA_MM2;
+                let offset = (self.tare_n * COUNTS_PER_N).round();
+                self.line(&format!(
+                    "Event: TARE offset={offset:.0} sigma=0 n=1 rejected=0 t={t}"
+                ));
+            }
+            "INFO?" => self.line(concat!(
+                "Info: fw=",
+                env!("CARGO_PKG_VERSION"),
+                " git=sim built=sim board=sim serial=SIMULATED backend=sim"
+            )),
+            "CONFIG?" => {
+                self.line("Config: UNITS N");
+                self.line("Config: n=1");
+            }
+            "META?" => {
+                let line = format!("Meta: {}", self.meta.join(" "));
+                self.line(&line);
+            }
+            _ => return Err((1, "unknown command")),
+        }
+        Ok(())
+    }
+
+    /// Device time for wall time now.
+    fn now_us(&self) -> u64 {
+        self.epoch.elapsed().as_micros() as u64
+    }
+}
+
+impl Transport for Simulator {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.out.is_empty() {
+            let due = self.t_us + PERIOD_US;
+            let wait = due.saturating_sub(self.now_us()).min(100_000);
+            std::thread::sleep(Duration::from_micros(wait));
+            while self.t_us + PERIOD_US <= self.now_us() {
+                self.tick();
+            }
+        }
+        let n = buf.len().min(self.out.len());
+        for (slot, byte) in buf.iter_mut().zip(self.out.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        self.command(command);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{Geometry, Tensile};
+    use crate::{Decoder, Line};
+
+    fn drain(sim: &mut Simulator, decoder: &mut Decoder) -> Vec<Line> {
+        let bytes: Vec<u8> = sim.out.drain(..).collect();
+        decoder
+            .push(&bytes)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn curve_shape() {
+        let c = Curve::PLASTIC;
+        assert_eq!(c.stress(0.01), 20.0);
+        assert_eq!(c.stress(0.02), 40.0);
+        assert_eq!(c.stress(c.uts_strain), 50.0);
+        assert!(c.stress(0.1) > 40.0 && c.stress(0.1) < 50.0);
+        assert!((c.stress(0.2499) - 42.5).abs() < 0.1);
+        assert_eq!(c.stress(0.25), 0.0);
+    }
+
+    #[test]
+    fn a_pull_analyses_like_the_curve() {
+        let mut sim = Simulator::new(Curve::PLASTIC, 7);
+        let mut decoder = Decoder::new();
+        sim.command("META?");
+        let Line::Reply(meta) = drain(&mut sim, &mut decoder).remove(0) else {
+            panic!("no Meta reply");
+        };
+        let mut tensile = Tensile::new(Geometry::from_meta(&meta.fields), 0);
+        sim.command("start");
+        let mut stopped = false;
+        let mut samples = 0;
+        while !stopped {
+            sim.tick();
+            for line in drain(&mut sim, &mut decoder) {
+                match line {
+                    Line::Sample(s) => {
+                        tensile.push(&s);
+                        samples += 1;
+                    }
+                    Line::Event(e) if e.name == "BREAK" => tensile.mark_break(),
+                    Line::Event(e) if e.name == "TEST_STOP" => stopped = true,
+                    _ => {}
+                }
+            }
+            assert!(samples < 10_000, "never stopped");
+        }
+        // 25 s to the break, then a second.
+        assert!((2_075..=2_085).contains(&samples));
+        let summary = tensile.summary();
+        let elastic = summary.elastic.unwrap();
+        assert!((elastic.modulus_mpa / 2_000.0 - 1.0).abs() < 0.02);
+        assert!(elastic.r_squared > 0.999);
+        assert!((summary.uts_mpa.unwrap() - 50.0).abs() < 0.2);
+        assert!((summary.yield_mpa.unwrap() - 40.5).abs() < 1.0);
+        assert!((summary.elongation_pct.unwrap() - 25.0).abs() < 0.2);
+    }
+
+    #[test]
+    fn answers_commands() {
+        let mut sim = Simulator::new(Curve::PLASTIC, 1);
+        let mut decoder = Decoder::new();
+        sim.send("STOP").unwrap();
+        sim.send("FILTER AVG 8").unwrap();
+        sim.send("START").unwrap();
+        let lines = drain(&mut sim, &mut decoder);
+        let error = |code, message: &str| Line::Error {
+            code: Some(code),
+            message: message.into(),
+        };
+        assert_eq!(lines[0], error(3, "no test running"));
+        assert_eq!(lines[1], error(1, "unknown command"));
+        assert!(matches!(&lines[2], Line::Event(e) if e.name == "TEST_START"));
+        assert_eq!(lines[3], Line::Ok);
+
+        sim.send("SYNC 1792065600000000").unwrap();
+        let lines = drain(&mut sim, &mut decoder);
+        let Some(Line::Reply(r)) = lines.iter().find(|l| matches!(l, Line::Reply(_))) else {
+            panic!("no Sync reply in {lines:?}");
+        };
+        assert_eq!(
+            (r.kind.as_str(), r.get("host")),
+            ("Sync", Some("1792065600000000"))
+        );
+
+        sim.send("META specimen=a7").unwrap();
+        sim.send("META AREA=").unwrap();
+        sim.send("META?").unwrap();
+        sim.send("RUN pla").unwrap();
+        let lines = drain(&mut sim, &mut decoder);
+        let Some(Line::Reply(r)) = lines.iter().find(|l| matches!(l, Line::Reply(_))) else {
+            panic!("no Meta reply in {lines:?}");
+        };
+        assert_eq!(r.text, "GAUGE=50 SPECIMEN=a7");
+        assert!(lines.contains(&Line::Error {
+            code: Some(3),
+            message: "test already running".into()
+        }));
+    }
+
+    #[test]
+    fn repeating_starts_tests_by_itself() {
+        let mut sim = Simulator::new(Curve::PLASTIC, 1).repeating();
+        let mut decoder = Decoder::new();
+        let mut starts = Vec::new();
+        // Two seconds idle, 26 s per pull.
+        for _ in 0..(2 + 26 + 2) * RATE_HZ + 1 {
+            sim.tick();
+            for line in drain(&mut sim, &mut decoder) {
+                if let Line::Event(e) = line {
+                    if e.name == "TEST_START" {
+                        starts.push(e.get("test").map(str::to_owned));
+                    }
+                }
+            }
+        }
+        assert_eq!(starts, [Some("1".into()), Some("2".into())]);
+    }
+
+    #[test]
+    fn heartbeat_every_second() {
+        let mut sim = Simulator::new(Curve::PLASTIC, 1);
+        let mut decoder = Decoder::new();
+        for _ in 0..3 * RATE_HZ {
+            sim.tick();
+        }
+        let beats: Vec<_> = drain(&mut sim, &mut decoder)
+            .into_iter()
+            .filter_map(|l| match l {
+                Line::Reply(r) if r.kind == "Heartbeat" => Some(r),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(beats.len(), 3);
+        assert_eq!(beats[2].get("uptime"), Some("3"));
+    }
+}
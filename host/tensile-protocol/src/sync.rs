@@ -0,0 +1,162 @@
+//! Mapping device timestamps to host wall-clock time with `SYNC`.
+//!
+//! The host sends `SYNC <its clock in µs>` and the device answers
+//! `Sync: host=<that> t=<its clock>`. The device read its clock somewhere
+//! in the round trip, so the midpoint is the best guess at the host time
+//! that matched, good to half the round trip. A line through the fastest
+//! exchanges gives the offset and the drift between the two crystals.
+
+use crate::Reply;
+
+/// One `SYNC` round trip, in µs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exchange {
+    pub sent_us: u64,
+    pub device_us: u64,
+    pub received_us: u64,
+}
+
+impl Exchange {
+    /// From a `Sync:` reply and the host time it arrived.
+    pub fn from_reply(reply: &Reply, received_us: u64) -> Option<Self> {
+        if reply.kind != "Sync" {
+            return None;
+        }
+        let sent_us = reply.get("host")?.parse().ok()?;
+        let device_us = reply.get("t")?.parse().ok()?;
+        (sent_us <= received_us).then_some(Self {
+            sent_us,
+            device_us,
+            received_us,
+        })
+    }
+
+    pub fn round_trip_us(&self) -> u64 {
+        self.received_us - self.sent_us
+    }
+
+    fn midpoint_us(&self) -> f64 {
+        self.sent_us as f64 + self.round_trip_us() as f64 / 2.0
+    }
+}
+
+/// Exchanges slower than this multiple of the fastest queued behind other
+/// traffic somewhere, and are left out of the fit.
+const SLOW: u64 = 2;
+
+/// The device-to-host clock map fitted to the exchanges so far.
+#[derive(Debug, Clone, Default)]
+pub struct ClockSync {
+    exchanges: Vec<Exchange>,
+    /// `(device_us, host_us, slope)` through the fast exchanges.
+    fit: Option<(f64, f64, f64)>,
+}
+
+impl ClockSync {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, exchange: Exchange) {
+        self.exchanges.push(exchange);
+        self.refit();
+    }
+
+    fn refit(&mut self) {
+        let Some(fastest) = self.exchanges.iter().map(Exchange::round_trip_us).min() else {
+            return;
+        };
+        let fast: Vec<_> = self
+            .exchanges
+            .iter()
+            .filter(|e| e.round_trip_us() <= fastest.max(1) * SLOW)
+            .collect();
+        let n = fast.len() as f64;
+        // Relative to the first, to keep the sums small.
+        let (x0, y0) = (fast[0].device_us as f64, fast[0].midpoint_us());
+        let points = || {
+            fast.iter()
+                .map(|e| (e.device_us as f64 - x0, e.midpoint_us() - y0))
+        };
+        let mean_x = points().map(|p| p.0).sum::<f64>() / n;
+        let mean_y = points().map(|p| p.1).sum::<f64>() / n;
+        let sxx: f64 = points().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = points().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+        // A second's spread at least before trusting a slope.
+        let slope = if sxx > 1e12 { sxy / sxx } else { 1.0 };
+        self.fit = Some((x0 + mean_x, y0 + mean_y, slope));
+    }
+
+    /// Host time for a device timestamp, once there has been an exchange.
+    pub fn host_us(&self, device_us: u64) -> Option<f64> {
+        let (x, y, slope) = self.fit?;
+        Some(y + (device_us as f64 - x) * slope)
+    }
+
+    /// How much faster the device clock runs than the host's, in parts per
+    /// million.
+    pub fn drift_ppm(&self) -> Option<f64> {
+        self.fit.map(|(_, _, slope)| (1.0 / slope - 1.0) * 1e6)
+    }
+
+    /// Half the fastest round trip: the worst error of a mapped time.
+    pub fn uncertainty_us(&self) -> Option<u64> {
+        self.exchanges.iter().map(|e| e.round_trip_us() / 2).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_line, Line};
+
+    const EPOCH_US: u64 = 1_792_065_600_000_000;
+
+    #[test]
+    fn exchange_from_reply() {
+        let Ok(Line::Reply(r)) = parse_line("Sync: host=1792065600000000 t=5000000") else {
+            panic!("not a reply");
+        };
+        let e = Exchange::from_reply(&r, EPOCH_US + 800).unwrap();
+        assert_eq!((e.device_us, e.round_trip_us()), (5_000_000, 800));
+        assert_eq!(Exchange::from_reply(&r, EPOCH_US - 1), None);
+    }
+
+    /// A device that booted at `EPOCH_US` and runs `ppm` fast, answering
+    /// `delay_us` after the request with `rtt_us` round trip.
+    fn exchange(at_s: u64, ppm: f64, delay_us: u64, rtt_us: u64) -> Exchange {
+        let sent_us = EPOCH_US + at_s * 1_000_000;
+        let true_host = (sent_us + delay_us - EPOCH_US) as f64;
+        Exchange {
+            sent_us,
+            device_us: (true_host * (1.0 + ppm / 1e6)).round() as u64,
+            received_us: sent_us + rtt_us,
+        }
+    }
+
+    #[test]
+    fn offset_from_one_exchange() {
+        let mut sync = ClockSync::new();
+        assert_eq!(sync.host_us(0), None);
+        sync.push(exchange(10, 0.0, 400, 1_000));
+        // Answered 100 µs before the midpoint.
+        let mapped = sync.host_us(10_000_400).unwrap();
+        assert!((mapped - (EPOCH_US + 10_000_500) as f64).abs() < 1.0);
+        assert_eq!(sync.uncertainty_us(), Some(500));
+        assert_eq!(sync.drift_ppm(), Some(0.0));
+    }
+
+    #[test]
+    fn drift_and_slow_exchanges() {
+        let mut sync = ClockSync::new();
+        for at_s in [0, 20, 40, 60] {
+            sync.push(exchange(at_s, 50.0, 500, 1_000));
+        }
+        // Held up behind a burst of samples; would skew the fit.
+        sync.push(exchange(30, 50.0, 30_000, 60_000));
+        assert!((sync.drift_ppm().unwrap() - 50.0).abs() < 0.1);
+        let device_us = exchange(100, 50.0, 500, 1_000).device_us;
+        let error = sync.host_us(device_us).unwrap() - (EPOCH_US + 100_000_500) as f64;
+        assert!(error.abs() < 10.0, "{error}");
+    }
+}
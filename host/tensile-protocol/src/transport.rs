@@ -0,0 +1,13 @@
+//! The byte stream to and from a tester.
+
+use std::io;
+
+/// A tester's line-oriented link: a serial port, or a
+/// [`Simulator`](crate::Simulator) standing in for one.
+pub trait Transport {
+    /// Read what has arrived, waiting up to about 100 ms; 0 if nothing did.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Send one command line.
+    fn send(&mut self, command: &str) -> io::Result<()>;
+}
@@ -9,6 +9,9 @@ import csv
 import os
 from collections import deque
 
+from plugins import PluginManager
+from protocol import DeviceClock, parse_line
+
 # --- Matplotlib Imports ---
 from matplotlib.backends.backend_tkagg import FigureCanvasTkAgg, NavigationToolbar2Tk
 from matplotlib.figure import Figure
@@ -20,6 +23,13 @@ COLOR_FG = "#E0E0E0"        # Light Grey
 COLOR_ACCENT = "#32CD32"    # Lime Green
 COLOR_GRAPH_BG = "#2B2B2B"  # Slightly lighter grey for graph
 COLOR_GRID = "#444444"      # Dim grey for grid lines
+COLOR_EVENT = "#FFB000"     # Amber for event markers
+
+# Device events marked on the live plot
+PLOT_EVENTS = {
+    "TEST_START", "TEST_STOP", "TEST_PAUSE", "TEST_RESUME", "TARE", "CAL",
+    "OVERLOAD", "SENSOR_FAULT", "TRIGGER", "BREAK",
+}
 
 class TensileTesterSuite:
     def __init__(self, root):
@@ -30,9 +40,13 @@ class TensileTesterSuite:
         
         # --- Variables ---
         self.serial_port = None
+        # Stream position, kept across reconnects so REPLAY can fill the gap
+        self.last_session = None
+        self.last_seq = None
         self.is_running = False
         self.is_recording = False
         self.data_queue = queue.Queue()
+        self.event_queue = queue.Queue()
         self.smooth_buffer = deque(maxlen=10)
         
         self.time_data = []
@@ -44,6 +58,10 @@ class TensileTesterSuite:
         self.save_dir = os.getcwd()
         self.tare_offset = 0 # Python-side Tare value
         self.current_raw = 0 # Keep track of raw value for taring
+
+        # --- Analysis Plugins ---
+        self.plugins = PluginManager()
+        self.plugins.load_dir()
         
         # --- STYLE CONFIGURATION ---
         self.setup_styles()
@@ -203,6 +221,7 @@ class TensileTesterSuite:
                 
                 self.time_data = []
                 self.force_data = []
+                self.clock = DeviceClock()
                 self.thread = threading.Thread(target=self.serial_loop, daemon=True)
                 self.thread.start()
             except Exception as e:
@@ -243,7 +262,12 @@ class TensileTesterSuite:
                 self.line, = self.ax.plot([], [], color=COLOR_ACCENT, linewidth=2)
                 
                 self.start_time = time.time()
+                self.start_device_time = None
                 self.is_recording = True
+                self.plugins.call("on_test_start", {
+                    "filename": self.filename_var.get(),
+                    "cal_factor": self.cal_var.get(),
+                })
                 self.record_btn.config(text="STOP RECORDING", bg="#FF4444", fg="white")
             except Exception as e:
                 messagebox.showerror("Error", str(e))
@@ -253,14 +277,26 @@ class TensileTesterSuite:
             if self.csv_file: self.csv_file.close()
             self.record_btn.config(text="START RECORDING", bg="#333", fg="white")
             
+            # --- PLUGIN ANALYSIS ---
+            extra = self.plugins.call("on_test_end", {
+                "filename": self.filename_var.get(),
+                "peak_force": self.peak_force,
+                "times": list(self.time_data),
+                "forces": list(self.force_data),
+            })
+
             # --- SAVE SUMMARY (New Feature) ---
             # This saves the peak to a master file, but leaves the GUI display alone!
-            self.save_summary()
+            self.save_summary(extra)
             
-            messagebox.showinfo("Test Complete", f"Peak Force: {self.peak_force:.2f} kg\n\nData and Summary Saved.")
+            details = "".join(f"\n{k}: {v}" for k, v in extra.items())
+            messagebox.showinfo("Test Complete", f"Peak Force: {self.peak_force:.2f} kg{details}\n\nData and Summary Saved.")
+
+    def save_summary(self, extra=None):
+        """Appends the test result to a master summary CSV.
 
-    def save_summary(self):
-        """Appends the test result to a master summary CSV."""
+        Plugin results are appended as "key=value" cells after the fixed columns.
+        """
         summary_path = os.path.join(self.save_dir, "doe_summary.csv")
         file_exists = os.path.isfile(summary_path)
         
@@ -274,7 +310,7 @@ class TensileTesterSuite:
                     self.filename_var.get(),
                     f"{self.peak_force:.2f}",
                     time.strftime("%Y-%m-%d %H:%M:%S")
-                ])
+                ] + [f"{k}={v}" for k, v in (extra or {}).items()])
         except Exception as e:
             print(f"Summary Error: {e}")
 
@@ -284,10 +320,31 @@ class TensileTesterSuite:
             try:
                 if self.serial_port.in_waiting:
                     line = self.serial_port.readline().decode('utf-8', errors='ignore').strip()
-                    if line.startswith("Force:"):
-                        # Extract Raw Data from Pico
-                        self.current_raw = int(line.split(":")[1].strip())
-                        self.data_queue.put(self.current_raw)
+                    parsed = parse_line(line)
+                    if not parsed: continue
+                    kind, value, fields = parsed
+                    if kind == "Event" and fields.get("session"):
+                        # Same boot as before the reconnect: fetch what we missed
+                        if fields["session"] == self.last_session and self.last_seq is not None:
+                            self.serial_port.write(f"REPLAY {self.last_seq}\r\n".encode())
+                        self.last_session = fields["session"]
+                        self.clock.on_epoch(fields["session"], int(fields.get("t", 0)))
+                    elif kind == "Event" and value in PLOT_EVENTS and "t" in fields:
+                        t_dev = self.clock.peek_seconds(int(fields["t"]))
+                        if t_dev is not None:
+                            self.event_queue.put((value, t_dev))
+                    elif kind == "Force" and value is not None:
+                        if "seq" in fields:
+                            seq = int(fields["seq"])
+                            if self.last_seq is not None and seq <= self.last_seq:
+                                continue  # Already have it
+                            self.last_seq = seq
+                        # Extract Raw Data from Pico. With UNITS set the value is
+                        # already scaled, so fall back to the tared counts.
+                        self.current_raw = int(fields["raw"]) if "unit" in fields else int(value)
+                        # Device timestamp (s) if the firmware sends one
+                        t_dev = self.clock.to_seconds(int(fields["t"])) if "t" in fields else None
+                        self.data_queue.put((self.current_raw, t_dev))
             except: pass
             time.sleep(0.001)
 
@@ -296,7 +353,7 @@ class TensileTesterSuite:
         try:
             while True:
                 # 1. Get RAW value from Pico
-                raw_val = self.data_queue.get_nowait()
+                raw_val, t_dev = self.data_queue.get_nowait()
                 
                 self.smooth_buffer.append(raw_val)
                 if len(self.smooth_buffer) > 0:
@@ -322,15 +379,24 @@ class TensileTesterSuite:
                 
                 # 5. Record/Graph
                 if self.is_recording:
-                    elapsed = round(time.time() - self.start_time, 3)
+                    # Prefer the device clock: host time includes USB/queue latency
+                    if t_dev is not None:
+                        if self.start_device_time is None:
+                            self.start_device_time = t_dev
+                        elapsed = round(t_dev - self.start_device_time, 3)
+                    else:
+                        elapsed = round(time.time() - self.start_time, 3)
                     self.time_data.append(elapsed)
                     self.force_data.append(force_kg)
                     self.csv_writer.writerow({"Time_Sec": elapsed, "Force_Kg": force_kg})
+                    self.plugins.call("on_sample", elapsed, force_kg)
                     
         except queue.Empty: pass
         self.root.after(10, self.process_queue)
 
     def update_plot(self):
+        while not self.event_queue.empty():
+            self.mark_event(*self.event_queue.get_nowait())
         if self.is_recording and len(self.time_data) > 0:
             self.line.set_data(self.time_data, self.force_data)
             self.ax.relim()
@@ -338,6 +404,15 @@ class TensileTesterSuite:
             self.canvas.draw()
         self.root.after(100, self.update_plot)
 
+    def mark_event(self, name, t_dev):
+        """Draw a labelled marker where a device event happened."""
+        if not self.is_recording or self.start_device_time is None:
+            return
+        elapsed = t_dev - self.start_device_time
+        self.ax.axvline(elapsed, color=COLOR_EVENT, linestyle=':', linewidth=1)
+        self.ax.text(elapsed, 0.98, name, transform=self.ax.get_xaxis_transform(),
+                     rotation=90, va='top', ha='right', fontsize=8, color=COLOR_EVENT)
+
     def browse_folder(self):
         folder = filedialog.askdirectory()
         if folder:
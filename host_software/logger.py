@@ -3,6 +3,8 @@ import serial.tools.list_ports
 import csv
 import time
 
+from protocol import DeviceClock, parse_line
+
 # --- CONFIGURATION ---
 FILENAME = "doe_run_safe.csv"
 BAUD_RATE = 115200
@@ -10,7 +12,13 @@ BAUD_RATE = 115200
 def get_pico_port():
     ports = list(serial.tools.list_ports.comports())
     for p in ports:
-        if "USB Serial Device" in p.description or "Pi" in p.manufacturer:
+        if p.interface == "Tensile Control":
+            continue
+        if (
+            p.product == "Pico Tensile Tester"
+            or "USB Serial Device" in p.description
+            or "Pi" in (p.manufacturer or "")
+        ):
             return p.device
     return None
 
@@ -31,13 +39,14 @@ def main():
         # 1. Open CSV with DictWriter
         with open(FILENAME, mode='w', newline='') as f:
             # Define exact column names
-            fieldnames = ["Time_Sec", "Raw_Force"]
+            fieldnames = ["Time_Sec", "Raw_Force", "Ch1", "Aux0", "Aux1", "Aux2"]
             writer = csv.DictWriter(f, fieldnames=fieldnames)
             
             # Write the header once
             writer.writeheader()
             
             start_time = time.time()
+            clock = DeviceClock()
             
             # 2. Recording Loop
             while True:
@@ -46,19 +55,23 @@ def main():
                         # Read and clean the line
                         line = ser.readline().decode('utf-8', errors='ignore').strip()
                         
+                        parsed = parse_line(line)
+                        if not parsed: continue # Skip malformed lines
+                        kind, value, fields = parsed
+
+                        if kind == "Event" and fields.get("session"):
+                            clock.on_epoch(fields["session"], int(fields.get("t", 0)))
+
                         # Only process lines that look correct
-                        if line.startswith("Force:"):
-                            # Parse the Number
-                            parts = line.split(":")
-                            if len(parts) < 2: continue # Skip malformed lines
-                            
-                            raw_val_str = parts[1].strip()
-                            if not raw_val_str: continue # Skip empty values
-                            
-                            current_force = int(raw_val_str)
+                        if kind == "Force" and value is not None:
+                            # Scaled (UNITS) values are decimals, counts are integers
+                            current_force = float(value) if "unit" in fields else int(value)
                             
-                            # Calculate Time
-                            current_time = round(time.time() - start_time, 3)
+                            # Calculate Time (device clock when available)
+                            if "t" in fields:
+                                current_time = round(clock.to_seconds(int(fields["t"])), 6)
+                            else:
+                                current_time = round(time.time() - start_time, 3)
                             
                             # Print to Console (Verify columns aren't swapping here!)
                             print(f"Time: {current_time} -> Force: {current_force}")
@@ -67,7 +80,13 @@ def main():
                             # This makes swapping impossible
                             writer.writerow({
                                 "Time_Sec": current_time, 
-                                "Raw_Force": current_force
+                                "Raw_Force": current_force,
+                                # Second load cell, blank on single-channel builds
+                                "Ch1": fields.get("ch1", ""),
+                                # Scaled AUX channels, blank when disabled
+                                "Aux0": fields.get("aux0", ""),
+                                "Aux1": fields.get("aux1", ""),
+                                "Aux2": fields.get("aux2", ""),
                             })
                             
                             # Ensure it writes to disk immediately
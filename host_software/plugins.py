@@ -0,0 +1,73 @@
+"""Plugin hooks for custom analysis in the host tools.
+
+A plugin is any object (or module) with some of these callbacks:
+
+    on_test_start(info)   -> None       info: {"filename": ..., "cal_factor": ...}
+    on_sample(t, force)   -> None       t in seconds, force in kg
+    on_test_end(summary)  -> dict|None  summary: {"filename", "peak_force", "times", "forces"}
+
+Whatever on_test_end returns is merged into the test summary, so a plugin
+can add its own pass/fail verdicts or numbers without touching the GUI.
+
+Drop a .py file into the plugins/ folder next to this file and it is loaded
+at startup, or call PluginManager.register() with an object from code.
+"""
+import importlib.util
+import os
+import traceback
+
+HOOKS = ("on_test_start", "on_sample", "on_test_end")
+PLUGIN_DIR = os.path.join(os.path.dirname(os.path.abspath(__file__)), "plugins")
+
+
+class PluginManager:
+    def __init__(self):
+        self.plugins = []
+
+    def register(self, plugin, name=None):
+        """Add a plugin object. It only needs the hooks it cares about."""
+        if not any(callable(getattr(plugin, h, None)) for h in HOOKS):
+            raise ValueError(f"{plugin!r} defines none of {HOOKS}")
+        self.plugins.append((name or getattr(plugin, "__name__", repr(plugin)), plugin))
+
+    def load_dir(self, folder=PLUGIN_DIR):
+        """Import every .py file in `folder` as a plugin module."""
+        if not os.path.isdir(folder):
+            return
+        for filename in sorted(os.listdir(folder)):
+            if not filename.endswith(".py") or filename.startswith("_"):
+                continue
+            path = os.path.join(folder, filename)
+            name = filename[:-3]
+            try:
+                spec = importlib.util.spec_from_file_location(f"tensile_plugin_{name}", path)
+                module = importlib.util.module_from_spec(spec)
+                spec.loader.exec_module(module)
+                self.register(module, name)
+                print(f"Plugin loaded: {name}")
+            except Exception:
+                print(f"Plugin {name} failed to load:")
+                traceback.print_exc()
+
+    def call(self, hook, *args):
+        """Run `hook` on every plugin and collect non-None results.
+
+        A plugin that raises is reported and dropped so one bad script can't
+        break acquisition.
+        """
+        results = {}
+        for entry in list(self.plugins):
+            name, plugin = entry
+            fn = getattr(plugin, hook, None)
+            if not callable(fn):
+                continue
+            try:
+                result = fn(*args)
+            except Exception:
+                print(f"Plugin {name} raised in {hook}; disabling it:")
+                traceback.print_exc()
+                self.plugins.remove(entry)
+                continue
+            if isinstance(result, dict):
+                results.update(result)
+        return results
@@ -0,0 +1,12 @@
+"""Example plugin: flags tests whose peak is below a minimum.
+
+Files starting with "_" are skipped; copy this to e.g. break_check.py to
+enable it.
+"""
+
+MIN_PEAK_KG = 5.0
+
+
+def on_test_end(summary):
+    peak = summary["peak_force"]
+    return {"break_check": "PASS" if peak >= MIN_PEAK_KG else "FAIL"}
@@ -0,0 +1,346 @@
+"""Parsing helpers for the firmware's ASCII stream.
+
+Lines look like "<Kind>: <value> key=value key=value", e.g.
+
+    Force: 1234 raw=1240 t=51234567 seq=812
+    Force: 12.094 raw=1240 t=51334567 seq=813 unit=N
+    Event: EPOCH session=9f3c01aa t=51200000
+
+Timestamps (t=) are unsigned 64-bit microseconds since the device booted,
+so they never wrap in practice. Each boot is a new session with a random
+id; the device announces it with an EPOCH event whenever a host opens the
+port. Across sessions `t` restarts from zero, and DeviceClock stitches the
+sessions into one continuous axis.
+
+Force lines carry a per-session sequence number (seq=). After a reconnect
+within the same session, sending "REPLAY <last seq>" makes the device
+resend the lines it still holds, announced by "Event: REPLAY count= lost=".
+"DECIMATE <n>" sends only every nth Force line, so seq steps by n; REPLAY
+still has every line.
+
+Calibrated values (any unit but raw counts) print with three decimal places;
+"DECIMALS <n>", 0 to 6, changes that for the Force value, rate=, ch<n>=,
+MEAS:FORC?, MEAS:PEAK? and the OLED.
+
+"FORMAT <key,key,...>" picks which fields Force lines carry, e.g.
+"FORMAT force,t,seq"; the keys are force (the leading value), raw, range, t,
+seq, rate, ch, unit, aux, ext, work, temp, chamber and din, and "FORMAT
+ALL" restores them all. Each change is announced with "Event: FORMAT columns=<keys>",
+repeated to every host that connects while the format is trimmed, and
+FORMAT? answers "Format: columns=<keys>". seq still counts every line, and
+the tensile CLI needs force, t and seq.
+
+Samples can also go out as binary frames, as the tensile CLI asks for: a
+host that sends a zero byte as the first byte on the data port gets them
+(announced with "Event: PROTOCOL mode=binary t="); anything else, such as
+these scripts' first command, keeps Force lines. Each frame is the fields
+FORMAT picked, then a CRC-32, COBS-encoded between zero bytes. Events are
+framed the same way, as their text after "Event: " behind a kind byte of 2
+where samples have 1; replies stay text in between. "PROTOCOL
+ASCII|BINARY" fixes the choice instead, "PROTOCOL AUTO" goes back to
+detecting it, and PROTOCOL? answers "Protocol: mode=ascii|binary
+select=auto|fixed". A UART stream mirrors the data port, and REPLAY resends
+lines the way they first went out.
+
+For snaps and impacts, "BURST <s>" (up to 10 s) runs the converter at its
+fastest rate and keeps every conversion in RAM instead of streaming it:
+no Force lines, events or heartbeats go out until it ends, so none can be
+lost to the link. "Event: BURST_START sps= t=" opens it and "Event:
+BURST_END samples= missing= full=0|1 t=" closes it, when the time is up, the
+buffer is full (6144 conversions) or "BURST STOP" ends it early. The rate then
+goes back to SAMPLERATE and the conversions follow as "Burst: <force> t=
+raw=", in the current unit, only as fast as the link takes them, and "Event:
+BURST_SENT t=" after the last. A burst refuses to start during a test or
+with RANGE on, and START, RUN and SAMPLERATE wait for one to finish
+recording.
+
+Every Event line ends with the t= it happened at: the sample's timestamp for
+events raised by a reading (TARE, OVERLOAD, SENSOR_FAULT, TRIGGER, BREAK,
+...), the device clock for those raised by a command (CAL, TEST_START,
+TEST_STOP, ...). Hosts can place them on the same axis as the Force lines.
+
+"SYNC <token>" is answered at once with "Sync: host=<token> t=<device us>".
+Sending the host's own clock as the token and timing the round trip gives
+the offset between the two clocks; repeating it over a run gives the drift.
+
+Once a second (HEARTBEAT <ms> changes it, 0 stops it) the device sends
+"Heartbeat: test= stream= sensor= buffer=<n>/<capacity> faults=<hex>
+outputs=<hex> uptime=<s> [temp=] t=", even while Force lines are paused.
+faults= is a bit set: 1 sensor fault, 2 overload, 4 stream bytes dropped
+this test, 8 readings dropped since boot. outputs= has bit n set while
+output n is on, whether TRIG or OUT switched it. A host that hears nothing for a few
+intervals can treat the device as hung or gone.
+
+"DIAG?" answers "Diag: loops= loop_mean_us= loop_max_us= intervals=
+interval_min_us= interval_mean_us= interval_max_us= jitter_us= lines=
+latency_mean_us= latency_max_us= tx_dropped= acq_dropped= t=", to measure
+what a build or setting costs on the hardware. The loop figures are how
+long each pass of the main loop was busy; the interval figures are the time
+between conversions, jitter_us= their spread, restarted by a rate change;
+latency is how old a sample was when its live Force line went to USB.
+Each group is left out until there is something in it. tx_dropped= is as
+in the heartbeat's faults (this test), acq_dropped= readings lost since
+boot. "DIAG RESET" starts the figures afresh.
+
+Diagnostics that otherwise need a debug probe can be had over USB: "LOG
+LEVEL error|warn|info|debug|trace" sends log lines at that level and above
+as "Log: <level> <text>", behind other traffic (the events port when one is
+open); "LOG LEVEL OFF", the default, stops them. debug includes each failed
+conversion and why. Sixteen lines wait for room; "LOG?" answers "Log:
+level= lost=<lines dropped since boot>".
+
+"POWER LOW <s>" (1 to 3600) is for running off a battery: the load cell
+converter is powered down between samples that many seconds apart, woken
+early enough to settle before each, and the heartbeat, display and status
+LED blinks stop (the LED gives a short flash every 10 s instead). A fault
+or overload still shows. "POWER NORMAL" goes back to converting at the
+set rate; "POWER?" answers "Power: mode=normal" or "Power: mode=low
+interval_s=<s>".
+
+Commands that would change what a running test's readings mean (TARE,
+CAL, CH <n> TARE|CAL, UNITS, GRAVITY, SAMPLERATE, POLARITY, ZERO TRACK,
+TEMPCO, DUAL, RANGE, EXT ZERO|SCALE, UNIT:FORC and CAL:ZERO) are refused
+with error 3 (-221 for SCPI) while a test runs, so a dataset never changes
+scale partway. "MIDTEST DEFER" holds them instead: each is answered
+"Event: DEFERRED n=<held> t=" and OK, up to eight (then error 5), and once
+the test has ended they run in order. Each has had its OK, so the outcome
+comes as "Event: APPLIED cmd=<command> result=ok|err [code=<n>] t="
+rather than a second OK or ERR. "MIDTEST REJECT" is the default; "MIDTEST?" answers "MidTest:
+policy=reject|defer deferred=<held>". *RST drops anything held.
+
+Every short command is answered, after any event it causes, with "OK" or
+"ERR <code> <reason>": 1 unknown command, 2 bad argument, 3 not allowed in
+this state, 4 not built in, 5 storage full, 6 hardware fault. SCPI queries
+answer with their value alone; SCPI commands answer errors the same way,
+with the SCPI error number as the code, and still queue them for
+SYST:ERR?.
+
+Firmware built with the interlock feature refuses START, RUN and INIT
+("ERR 3 locked, UNLOCK first") until "UNLOCK <serial number>" names the
+device. The unlock is good for one start or 30 s, whichever comes first;
+an unused one ends with "Event: LOCKED reason=timeout". LOCK withdraws it
+and LOCK? reports "Lock: state=locked|unlocked [remaining_ms=]".
+
+test= in Status and Heartbeat lines is the test's phase: idle, armed (a
+start is scheduled), preload, running, holding (TEST PAUSE), stopping or
+fault (the sensor failed; nothing starts until SENSOR_OK). With "PRELOAD
+<counts>" set, a test starts in preload and moves to running with
+"Event: PRELOADED force= t=" once the load reaches it; break detection, work
+and the test's peak only count from then, and ext= is zeroed there. A
+profile's "PRELOAD <counts>" option overrides the standing one for its runs.
+
+"NOISE?" characterises the front end, e.g. after rewiring it or changing
+the excitation supply: it takes 3 seconds of tared, unfiltered readings
+with the cell unloaded, then answers "Noise: samples=<n> sigma=<counts>
+pp=<counts> [sigma_force= pp_force= unit=] effective_bits= noise_free_bits=".
+The bits are what is left of the converter's 24 over σ and over the
+peak-to-peak. It is refused during a test, and a test starting ends it with
+"Noise: state=aborted".
+
+"RANGE <B counts per kg> <switch> <hysteresis>" auto-ranges an HX711
+between its inputs: a small cell on B (gain 32), calibrated with the
+factor given, carries loads below the switch point, and the large cell on A
+(gain 128, the CAL one) the rest. Levels are in A's counts; it moves up at
+switch and back down below switch - hysteresis, announcing each change with
+"Event: RANGE input=a|b t=". B's readings are rescaled into A's counts, so
+units and filters carry on unchanged, and while it is on every Force line
+says which input it came from with range=a|b. The HX711 needs about 400 ms
+to settle on the new input, and no Force lines come in between. RANGE
+starts on B and zeroes it, so send it with the cell unloaded; TARE zeroes
+whichever input is in use. "RANGE OFF" goes back to A alone and RANGE?
+reports the setting and the input in use.
+
+"GAINCHECK <minutes> <tolerance>" checks the HX711 front end that often
+while idle: it averages A at gain 128, at gain 64 (doubled) and at 128
+again, and the two gains should agree. If they differ by more than
+tolerance counts it sends "Event: DRIFT_WARN high= low= diff= tol= t=";
+a load that moved more than tolerance during the check is reported as
+unsteady instead. Force lines pause while the check is on gain 64 (about
+1.2 s at 10 SPS), and START, RANGE or low power abort it. GAINCHECK?
+answers "GainCheck: every_min= tol=" plus "result=ok|drift|unsteady high=
+low= diff=" after the first check, or "GainCheck: off"; "GAINCHECK OFF"
+stops it. A check skipped for a test runs one period later.
+
+"SLIP <drop %> <min peak> <window> [STOP]" watches a running test for the
+grips slipping: the load falls that far below its peak (once the peak is
+at least min peak counts), then climbs back at least halfway within window
+samples. Each one is reported as "Event: SLIP peak=<counts> low=<counts>
+t="; with STOP it also ends the test with reason=slip. A fall that does
+not recover is not a slip. "SLIP OFF" turns it off.
+
+Firmware built with digital-inputs reads four logic inputs on GPIO18-21,
+pulled up, for a guard door switch or a fixture sensor. "DIN <n> ON
+[INVERT] [GATE]" turns input n on: it is active while pulled low (high with
+INVERT), Force lines carry din=<bits> with bit n set while it is active,
+and each change is announced with "Event: DIN state=<bits> t=". With GATE
+no test starts unless the input is active (START and RUN answer "ERR 3
+gating input inactive", other starts end in "Event: TEST_CANCELLED
+reason=input in=<n> t="), and a running test stops with reason=input when
+it goes inactive. "DIN <n> OFF" turns it off; DIN? lists them as "Din:
+in=<n> active=<0|1> invert= gate=" or "Din: in=<n> active=off".
+
+Testers, cameras and DAQs can start on one hardware edge. "TRIG <out> TEST"
+drives trigger output out high for as long as each test runs, raised before
+anything else at the start and announced with "Event: SYNC out=<n> t=".
+"TRIG ARM <in> [RISE|FALL]" (digital-inputs builds) arms a test to start on
+that edge of logic input in, which need not be turned on with DIN: "Event:
+TRIG_ARMED in= edge= t=" now, then "Event: SYNC in=<n> t=<edge>" when the
+edge comes, followed by TEST_START. Wiring a leader's TEST output to the
+followers' inputs starts them all together, and the SYNC times line their
+streams up afterwards. STOP or "TRIG ARM OFF" cancels the arm, as does any
+other start; "TRIG <out> OFF" or OUT frees the output. TRIG? lists a TEST
+output as "Trig: out=<n> edge=test state=<0|1>".
+
+"MARK <label>" annotates the curve, e.g. "MARK necking visible": the label
+(up to 48 characters of text) is tied to the next sample streamed, sent
+just before it as "Event: MARK seq=<its seq> t=<its t>" followed by
+"Mark: <label>". Up to 4 marks can wait for a sample; while the stream is
+paused or a test held they wait until samples flow again.
+
+TEST_START is followed by a "Meta:" line carrying whatever the operator set
+with "META KEY=VALUE" (specimen ID, area, gauge length, ...), when any.
+
+Tension reads positive unless "POLARITY INVERTED" makes compression read
+positive instead. "MODE COMPRESSION" tells the device tests push rather than
+pull, so break detection, the test's peak and the OLED, Modbus and
+MEAS:PEAK? peaks follow the compressive side; "MODE?" reports both.
+
+TEST_START also carries test=<n>, a number kept in flash that goes up by
+one every test, across power cycles. "TEST?" answers "Test: number=<latest>
+[last=<n> peak=<counts> duration=<s> reason=<why>]" for the last test that
+finished; reason is the one its TEST_STOP gave.
+
+A test does not outlive the host watching it. If every host that had a
+port open closes it, or the USB bus suspends because the cable was pulled
+or VBUS browned out, a running test stops with reason=host_lost (its
+result is still logged) and an armed one is cancelled. The next host to
+connect is told with "Event: HOST_LOST test=stopped|cancelled t=<when>".
+Tests driven only from the UART, Modbus, I2C or the panel, with no USB
+host ever attached, are not affected.
+
+Built with the i2c-target feature, the tester also answers on I2C1 (GPIO6
+SDA, GPIO7 SCL) at address 0x2A, for a PLC or microcontroller: write a
+register address, then read on from it. Force in counts and in thousandths
+of the current unit, the peak, the extensometer's displacement in um and
+the sequence number are 32-bit, high byte first, from 0x02; 0x01 holds the
+status bits and 0x16 takes 1 start, 2 stop, 3 tare or 4 peak reset. A stop
+from there reports reason=i2c. "I2C <hex address>" moves it (08-77) and
+"I2C OFF" takes it off the bus. The map is in firmware/src/i2c_target.rs.
+
+Built with the bluetooth feature, the UART stream goes to an HC-05 or HM-10
+module for a tablet on the shop floor, speaking this same protocol. It
+starts at 9600 baud, what the modules ship at; "UART <baud> MIRROR|ONLY"
+changes the tester's side once the module has been set to match. The
+module's STATE pin (GPIO2, GPIO17 on the carrier) says when a tablet is
+connected: held high for 1.2 s, so an HM-10's blinking while it advertises
+does not count. Nothing goes out on the UART without one. "Event:
+BT_CONNECTED t=" is followed by an EPOCH event for the tablet, and "Event:
+BT_DISCONNECTED t=" stops a running test with reason=host_lost unless a USB
+host is attached. "BT?" answers "Bluetooth: link=up|down baud=<rate>".
+
+Every CAL and CH <n> CAL is kept in a calibration history in flash, the
+newest 128 of them. Either takes "MASS <g>" for the reference mass used and
+"BY <operator>" (one word), falling back to META OPERATOR=; the date comes
+from the clock TIME set. "CALLOG? [n]" lists the last n, oldest first, as
+"CalLog: seq= ch= counts_per_kg= [unix= utc=] [mass_g=] [operator=]" (ch=0
+is the main cell), ending with "CalLog: end n=<count>".
+
+A cell that creeps under a held load can have it taken out: "CAL <factor>
+CREEP <ppm> <tau s>" says the reading drifts by ppm millionths of the load,
+either sign, with time constant tau (1-3600 s), e.g. "CREEP 1000 180" for
+0.1% over about ten minutes. From then on each tared reading has the drift
+built up so far removed; a TARE starts it over from no load. The model goes
+into the history (creep_ppm= creep_tau_s= on CALLOG?) and CONFIG? with the
+factor, and a CAL without CREEP turns it off. Only the main cell takes one.
+
+After a reboot caused by a firmware panic, the first host to connect gets
+"Event: PANIC at=<file>:<line> t=" followed by "Panic: <message>", the
+message as free text to the end of the line.
+
+The device exposes two serial ports, "Tensile Data" and "Tensile Control".
+While a host holds the control port open, command replies and events go
+there and the data port carries only Force lines; otherwise everything
+shares the data port. Commands are accepted on either.
+
+Both ports carry the USB serial number, the flash chip's unique ID, which
+INFO? repeats as serial=. "NAME <name>" (1-24 letters, digits, _ - or .)
+gives the tester a name that is kept in flash and added to INFO? as name=,
+so a lab with several can tell them apart; "NAME CLEAR" removes it and
+NAME? answers "Name: <name>" or "Name: none".
+
+"CONFIG DUMP" answers like CONFIG? but adds a "Config: PROFILE ..." line per
+stored profile and "Config: NAME <name>", and ends "Config: n=<lines>
+crc=<hex>", the CRC-32 of the lines before it as sent, "\r\n" included. Saved
+as is, that is the tester's whole setup. To clone it, send "CONFIG LOAD" and
+then the saved lines unchanged: CONFIG LOAD resets the settings as *RST does,
+sample rate and gravity included, and deletes the calibration, profiles and
+name, each "Config: " line then runs as the command after the prefix and is
+answered as usual, and the closing n= line
+gives "Event: CONFIG_LOADED n= failed= crc=ok|bad t=" before its OK, or
+"ERR 2 dump damaged" if lines went missing or changed. failed= counts the
+lines the tester refused, such as a CH line on a unit without that channel.
+"""
+
+
+def parse_line(line):
+    """Split a stream line into (kind, value, fields).
+
+    Returns None for lines that aren't in "<Kind>: ..." form. `value` is the
+    first token after the colon (or None if it is a key=value pair).
+    """
+    kind, sep, rest = line.partition(":")
+    if not sep or not kind or " " in kind:
+        return None
+    value = None
+    fields = {}
+    for i, token in enumerate(rest.split()):
+        key, eq, val = token.partition("=")
+        if eq:
+            fields[key] = val
+        elif i == 0:
+            value = token
+    return kind, value, fields
+
+
+class DeviceClock:
+    """Maps device timestamps onto one monotonic time axis in seconds.
+
+    The axis starts at zero on the first timestamp seen. Within a session it
+    advances as t / 1e6. When the session changes (device rebooted) or t goes backwards, the new
+    session is appended after the last time seen, so plots and CSVs never
+    fold back on themselves.
+    """
+
+    def __init__(self):
+        self.session = None
+        self.offset = 0.0
+        self.last = None
+        self.segments = 0
+
+    def on_epoch(self, session, t_us):
+        if session != self.session:
+            if self.session is not None:
+                self._new_segment(t_us)
+            self.session = session
+
+    def to_seconds(self, t_us):
+        t = t_us / 1e6
+        if self.last is None:
+            self.offset = -t
+        elif t + self.offset < self.last:
+            self._new_segment(t_us)
+        self.last = t + self.offset
+        return self.last
+
+    def peek_seconds(self, t_us):
+        """Like to_seconds, but leaves the axis alone. For event timestamps,
+        which may run ahead of the Force lines still in flight. None until
+        the first Force timestamp has been seen."""
+        if self.last is None:
+            return None
+        return t_us / 1e6 + self.offset
+
+    def _new_segment(self, t_us):
+        # Continue from the last point seen; the real gap is unknown.
+        self.offset = (self.last or 0.0) - t_us / 1e6
+        self.segments += 1
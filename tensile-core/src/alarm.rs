@@ -0,0 +1,98 @@
+//! Audible alarms: a distinct beep pattern per condition.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Alarm {
+    /// Three short high beeps.
+    Overload,
+    /// One long mid tone.
+    Break,
+    /// Two long low tones.
+    Fault,
+}
+
+impl Alarm {
+    /// Tone in Hz, beep count, and on and off times in ms.
+    fn pattern(self) -> (u32, u64, u64, u64) {
+        match self {
+            Alarm::Overload => (2_700, 3, 100, 100),
+            Alarm::Break => (1_000, 1, 600, 0),
+            Alarm::Fault => (400, 2, 400, 200),
+        }
+    }
+}
+
+/// Plays one alarm at a time. A new alarm replaces the one playing unless
+/// that one is more serious.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sounder {
+    playing: Option<(Alarm, u64)>,
+}
+
+impl Sounder {
+    pub const fn new() -> Self {
+        Self { playing: None }
+    }
+
+    pub fn start(&mut self, alarm: Alarm, now_ms: u64) {
+        if self.playing.is_none_or(|(current, _)| alarm >= current) {
+            self.playing = Some((alarm, now_ms));
+        }
+    }
+
+    /// The tone to sound at `now_ms`, or `None` for silence.
+    pub fn tone(&mut self, now_ms: u64) -> Option<u32> {
+        let (alarm, start) = self.playing?;
+        let (hz, beeps, on, off) = alarm.pattern();
+        let elapsed = now_ms.saturating_sub(start);
+        if elapsed >= beeps * (on + off) {
+            self.playing = None;
+            return None;
+        }
+        (elapsed % (on + off) < on).then_some(hz)
+    }
+
+    /// When `tone` next changes.
+    pub fn next_change(&self, now_ms: u64) -> Option<u64> {
+        let (alarm, start) = self.playing?;
+        let (_, _, on, off) = alarm.pattern();
+        let elapsed = now_ms.saturating_sub(start);
+        let phase = elapsed % (on + off);
+        let step = if phase < on {
+            on - phase
+        } else {
+            on + off - phase
+        };
+        Some(now_ms + step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plays_the_pattern_then_stops() {
+        let mut sounder = Sounder::new();
+        sounder.start(Alarm::Overload, 1_000);
+        assert_eq!(sounder.tone(1_000), Some(2_700));
+        assert_eq!(sounder.next_change(1_000), Some(1_100));
+        assert_eq!(sounder.tone(1_150), None);
+        assert_eq!(sounder.next_change(1_150), Some(1_200));
+        assert_eq!(sounder.tone(1_450), Some(2_700));
+        assert_eq!(sounder.tone(1_600), None);
+        assert_eq!(sounder.next_change(1_600), None);
+    }
+
+    #[test]
+    fn serious_alarms_are_not_cut_short() {
+        let mut sounder = Sounder::new();
+        sounder.start(Alarm::Fault, 0);
+        sounder.start(Alarm::Overload, 10);
+        assert_eq!(sounder.tone(20), Some(400));
+        sounder.start(Alarm::Fault, 500);
+        assert_eq!(sounder.tone(500), Some(400));
+        assert_eq!(sounder.tone(1_000), None);
+        assert_eq!(sounder.tone(1_100), Some(400));
+        assert_eq!(sounder.tone(1_700), None);
+    }
+}
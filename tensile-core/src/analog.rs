@@ -0,0 +1,59 @@
+//! Scaling for auxiliary analog inputs such as a clip-on extensometer.
+
+/// Convert a 12-bit RP2040 ADC reading (3.3 V reference) to millivolts.
+pub fn adc_to_millivolts(raw: u16) -> i32 {
+    (raw as i32 * 3300 + 2048) / 4096
+}
+
+/// `output = gain * volts + offset`, with gain and offset in thousandths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuxScale {
+    pub gain_milli: i32,
+    pub offset_milli: i32,
+}
+
+impl Default for AuxScale {
+    /// Report plain volts.
+    fn default() -> Self {
+        Self {
+            gain_milli: 1000,
+            offset_milli: 0,
+        }
+    }
+}
+
+impl AuxScale {
+    /// Output in thousandths of the channel's unit.
+    pub fn apply(&self, millivolts: i32) -> i64 {
+        self.gain_milli as i64 * millivolts as i64 / 1000 + self.offset_milli as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_scale_is_3v3() {
+        assert_eq!(adc_to_millivolts(0), 0);
+        assert_eq!(adc_to_millivolts(4095), 3299);
+        assert_eq!(adc_to_millivolts(2048), 1650);
+    }
+
+    #[test]
+    fn default_reports_volts() {
+        assert_eq!(AuxScale::default().apply(1234), 1234);
+    }
+
+    #[test]
+    fn extensometer_gain_and_offset() {
+        // 2.5 mm per volt, zero at 1.65 V.
+        let scale = AuxScale {
+            gain_milli: 2500,
+            offset_milli: -4125,
+        };
+        assert_eq!(scale.apply(1650), 0);
+        assert_eq!(scale.apply(3300), 4125);
+        assert_eq!(scale.apply(0), -4125);
+    }
+}
@@ -0,0 +1,71 @@
+//! Whether a Bluetooth serial module on the UART has a tablet connected,
+//! from its STATE pin. An HC-05 holds the pin high while connected; an
+//! HM-10 blinks it while advertising, so the link only counts as up once
+//! the pin has stayed high longer than a blink.
+
+/// Longer than the HM-10's 500 ms blink.
+pub const HOLD_MS: u64 = 1_200;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Link {
+    /// When the pin last went high, while it is.
+    high_since: Option<u64>,
+    connected: bool,
+}
+
+impl Link {
+    pub const fn new() -> Self {
+        Self {
+            high_since: None,
+            connected: false,
+        }
+    }
+
+    pub fn connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Feed the pin level at `now_ms`. Returns the new state if it changed.
+    pub fn update(&mut self, high: bool, now_ms: u64) -> Option<bool> {
+        let connected = match (high, self.high_since) {
+            (false, _) => {
+                self.high_since = None;
+                false
+            }
+            (true, None) => {
+                self.high_since = Some(now_ms);
+                self.connected
+            }
+            (true, Some(since)) => self.connected || now_ms - since >= HOLD_MS,
+        };
+        (connected != self.connected).then(|| {
+            self.connected = connected;
+            connected
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blinking_is_not_a_connection() {
+        let mut link = Link::new();
+        for t in (0..5_000).step_by(100) {
+            assert_eq!(link.update(t % 1_000 < 500, t), None);
+        }
+        assert!(!link.connected());
+    }
+
+    #[test]
+    fn connects_when_held_and_drops_at_once() {
+        let mut link = Link::new();
+        assert_eq!(link.update(true, 0), None);
+        assert_eq!(link.update(true, 1_000), None);
+        assert_eq!(link.update(true, HOLD_MS), Some(true));
+        assert_eq!(link.update(true, 9_000), None);
+        assert_eq!(link.update(false, 9_010), Some(false));
+        assert_eq!(link.update(true, 9_020), None);
+    }
+}
@@ -0,0 +1,186 @@
+//! Burst capture: every conversion of a short window kept in RAM instead of
+//! streamed, for snaps and impacts too fast to trust to the live link, then
+//! sent once the window is over.
+
+use crate::quantity::Micros;
+
+/// Longest window `BURST` accepts.
+pub const MAX_MS: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    Recording {
+        start: Option<Micros>,
+        window_us: u64,
+        stop: bool,
+    },
+    Sending {
+        next: usize,
+    },
+}
+
+/// How a recording went, once it has ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub samples: usize,
+    /// Conversions that failed or never came.
+    pub missing: u32,
+    /// The buffer filled before the window ended.
+    pub full: bool,
+}
+
+pub struct Burst<const N: usize> {
+    /// Microseconds since the first sample, and the converter's value.
+    samples: [(u32, i32); N],
+    len: usize,
+    first: Micros,
+    missing: u32,
+    state: State,
+}
+
+impl<const N: usize> Burst<N> {
+    pub const fn new() -> Self {
+        Self {
+            samples: [(0, 0); N],
+            len: 0,
+            first: Micros(0),
+            missing: 0,
+            state: State::Idle,
+        }
+    }
+
+    /// Record everything for `ms` from the next sample, dropping whatever
+    /// an earlier burst had not yet sent.
+    pub fn start(&mut self, ms: u32) {
+        self.len = 0;
+        self.missing = 0;
+        self.state = State::Recording {
+            start: None,
+            window_us: ms as u64 * 1_000,
+            stop: false,
+        };
+    }
+
+    pub fn recording(&self) -> bool {
+        matches!(self.state, State::Recording { .. })
+    }
+
+    pub fn sending(&self) -> bool {
+        matches!(self.state, State::Sending { .. })
+    }
+
+    /// End the recording at the next sample instead of when the window
+    /// closes; drop what is left to send of a finished one.
+    pub fn stop(&mut self) {
+        match &mut self.state {
+            State::Recording { stop, .. } => *stop = true,
+            State::Sending { .. } => self.state = State::Idle,
+            State::Idle => {}
+        }
+    }
+
+    /// Record a conversion, `None` if it failed; the summary once this
+    /// sample ended the recording, which it is not part of.
+    pub fn push(&mut self, t: Micros, value: Option<i32>) -> Option<Summary> {
+        let State::Recording {
+            start,
+            window_us,
+            stop,
+        } = &mut self.state
+        else {
+            return None;
+        };
+        let start = *start.get_or_insert(t);
+        let elapsed = t.0.saturating_sub(start.0);
+        let full = self.len == N;
+        if *stop || full || elapsed >= *window_us {
+            self.first = start;
+            self.state = match self.len {
+                0 => State::Idle,
+                _ => State::Sending { next: 0 },
+            };
+            return Some(Summary {
+                samples: self.len,
+                missing: self.missing,
+                full,
+            });
+        }
+        match value {
+            Some(value) => {
+                self.samples[self.len] = (elapsed as u32, value);
+                self.len += 1;
+            }
+            None => self.missing += 1,
+        }
+        None
+    }
+
+    /// The recorded samples in order, with their times; sending ends with
+    /// the last.
+    pub fn pop(&mut self) -> Option<(Micros, i32)> {
+        let State::Sending { next } = &mut self.state else {
+            return None;
+        };
+        let (dt, value) = self.samples[*next];
+        *next += 1;
+        if *next == self.len {
+            self.state = State::Idle;
+        }
+        Some((Micros(self.first.0 + dt as u64), value))
+    }
+}
+
+impl<const N: usize> Default for Burst<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_a_window_then_sends_it() {
+        let mut burst = Burst::<16>::new();
+        assert_eq!(burst.push(Micros(0), Some(1)), None);
+        burst.start(3);
+        assert!(burst.recording());
+        let mut summary = None;
+        for i in 0..10u64 {
+            let value = (i != 2).then_some(i as i32);
+            summary = summary.or(burst.push(Micros(5_000 + i * 1_000), value));
+        }
+        assert_eq!(
+            summary,
+            Some(Summary {
+                samples: 2,
+                missing: 1,
+                full: false
+            })
+        );
+        assert!(burst.sending());
+        assert_eq!(burst.pop(), Some((Micros(5_000), 0)));
+        assert_eq!(burst.pop(), Some((Micros(6_000), 1)));
+        assert!(!burst.sending());
+        assert_eq!(burst.pop(), None);
+    }
+
+    #[test]
+    fn ends_when_full_or_stopped() {
+        let mut burst = Burst::<2>::new();
+        burst.start(MAX_MS);
+        assert_eq!(burst.push(Micros(0), Some(7)), None);
+        assert_eq!(burst.push(Micros(10), Some(8)), None);
+        let summary = burst.push(Micros(20), Some(9)).unwrap();
+        assert!(summary.full && summary.samples == 2);
+        burst.stop();
+        assert!(!burst.sending());
+
+        burst.start(MAX_MS);
+        burst.stop();
+        assert_eq!(burst.push(Micros(0), Some(1)).map(|s| s.samples), Some(0));
+        assert!(!burst.recording() && !burst.sending());
+    }
+}
@@ -0,0 +1,249 @@
+//! Reference-weight calibration check.
+//!
+//! The operator places a series of known masses. Each placement is averaged
+//! over a fixed number of samples and recorded. Finishing the run fits a line
+//! through zero and reports the sensitivity and the worst deviation from it,
+//! which is what a routine metrology check signs off on.
+
+use crate::quantity::{Counts, Milligrams};
+use crate::stats::RunningStats;
+
+/// One averaged placement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalPoint {
+    /// Nominal reference mass.
+    pub mass: Milligrams,
+    pub mean: Counts,
+    pub sigma: u32,
+}
+
+/// Result of a finished check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Certificate {
+    pub points: usize,
+    /// Slope of the least-squares fit through zero. `None` if every point
+    /// was unloaded.
+    pub counts_per_kg: Option<i32>,
+    /// Largest distance of any point from the fit, in counts.
+    pub max_error: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalCheckError {
+    NotStarted,
+    /// A placement is still being averaged.
+    Busy,
+    Full,
+    NoPoints,
+}
+
+impl CalCheckError {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CalCheckError::NotStarted => "calcheck not started",
+            CalCheckError::Busy => "calcheck point in progress",
+            CalCheckError::Full => "calcheck full",
+            CalCheckError::NoPoints => "calcheck has no points",
+        }
+    }
+}
+
+pub struct CalCheck {
+    points: [CalPoint; CalCheck::MAX_POINTS],
+    len: usize,
+    samples_per_point: u32,
+    active: bool,
+    capture: Option<(Milligrams, RunningStats)>,
+    certificate: Option<Certificate>,
+}
+
+impl CalCheck {
+    pub const MAX_POINTS: usize = 10;
+
+    /// `samples_per_point` is clamped to at least 1.
+    pub fn new(samples_per_point: u32) -> Self {
+        Self {
+            points: [CalPoint {
+                mass: Milligrams(0),
+                mean: Counts(0),
+                sigma: 0,
+            }; Self::MAX_POINTS],
+            len: 0,
+            samples_per_point: samples_per_point.max(1),
+            active: false,
+            capture: None,
+            certificate: None,
+        }
+    }
+
+    /// Begin a new run, discarding any previous points and certificate.
+    pub fn start(&mut self) {
+        self.len = 0;
+        self.active = true;
+        self.capture = None;
+        self.certificate = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Start averaging a placement of `mass`.
+    pub fn begin_point(&mut self, mass: Milligrams) -> Result<(), CalCheckError> {
+        if !self.active {
+            return Err(CalCheckError::NotStarted);
+        }
+        if self.capture.is_some() {
+            return Err(CalCheckError::Busy);
+        }
+        if self.len == Self::MAX_POINTS {
+            return Err(CalCheckError::Full);
+        }
+        self.capture = Some((mass, RunningStats::new()));
+        Ok(())
+    }
+
+    /// Feed a tared sample. Returns the point once its average is complete.
+    pub fn push(&mut self, value: Counts) -> Option<CalPoint> {
+        let (mass, stats) = self.capture.as_mut()?;
+        stats.push(value.0);
+        let summary = stats.summary()?;
+        if summary.count < self.samples_per_point {
+            return None;
+        }
+        let point = CalPoint {
+            mass: *mass,
+            mean: Counts(summary.mean),
+            sigma: summary.sigma,
+        };
+        self.capture = None;
+        self.points[self.len] = point;
+        self.len += 1;
+        Some(point)
+    }
+
+    pub fn points(&self) -> &[CalPoint] {
+        &self.points[..self.len]
+    }
+
+    /// End the run and compute its certificate.
+    pub fn finish(&mut self) -> Result<Certificate, CalCheckError> {
+        if !self.active {
+            return Err(CalCheckError::NotStarted);
+        }
+        if self.capture.is_some() {
+            return Err(CalCheckError::Busy);
+        }
+        if self.len == 0 {
+            return Err(CalCheckError::NoPoints);
+        }
+        let points = self.points();
+        // Least squares through the origin: k = Σ(m·c) / Σ(m²).
+        let mut sum_mc: i128 = 0;
+        let mut sum_mm: i128 = 0;
+        for p in points {
+            sum_mc += p.mass.0 as i128 * p.mean.0 as i128;
+            sum_mm += p.mass.0 as i128 * p.mass.0 as i128;
+        }
+        let max_error = points
+            .iter()
+            .map(|p| {
+                let fit = if sum_mm == 0 {
+                    0
+                } else {
+                    p.mass.0 as i128 * sum_mc / sum_mm
+                };
+                (p.mean.0 as i128 - fit)
+                    .unsigned_abs()
+                    .min(u32::MAX as u128) as u32
+            })
+            .max()
+            .unwrap_or(0);
+        let certificate = Certificate {
+            points: self.len,
+            counts_per_kg: (sum_mm != 0).then(|| {
+                (sum_mc * 1_000_000 / sum_mm).clamp(i32::MIN as i128, i32::MAX as i128) as i32
+            }),
+            max_error,
+        };
+        self.active = false;
+        self.certificate = Some(certificate);
+        Ok(certificate)
+    }
+
+    /// Certificate of the last finished run.
+    pub fn certificate(&self) -> Option<Certificate> {
+        self.certificate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(check: &mut CalCheck, mass_mg: u32, value: i32) -> CalPoint {
+        check.begin_point(Milligrams(mass_mg)).unwrap();
+        for _ in 0..3 {
+            if let Some(point) = check.push(Counts(value)) {
+                return point;
+            }
+        }
+        panic!("point never completed");
+    }
+
+    #[test]
+    fn requires_start() {
+        let mut check = CalCheck::new(3);
+        assert_eq!(
+            check.begin_point(Milligrams(1000)),
+            Err(CalCheckError::NotStarted)
+        );
+        assert_eq!(check.push(Counts(5)), None);
+    }
+
+    #[test]
+    fn averages_each_placement() {
+        let mut check = CalCheck::new(3);
+        check.start();
+        check.begin_point(Milligrams(500_000)).unwrap();
+        assert_eq!(check.push(Counts(99)), None);
+        assert_eq!(check.begin_point(Milligrams(1)), Err(CalCheckError::Busy));
+        assert_eq!(check.push(Counts(100)), None);
+        let point = check.push(Counts(101)).unwrap();
+        assert_eq!((point.mass, point.mean), (Milligrams(500_000), Counts(100)));
+        assert_eq!(check.points().len(), 1);
+    }
+
+    #[test]
+    fn certificate_reports_slope_and_worst_point() {
+        let mut check = CalCheck::new(3);
+        check.start();
+        place(&mut check, 0, 2);
+        place(&mut check, 1_000_000, 20_000);
+        place(&mut check, 2_000_000, 40_010);
+        let cert = check.finish().unwrap();
+        assert_eq!(cert.points, 3);
+        assert_eq!(cert.counts_per_kg, Some(20_004));
+        // 1 kg fits at 20_004.
+        assert_eq!(cert.max_error, 4);
+        assert!(!check.is_active());
+        assert_eq!(check.certificate(), Some(cert));
+    }
+
+    #[test]
+    fn zero_only_run_has_no_slope() {
+        let mut check = CalCheck::new(1);
+        check.start();
+        place(&mut check, 0, -3);
+        let cert = check.finish().unwrap();
+        assert_eq!(cert.counts_per_kg, None);
+        assert_eq!(cert.max_error, 3);
+    }
+
+    #[test]
+    fn finish_needs_points() {
+        let mut check = CalCheck::new(1);
+        check.start();
+        assert_eq!(check.finish(), Err(CalCheckError::NoPoints));
+    }
+}
@@ -0,0 +1,171 @@
+//! Layout of the calibration history kept in flash, read back with
+//! `CALLOG?`, so a cell's calibrations can be traced without a notebook.
+//!
+//! Stored as a ring like the fault log; each record carries a CRC since a
+//! calibration is worth more than a fault count.
+
+use crate::creep::Creep;
+use crate::math::crc32;
+use crate::quantity::Milligrams;
+
+pub const RECORD_LEN: usize = 64;
+pub const OPERATOR_LEN: usize = 32;
+
+/// Erased flash reads as all ones; no real record uses this sequence number.
+const BLANK_SEQ: u32 = u32::MAX;
+const NO_MASS: u32 = u32::MAX;
+const OPERATOR_AT: usize = 18;
+const CREEP_AT: usize = OPERATOR_AT + OPERATOR_LEN;
+const CRC_AT: usize = RECORD_LEN - 4;
+
+/// Who calibrated: one word of printable ASCII, as `CAL ... BY` takes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operator {
+    bytes: [u8; OPERATOR_LEN],
+    len: u8,
+}
+
+impl Operator {
+    pub fn parse(word: &str) -> Option<Self> {
+        if word.is_empty()
+            || word.len() > OPERATOR_LEN
+            || !word.bytes().all(|b| b.is_ascii_graphic())
+        {
+            return None;
+        }
+        let mut bytes = [0; OPERATOR_LEN];
+        bytes[..word.len()].copy_from_slice(word.as_bytes());
+        Some(Self {
+            bytes,
+            len: word.len() as u8,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    pub seq: u32,
+    /// Unix seconds, `None` if the clock was not set.
+    pub unix: Option<u32>,
+    /// 0 for the primary cell, else the extra channel.
+    pub channel: u8,
+    pub counts_per_kg: i32,
+    /// The reference mass the factor was taken with, when given.
+    pub mass: Option<Milligrams>,
+    pub operator: Option<Operator>,
+    /// The creep model taken on with the factor. Records written before
+    /// there was one read back as `None`.
+    pub creep: Option<Creep>,
+}
+
+impl Record {
+    pub fn encode(&self) -> [u8; RECORD_LEN] {
+        let mut out = [0xFF; RECORD_LEN];
+        out[0..4].copy_from_slice(&self.seq.to_le_bytes());
+        out[4..8].copy_from_slice(&self.unix.unwrap_or(0).to_le_bytes());
+        out[8..12].copy_from_slice(&self.counts_per_kg.to_le_bytes());
+        let mass = self.mass.map_or(NO_MASS, |m| m.0);
+        out[12..16].copy_from_slice(&mass.to_le_bytes());
+        out[16] = self.channel;
+        if let Some(operator) = &self.operator {
+            out[17] = operator.len;
+            out[OPERATOR_AT..OPERATOR_AT + OPERATOR_LEN].copy_from_slice(&operator.bytes);
+        } else {
+            out[17] = 0;
+        }
+        if let Some(creep) = &self.creep {
+            out[CREEP_AT..CREEP_AT + 4].copy_from_slice(&creep.ppm.to_le_bytes());
+            out[CREEP_AT + 4..CREEP_AT + 8].copy_from_slice(&creep.tau_s.to_le_bytes());
+        }
+        let crc = crc32(&out[..CRC_AT]);
+        out[CRC_AT..].copy_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// `None` for an erased slot or one that fails its CRC.
+    pub fn decode(raw: &[u8; RECORD_LEN]) -> Option<Self> {
+        let word = |i: usize| u32::from_le_bytes([raw[i], raw[i + 1], raw[i + 2], raw[i + 3]]);
+        let seq = word(0);
+        if seq == BLANK_SEQ || word(CRC_AT) != crc32(&raw[..CRC_AT]) {
+            return None;
+        }
+        let len = raw[17] as usize;
+        let operator = match len {
+            0 => None,
+            1..=OPERATOR_LEN => {
+                let text = &raw[OPERATOR_AT..OPERATOR_AT + len];
+                Some(Operator::parse(core::str::from_utf8(text).ok()?)?)
+            }
+            _ => return None,
+        };
+        Some(Record {
+            seq,
+            unix: Some(word(4)).filter(|&t| t != 0),
+            channel: raw[16],
+            counts_per_kg: word(8) as i32,
+            mass: Some(word(12)).filter(|&m| m != NO_MASS).map(Milligrams),
+            operator,
+            creep: Creep::new(word(CREEP_AT) as i32, word(CREEP_AT + 4)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let record = Record {
+            seq: 7,
+            unix: Some(1_760_000_000),
+            channel: 1,
+            counts_per_kg: -41_000,
+            mass: Some(Milligrams(2_000_000)),
+            operator: Operator::parse("j.doe"),
+            creep: Creep::new(1_000, 180),
+        };
+        let decoded = Record::decode(&record.encode()).unwrap();
+        assert_eq!(decoded, record);
+        assert_eq!(decoded.operator.unwrap().as_str(), "j.doe");
+
+        let bare = Record {
+            unix: None,
+            mass: None,
+            operator: None,
+            creep: None,
+            ..record
+        };
+        assert_eq!(Record::decode(&bare.encode()), Some(bare));
+    }
+
+    #[test]
+    fn rejects_blank_and_damaged_slots() {
+        assert_eq!(Record::decode(&[0xFF; RECORD_LEN]), None);
+        let mut raw = Record {
+            seq: 0,
+            unix: None,
+            channel: 0,
+            counts_per_kg: 41_000,
+            mass: None,
+            operator: None,
+            creep: None,
+        }
+        .encode();
+        raw[9] ^= 1;
+        assert_eq!(Record::decode(&raw), None);
+    }
+
+    #[test]
+    fn operators_are_one_printable_word() {
+        assert!(Operator::parse("QA-lab_3").is_some());
+        assert!(Operator::parse("").is_none());
+        assert!(Operator::parse("j doe").is_none());
+        assert!(Operator::parse("ü").is_none());
+        assert!(Operator::parse("abcdefghijklmnopqrstuvwxyz0123456").is_none());
+    }
+}
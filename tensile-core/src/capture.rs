@@ -0,0 +1,180 @@
+//! Triggered capture: keep the stream quiet until the force crosses a
+//! level, then record for a set time or number of samples, led by the
+//! samples just before the crossing.
+
+use crate::quantity::{Counts, Micros};
+use crate::trigger::Edge;
+
+/// How much to record once triggered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
+    Samples(u32),
+    Millis(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Waiting for the force to be on the near side of the level, so a
+    /// capture armed under load doesn't fire at once.
+    Arming,
+    Armed,
+    Recording {
+        start: Micros,
+        samples: u32,
+    },
+    Done,
+}
+
+/// What to do with a sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Armed: keep it only as a possible pre-trigger sample.
+    Waiting,
+    /// Drop it.
+    Quiet,
+    /// It crossed the level: the first sample of the capture.
+    Started,
+    Record,
+    /// The capture is complete, holding this many samples; drop this one.
+    Ended {
+        samples: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Capture {
+    edge: Edge,
+    level: Counts,
+    length: Length,
+    pre_trigger: u32,
+    state: State,
+}
+
+impl Capture {
+    /// `pre_trigger` samples from before the crossing lead the capture; they
+    /// are not counted in `length`.
+    pub fn new(edge: Edge, level: Counts, length: Length, pre_trigger: u32) -> Self {
+        Self {
+            edge,
+            level,
+            length,
+            pre_trigger,
+            state: State::Arming,
+        }
+    }
+
+    pub fn edge(&self) -> Edge {
+        self.edge
+    }
+
+    pub fn level(&self) -> Counts {
+        self.level
+    }
+
+    pub fn length(&self) -> Length {
+        self.length
+    }
+
+    pub fn pre_trigger(&self) -> u32 {
+        self.pre_trigger
+    }
+
+    pub fn state_str(&self) -> &'static str {
+        match self.state {
+            State::Arming | State::Armed => "armed",
+            State::Recording { .. } => "recording",
+            State::Done => "done",
+        }
+    }
+
+    fn crossed(&self, force: Counts) -> bool {
+        match self.edge {
+            Edge::Above => force >= self.level,
+            Edge::Below => force <= self.level,
+        }
+    }
+
+    pub fn push(&mut self, force: Counts, t: Micros) -> Step {
+        match self.state {
+            State::Arming => {
+                if !self.crossed(force) {
+                    self.state = State::Armed;
+                }
+                Step::Waiting
+            }
+            State::Armed if self.crossed(force) => {
+                self.state = State::Recording {
+                    start: t,
+                    samples: 1,
+                };
+                Step::Started
+            }
+            State::Armed => Step::Waiting,
+            State::Done => Step::Quiet,
+            State::Recording { start, samples } => {
+                let full = match self.length {
+                    Length::Samples(n) => samples >= n,
+                    Length::Millis(ms) => t.0.saturating_sub(start.0) >= ms as u64 * 1_000,
+                };
+                if full {
+                    self.state = State::Done;
+                    Step::Ended { samples }
+                } else {
+                    self.state = State::Recording {
+                        start,
+                        samples: samples + 1,
+                    };
+                    Step::Record
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(capture: &mut Capture, forces: &[i32]) -> [Step; 6] {
+        let mut steps = [Step::Quiet; 6];
+        for (i, (&force, step)) in forces.iter().zip(&mut steps).enumerate() {
+            *step = capture.push(Counts(force), Micros(i as u64 * 100_000));
+        }
+        steps
+    }
+
+    #[test]
+    fn records_a_sample_count_after_crossing() {
+        let mut capture = Capture::new(Edge::Above, Counts(100), Length::Samples(2), 0);
+        let steps = feed(&mut capture, &[0, 50, 120, 80, 90, 200]);
+        assert_eq!(
+            steps,
+            [
+                Step::Waiting,
+                Step::Waiting,
+                Step::Started,
+                Step::Record,
+                Step::Ended { samples: 2 },
+                Step::Quiet
+            ]
+        );
+        assert_eq!(capture.state_str(), "done");
+    }
+
+    #[test]
+    fn records_for_a_time() {
+        let mut capture = Capture::new(Edge::Below, Counts(-100), Length::Millis(250), 0);
+        let steps = feed(&mut capture, &[0, -100, 0, 0, 0, 0]);
+        assert_eq!(steps[1], Step::Started);
+        assert_eq!(steps[3], Step::Record);
+        assert_eq!(steps[4], Step::Ended { samples: 3 });
+    }
+
+    #[test]
+    fn needs_a_crossing_not_just_a_level() {
+        let mut capture = Capture::new(Edge::Above, Counts(100), Length::Samples(1), 0);
+        let steps = feed(&mut capture, &[150, 150, 50, 150, 0, 0]);
+        assert_eq!(steps[..3], [Step::Waiting; 3]);
+        assert_eq!(steps[3], Step::Started);
+    }
+}
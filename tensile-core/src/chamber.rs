@@ -0,0 +1,149 @@
+//! Temperature chamber for elevated-temperature tests: an NTC thermistor
+//! and a PID loop setting the heater's duty.
+
+use crate::quantity::MilliCelsius;
+
+/// Sixteen summed 12-bit readings (full scale 65 536) of a 10 kΩ B3950 NTC
+/// to ground under a 10 kΩ pull-up, every `TABLE_STEP_C` from
+/// `TABLE_FIRST_C`.
+const NTC_TABLE: [u16; 55] = [
+    59856, 58080, 55933, 53406, 50512, 47293, 43817, 40171, 36455, 32768, 29202, 25831, 22706,
+    19860, 17306, 15040, 13049, 11313, 9807, 8507, 7387, 6424, 5596, 4885, 4273, 3747, 3294, 2902,
+    2564, 2270, 2015, 1794, 1600, 1431, 1283, 1153, 1038, 937, 848, 768, 698, 635, 579, 529, 484,
+    444, 408, 375, 346, 319, 295, 273, 254, 235, 219,
+];
+const TABLE_FIRST_C: i32 = -20;
+const TABLE_STEP_C: i32 = 5;
+
+/// Readings summed per conversion.
+pub const OVERSAMPLE: u32 = 16;
+
+/// Temperature for a sum of `OVERSAMPLE` readings, or `None` outside the
+/// table: an open or shorted thermistor.
+pub fn thermistor_temp(sum: u32) -> Option<MilliCelsius> {
+    let i = NTC_TABLE
+        .windows(2)
+        .position(|w| (w[1] as u32..=w[0] as u32).contains(&sum))?;
+    let (hot, cold) = (NTC_TABLE[i] as i32, NTC_TABLE[i + 1] as i32);
+    let base = (TABLE_FIRST_C + TABLE_STEP_C * i as i32) * 1000;
+    Some(MilliCelsius(
+        base + TABLE_STEP_C * 1000 * (hot - sum as i32) / (hot - cold),
+    ))
+}
+
+/// PID gains in thousandths, giving heater duty in permille: per °C of
+/// error, per °C·s of its integral and per °C/s of the temperature's rise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gains {
+    pub kp_milli: u32,
+    pub ki_milli: u32,
+    pub kd_milli: u32,
+}
+
+impl Gains {
+    /// Gentle enough for a small insulated box with a few hundred watts.
+    pub const DEFAULT: Self = Self {
+        kp_milli: 50_000,
+        ki_milli: 500,
+        kd_milli: 0,
+    };
+}
+
+/// Full heater duty.
+pub const FULL_DUTY: u16 = 1000;
+
+/// Duty from the error to a setpoint. The derivative acts on the
+/// measurement, so a new setpoint doesn't kick, and the integral stops
+/// growing while the output is pinned.
+#[derive(Debug, Clone, Copy)]
+pub struct Pid {
+    gains: Gains,
+    /// m°C·ms.
+    integral: i64,
+    last: Option<MilliCelsius>,
+}
+
+impl Pid {
+    pub fn new(gains: Gains) -> Self {
+        Self {
+            gains,
+            integral: 0,
+            last: None,
+        }
+    }
+
+    pub fn gains(&self) -> Gains {
+        self.gains
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0;
+        self.last = None;
+    }
+
+    /// Duty in permille for the next `dt_ms`.
+    pub fn update(&mut self, setpoint: MilliCelsius, temp: MilliCelsius, dt_ms: u32) -> u16 {
+        let error = setpoint.0 as i64 - temp.0 as i64;
+        let rise = self.last.map_or(0, |last| temp.0 as i64 - last.0 as i64);
+        self.last = Some(temp);
+        let Gains {
+            kp_milli,
+            ki_milli,
+            kd_milli,
+        } = self.gains;
+        let output = |integral: i64| {
+            kp_milli as i64 * error / 1_000_000 + ki_milli as i64 * integral / 1_000_000_000
+                - kd_milli as i64 * rise / (1000 * dt_ms.max(1) as i64)
+        };
+        let integral = self.integral + error * dt_ms as i64;
+        let pinned = |out: i64| (out >= FULL_DUTY as i64 && error > 0) || (out <= 0 && error < 0);
+        if !pinned(output(integral)) {
+            self.integral = integral;
+        }
+        output(self.integral).clamp(0, FULL_DUTY as i64) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thermistor_reads_along_the_curve() {
+        assert_eq!(thermistor_temp(32768), Some(MilliCelsius(25_000)));
+        let t = thermistor_temp(2_400).unwrap();
+        assert!((122_500..=123_000).contains(&t.0), "{t:?}");
+        assert_eq!(thermistor_temp(65_000), None);
+        assert_eq!(thermistor_temp(0), None);
+    }
+
+    #[test]
+    fn heats_flat_out_when_cold_and_holds_when_there() {
+        let mut pid = Pid::new(Gains::DEFAULT);
+        let set = MilliCelsius(80_000);
+        assert_eq!(pid.update(set, MilliCelsius(20_000), 2_000), FULL_DUTY);
+        // Pinned, so a long warm-up does not wind up the integral.
+        for _ in 0..100 {
+            pid.update(set, MilliCelsius(20_000), 2_000);
+        }
+        assert_eq!(pid.update(set, MilliCelsius(80_000), 2_000), 0);
+        assert_eq!(pid.update(set, MilliCelsius(85_000), 2_000), 0);
+    }
+
+    #[test]
+    fn integral_holds_duty_at_the_setpoint() {
+        let mut pid = Pid::new(Gains {
+            kp_milli: 0,
+            ki_milli: 1_000,
+            kd_milli: 0,
+        });
+        let set = MilliCelsius(50_000);
+        for _ in 0..50 {
+            pid.update(set, MilliCelsius(49_000), 2_000);
+        }
+        // 1 °C for 100 s at 1 permille per °C·s.
+        assert_eq!(pid.update(set, set, 2_000), 100);
+        pid.reset();
+        assert_eq!(pid.update(set, set, 2_000), 0);
+    }
+}
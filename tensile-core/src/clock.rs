@@ -0,0 +1,168 @@
+//! Wall-clock time: calendar conversion and the mapping from the µs timer.
+
+use crate::quantity::Micros;
+
+/// A UTC calendar time, from 1970 on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Days from 1970-01-01 to the given date, proleptic Gregorian.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let (era, year_of_era) = (year / 400, year % 400);
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+impl DateTime {
+    pub fn from_unix(secs: u64) -> Self {
+        let days = secs / SECS_PER_DAY + 719_468;
+        let (era, day_of_era) = (days / 146_097, days % 146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+        let month = (month_from_march + 2) % 12 + 1;
+        let year = year_of_era + era * 400 + (month <= 2) as u64;
+        let secs_of_day = secs % SECS_PER_DAY;
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (secs_of_day / 3_600) as u8,
+            minute: (secs_of_day / 60 % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+        }
+    }
+
+    /// `None` for a field out of range or a date before 1970.
+    pub fn to_unix(self) -> Option<u64> {
+        let valid = self.year >= 1970
+            && (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60;
+        if !valid {
+            return None;
+        }
+        let days = days_from_civil(self.year as u64, self.month as u64, self.day as u64);
+        let secs = days * SECS_PER_DAY
+            + self.hour as u64 * 3_600
+            + self.minute as u64 * 60
+            + self.second as u64;
+        // Rejects the 31st of short months, which roll into the next one.
+        (Self::from_unix(secs) == self).then_some(secs)
+    }
+
+    /// ISO weekday, 1 for Monday to 7 for Sunday.
+    pub fn weekday(self) -> u8 {
+        let days = days_from_civil(self.year as u64, self.month as u64, self.day as u64);
+        // 1970-01-01 was a Thursday.
+        ((days + 3) % 7 + 1) as u8
+    }
+}
+
+/// Maps the µs timer onto Unix time once something has set it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WallClock {
+    /// Unix time in µs at timer zero.
+    boot_unix_us: Option<u64>,
+}
+
+impl WallClock {
+    pub const fn new() -> Self {
+        Self { boot_unix_us: None }
+    }
+
+    /// It is `unix` seconds at timer time `now`.
+    pub fn set(&mut self, unix: u64, now: Micros) {
+        self.boot_unix_us = Some((unix * 1_000_000).saturating_sub(now.0));
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.boot_unix_us.is_some()
+    }
+
+    /// Unix seconds at timer time `t`.
+    pub fn unix(&self, t: Micros) -> Option<u64> {
+        self.boot_unix_us.map(|boot| (boot + t.0) / 1_000_000)
+    }
+
+    /// Timer time at which it will be `unix` seconds; zero if that was
+    /// before boot.
+    pub fn timer_at(&self, unix: u64) -> Option<Micros> {
+        self.boot_unix_us
+            .map(|boot| Micros((unix * 1_000_000).saturating_sub(boot)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: u16, month: u8, day: u8) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        }
+    }
+
+    #[test]
+    fn converts_known_dates() {
+        assert_eq!(date(1970, 1, 1).to_unix(), Some(0));
+        assert_eq!(date(2000, 3, 1).to_unix(), Some(951_868_800));
+        let t = DateTime::from_unix(1_792_065_600);
+        assert_eq!(
+            t,
+            DateTime {
+                hour: 12,
+                ..date(2026, 10, 15)
+            }
+        );
+        assert_eq!(t.to_unix(), Some(1_792_065_600));
+        assert_eq!(DateTime::from_unix(951_782_400), date(2000, 2, 29));
+    }
+
+    #[test]
+    fn rejects_impossible_dates() {
+        assert_eq!(date(2026, 2, 29).to_unix(), None);
+        assert_eq!(date(2026, 4, 31).to_unix(), None);
+        assert_eq!(date(1969, 12, 31).to_unix(), None);
+        assert_eq!(date(2026, 13, 1).to_unix(), None);
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(date(1970, 1, 1).weekday(), 4);
+        assert_eq!(date(2026, 10, 15).weekday(), 4);
+        assert_eq!(date(2026, 10, 18).weekday(), 7);
+    }
+
+    #[test]
+    fn wall_clock_follows_the_timer() {
+        let mut clock = WallClock::new();
+        assert_eq!(clock.unix(Micros(0)), None);
+        clock.set(1_000, Micros(2_500_000));
+        assert_eq!(clock.unix(Micros(2_500_000)), Some(1_000));
+        assert_eq!(clock.unix(Micros(4_000_000)), Some(1_001));
+        assert_eq!(clock.timer_at(1_010), Some(Micros(12_500_000)));
+        assert_eq!(clock.timer_at(10), Some(Micros(0)));
+    }
+}
@@ -0,0 +1,136 @@
+//! Which fields `Force:` lines carry, chosen with `FORMAT` so a slow link
+//! only carries what the host reads.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// The line's leading value.
+    Force,
+    Raw,
+    T,
+    Seq,
+    Rate,
+    /// Every `ch<n>=`.
+    Ch,
+    Unit,
+    /// Every `aux<n>=`.
+    Aux,
+    Ext,
+    Work,
+    Temp,
+    Chamber,
+    /// Which HX711 input, while auto-ranging.
+    Range,
+    /// The digital inputs, bit n for input n.
+    Din,
+}
+
+/// In the order they appear on the line.
+const COLUMNS: [Column; 14] = [
+    Column::Force,
+    Column::Raw,
+    Column::Range,
+    Column::T,
+    Column::Seq,
+    Column::Rate,
+    Column::Ch,
+    Column::Unit,
+    Column::Aux,
+    Column::Ext,
+    Column::Work,
+    Column::Temp,
+    Column::Chamber,
+    Column::Din,
+];
+
+impl Column {
+    /// The field's key.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Column::Force => "force",
+            Column::Raw => "raw",
+            Column::T => "t",
+            Column::Seq => "seq",
+            Column::Rate => "rate",
+            Column::Ch => "ch",
+            Column::Unit => "unit",
+            Column::Aux => "aux",
+            Column::Ext => "ext",
+            Column::Work => "work",
+            Column::Temp => "temp",
+            Column::Chamber => "chamber",
+            Column::Range => "range",
+            Column::Din => "din",
+        }
+    }
+
+    /// Its tag in a binary frame.
+    pub(crate) fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        COLUMNS.into_iter().find(|c| c.id() == id)
+    }
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Columns(u16);
+
+impl Columns {
+    pub const ALL: Self = Self((1 << COLUMNS.len()) - 1);
+
+    /// Comma-separated keys in any order, e.g. `force,t,seq`, or `ALL`.
+    /// `None` for an unknown key or an empty list.
+    pub fn parse(list: &str) -> Option<Self> {
+        if list.eq_ignore_ascii_case("ALL") {
+            return Some(Self::ALL);
+        }
+        let mut bits = 0;
+        for name in list.split(',') {
+            let column = COLUMNS
+                .into_iter()
+                .find(|c| c.as_str().eq_ignore_ascii_case(name))?;
+            bits |= column.bit();
+        }
+        Some(Self(bits))
+    }
+
+    pub fn has(self, column: Column) -> bool {
+        self.0 & column.bit() != 0
+    }
+
+    /// The chosen columns in line order.
+    pub fn iter(self) -> impl Iterator<Item = Column> {
+        COLUMNS.into_iter().filter(move |c| self.has(*c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lists() {
+        let columns = Columns::parse("SEQ,force,t").unwrap();
+        assert!(columns.has(Column::Force) && columns.has(Column::Seq));
+        assert!(!columns.has(Column::Raw));
+        let mut order = columns.iter();
+        assert_eq!(order.next(), Some(Column::Force));
+        assert_eq!(order.next(), Some(Column::T));
+        assert_eq!(order.next(), Some(Column::Seq));
+        assert_eq!(order.next(), None);
+        assert_eq!(Columns::parse("all"), Some(Columns::ALL));
+        assert_eq!(Columns::ALL.iter().count(), COLUMNS.len());
+    }
+
+    #[test]
+    fn rejects_unknown_and_empty() {
+        assert_eq!(Columns::parse("force,pos"), None);
+        assert_eq!(Columns::parse(""), None);
+        assert_eq!(Columns::parse("force,,t"), None);
+    }
+}
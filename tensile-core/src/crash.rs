@@ -0,0 +1,139 @@
+//! A panic's location and message, laid out to be kept in RAM the reboot
+//! after it does not clear, and reported once a host connects.
+
+use core::fmt::{self, Display, Write};
+
+use crate::math::crc32;
+
+pub const RECORD_LEN: usize = 160;
+const FILE_LEN: usize = 48;
+const MESSAGE_LEN: usize = 96;
+
+const MAGIC: u32 = 0x434E_4150; // "PANC"
+const FILE_AT: usize = 12;
+const MESSAGE_AT: usize = FILE_AT + FILE_LEN;
+const CRC_AT: usize = MESSAGE_AT + MESSAGE_LEN;
+
+#[derive(Clone)]
+pub struct Crash {
+    raw: [u8; RECORD_LEN],
+}
+
+impl Crash {
+    /// Long paths keep their tail, where the file name is; long messages
+    /// keep their start. Line breaks become spaces so it prints on one line.
+    pub fn new(file: &str, line: u32, message: impl Display) -> Self {
+        let mut raw = [0; RECORD_LEN];
+        raw[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        raw[4..8].copy_from_slice(&line.to_le_bytes());
+        let mut skip = file.len().saturating_sub(FILE_LEN);
+        while !file.is_char_boundary(skip) {
+            skip += 1;
+        }
+        let mut text = Text::new(&mut raw[FILE_AT..MESSAGE_AT]);
+        let _ = text.write_str(&file[skip..]);
+        raw[8] = text.len as u8;
+        let mut text = Text::new(&mut raw[MESSAGE_AT..CRC_AT]);
+        let _ = write!(text, "{}", message);
+        raw[9] = text.len as u8;
+        let crc = crc32(&raw[..CRC_AT]);
+        raw[CRC_AT..].copy_from_slice(&crc.to_le_bytes());
+        Self { raw }
+    }
+
+    /// `None` unless `raw` holds a record, rather than whatever RAM held at
+    /// power-up.
+    pub fn from_raw(raw: [u8; RECORD_LEN]) -> Option<Self> {
+        let word = |i: usize| u32::from_le_bytes([raw[i], raw[i + 1], raw[i + 2], raw[i + 3]]);
+        let valid = word(0) == MAGIC
+            && word(CRC_AT) == crc32(&raw[..CRC_AT])
+            && raw[8] as usize <= FILE_LEN
+            && raw[9] as usize <= MESSAGE_LEN;
+        valid.then_some(Self { raw })
+    }
+
+    pub fn raw(&self) -> &[u8; RECORD_LEN] {
+        &self.raw
+    }
+
+    pub fn file(&self) -> &str {
+        self.text(FILE_AT, self.raw[8])
+    }
+
+    pub fn line(&self) -> u32 {
+        u32::from_le_bytes([self.raw[4], self.raw[5], self.raw[6], self.raw[7]])
+    }
+
+    pub fn message(&self) -> &str {
+        self.text(MESSAGE_AT, self.raw[9])
+    }
+
+    fn text(&self, at: usize, len: u8) -> &str {
+        core::str::from_utf8(&self.raw[at..at + len as usize]).unwrap_or_default()
+    }
+}
+
+/// Fills a buffer, dropping whatever does not fit.
+struct Text<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Text<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+}
+
+impl Write for Text<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            let c = if c.is_control() { ' ' } else { c };
+            let end = self.len + c.len_utf8();
+            if end > self.buf.len() {
+                return Err(fmt::Error);
+            }
+            c.encode_utf8(&mut self.buf[self.len..end]);
+            self.len = end;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let crash = Crash::new(
+            "src/acquire.rs",
+            88,
+            format_args!("index {} out\nof range", 7),
+        );
+        let crash = Crash::from_raw(*crash.raw()).unwrap();
+        assert_eq!(crash.file(), "src/acquire.rs");
+        assert_eq!(crash.line(), 88);
+        assert_eq!(crash.message(), "index 7 out of range");
+    }
+
+    #[test]
+    fn rejects_leftover_ram() {
+        assert!(Crash::from_raw([0; RECORD_LEN]).is_none());
+        assert!(Crash::from_raw([0xA5; RECORD_LEN]).is_none());
+        let mut raw = *Crash::new("a.rs", 1, "boom").raw();
+        raw[MESSAGE_AT] ^= 1;
+        assert!(Crash::from_raw(raw).is_none());
+    }
+
+    #[test]
+    fn truncates_to_fit() {
+        let file = "/home/builder/.cargo/registry/src/index/rp2040-hal-0.10.2/src/é.rs";
+        let crash = Crash::new(file, 3, format_args!("{:ü<100}", ""));
+        assert!(file.ends_with(crash.file()));
+        assert!(crash.file().len() <= FILE_LEN);
+        assert!(crash.file().ends_with("/src/é.rs"));
+        assert_eq!(crash.message().len(), MESSAGE_LEN);
+        assert!(crash.message().chars().all(|c| c == 'ü'));
+    }
+}
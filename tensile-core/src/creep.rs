@@ -0,0 +1,108 @@
+//! Load-cell creep: under a constant load the reading keeps rising (or
+//! falling) towards `1 + ppm` of its initial value with time constant `tau`.
+//! Tracking the load through the same lag and subtracting it takes the
+//! drift back out, which matters for relaxation tests that hold for minutes.
+
+use crate::quantity::Counts;
+
+pub const MAX_TAU_S: u32 = 3_600;
+pub const MAX_PPM: i32 = 100_000;
+
+/// Fractional bits kept on the tracked load, so slow lags still move.
+const FRAC_BITS: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Creep {
+    /// Final drift as a share of the load, in millionths; negative for a
+    /// cell that relaxes.
+    pub ppm: i32,
+    pub tau_s: u32,
+}
+
+impl Creep {
+    pub fn new(ppm: i32, tau_s: u32) -> Option<Self> {
+        (ppm != 0 && ppm.abs() <= MAX_PPM && (1..=MAX_TAU_S).contains(&tau_s))
+            .then_some(Self { ppm, tau_s })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreepComp {
+    model: Creep,
+    /// The load lagged by `tau`, in counts with `FRAC_BITS` fractional bits.
+    lagged: i64,
+    last_us: Option<u64>,
+}
+
+impl CreepComp {
+    pub const fn new(model: Creep) -> Self {
+        Self {
+            model,
+            lagged: 0,
+            last_us: None,
+        }
+    }
+
+    pub fn model(&self) -> Creep {
+        self.model
+    }
+
+    /// Start over from no load, as after a tare.
+    pub fn reset(&mut self) {
+        self.lagged = 0;
+        self.last_us = None;
+    }
+
+    /// Feed a tared reading taken at `t_us`; returns it with the creep
+    /// built up so far removed.
+    pub fn correct(&mut self, counts: Counts, t_us: u64) -> Counts {
+        let tau_us = self.model.tau_s as u64 * 1_000_000;
+        let dt_us = self.last_us.map_or(0, |last| t_us.saturating_sub(last));
+        self.last_us = Some(t_us);
+        let target = (counts.0 as i64) << FRAC_BITS;
+        if dt_us >= tau_us {
+            self.lagged = target;
+        } else {
+            let step = (target - self.lagged) as i128 * dt_us as i128 / tau_us as i128;
+            self.lagged += step as i64;
+        }
+        let drift = (self.lagged as i128 * self.model.ppm as i128 / 1_000_000) >> FRAC_BITS;
+        Counts((counts.0 as i64 - drift as i64) as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_the_model() {
+        assert!(Creep::new(1_000, 180).is_some());
+        assert!(Creep::new(-1_000, 180).is_some());
+        assert!(Creep::new(0, 180).is_none());
+        assert!(Creep::new(1_000, 0).is_none());
+        assert!(Creep::new(1_000, MAX_TAU_S + 1).is_none());
+        assert!(Creep::new(MAX_PPM + 1, 180).is_none());
+    }
+
+    #[test]
+    fn removes_drift_under_a_held_load() {
+        // 0.1% over a 180 s time constant, sampled at 10 Hz.
+        let mut comp = CreepComp::new(Creep::new(1_000, 180).unwrap());
+        let load = 1_000_000.0f64;
+        let mut decay = 1.0f64;
+        let mut worst = 0i32;
+        for i in 0..=6_000u64 {
+            let reading = load * (1.0 + 0.001 * (1.0 - decay));
+            decay *= 1.0 - 0.1 / 180.0;
+            let out = comp.correct(Counts(reading as i32), i * 100_000);
+            worst = worst.max((out.0 - load as i32).abs());
+        }
+        // Uncorrected, the last reading would be over 960 counts high.
+        assert!(worst <= 5, "{worst}");
+
+        comp.reset();
+        assert_eq!(comp.correct(Counts(0), 0), Counts(0));
+        assert_eq!(comp.correct(Counts(500_000), 100_000), Counts(500_000));
+    }
+}
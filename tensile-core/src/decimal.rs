@@ -0,0 +1,55 @@
+//! Fixed-point decimals as the stream prints them, e.g. `-0.050`.
+
+/// Room for any `i64` with a sign and a decimal point.
+pub const MAX_LEN: usize = 21;
+
+/// Render `value`, held with `places` implied decimal places (at most 18),
+/// into `buf`. Every place is printed, so widths stay steady.
+pub fn fixed(value: i64, places: u32, buf: &mut [u8; MAX_LEN]) -> &str {
+    let mut pos = MAX_LEN;
+    let mut put = |byte: u8| {
+        pos -= 1;
+        buf[pos] = byte;
+    };
+    let mut n = value.unsigned_abs();
+    for i in 0..=places.min(18) {
+        if i == places && i > 0 {
+            put(b'.');
+        }
+        put(b'0' + (n % 10) as u8);
+        n /= 10;
+    }
+    while n > 0 {
+        put(b'0' + (n % 10) as u8);
+        n /= 10;
+    }
+    if value < 0 {
+        put(b'-');
+    }
+    core::str::from_utf8(&buf[pos..]).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(value: i64, places: u32, expected: &str) {
+        let mut buf = [0; MAX_LEN];
+        assert_eq!(fixed(value, places, &mut buf), expected);
+    }
+
+    #[test]
+    fn pads_the_fraction() {
+        check(-50, 3, "-0.050");
+        check(0, 3, "0.000");
+        check(12_094, 3, "12.094");
+        check(9_806_650, 6, "9.806650");
+        check(7, 0, "7");
+    }
+
+    #[test]
+    fn fits_the_extremes() {
+        check(i64::MIN, 3, "-9223372036854775.808");
+        check(i64::MIN, 18, "-9.223372036854775808");
+    }
+}
@@ -0,0 +1,127 @@
+//! Timing diagnostics for `DIAG?`, so what a feature costs the main loop
+//! and the stream can be measured on the hardware.
+
+use crate::quantity::Micros;
+
+/// Fewest, average and most microseconds something took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spread {
+    pub n: u32,
+    pub min: u64,
+    pub mean: u64,
+    pub max: u64,
+}
+
+/// Durations recorded since the last reset.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timing {
+    n: u32,
+    min: u64,
+    max: u64,
+    total: u64,
+}
+
+impl Timing {
+    pub const fn new() -> Self {
+        Self {
+            n: 0,
+            min: 0,
+            max: 0,
+            total: 0,
+        }
+    }
+
+    pub fn record(&mut self, us: u64) {
+        self.min = if self.n == 0 { us } else { self.min.min(us) };
+        self.max = self.max.max(us);
+        self.total = self.total.saturating_add(us);
+        self.n = self.n.saturating_add(1);
+    }
+
+    /// `None` until something is recorded.
+    pub fn spread(&self) -> Option<Spread> {
+        (self.n > 0).then(|| Spread {
+            n: self.n,
+            min: self.min,
+            mean: self.total / self.n as u64,
+            max: self.max,
+        })
+    }
+}
+
+/// Time between successive events, e.g. conversions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Intervals {
+    last: Option<Micros>,
+    timing: Timing,
+}
+
+impl Intervals {
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            timing: Timing::new(),
+        }
+    }
+
+    pub fn tick(&mut self, t: Micros) {
+        if let Some(last) = self.last.replace(t) {
+            self.timing.record(t.0.saturating_sub(last.0));
+        }
+    }
+
+    /// Start afresh from the next event, e.g. after a rate change.
+    pub fn restart(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn spread(&self) -> Option<Spread> {
+        self.timing.spread()
+    }
+
+    /// How far the intervals have strayed from each other.
+    pub fn jitter(&self) -> Option<u64> {
+        self.spread().map(|s| s.max - s.min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_min_mean_and_max() {
+        let mut timing = Timing::new();
+        assert_eq!(timing.spread(), None);
+        for us in [30, 10, 20] {
+            timing.record(us);
+        }
+        assert_eq!(
+            timing.spread(),
+            Some(Spread {
+                n: 3,
+                min: 10,
+                mean: 20,
+                max: 30
+            })
+        );
+    }
+
+    #[test]
+    fn jitter_is_the_spread_of_intervals() {
+        let mut intervals = Intervals::new();
+        intervals.tick(Micros(1_000));
+        assert_eq!(intervals.jitter(), None);
+        for t in [13_500, 26_000, 38_400] {
+            intervals.tick(Micros(t));
+        }
+        assert_eq!(
+            intervals.spread().map(|s| (s.n, s.min, s.max)),
+            Some((3, 12_400, 12_500))
+        );
+        assert_eq!(intervals.jitter(), Some(100));
+        intervals.restart();
+        intervals.tick(Micros(50_000));
+        assert_eq!(intervals.spread(), None);
+    }
+}
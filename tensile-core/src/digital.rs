@@ -0,0 +1,124 @@
+//! Logic inputs such as a guard door switch or a fixture sensor, recorded
+//! with each sample as the `din=` bitfield and able to hold off a test.
+
+pub const INPUTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Setting {
+    /// Active with the pin high. Inputs are pulled up, so by default a
+    /// switch closed to ground is active.
+    pub invert: bool,
+    /// No test starts unless the input is active, and one that is running
+    /// stops if it goes inactive.
+    pub gate: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DigitalInputs {
+    settings: [Option<Setting>; INPUTS],
+    /// Bit n set while input n is on and active.
+    state: u8,
+}
+
+impl DigitalInputs {
+    pub const fn new() -> Self {
+        Self {
+            settings: [None; INPUTS],
+            state: 0,
+        }
+    }
+
+    /// Turn input `n` on with `setting`, or off with `None`. False for an
+    /// input that does not exist.
+    pub fn set(&mut self, n: usize, setting: Option<Setting>) -> bool {
+        let Some(slot) = self.settings.get_mut(n) else {
+            return false;
+        };
+        *slot = setting;
+        self.state &= !(1 << n);
+        true
+    }
+
+    pub fn setting(&self, n: usize) -> Option<Setting> {
+        self.settings.get(n).copied().flatten()
+    }
+
+    /// True once any input is on.
+    pub fn any(&self) -> bool {
+        self.settings.iter().any(Option::is_some)
+    }
+
+    /// Feed the pin levels, bit n high for input n. Returns the new state
+    /// if it changed.
+    pub fn update(&mut self, levels: u8) -> Option<u8> {
+        let mut state = 0;
+        for (n, setting) in self.settings.iter().enumerate() {
+            if let Some(setting) = setting {
+                let high = levels & 1 << n != 0;
+                state |= ((high == setting.invert) as u8) << n;
+            }
+        }
+        let changed = state != self.state;
+        self.state = state;
+        changed.then_some(state)
+    }
+
+    pub fn state(&self) -> u8 {
+        self.state
+    }
+
+    /// The first gating input that is inactive, which holds off testing.
+    pub fn blocking(&self) -> Option<usize> {
+        (0..INPUTS).find(|&n| self.setting(n).is_some_and(|s| s.gate) && self.state & 1 << n == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: Setting = Setting {
+        invert: false,
+        gate: false,
+    };
+
+    #[test]
+    fn reports_active_inputs() {
+        let mut inputs = DigitalInputs::new();
+        assert!(!inputs.any());
+        assert!(inputs.set(0, Some(PLAIN)));
+        assert!(inputs.set(
+            2,
+            Some(Setting {
+                invert: true,
+                ..PLAIN
+            })
+        ));
+        assert!(!inputs.set(4, Some(PLAIN)));
+        // Input 0 pulled low, input 2 high; 1 and 3 are off.
+        assert_eq!(inputs.update(0b0100), Some(0b101));
+        assert_eq!(inputs.update(0b1110), None);
+        assert_eq!(inputs.update(0b0001), Some(0));
+        inputs.set(2, None);
+        assert_eq!(inputs.state(), 0);
+    }
+
+    #[test]
+    fn gates_until_active() {
+        let mut inputs = DigitalInputs::new();
+        assert_eq!(inputs.blocking(), None);
+        inputs.set(
+            1,
+            Some(Setting {
+                gate: true,
+                ..PLAIN
+            }),
+        );
+        inputs.set(3, Some(PLAIN));
+        assert_eq!(inputs.blocking(), Some(1));
+        inputs.update(0b1101);
+        assert_eq!(inputs.blocking(), None);
+        inputs.update(0b1111);
+        assert_eq!(inputs.blocking(), Some(1));
+    }
+}
@@ -0,0 +1,187 @@
+//! Text frame for a 128×64 monochrome OLED.
+//!
+//! The buffer is laid out the way SSD1306-style controllers take it: eight
+//! pages of 8 pixel rows, one byte per column, least significant bit at the
+//! top. Text uses a 5×7 font on a 6-pixel pitch, so a page holds 21
+//! characters, or 10 at double size.
+
+pub const WIDTH: usize = 128;
+pub const PAGES: usize = 8;
+
+/// Horizontal pitch of one character at size 1.
+pub const CHAR_WIDTH: usize = 6;
+
+/// Printable ASCII from space to `Z`. Lower case is drawn as upper case and
+/// anything else as `?`.
+const FONT: [[u8; 5]; 59] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // #
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x56, 0x20, 0x50], // &
+    [0x00, 0x08, 0x07, 0x03, 0x00], // '
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // )
+    [0x2A, 0x1C, 0x7F, 0x1C, 0x2A], // *
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // +
+    [0x00, 0x80, 0x70, 0x30, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x00, 0x60, 0x60, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // 0
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // 1
+    [0x72, 0x49, 0x49, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x49, 0x4D, 0x33], // 3
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3C, 0x4A, 0x49, 0x49, 0x31], // 6
+    [0x41, 0x21, 0x11, 0x09, 0x07], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x46, 0x49, 0x49, 0x29, 0x1E], // 9
+    [0x00, 0x00, 0x14, 0x00, 0x00], // :
+    [0x00, 0x40, 0x34, 0x00, 0x00], // ;
+    [0x00, 0x08, 0x14, 0x22, 0x41], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x00, 0x41, 0x22, 0x14, 0x08], // >
+    [0x02, 0x01, 0x59, 0x09, 0x06], // ?
+    [0x3E, 0x41, 0x5D, 0x59, 0x4E], // @
+    [0x7C, 0x12, 0x11, 0x12, 0x7C], // A
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7F, 0x41, 0x41, 0x41, 0x3E], // D
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7F, 0x09, 0x09, 0x09, 0x01], // F
+    [0x3E, 0x41, 0x41, 0x51, 0x73], // G
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // H
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // J
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7F, 0x02, 0x1C, 0x02, 0x7F], // M
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // N
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // O
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // Q
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // R
+    [0x26, 0x49, 0x49, 0x49, 0x32], // S
+    [0x03, 0x01, 0x7F, 0x01, 0x03], // T
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // V
+    [0x3F, 0x40, 0x38, 0x40, 0x3F], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x03, 0x04, 0x78, 0x04, 0x03], // Y
+    [0x61, 0x59, 0x49, 0x4D, 0x43], // Z
+];
+
+fn glyph(c: char) -> [u8; 5] {
+    let c = c.to_ascii_uppercase() as usize;
+    FONT.get(c.wrapping_sub(' ' as usize))
+        .copied()
+        .unwrap_or(FONT['?' as usize - ' ' as usize])
+}
+
+/// Spread the low four bits of `bits` over a byte, each one twice.
+fn double(bits: u8) -> u8 {
+    (0..4).fold(0, |out, i| out | (((bits >> i) & 1) * 0b11) << (2 * i))
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Frame {
+    pages: [[u8; WIDTH]; PAGES],
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Frame {
+    pub const fn new() -> Self {
+        Self {
+            pages: [[0; WIDTH]; PAGES],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pages = [[0; WIDTH]; PAGES];
+    }
+
+    pub fn page(&self, page: usize) -> &[u8; WIDTH] {
+        &self.pages[page]
+    }
+
+    /// Draw `text` with its top-left corner at `page`, `column`. Size 2 is
+    /// twice as wide and two pages tall. Whatever runs off the edge is cut.
+    pub fn text(&mut self, page: usize, column: usize, text: &str, size: usize) {
+        let size = size.clamp(1, 2);
+        let mut x = column;
+        for c in text.chars() {
+            for (i, &bits) in glyph(c).iter().enumerate() {
+                for dx in 0..size {
+                    let col = x + i * size + dx;
+                    if col >= WIDTH {
+                        return;
+                    }
+                    if size == 1 {
+                        self.put(page, col, bits);
+                    } else {
+                        self.put(page, col, double(bits));
+                        self.put(page + 1, col, double(bits >> 4));
+                    }
+                }
+            }
+            x += CHAR_WIDTH * size;
+        }
+    }
+
+    /// Draw `text` so it ends at the right edge.
+    pub fn text_right(&mut self, page: usize, text: &str, size: usize) {
+        let width = text.chars().count() * CHAR_WIDTH * size.clamp(1, 2);
+        self.text(page, WIDTH.saturating_sub(width), text, size);
+    }
+
+    fn put(&mut self, page: usize, column: usize, bits: u8) {
+        if let Some(page) = self.pages.get_mut(page) {
+            page[column] = bits;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_glyphs_on_the_pitch() {
+        let mut frame = Frame::new();
+        frame.text(1, 0, "1a", 1);
+        assert_eq!(frame.page(1)[..6], [0x00, 0x42, 0x7F, 0x40, 0x00, 0x00]);
+        assert_eq!(frame.page(1)[6..11], [0x7C, 0x12, 0x11, 0x12, 0x7C]);
+        assert_eq!(frame.page(0), &[0; WIDTH]);
+        frame.text(2, 0, "~", 1);
+        assert_eq!(frame.page(2)[..5], glyph('?'));
+    }
+
+    #[test]
+    fn double_size_spans_two_pages() {
+        let mut frame = Frame::new();
+        frame.text(6, 0, "-", 2);
+        // `-` is row 3 only, which lands on rows 6 and 7 of the top page.
+        assert_eq!(frame.page(6)[..10], [0xC0; 10]);
+        assert_eq!(frame.page(7)[..10], [0; 10]);
+        frame.text(7, 0, "-", 2);
+        assert_eq!(frame.page(7)[..2], [0xC0; 2]);
+    }
+
+    #[test]
+    fn clips_at_the_right_edge() {
+        let mut frame = Frame::new();
+        frame.text_right(0, "AB", 1);
+        assert_eq!(frame.page(0)[WIDTH - 12..WIDTH - 7], glyph('A'));
+        frame.text(3, WIDTH - 3, "M", 1);
+        assert_eq!(frame.page(3)[WIDTH - 3..], [0x7F, 0x02, 0x1C]);
+    }
+}
@@ -0,0 +1,164 @@
+//! Two symmetric load cells combined into one reading.
+
+use crate::quantity::Counts;
+
+/// How the two cells make up the reported force.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combine {
+    /// Total load carried by both cells.
+    Sum,
+    /// Mean of the two, matching a single cell under a centred load.
+    Average,
+}
+
+impl Combine {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Combine::Sum => "sum",
+            Combine::Average => "avg",
+        }
+    }
+
+    /// Parse `SUM` or `AVG`, case-insensitively.
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("SUM") {
+            Some(Combine::Sum)
+        } else if name.eq_ignore_ascii_case("AVG") {
+            Some(Combine::Average)
+        } else {
+            None
+        }
+    }
+}
+
+/// Combines a primary and a secondary cell and watches for them disagreeing.
+///
+/// The secondary reading is rescaled into primary counts using both
+/// calibration factors, so the result can go through the primary's filters
+/// and units unchanged. An off-axis load shows up as the two cells carrying
+/// different shares; once that difference exceeds `limit_pct` of their mean
+/// for [`HOLD`](Self::HOLD) samples in a row the pair is reported as mismatched, and it
+/// clears the same way. Loads below `floor` counts are never judged, since
+/// noise alone is a large fraction of nothing.
+pub struct DualCell {
+    mode: Combine,
+    counts_per_kg: i32,
+    secondary_per_kg: i32,
+    limit_pct: u32,
+    floor: u32,
+    streak: u32,
+    mismatched: bool,
+}
+
+impl DualCell {
+    /// Consecutive samples needed to raise or clear a mismatch.
+    pub const HOLD: u32 = 3;
+
+    /// `counts_per_kg` and `secondary_per_kg` are the two `CAL` factors.
+    /// Returns `None` if either is zero.
+    pub fn new(
+        mode: Combine,
+        counts_per_kg: i32,
+        secondary_per_kg: i32,
+        limit_pct: u32,
+        floor: u32,
+    ) -> Option<Self> {
+        if counts_per_kg == 0 || secondary_per_kg == 0 {
+            return None;
+        }
+        Some(Self {
+            mode,
+            counts_per_kg,
+            secondary_per_kg,
+            limit_pct,
+            floor,
+            streak: 0,
+            mismatched: false,
+        })
+    }
+
+    pub fn mode(&self) -> Combine {
+        self.mode
+    }
+
+    pub fn limit_pct(&self) -> u32 {
+        self.limit_pct
+    }
+
+    pub fn is_mismatched(&self) -> bool {
+        self.mismatched
+    }
+
+    /// Feed one tared reading from each cell. Returns the combined value in
+    /// primary counts, and `Some(state)` when the mismatch state flips.
+    pub fn push(&mut self, primary: Counts, secondary: Counts) -> (Counts, Option<bool>) {
+        let a = primary.0 as i64;
+        let b = secondary.0 as i64 * self.counts_per_kg as i64 / self.secondary_per_kg as i64;
+        let combined = match self.mode {
+            Combine::Sum => a + b,
+            Combine::Average => (a + b) / 2,
+        };
+        let combined = Counts(combined.clamp(i32::MIN as i64, i32::MAX as i64) as i32);
+
+        let mean = (a.abs() + b.abs()) / 2;
+        let off = mean >= self.floor as i64 && (a - b).abs() * 100 > self.limit_pct as i64 * mean;
+        if off == self.mismatched {
+            self.streak = 0;
+            return (combined, None);
+        }
+        self.streak += 1;
+        if self.streak < Self::HOLD {
+            return (combined, None);
+        }
+        self.streak = 0;
+        self.mismatched = off;
+        (combined, Some(off))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rescales_secondary_into_primary_counts() {
+        // Secondary is half as sensitive, so 500 of its counts match 1000.
+        let mut dual = DualCell::new(Combine::Sum, 1000, 500, 10, 100).unwrap();
+        assert_eq!(dual.push(Counts(1000), Counts(500)), (Counts(2000), None));
+        dual = DualCell::new(Combine::Average, 1000, 500, 10, 100).unwrap();
+        assert_eq!(dual.push(Counts(1000), Counts(500)).0, Counts(1000));
+    }
+
+    #[test]
+    fn flags_and_clears_mismatch_after_hold() {
+        let mut dual = DualCell::new(Combine::Sum, 1000, 1000, 10, 100).unwrap();
+        for _ in 1..DualCell::HOLD {
+            assert_eq!(dual.push(Counts(1200), Counts(800)).1, None);
+        }
+        assert_eq!(dual.push(Counts(1200), Counts(800)).1, Some(true));
+        assert!(dual.is_mismatched());
+        for _ in 1..DualCell::HOLD {
+            assert_eq!(dual.push(Counts(1010), Counts(990)).1, None);
+        }
+        assert_eq!(dual.push(Counts(1010), Counts(990)).1, Some(false));
+    }
+
+    #[test]
+    fn single_outlier_does_not_trigger() {
+        let mut dual = DualCell::new(Combine::Sum, 1000, 1000, 10, 100).unwrap();
+        dual.push(Counts(1200), Counts(800));
+        dual.push(Counts(1000), Counts(1000));
+        for _ in 0..DualCell::HOLD - 1 {
+            assert_eq!(dual.push(Counts(1200), Counts(800)).1, None);
+        }
+    }
+
+    #[test]
+    fn ignores_loads_below_floor() {
+        let mut dual = DualCell::new(Combine::Sum, 1000, 1000, 10, 100).unwrap();
+        for _ in 0..10 {
+            assert_eq!(dual.push(Counts(60), Counts(-20)).1, None);
+        }
+        assert!(DualCell::new(Combine::Sum, 1000, 0, 10, 100).is_none());
+    }
+}
@@ -0,0 +1,249 @@
+//! Layout of the persistent fault log kept in flash.
+//!
+//! The log spans a few erase sectors used as a ring of fixed-size entries.
+//! Entries carry an increasing sequence number, so after a reset the newest
+//! one is found by scanning instead of keeping a separate head pointer that
+//! would wear out its own sector.
+
+use crate::health::{FaultKind, Health};
+
+pub const ENTRY_LEN: usize = 16;
+
+/// Erased flash reads as all ones; no real entry uses this sequence number.
+const BLANK_SEQ: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub seq: u32,
+    /// Boot session the fault happened in.
+    pub session: u32,
+    /// Seconds since that boot.
+    pub uptime_s: u32,
+    pub code: u16,
+    pub arg: u16,
+}
+
+impl Entry {
+    pub fn encode(&self) -> [u8; ENTRY_LEN] {
+        let mut out = [0; ENTRY_LEN];
+        out[0..4].copy_from_slice(&self.seq.to_le_bytes());
+        out[4..8].copy_from_slice(&self.session.to_le_bytes());
+        out[8..12].copy_from_slice(&self.uptime_s.to_le_bytes());
+        out[12..14].copy_from_slice(&self.code.to_le_bytes());
+        out[14..16].copy_from_slice(&self.arg.to_le_bytes());
+        out
+    }
+
+    /// `None` for an erased slot.
+    pub fn decode(raw: &[u8; ENTRY_LEN]) -> Option<Self> {
+        let word = |i: usize| u32::from_le_bytes([raw[i], raw[i + 1], raw[i + 2], raw[i + 3]]);
+        let half = |i: usize| u16::from_le_bytes([raw[i], raw[i + 1]]);
+        let seq = word(0);
+        (seq != BLANK_SEQ).then(|| Entry {
+            seq,
+            session: word(4),
+            uptime_s: word(8),
+            code: half(12),
+            arg: half(14),
+        })
+    }
+
+    /// False for a code no firmware writes, as left by a write cut short or
+    /// a worn sector.
+    pub fn is_plausible(&self) -> bool {
+        matches!(self.code, CODE_OVERLOAD | CODE_NO_DATA | CODE_STUCK)
+    }
+}
+
+/// Fault codes stored in `Entry::code`.
+pub const CODE_OVERLOAD: u16 = 1;
+pub const CODE_NO_DATA: u16 = 2;
+pub const CODE_STUCK: u16 = 3;
+
+/// Code to log when the sensor enters `health`, if it is a fault.
+pub fn health_code(health: Health) -> Option<u16> {
+    match health {
+        Health::Ok => None,
+        Health::Overload => Some(CODE_OVERLOAD),
+        Health::Fault(FaultKind::NoData) => Some(CODE_NO_DATA),
+        Health::Fault(FaultKind::Stuck) => Some(CODE_STUCK),
+    }
+}
+
+pub fn code_str(code: u16) -> &'static str {
+    match code {
+        CODE_OVERLOAD => "overload",
+        CODE_NO_DATA => "no_data",
+        CODE_STUCK => "stuck",
+        _ => "unknown",
+    }
+}
+
+/// Where the next entry goes in a ring of `sectors` × `slots_per_sector`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingIndex {
+    slots_per_sector: usize,
+    slots: usize,
+    next: usize,
+    next_seq: u32,
+}
+
+/// Where to write an appended entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Append {
+    pub slot: usize,
+    pub seq: u32,
+    /// Sector to erase before writing, when the slot starts a new sector.
+    pub erase: Option<usize>,
+}
+
+impl RingIndex {
+    /// Rebuild the index from every slot's sequence number, `None` where
+    /// erased, in storage order.
+    pub fn scan(
+        sectors: usize,
+        slots_per_sector: usize,
+        seqs: impl IntoIterator<Item = Option<u32>>,
+    ) -> Self {
+        let slots = sectors * slots_per_sector;
+        let newest = seqs
+            .into_iter()
+            .take(slots)
+            .enumerate()
+            .filter_map(|(slot, seq)| seq.map(|seq| (slot, seq)))
+            .max_by_key(|&(_, seq)| seq);
+        let (next, next_seq) = match newest {
+            Some((slot, seq)) => ((slot + 1) % slots, seq.wrapping_add(1)),
+            None => (0, 0),
+        };
+        Self {
+            slots_per_sector,
+            slots,
+            next,
+            next_seq,
+        }
+    }
+
+    pub fn append(&mut self) -> Append {
+        let slot = self.next;
+        let append = Append {
+            slot,
+            seq: self.next_seq,
+            erase: slot
+                .is_multiple_of(self.slots_per_sector)
+                .then_some(slot / self.slots_per_sector),
+        };
+        self.next = (slot + 1) % self.slots;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        append
+    }
+
+    /// Slots from oldest to newest; erased ones still have to be skipped.
+    pub fn oldest_first(&self) -> impl Iterator<Item = usize> {
+        let (next, slots) = (self.next, self.slots);
+        (0..slots).map(move |i| (next + i) % slots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seqs<const N: usize>(slots: [Option<Entry>; N]) -> [Option<u32>; N] {
+        slots.map(|e| e.map(|e| e.seq))
+    }
+
+    fn entry(seq: u32) -> Entry {
+        Entry {
+            seq,
+            session: 0xdead_beef,
+            uptime_s: 42,
+            code: CODE_STUCK,
+            arg: 7,
+        }
+    }
+
+    #[test]
+    fn encode_round_trips_and_blank_decodes_to_none() {
+        assert_eq!(Entry::decode(&entry(3).encode()), Some(entry(3)));
+        assert_eq!(Entry::decode(&[0xFF; ENTRY_LEN]), None);
+    }
+
+    #[test]
+    fn unknown_codes_are_implausible() {
+        assert!(entry(1).is_plausible());
+        let garbled = Entry {
+            code: 0x5A5A,
+            ..entry(1)
+        };
+        assert!(!garbled.is_plausible());
+    }
+
+    #[test]
+    fn empty_log_starts_at_zero_with_erase() {
+        let mut ring = RingIndex::scan(2, 4, [None; 8]);
+        assert_eq!(
+            ring.append(),
+            Append {
+                slot: 0,
+                seq: 0,
+                erase: Some(0)
+            }
+        );
+        assert_eq!(ring.append().erase, None);
+    }
+
+    #[test]
+    fn resumes_after_newest_and_erases_next_sector() {
+        let slots = [
+            Some(entry(4)),
+            Some(entry(5)),
+            Some(entry(6)),
+            Some(entry(7)),
+            Some(entry(0)),
+            Some(entry(1)),
+            Some(entry(2)),
+            Some(entry(3)),
+        ];
+        let mut ring = RingIndex::scan(2, 4, seqs(slots));
+        assert_eq!(
+            ring.append(),
+            Append {
+                slot: 4,
+                seq: 8,
+                erase: Some(1)
+            }
+        );
+    }
+
+    #[test]
+    fn wraps_from_last_slot_to_first() {
+        let slots = [None, None, None, Some(entry(9))];
+        let mut ring = RingIndex::scan(2, 2, seqs(slots));
+        assert_eq!(ring.append().slot, 0);
+    }
+
+    #[test]
+    fn reads_oldest_first() {
+        let slots = [Some(entry(2)), None, Some(entry(0)), Some(entry(1))];
+        let ring = RingIndex::scan(2, 2, seqs(slots));
+        let order: [usize; 4] = {
+            let mut it = ring.oldest_first();
+            core::array::from_fn(|_| it.next().unwrap())
+        };
+        assert_eq!(order, [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn maps_health_to_codes() {
+        assert_eq!(health_code(Health::Ok), None);
+        assert_eq!(
+            health_code(Health::Overload).map(code_str),
+            Some("overload")
+        );
+        assert_eq!(
+            health_code(Health::Fault(FaultKind::NoData)).map(code_str),
+            Some("no_data")
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Clip-on digital extensometer: quadrature counts to extension.
+
+/// Counts since `zero`, times the encoder's resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extensometer {
+    zero: u32,
+    /// Negative for an encoder mounted the other way round.
+    nm_per_count: i32,
+}
+
+impl Extensometer {
+    /// A 1 µm scale.
+    pub const DEFAULT_NM_PER_COUNT: i32 = 1_000;
+
+    pub fn new(nm_per_count: i32) -> Self {
+        Self {
+            zero: 0,
+            nm_per_count,
+        }
+    }
+
+    pub fn zero(&mut self, count: u32) {
+        self.zero = count;
+    }
+
+    pub fn nm_per_count(&self) -> i32 {
+        self.nm_per_count
+    }
+
+    pub fn set_nm_per_count(&mut self, nm_per_count: i32) {
+        self.nm_per_count = nm_per_count;
+    }
+
+    /// Extension in µm at `count`. The counter is free-running, so it may
+    /// have wrapped since the zero.
+    pub fn extension_um(&self, count: u32) -> i64 {
+        count.wrapping_sub(self.zero) as i32 as i64 * self.nm_per_count as i64 / 1000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_from_the_zero() {
+        let mut ext = Extensometer::new(500);
+        ext.zero(1_000);
+        assert_eq!(ext.extension_um(5_000), 2_000);
+        assert_eq!(ext.extension_um(0), -500);
+        ext.set_nm_per_count(-1_000);
+        assert_eq!(ext.extension_um(1_010), -10);
+    }
+
+    #[test]
+    fn survives_counter_wrap() {
+        let mut ext = Extensometer::new(Extensometer::DEFAULT_NM_PER_COUNT);
+        ext.zero(u32::MAX - 9);
+        assert_eq!(ext.extension_um(10), 20);
+    }
+}
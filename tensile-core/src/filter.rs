@@ -0,0 +1,433 @@
+//! Filtering of load-cell counts.
+//!
+//! Every stage works on integer counts so it runs cheaply on the M0+: the
+//! spike filter sorts at most five values, the boxcar keeps a running sum,
+//! and the IIR keeps its state in Q16.
+
+/// Boxcar (moving-average) filter over the last `window` samples.
+pub struct MovingAverage {
+    buf: [i32; MovingAverage::MAX_WINDOW],
+    window: usize,
+    pos: usize,
+    filled: usize,
+    sum: i64,
+}
+
+impl MovingAverage {
+    pub const MAX_WINDOW: usize = 32;
+
+    /// `window` is clamped to `1..=MAX_WINDOW`.
+    pub fn new(window: usize) -> Self {
+        Self {
+            buf: [0; Self::MAX_WINDOW],
+            window: window.clamp(1, Self::MAX_WINDOW),
+            pos: 0,
+            filled: 0,
+            sum: 0,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Add a sample and return the mean of the samples seen so far (up to
+    /// `window` of them).
+    pub fn push(&mut self, sample: i32) -> i32 {
+        if self.filled == self.window {
+            self.sum -= self.buf[self.pos] as i64;
+        } else {
+            self.filled += 1;
+        }
+        self.buf[self.pos] = sample;
+        self.sum += sample as i64;
+        self.pos = (self.pos + 1) % self.window;
+        div_round(self.sum, self.filled as i64) as i32
+    }
+
+    pub fn reset(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+        self.sum = 0;
+    }
+}
+
+/// Single-pole IIR low-pass, `y += alpha * (x - y)`.
+pub struct LowPass {
+    alpha_q16: i64,
+    state_q16: Option<i64>,
+}
+
+impl LowPass {
+    /// Smoothing factor for an RC low-pass sampled at `sample_rate_mhz`:
+    /// `alpha = 2πfc / (fs + 2πfc)`. Frequencies are in millihertz so
+    /// sub-hertz cutoffs can be expressed without floats.
+    pub fn new(cutoff_mhz: u32, sample_rate_mhz: u32) -> Self {
+        // 710/113 is 2π to within 0.3 ppm.
+        let omega = cutoff_mhz as i64 * 710 / 113;
+        let alpha_q16 = if omega == 0 {
+            0
+        } else {
+            (omega << 16) / (sample_rate_mhz as i64 + omega)
+        };
+        Self {
+            alpha_q16: alpha_q16.max(1),
+            state_q16: None,
+        }
+    }
+
+    /// Add a sample and return the filtered value. The first sample seeds the
+    /// state so the output doesn't ramp up from zero.
+    pub fn push(&mut self, sample: i32) -> i32 {
+        let x = (sample as i64) << 16;
+        let y = match self.state_q16 {
+            Some(y) => y + (((x - y) * self.alpha_q16) >> 16),
+            None => x,
+        };
+        self.state_q16 = Some(y);
+        div_round(y, 1 << 16) as i32
+    }
+
+    pub fn reset(&mut self) {
+        self.state_q16 = None;
+    }
+}
+
+/// How the spike filter treats the newest sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpikeMode {
+    /// Always output the median of the window.
+    Median,
+    /// Pass samples through unless they are outliers, which are replaced by
+    /// the window median.
+    Mad,
+}
+
+/// Median / median-absolute-deviation spike rejection over 3–5 samples.
+///
+/// A sample counts as an outlier when it sits more than `k` MADs from the
+/// window median. The MAD is floored at `MIN_MAD` counts so a perfectly quiet
+/// signal doesn't flag every bit of noise. Raw samples always enter the
+/// window, so a genuine step gets through once it fills half the window.
+pub struct SpikeFilter {
+    buf: [i32; SpikeFilter::MAX_WINDOW],
+    window: usize,
+    pos: usize,
+    filled: usize,
+    mode: SpikeMode,
+    k: u32,
+    rejected: u32,
+}
+
+impl SpikeFilter {
+    pub const MIN_WINDOW: usize = 3;
+    pub const MAX_WINDOW: usize = 5;
+    pub const MIN_MAD: i32 = 4;
+    /// Widest threshold, in MADs; past this nothing is ever an outlier.
+    pub const MAX_K: u32 = 20;
+
+    /// `window` is clamped to `MIN_WINDOW..=MAX_WINDOW`; `k` to `1..=MAX_K`.
+    pub fn new(mode: SpikeMode, window: usize, k: u32) -> Self {
+        Self {
+            buf: [0; Self::MAX_WINDOW],
+            window: window.clamp(Self::MIN_WINDOW, Self::MAX_WINDOW),
+            pos: 0,
+            filled: 0,
+            mode,
+            k: k.clamp(1, Self::MAX_K),
+            rejected: 0,
+        }
+    }
+
+    pub fn mode(&self) -> SpikeMode {
+        self.mode
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    /// Number of samples judged outliers since construction.
+    pub fn rejected(&self) -> u32 {
+        self.rejected
+    }
+
+    pub fn push(&mut self, sample: i32) -> i32 {
+        self.buf[self.pos] = sample;
+        self.pos = (self.pos + 1) % self.window;
+        self.filled = (self.filled + 1).min(self.window);
+        if self.filled < self.window {
+            return sample;
+        }
+
+        let mut sorted = [0i32; Self::MAX_WINDOW];
+        let window = &mut sorted[..self.window];
+        window.copy_from_slice(&self.buf[..self.window]);
+        let median = median_of(window);
+        for v in window.iter_mut() {
+            *v = v.abs_diff(median) as i32;
+        }
+        let mad = median_of(window).max(Self::MIN_MAD);
+
+        let outlier = sample.abs_diff(median) as u64 > self.k as u64 * mad as u64;
+        if outlier {
+            self.rejected = self.rejected.saturating_add(1);
+        }
+        match self.mode {
+            SpikeMode::Median => median,
+            SpikeMode::Mad if outlier => median,
+            SpikeMode::Mad => sample,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+/// Median of an odd-length slice; sorts it in place.
+fn median_of(values: &mut [i32]) -> i32 {
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+/// Optional spike rejection, then an optional boxcar, then an optional IIR
+/// stage.
+#[derive(Default)]
+pub struct FilterChain {
+    pub spike: Option<SpikeFilter>,
+    pub average: Option<MovingAverage>,
+    pub low_pass: Option<LowPass>,
+}
+
+impl FilterChain {
+    pub fn push(&mut self, sample: i32) -> i32 {
+        let mut value = sample;
+        if let Some(spike) = &mut self.spike {
+            value = spike.push(value);
+        }
+        if let Some(avg) = &mut self.average {
+            value = avg.push(value);
+        }
+        if let Some(lp) = &mut self.low_pass {
+            value = lp.push(value);
+        }
+        value
+    }
+
+    /// Outliers flagged by the spike stage, 0 if it is disabled.
+    pub fn rejected(&self) -> u32 {
+        self.spike.as_ref().map_or(0, SpikeFilter::rejected)
+    }
+
+    pub fn reset(&mut self) {
+        if let Some(spike) = &mut self.spike {
+            spike.reset();
+        }
+        if let Some(avg) = &mut self.average {
+            avg.reset();
+        }
+        if let Some(lp) = &mut self.low_pass {
+            lp.reset();
+        }
+    }
+}
+
+/// Passes one filtered sample in every `n`, for a link slower than the
+/// sampling. Only the filter in front of it keeps this from aliasing, so
+/// pair it with a boxcar at least `n` long.
+#[derive(Debug, Clone, Copy)]
+pub struct Decimator {
+    n: u32,
+    count: u32,
+}
+
+impl Decimator {
+    pub const MAX: u32 = 1000;
+
+    /// `n` is clamped to `1..=MAX`; 1 passes everything.
+    pub fn new(n: u32) -> Self {
+        Self {
+            n: n.clamp(1, Self::MAX),
+            count: 0,
+        }
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    /// Whether this sample goes out. The first one after `new` always does.
+    pub fn push(&mut self) -> bool {
+        let pass = self.count == 0;
+        self.count = (self.count + 1) % self.n;
+        pass
+    }
+}
+
+/// Integer division rounding half away from zero.
+fn div_round(num: i64, den: i64) -> i64 {
+    if (num < 0) == (den < 0) {
+        (num + den / 2) / den
+    } else {
+        (num - den / 2) / den
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_average_warms_up_then_slides() {
+        let mut avg = MovingAverage::new(4);
+        assert_eq!(avg.push(4), 4);
+        assert_eq!(avg.push(8), 6);
+        assert_eq!(avg.push(0), 4);
+        assert_eq!(avg.push(4), 4);
+        // 4 drops out of the window.
+        assert_eq!(avg.push(20), 8);
+    }
+
+    #[test]
+    fn moving_average_clamps_window() {
+        assert_eq!(MovingAverage::new(0).window(), 1);
+        assert_eq!(MovingAverage::new(1000).window(), MovingAverage::MAX_WINDOW);
+    }
+
+    #[test]
+    fn moving_average_rounds_negative_means() {
+        let mut avg = MovingAverage::new(2);
+        avg.push(-1);
+        assert_eq!(avg.push(-2), -2);
+    }
+
+    #[test]
+    fn low_pass_seeds_from_first_sample() {
+        let mut lp = LowPass::new(1_000, 10_000);
+        assert_eq!(lp.push(1000), 1000);
+    }
+
+    #[test]
+    fn low_pass_converges_on_step() {
+        // fc = 1 Hz at fs = 10 Hz gives alpha ~= 0.386.
+        let mut lp = LowPass::new(1_000, 10_000);
+        lp.push(0);
+        let first = lp.push(1000);
+        assert!((380..=392).contains(&first), "{first}");
+        let mut y = first;
+        for _ in 0..50 {
+            y = lp.push(1000);
+        }
+        assert_eq!(y, 1000);
+    }
+
+    #[test]
+    fn low_pass_attenuates_alternating_noise() {
+        let mut lp = LowPass::new(500, 10_000);
+        let mut worst = 0;
+        for i in 0..200 {
+            let noise = if i % 2 == 0 { 30 } else { -30 };
+            let y = lp.push(5000 + noise);
+            if i > 50 {
+                worst = worst.max((y - 5000).abs());
+            }
+        }
+        assert!(worst < 10, "{worst}");
+    }
+
+    #[test]
+    fn chain_applies_both_stages_in_order() {
+        let mut chain = FilterChain {
+            spike: None,
+            average: Some(MovingAverage::new(2)),
+            low_pass: Some(LowPass::new(1_000, 10_000)),
+        };
+        assert_eq!(chain.push(100), 100);
+        chain.reset();
+        assert_eq!(chain.push(-50), -50);
+    }
+
+    #[test]
+    fn median_removes_single_glitch() {
+        let mut spike = SpikeFilter::new(SpikeMode::Median, 3, 5);
+        let out: [i32; 5] = core::array::from_fn(|i| spike.push([10, 12, 8_000_000, 11, 9][i]));
+        assert_eq!(out, [10, 12, 12, 12, 11]);
+        assert_eq!(spike.rejected(), 1);
+    }
+
+    #[test]
+    fn mad_passes_noise_and_replaces_outliers() {
+        let mut spike = SpikeFilter::new(SpikeMode::Mad, 5, 5);
+        for v in [100, 130, 80, 110, 95] {
+            assert_eq!(spike.push(v), v);
+        }
+        assert_eq!(spike.push(-8_388_608), 95);
+        assert_eq!(spike.push(120), 120);
+        assert_eq!(spike.rejected(), 1);
+    }
+
+    #[test]
+    fn mad_lets_real_steps_through() {
+        let mut spike = SpikeFilter::new(SpikeMode::Mad, 5, 5);
+        for _ in 0..5 {
+            spike.push(0);
+        }
+        let out: [i32; 4] = core::array::from_fn(|_| spike.push(5000));
+        assert_eq!(out, [0, 0, 5000, 5000]);
+    }
+
+    #[test]
+    fn wide_thresholds_do_not_overflow() {
+        let mut spike = SpikeFilter::new(SpikeMode::Mad, 3, u32::MAX);
+        assert_eq!(spike.k(), SpikeFilter::MAX_K);
+        for v in [0, i32::MAX, i32::MIN, 0] {
+            spike.push(v);
+        }
+    }
+
+    #[test]
+    fn mad_floor_keeps_quiet_signal_from_flagging() {
+        let mut spike = SpikeFilter::new(SpikeMode::Mad, 3, 3);
+        for v in [0, 0, 0, 0, 6, 0, -6, 0] {
+            assert_eq!(spike.push(v), v);
+        }
+        assert_eq!(spike.rejected(), 0);
+    }
+
+    #[test]
+    fn chain_reports_rejections() {
+        let mut chain = FilterChain {
+            spike: Some(SpikeFilter::new(SpikeMode::Mad, 3, 5)),
+            ..Default::default()
+        };
+        for v in [1, 2, 1, 900_000, 2] {
+            chain.push(v);
+        }
+        assert_eq!(chain.rejected(), 1);
+    }
+
+    #[test]
+    fn empty_chain_is_passthrough() {
+        let mut chain = FilterChain::default();
+        assert_eq!(chain.push(-123), -123);
+    }
+
+    #[test]
+    fn decimator_passes_every_nth() {
+        let mut decimate = Decimator::new(4);
+        let passed: [bool; 9] = core::array::from_fn(|_| decimate.push());
+        assert_eq!(
+            passed,
+            [true, false, false, false, true, false, false, false, true]
+        );
+        let mut all = Decimator::new(0);
+        assert_eq!(all.n(), 1);
+        assert!(all.push() && all.push());
+    }
+}
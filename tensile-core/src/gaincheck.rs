@@ -0,0 +1,228 @@
+//! Periodic gain check on the HX711: input A read at gain 64 should give
+//! half what it gives at 128. When the two stop agreeing the front end is
+//! failing, e.g. sagging excitation or a damaged gauge, and it shows here
+//! before it shows in a calibration.
+//!
+//! A check averages A at 128, then at 64, then at 128 again, so a load that
+//! moves in the meantime is caught rather than taken for drift.
+
+use crate::range::Input;
+
+/// Conversions averaged at each step.
+pub const SAMPLES: u32 = 8;
+
+/// How a check came out. Levels are raw counts at gain 128.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outcome {
+    /// Mean of the readings at 128 either side.
+    pub high: i32,
+    /// Twice the mean at 64.
+    pub low: i32,
+    /// The load changed by more than the tolerance during the check, so
+    /// nothing can be said.
+    pub unsteady: bool,
+    pub drifted: bool,
+}
+
+impl Outcome {
+    pub fn diff(&self) -> i32 {
+        self.high.saturating_sub(self.low)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match (self.unsteady, self.drifted) {
+            (true, _) => "unsteady",
+            (false, true) => "drift",
+            (false, false) => "ok",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Waiting,
+    Before,
+    Low { before: i32 },
+    After { before: i32, low: i32 },
+}
+
+/// What the caller has to do after a reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Select(Input),
+    Done(Outcome),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GainCheck {
+    period_ms: u64,
+    tolerance: u32,
+    next_ms: u64,
+    step: Step,
+    sum: i64,
+    n: u32,
+    last: Option<Outcome>,
+}
+
+impl GainCheck {
+    /// A check every `period_ms`, the first at `now_ms`, warning when the
+    /// two gains differ by more than `tolerance` counts.
+    pub fn new(period_ms: u64, tolerance: u32, now_ms: u64) -> Self {
+        Self {
+            period_ms,
+            tolerance,
+            next_ms: now_ms,
+            step: Step::Waiting,
+            sum: 0,
+            n: 0,
+            last: None,
+        }
+    }
+
+    pub fn period_ms(&self) -> u64 {
+        self.period_ms
+    }
+
+    pub fn tolerance(&self) -> u32 {
+        self.tolerance
+    }
+
+    pub fn last(&self) -> Option<Outcome> {
+        self.last
+    }
+
+    /// Start a check if one is due. The caller only asks while a check
+    /// can run: idle, on input A.
+    pub fn begin(&mut self, now_ms: u64) -> bool {
+        if self.step != Step::Waiting || now_ms < self.next_ms {
+            return false;
+        }
+        self.next_ms = now_ms + self.period_ms;
+        self.step = Step::Before;
+        self.sum = 0;
+        self.n = 0;
+        true
+    }
+
+    /// Readings at gain 64 are what the check is after.
+    pub fn low_gain(&self) -> bool {
+        matches!(self.step, Step::Low { .. })
+    }
+
+    /// Give up on a check under way, e.g. for a test. Returns true if
+    /// input A has to be selected again.
+    pub fn abort(&mut self) -> bool {
+        let low = self.low_gain();
+        self.step = Step::Waiting;
+        low
+    }
+
+    /// Feed a raw reading and the input it came from.
+    pub fn push(&mut self, input: Input, raw: i32) -> Option<Action> {
+        let wanted = if self.low_gain() {
+            Input::A64
+        } else {
+            Input::A
+        };
+        if self.step == Step::Waiting || input != wanted {
+            return None;
+        }
+        self.sum += raw as i64;
+        self.n += 1;
+        if self.n < SAMPLES {
+            return None;
+        }
+        let mean = (self.sum / self.n as i64) as i32;
+        self.sum = 0;
+        self.n = 0;
+        let (step, action) = match self.step {
+            Step::Before => (Step::Low { before: mean }, Action::Select(Input::A64)),
+            Step::Low { before } => (
+                Step::After {
+                    before,
+                    low: mean.saturating_mul(2),
+                },
+                Action::Select(Input::A),
+            ),
+            Step::After { before, low } => {
+                let high = ((before as i64 + mean as i64) / 2) as i32;
+                let mut outcome = Outcome {
+                    high,
+                    low,
+                    unsteady: before.abs_diff(mean) > self.tolerance,
+                    drifted: false,
+                };
+                outcome.drifted =
+                    !outcome.unsteady && outcome.diff().unsigned_abs() > self.tolerance;
+                self.last = Some(outcome);
+                (Step::Waiting, Action::Done(outcome))
+            }
+            Step::Waiting => return None,
+        };
+        self.step = step;
+        Some(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(check: &mut GainCheck, before: i32, low: i32, after: i32) -> Outcome {
+        assert!(check.begin(0));
+        for _ in 1..SAMPLES {
+            assert_eq!(check.push(Input::A, before), None);
+        }
+        assert_eq!(
+            check.push(Input::A, before),
+            Some(Action::Select(Input::A64))
+        );
+        // Still settling on the old input.
+        assert_eq!(check.push(Input::A, before), None);
+        for _ in 1..SAMPLES {
+            assert_eq!(check.push(Input::A64, low), None);
+        }
+        assert_eq!(check.push(Input::A64, low), Some(Action::Select(Input::A)));
+        for _ in 1..SAMPLES {
+            assert_eq!(check.push(Input::A, after), None);
+        }
+        match check.push(Input::A, after) {
+            Some(Action::Done(outcome)) => outcome,
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn matching_gains_pass_and_diverging_ones_warn() {
+        let mut check = GainCheck::new(60_000, 500, 0);
+        let ok = run(&mut check, 200_000, 100_100, 200_000);
+        assert_eq!((ok.diff(), ok.as_str()), (-200, "ok"));
+        assert!(!check.begin(59_999));
+
+        let mut check = GainCheck::new(60_000, 500, 0);
+        let bad = run(&mut check, 200_000, 99_000, 200_000);
+        assert!(bad.drifted && bad.diff() == 2_000);
+        assert_eq!(check.last(), Some(bad));
+    }
+
+    #[test]
+    fn a_moving_load_is_not_drift() {
+        let mut check = GainCheck::new(60_000, 500, 0);
+        let outcome = run(&mut check, 200_000, 99_000, 210_000);
+        assert_eq!(outcome.as_str(), "unsteady");
+        assert!(!outcome.drifted);
+    }
+
+    #[test]
+    fn abort_says_whether_to_switch_back() {
+        let mut check = GainCheck::new(60_000, 500, 0);
+        assert!(check.begin(0));
+        assert!(!check.abort());
+        assert!(check.begin(60_000));
+        for _ in 0..SAMPLES {
+            check.push(Input::A, 0);
+        }
+        assert!(check.low_gain() && check.abort());
+        assert_eq!(check.push(Input::A, 0), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! Load-cell front-end health: saturation, missing data and stuck lines.
+
+/// Largest and smallest codes a 24-bit converter can return.
+pub const FULL_SCALE_POS: i32 = 0x7F_FFFF;
+pub const FULL_SCALE_NEG: i32 = -0x80_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// No conversion arrived within `MISSING_LIMIT` polls (DT stuck high or
+    /// chip unpowered).
+    NoData,
+    /// The same code repeated `STUCK_LIMIT` times (DT stuck low or shorted).
+    Stuck,
+}
+
+impl FaultKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FaultKind::NoData => "no_data",
+            FaultKind::Stuck => "stuck",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Ok,
+    /// Reading pinned at full scale.
+    Overload,
+    Fault(FaultKind),
+}
+
+impl Health {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Health::Ok => "ok",
+            Health::Overload => "overload",
+            Health::Fault(kind) => kind.as_str(),
+        }
+    }
+}
+
+/// Tracks the converter output and reports changes in `Health`.
+pub struct SensorMonitor {
+    health: Health,
+    missing: u32,
+    last: Option<i32>,
+    repeats: u32,
+}
+
+impl Default for SensorMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SensorMonitor {
+    /// Consecutive polls without a conversion before `NoData`.
+    pub const MISSING_LIMIT: u32 = 10;
+    /// Consecutive identical codes before `Stuck`. A live 24-bit bridge
+    /// reading never holds still this long.
+    pub const STUCK_LIMIT: u32 = 32;
+
+    pub const fn new() -> Self {
+        Self {
+            health: Health::Ok,
+            missing: 0,
+            last: None,
+            repeats: 0,
+        }
+    }
+
+    pub fn health(&self) -> Health {
+        self.health
+    }
+
+    /// Record a raw (untared) conversion. Returns the new state if it changed.
+    pub fn on_sample(&mut self, raw: i32) -> Option<Health> {
+        self.missing = 0;
+        if self.last == Some(raw) {
+            self.repeats += 1;
+        } else {
+            self.repeats = 1;
+            self.last = Some(raw);
+        }
+
+        let health = if raw >= FULL_SCALE_POS || raw <= FULL_SCALE_NEG {
+            Health::Overload
+        } else if self.repeats >= Self::STUCK_LIMIT {
+            Health::Fault(FaultKind::Stuck)
+        } else {
+            Health::Ok
+        };
+        self.set(health)
+    }
+
+    /// Record a poll where no conversion was ready.
+    pub fn on_missing(&mut self) -> Option<Health> {
+        self.missing = self.missing.saturating_add(1);
+        if self.missing >= Self::MISSING_LIMIT {
+            self.set(Health::Fault(FaultKind::NoData))
+        } else {
+            None
+        }
+    }
+
+    fn set(&mut self, health: Health) -> Option<Health> {
+        (health != self.health).then(|| {
+            self.health = health;
+            health
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_scale_codes_are_overload() {
+        let mut mon = SensorMonitor::new();
+        assert_eq!(mon.on_sample(1000), None);
+        assert_eq!(mon.on_sample(FULL_SCALE_POS), Some(Health::Overload));
+        assert_eq!(mon.on_sample(FULL_SCALE_POS), None);
+        assert_eq!(mon.on_sample(12), Some(Health::Ok));
+        assert_eq!(mon.on_sample(FULL_SCALE_NEG), Some(Health::Overload));
+    }
+
+    #[test]
+    fn missing_data_faults_after_limit() {
+        let mut mon = SensorMonitor::new();
+        for _ in 1..SensorMonitor::MISSING_LIMIT {
+            assert_eq!(mon.on_missing(), None);
+        }
+        assert_eq!(mon.on_missing(), Some(Health::Fault(FaultKind::NoData)));
+        assert_eq!(mon.on_missing(), None);
+        assert_eq!(mon.on_sample(5), Some(Health::Ok));
+    }
+
+    #[test]
+    fn occasional_misses_are_tolerated() {
+        let mut mon = SensorMonitor::new();
+        for i in 0..100 {
+            if i % 3 == 0 {
+                assert_eq!(mon.on_missing(), None);
+            } else {
+                assert_eq!(mon.on_sample(i), None);
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_code_is_stuck() {
+        let mut mon = SensorMonitor::new();
+        for _ in 1..SensorMonitor::STUCK_LIMIT {
+            assert_eq!(mon.on_sample(0), None);
+        }
+        assert_eq!(mon.on_sample(0), Some(Health::Fault(FaultKind::Stuck)));
+        assert_eq!(mon.on_sample(1), Some(Health::Ok));
+    }
+}
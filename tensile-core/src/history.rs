@@ -0,0 +1,146 @@
+//! Recently streamed lines, kept so a reconnecting host can fill its gap.
+
+/// Byte ring of numbered lines, oldest evicted first.
+///
+/// Each line gets the next sequence number when pushed; numbers are
+/// consecutive, so only the oldest one needs storing. Records are a
+/// little-endian `u16` length followed by the line, wrapping around the end
+/// of the buffer.
+pub struct History<const CAP: usize> {
+    buf: [u8; CAP],
+    /// Offset of the oldest record.
+    start: usize,
+    /// Bytes in use.
+    used: usize,
+    /// Sequence number of the oldest record.
+    first_seq: u32,
+    /// Number of records held.
+    count: u32,
+}
+
+impl<const CAP: usize> History<CAP> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; CAP],
+            start: 0,
+            used: 0,
+            first_seq: 0,
+            count: 0,
+        }
+    }
+
+    /// Number the next pushed line will get.
+    pub fn next_seq(&self) -> u32 {
+        self.first_seq.wrapping_add(self.count)
+    }
+
+    /// Oldest number still held, if any.
+    pub fn oldest(&self) -> Option<u32> {
+        (self.count > 0).then_some(self.first_seq)
+    }
+
+    /// Store `line` under `next_seq()`, evicting old lines to make room. A
+    /// line too long to ever fit empties the history but still uses up its
+    /// number.
+    pub fn push(&mut self, line: &[u8]) {
+        let need = line.len() + 2;
+        if need > CAP || line.len() > u16::MAX as usize {
+            self.first_seq = self.next_seq().wrapping_add(1);
+            self.start = 0;
+            self.used = 0;
+            self.count = 0;
+            return;
+        }
+        while CAP - self.used < need {
+            let len = self.record_len(self.start);
+            self.start = (self.start + 2 + len) % CAP;
+            self.used -= 2 + len;
+            self.first_seq = self.first_seq.wrapping_add(1);
+            self.count -= 1;
+        }
+        let mut at = (self.start + self.used) % CAP;
+        for &byte in (line.len() as u16).to_le_bytes().iter().chain(line) {
+            self.buf[at] = byte;
+            at = (at + 1) % CAP;
+        }
+        self.used += need;
+        self.count += 1;
+    }
+
+    /// Copy line `seq` into `out`. Returns its length, or `None` if it is no
+    /// longer (or not yet) held or `out` is too small.
+    pub fn copy(&self, seq: u32, out: &mut [u8]) -> Option<usize> {
+        let index = seq.wrapping_sub(self.first_seq);
+        if index >= self.count {
+            return None;
+        }
+        let mut at = self.start;
+        for _ in 0..index {
+            at = (at + 2 + self.record_len(at)) % CAP;
+        }
+        let len = self.record_len(at);
+        let out = out.get_mut(..len)?;
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = self.buf[(at + 2 + i) % CAP];
+        }
+        Some(len)
+    }
+
+    fn record_len(&self, at: usize) -> usize {
+        u16::from_le_bytes([self.buf[at], self.buf[(at + 1) % CAP]]) as usize
+    }
+}
+
+impl<const CAP: usize> Default for History<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get<const CAP: usize>(history: &History<CAP>, seq: u32) -> Option<[u8; 4]> {
+        let mut out = [0; 4];
+        history.copy(seq, &mut out).map(|_| out)
+    }
+
+    #[test]
+    fn numbers_lines_consecutively() {
+        let mut history = History::<64>::new();
+        assert_eq!(history.oldest(), None);
+        history.push(b"aaaa");
+        history.push(b"bbbb");
+        assert_eq!(history.oldest(), Some(0));
+        assert_eq!(history.next_seq(), 2);
+        assert_eq!(get(&history, 1), Some(*b"bbbb"));
+        assert_eq!(get(&history, 2), None);
+    }
+
+    #[test]
+    fn evicts_oldest_and_wraps() {
+        // Room for two 6-byte records; the third wraps round the end.
+        let mut history = History::<14>::new();
+        history.push(b"aaaa");
+        history.push(b"bbbb");
+        history.push(b"cccc");
+        assert_eq!(history.oldest(), Some(1));
+        assert_eq!(get(&history, 0), None);
+        assert_eq!(get(&history, 1), Some(*b"bbbb"));
+        assert_eq!(get(&history, 2), Some(*b"cccc"));
+        history.push(b"dddd");
+        assert_eq!(get(&history, 3), Some(*b"dddd"));
+    }
+
+    #[test]
+    fn oversized_line_clears_but_keeps_numbering() {
+        let mut history = History::<8>::new();
+        history.push(b"aaaa");
+        history.push(b"0123456789");
+        assert_eq!(history.oldest(), None);
+        assert_eq!(history.next_seq(), 2);
+        history.push(b"bbbb");
+        assert_eq!(get(&history, 2), Some(*b"bbbb"));
+    }
+}
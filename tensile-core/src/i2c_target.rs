@@ -0,0 +1,103 @@
+//! I2C target side, register access as on most I2C sensors: the controller
+//! writes a register address, then reads on from it or writes on into it,
+//! the address advancing a byte at a time.
+//!
+//! The register map itself belongs to the caller, as bytes.
+
+use core::ops::RangeInclusive;
+
+/// 7-bit addresses outside the ones I2C reserves.
+pub const ADDRESSES: RangeInclusive<u8> = 0x08..=0x77;
+
+pub struct Bank<const N: usize> {
+    /// The map as it stood when the transfer started, so a value wider
+    /// than a byte is never read half old and half new.
+    regs: [u8; N],
+    pointer: u8,
+    /// The next byte written is a register address.
+    addressing: bool,
+}
+
+impl<const N: usize> Bank<N> {
+    pub const fn new() -> Self {
+        Self {
+            regs: [0; N],
+            pointer: 0,
+            addressing: false,
+        }
+    }
+
+    /// A start condition, with the map as it stands now.
+    pub fn start(&mut self, regs: [u8; N]) {
+        self.regs = regs;
+        self.addressing = true;
+    }
+
+    /// A repeated start: the map read is the one from the start, and the
+    /// register address carries over as it would after a stop.
+    pub fn restart(&mut self) {
+        self.addressing = true;
+    }
+
+    /// A byte from the controller; the register and value if it was data
+    /// rather than the register address.
+    pub fn receive(&mut self, byte: u8) -> Option<(u8, u8)> {
+        if core::mem::replace(&mut self.addressing, false) {
+            self.pointer = byte;
+            return None;
+        }
+        let reg = self.pointer;
+        self.pointer = self.pointer.wrapping_add(1);
+        Some((reg, byte))
+    }
+
+    /// The next byte for the controller, `0xFF` past the end of the map.
+    pub fn send(&mut self) -> u8 {
+        self.addressing = false;
+        let byte = self
+            .regs
+            .get(self.pointer as usize)
+            .copied()
+            .unwrap_or(0xFF);
+        self.pointer = self.pointer.wrapping_add(1);
+        byte
+    }
+}
+
+impl<const N: usize> Default for Bank<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_on_from_the_written_address() {
+        let mut bank = Bank::<4>::new();
+        bank.start([1, 2, 3, 4]);
+        assert_eq!(bank.receive(2), None);
+        bank.restart();
+        assert_eq!((bank.send(), bank.send(), bank.send()), (3, 4, 0xFF));
+
+        // A read in a transfer of its own carries on, from a fresh map.
+        bank.start([5, 6, 7, 8]);
+        assert_eq!(bank.receive(1), None);
+        bank.start([9, 10, 11, 12]);
+        assert_eq!((bank.send(), bank.send()), (10, 11));
+    }
+
+    #[test]
+    fn writes_land_on_consecutive_registers() {
+        let mut bank = Bank::<4>::new();
+        bank.start([0; 4]);
+        assert_eq!(bank.receive(3), None);
+        assert_eq!(bank.receive(0xAA), Some((3, 0xAA)));
+        assert_eq!(bank.receive(0xBB), Some((4, 0xBB)));
+        bank.start([0; 4]);
+        assert_eq!(bank.receive(0), None);
+        assert_eq!(bank.receive(1), Some((0, 1)));
+    }
+}
@@ -0,0 +1,87 @@
+//! The name a lab gives a tester (`NAME 5kN-frame`), kept in flash and
+//! reported by `INFO?` so host tools can open it by name.
+
+use crate::math::crc32;
+
+pub const NAME_LEN: usize = 24;
+pub const RECORD_LEN: usize = 32;
+
+const CRC_AT: usize = RECORD_LEN - 4;
+
+/// 1–24 letters, digits, `_`, `-` or `.`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceName {
+    bytes: [u8; NAME_LEN],
+    len: u8,
+}
+
+impl DeviceName {
+    pub fn new(name: &str) -> Option<Self> {
+        let valid = !name.is_empty()
+            && name.len() <= NAME_LEN
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'));
+        if !valid {
+            return None;
+        }
+        let mut bytes = [0; NAME_LEN];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Some(Self {
+            bytes,
+            len: name.len() as u8,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+
+    pub fn encode(&self) -> [u8; RECORD_LEN] {
+        let mut out = [0xFF; RECORD_LEN];
+        out[0] = self.len;
+        out[1..1 + NAME_LEN].copy_from_slice(&self.bytes);
+        let crc = crc32(&out[..CRC_AT]);
+        out[CRC_AT..].copy_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// `None` for erased flash or a record that fails its CRC.
+    pub fn decode(raw: &[u8; RECORD_LEN]) -> Option<Self> {
+        let crc = u32::from_le_bytes([
+            raw[CRC_AT],
+            raw[CRC_AT + 1],
+            raw[CRC_AT + 2],
+            raw[CRC_AT + 3],
+        ]);
+        if crc != crc32(&raw[..CRC_AT]) {
+            return None;
+        }
+        let text = raw[1..].get(..raw[0] as usize)?;
+        Self::new(core::str::from_utf8(text).ok()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips() {
+        let name = DeviceName::new("5kN-frame.2").unwrap();
+        assert_eq!(DeviceName::decode(&name.encode()), Some(name));
+        assert_eq!(name.as_str(), "5kN-frame.2");
+    }
+
+    #[test]
+    fn rejects_blank_damaged_and_bad_names() {
+        assert_eq!(DeviceName::decode(&[0xFF; RECORD_LEN]), None);
+        let mut raw = DeviceName::new("rig2").unwrap().encode();
+        raw[2] ^= 1;
+        assert_eq!(DeviceName::decode(&raw), None);
+        assert!(DeviceName::new("").is_none());
+        assert!(DeviceName::new("5 kN").is_none());
+        assert!(DeviceName::new("a=b").is_none());
+        assert!(DeviceName::new("abcdefghijklmnopqrstuvwxy").is_none());
+    }
+}
@@ -0,0 +1,79 @@
+//! Blink patterns for a single status LED.
+
+/// What the tester is doing, most urgent first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indication {
+    /// Three flashes every two seconds.
+    Fault,
+    /// Fast 5 Hz blink.
+    Overload,
+    /// Steady on.
+    Running,
+    /// 1 Hz blink while a host has the data port open.
+    Streaming,
+    /// A short blip every two seconds, to show the board is alive.
+    Idle,
+    /// A shorter blip every ten seconds, in low-power mode.
+    Asleep,
+}
+
+impl Indication {
+    /// Cycle length and the `[start, end)` on-times within it, in ms.
+    fn pattern(self) -> (u64, &'static [(u64, u64)]) {
+        match self {
+            Indication::Fault => (2_000, &[(0, 150), (300, 450), (600, 750)]),
+            Indication::Overload => (200, &[(0, 100)]),
+            Indication::Running => (1_000, &[(0, 1_000)]),
+            Indication::Streaming => (1_000, &[(0, 500)]),
+            Indication::Idle => (2_000, &[(0, 50)]),
+            Indication::Asleep => (10_000, &[(0, 20)]),
+        }
+    }
+
+    pub fn is_on(self, t_ms: u64) -> bool {
+        let (cycle, on) = self.pattern();
+        let phase = t_ms % cycle;
+        on.iter().any(|&(start, end)| (start..end).contains(&phase))
+    }
+
+    /// The first time after `t_ms` at which `is_on` changes, or `None` for a
+    /// steady pattern.
+    pub fn next_change(self, t_ms: u64) -> Option<u64> {
+        let (cycle, on) = self.pattern();
+        if on == [(0, cycle)] {
+            return None;
+        }
+        let phase = t_ms % cycle;
+        let edge = on
+            .iter()
+            .flat_map(|&(start, end)| [start, end])
+            .filter(|&edge| edge > phase)
+            .min()
+            .unwrap_or(cycle);
+        Some(t_ms - phase + edge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fault_flashes_three_times_per_cycle() {
+        let on: [bool; 14] = core::array::from_fn(|i| Indication::Fault.is_on(i as u64 * 150));
+        assert_eq!(on.iter().filter(|&&on| on).count(), 3);
+        assert!(on[0] && !on[1] && on[2] && on[4] && !on[5] && !on[13]);
+        assert!(Indication::Fault.is_on(4_000));
+    }
+
+    #[test]
+    fn next_change_finds_the_following_edge() {
+        assert_eq!(Indication::Fault.next_change(0), Some(150));
+        assert_eq!(Indication::Fault.next_change(160), Some(300));
+        assert_eq!(Indication::Fault.next_change(800), Some(2_000));
+        assert_eq!(Indication::Idle.next_change(4_049), Some(4_050));
+        assert_eq!(Indication::Asleep.next_change(20), Some(10_000));
+        assert_eq!(Indication::Running.next_change(123), None);
+        assert!(Indication::Running.is_on(123));
+    }
+}
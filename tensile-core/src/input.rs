@@ -0,0 +1,146 @@
+//! Front-panel controls: a quadrature rotary encoder and a push button.
+
+use crate::quantity::Micros;
+
+/// Quadrature steps per detent on the common mechanical encoders.
+const STEPS_PER_DETENT: i8 = 4;
+
+/// Direction of each A/B transition, indexed by previous and current
+/// state. Invalid double steps (contact bounce) count as nothing.
+const TRANSITIONS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+/// Decodes A/B levels into detents.
+#[derive(Debug, Clone, Copy)]
+pub struct Quadrature {
+    state: u8,
+    steps: i8,
+}
+
+impl Quadrature {
+    pub fn new(a: bool, b: bool) -> Self {
+        Self {
+            state: (a as u8) << 1 | b as u8,
+            steps: 0,
+        }
+    }
+
+    /// Feed the current levels. Returns the detents turned since the last
+    /// call, positive clockwise.
+    pub fn update(&mut self, a: bool, b: bool) -> i32 {
+        let state = (a as u8) << 1 | b as u8;
+        self.steps += TRANSITIONS[(self.state << 2 | state) as usize];
+        self.state = state;
+        let detents = self.steps / STEPS_PER_DETENT;
+        self.steps %= STEPS_PER_DETENT;
+        detents as i32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Press {
+    /// Released before `Button::LONG_US`.
+    Short,
+    /// Held for `Button::LONG_US`; reported while still held.
+    Long,
+}
+
+/// Debounces a push button and tells short presses from long ones.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Button {
+    level: bool,
+    changed_at: Micros,
+    pressed: bool,
+    pressed_at: Option<Micros>,
+}
+
+impl Button {
+    /// A level must hold this long to count.
+    pub const DEBOUNCE_US: u64 = 20_000;
+    pub const LONG_US: u64 = 1_000_000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed the level, `true` while pressed.
+    pub fn update(&mut self, level: bool, now: Micros) -> Option<Press> {
+        if level != self.level {
+            self.level = level;
+            self.changed_at = now;
+        }
+        if self.level != self.pressed && now.0 >= self.changed_at.0 + Self::DEBOUNCE_US {
+            self.pressed = self.level;
+            if self.pressed {
+                self.pressed_at = Some(now);
+            } else if self.pressed_at.take().is_some() {
+                return Some(Press::Short);
+            }
+        }
+        let held = self.pressed_at.filter(|at| now.0 >= at.0 + Self::LONG_US);
+        held.map(|_| {
+            // Reported once; the release that follows is not a short press.
+            self.pressed_at = None;
+            Press::Long
+        })
+    }
+
+    /// When `update` must next be called even if the level stays put.
+    pub fn due(&self) -> Option<Micros> {
+        if self.level != self.pressed {
+            Some(Micros(self.changed_at.0 + Self::DEBOUNCE_US))
+        } else {
+            self.pressed_at.map(|at| Micros(at.0 + Self::LONG_US))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A then B falling, seen from the 11 rest position.
+    const CW: [(bool, bool); 4] = [(false, true), (false, false), (true, false), (true, true)];
+
+    fn feed(enc: &mut Quadrature, levels: &[(bool, bool)]) -> i32 {
+        levels.iter().map(|&(a, b)| enc.update(a, b)).sum()
+    }
+
+    #[test]
+    fn counts_detents_both_ways() {
+        let mut enc = Quadrature::new(true, true);
+        assert_eq!(feed(&mut enc, &CW), 1);
+        assert_eq!(feed(&mut enc, &CW[..2]), 0);
+        assert_eq!(feed(&mut enc, &CW[2..]), 1);
+        let ccw = [(true, false), (false, false), (false, true), (true, true)];
+        assert_eq!(feed(&mut enc, &ccw), -1);
+    }
+
+    #[test]
+    fn contact_bounce_cancels_out() {
+        let mut enc = Quadrature::new(true, true);
+        let bouncy = [(false, true), (true, true), (false, true), (false, false)];
+        assert_eq!(feed(&mut enc, &bouncy), 0);
+        assert_eq!(feed(&mut enc, &CW[2..]), 1);
+    }
+
+    #[test]
+    fn short_and_long_presses() {
+        let mut button = Button::new();
+        assert_eq!(button.update(true, Micros(0)), None);
+        // Bounce shorter than the debounce time is ignored.
+        assert_eq!(button.update(false, Micros(5_000)), None);
+        assert_eq!(button.update(true, Micros(6_000)), None);
+        assert_eq!(button.due(), Some(Micros(26_000)));
+        assert_eq!(button.update(true, Micros(26_000)), None);
+        assert_eq!(button.update(false, Micros(200_000)), None);
+        assert_eq!(button.update(false, Micros(220_000)), Some(Press::Short));
+        assert_eq!(button.due(), None);
+
+        assert_eq!(button.update(true, Micros(300_000)), None);
+        assert_eq!(button.update(true, Micros(320_000)), None);
+        assert_eq!(button.due(), Some(Micros(1_320_000)));
+        assert_eq!(button.update(true, Micros(1_320_000)), Some(Press::Long));
+        assert_eq!(button.update(false, Micros(1_500_000)), None);
+        assert_eq!(button.update(false, Micros(1_520_000)), None);
+    }
+}
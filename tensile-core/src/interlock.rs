@@ -0,0 +1,108 @@
+//! Interlock on starting a test from a serial port.
+
+/// Holds test starts back until the host names this device.
+///
+/// `UNLOCK` must carry the device's key (its serial number), so a script
+/// talking to the wrong port cannot start a test. An unlock lasts until one
+/// start uses it or `relock_ms` passes, whichever comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interlock {
+    key: u64,
+    relock_ms: u64,
+    until: Option<u64>,
+}
+
+impl Interlock {
+    pub const DEFAULT_RELOCK_MS: u64 = 30_000;
+
+    pub fn new(key: u64, relock_ms: u64) -> Self {
+        Self {
+            key,
+            relock_ms,
+            until: None,
+        }
+    }
+
+    /// Returns false, and stays as it was, if `token` is not the key.
+    pub fn unlock(&mut self, token: u64, now_ms: u64) -> bool {
+        if token != self.key {
+            return false;
+        }
+        self.until = Some(now_ms + self.relock_ms);
+        true
+    }
+
+    pub fn lock(&mut self) {
+        self.until = None;
+    }
+
+    /// Time left before it relocks, if unlocked.
+    pub fn remaining_ms(&self, now_ms: u64) -> Option<u64> {
+        self.until
+            .filter(|&until| now_ms < until)
+            .map(|until| until - now_ms)
+    }
+
+    /// Whether a start may go ahead, using up the unlock if so.
+    pub fn take(&mut self, now_ms: u64) -> bool {
+        let open = self.remaining_ms(now_ms).is_some();
+        self.until = None;
+        open
+    }
+
+    /// True once, when an unused unlock runs out.
+    pub fn expired(&mut self, now_ms: u64) -> bool {
+        let expired = self.until.is_some_and(|until| now_ms >= until);
+        if expired {
+            self.until = None;
+        }
+        expired
+    }
+
+    /// When an unused unlock runs out.
+    pub fn relock_at(&self) -> Option<u64> {
+        self.until
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: u64 = 0xE661_4103_E72B_5A2F;
+
+    #[test]
+    fn starts_only_with_the_key() {
+        let mut lock = Interlock::new(KEY, 1_000);
+        assert!(!lock.take(0));
+        assert!(!lock.unlock(KEY ^ 1, 0));
+        assert!(!lock.take(0));
+        assert!(lock.unlock(KEY, 0));
+        assert_eq!(lock.remaining_ms(400), Some(600));
+        assert!(lock.take(400));
+        // One unlock, one start.
+        assert!(!lock.take(500));
+    }
+
+    #[test]
+    fn relocks_after_timeout() {
+        let mut lock = Interlock::new(KEY, 1_000);
+        lock.unlock(KEY, 100);
+        assert_eq!(lock.relock_at(), Some(1_100));
+        assert!(!lock.expired(1_099));
+        assert!(lock.expired(1_100));
+        assert!(!lock.expired(1_200));
+        assert_eq!(lock.remaining_ms(1_200), None);
+        lock.unlock(KEY, 2_000);
+        assert!(!lock.take(3_000));
+    }
+
+    #[test]
+    fn lock_cancels_an_unlock() {
+        let mut lock = Interlock::new(KEY, 1_000);
+        lock.unlock(KEY, 0);
+        lock.lock();
+        assert!(!lock.take(1));
+        assert!(!lock.expired(2_000));
+    }
+}
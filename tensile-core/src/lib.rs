@@ -0,0 +1,59 @@
+//! Hardware-independent logic shared by the firmware and testable on the host.
+
+#![no_std]
+
+pub mod alarm;
+pub mod analog;
+pub mod bluetooth;
+pub mod burst;
+pub mod calcheck;
+pub mod callog;
+pub mod capture;
+pub mod chamber;
+pub mod clock;
+pub mod columns;
+pub mod crash;
+pub mod creep;
+pub mod decimal;
+pub mod diag;
+pub mod digital;
+pub mod display;
+pub mod dual;
+pub mod errlog;
+pub mod extensometer;
+pub mod filter;
+pub mod gaincheck;
+pub mod health;
+pub mod history;
+pub mod i2c_target;
+pub mod identity;
+pub mod indicator;
+pub mod input;
+pub mod interlock;
+pub mod loading;
+pub mod log;
+pub mod mark;
+pub mod math;
+pub mod meta;
+pub mod midtest;
+pub mod modbus;
+pub mod pager;
+pub mod peak;
+pub mod profile;
+pub mod qa;
+pub mod quantity;
+pub mod range;
+pub mod rate;
+pub mod scpi;
+pub mod sequencer;
+pub mod servo;
+pub mod specimen;
+pub mod stats;
+pub mod stream;
+pub mod tare;
+pub mod temp;
+pub mod testlog;
+pub mod trigger;
+pub mod units;
+pub mod work;
+pub mod zero;
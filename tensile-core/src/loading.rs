@@ -0,0 +1,135 @@
+//! Which way a test loads the specimen, and which way reads positive.
+
+use crate::peak::{Extreme, PeakHold};
+
+/// Sign convention for the load cell's readings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    /// Tension reads positive.
+    Normal,
+    /// Compression reads positive.
+    Inverted,
+}
+
+impl Polarity {
+    /// A reading in this convention, from one where tension is positive.
+    pub fn apply(self, counts: i32) -> i32 {
+        match self {
+            Polarity::Normal => counts,
+            Polarity::Inverted => counts.saturating_neg(),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Polarity::Normal => "normal",
+            Polarity::Inverted => "inverted",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [Polarity::Normal, Polarity::Inverted]
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Tension,
+    Compression,
+}
+
+impl Direction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Tension => "tension",
+            Direction::Compression => "compression",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [Direction::Tension, Direction::Compression]
+            .into_iter()
+            .find(|d| d.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A test's direction together with the sign convention, which between
+/// them say which sign of force loads the specimen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Loading {
+    pub direction: Direction,
+    pub polarity: Polarity,
+}
+
+impl Loading {
+    pub const TENSION: Self = Self {
+        direction: Direction::Tension,
+        polarity: Polarity::Normal,
+    };
+
+    fn positive(self) -> bool {
+        (self.direction == Direction::Tension) == (self.polarity == Polarity::Normal)
+    }
+
+    /// `force` as load on the specimen: positive the way the test loads it,
+    /// negative the other way.
+    pub fn load(self, force: i32) -> i32 {
+        if self.positive() {
+            force
+        } else {
+            force.saturating_neg()
+        }
+    }
+
+    /// The peak the way the test loads the specimen.
+    pub fn peak(self, hold: &PeakHold) -> Option<Extreme> {
+        if self.positive() {
+            hold.max()
+        } else {
+            hold.min()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quantity::{Counts, Micros};
+
+    #[test]
+    fn compression_loads_negative_unless_inverted() {
+        let compression = Loading {
+            direction: Direction::Compression,
+            polarity: Polarity::Normal,
+        };
+        assert_eq!(Loading::TENSION.load(500), 500);
+        assert_eq!(compression.load(-500), 500);
+        assert_eq!(compression.load(i32::MIN), i32::MAX);
+        assert_eq!(
+            Direction::parse("COMPRESSION"),
+            Some(Direction::Compression)
+        );
+        let inverted = Loading {
+            polarity: Polarity::Inverted,
+            ..compression
+        };
+        // Compression now reads positive, and still loads the specimen.
+        assert_eq!(inverted.load(Polarity::Inverted.apply(-500)), 500);
+    }
+
+    #[test]
+    fn peak_follows_the_direction() {
+        let mut hold = PeakHold::new();
+        for (t, v) in [(0, 120), (1, -900), (2, 40)] {
+            hold.push(Counts(v), Micros(t));
+        }
+        let compression = Loading {
+            direction: Direction::Compression,
+            polarity: Polarity::Normal,
+        };
+        assert_eq!(Loading::TENSION.peak(&hold).unwrap().value, Counts(120));
+        assert_eq!(compression.peak(&hold).unwrap().value, Counts(-900));
+    }
+}
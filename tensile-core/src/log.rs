@@ -0,0 +1,174 @@
+//! Log lines held for the host, so driver diagnostics can be read in the
+//! field without a debug probe (`LOG LEVEL debug`).
+
+/// Longest line kept; longer ones are cut.
+pub const LINE_LEN: usize = 80;
+
+/// Most to least severe; a level lets through itself and everything above.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    const ALL: [Level; 5] = [
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+
+    pub fn parse(word: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(word))
+    }
+
+    /// The inverse of `level as u8`.
+    pub fn from_u8(n: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|&level| level as u8 == n)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    level: Level,
+    buf: [u8; LINE_LEN],
+    len: usize,
+}
+
+impl Entry {
+    pub const fn new(level: Level) -> Self {
+        Self {
+            level,
+            buf: [0; LINE_LEN],
+            len: 0,
+        }
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Append `s`, as much of it as fits on whole characters.
+    pub fn push_str(&mut self, s: &str) {
+        let mut take = s.len().min(LINE_LEN - self.len);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+/// The newest `N` lines not yet sent.
+pub struct Queue<const N: usize> {
+    entries: [Entry; N],
+    head: usize,
+    len: usize,
+    /// Lines pushed out by newer ones before they could be sent.
+    lost: u32,
+}
+
+impl<const N: usize> Queue<N> {
+    pub const fn new() -> Self {
+        Self {
+            entries: [Entry::new(Level::Error); N],
+            head: 0,
+            len: 0,
+            lost: 0,
+        }
+    }
+
+    pub fn push(&mut self, entry: Entry) {
+        if self.len == N {
+            self.head = (self.head + 1) % N;
+            self.len -= 1;
+            self.lost = self.lost.saturating_add(1);
+        }
+        self.entries[(self.head + self.len) % N] = entry;
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<Entry> {
+        if self.len == 0 {
+            return None;
+        }
+        let entry = self.entries[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(entry)
+    }
+
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+}
+
+impl<const N: usize> Default for Queue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: Level, text: &str) -> Entry {
+        let mut entry = Entry::new(level);
+        entry.push_str(text);
+        entry
+    }
+
+    #[test]
+    fn levels_order_and_parse() {
+        assert!(Level::Error < Level::Debug);
+        assert_eq!(Level::parse("DEBUG"), Some(Level::Debug));
+        assert_eq!(Level::parse("off"), None);
+        assert_eq!(Level::from_u8(Level::Warn as u8), Some(Level::Warn));
+        assert_eq!(Level::from_u8(0), None);
+    }
+
+    #[test]
+    fn cuts_long_lines_on_a_character() {
+        let mut e = entry(Level::Info, "");
+        for _ in 0..LINE_LEN - 1 {
+            e.push_str("a");
+        }
+        e.push_str("µs");
+        assert_eq!(e.as_str().len(), LINE_LEN - 1);
+    }
+
+    #[test]
+    fn drops_the_oldest_when_full() {
+        let mut queue = Queue::<2>::new();
+        queue.push(entry(Level::Warn, "one"));
+        queue.push(entry(Level::Debug, "two"));
+        queue.push(entry(Level::Debug, "three"));
+        assert_eq!(queue.lost(), 1);
+        let e = queue.pop().unwrap();
+        assert_eq!((e.level(), e.as_str()), (Level::Debug, "two"));
+        assert_eq!(queue.pop().unwrap().as_str(), "three");
+        assert!(queue.pop().is_none());
+    }
+}
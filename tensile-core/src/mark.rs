@@ -0,0 +1,107 @@
+//! Operator annotations (`MARK necking visible`), each tied to the sample
+//! that follows it so it lands on the right point of the curve.
+
+pub const LABEL_LEN: usize = 48;
+/// Marks that can wait for the next sample; at a slow sample rate an
+/// operator can get a few in first.
+pub const PENDING: usize = 4;
+
+/// Free text: printable ASCII and spaces, trimmed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label {
+    bytes: [u8; LABEL_LEN],
+    len: u8,
+}
+
+impl Label {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if text.is_empty()
+            || text.len() > LABEL_LEN
+            || !text.bytes().all(|b| b == b' ' || b.is_ascii_graphic())
+        {
+            return None;
+        }
+        let mut bytes = [0; LABEL_LEN];
+        bytes[..text.len()].copy_from_slice(text.as_bytes());
+        Some(Self {
+            bytes,
+            len: text.len() as u8,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+/// No room for another mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Full;
+
+/// Marks waiting for the next sample, oldest first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Marks {
+    pending: [Option<Label>; PENDING],
+    len: usize,
+}
+
+impl Marks {
+    pub const fn new() -> Self {
+        Self {
+            pending: [None; PENDING],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, label: Label) -> Result<(), Full> {
+        let slot = self.pending.get_mut(self.len).ok_or(Full)?;
+        *slot = Some(label);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// The oldest waiting mark, for the sample now being sent.
+    pub fn pop(&mut self) -> Option<Label> {
+        let first = self.pending[..self.len].first_mut()?.take();
+        self.pending[..self.len].rotate_left(1);
+        self.len -= 1;
+        first
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_are_trimmed_printable_text() {
+        assert_eq!(
+            Label::parse("  necking visible ").unwrap().as_str(),
+            "necking visible"
+        );
+        assert!(Label::parse("   ").is_none());
+        assert!(Label::parse("tab\there").is_none());
+        assert!(Label::parse("ü").is_none());
+        assert!(Label::parse("0123456789012345678901234567890123456789012345678").is_none());
+    }
+
+    #[test]
+    fn marks_wait_in_order() {
+        let mut marks = Marks::new();
+        assert_eq!(marks.pop(), None);
+        for text in ["a", "b", "c", "d"] {
+            marks.push(Label::parse(text).unwrap()).unwrap();
+        }
+        assert_eq!(marks.push(Label::parse("e").unwrap()), Err(Full));
+        assert_eq!(marks.pop().unwrap().as_str(), "a");
+        marks.push(Label::parse("e").unwrap()).unwrap();
+        let order = [marks.pop(), marks.pop(), marks.pop(), marks.pop()];
+        assert_eq!(order.map(|l| l.unwrap().as_str().as_bytes()[0]), *b"bcde");
+        assert_eq!(marks.pop(), None);
+    }
+}
@@ -0,0 +1,108 @@
+//! Integer helpers for targets without an FPU.
+
+/// Floor of the square root of `n`.
+pub fn isqrt(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's method from an initial guess at or above the root.
+    let mut x = 1u64 << (64 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Base-2 logarithm of `n` in tenths, rounded; 0 for 0.
+pub fn log2_tenths(n: u32) -> u32 {
+    if n == 0 {
+        return 0;
+    }
+    let whole = n.ilog2();
+    // Mantissa in [1, 2) as Q30; each squaring yields one binary place.
+    let mut m = ((n as u64) << 30) >> whole;
+    let mut frac = 0;
+    for _ in 0..8 {
+        m = (m * m) >> 30;
+        frac <<= 1;
+        if m >= 2 << 30 {
+            m >>= 1;
+            frac |= 1;
+        }
+    }
+    whole * 10 + (frac * 10 + 128) / 256
+}
+
+/// CRC-32 (IEEE 802.3, as used by zlib), bit by bit to stay small.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finish()
+}
+
+/// [`crc32`] over data that arrives in pieces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self(!0)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 ^= byte as u32;
+            for _ in 0..8 {
+                self.0 = (self.0 >> 1) ^ (0xEDB8_8320 & (self.0 & 1).wrapping_neg());
+            }
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log2_in_tenths() {
+        assert_eq!(log2_tenths(0), 0);
+        assert_eq!(log2_tenths(1), 0);
+        assert_eq!(log2_tenths(2), 10);
+        assert_eq!(log2_tenths(3), 16);
+        assert_eq!(log2_tenths(10), 33);
+        assert_eq!(log2_tenths(1000), 100);
+        assert_eq!(log2_tenths(u32::MAX), 320);
+    }
+
+    #[test]
+    fn isqrt_matches_floor_sqrt() {
+        for n in 0..10_000u64 {
+            let r = isqrt(n);
+            assert!(r * r <= n && (r + 1) * (r + 1) > n, "{n}");
+        }
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+}
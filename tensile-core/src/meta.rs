@@ -0,0 +1,152 @@
+//! Test metadata: `KEY=VALUE` pairs such as specimen ID, area, gauge length
+//! and operator, echoed into the stream when a test starts.
+
+pub const MAX_ENTRIES: usize = 8;
+pub const KEY_LEN: usize = 16;
+pub const VALUE_LEN: usize = 32;
+
+/// One `KEY=VALUE` pair. Keys are stored upper-case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    key: [u8; KEY_LEN],
+    key_len: u8,
+    value: [u8; VALUE_LEN],
+    value_len: u8,
+}
+
+impl Entry {
+    /// Parse `KEY=VALUE`. Keys are letters, digits and `_`; values must not
+    /// contain `=` or whitespace, so a line of entries stays splittable. An
+    /// empty value means "remove".
+    pub fn parse(word: &str) -> Option<Self> {
+        let (key, value) = word.split_once('=')?;
+        let key_ok = !key.is_empty()
+            && key.len() <= KEY_LEN
+            && key.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+        let value_ok =
+            value.len() <= VALUE_LEN && !value.contains(|c: char| c == '=' || c.is_whitespace());
+        if !(key_ok && value_ok) {
+            return None;
+        }
+        let mut entry = Self {
+            key: [0; KEY_LEN],
+            key_len: key.len() as u8,
+            value: [0; VALUE_LEN],
+            value_len: value.len() as u8,
+        };
+        entry.key[..key.len()].copy_from_slice(key.as_bytes());
+        entry.key.make_ascii_uppercase();
+        entry.value[..value.len()].copy_from_slice(value.as_bytes());
+        Some(entry)
+    }
+
+    pub fn key(&self) -> &str {
+        core::str::from_utf8(&self.key[..self.key_len as usize]).unwrap_or("")
+    }
+
+    pub fn value(&self) -> &str {
+        core::str::from_utf8(&self.value[..self.value_len as usize]).unwrap_or("")
+    }
+}
+
+/// No room for another key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Full;
+
+/// The entries in the order they were first set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Metadata {
+    entries: [Option<Entry>; MAX_ENTRIES],
+}
+
+impl Metadata {
+    pub const fn new() -> Self {
+        Self {
+            entries: [None; MAX_ENTRIES],
+        }
+    }
+
+    /// Add or replace `entry`'s key, or remove it if the value is empty.
+    pub fn set(&mut self, entry: Entry) -> Result<(), Full> {
+        let existing = self
+            .entries
+            .iter()
+            .position(|e| e.is_some_and(|e| e.key() == entry.key()));
+        match (existing, entry.value_len) {
+            (Some(i), 0) => {
+                self.entries[i..].rotate_left(1);
+                self.entries[MAX_ENTRIES - 1] = None;
+            }
+            (Some(i), _) => self.entries[i] = Some(entry),
+            (None, 0) => {}
+            (None, _) => {
+                let slot = self.entries.iter_mut().find(|e| e.is_none()).ok_or(Full)?;
+                *slot = Some(entry);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = [None; MAX_ENTRIES];
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries[0].is_none()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(word: &str) -> Entry {
+        Entry::parse(word).unwrap()
+    }
+
+    #[test]
+    fn parses_and_validates_entries() {
+        let e = entry("specimen_id=A-12");
+        assert_eq!((e.key(), e.value()), ("SPECIMEN_ID", "A-12"));
+        assert_eq!(entry("AREA=").value(), "");
+        assert_eq!(Entry::parse("AREA"), None);
+        assert_eq!(Entry::parse("=12"), None);
+        assert_eq!(Entry::parse("GAUGE LENGTH=50"), None);
+        assert_eq!(Entry::parse("GAUGE=5=0"), None);
+        assert_eq!(
+            Entry::parse("OPERATOR=a_very_long_name_that_will_not_fit"),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_order_and_replaces_by_key() {
+        let mut meta = Metadata::new();
+        assert!(meta.is_empty());
+        meta.set(entry("SPECIMEN=A1")).unwrap();
+        meta.set(entry("AREA=12.5")).unwrap();
+        meta.set(entry("OPERATOR=jd")).unwrap();
+        meta.set(entry("specimen=A2")).unwrap();
+        meta.set(entry("AREA=")).unwrap();
+        let mut it = meta.iter().map(|e| (e.key(), e.value()));
+        assert_eq!(it.next(), Some(("SPECIMEN", "A2")));
+        assert_eq!(it.next(), Some(("OPERATOR", "jd")));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn refuses_new_keys_when_full() {
+        let mut meta = Metadata::new();
+        for word in ["A=1", "B=1", "C=1", "D=1", "E=1", "F=1", "G=1", "H=1"] {
+            meta.set(entry(word)).unwrap();
+        }
+        assert_eq!(meta.set(entry("I=1")), Err(Full));
+        assert_eq!(meta.set(entry("A=2")), Ok(()));
+        meta.clear();
+        assert!(meta.is_empty());
+    }
+}
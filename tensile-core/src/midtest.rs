@@ -0,0 +1,139 @@
+//! What happens to a command that would change a running test's scale
+//! (`TARE`, `CAL`, `UNITS`, `SAMPLERATE`, ...): refused, or held until the
+//! test ends, so a dataset never changes meaning halfway through a pull.
+
+/// Longest command held, as the dispatcher reads them.
+pub const LINE_LEN: usize = 128;
+/// Commands that can wait for the end of a test.
+pub const DEFERRED: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Policy {
+    /// Refuse them with a state error.
+    #[default]
+    Reject,
+    /// Hold them and run them in order once the test has ended.
+    Defer,
+}
+
+impl Policy {
+    pub fn parse(word: &str) -> Option<Self> {
+        [Policy::Reject, Policy::Defer]
+            .into_iter()
+            .find(|policy| policy.as_str().eq_ignore_ascii_case(word))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Policy::Reject => "reject",
+            Policy::Defer => "defer",
+        }
+    }
+}
+
+/// A command line as it was received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line {
+    bytes: [u8; LINE_LEN],
+    len: u8,
+}
+
+impl Line {
+    pub fn new(text: &str) -> Option<Self> {
+        if text.len() > LINE_LEN {
+            return None;
+        }
+        let mut bytes = [0; LINE_LEN];
+        bytes[..text.len()].copy_from_slice(text.as_bytes());
+        Some(Self {
+            bytes,
+            len: text.len() as u8,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+/// No room for another command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Full;
+
+/// Commands waiting for the test to end, oldest first.
+#[derive(Debug, Clone, Copy)]
+pub struct Deferred {
+    pending: [Option<Line>; DEFERRED],
+    len: usize,
+}
+
+impl Deferred {
+    pub const fn new() -> Self {
+        Self {
+            pending: [None; DEFERRED],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, text: &str) -> Result<(), Full> {
+        let slot = self.pending.get_mut(self.len).ok_or(Full)?;
+        *slot = Some(Line::new(text).ok_or(Full)?);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<Line> {
+        let first = self.pending[..self.len].first_mut()?.take();
+        self.pending[..self.len].rotate_left(1);
+        self.len -= 1;
+        first
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for Deferred {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies_parse() {
+        assert_eq!(Policy::parse("DEFER"), Some(Policy::Defer));
+        assert_eq!(Policy::parse("queue"), None);
+        assert_eq!(Policy::default().as_str(), "reject");
+    }
+
+    #[test]
+    fn commands_wait_in_order_until_full() {
+        let mut deferred = Deferred::new();
+        assert_eq!(deferred.pop(), None);
+        for _ in 0..DEFERRED {
+            deferred.push("TARE").unwrap();
+        }
+        assert_eq!(deferred.push("UNITS N"), Err(Full));
+        assert_eq!(deferred.pop().unwrap().as_str(), "TARE");
+        deferred.push("UNITS N").unwrap();
+        assert_eq!(deferred.len(), DEFERRED);
+        while deferred.len() > 1 {
+            deferred.pop();
+        }
+        assert_eq!(deferred.pop().unwrap().as_str(), "UNITS N");
+        assert!(deferred.is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+//! Modbus RTU server side: frame checking, function decoding and replies.
+//!
+//! The register map itself belongs to the caller, behind `Registers`.
+
+/// Largest RTU frame, address and CRC included.
+pub const MAX_FRAME: usize = 256;
+
+/// Unit address that every server obeys without replying.
+pub const BROADCAST: u8 = 0;
+
+const READ_HOLDING: u8 = 0x03;
+const READ_INPUT: u8 = 0x04;
+const WRITE_SINGLE: u8 = 0x06;
+const WRITE_MULTIPLE: u8 = 0x10;
+
+/// Most registers one read may ask for; keeps the reply inside `MAX_FRAME`.
+const MAX_READ: u16 = 125;
+const MAX_WRITE: u16 = 123;
+
+/// CRC-16/MODBUS. Sent low byte first.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFF_u16;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Exception codes sent back in place of a normal reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    IllegalFunction = 1,
+    IllegalDataAddress = 2,
+    IllegalDataValue = 3,
+    ServerDeviceFailure = 4,
+    ServerDeviceBusy = 6,
+}
+
+/// The server's register map.
+pub trait Registers {
+    /// Read-only input register, or `None` if unmapped.
+    fn input(&self, addr: u16) -> Option<u16>;
+
+    /// Holding register, or `None` if unmapped.
+    fn holding(&self, addr: u16) -> Option<u16>;
+
+    fn write_holding(&mut self, addr: u16, value: u16) -> Result<(), Exception>;
+}
+
+/// Act on one received frame and build the reply into `reply`.
+///
+/// Returns the reply length, or `None` when no reply is due: a corrupt or
+/// truncated frame, one for another unit, or a broadcast.
+pub fn respond(
+    frame: &[u8],
+    unit: u8,
+    regs: &mut impl Registers,
+    reply: &mut [u8; MAX_FRAME],
+) -> Option<usize> {
+    let (body, crc) = frame.split_last_chunk::<2>()?;
+    if body.len() < 2 || crc16(body) != u16::from_le_bytes(*crc) {
+        return None;
+    }
+    let (address, function) = (body[0], body[1]);
+    if address != unit && address != BROADCAST {
+        return None;
+    }
+    reply[0] = unit;
+    reply[1] = function;
+    let len = match execute(function, &body[2..], regs, reply) {
+        Ok(len) => len,
+        Err(exception) => {
+            reply[1] = function | 0x80;
+            reply[2] = exception as u8;
+            3
+        }
+    };
+    if address == BROADCAST {
+        return None;
+    }
+    let crc = crc16(&reply[..len]).to_le_bytes();
+    reply[len..len + 2].copy_from_slice(&crc);
+    Some(len + 2)
+}
+
+/// Run one request. Returns the reply length before the CRC.
+fn execute(
+    function: u8,
+    data: &[u8],
+    regs: &mut impl Registers,
+    reply: &mut [u8; MAX_FRAME],
+) -> Result<usize, Exception> {
+    let word = |i: usize| {
+        data.get(i..i + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or(Exception::IllegalDataValue)
+    };
+    match function {
+        READ_HOLDING | READ_INPUT => {
+            let (start, count) = (word(0)?, word(2)?);
+            if data.len() != 4 || !(1..=MAX_READ).contains(&count) {
+                return Err(Exception::IllegalDataValue);
+            }
+            reply[2] = (count * 2) as u8;
+            for i in 0..count {
+                let addr = start.checked_add(i).ok_or(Exception::IllegalDataAddress)?;
+                let value = if function == READ_HOLDING {
+                    regs.holding(addr)
+                } else {
+                    regs.input(addr)
+                };
+                let value = value.ok_or(Exception::IllegalDataAddress)?;
+                let at = 3 + 2 * i as usize;
+                reply[at..at + 2].copy_from_slice(&value.to_be_bytes());
+            }
+            Ok(3 + 2 * count as usize)
+        }
+        WRITE_SINGLE => {
+            let (addr, value) = (word(0)?, word(2)?);
+            if data.len() != 4 {
+                return Err(Exception::IllegalDataValue);
+            }
+            regs.holding(addr).ok_or(Exception::IllegalDataAddress)?;
+            regs.write_holding(addr, value)?;
+            reply[2..6].copy_from_slice(&data[..4]);
+            Ok(6)
+        }
+        WRITE_MULTIPLE => {
+            let (start, count) = (word(0)?, word(2)?);
+            let values = data.get(5..).ok_or(Exception::IllegalDataValue)?;
+            if !(1..=MAX_WRITE).contains(&count)
+                || data[4] as usize != count as usize * 2
+                || values.len() != data[4] as usize
+            {
+                return Err(Exception::IllegalDataValue);
+            }
+            for i in 0..count {
+                let addr = start.checked_add(i).ok_or(Exception::IllegalDataAddress)?;
+                regs.holding(addr).ok_or(Exception::IllegalDataAddress)?;
+            }
+            for (addr, value) in (start..).zip(values.chunks_exact(2)) {
+                regs.write_holding(addr, u16::from_be_bytes([value[0], value[1]]))?;
+            }
+            reply[2..6].copy_from_slice(&data[..4]);
+            Ok(6)
+        }
+        _ => Err(Exception::IllegalFunction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Map {
+        input: [u16; 4],
+        holding: [u16; 2],
+    }
+
+    impl Registers for Map {
+        fn input(&self, addr: u16) -> Option<u16> {
+            self.input.get(addr as usize).copied()
+        }
+
+        fn holding(&self, addr: u16) -> Option<u16> {
+            self.holding.get(addr as usize).copied()
+        }
+
+        fn write_holding(&mut self, addr: u16, value: u16) -> Result<(), Exception> {
+            if value > 100 {
+                return Err(Exception::IllegalDataValue);
+            }
+            self.holding[addr as usize] = value;
+            Ok(())
+        }
+    }
+
+    fn map() -> Map {
+        Map {
+            input: [0x1234, 0x5678, 0, 7],
+            holding: [0, 0],
+        }
+    }
+
+    fn frame<const N: usize>(body: [u8; N]) -> ([u8; MAX_FRAME], usize) {
+        let mut out = [0; MAX_FRAME];
+        out[..N].copy_from_slice(&body);
+        out[N..N + 2].copy_from_slice(&crc16(&body).to_le_bytes());
+        (out, N + 2)
+    }
+
+    #[test]
+    fn crc_matches_reference_frame() {
+        assert_eq!(
+            crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]).to_le_bytes(),
+            [0xC5, 0xCD]
+        );
+    }
+
+    #[test]
+    fn reads_input_registers() {
+        let (request, len) = frame([1, READ_INPUT, 0, 0, 0, 2]);
+        let mut reply = [0; MAX_FRAME];
+        let n = respond(&request[..len], 1, &mut map(), &mut reply).unwrap();
+        assert_eq!(&reply[..n - 2], &[1, READ_INPUT, 4, 0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(crc16(&reply[..n]), 0);
+    }
+
+    #[test]
+    fn unmapped_and_invalid_requests_raise_exceptions() {
+        let mut reply = [0; MAX_FRAME];
+        let (request, len) = frame([1, READ_INPUT, 0, 3, 0, 2]);
+        let n = respond(&request[..len], 1, &mut map(), &mut reply).unwrap();
+        assert_eq!(&reply[..3], &[1, READ_INPUT | 0x80, 2]);
+        assert_eq!(n, 5);
+        let (request, len) = frame([1, WRITE_SINGLE, 0, 1, 0x01, 0x00]);
+        respond(&request[..len], 1, &mut map(), &mut reply).unwrap();
+        assert_eq!(&reply[..3], &[1, WRITE_SINGLE | 0x80, 3]);
+        let (request, len) = frame([1, 0x2B, 0, 0]);
+        respond(&request[..len], 1, &mut map(), &mut reply).unwrap();
+        assert_eq!(&reply[..3], &[1, 0x2B | 0x80, 1]);
+    }
+
+    #[test]
+    fn writes_and_broadcasts() {
+        let mut regs = map();
+        let mut reply = [0; MAX_FRAME];
+        let (request, len) = frame([1, WRITE_MULTIPLE, 0, 0, 0, 2, 4, 0, 5, 0, 6]);
+        let n = respond(&request[..len], 1, &mut regs, &mut reply).unwrap();
+        assert_eq!(&reply[..n - 2], &[1, WRITE_MULTIPLE, 0, 0, 0, 2]);
+        assert_eq!(regs.holding, [5, 6]);
+        let (request, len) = frame([BROADCAST, WRITE_SINGLE, 0, 1, 0, 9]);
+        assert_eq!(respond(&request[..len], 1, &mut regs, &mut reply), None);
+        assert_eq!(regs.holding, [5, 9]);
+    }
+
+    #[test]
+    fn ignores_other_units_and_bad_crc() {
+        let mut reply = [0; MAX_FRAME];
+        let (request, len) = frame([2, READ_INPUT, 0, 0, 0, 1]);
+        assert_eq!(respond(&request[..len], 1, &mut map(), &mut reply), None);
+        let (mut request, len) = frame([1, READ_INPUT, 0, 0, 0, 1]);
+        request[2] ^= 1;
+        assert_eq!(respond(&request[..len], 1, &mut map(), &mut reply), None);
+    }
+}
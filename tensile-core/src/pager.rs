@@ -0,0 +1,95 @@
+//! A reply longer than the link's queue, held in RAM and handed over a
+//! whole line at a time as the queue empties, so nothing is lost or torn.
+
+pub struct Pager<const N: usize> {
+    buf: [u8; N],
+    start: usize,
+    len: usize,
+}
+
+impl<const N: usize> Pager<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.len
+    }
+
+    /// Append `bytes`; returns how many did not fit.
+    pub fn push(&mut self, bytes: &[u8]) -> usize {
+        if self.is_empty() {
+            self.start = 0;
+            self.len = 0;
+        }
+        let take = bytes.len().min(N - self.len);
+        self.buf[self.len..self.len + take].copy_from_slice(&bytes[..take]);
+        self.len += take;
+        bytes.len() - take
+    }
+
+    /// The next complete line, with its terminator: a line end, or the zero
+    /// that closes a binary frame.
+    pub fn line(&self) -> Option<&[u8]> {
+        let rest = &self.buf[self.start..self.len];
+        let end = rest.iter().position(|&b| b == b'\n' || b == 0)?;
+        Some(&rest[..=end])
+    }
+
+    /// Drop the line `line` returned, once it has been sent.
+    pub fn advance(&mut self) {
+        if let Some(n) = self.line().map(<[u8]>::len) {
+            self.start += n;
+        }
+    }
+}
+
+impl<const N: usize> Default for Pager<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hands_out_whole_lines() {
+        let mut pager = Pager::<32>::new();
+        assert_eq!(pager.push(b"Config: RATE 5"), 0);
+        assert_eq!(pager.line(), None);
+        pager.push(b"\r\nOK\r\n");
+        assert_eq!(pager.line(), Some(&b"Config: RATE 5\r\n"[..]));
+        pager.advance();
+        assert_eq!(pager.line(), Some(&b"OK\r\n"[..]));
+        pager.advance();
+        assert!(pager.is_empty());
+    }
+
+    #[test]
+    fn frames_end_at_their_zero() {
+        let mut pager = Pager::<32>::new();
+        pager.push(b"\0\x03\n\x01\0OK\r\n");
+        assert_eq!(pager.line(), Some(&b"\0"[..]));
+        pager.advance();
+        assert_eq!(pager.line(), Some(&b"\x03\n"[..]));
+        pager.advance();
+        assert_eq!(pager.line(), Some(&b"\x01\0"[..]));
+        pager.advance();
+        assert_eq!(pager.line(), Some(&b"OK\r\n"[..]));
+    }
+
+    #[test]
+    fn counts_what_does_not_fit_and_reuses_the_space() {
+        let mut pager = Pager::<8>::new();
+        assert_eq!(pager.push(b"abcdef\r\n!!"), 2);
+        pager.advance();
+        assert!(pager.is_empty());
+        assert_eq!(pager.push(b"12345678"), 0);
+    }
+}
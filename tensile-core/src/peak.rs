@@ -0,0 +1,98 @@
+//! Peak-hold register for the largest and smallest force seen.
+
+use crate::quantity::{Counts, Micros};
+
+/// An extreme value and the sample timestamp it occurred at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extreme {
+    pub value: Counts,
+    pub t: Micros,
+}
+
+/// Holds the maximum and minimum since the last `reset`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PeakHold {
+    max: Option<Extreme>,
+    min: Option<Extreme>,
+}
+
+impl PeakHold {
+    pub const fn new() -> Self {
+        Self {
+            max: None,
+            min: None,
+        }
+    }
+
+    pub fn push(&mut self, value: Counts, t: Micros) {
+        let sample = Extreme { value, t };
+        if self.max.is_none_or(|m| value > m.value) {
+            self.max = Some(sample);
+        }
+        if self.min.is_none_or(|m| value < m.value) {
+            self.min = Some(sample);
+        }
+    }
+
+    pub fn max(&self) -> Option<Extreme> {
+        self.max
+    }
+
+    pub fn min(&self) -> Option<Extreme> {
+        self.min
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_until_first_sample() {
+        let peak = PeakHold::new();
+        assert_eq!(peak.max(), None);
+        assert_eq!(peak.min(), None);
+    }
+
+    #[test]
+    fn tracks_both_extremes_with_time() {
+        let mut peak = PeakHold::new();
+        for (t, v) in [(0, 5), (1, 40), (2, -7), (3, 12)] {
+            peak.push(Counts(v), Micros(t));
+        }
+        assert_eq!(
+            peak.max(),
+            Some(Extreme {
+                value: Counts(40),
+                t: Micros(1)
+            })
+        );
+        assert_eq!(
+            peak.min(),
+            Some(Extreme {
+                value: Counts(-7),
+                t: Micros(2)
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_first_occurrence_of_a_tie() {
+        let mut peak = PeakHold::new();
+        peak.push(Counts(9), Micros(10));
+        peak.push(Counts(9), Micros(20));
+        assert_eq!(peak.max().unwrap().t, Micros(10));
+    }
+
+    #[test]
+    fn reset_clears() {
+        let mut peak = PeakHold::new();
+        peak.push(Counts(1), Micros(0));
+        peak.reset();
+        assert_eq!(peak.max(), None);
+    }
+}
@@ -0,0 +1,345 @@
+//! Named test profiles: the break criterion, stop limits, clamp angle,
+//! preload and end-of-test output for a kind of test, kept in flash and
+//! started with `RUN <name>`.
+
+use crate::math::crc32;
+
+pub const NAME_LEN: usize = 12;
+pub const MAX_PROFILES: usize = 8;
+pub const RECORD_LEN: usize = 32;
+/// The whole table, two flash pages: the records, then an extension record
+/// per slot for settings added since. Tables from before then have the
+/// second page erased, which reads as none of them set.
+pub const TABLE_LEN: usize = 2 * MAX_PROFILES * RECORD_LEN;
+const EXT_AT: usize = MAX_PROFILES * RECORD_LEN;
+
+const HAS_BREAK: u8 = 1 << 0;
+const HAS_MAX_FORCE: u8 = 1 << 1;
+const HAS_MAX_DURATION: u8 = 1 << 2;
+const HAS_CLAMP: u8 = 1 << 3;
+const HAS_END_OUTPUT: u8 = 1 << 4;
+const HAS_PRELOAD: u8 = 1 << 0;
+
+/// Conditions that end a test on their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Stop once the filtered force reaches this many counts either way.
+    pub max_force: Option<u32>,
+    /// Stop this long after the start.
+    pub max_duration_s: Option<u32>,
+}
+
+impl Limits {
+    pub const NONE: Self = Self {
+        max_force: None,
+        max_duration_s: None,
+    };
+
+    /// Why a test at `force` counts, `elapsed_s` in, should stop. The force
+    /// limit is on magnitude, so it holds in compression too.
+    pub fn exceeded(&self, force: i32, elapsed_s: u64) -> Option<&'static str> {
+        if self
+            .max_force
+            .is_some_and(|max| force.unsigned_abs() >= max)
+        {
+            Some("force_limit")
+        } else if self
+            .max_duration_s
+            .is_some_and(|max| elapsed_s >= max as u64)
+        {
+            Some("duration")
+        } else {
+            None
+        }
+    }
+}
+
+/// A profile name: 1–12 letters, digits, `_` or `-`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Name {
+    bytes: [u8; NAME_LEN],
+    len: u8,
+}
+
+impl Name {
+    pub fn new(name: &str) -> Option<Self> {
+        let valid = !name.is_empty()
+            && name.len() <= NAME_LEN
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+        if !valid {
+            return None;
+        }
+        let mut bytes = [0; NAME_LEN];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Some(Self {
+            bytes,
+            len: name.len() as u8,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or("")
+    }
+
+    fn matches(&self, other: &Name) -> bool {
+        self.as_str().eq_ignore_ascii_case(other.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    pub name: Name,
+    /// Drop in percent and minimum peak in counts, as for `BREAK`.
+    pub breaks: Option<(u8, u32)>,
+    pub limits: Limits,
+    /// Servo angle to close the clamp to before the test starts.
+    pub clamp: Option<u8>,
+    /// Output (below 128) and the state to drive it to when the test ends.
+    pub end_output: Option<(u8, bool)>,
+    /// Load in counts to take up slack to, as for `PRELOAD`, in place of
+    /// the standing one.
+    pub preload: Option<u32>,
+}
+
+impl Profile {
+    /// A profile that neither detects breaks nor stops on its own.
+    pub fn new(name: Name) -> Self {
+        Self {
+            name,
+            breaks: None,
+            limits: Limits::NONE,
+            clamp: None,
+            end_output: None,
+            preload: None,
+        }
+    }
+
+    pub fn encode(&self) -> [u8; RECORD_LEN] {
+        let mut out = [0; RECORD_LEN];
+        out[..NAME_LEN].copy_from_slice(&self.name.bytes);
+        let (drop_pct, min_peak) = self.breaks.unwrap_or((0, 0));
+        out[12] = (self.breaks.is_some() as u8 * HAS_BREAK)
+            | (self.limits.max_force.is_some() as u8 * HAS_MAX_FORCE)
+            | (self.limits.max_duration_s.is_some() as u8 * HAS_MAX_DURATION)
+            | (self.clamp.is_some() as u8 * HAS_CLAMP)
+            | (self.end_output.is_some() as u8 * HAS_END_OUTPUT);
+        out[13] = drop_pct;
+        out[14] = self.clamp.unwrap_or(0);
+        let (output, on) = self.end_output.unwrap_or((0, false));
+        out[15] = (output & 0x7F) | (on as u8) << 7;
+        out[16..20].copy_from_slice(&min_peak.to_le_bytes());
+        out[20..24].copy_from_slice(&self.limits.max_force.unwrap_or(0).to_le_bytes());
+        out[24..28].copy_from_slice(&self.limits.max_duration_s.unwrap_or(0).to_le_bytes());
+        let crc = crc32(&out[..28]);
+        out[28..].copy_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// `None` for an erased or corrupted record.
+    pub fn decode(raw: &[u8; RECORD_LEN]) -> Option<Self> {
+        let word = |i: usize| u32::from_le_bytes([raw[i], raw[i + 1], raw[i + 2], raw[i + 3]]);
+        if crc32(&raw[..28]) != word(28) {
+            return None;
+        }
+        let name_len = raw[..NAME_LEN]
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(NAME_LEN);
+        let mut profile = Self::new(Name::new(core::str::from_utf8(&raw[..name_len]).ok()?)?);
+        let flags = raw[12];
+        profile.breaks = (flags & HAS_BREAK != 0).then_some((raw[13], word(16)));
+        profile.limits = Limits {
+            max_force: (flags & HAS_MAX_FORCE != 0).then_some(word(20)),
+            max_duration_s: (flags & HAS_MAX_DURATION != 0).then_some(word(24)),
+        };
+        profile.clamp = (flags & HAS_CLAMP != 0).then_some(raw[14]);
+        profile.end_output =
+            (flags & HAS_END_OUTPUT != 0).then_some((raw[15] & 0x7F, raw[15] & 0x80 != 0));
+        Some(profile)
+    }
+
+    fn encode_ext(&self) -> [u8; RECORD_LEN] {
+        let mut out = [0; RECORD_LEN];
+        out[0] = self.preload.is_some() as u8 * HAS_PRELOAD;
+        out[4..8].copy_from_slice(&self.preload.unwrap_or(0).to_le_bytes());
+        let crc = crc32(&out[..28]);
+        out[28..].copy_from_slice(&crc.to_le_bytes());
+        out
+    }
+
+    /// Take the extension's settings; an erased or damaged one leaves them
+    /// unset.
+    fn decode_ext(&mut self, raw: &[u8]) {
+        let word = |i: usize| u32::from_le_bytes([raw[i], raw[i + 1], raw[i + 2], raw[i + 3]]);
+        if crc32(&raw[..28]) != word(28) {
+            return;
+        }
+        self.preload = (raw[0] & HAS_PRELOAD != 0).then_some(word(4));
+    }
+}
+
+/// No room for another profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Full;
+
+/// The stored profiles, looked up by name without regard to case.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Profiles {
+    slots: [Option<Profile>; MAX_PROFILES],
+}
+
+impl Profiles {
+    pub const fn new() -> Self {
+        Self {
+            slots: [None; MAX_PROFILES],
+        }
+    }
+
+    pub fn get(&self, name: &Name) -> Option<&Profile> {
+        self.iter().find(|p| p.name.matches(name))
+    }
+
+    /// Add `profile`, or replace the one of the same name.
+    pub fn set(&mut self, profile: Profile) -> Result<(), Full> {
+        let same = |p: &Option<Profile>| p.is_some_and(|p| p.name.matches(&profile.name));
+        let slot = match self.slots.iter().position(same) {
+            Some(i) => &mut self.slots[i],
+            None => self.slots.iter_mut().find(|p| p.is_none()).ok_or(Full)?,
+        };
+        *slot = Some(profile);
+        Ok(())
+    }
+
+    /// False if there was no such profile.
+    pub fn remove(&mut self, name: &Name) -> bool {
+        let found = self
+            .slots
+            .iter_mut()
+            .find(|p| p.is_some_and(|p| p.name.matches(name)));
+        found.map(|p| p.take()).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Profile> {
+        self.slots.iter().flatten()
+    }
+
+    /// Erased slots are left as 0xFF so the table programs onto a blank page.
+    pub fn encode(&self) -> [u8; TABLE_LEN] {
+        let mut out = [0xFF; TABLE_LEN];
+        let (records, exts) = out.split_at_mut(EXT_AT);
+        let records = records.chunks_exact_mut(RECORD_LEN);
+        for ((slot, record), ext) in self
+            .slots
+            .iter()
+            .zip(records)
+            .zip(exts.chunks_exact_mut(RECORD_LEN))
+        {
+            if let Some(profile) = slot {
+                record.copy_from_slice(&profile.encode());
+                ext.copy_from_slice(&profile.encode_ext());
+            }
+        }
+        out
+    }
+
+    pub fn decode(raw: &[u8; TABLE_LEN]) -> Self {
+        let mut profiles = Self::new();
+        let (records, exts) = raw.split_at(EXT_AT);
+        let records = records.chunks_exact(RECORD_LEN);
+        for ((slot, record), ext) in profiles
+            .slots
+            .iter_mut()
+            .zip(records)
+            .zip(exts.chunks_exact(RECORD_LEN))
+        {
+            *slot = record.try_into().ok().and_then(Profile::decode);
+            if let Some(profile) = slot {
+                profile.decode_ext(ext);
+            }
+        }
+        profiles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(name: &str) -> Name {
+        Name::new(name).unwrap()
+    }
+
+    fn pull() -> Profile {
+        let mut p = Profile::new(name("pull-50"));
+        p.breaks = Some((40, 500));
+        p.limits.max_duration_s = Some(600);
+        p.clamp = Some(75);
+        p.end_output = Some((2, true));
+        p.preload = Some(250);
+        p
+    }
+
+    #[test]
+    fn names_are_checked() {
+        assert!(Name::new("").is_none());
+        assert!(Name::new("way_too_long_name").is_none());
+        assert!(Name::new("a b").is_none());
+        assert_eq!(name("Peel_90").as_str(), "Peel_90");
+    }
+
+    #[test]
+    fn table_round_trips_and_skips_damage() {
+        let mut profiles = Profiles::new();
+        profiles.set(pull()).unwrap();
+        profiles.set(Profile::new(name("creep"))).unwrap();
+        let mut raw = profiles.encode();
+        let decoded = Profiles::decode(&raw);
+        assert_eq!(decoded.get(&name("PULL-50")), Some(&pull()));
+        assert_eq!(decoded.iter().count(), 2);
+        raw[RECORD_LEN + 3] ^= 1;
+        assert_eq!(Profiles::decode(&raw).iter().count(), 1);
+        assert_eq!(Profiles::decode(&[0xFF; TABLE_LEN]).iter().count(), 0);
+    }
+
+    #[test]
+    fn tables_without_extensions_still_load() {
+        let mut profiles = Profiles::new();
+        profiles.set(pull()).unwrap();
+        let mut raw = profiles.encode();
+        raw[EXT_AT..].fill(0xFF);
+        let old = Profiles::decode(&raw);
+        let p = old.get(&name("pull-50")).unwrap();
+        assert_eq!(p.preload, None);
+        assert_eq!(p.clamp, Some(75));
+    }
+
+    #[test]
+    fn set_replaces_by_name_until_full() {
+        let mut profiles = Profiles::new();
+        profiles.set(pull()).unwrap();
+        profiles.set(Profile::new(name("PULL-50"))).unwrap();
+        assert_eq!(profiles.iter().count(), 1);
+        assert_eq!(profiles.get(&name("pull-50")).unwrap().breaks, None);
+        for other in ["a", "b", "c", "d", "e", "f", "g"] {
+            profiles.set(Profile::new(name(other))).unwrap();
+        }
+        assert_eq!(profiles.set(Profile::new(name("h"))), Err(Full));
+        assert!(profiles.remove(&name("A")));
+        assert!(!profiles.remove(&name("A")));
+        assert!(profiles.set(Profile::new(name("h"))).is_ok());
+    }
+
+    #[test]
+    fn limits_stop_on_force_or_time() {
+        let limits = Limits {
+            max_force: Some(1_000),
+            max_duration_s: Some(60),
+        };
+        assert_eq!(limits.exceeded(999, 59), None);
+        assert_eq!(limits.exceeded(-1_000, 0), Some("force_limit"));
+        assert_eq!(limits.exceeded(0, 60), Some("duration"));
+        assert_eq!(Limits::NONE.exceeded(i32::MIN, u64::MAX), None);
+    }
+}
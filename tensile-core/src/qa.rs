@@ -0,0 +1,241 @@
+//! Lightweight health checks recorded periodically during long tests.
+
+use crate::math::{isqrt, log2_tenths};
+use crate::stats::RunningStats;
+
+/// Compares the local timer against USB start-of-frame numbers, which the
+/// host sends every 1 ms from its own crystal.
+///
+/// The 11-bit frame counter wraps every 2.048 s, so `update` must be called
+/// more often than that. A stalled counter (suspend, unplug) restarts the
+/// measurement.
+#[derive(Default)]
+pub struct FrameDrift {
+    start_us: Option<u64>,
+    last_us: u64,
+    last_frame: u16,
+    frames: u64,
+}
+
+impl FrameDrift {
+    const FRAME_MASK: u16 = 0x7FF;
+
+    /// Feed the current timer value and the SOF frame number.
+    pub fn update(&mut self, now_us: u64, frame: u16) {
+        let frame = frame & Self::FRAME_MASK;
+        match self.start_us {
+            None => {
+                self.start_us = Some(now_us);
+                self.frames = 0;
+                self.last_us = now_us;
+            }
+            Some(_) if frame == self.last_frame => {
+                if now_us - self.last_us > 2_000 {
+                    self.start_us = None;
+                }
+            }
+            Some(_) => {
+                self.frames += (frame.wrapping_sub(self.last_frame) & Self::FRAME_MASK) as u64;
+                self.last_us = now_us;
+            }
+        }
+        self.last_frame = frame;
+    }
+
+    /// Timer rate relative to the host frame clock in parts per million,
+    /// positive when the local timer runs fast. `None` until at least a
+    /// second of frames has been seen.
+    pub fn drift_ppm(&self) -> Option<i32> {
+        let start = self.start_us?;
+        if self.frames < 1_000 {
+            return None;
+        }
+        let local_us = (self.last_us - start) as i64;
+        let host_us = self.frames as i64 * 1_000;
+        Some(((local_us - host_us) * 1_000_000 / host_us) as i32)
+    }
+
+    pub fn restart(&mut self) {
+        self.start_us = None;
+    }
+}
+
+/// Sensor noise estimated from successive differences, which ignores slow
+/// trends such as the loading ramp itself: σ ≈ rms(Δx) / √2.
+#[derive(Default)]
+pub struct DiffNoise {
+    prev: Option<i32>,
+    sum_sq: u64,
+    count: u32,
+}
+
+impl DiffNoise {
+    pub fn push(&mut self, sample: i32) {
+        if let Some(prev) = self.prev {
+            let d = sample.abs_diff(prev) as u64;
+            self.sum_sq = self.sum_sq.saturating_add(d * d);
+            self.count += 1;
+        }
+        self.prev = Some(sample);
+    }
+
+    /// Estimated standard deviation in counts, `None` before two samples.
+    pub fn sigma(&self) -> Option<u32> {
+        (self.count > 0).then(|| isqrt(self.sum_sq / (2 * self.count as u64)) as u32)
+    }
+
+    /// Start a new interval, keeping the last sample as the reference.
+    pub fn reset(&mut self) {
+        self.sum_sq = 0;
+        self.count = 0;
+    }
+}
+
+/// Bits every converter fitted delivers.
+pub const CONVERTER_BITS: u32 = 24;
+
+/// What `NOISE?` found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoiseReport {
+    pub samples: u32,
+    /// Population standard deviation, in counts.
+    pub sigma: u32,
+    pub peak_to_peak: u32,
+}
+
+impl NoiseReport {
+    /// Effective resolution in tenths of a bit: the converter's full scale
+    /// over σ.
+    pub fn effective_bits(&self) -> u32 {
+        (CONVERTER_BITS * 10).saturating_sub(log2_tenths(self.sigma))
+    }
+
+    /// Noise-free resolution in tenths of a bit: full scale over the
+    /// peak-to-peak.
+    pub fn noise_free_bits(&self) -> u32 {
+        (CONVERTER_BITS * 10).saturating_sub(log2_tenths(self.peak_to_peak))
+    }
+}
+
+/// Collects a run of idle readings to characterise the front end, e.g.
+/// after rewiring it or changing the excitation supply.
+pub struct NoiseCheck {
+    stats: RunningStats,
+    left: u32,
+}
+
+impl NoiseCheck {
+    /// Over the next `samples` readings, at least two.
+    pub fn new(samples: u32) -> Self {
+        Self {
+            stats: RunningStats::new(),
+            left: samples.max(2),
+        }
+    }
+
+    /// Feed a tared, unfiltered reading. Returns the report with the last.
+    pub fn push(&mut self, value: i32) -> Option<NoiseReport> {
+        if self.left == 0 {
+            return None;
+        }
+        self.stats.push(value);
+        self.left -= 1;
+        if self.left > 0 {
+            return None;
+        }
+        let s = self.stats.summary()?;
+        Some(NoiseReport {
+            samples: s.count,
+            sigma: s.sigma,
+            peak_to_peak: s.max.abs_diff(s.min),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(drift: &mut FrameDrift, seconds: u64, us_per_ms: u64) {
+        // Poll every 100 host frames.
+        for step in 1..=seconds * 10 {
+            let frame = (step * 100) as u16;
+            drift.update(step * 100 * us_per_ms, frame);
+        }
+    }
+
+    #[test]
+    fn drift_is_zero_for_matched_clocks() {
+        let mut drift = FrameDrift::default();
+        drift.update(0, 0);
+        run(&mut drift, 10, 1_000);
+        assert_eq!(drift.drift_ppm(), Some(0));
+    }
+
+    #[test]
+    fn drift_detects_fast_timer_across_wraps() {
+        let mut drift = FrameDrift::default();
+        drift.update(0, 0);
+        // Local timer counts 1001 µs per host millisecond: +1000 ppm.
+        run(&mut drift, 10, 1_001);
+        assert_eq!(drift.drift_ppm(), Some(1_000));
+    }
+
+    #[test]
+    fn drift_needs_a_second_of_frames() {
+        let mut drift = FrameDrift::default();
+        drift.update(0, 0);
+        drift.update(500_000, 500);
+        assert_eq!(drift.drift_ppm(), None);
+    }
+
+    #[test]
+    fn stalled_frames_restart_measurement() {
+        let mut drift = FrameDrift::default();
+        drift.update(0, 0);
+        run(&mut drift, 2, 1_000);
+        drift.update(2_100_000, 2000 & 0x7FF);
+        drift.update(2_200_000, 2000 & 0x7FF);
+        assert_eq!(drift.drift_ppm(), None);
+    }
+
+    #[test]
+    fn ramp_only_contributes_its_step() {
+        let mut noise = DiffNoise::default();
+        for i in 0..100 {
+            noise.push(1000 + i * 10);
+        }
+        // Every difference is the 10-count step regardless of the offset.
+        assert_eq!(noise.sigma(), Some(7));
+    }
+
+    #[test]
+    fn noise_of_alternating_signal() {
+        let mut noise = DiffNoise::default();
+        assert_eq!(noise.sigma(), None);
+        for i in 0..100 {
+            noise.push(if i % 2 == 0 { 20 } else { -20 });
+        }
+        // |Δ| = 40 → σ ≈ 28.
+        assert_eq!(noise.sigma(), Some(28));
+        noise.reset();
+        assert_eq!(noise.sigma(), None);
+    }
+
+    #[test]
+    fn noise_check_reports_once() {
+        let mut check = NoiseCheck::new(4);
+        assert_eq!(check.push(-20), None);
+        assert_eq!(check.push(20), None);
+        assert_eq!(check.push(-20), None);
+        let report = check.push(20).unwrap();
+        assert_eq!(
+            (report.samples, report.sigma, report.peak_to_peak),
+            (4, 20, 40)
+        );
+        // 2²⁴ / 20 is 2^19.7; 2²⁴ / 40 is 2^18.7.
+        assert_eq!(report.effective_bits(), 197);
+        assert_eq!(report.noise_free_bits(), 187);
+        assert_eq!(check.push(0), None);
+    }
+}
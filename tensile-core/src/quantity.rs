@@ -0,0 +1,30 @@
+//! Fixed-point newtypes for the quantities passed between modules.
+//!
+//! They exist so that handing counts to something expecting newtons, or a
+//! duration to something expecting a timestamp, fails to compile. Each wraps
+//! the integer the rest of the crate already used; `.0` gets it back at the
+//! edges (formatting, hardware registers).
+
+/// Tared load-cell converter output.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Counts(pub i32);
+
+/// Rate of change of `Counts`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CountsPerSecond(pub i32);
+
+/// Force in µN.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MicroNewtons(pub i64);
+
+/// Mass in mg, e.g. of a reference weight.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Milligrams(pub u32);
+
+/// Timestamp in µs since boot.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Micros(pub u64);
+
+/// Temperature in m°C.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MilliCelsius(pub i32);
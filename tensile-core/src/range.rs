@@ -0,0 +1,143 @@
+//! Auto-ranging across the HX711's two inputs: a small, sensitive cell on
+//! B for light loads and a large one on A for the rest.
+
+use crate::quantity::Counts;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Input {
+    /// Gain 128.
+    A,
+    /// Gain 32.
+    B,
+    /// A at gain 64, only ever for the gain check.
+    A64,
+}
+
+impl Input {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Input::A => "a",
+            Input::B => "b",
+            Input::A64 => "a64",
+        }
+    }
+}
+
+/// Picks the input for the load and rescales B's readings into A's counts,
+/// so the rest of the chain sees one cell calibrated with A's `CAL` factor.
+///
+/// Moves up to A once the load reaches `switch` counts, and back down to B
+/// once it falls below `switch - hysteresis`, so a load held near the
+/// switch point does not flip between them.
+#[derive(Debug, Clone, Copy)]
+pub struct AutoRange {
+    a_per_kg: i32,
+    b_per_kg: i32,
+    switch: u32,
+    hysteresis: u32,
+    active: Input,
+}
+
+impl AutoRange {
+    /// `a_per_kg` and `b_per_kg` are the two inputs' `CAL` factors; the
+    /// levels are in A's counts. Starts on B. Returns `None` if either
+    /// factor is zero or the hysteresis is not below the switch point.
+    pub fn new(a_per_kg: i32, b_per_kg: i32, switch: u32, hysteresis: u32) -> Option<Self> {
+        if a_per_kg == 0 || b_per_kg == 0 || hysteresis >= switch {
+            return None;
+        }
+        Some(Self {
+            a_per_kg,
+            b_per_kg,
+            switch,
+            hysteresis,
+            active: Input::B,
+        })
+    }
+
+    /// A was recalibrated; a zero factor is ignored.
+    pub fn set_a_per_kg(&mut self, a_per_kg: i32) {
+        if a_per_kg != 0 {
+            self.a_per_kg = a_per_kg;
+        }
+    }
+
+    pub fn b_per_kg(&self) -> i32 {
+        self.b_per_kg
+    }
+
+    pub fn switch(&self) -> u32 {
+        self.switch
+    }
+
+    pub fn hysteresis(&self) -> u32 {
+        self.hysteresis
+    }
+
+    /// The input last asked for.
+    pub fn active(&self) -> Input {
+        self.active
+    }
+
+    /// B's tared counts as A's.
+    pub fn to_a(&self, b: Counts) -> Counts {
+        Counts(rescale(b.0, self.a_per_kg, self.b_per_kg))
+    }
+
+    /// A's counts as B's.
+    pub fn to_b(&self, a: Counts) -> Counts {
+        Counts(rescale(a.0, self.b_per_kg, self.a_per_kg))
+    }
+
+    /// Feed a tared reading in A's counts, either direction. Returns the
+    /// input to switch to.
+    pub fn push(&mut self, load: Counts) -> Option<Input> {
+        let load = load.0.unsigned_abs();
+        let next = match self.active {
+            Input::B if load >= self.switch => Input::A,
+            Input::A if load < self.switch - self.hysteresis => Input::B,
+            _ => return None,
+        };
+        self.active = next;
+        Some(next)
+    }
+}
+
+fn rescale(counts: i32, to_per_kg: i32, from_per_kg: i32) -> i32 {
+    let scaled = counts as i64 * to_per_kg as i64 / from_per_kg as i64;
+    scaled.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switches_with_hysteresis() {
+        let mut range = AutoRange::new(1_000, 20_000, 4_500, 500).unwrap();
+        assert_eq!(range.active(), Input::B);
+        assert_eq!(range.push(Counts(4_499)), None);
+        assert_eq!(range.push(Counts(-4_500)), Some(Input::A));
+        // Between the two levels it stays where it is.
+        assert_eq!(range.push(Counts(4_000)), None);
+        assert_eq!(range.push(Counts(3_999)), Some(Input::B));
+        assert_eq!(range.push(Counts(4_000)), None);
+        assert_eq!(range.active(), Input::B);
+    }
+
+    #[test]
+    fn rescales_between_inputs() {
+        let range = AutoRange::new(1_000, 20_000, 4_500, 500).unwrap();
+        // 1 kg either way.
+        assert_eq!(range.to_a(Counts(20_000)), Counts(1_000));
+        assert_eq!(range.to_b(Counts(-1_000)), Counts(-20_000));
+        assert_eq!(range.to_b(Counts(i32::MAX)), Counts(i32::MAX));
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        assert!(AutoRange::new(0, 20_000, 4_500, 500).is_none());
+        assert!(AutoRange::new(1_000, 0, 4_500, 500).is_none());
+        assert!(AutoRange::new(1_000, 20_000, 500, 500).is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! Loading rate (dF/dt) from timestamped force samples.
+
+use crate::quantity::{Counts, CountsPerSecond, Micros};
+
+/// Slope between the newest sample and the one `window` samples earlier.
+///
+/// Differencing across the window rather than adjacent samples trades a
+/// little lag for much less noise amplification.
+pub struct Derivative {
+    buf: [(Counts, Micros); Derivative::MAX_WINDOW + 1],
+    window: usize,
+    pos: usize,
+    filled: usize,
+}
+
+impl Derivative {
+    pub const MAX_WINDOW: usize = 32;
+
+    /// `window` is clamped to `1..=MAX_WINDOW`.
+    pub fn new(window: usize) -> Self {
+        Self {
+            buf: [(Counts(0), Micros(0)); Self::MAX_WINDOW + 1],
+            window: window.clamp(1, Self::MAX_WINDOW),
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Add a sample and return the rate once the window has filled.
+    /// Returns `None` if the timestamps don't advance.
+    pub fn push(&mut self, value: Counts, t: Micros) -> Option<CountsPerSecond> {
+        let len = self.window + 1;
+        self.buf[self.pos] = (value, t);
+        self.pos = (self.pos + 1) % len;
+        self.filled = (self.filled + 1).min(len);
+        if self.filled < len {
+            return None;
+        }
+        // `pos` now points at the oldest sample.
+        let (old, t_old) = self.buf[self.pos];
+        let dt = t.0.checked_sub(t_old.0).filter(|&dt| dt > 0)? as i64;
+        let rate = (value.0 as i64 - old.0 as i64) * 1_000_000 / dt;
+        Some(CountsPerSecond(
+            rate.clamp(i32::MIN as i64, i32::MAX as i64) as i32,
+        ))
+    }
+
+    pub fn reset(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_window_then_reports_slope() {
+        let mut rate = Derivative::new(2);
+        assert_eq!(rate.push(Counts(0), Micros(0)), None);
+        assert_eq!(rate.push(Counts(10), Micros(100_000)), None);
+        // 20 counts over 0.2 s.
+        assert_eq!(
+            rate.push(Counts(20), Micros(200_000)),
+            Some(CountsPerSecond(100))
+        );
+        assert_eq!(
+            rate.push(Counts(20), Micros(300_000)),
+            Some(CountsPerSecond(50))
+        );
+    }
+
+    #[test]
+    fn negative_rate_when_unloading() {
+        let mut rate = Derivative::new(1);
+        rate.push(Counts(1000), Micros(0));
+        assert_eq!(
+            rate.push(Counts(500), Micros(500_000)),
+            Some(CountsPerSecond(-1000))
+        );
+    }
+
+    #[test]
+    fn stalled_clock_gives_none() {
+        let mut rate = Derivative::new(1);
+        rate.push(Counts(0), Micros(7));
+        assert_eq!(rate.push(Counts(5), Micros(7)), None);
+    }
+
+    #[test]
+    fn reset_restarts_warm_up() {
+        let mut rate = Derivative::new(1);
+        rate.push(Counts(0), Micros(0));
+        rate.push(Counts(1), Micros(1));
+        rate.reset();
+        assert_eq!(rate.push(Counts(2), Micros(2)), None);
+    }
+}
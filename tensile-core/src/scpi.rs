@@ -0,0 +1,189 @@
+//! SCPI header matching and the instrument error queue.
+
+/// Whether one header node names `mnemonic`.
+///
+/// Mnemonics are written the SCPI way, short form in upper case and the
+/// rest in lower case (`MEASure`); either form matches, in any case.
+pub fn node_matches(mnemonic: &str, node: &str) -> bool {
+    let short = mnemonic.len()
+        - mnemonic
+            .bytes()
+            .rev()
+            .take_while(|b| b.is_ascii_lowercase())
+            .count();
+    node.eq_ignore_ascii_case(mnemonic) || node.eq_ignore_ascii_case(&mnemonic[..short])
+}
+
+/// Whether a received header such as `meas:forc?` matches a pattern such as
+/// `MEASure[:SCALar]:FORCe?`. Bracketed nodes are optional, a leading colon
+/// on the header is ignored, and a query only matches a query pattern.
+pub fn header_matches(pattern: &str, header: &str) -> bool {
+    let header = header.strip_prefix(':').unwrap_or(header);
+    let (pattern, query) = match pattern.strip_suffix('?') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let header = match header.strip_suffix('?') {
+        Some(header) if query => header,
+        None if !query => header,
+        _ => return false,
+    };
+    nodes_match(pattern.split(':'), header.split(':'))
+}
+
+fn nodes_match<'a>(
+    mut pattern: impl Iterator<Item = &'a str> + Clone,
+    header: impl Iterator<Item = &'a str> + Clone,
+) -> bool {
+    let Some(token) = pattern.next() else {
+        return header.clone().next().is_none();
+    };
+    // `ERRor[:NEXT]` splits into `ERRor[` and `NEXT]`.
+    let optional = token.ends_with(']');
+    let mnemonic = token.trim_matches(['[', ']']);
+    let mut rest = header.clone();
+    if rest.next().is_some_and(|node| node_matches(mnemonic, node))
+        && nodes_match(pattern.clone(), rest)
+    {
+        return true;
+    }
+    optional && nodes_match(pattern, header)
+}
+
+/// Standard SCPI error codes this instrument reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    UndefinedHeader,
+    InitIgnored,
+    SettingsConflict,
+    IllegalParameterValue,
+    DataStale,
+    HardwareError,
+    QueueOverflow,
+}
+
+impl Error {
+    pub fn code(self) -> i16 {
+        match self {
+            Error::UndefinedHeader => -113,
+            Error::InitIgnored => -213,
+            Error::SettingsConflict => -221,
+            Error::IllegalParameterValue => -224,
+            Error::DataStale => -230,
+            Error::HardwareError => -240,
+            Error::QueueOverflow => -350,
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Error::UndefinedHeader => "Undefined header",
+            Error::InitIgnored => "Init ignored",
+            Error::SettingsConflict => "Settings conflict",
+            Error::IllegalParameterValue => "Illegal parameter value",
+            Error::DataStale => "Data corrupt or stale",
+            Error::HardwareError => "Hardware error",
+            Error::QueueOverflow => "Queue overflow",
+        }
+    }
+}
+
+/// First-in first-out error queue, read by `SYSTem:ERRor?`.
+///
+/// As SCPI requires, an error arriving at a full queue replaces the newest
+/// entry with `QueueOverflow`, so the oldest errors survive.
+#[derive(Debug, Clone)]
+pub struct ErrorQueue<const N: usize> {
+    entries: [Error; N],
+    start: usize,
+    len: usize,
+}
+
+impl<const N: usize> Default for ErrorQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ErrorQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            entries: [Error::QueueOverflow; N],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, error: Error) {
+        if N == 0 {
+            return;
+        }
+        if self.len == N {
+            self.entries[(self.start + N - 1) % N] = Error::QueueOverflow;
+        } else {
+            self.entries[(self.start + self.len) % N] = error;
+            self.len += 1;
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Error> {
+        if self.len == 0 {
+            return None;
+        }
+        let error = self.entries[self.start];
+        self.start = (self.start + 1) % N;
+        self.len -= 1;
+        Some(error)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_and_long_forms_match() {
+        assert!(node_matches("MEASure", "MEAS"));
+        assert!(node_matches("MEASure", "measure"));
+        assert!(!node_matches("MEASure", "MEASU"));
+        assert!(node_matches("*IDN", "*idn"));
+    }
+
+    #[test]
+    fn optional_nodes_and_queries() {
+        let pattern = "SYSTem:ERRor[:NEXT]?";
+        assert!(header_matches(pattern, "SYST:ERR?"));
+        assert!(header_matches(pattern, ":system:error:next?"));
+        assert!(!header_matches(pattern, "SYST:ERR"));
+        assert!(!header_matches(pattern, "SYST:ERR:COUN?"));
+        assert!(header_matches("[SENSe]:TARE", "TARE"));
+        assert!(!header_matches("INITiate", "INIT?"));
+    }
+
+    #[test]
+    fn full_queue_keeps_oldest_and_flags_overflow() {
+        let mut queue = ErrorQueue::<3>::new();
+        queue.push(Error::UndefinedHeader);
+        queue.push(Error::IllegalParameterValue);
+        queue.push(Error::InitIgnored);
+        queue.push(Error::DataStale);
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop(), Some(Error::UndefinedHeader));
+        assert_eq!(queue.pop(), Some(Error::IllegalParameterValue));
+        assert_eq!(queue.pop(), Some(Error::QueueOverflow));
+        assert_eq!(queue.pop(), None);
+    }
+}
@@ -0,0 +1,347 @@
+//! The phases a test goes through, from being armed to its end, and which
+//! moves between them are allowed.
+//!
+//! Idle → Armed → Preload → Running ⇄ Holding → Stopping → Idle. A start
+//! without a preload goes straight to Running, an armed one can be
+//! cancelled, and a sensor failure drops any phase into Fault until the
+//! sensor recovers.
+
+use crate::quantity::Micros;
+
+/// A held test may only resume if the force moved by less than this many
+/// counts, or `RESUME_DRIFT_PERCENT` of the force at the hold if larger.
+pub const RESUME_DRIFT_COUNTS: i32 = 500;
+pub const RESUME_DRIFT_PERCENT: i32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Idle,
+    /// Waiting for the start time.
+    Armed,
+    /// Started, but the load has not yet reached the preload.
+    Preload,
+    Running,
+    /// Logging is held for the operator.
+    Holding,
+    /// Stopped; how it went is still to be recorded.
+    Stopping,
+    /// The sensor failed. No test starts until it recovers.
+    Fault,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Idle => "idle",
+            Phase::Armed => "armed",
+            Phase::Preload => "preload",
+            Phase::Running => "running",
+            Phase::Holding => "holding",
+            Phase::Stopping => "stopping",
+            Phase::Fault => "fault",
+        }
+    }
+}
+
+/// Why and when a test stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ending {
+    pub reason: &'static str,
+    pub t: Micros,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeError {
+    NotHolding,
+    /// The force moved further than allowed while held.
+    Drifted {
+        drift: u32,
+        limit: u32,
+    },
+}
+
+pub struct Sequencer {
+    phase: Phase,
+    start_at: Micros,
+    preload: Option<i32>,
+    /// Stands in for `preload` for the next test only.
+    next_preload: Option<i32>,
+    /// The running test's preload.
+    threshold: i32,
+    held: i32,
+    ending: Option<Ending>,
+}
+
+impl Sequencer {
+    pub const fn new() -> Self {
+        Self {
+            phase: Phase::Idle,
+            start_at: Micros(0),
+            preload: None,
+            next_preload: None,
+            threshold: 0,
+            held: 0,
+            ending: None,
+        }
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// A test has started and not yet been recorded as stopped.
+    pub fn testing(&self) -> bool {
+        matches!(
+            self.phase,
+            Phase::Preload | Phase::Running | Phase::Holding | Phase::Stopping
+        )
+    }
+
+    /// Load, in counts the way the test loads the specimen, a test must
+    /// reach before it counts as running; `None` runs from the start.
+    pub fn preload(&self) -> Option<i32> {
+        self.preload
+    }
+
+    pub fn set_preload(&mut self, preload: Option<i32>) {
+        self.preload = preload;
+    }
+
+    /// Use `preload` for the next test only, as a profile does.
+    pub fn preload_next(&mut self, preload: i32) {
+        self.next_preload = Some(preload);
+    }
+
+    /// When the armed test starts.
+    pub fn start_at(&self) -> Option<Micros> {
+        (self.phase == Phase::Armed).then_some(self.start_at)
+    }
+
+    /// The force when the test was held.
+    pub fn held(&self) -> Option<i32> {
+        (self.phase == Phase::Holding).then_some(self.held)
+    }
+
+    /// Start a test at `at`, replacing any earlier start time. False if a
+    /// test is on or the sensor has failed.
+    pub fn arm(&mut self, at: Micros) -> bool {
+        if !matches!(self.phase, Phase::Idle | Phase::Armed) {
+            return false;
+        }
+        self.phase = Phase::Armed;
+        self.start_at = at;
+        true
+    }
+
+    /// Drop an armed start. False if none was armed.
+    pub fn cancel(&mut self) -> bool {
+        self.next_preload = None;
+        let armed = self.phase == Phase::Armed;
+        if armed {
+            self.phase = Phase::Idle;
+        }
+        armed
+    }
+
+    /// Start the armed test once its time has come. True as it starts.
+    pub fn poll(&mut self, now: Micros) -> bool {
+        if self.phase != Phase::Armed || now < self.start_at {
+            return false;
+        }
+        self.phase = match self.next_preload.take().or(self.preload) {
+            Some(preload) => {
+                self.threshold = preload;
+                Phase::Preload
+            }
+            None => Phase::Running,
+        };
+        true
+    }
+
+    /// Feed a reading as load on the specimen. True as it reaches the
+    /// preload.
+    pub fn push(&mut self, load: i32) -> bool {
+        let reached = self.phase == Phase::Preload && load >= self.threshold;
+        if reached {
+            self.phase = Phase::Running;
+        }
+        reached
+    }
+
+    /// Hold a running test at `force`. False if none is running.
+    pub fn hold(&mut self, force: i32) -> bool {
+        let running = self.phase == Phase::Running;
+        if running {
+            self.phase = Phase::Holding;
+            self.held = force;
+        }
+        running
+    }
+
+    /// Carry on with a held test, unless the specimen relaxed or the load
+    /// crept to `force` while it was held.
+    pub fn resume(&mut self, force: i32) -> Result<(), ResumeError> {
+        if self.phase != Phase::Holding {
+            return Err(ResumeError::NotHolding);
+        }
+        let drift = force.abs_diff(self.held);
+        let limit = RESUME_DRIFT_COUNTS.max(self.held.abs() / 100 * RESUME_DRIFT_PERCENT) as u32;
+        if drift > limit {
+            return Err(ResumeError::Drifted { drift, limit });
+        }
+        self.phase = Phase::Running;
+        Ok(())
+    }
+
+    /// Stop the test. False if none was on.
+    pub fn stop(&mut self, reason: &'static str, t: Micros) -> bool {
+        let on = matches!(self.phase, Phase::Preload | Phase::Running | Phase::Holding);
+        if on {
+            self.phase = Phase::Stopping;
+            self.ending = Some(Ending { reason, t });
+        }
+        on
+    }
+
+    /// The sensor failed: stop any test for `reason` and refuse new ones
+    /// until [`recover`](Self::recover). Returns the phase it left.
+    pub fn fault(&mut self, reason: &'static str, t: Micros) -> Phase {
+        let left = self.phase;
+        self.stop(reason, t);
+        self.next_preload = None;
+        self.phase = Phase::Fault;
+        left
+    }
+
+    /// The sensor is back.
+    pub fn recover(&mut self) {
+        if self.phase == Phase::Fault {
+            self.phase = Phase::Idle;
+        }
+    }
+
+    /// Take a stopped test's ending to record it, leaving `Stopping`.
+    pub fn finish(&mut self) -> Option<Ending> {
+        if self.phase == Phase::Stopping {
+            self.phase = Phase::Idle;
+        }
+        self.ending.take()
+    }
+}
+
+impl Default for Sequencer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_from_armed_to_idle() {
+        let mut seq = Sequencer::new();
+        assert!(seq.arm(Micros(1_000)));
+        assert_eq!(seq.start_at(), Some(Micros(1_000)));
+        assert!(!seq.poll(Micros(999)));
+        assert!(seq.poll(Micros(1_000)));
+        assert_eq!(seq.phase(), Phase::Running);
+        assert!(!seq.arm(Micros(0)));
+
+        assert!(seq.stop("command", Micros(5_000)));
+        assert_eq!(seq.phase(), Phase::Stopping);
+        assert!(seq.testing());
+        assert!(!seq.stop("panel", Micros(6_000)));
+        assert_eq!(
+            seq.finish(),
+            Some(Ending {
+                reason: "command",
+                t: Micros(5_000)
+            })
+        );
+        assert_eq!(seq.phase(), Phase::Idle);
+        assert_eq!(seq.finish(), None);
+    }
+
+    #[test]
+    fn armed_start_can_be_moved_or_cancelled() {
+        let mut seq = Sequencer::new();
+        assert!(!seq.cancel());
+        seq.arm(Micros(10));
+        assert!(seq.arm(Micros(20)));
+        assert!(!seq.poll(Micros(15)));
+        assert!(seq.cancel());
+        assert!(!seq.poll(Micros(25)));
+        assert_eq!(seq.phase(), Phase::Idle);
+        assert!(!seq.stop("command", Micros(30)));
+    }
+
+    #[test]
+    fn preload_holds_off_running() {
+        let mut seq = Sequencer::new();
+        seq.set_preload(Some(200));
+        seq.arm(Micros(0));
+        seq.poll(Micros(0));
+        assert_eq!(seq.phase(), Phase::Preload);
+        assert!(seq.testing());
+        assert!(!seq.hold(150));
+        assert!(!seq.push(199));
+        assert!(seq.push(200));
+        assert_eq!(seq.phase(), Phase::Running);
+        assert!(!seq.push(300));
+
+        // A profile's preload is used once, ahead of the standing one.
+        seq.stop("command", Micros(1));
+        seq.finish();
+        seq.preload_next(50);
+        seq.arm(Micros(0));
+        seq.poll(Micros(0));
+        assert!(seq.push(50));
+        seq.stop("command", Micros(2));
+        seq.finish();
+        seq.arm(Micros(0));
+        seq.poll(Micros(0));
+        assert!(!seq.push(50));
+    }
+
+    #[test]
+    fn resume_refuses_drift() {
+        let mut seq = Sequencer::new();
+        seq.arm(Micros(0));
+        seq.poll(Micros(0));
+        assert_eq!(seq.resume(0), Err(ResumeError::NotHolding));
+        assert!(seq.hold(20_000));
+        assert_eq!(seq.held(), Some(20_000));
+        // 5 % of 20 000 is more than the 500-count floor.
+        assert_eq!(
+            seq.resume(18_999),
+            Err(ResumeError::Drifted {
+                drift: 1_001,
+                limit: 1_000
+            })
+        );
+        assert_eq!(seq.phase(), Phase::Holding);
+        assert_eq!(seq.resume(19_000), Ok(()));
+        assert_eq!(seq.phase(), Phase::Running);
+    }
+
+    #[test]
+    fn fault_stops_the_test_and_blocks_starts() {
+        let mut seq = Sequencer::new();
+        seq.arm(Micros(0));
+        seq.poll(Micros(0));
+        seq.hold(100);
+        assert_eq!(seq.fault("stuck", Micros(7)), Phase::Holding);
+        assert_eq!(seq.phase(), Phase::Fault);
+        assert!(!seq.arm(Micros(8)));
+        assert_eq!(seq.finish().map(|e| e.reason), Some("stuck"));
+        assert_eq!(seq.phase(), Phase::Fault);
+        seq.recover();
+        assert!(seq.arm(Micros(9)));
+
+        // An armed start is dropped, with nothing to record.
+        assert_eq!(seq.fault("overload", Micros(10)), Phase::Armed);
+        assert_eq!(seq.finish(), None);
+    }
+}
@@ -0,0 +1,32 @@
+//! Hobby servo positions for the specimen clamp.
+
+/// Frame period of the standard 50 Hz servo signal, in µs.
+pub const PERIOD_US: u16 = 20_000;
+/// Pulse widths at 0° and `MAX_DEG`, in µs. Most servos take 500–2500.
+pub const MIN_PULSE_US: u16 = 500;
+pub const MAX_PULSE_US: u16 = 2_500;
+pub const MAX_DEG: u8 = 180;
+
+/// Where `CLAMP OPEN` and `CLAMP CLOSE` drive the servo.
+pub const OPEN_DEG: u8 = 0;
+pub const CLOSED_DEG: u8 = 90;
+
+/// Pulse width for `deg`, held to the servo's travel.
+pub fn pulse_us(deg: u8) -> u16 {
+    let span = (MAX_PULSE_US - MIN_PULSE_US) as u32;
+    MIN_PULSE_US + (deg.min(MAX_DEG) as u32 * span / MAX_DEG as u32) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pulse_spans_the_travel() {
+        assert_eq!(pulse_us(OPEN_DEG), 500);
+        assert_eq!(pulse_us(CLOSED_DEG), 1_500);
+        assert_eq!(pulse_us(45), 1_000);
+        assert_eq!(pulse_us(MAX_DEG), 2_500);
+        assert_eq!(pulse_us(255), 2_500);
+    }
+}
@@ -0,0 +1,211 @@
+//! Specimen break and grip-slip detection.
+
+/// Flags the sudden loss of load when a specimen breaks.
+///
+/// Once the load has reached `min_peak`, a fall to `drop_pct` percent below
+/// the largest load seen counts as a break. It fires once per `reset`, so
+/// the unloaded tail after the break stays quiet.
+///
+/// Loads are positive the way the test loads the specimen
+/// (`Loading::load`); anything the other way counts as no load.
+#[derive(Debug, Clone, Copy)]
+pub struct BreakDetector {
+    drop_pct: u32,
+    min_peak: u32,
+    peak: u32,
+    broken: bool,
+}
+
+impl BreakDetector {
+    /// `drop_pct` is clamped to 1–100.
+    pub fn new(drop_pct: u32, min_peak: u32) -> Self {
+        Self {
+            drop_pct: drop_pct.clamp(1, 100),
+            min_peak,
+            peak: 0,
+            broken: false,
+        }
+    }
+
+    pub fn drop_pct(&self) -> u32 {
+        self.drop_pct
+    }
+
+    pub fn min_peak(&self) -> u32 {
+        self.min_peak
+    }
+
+    /// Feed a tared load. Returns the peak load when a break is seen.
+    pub fn push(&mut self, load: i32) -> Option<u32> {
+        if self.broken {
+            return None;
+        }
+        let load = load.max(0) as u32;
+        self.peak = self.peak.max(load);
+        if self.peak < self.min_peak.max(1) {
+            return None;
+        }
+        let threshold = self.peak as u64 * (100 - self.drop_pct) as u64;
+        (load as u64 * 100 <= threshold).then(|| {
+            self.broken = true;
+            self.peak
+        })
+    }
+
+    pub fn reset(&mut self) {
+        self.peak = 0;
+        self.broken = false;
+    }
+}
+
+/// A slip: the load before it and the lowest it fell to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slip {
+    pub from: u32,
+    pub low: u32,
+}
+
+/// Flags a specimen slipping in the grips: the load falls `drop_pct`
+/// percent below its peak, then climbs back at least halfway within
+/// `window` samples. A fall that does not recover is left to the break
+/// detector, and the peak restarts from there.
+///
+/// Loads are taken as for `BreakDetector`.
+#[derive(Debug, Clone, Copy)]
+pub struct SlipDetector {
+    drop_pct: u32,
+    min_peak: u32,
+    window: u32,
+    peak: u32,
+    /// Lowest load and samples since the fall, while watching for recovery.
+    fallen: Option<(u32, u32)>,
+}
+
+impl SlipDetector {
+    /// `drop_pct` is clamped to 1–100.
+    pub fn new(drop_pct: u32, min_peak: u32, window: u32) -> Self {
+        Self {
+            drop_pct: drop_pct.clamp(1, 100),
+            min_peak,
+            window,
+            peak: 0,
+            fallen: None,
+        }
+    }
+
+    pub fn drop_pct(&self) -> u32 {
+        self.drop_pct
+    }
+
+    pub fn min_peak(&self) -> u32 {
+        self.min_peak
+    }
+
+    pub fn window(&self) -> u32 {
+        self.window
+    }
+
+    /// Feed a tared load. Returns the slip once the load has recovered.
+    pub fn push(&mut self, load: i32) -> Option<Slip> {
+        let load = load.max(0) as u32;
+        let below = |peak: u32, pct: u32| load as u64 * 100 <= peak as u64 * (100 - pct) as u64;
+        match self.fallen {
+            None => {
+                self.peak = self.peak.max(load);
+                if self.peak >= self.min_peak.max(1) && below(self.peak, self.drop_pct) {
+                    self.fallen = Some((load, 0));
+                }
+                None
+            }
+            Some((low, samples)) => {
+                let low = low.min(load);
+                if !below(self.peak, self.drop_pct.div_ceil(2)) {
+                    self.fallen = None;
+                    return Some(Slip {
+                        from: self.peak,
+                        low,
+                    });
+                }
+                if samples + 1 >= self.window {
+                    self.fallen = None;
+                    self.peak = load;
+                } else {
+                    self.fallen = Some((low, samples + 1));
+                }
+                None
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.peak = 0;
+        self.fallen = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slip_is_a_fall_that_recovers() {
+        let mut detector = SlipDetector::new(20, 500, 5);
+        for value in [0, 600, 1000, 700, 750] {
+            assert_eq!(detector.push(value), None);
+        }
+        // Back above 900, half of the 20 % fall.
+        assert_eq!(
+            detector.push(950),
+            Some(Slip {
+                from: 1000,
+                low: 700
+            })
+        );
+        assert_eq!(detector.push(1100), None);
+    }
+
+    #[test]
+    fn a_fall_that_stays_down_is_not_a_slip() {
+        let mut detector = SlipDetector::new(20, 500, 3);
+        for value in [1000, 500, 480, 470, 460] {
+            assert_eq!(detector.push(value), None);
+        }
+        // The peak restarted at 460, so climbing on is just loading.
+        assert_eq!(detector.push(1000), None);
+        // Small loads never count.
+        let mut detector = SlipDetector::new(20, 500, 3);
+        for value in [400, 100, 400] {
+            assert_eq!(detector.push(value), None);
+        }
+    }
+
+    #[test]
+    fn fires_once_on_drop_from_peak() {
+        let mut detector = BreakDetector::new(50, 1000);
+        for value in [0, 400, 900, 1500, 2000, 1900, 1001] {
+            assert_eq!(detector.push(value), None);
+        }
+        assert_eq!(detector.push(1000), Some(2000));
+        assert_eq!(detector.push(0), None);
+        detector.reset();
+        assert_eq!(detector.push(0), None);
+    }
+
+    #[test]
+    fn ignores_drops_below_min_peak() {
+        let mut detector = BreakDetector::new(50, 1000);
+        assert_eq!(detector.push(900), None);
+        assert_eq!(detector.push(0), None);
+        assert_eq!(detector.push(1200), None);
+        assert_eq!(detector.push(100), Some(1200));
+    }
+
+    #[test]
+    fn load_the_other_way_is_no_load() {
+        let mut detector = BreakDetector::new(50, 1000);
+        assert_eq!(detector.push(-5000), None);
+        assert_eq!(detector.push(1500), None);
+        // A swing the other way after the peak is still a break.
+        assert_eq!(detector.push(-200), Some(1500));
+    }
+}
@@ -0,0 +1,127 @@
+//! Running statistics over the force stream.
+
+use crate::math::isqrt;
+
+/// Summary of the samples pushed into [`RunningStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub count: u32,
+    pub min: i32,
+    pub max: i32,
+    pub mean: i32,
+    pub rms: u32,
+    /// Population standard deviation.
+    pub sigma: u32,
+}
+
+/// Incremental min/max/mean/RMS/σ in integer counts.
+///
+/// Sums are kept exactly so the result doesn't drift over long tests; the
+/// squares sum needs 128 bits once a test runs past a few hundred thousand
+/// full-scale samples.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RunningStats {
+    count: u32,
+    min: i32,
+    max: i32,
+    sum: i64,
+    sum_sq: u128,
+}
+
+impl RunningStats {
+    pub const fn new() -> Self {
+        Self {
+            count: 0,
+            min: 0,
+            max: 0,
+            sum: 0,
+            sum_sq: 0,
+        }
+    }
+
+    pub fn push(&mut self, value: i32) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count = self.count.saturating_add(1);
+        self.sum += value as i64;
+        self.sum_sq += (value as i64 * value as i64) as u128;
+    }
+
+    /// `None` until the first sample.
+    pub fn summary(&self) -> Option<Summary> {
+        if self.count == 0 {
+            return None;
+        }
+        let n = self.count as i128;
+        let sum = self.sum as i128;
+        let mean = if sum < 0 {
+            (sum - n / 2) / n
+        } else {
+            (sum + n / 2) / n
+        };
+        let mean_sq = self.sum_sq / n as u128;
+        // n²·var = n·Σx² − (Σx)², which is never negative.
+        let var = (self.sum_sq * n as u128 - (sum * sum) as u128) / (n * n) as u128;
+        Some(Summary {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: mean as i32,
+            rms: isqrt(mean_sq as u64) as u32,
+            sigma: isqrt(var as u64) as u32,
+        })
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_has_no_summary() {
+        assert_eq!(RunningStats::new().summary(), None);
+    }
+
+    #[test]
+    fn summarises_known_set() {
+        let mut stats = RunningStats::new();
+        for v in [2, 4, 4, 4, 5, 5, 7, 9] {
+            stats.push(v);
+        }
+        let s = stats.summary().unwrap();
+        assert_eq!((s.count, s.min, s.max, s.mean), (8, 2, 9, 5));
+        assert_eq!(s.sigma, 2);
+        // sqrt(232 / 8) = 5.39
+        assert_eq!(s.rms, 5);
+    }
+
+    #[test]
+    fn negative_values_and_full_scale() {
+        let mut stats = RunningStats::new();
+        for _ in 0..1000 {
+            stats.push(-0x800000);
+            stats.push(0x7FFFFF);
+        }
+        let s = stats.summary().unwrap();
+        assert_eq!((s.min, s.max, s.mean), (-0x800000, 0x7FFFFF, -1));
+        assert_eq!(s.sigma, 0x7FFFFF);
+        assert_eq!(s.rms, 0x7FFFFF);
+    }
+
+    #[test]
+    fn reset_clears() {
+        let mut stats = RunningStats::new();
+        stats.push(10);
+        stats.reset();
+        assert_eq!(stats.summary(), None);
+    }
+}
@@ -0,0 +1,474 @@
+//! The two encodings of a `Force:` sample on the data port: the text line
+//! older host scripts read, and a framed binary form for the CLI.
+//!
+//! A binary frame is the sample's fields, then a CRC-32, COBS-encoded and
+//! wrapped in zero bytes. Text never contains a zero byte, so replies can
+//! still go out as text between frames, and a frame cut short on the wire
+//! is dropped at the next zero. Events are framed too, as their text, so a
+//! host reading frames can tell them from replies without parsing lines.
+//!
+//! A host asks for frames by sending [`HELLO`] before its first command.
+
+use crate::columns::Column;
+use crate::decimal;
+use crate::math::crc32;
+use crate::range::Input;
+use crate::units::Unit;
+
+/// Longest encoded sample, either way.
+pub const LINE_LEN: usize = 256;
+/// Opens and closes every frame; sent by a host that wants frames.
+pub const HELLO: u8 = 0;
+
+/// Fields that fit in a frame; COBS adds a byte per 254 and the CRC and
+/// delimiters the rest.
+const MAX_PAYLOAD: usize = LINE_LEN - 8;
+const CRC_LEN: usize = 4;
+/// The payload's first byte, so later layouts can be told apart.
+const KIND_SAMPLE: u8 = 1;
+const KIND_EVENT: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Ascii,
+    Binary,
+}
+
+impl Protocol {
+    /// What a host speaks, from the first byte it sends.
+    pub fn detect(first: u8) -> Self {
+        if first == HELLO {
+            Protocol::Binary
+        } else {
+            Protocol::Ascii
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Protocol::Ascii => "ascii",
+            Protocol::Binary => "binary",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    /// `value` with `places` implied decimal places; counts have none.
+    Fixed {
+        value: i64,
+        places: u32,
+    },
+    Input(Input),
+    Unit(Unit),
+}
+
+impl Value {
+    pub fn whole(value: i64) -> Self {
+        Value::Fixed { value, places: 0 }
+    }
+
+    /// Thousandths, as the auxiliary values are kept.
+    pub fn milli(value: i64) -> Self {
+        Value::Fixed { value, places: 3 }
+    }
+}
+
+/// Builds one sample, field by field in line order.
+pub trait Encoder {
+    fn begin(&mut self);
+
+    /// `index` numbers the `ch<n>=` and `aux<n>=` fields; other columns
+    /// ignore it.
+    fn field(&mut self, column: Column, index: u8, value: Value);
+
+    /// The sample, ready to send.
+    fn finish(&mut self) -> &[u8];
+}
+
+/// `Force: <force> raw=<counts> t=<us> ...`, ending in CR LF.
+pub struct AsciiEncoder {
+    buf: [u8; LINE_LEN],
+    len: usize,
+}
+
+impl AsciiEncoder {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; LINE_LEN],
+            len: 0,
+        }
+    }
+
+    /// Anything past the end of the line is cut, leaving room for CR LF.
+    fn put(&mut self, s: &str) {
+        let take = s.len().min(LINE_LEN - 2 - self.len);
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+    }
+
+    fn put_number(&mut self, value: i64, places: u32) {
+        let mut buf = [0; decimal::MAX_LEN];
+        self.put(decimal::fixed(value, places, &mut buf));
+    }
+}
+
+impl Default for AsciiEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder for AsciiEncoder {
+    fn begin(&mut self) {
+        self.len = 0;
+        self.put("Force:");
+    }
+
+    fn field(&mut self, column: Column, index: u8, value: Value) {
+        self.put(" ");
+        if column != Column::Force {
+            self.put(column.as_str());
+            if matches!(column, Column::Ch | Column::Aux) {
+                self.put_number(index as i64, 0);
+            }
+            self.put("=");
+        }
+        match value {
+            Value::Fixed { value, places } => self.put_number(value, places),
+            Value::Input(input) => self.put(input.as_str()),
+            Value::Unit(unit) => self.put(unit.as_str()),
+        }
+    }
+
+    fn finish(&mut self) -> &[u8] {
+        self.buf[self.len..self.len + 2].copy_from_slice(b"\r\n");
+        &self.buf[..self.len + 2]
+    }
+}
+
+/// Each field is a tag byte, the column in the low nibble and the index in
+/// the high one, then for a number its places and the value as a zigzag
+/// LEB128 varint, for an input 0 (A) or 1 (B), and for a unit its position
+/// in [`Unit::ALL`].
+pub struct BinaryEncoder {
+    payload: [u8; MAX_PAYLOAD],
+    len: usize,
+    out: [u8; LINE_LEN],
+}
+
+impl BinaryEncoder {
+    pub const fn new() -> Self {
+        Self {
+            payload: [0; MAX_PAYLOAD],
+            len: 0,
+            out: [0; LINE_LEN],
+        }
+    }
+}
+
+impl Default for BinaryEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Encoder for BinaryEncoder {
+    fn begin(&mut self) {
+        self.payload[0] = KIND_SAMPLE;
+        self.len = 1;
+    }
+
+    /// A field that does not fit is left out whole.
+    fn field(&mut self, column: Column, index: u8, value: Value) {
+        let mut field = [0; 12];
+        field[0] = column.id() | index << 4;
+        let len = match value {
+            Value::Fixed { value, places } => {
+                field[1] = places as u8;
+                2 + put_varint(&mut field[2..], (value << 1 ^ value >> 63) as u64)
+            }
+            Value::Input(input) => {
+                field[1] = (input == Input::B) as u8;
+                2
+            }
+            Value::Unit(unit) => {
+                field[1] = Unit::ALL.iter().position(|u| *u == unit).unwrap_or(0) as u8;
+                2
+            }
+        };
+        if self.len + len + CRC_LEN <= MAX_PAYLOAD {
+            self.payload[self.len..self.len + len].copy_from_slice(&field[..len]);
+            self.len += len;
+        }
+    }
+
+    fn finish(&mut self) -> &[u8] {
+        seal(&mut self.payload, self.len, &mut self.out)
+    }
+}
+
+/// An event as a frame: the text of its `Event:` line after the prefix,
+/// `NAME key=value ... t=<us>`, without the line end. Text past what a
+/// frame holds is cut.
+pub fn encode_event<'a>(text: &str, out: &'a mut FrameBuf) -> &'a [u8] {
+    let mut payload = [0; MAX_PAYLOAD];
+    payload[0] = KIND_EVENT;
+    let len = text.len().min(MAX_PAYLOAD - 1 - CRC_LEN);
+    payload[1..1 + len].copy_from_slice(&text.as_bytes()[..len]);
+    seal(&mut payload, 1 + len, out)
+}
+
+/// Append the CRC to `payload[..len]` and COBS-encode it between zeros.
+fn seal<'a>(payload: &mut [u8; MAX_PAYLOAD], len: usize, out: &'a mut FrameBuf) -> &'a [u8] {
+    let crc = crc32(&payload[..len]);
+    payload[len..len + CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+    out[0] = HELLO;
+    let n = cobs_encode(&payload[..len + CRC_LEN], &mut out[1..]);
+    out[1 + n] = HELLO;
+    &out[..n + 2]
+}
+
+/// The payload of an intact frame, CRC checked and removed.
+fn open<'a>(encoded: &[u8], scratch: &'a mut FrameBuf) -> Option<&'a [u8]> {
+    let len = cobs_decode(encoded, scratch)?;
+    let (payload, crc) = scratch[..len].split_at(len.checked_sub(CRC_LEN)?);
+    (crc32(payload).to_le_bytes() == crc).then_some(payload)
+}
+
+/// 7 bits a byte, low first; returns the bytes used.
+fn put_varint(out: &mut [u8], mut n: u64) -> usize {
+    let mut i = 0;
+    loop {
+        let byte = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            out[i] = byte;
+            return i + 1;
+        }
+        out[i] = byte | 0x80;
+        i += 1;
+    }
+}
+
+fn take_varint(input: &mut &[u8]) -> Option<u64> {
+    let mut n = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input.split_first()?;
+        *input = rest;
+        n |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(n);
+        }
+    }
+    None
+}
+
+fn cobs_encode(data: &[u8], out: &mut [u8]) -> usize {
+    let mut code_at = 0;
+    let mut len = 1;
+    let mut code = 1u8;
+    for &byte in data {
+        if byte != 0 {
+            out[len] = byte;
+            len += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            out[code_at] = code;
+            code_at = len;
+            len += 1;
+            code = 1;
+        }
+    }
+    out[code_at] = code;
+    len
+}
+
+/// `None` if a zero byte or a code runs past the end.
+fn cobs_decode(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let mut len = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let code = data[i] as usize;
+        if code == 0 || i + code > data.len() || len + code - 1 > out.len() {
+            return None;
+        }
+        out[len..len + code - 1].copy_from_slice(&data[i + 1..i + code]);
+        len += code - 1;
+        i += code;
+        if code < 0xFF && i < data.len() {
+            *out.get_mut(len)? = 0;
+            len += 1;
+        }
+    }
+    Some(len)
+}
+
+/// Scratch space for [`decode_frame`].
+pub type FrameBuf = [u8; LINE_LEN];
+
+/// Decode the bytes between a frame's delimiters. `None` if it is damaged
+/// or not a sample.
+pub fn decode_frame<'a>(encoded: &[u8], scratch: &'a mut FrameBuf) -> Option<Fields<'a>> {
+    match open(encoded, scratch)? {
+        [KIND_SAMPLE, rest @ ..] => Some(Fields { rest }),
+        _ => None,
+    }
+}
+
+/// The text of an event frame, as [`encode_event`] took it. `None` if it is
+/// damaged or not an event.
+pub fn decode_event<'a>(encoded: &[u8], scratch: &'a mut FrameBuf) -> Option<&'a str> {
+    match open(encoded, scratch)? {
+        [KIND_EVENT, text @ ..] => core::str::from_utf8(text).ok(),
+        _ => None,
+    }
+}
+
+/// A decoded frame's fields, in order; stops at the first it cannot read.
+pub struct Fields<'a> {
+    rest: &'a [u8],
+}
+
+impl Iterator for Fields<'_> {
+    type Item = (Column, u8, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&tag, rest) = self.rest.split_first()?;
+        let (&arg, mut rest) = rest.split_first()?;
+        let column = Column::from_id(tag & 0x0F)?;
+        let value = match column {
+            Column::Range => Value::Input(if arg == 0 { Input::A } else { Input::B }),
+            Column::Unit => Value::Unit(*Unit::ALL.get(arg as usize)?),
+            _ => {
+                let n = take_varint(&mut rest)?;
+                Value::Fixed {
+                    value: (n >> 1) as i64 ^ -((n & 1) as i64),
+                    places: arg as u32,
+                }
+            }
+        };
+        self.rest = rest;
+        Some((column, tag >> 4, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(encoder: &mut impl Encoder) -> &[u8] {
+        encoder.begin();
+        encoder.field(
+            Column::Force,
+            0,
+            Value::Fixed {
+                value: -12_094,
+                places: 3,
+            },
+        );
+        encoder.field(Column::Raw, 0, Value::whole(1_240));
+        encoder.field(Column::Range, 0, Value::Input(Input::B));
+        encoder.field(Column::T, 0, Value::whole(51_334_567_000));
+        encoder.field(Column::Ch, 1, Value::whole(0));
+        encoder.field(Column::Unit, 0, Value::Unit(Unit::Newton));
+        encoder.field(Column::Aux, 2, Value::milli(750));
+        encoder.finish()
+    }
+
+    #[test]
+    fn writes_force_lines() {
+        let mut ascii = AsciiEncoder::new();
+        assert_eq!(
+            sample(&mut ascii),
+            b"Force: -12.094 raw=1240 range=b t=51334567000 ch1=0 unit=N aux2=0.750\r\n"
+        );
+        ascii.begin();
+        assert_eq!(ascii.finish(), b"Force:\r\n");
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut binary = BinaryEncoder::new();
+        let frame = sample(&mut binary);
+        assert_eq!((frame[0], frame[frame.len() - 1]), (HELLO, HELLO));
+        assert!(!frame[1..frame.len() - 1].contains(&HELLO));
+
+        let mut scratch = [0; LINE_LEN];
+        let mut fields = decode_frame(&frame[1..frame.len() - 1], &mut scratch).unwrap();
+        assert_eq!(
+            fields.next(),
+            Some((
+                Column::Force,
+                0,
+                Value::Fixed {
+                    value: -12_094,
+                    places: 3
+                }
+            ))
+        );
+        assert_eq!(fields.next(), Some((Column::Raw, 0, Value::whole(1_240))));
+        assert_eq!(
+            fields.next(),
+            Some((Column::Range, 0, Value::Input(Input::B)))
+        );
+        assert_eq!(
+            fields.next(),
+            Some((Column::T, 0, Value::whole(51_334_567_000)))
+        );
+        assert_eq!(fields.next(), Some((Column::Ch, 1, Value::whole(0))));
+        assert_eq!(
+            fields.next(),
+            Some((Column::Unit, 0, Value::Unit(Unit::Newton)))
+        );
+        assert_eq!(fields.next(), Some((Column::Aux, 2, Value::milli(750))));
+        assert_eq!(fields.next(), None);
+    }
+
+    #[test]
+    fn events_frame_as_text() {
+        let mut out = [0; LINE_LEN];
+        let frame = encode_event("TARE offset=3 t=10", &mut out);
+        let body = &frame[1..frame.len() - 1];
+        assert!(!body.contains(&HELLO));
+        let mut scratch = [0; LINE_LEN];
+        assert_eq!(decode_event(body, &mut scratch), Some("TARE offset=3 t=10"));
+        assert!(decode_frame(body, &mut scratch).is_none());
+
+        let mut binary = BinaryEncoder::new();
+        let frame = sample(&mut binary);
+        assert!(decode_event(&frame[1..frame.len() - 1], &mut scratch).is_none());
+    }
+
+    #[test]
+    fn drops_damaged_frames() {
+        let mut binary = BinaryEncoder::new();
+        let frame = sample(&mut binary);
+        let mut damaged = [0; LINE_LEN];
+        let body = &frame[1..frame.len() - 1];
+        damaged[..body.len()].copy_from_slice(body);
+        damaged[3] ^= 0x40;
+        let mut scratch = [0; LINE_LEN];
+        assert!(decode_frame(&damaged[..body.len()], &mut scratch).is_none());
+        assert!(decode_frame(&body[..body.len() - 2], &mut scratch).is_none());
+        assert!(decode_frame(&[], &mut scratch).is_none());
+    }
+
+    #[test]
+    fn cobs_handles_long_runs() {
+        let data = [7; 600];
+        let mut encoded = [0; 610];
+        let n = cobs_encode(&data, &mut encoded);
+        assert!(!encoded[..n].contains(&0));
+        let mut decoded = [0; 600];
+        assert_eq!(cobs_decode(&encoded[..n], &mut decoded), Some(600));
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn detects_the_protocol() {
+        assert_eq!(Protocol::detect(HELLO), Protocol::Binary);
+        assert_eq!(Protocol::detect(b'S'), Protocol::Ascii);
+    }
+}
@@ -0,0 +1,115 @@
+//! Tare offset averaged over several readings.
+
+use crate::filter::SpikeFilter;
+use crate::stats::RunningStats;
+
+/// Offset found by a finished tare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TareResult {
+    pub offset: i32,
+    /// Standard deviation of the readings that were kept.
+    pub sigma: u32,
+    pub used: usize,
+    pub rejected: usize,
+}
+
+/// Collects raw readings and averages them once enough have arrived.
+///
+/// Readings more than `K` MADs from the median are left out, so a knock on
+/// the frame during the tare doesn't shift every later value.
+pub struct Tare {
+    buf: [i32; Tare::MAX_SAMPLES],
+    target: usize,
+    len: usize,
+}
+
+impl Tare {
+    pub const DEFAULT_SAMPLES: usize = 16;
+    pub const MAX_SAMPLES: usize = 64;
+    pub const K: u32 = 3;
+
+    /// `samples` is clamped to `1..=MAX_SAMPLES`.
+    pub fn new(samples: usize) -> Self {
+        Self {
+            buf: [0; Self::MAX_SAMPLES],
+            target: samples.clamp(1, Self::MAX_SAMPLES),
+            len: 0,
+        }
+    }
+
+    /// Add a raw reading. Returns the result once `samples` have been seen.
+    pub fn push(&mut self, raw: i32) -> Option<TareResult> {
+        if self.len < self.target {
+            self.buf[self.len] = raw;
+            self.len += 1;
+        }
+        if self.len < self.target {
+            return None;
+        }
+
+        let readings = &mut self.buf[..self.len];
+        readings.sort_unstable();
+        let median = readings[readings.len() / 2];
+        let mut deviations = [0u32; Self::MAX_SAMPLES];
+        for (d, &r) in deviations.iter_mut().zip(readings.iter()) {
+            *d = r.abs_diff(median);
+        }
+        let deviations = &mut deviations[..readings.len()];
+        deviations.sort_unstable();
+        let mad = deviations[deviations.len() / 2].max(SpikeFilter::MIN_MAD as u32);
+
+        let mut stats = RunningStats::new();
+        for &r in readings.iter() {
+            if r.abs_diff(median) <= Self::K * mad {
+                stats.push(r);
+            }
+        }
+        // The median itself always passes, so there is at least one sample.
+        let summary = stats.summary()?;
+        Some(TareResult {
+            offset: summary.mean,
+            sigma: summary.sigma,
+            used: summary.count as usize,
+            rejected: self.len - summary.count as usize,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_after_target_count() {
+        let mut tare = Tare::new(4);
+        for r in [100, 102, 98] {
+            assert_eq!(tare.push(r), None);
+        }
+        let result = tare.push(100).unwrap();
+        assert_eq!((result.offset, result.used, result.rejected), (100, 4, 0));
+        assert_eq!(result.sigma, 1);
+    }
+
+    #[test]
+    fn rejects_a_knock() {
+        let mut tare = Tare::new(Tare::DEFAULT_SAMPLES);
+        let mut result = None;
+        for i in 0..Tare::DEFAULT_SAMPLES {
+            let r = if i == 7 {
+                50_000
+            } else {
+                -1000 + (i as i32 % 3)
+            };
+            result = tare.push(r);
+        }
+        let result = result.unwrap();
+        assert_eq!(result.rejected, 1);
+        assert_eq!(result.offset, -999);
+    }
+
+    #[test]
+    fn clamps_sample_count() {
+        let mut tare = Tare::new(0);
+        assert!(tare.push(5).is_some());
+    }
+}
@@ -0,0 +1,62 @@
+//! RP2040 die temperature and linear drift compensation.
+
+use crate::quantity::{Counts, MilliCelsius};
+
+/// Convert a 12-bit reading of the on-chip sensor (3.3 V reference) to
+/// temperature, using the datasheet's `27 - (V - 0.706) / 0.001721`.
+pub fn adc_to_millicelsius(raw: u16) -> MilliCelsius {
+    let microvolts = raw as i64 * 3_300_000 / 4096;
+    MilliCelsius((27_000 - (microvolts - 706_000) * 1000 / 1721) as i32)
+}
+
+/// Removes `coeff` counts per degree of change from a reference temperature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TempComp {
+    /// Thousandths of a count per °C.
+    pub coeff_milli: i32,
+    /// Temperature at which readings need no correction.
+    pub reference: MilliCelsius,
+}
+
+impl TempComp {
+    pub fn correct(&self, counts: Counts, temp: MilliCelsius) -> Counts {
+        let delta_mc = temp.0 as i64 - self.reference.0 as i64;
+        let drift = self.coeff_milli as i64 * delta_mc / 1_000_000;
+        Counts((counts.0 as i64 - drift) as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datasheet_reference_point() {
+        // 0.706 V reads as 876 counts, which is 27 °C.
+        let t = adc_to_millicelsius(876);
+        assert!((26_500..=27_500).contains(&t.0), "{t:?}");
+        // Warmer die, lower voltage.
+        assert!(adc_to_millicelsius(850) > t);
+    }
+
+    #[test]
+    fn removes_linear_drift() {
+        let comp = TempComp {
+            coeff_milli: 3_500,
+            reference: MilliCelsius(20_000),
+        };
+        assert_eq!(
+            comp.correct(Counts(1000), MilliCelsius(20_000)),
+            Counts(1000)
+        );
+        // +4 °C at 3.5 counts/°C.
+        assert_eq!(
+            comp.correct(Counts(1014), MilliCelsius(24_000)),
+            Counts(1000)
+        );
+        assert_eq!(
+            comp.correct(Counts(993), MilliCelsius(18_000)),
+            Counts(1000)
+        );
+    }
+}
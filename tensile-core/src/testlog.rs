@@ -0,0 +1,142 @@
+//! Layout of the test log kept in flash: one record per test, so tests are
+//! numbered across power cycles and the last one's result can be read back.
+//!
+//! A record is written in two goes into the same slot. The start writes
+//! the sequence number and leaves the summary erased; the end programs the
+//! summary into those bytes. A test cut short by a reset keeps its number
+//! but has no summary.
+
+pub const RECORD_LEN: usize = 16;
+
+/// Erased flash reads as all ones; no real record uses this sequence number.
+const BLANK_SEQ: u32 = u32::MAX;
+const BLANK_REASON: u8 = 0xFF;
+const UNKNOWN_REASON: u8 = 0xFE;
+
+/// Why a test ended, as the `reason=` of `TEST_STOP`.
+const REASONS: [&str; 12] = [
+    "command",
+    "reset",
+    "modbus",
+    "panel",
+    "overload",
+    "no_data",
+    "stuck",
+    "force_limit",
+    "duration",
+    "slip",
+    "host_lost",
+    "input",
+];
+
+/// How a test went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    /// Filtered reading furthest the way the test loaded the specimen, in
+    /// counts; `None` if there were none.
+    pub peak: Option<i32>,
+    pub duration_ms: u32,
+    reason: u8,
+}
+
+impl Summary {
+    pub fn new(peak: Option<i32>, duration_ms: u32, reason: &str) -> Self {
+        Self {
+            peak,
+            duration_ms,
+            // Anything unlisted is stored as "unknown" rather than blank,
+            // so the test still reads as finished.
+            reason: REASONS
+                .iter()
+                .position(|r| *r == reason)
+                .map_or(UNKNOWN_REASON, |i| i as u8),
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        REASONS.get(self.reason as usize).unwrap_or(&"unknown")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+    pub seq: u32,
+    /// `None` until the test ends.
+    pub summary: Option<Summary>,
+}
+
+impl Record {
+    /// Tests count from one.
+    pub fn number(&self) -> u32 {
+        self.seq.wrapping_add(1)
+    }
+
+    /// The bytes written at the start: the sequence number only.
+    pub fn encode_start(seq: u32) -> [u8; RECORD_LEN] {
+        let mut out = [0xFF; RECORD_LEN];
+        out[0..4].copy_from_slice(&seq.to_le_bytes());
+        out
+    }
+
+    /// The bytes written at the end, leaving the sequence number alone.
+    pub fn encode_end(summary: &Summary) -> [u8; RECORD_LEN] {
+        let mut out = [0xFF; RECORD_LEN];
+        out[4..8].copy_from_slice(&summary.duration_ms.to_le_bytes());
+        out[8..12].copy_from_slice(&summary.peak.unwrap_or(i32::MIN).to_le_bytes());
+        out[12] = summary.reason;
+        out
+    }
+
+    /// `None` for an erased slot.
+    pub fn decode(raw: &[u8; RECORD_LEN]) -> Option<Self> {
+        let word = |i: usize| [raw[i], raw[i + 1], raw[i + 2], raw[i + 3]];
+        let seq = u32::from_le_bytes(word(0));
+        (seq != BLANK_SEQ).then(|| Record {
+            seq,
+            summary: (raw[12] != BLANK_REASON).then(|| Summary {
+                peak: Some(i32::from_le_bytes(word(8))).filter(|&p| p != i32::MIN),
+                duration_ms: u32::from_le_bytes(word(4)),
+                reason: raw[12],
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What flash holds after programming `a` then `b` into the same slot.
+    fn program(a: [u8; RECORD_LEN], b: [u8; RECORD_LEN]) -> [u8; RECORD_LEN] {
+        core::array::from_fn(|i| a[i] & b[i])
+    }
+
+    #[test]
+    fn start_then_end_round_trips() {
+        let started = Record::encode_start(41);
+        let record = Record::decode(&started).unwrap();
+        assert_eq!(record.number(), 42);
+        assert_eq!(record.summary, None);
+
+        let summary = Summary::new(Some(-1_250), 61_500, "force_limit");
+        let ended = program(started, Record::encode_end(&summary));
+        assert_eq!(
+            Record::decode(&ended),
+            Some(Record {
+                seq: 41,
+                summary: Some(summary)
+            })
+        );
+        assert_eq!(summary.reason(), "force_limit");
+        assert_eq!(Record::decode(&[0xFF; RECORD_LEN]), None);
+    }
+
+    #[test]
+    fn odd_reasons_and_empty_peaks_survive() {
+        let summary = Summary::new(None, 0, "gremlins");
+        let raw = program(Record::encode_start(0), Record::encode_end(&summary));
+        let summary = Record::decode(&raw).unwrap().summary.unwrap();
+        assert_eq!(summary.reason(), "unknown");
+        assert_eq!(summary.peak, None);
+    }
+}
@@ -0,0 +1,117 @@
+//! Force thresholds that drive digital outputs.
+
+/// Which side of the level asserts the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Above,
+    Below,
+}
+
+impl Edge {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edge::Above => "above",
+            Edge::Below => "below",
+        }
+    }
+
+    /// Parse `ABOVE` or `BELOW`, case-insensitively.
+    pub fn parse(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("ABOVE") {
+            Some(Edge::Above)
+        } else if name.eq_ignore_ascii_case("BELOW") {
+            Some(Edge::Below)
+        } else {
+            None
+        }
+    }
+}
+
+/// A threshold with hysteresis.
+///
+/// An `Above` trigger asserts once the value reaches `level` and releases
+/// only after it drops below `level - hysteresis`; `Below` mirrors that. The
+/// band keeps noise around the level from chattering the output.
+#[derive(Debug, Clone, Copy)]
+pub struct Trigger {
+    edge: Edge,
+    level: i32,
+    hysteresis: u32,
+    active: bool,
+}
+
+impl Trigger {
+    pub fn new(edge: Edge, level: i32, hysteresis: u32) -> Self {
+        Self {
+            edge,
+            level,
+            hysteresis,
+            active: false,
+        }
+    }
+
+    pub fn edge(&self) -> Edge {
+        self.edge
+    }
+
+    pub fn level(&self) -> i32 {
+        self.level
+    }
+
+    pub fn hysteresis(&self) -> u32 {
+        self.hysteresis
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Feed a value. Returns the new state when it changes.
+    pub fn push(&mut self, value: i32) -> Option<bool> {
+        let (value, level) = match self.edge {
+            Edge::Above => (value as i64, self.level as i64),
+            Edge::Below => (-(value as i64), -(self.level as i64)),
+        };
+        let active = if self.active {
+            value >= level - self.hysteresis as i64
+        } else {
+            value >= level
+        };
+        (active != self.active).then(|| {
+            self.active = active;
+            active
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn above_asserts_at_level_and_releases_below_band() {
+        let mut trigger = Trigger::new(Edge::Above, 1000, 50);
+        assert_eq!(trigger.push(999), None);
+        assert_eq!(trigger.push(1000), Some(true));
+        assert_eq!(trigger.push(960), None);
+        assert_eq!(trigger.push(950), None);
+        assert_eq!(trigger.push(949), Some(false));
+    }
+
+    #[test]
+    fn below_mirrors_above() {
+        let mut trigger = Trigger::new(Edge::Below, -200, 20);
+        assert_eq!(trigger.push(0), None);
+        assert_eq!(trigger.push(-200), Some(true));
+        assert_eq!(trigger.push(-180), None);
+        assert_eq!(trigger.push(-179), Some(false));
+        assert!(!trigger.is_active());
+    }
+
+    #[test]
+    fn extreme_values_do_not_overflow() {
+        let mut trigger = Trigger::new(Edge::Below, i32::MIN, u32::MAX);
+        assert_eq!(trigger.push(i32::MIN), Some(true));
+        assert_eq!(trigger.push(i32::MAX), None);
+    }
+}
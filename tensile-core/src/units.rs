@@ -0,0 +1,186 @@
+//! Conversion of tared counts into engineering units.
+//!
+//! Everything is integer: the scale is counts per kilogram of reference mass
+//! and local gravity is in µm/s², so force comes out in µN before it is
+//! scaled to thousandths of the output unit.
+
+use crate::quantity::{Counts, MicroNewtons};
+
+/// Standard gravity, which defines kgf and lbf, in µm/s².
+pub const STANDARD_GRAVITY_UM_S2: u32 = 9_806_650;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Raw,
+    Newton,
+    KilogramForce,
+    PoundForce,
+    /// Gram-force, i.e. the mass a static load would read as.
+    Gram,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 5] = [
+        Unit::Raw,
+        Unit::Newton,
+        Unit::KilogramForce,
+        Unit::PoundForce,
+        Unit::Gram,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Unit::Raw => "raw",
+            Unit::Newton => "N",
+            Unit::KilogramForce => "kgf",
+            Unit::PoundForce => "lbf",
+            Unit::Gram => "g",
+        }
+    }
+
+    /// Case-insensitive inverse of `as_str`.
+    pub fn parse(name: &str) -> Option<Self> {
+        Unit::ALL
+            .into_iter()
+            .find(|u| u.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// `steps` places along `ALL`, wrapping at either end.
+    pub fn cycle(self, steps: i32) -> Self {
+        let len = Unit::ALL.len() as i32;
+        let here = Unit::ALL.iter().position(|&u| u == self).unwrap_or(0) as i32;
+        Unit::ALL[(here + steps).rem_euclid(len) as usize]
+    }
+}
+
+/// Calibration plus local gravity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scale {
+    counts_per_kg: i32,
+    gravity_um_s2: u32,
+}
+
+impl Scale {
+    /// `None` if `counts_per_kg` is zero.
+    pub fn new(counts_per_kg: i32, gravity_um_s2: u32) -> Option<Self> {
+        (counts_per_kg != 0).then_some(Self {
+            counts_per_kg,
+            gravity_um_s2,
+        })
+    }
+
+    pub fn counts_per_kg(&self) -> i32 {
+        self.counts_per_kg
+    }
+
+    pub fn gravity_um_s2(&self) -> u32 {
+        self.gravity_um_s2
+    }
+
+    pub fn force(&self, counts: Counts) -> MicroNewtons {
+        let micro_newton =
+            counts.0 as i128 * self.gravity_um_s2 as i128 / self.counts_per_kg as i128;
+        MicroNewtons(micro_newton as i64)
+    }
+
+    /// Most decimal places `convert_places` gives.
+    pub const MAX_PLACES: u32 = 6;
+
+    /// Force in thousandths of `unit`. `Raw` returns the counts unchanged
+    /// (not scaled by 1000).
+    pub fn convert(&self, counts: Counts, unit: Unit) -> i64 {
+        self.convert_places(counts, unit, 3)
+    }
+
+    /// Force in units of 10^-`places` of `unit`, rounded, with `places`
+    /// capped at `MAX_PLACES`. `Raw` returns the counts unchanged.
+    pub fn convert_places(&self, counts: Counts, unit: Unit, places: u32) -> i64 {
+        let micro_newton = self.force(counts).0 as i128;
+        // Divisors are the unit in µN/1000, scaled to stay integer.
+        let (num, den): (i128, i128) = match unit {
+            Unit::Raw => return counts.0 as i64,
+            Unit::Newton => (1, 1_000),
+            Unit::KilogramForce => (100, STANDARD_GRAVITY_UM_S2 as i128 / 10),
+            Unit::PoundForce => (10_000, 44_482_216),
+            Unit::Gram => (100_000, STANDARD_GRAVITY_UM_S2 as i128 / 10),
+        };
+        let places = places.min(Self::MAX_PLACES);
+        div_round(micro_newton * num * 10i128.pow(places), den * 1_000) as i64
+    }
+}
+
+fn div_round(num: i128, den: i128) -> i128 {
+    if num < 0 {
+        (num - den / 2) / den
+    } else {
+        (num + den / 2) / den
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 20_000 counts per kg at standard gravity.
+    fn scale() -> Scale {
+        Scale::new(20_000, STANDARD_GRAVITY_UM_S2).unwrap()
+    }
+
+    #[test]
+    fn one_kilogram_in_every_unit() {
+        let s = scale();
+        assert_eq!(s.convert(Counts(20_000), Unit::Raw), 20_000);
+        assert_eq!(s.convert(Counts(20_000), Unit::Newton), 9_807);
+        assert_eq!(s.convert(Counts(20_000), Unit::KilogramForce), 1_000);
+        assert_eq!(s.convert(Counts(20_000), Unit::PoundForce), 2_205);
+        assert_eq!(s.convert(Counts(20_000), Unit::Gram), 1_000_000);
+    }
+
+    #[test]
+    fn local_gravity_changes_force_not_raw() {
+        let equator = Scale::new(20_000, 9_780_330).unwrap();
+        assert_eq!(equator.convert(Counts(20_000), Unit::Newton), 9_780);
+        assert_eq!(equator.convert(Counts(20_000), Unit::KilogramForce), 997);
+        assert_eq!(equator.convert(Counts(20_000), Unit::Raw), 20_000);
+    }
+
+    #[test]
+    fn negative_and_full_scale() {
+        let s = scale();
+        assert_eq!(s.convert(Counts(-10_000), Unit::KilogramForce), -500);
+        assert_eq!(s.convert(Counts(-0x80_0000), Unit::Gram), -419_430_400);
+    }
+
+    #[test]
+    fn places_round_or_extend() {
+        let s = scale();
+        // 1 kg is 9.80665 N.
+        assert_eq!(s.convert_places(Counts(20_000), Unit::Newton, 0), 10);
+        assert_eq!(s.convert_places(Counts(20_000), Unit::Newton, 1), 98);
+        assert_eq!(s.convert_places(Counts(20_000), Unit::Newton, 5), 980_665);
+        assert_eq!(
+            s.convert_places(Counts(-20_000), Unit::Newton, 9),
+            -9_806_650
+        );
+        assert_eq!(s.convert_places(Counts(20_000), Unit::Raw, 2), 20_000);
+    }
+
+    #[test]
+    fn zero_scale_rejected() {
+        assert_eq!(Scale::new(0, STANDARD_GRAVITY_UM_S2), None);
+    }
+
+    #[test]
+    fn unit_names_round_trip() {
+        assert_eq!(Unit::parse("LBF"), Some(Unit::PoundForce));
+        assert_eq!(Unit::parse("n"), Some(Unit::Newton));
+        assert_eq!(Unit::parse("stone"), None);
+    }
+
+    #[test]
+    fn cycle_wraps_both_ways() {
+        assert_eq!(Unit::Raw.cycle(1), Unit::Newton);
+        assert_eq!(Unit::Raw.cycle(-1), Unit::Gram);
+        assert_eq!(Unit::Gram.cycle(7), Unit::Newton);
+    }
+}
@@ -0,0 +1,63 @@
+//! Work done on the specimen: force integrated over extension.
+
+use crate::quantity::MicroNewtons;
+
+/// Trapezoidal sum of force over extension since the last `reset`.
+/// Unloading gives energy back, so the total is what the specimen absorbed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Work {
+    /// Previous force in µN and extension in µm.
+    last: Option<(i64, i64)>,
+    /// pJ, so a kilonewton over a metre still fits.
+    total_pj: i64,
+}
+
+impl Work {
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            total_pj: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn push(&mut self, force: MicroNewtons, extension_um: i64) {
+        if let Some((force0, extension0)) = self.last {
+            self.total_pj += (force0 + force.0) * (extension_um - extension0) / 2;
+        }
+        self.last = Some((force.0, extension_um));
+    }
+
+    pub fn millijoules(&self) -> i64 {
+        self.total_pj / 1_000_000_000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integrates_a_linear_ramp() {
+        let mut work = Work::new();
+        // 0 to 100 N over 2 mm: half of 100 N × 2 mm is 100 mJ.
+        for step in 0..=20 {
+            work.push(MicroNewtons(step * 5_000_000), step * 100);
+        }
+        assert_eq!(work.millijoules(), 100);
+        work.reset();
+        assert_eq!(work.millijoules(), 0);
+    }
+
+    #[test]
+    fn unloading_returns_energy() {
+        let mut work = Work::new();
+        work.push(MicroNewtons(0), 0);
+        work.push(MicroNewtons(10_000_000), 1_000);
+        work.push(MicroNewtons(0), 0);
+        assert_eq!(work.millijoules(), 0);
+    }
+}
@@ -0,0 +1,88 @@
+//! Automatic zero tracking for slow thermal drift.
+
+/// Nudges the tare offset while the reading sits near zero.
+///
+/// Once `hold` consecutive samples stay within `±band` counts, the tracker
+/// asks for the offset to move half-way towards their mean (at least one
+/// count), then starts a fresh window. A real load leaves the band and
+/// restarts the count, so only a quiet, unloaded cell gets corrected.
+pub struct ZeroTracker {
+    band: u32,
+    hold: u32,
+    count: u32,
+    sum: i64,
+}
+
+impl ZeroTracker {
+    /// `hold` is clamped to at least 1 sample.
+    pub fn new(band: u32, hold: u32) -> Self {
+        Self {
+            band,
+            hold: hold.max(1),
+            count: 0,
+            sum: 0,
+        }
+    }
+
+    /// Feed a tared sample. Returns the amount to add to the tare offset.
+    pub fn push(&mut self, value: i32) -> Option<i32> {
+        if value.unsigned_abs() > self.band {
+            self.reset();
+            return None;
+        }
+        self.count += 1;
+        self.sum += value as i64;
+        if self.count < self.hold {
+            return None;
+        }
+        let mean = self.sum / self.count as i64;
+        self.reset();
+        match mean {
+            0 => None,
+            m => Some((m / 2 + m.signum()).clamp(-(self.band as i64), self.band as i64) as i32),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.sum = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corrects_after_quiet_hold() {
+        let mut zero = ZeroTracker::new(20, 4);
+        for _ in 0..3 {
+            assert_eq!(zero.push(8), None);
+        }
+        assert_eq!(zero.push(8), Some(5));
+    }
+
+    #[test]
+    fn load_outside_band_restarts_window() {
+        let mut zero = ZeroTracker::new(20, 3);
+        zero.push(5);
+        zero.push(5);
+        assert_eq!(zero.push(500), None);
+        assert_eq!(zero.push(5), None);
+        assert_eq!(zero.push(5), None);
+        assert_eq!(zero.push(5), Some(3));
+    }
+
+    #[test]
+    fn centred_reading_needs_no_correction() {
+        let mut zero = ZeroTracker::new(20, 2);
+        zero.push(-3);
+        assert_eq!(zero.push(3), None);
+    }
+
+    #[test]
+    fn small_negative_drift_still_moves() {
+        let mut zero = ZeroTracker::new(20, 1);
+        assert_eq!(zero.push(-1), Some(-1));
+    }
+}